@@ -209,7 +209,7 @@ impl WordPiece {
   pub fn init(vocab: Vocab, options: Option<WordPieceOptions>) -> Result<Model> {
     let options = options.unwrap_or_default();
 
-    let mut builder = tk::models::wordpiece::WordPiece::builder().vocab(vocab);
+    let mut builder = tk::models::wordpiece::WordPiece::builder().vocab(vocab.into());
     builder = options.apply_to_wordpiece_builder(builder);
     let model = builder
       .build()
@@ -265,7 +265,7 @@ impl WordLevel {
   #[napi(factory, ts_return_type = "Model")]
   pub fn init(vocab: Vocab, options: Option<WordLevelOptions>) -> Result<Model> {
     let options = options.unwrap_or_default();
-    let mut builder = tk::models::wordlevel::WordLevel::builder().vocab(vocab);
+    let mut builder = tk::models::wordlevel::WordLevel::builder().vocab(vocab.into());
     builder = options.apply_to_wordlevel_builder(builder);
     let model = builder
       .build()
@@ -147,7 +147,7 @@ pub fn whitespace_split_pre_tokenizer() -> PreTokenizer {
 pub fn bert_pre_tokenizer() -> PreTokenizer {
   PreTokenizer {
     pretok: Some(Arc::new(RwLock::new(
-      tk::pre_tokenizers::bert::BertPreTokenizer.into(),
+      tk::pre_tokenizers::bert::BertPreTokenizer::default().into(),
     ))),
   }
 }
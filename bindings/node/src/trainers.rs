@@ -63,4 +63,19 @@ impl tk::Trainer for Trainer {
       .unwrap()
       .feed(iterator, process)
   }
+
+  fn feed_weighted<I, S, F>(&mut self, iterator: I, process: F) -> tk::Result<()>
+  where
+    I: Iterator<Item = (S, f64)> + Send,
+    S: AsRef<str> + Send,
+    F: Fn(&str) -> tk::Result<Vec<String>> + Sync,
+  {
+    self
+      .trainer
+      .as_ref()
+      .ok_or("Uninitialized Trainer")?
+      .write()
+      .unwrap()
+      .feed_weighted(iterator, process)
+  }
 }
@@ -58,6 +58,9 @@ impl PyDecoder {
                 DecoderWrapper::Sequence(_) => {
                     Py::new(py, (PySequenceDecoder {}, base))?.into_py(py)
                 }
+                // Wrapper variants without a dedicated Python class are
+                // exposed as the base Decoder class
+                _ => Py::new(py, base)?.into_py(py),
             },
         })
     }
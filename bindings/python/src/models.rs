@@ -39,6 +39,9 @@ impl PyModel {
             ModelWrapper::WordPiece(_) => Py::new(py, (PyWordPiece {}, base))?.into_py(py),
             ModelWrapper::WordLevel(_) => Py::new(py, (PyWordLevel {}, base))?.into_py(py),
             ModelWrapper::Unigram(_) => Py::new(py, (PyUnigram {}, base))?.into_py(py),
+            // Wrapper variants without a dedicated Python class are exposed
+            // as the base Model class
+            _ => Py::new(py, base)?.into_py(py),
         })
     }
 }
@@ -632,7 +635,7 @@ impl PyWordPiece {
         if let Some(vocab) = vocab {
             match vocab {
                 PyVocab::Vocab(vocab) => {
-                    builder = builder.vocab(vocab);
+                    builder = builder.vocab(vocab.into());
                 }
                 PyVocab::Filename(vocab_filename) => {
                     deprecation_warning(
@@ -664,9 +667,14 @@ impl PyWordPiece {
     #[staticmethod]
     #[pyo3(text_signature = "(vocab)")]
     fn read_file(vocab: &str) -> PyResult<Vocab> {
-        WordPiece::read_file(vocab).map_err(|e| {
-            exceptions::PyException::new_err(format!("Error while reading WordPiece file: {}", e))
-        })
+        WordPiece::read_file(vocab)
+            .map(|vocab| (&vocab).into())
+            .map_err(|e| {
+                exceptions::PyException::new_err(format!(
+                    "Error while reading WordPiece file: {}",
+                    e
+                ))
+            })
     }
 
     /// Instantiate a WordPiece model from the given file
@@ -700,7 +708,7 @@ impl PyWordPiece {
         })?;
         Py::new(
             py,
-            PyWordPiece::new(py, Some(PyVocab::Vocab(vocab)), kwargs)?,
+            PyWordPiece::new(py, Some(PyVocab::Vocab((&vocab).into())), kwargs)?,
         )
     }
 }
@@ -742,7 +750,7 @@ impl PyWordLevel {
         if let Some(vocab) = vocab {
             match vocab {
                 PyVocab::Vocab(vocab) => {
-                    builder = builder.vocab(vocab);
+                    builder = builder.vocab(vocab.into());
                 }
                 PyVocab::Filename(vocab_filename) => {
                     deprecation_warning(
@@ -783,9 +791,14 @@ impl PyWordLevel {
     #[staticmethod]
     #[pyo3(text_signature = "(vocab)")]
     fn read_file(vocab: &str) -> PyResult<Vocab> {
-        WordLevel::read_file(vocab).map_err(|e| {
-            exceptions::PyException::new_err(format!("Error while reading WordLevel file: {}", e))
-        })
+        WordLevel::read_file(vocab)
+            .map(|vocab| (&vocab).into())
+            .map_err(|e| {
+                exceptions::PyException::new_err(format!(
+                    "Error while reading WordLevel file: {}",
+                    e
+                ))
+            })
     }
 
     /// Instantiate a WordLevel model from the given file
@@ -819,7 +832,7 @@ impl PyWordLevel {
         })?;
         Py::new(
             py,
-            PyWordLevel::new(py, Some(PyVocab::Vocab(vocab)), unk_token)?,
+            PyWordLevel::new(py, Some(PyVocab::Vocab((&vocab).into())), unk_token)?,
         )
     }
 }
@@ -86,6 +86,9 @@ impl PyNormalizer {
                     }
                     NormalizerWrapper::Replace(_) => Py::new(py, (PyReplace {}, base))?.into_py(py),
                     NormalizerWrapper::Nmt(_) => Py::new(py, (PyNmt {}, base))?.into_py(py),
+                    // Wrapper variants without a dedicated Python class are
+                    // exposed as the base Normalizer class
+                    _ => Py::new(py, base)?.into_py(py),
                 },
             },
         })
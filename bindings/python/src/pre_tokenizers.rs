@@ -89,6 +89,9 @@ impl PyPreTokenizer {
                         PreTokenizerWrapper::UnicodeScripts(_) => {
                             Py::new(py, (PyUnicodeScripts {}, base))?.into_py(py)
                         }
+                        // Wrapper variants without a dedicated Python class
+                        // are exposed as the base PreTokenizer class
+                        _ => Py::new(py, base)?.into_py(py),
                     },
                 }
             }
@@ -414,7 +417,7 @@ impl PyBertPreTokenizer {
     #[new]
     #[pyo3(text_signature = "(self)")]
     fn new() -> (Self, PyPreTokenizer) {
-        (PyBertPreTokenizer {}, BertPreTokenizer.into())
+        (PyBertPreTokenizer {}, BertPreTokenizer::default().into())
     }
 }
 
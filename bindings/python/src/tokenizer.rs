@@ -912,8 +912,12 @@ impl PyTokenizer {
                 match params.strategy {
                     tk::PaddingStrategy::BatchLongest => None,
                     tk::PaddingStrategy::Fixed(size) => Some(size),
+                    tk::PaddingStrategy::BucketedToLongest { .. } => None,
                 },
             )?;
+            if let tk::PaddingStrategy::BucketedToLongest { ref buckets } = params.strategy {
+                dict.set_item("buckets", buckets)?;
+            }
             dict.set_item("pad_to_multiple_of", params.pad_to_multiple_of)?;
             dict.set_item("pad_id", params.pad_id)?;
             dict.set_item("pad_token", &params.pad_token)?;
@@ -39,6 +39,9 @@ impl PyTrainer {
             TrainerWrapper::UnigramTrainer(_) => {
                 Py::new(py, (PyUnigramTrainer {}, base))?.into_py(py)
             }
+            // Wrapper variants without a dedicated Python class are exposed
+            // as the base Trainer class
+            _ => Py::new(py, base)?.into_py(py),
         })
     }
 }
@@ -103,6 +106,18 @@ impl Trainer for PyTrainer {
     {
         self.trainer.write().unwrap().feed(iterator, process)
     }
+
+    fn feed_weighted<I, S, F>(&mut self, iterator: I, process: F) -> tk::Result<()>
+    where
+        I: Iterator<Item = (S, f64)> + Send,
+        S: AsRef<str> + Send,
+        F: Fn(&str) -> tk::Result<Vec<String>> + Sync,
+    {
+        self.trainer
+            .write()
+            .unwrap()
+            .feed_weighted(iterator, process)
+    }
 }
 
 impl<I> From<I> for PyTrainer
@@ -14,7 +14,7 @@ use tokenizers::pre_tokenizers::bert::BertPreTokenizer;
 use tokenizers::processors::bert::BertProcessing;
 use tokenizers::{decoders, EncodeInput, Model, TokenizerImpl};
 
-use common::{iter_bench_encode, iter_bench_encode_batch, iter_bench_train};
+use common::{iter_bench_count, iter_bench_encode, iter_bench_encode_batch, iter_bench_train};
 use tokenizers::decoders::DecoderWrapper;
 use tokenizers::pre_tokenizers::whitespace::Whitespace;
 use tokenizers::processors::PostProcessorWrapper;
@@ -34,7 +34,7 @@ fn create_bert_tokenizer(wp: WordPiece) -> BertTokenizer {
     let sep_id = *wp.get_vocab().get("[SEP]").unwrap();
     let cls_id = *wp.get_vocab().get("[CLS]").unwrap();
     let mut tokenizer = TokenizerImpl::new(wp);
-    tokenizer.with_pre_tokenizer(Some(BertPreTokenizer));
+    tokenizer.with_pre_tokenizer(Some(BertPreTokenizer::default()));
     tokenizer.with_normalizer(Some(BertNormalizer::default()));
     tokenizer.with_decoder(Some(decoders::wordpiece::WordPiece::default()));
     tokenizer.with_post_processor(Some(BertProcessing::new(
@@ -67,6 +67,10 @@ pub fn bench_bert(c: &mut Criterion) {
     c.bench_function("WordPiece BERT encode batch", |b| {
         b.iter_custom(|iters| iter_bench_encode_batch(iters, &tokenizer, &batches))
     });
+
+    c.bench_function("WordPiece BERT count_tokens", |b| {
+        b.iter_custom(|iters| iter_bench_count(iters, &tokenizer, &lines))
+    });
 }
 
 fn bench_train(c: &mut Criterion) {
@@ -15,7 +15,7 @@ use tokenizers::pre_tokenizers::whitespace::Whitespace;
 use tokenizers::tokenizer::{AddedToken, EncodeInput};
 use tokenizers::Tokenizer;
 
-use common::{iter_bench_encode, iter_bench_encode_batch, iter_bench_train};
+use common::{iter_bench_count, iter_bench_encode, iter_bench_encode_batch, iter_bench_train};
 use std::ops::Deref;
 
 static BATCH_SIZE: usize = 1_000;
@@ -53,6 +53,27 @@ fn bench_gpt2(c: &mut Criterion) {
         b.iter_custom(|iters| iter_bench_encode_batch(iters, tokenizer.deref(), &batches))
     });
 
+    c.bench_function("BPE GPT2 count_tokens", |b| {
+        b.iter_custom(|iters| iter_bench_count(iters, tokenizer.deref(), &lines))
+    });
+
+    let ids: Vec<Vec<u32>> = lines
+        .iter()
+        .take(BATCH_SIZE)
+        .map(|line| {
+            tokenizer
+                .encode(line.clone(), false)
+                .unwrap()
+                .get_ids()
+                .to_vec()
+        })
+        .collect();
+    let id_slices: Vec<&[u32]> = ids.iter().map(|ids| ids.as_slice()).collect();
+
+    c.bench_function("BPE GPT2 decode batch", |b| {
+        b.iter(|| criterion::black_box(tokenizer.decode_batch(&id_slices, false).unwrap()))
+    });
+
     let bpe = BPE::from_file("data/gpt2-vocab.json", "data/gpt2-merges.txt")
         .cache_capacity(0)
         .build()
@@ -32,6 +32,32 @@ where
     duration
 }
 
+pub fn iter_bench_count<M, N, PT, PP, D>(
+    iters: u64,
+    tokenizer: &TokenizerImpl<M, N, PT, PP, D>,
+    lines: &[EncodeInput],
+) -> Duration
+where
+    M: Model,
+    N: Normalizer,
+    PT: PreTokenizer,
+    PP: PostProcessor,
+    D: Decoder,
+{
+    let mut duration = Duration::new(0, 0);
+    let mut line_index: usize = 0;
+    for _i in 0..iters {
+        if line_index >= lines.len() {
+            line_index = 0;
+        }
+        let input = lines[line_index].clone();
+        let start = Instant::now();
+        let _ = black_box(tokenizer.count_tokens(input, false));
+        duration = duration.checked_add(start.elapsed()).unwrap();
+    }
+    duration
+}
+
 pub fn iter_bench_encode_batch<M, N, PT, PP, D>(
     iters: u64,
     tokenizer: &TokenizerImpl<M, N, PT, PP, D>,
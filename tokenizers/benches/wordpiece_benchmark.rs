@@ -0,0 +1,151 @@
+#[macro_use]
+extern crate criterion;
+
+use criterion::Criterion;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use tokenizers::models::wordpiece::{Vocab, WordPiece};
+use tokenizers::Model;
+
+/// The previous lookup strategy: probe the vocabulary for every candidate
+/// sub-token from the longest down, hashing borrowed keys (quadratic in the
+/// word length, unlike the trie-backed `WordPiece::tokenize`).
+fn tokenize_with_hashing(vocab: &Vocab, sequence: &str) -> usize {
+    let mut n_tokens = 0;
+    let mut start = 0;
+    while start < sequence.len() {
+        let mut end = sequence.len();
+        let mut found = false;
+        while start < end {
+            let substr = &sequence[start..end];
+            let prefix = if start > 0 { "##" } else { "" };
+            if vocab.get_with_prefix(prefix, substr).is_some() {
+                found = true;
+                break;
+            }
+            end -= substr.chars().last().map_or(1, |c| c.len_utf8());
+        }
+        if !found {
+            return 1;
+        }
+        n_tokens += 1;
+        start = end;
+    }
+    n_tokens
+}
+
+/// The oldest lookup strategy: additionally build a prefixed `String` for
+/// every candidate sub-token before probing the vocabulary.
+fn tokenize_with_allocation(vocab: &HashMap<String, u32>, sequence: &str) -> usize {
+    let mut n_tokens = 0;
+    let mut start = 0;
+    while start < sequence.len() {
+        let mut end = sequence.len();
+        let mut found = false;
+        while start < end {
+            let mut substr: Cow<str> = Cow::Borrowed(&sequence[start..end]);
+            if start > 0 {
+                substr = Cow::Owned(format!("##{}", substr));
+            }
+            if vocab.contains_key(substr.as_ref()) {
+                found = true;
+                break;
+            }
+            end -= substr.chars().last().map_or(1, |c| c.len_utf8());
+        }
+        if !found {
+            return 1;
+        }
+        n_tokens += 1;
+        start = end;
+    }
+    n_tokens
+}
+
+pub fn bench_wordpiece(c: &mut Criterion) {
+    let model = WordPiece::from_file("data/bert-base-uncased-vocab.txt")
+        .build()
+        .unwrap();
+    let vocab = model.get_vocab();
+    // The vocab entries themselves make a decent corpus of realistic words
+    let words: Vec<String> =
+        BufReader::new(File::open("data/bert-base-uncased-vocab.txt").unwrap())
+            .lines()
+            .map(|line| line.unwrap())
+            .filter(|w| !w.is_empty())
+            .collect();
+
+    let borrowed_vocab: Vocab = vocab.clone().into();
+
+    c.bench_function("WordPiece tokenize (trie)", |b| {
+        b.iter(|| {
+            words
+                .iter()
+                .map(|word| model.tokenize(word).unwrap().len())
+                .sum::<usize>()
+        })
+    });
+
+    c.bench_function("WordPiece tokenize (borrowed keys)", |b| {
+        b.iter(|| {
+            words
+                .iter()
+                .map(|word| tokenize_with_hashing(&borrowed_vocab, word))
+                .sum::<usize>()
+        })
+    });
+
+    c.bench_function("WordPiece tokenize (allocating keys)", |b| {
+        b.iter(|| {
+            words
+                .iter()
+                .map(|word| tokenize_with_allocation(&vocab, word))
+                .sum::<usize>()
+        })
+    });
+
+    // Long words (DNA sequences, URLs, ...) are where the quadratic candidate
+    // probing really hurts; single-letter continuations keep them tokenizable
+    let long_words: Vec<String> = (0..100)
+        .map(|i| {
+            "acgt"
+                .chars()
+                .cycle()
+                .skip(i % 4)
+                .take(500)
+                .collect::<String>()
+        })
+        .collect();
+    let long_model = WordPiece::builder()
+        .vocab(vocab.clone().into())
+        .max_input_chars_per_word(10_000)
+        .build()
+        .unwrap();
+
+    c.bench_function("WordPiece tokenize long words (trie)", |b| {
+        b.iter(|| {
+            long_words
+                .iter()
+                .map(|word| long_model.tokenize(word).unwrap().len())
+                .sum::<usize>()
+        })
+    });
+
+    c.bench_function("WordPiece tokenize long words (borrowed keys)", |b| {
+        b.iter(|| {
+            long_words
+                .iter()
+                .map(|word| tokenize_with_hashing(&borrowed_vocab, word))
+                .sum::<usize>()
+        })
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = bench_wordpiece
+}
+criterion_main!(benches);
@@ -0,0 +1,170 @@
+//! Analyze the vocabulary of a model over a corpus, typically before pruning
+//! it: which tokens never occur, which ones are near-duplicates of each other
+//! (same word up to case, accents or a whitespace marker), and which merges
+//! only ever produce a token the corpus barely uses.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use unicode_normalization_alignments::UnicodeNormalization;
+
+use crate::tokenizer::{Model, Result};
+
+/// A structured vocabulary analysis, produced by [`vocab_report`]. It
+/// serializes to JSON, so pruning decisions can be scripted against it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VocabReport {
+    /// The number of corpus sequences analyzed
+    pub sequences: usize,
+    /// The tokens of the vocabulary that never occur in any tokenization of
+    /// the corpus, sorted
+    pub dead_tokens: Vec<String>,
+    /// Groups of distinct tokens spelling the same word up to case, accents,
+    /// or a leading whitespace/continuation marker (`Ġ`, `▁`, `##`), each
+    /// group sorted, groups sorted by their first token
+    pub near_duplicates: Vec<Vec<String>>,
+    /// The tokens produced by a merge (for merge-based models) that occur at
+    /// most once in the corpus tokenizations, sorted
+    pub single_use_merges: Vec<String>,
+}
+
+/// The key under which near-duplicate tokens collide: the token without its
+/// leading whitespace or continuation marker, lowercased, with combining
+/// marks stripped
+fn duplicate_key(token: &str) -> String {
+    let stripped = token
+        .strip_prefix('Ġ')
+        .or_else(|| token.strip_prefix('▁'))
+        .or_else(|| token.strip_prefix("##"))
+        .unwrap_or(token);
+    stripped
+        .nfd()
+        .map(|(c, _)| c)
+        .filter(|c| !unicode_categories::UnicodeCategories::is_mark_nonspacing(*c))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Analyze the vocabulary of `model` over a corpus. Each corpus item is
+/// tokenized by the model directly, without normalization or
+/// pre-tokenization, so feed it the same pre-tokenized words the model sees
+/// in the full pipeline.
+pub fn vocab_report<M, I, S>(model: &M, corpus: I) -> Result<VocabReport>
+where
+    M: Model,
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut counts: HashMap<u32, u64> = HashMap::new();
+    let mut sequences = 0;
+    for sequence in corpus {
+        sequences += 1;
+        for token in model.tokenize(sequence.as_ref())? {
+            *counts.entry(token.id).or_insert(0) += 1;
+        }
+    }
+
+    let mut dead_tokens = vec![];
+    let mut single_use_merges = vec![];
+    let mut groups: HashMap<String, Vec<String>> = HashMap::new();
+    for (token, id) in model.get_vocab_iter() {
+        let count = counts.get(&id).copied().unwrap_or(0);
+        if count == 0 {
+            dead_tokens.push(token.to_string());
+        }
+        if count <= 1 && model.token_info(id).is_some_and(|info| info.rank.is_some()) {
+            single_use_merges.push(token.to_string());
+        }
+        groups
+            .entry(duplicate_key(&token))
+            .or_default()
+            .push(token.into_owned());
+    }
+    dead_tokens.sort_unstable();
+    single_use_merges.sort_unstable();
+
+    let mut near_duplicates: Vec<Vec<String>> = groups
+        .into_iter()
+        .filter(|(_, group)| group.len() > 1)
+        .map(|(_, mut group)| {
+            group.sort_unstable();
+            group
+        })
+        .collect();
+    near_duplicates.sort_unstable();
+
+    Ok(VocabReport {
+        sequences,
+        dead_tokens,
+        near_duplicates,
+        single_use_merges,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::wordlevel::WordLevel;
+    use std::collections::HashMap;
+
+    fn model(tokens: &[&str]) -> WordLevel {
+        let vocab: HashMap<String, u32> = tokens
+            .iter()
+            .enumerate()
+            .map(|(id, token)| (token.to_string(), id as u32))
+            .collect();
+        WordLevel::builder()
+            .vocab(vocab.into())
+            .unk_token("<unk>".into())
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn dead_and_duplicate_tokens() {
+        let model = model(&["<unk>", "the", "The", "thé", "world", "Ġworld", "unused"]);
+        let report = vocab_report(&model, ["the", "world", "The"]).unwrap();
+
+        assert_eq!(report.sequences, 3);
+        // Tokens never produced over the corpus
+        assert_eq!(report.dead_tokens, ["<unk>", "thé", "unused", "Ġworld"]);
+        // Case, accent and whitespace-marker variants collide
+        assert_eq!(
+            report.near_duplicates,
+            [
+                vec!["The".to_string(), "the".into(), "thé".into()],
+                vec!["world".to_string(), "Ġworld".into()],
+            ]
+        );
+        // WordLevel has no merges
+        assert!(report.single_use_merges.is_empty());
+    }
+
+    #[test]
+    fn single_use_merges_are_flagged() {
+        use crate::models::bpe::BPE;
+
+        let vocab: HashMap<String, u32> = vec![
+            ("a".into(), 0),
+            ("b".into(), 1),
+            ("ab".into(), 2),
+            ("abab".into(), 3),
+        ]
+        .into_iter()
+        .collect();
+        let merges = vec![
+            ("a".to_string(), "b".to_string()),
+            ("ab".to_string(), "ab".to_string()),
+        ];
+        let model = BPE::new(vocab, merges);
+
+        // "ab" occurs many times, "abab" only once
+        let report = vocab_report(&model, ["ab", "ab", "abab", "ab"]).unwrap();
+        assert_eq!(report.single_use_merges, ["abab"]);
+        // The merge inputs never surface on their own over this corpus
+        assert_eq!(report.dead_tokens, ["a", "b"]);
+
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(json.contains("\"single_use_merges\":[\"abab\"]"));
+    }
+}
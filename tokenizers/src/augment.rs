@@ -0,0 +1,371 @@
+//! Masking generators for masked-language-model data preparation.
+//!
+//! Both generators operate on an already-processed [`Encoding`]: they respect
+//! its `special_tokens_mask` (special tokens are never corrupted) and, for
+//! whole-word masking, its `word_ids` (a word is always masked together with
+//! all of its sub-tokens). Randomness comes from an explicit per-example
+//! seed, so a data pipeline re-reading the same example with the same seed
+//! reproduces the same mask.
+
+use crate::Encoding;
+
+/// A small deterministic RNG (splitmix64), so that masks do not depend on a
+/// platform RNG and can be reproduced from the seed alone
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn next_below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n.max(1) as u64) as usize
+    }
+}
+
+/// The output of a masking generator: the corrupted input ids, and one label
+/// per position holding the original id where the input was corrupted and
+/// `None` everywhere else.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaskedIds {
+    pub ids: Vec<u32>,
+    pub labels: Vec<Option<u32>>,
+}
+
+/// BERT-style whole-word masking: words are selected at random, and every
+/// sub-token of a selected word is corrupted together. A selected position is
+/// replaced by the mask token most of the time, by a random token or left
+/// unchanged the rest of the time, but always receives a label.
+pub struct WholeWordMasking {
+    mask_token_id: u32,
+    vocab_size: u32,
+    mask_probability: f64,
+    mask_rate: f64,
+    random_rate: f64,
+}
+
+impl WholeWordMasking {
+    /// Create a generator replacing selected tokens with `mask_token_id`,
+    /// with the BERT defaults: 15% of the words are selected, and a selected
+    /// position becomes the mask token 80% of the time, a random id below
+    /// `vocab_size` 10% of the time, and stays unchanged 10% of the time.
+    pub fn new(mask_token_id: u32, vocab_size: u32) -> Self {
+        Self {
+            mask_token_id,
+            vocab_size,
+            mask_probability: 0.15,
+            mask_rate: 0.8,
+            random_rate: 0.1,
+        }
+    }
+
+    /// Set the probability of selecting each word for masking.
+    #[must_use]
+    pub fn mask_probability(mut self, mask_probability: f64) -> Self {
+        self.mask_probability = mask_probability;
+        self
+    }
+
+    /// Set how selected positions are corrupted: a `mask_rate` fraction
+    /// becomes the mask token, a `random_rate` fraction becomes a random id,
+    /// and the rest keeps the original id.
+    #[must_use]
+    pub fn rates(mut self, mask_rate: f64, random_rate: f64) -> Self {
+        self.mask_rate = mask_rate;
+        self.random_rate = random_rate;
+        self
+    }
+
+    /// Mask the encoding, deterministically for a given seed. Special tokens
+    /// are never masked, and neither are tokens without a word id.
+    pub fn mask(&self, encoding: &Encoding, seed: u64) -> MaskedIds {
+        let mut rng = Rng::new(seed);
+        let mut ids = encoding.get_ids().to_vec();
+        let mut labels = vec![None; ids.len()];
+        let words = encoding.get_word_ids();
+        let special = encoding.get_special_tokens_mask();
+
+        let mut index = 0;
+        while index < ids.len() {
+            // The tokens of a word are consecutive and share its word id;
+            // word ids restart per sequence but never repeat across a run
+            let word = words[index];
+            let mut end = index + 1;
+            while end < ids.len() && words[end] == word && word.is_some() {
+                end += 1;
+            }
+            if word.is_some()
+                && special[index..end].iter().all(|s| *s == 0)
+                && rng.next_f64() < self.mask_probability
+            {
+                for position in index..end {
+                    labels[position] = Some(ids[position]);
+                    let roll = rng.next_f64();
+                    if roll < self.mask_rate {
+                        ids[position] = self.mask_token_id;
+                    } else if roll < self.mask_rate + self.random_rate {
+                        ids[position] = (rng.next_u64() % u64::from(self.vocab_size.max(1))) as u32;
+                    }
+                }
+            }
+            index = end;
+        }
+        MaskedIds { ids, labels }
+    }
+}
+
+/// The output of [`SpanCorruption`]: the input ids with each corrupted span
+/// collapsed into one sentinel id, and the label ids spelling out, for each
+/// sentinel, the original tokens of its span.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorruptedIds {
+    pub ids: Vec<u32>,
+    pub labels: Vec<u32>,
+}
+
+/// T5-style span corruption: a fraction of the tokens is removed in
+/// contiguous spans, each span is replaced in the input by a single sentinel
+/// id, and the labels hold the removed tokens, each span introduced by its
+/// sentinel and the whole sequence closed by one final sentinel.
+pub struct SpanCorruption {
+    first_sentinel_id: u32,
+    noise_density: f64,
+    mean_span_length: f64,
+}
+
+impl SpanCorruption {
+    /// Create a generator with the T5 defaults: 15% of the tokens are
+    /// corrupted in spans of 3 tokens on average. Sentinels count downwards
+    /// from `first_sentinel_id` (the `<extra_id_0>` convention).
+    pub fn new(first_sentinel_id: u32) -> Self {
+        Self {
+            first_sentinel_id,
+            noise_density: 0.15,
+            mean_span_length: 3.0,
+        }
+    }
+
+    /// Set the fraction of the tokens to corrupt.
+    #[must_use]
+    pub fn noise_density(mut self, noise_density: f64) -> Self {
+        self.noise_density = noise_density;
+        self
+    }
+
+    /// Set the average length of the corrupted spans.
+    #[must_use]
+    pub fn mean_span_length(mut self, mean_span_length: f64) -> Self {
+        self.mean_span_length = mean_span_length;
+        self
+    }
+
+    /// Randomly split `total` items into `parts` non-empty contiguous
+    /// segments, returning the segment lengths
+    fn segment(rng: &mut Rng, total: usize, parts: usize) -> Vec<usize> {
+        // Choose `parts - 1` distinct cut points among the `total - 1` gaps,
+        // with a partial Fisher-Yates shuffle
+        let mut gaps: Vec<usize> = (1..total).collect();
+        for i in 0..parts - 1 {
+            let j = i + rng.next_below(gaps.len() - i);
+            gaps.swap(i, j);
+        }
+        let mut cuts: Vec<usize> = gaps[..parts - 1].to_vec();
+        cuts.sort_unstable();
+        cuts.push(total);
+        cuts.iter()
+            .scan(0, |start, cut| {
+                let length = cut - *start;
+                *start = *cut;
+                Some(length)
+            })
+            .collect()
+    }
+
+    /// Corrupt the encoding, deterministically for a given seed. Special
+    /// tokens are never part of a span: corruption applies to the contiguous
+    /// run of regular tokens, and special tokens are carried over around it.
+    pub fn corrupt(&self, encoding: &Encoding, seed: u64) -> CorruptedIds {
+        let mut rng = Rng::new(seed);
+        let all_ids = encoding.get_ids();
+        let special = encoding.get_special_tokens_mask();
+        let candidates: Vec<usize> = (0..all_ids.len()).filter(|i| special[*i] == 0).collect();
+
+        let num_noise = ((candidates.len() as f64 * self.noise_density).round() as usize)
+            .clamp(usize::from(!candidates.is_empty()), candidates.len());
+        if num_noise == 0 {
+            return CorruptedIds {
+                ids: all_ids.to_vec(),
+                labels: vec![self.first_sentinel_id],
+            };
+        }
+        let num_spans = ((num_noise as f64 / self.mean_span_length).round() as usize)
+            .clamp(1, num_noise)
+            .min(candidates.len() - num_noise + 1);
+
+        // Interleave non-noise and noise segments over the candidate run, as
+        // in the T5 `random_spans_noise_mask`: each noise span is preceded by
+        // a (possibly longer) clear segment
+        let noise_lengths = Self::segment(&mut rng, num_noise, num_spans);
+        let clear_lengths = Self::segment(&mut rng, candidates.len() - num_noise + 1, num_spans)
+            .into_iter()
+            .map(|length| length - 1)
+            .collect::<Vec<_>>();
+
+        let mut noisy = vec![false; all_ids.len()];
+        let mut cursor = 0;
+        for (clear, noise) in clear_lengths.iter().zip(&noise_lengths) {
+            cursor += clear;
+            for position in &candidates[cursor..cursor + noise] {
+                noisy[*position] = true;
+            }
+            cursor += noise;
+        }
+
+        let mut ids = vec![];
+        let mut labels = vec![];
+        let mut sentinel = self.first_sentinel_id;
+        let mut previous_noisy = false;
+        for (position, id) in all_ids.iter().enumerate() {
+            if noisy[position] {
+                if !previous_noisy {
+                    ids.push(sentinel);
+                    labels.push(sentinel);
+                    sentinel = sentinel.wrapping_sub(1);
+                }
+                labels.push(*id);
+            } else {
+                ids.push(*id);
+            }
+            previous_noisy = noisy[position];
+        }
+        labels.push(sentinel);
+        CorruptedIds { ids, labels }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// An encoding of `n` tokens with ids `100..100 + n`, one word per two
+    /// tokens, wrapped in special tokens with id 0
+    fn encoding(n: usize) -> Encoding {
+        let ids: Vec<u32> = std::iter::once(0)
+            .chain((0..n as u32).map(|i| 100 + i))
+            .chain(std::iter::once(0))
+            .collect();
+        let words: Vec<Option<u32>> = std::iter::once(None)
+            .chain((0..n as u32).map(|i| Some(i / 2)))
+            .chain(std::iter::once(None))
+            .collect();
+        let special: Vec<u32> = std::iter::once(1)
+            .chain(std::iter::repeat(0).take(n))
+            .chain(std::iter::once(1))
+            .collect();
+        Encoding::new(
+            ids.clone(),
+            vec![0; n + 2],
+            ids.iter().map(|id| format!("tok{}", id)).collect(),
+            words,
+            vec![(0, 0); n + 2],
+            special,
+            vec![1; n + 2],
+            vec![],
+            HashMap::new(),
+        )
+    }
+
+    #[test]
+    fn whole_words_are_masked_together() {
+        let encoding = encoding(6);
+        let masking = WholeWordMasking::new(50, 1000)
+            .mask_probability(0.5)
+            .rates(1.0, 0.0);
+
+        let masked = masking.mask(&encoding, 0);
+        // Special tokens are never masked
+        assert_eq!(masked.ids[0], 0);
+        assert_eq!(masked.labels[0], None);
+        assert_eq!(*masked.ids.last().unwrap(), 0);
+        // Both tokens of a word are masked, or neither is
+        let mut some_masked = false;
+        for word in masked.ids[1..7].chunks(2) {
+            assert!((word[0] == 50) == (word[1] == 50));
+            some_masked |= word[0] == 50;
+        }
+        assert!(some_masked);
+        // Labels hold the original ids exactly at the masked positions
+        for (position, label) in masked.labels.iter().enumerate() {
+            match label {
+                Some(id) => assert_eq!(*id, encoding.get_ids()[position]),
+                None => assert_eq!(masked.ids[position], encoding.get_ids()[position]),
+            }
+        }
+    }
+
+    #[test]
+    fn masking_is_deterministic() {
+        let encoding = encoding(10);
+        let masking = WholeWordMasking::new(50, 1000);
+        assert_eq!(masking.mask(&encoding, 7), masking.mask(&encoding, 7));
+        // With everything masked the seed changes nothing; use a partial mask
+        let seeds: Vec<MaskedIds> = (0..20).map(|seed| masking.mask(&encoding, seed)).collect();
+        assert!(seeds.iter().any(|masked| *masked != seeds[0]));
+    }
+
+    #[test]
+    fn span_corruption_roundtrips() {
+        let encoding = encoding(20);
+        let corruption = SpanCorruption::new(32_000);
+        let corrupted = corruption.corrupt(&encoding, 3);
+
+        // The input got shorter, the specials are still in place
+        assert!(corrupted.ids.len() < encoding.len());
+        assert_eq!(corrupted.ids[0], 0);
+        assert_eq!(*corrupted.ids.last().unwrap(), 0);
+
+        // Replacing each sentinel of the input by the labels of its span
+        // reconstructs the original sequence
+        let mut reconstructed = vec![];
+        let mut labels = corrupted.labels.iter().peekable();
+        for id in &corrupted.ids {
+            if *id > 31_000 {
+                assert_eq!(labels.next(), Some(id));
+                while labels.peek().is_some_and(|next| **next <= 31_000) {
+                    reconstructed.push(**labels.next().as_ref().unwrap());
+                }
+            } else {
+                reconstructed.push(*id);
+            }
+        }
+        assert_eq!(reconstructed, encoding.get_ids());
+        // The labels end with the closing sentinel
+        assert!(*corrupted.labels.last().unwrap() > 31_000);
+    }
+
+    #[test]
+    fn span_corruption_respects_density() {
+        let encoding = encoding(100);
+        let corruption = SpanCorruption::new(32_000).noise_density(0.3);
+        let corrupted = corruption.corrupt(&encoding, 11);
+        let corrupted_tokens = corrupted
+            .labels
+            .iter()
+            .filter(|label| **label <= 31_000)
+            .count();
+        assert_eq!(corrupted_tokens, 30);
+    }
+}
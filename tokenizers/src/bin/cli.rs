@@ -0,0 +1,324 @@
+//! A small command line interface around a `tokenizer.json` file, to train,
+//! encode, decode and inspect tokenizers from shell scripts without writing
+//! any Rust or Python.
+
+#[cfg(feature = "runtime-only")]
+compile_error!("tokenizers-cli needs the training and filesystem entry points, and cannot be built with the `runtime-only` feature");
+
+use std::env;
+use std::io::{self, BufRead};
+use std::time::Instant;
+
+use tokenizers::models::TrainerWrapper;
+use tokenizers::{Model, Result, Tokenizer};
+
+const USAGE: &str = "\
+Usage: tokenizers-cli <command> [<args>]
+
+Commands:
+    encode <tokenizer.json> [<text>...]
+        Encode each given text (or each line of stdin when no text is given),
+        printing one JSON object per input with the ids, tokens and offsets.
+
+    decode <tokenizer.json> [<ids>...]
+        Decode the given ids (or each line of stdin when no ids are given).
+        Ids can be separated by spaces or commas.
+
+    inspect <tokenizer.json> [--vocab]
+        Print a summary of the tokenizer pipeline and vocabulary size.
+        With --vocab, also dump the full vocabulary as `id<TAB>token` lines.
+
+    train <tokenizer.json> <output.json> [--json-field <name>] <file>...
+        Train the model of the given tokenizer on the provided text files,
+        using its default trainer, and save the result to <output.json>.
+        With the `compression` feature, `.gz` and `.zst` files are
+        decompressed on the fly, and --json-field extracts the given field
+        from each line of line-delimited JSON files.
+
+    bench <tokenizer.json> [--corpus <file>]... [--dataset <repo_id>:<file>]...
+          [--iterations <n>] [--train]
+        Measure encode throughput (MB/s) and decode throughput (tokens/s),
+        and the training time with --train, over a small bundled corpus and
+        the given extra corpora, printing one JSON object with the results.
+        With the `http` feature, --dataset benchmarks against a file
+        downloaded from a dataset of the Hugging Face Hub.
+";
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("encode") => encode(&args[1..]),
+        Some("decode") => decode(&args[1..]),
+        Some("inspect") => inspect(&args[1..]),
+        Some("train") => train(&args[1..]),
+        Some("bench") => bench(&args[1..]),
+        Some("help") | Some("--help") | Some("-h") | None => {
+            print!("{USAGE}");
+            Ok(())
+        }
+        Some(other) => Err(format!("Unknown command {other:?}\n\n{USAGE}").into()),
+    }
+}
+
+fn load_tokenizer(args: &[String]) -> Result<Tokenizer> {
+    let file = args
+        .first()
+        .ok_or_else(|| format!("Missing tokenizer file\n\n{USAGE}"))?;
+    Tokenizer::from_file(file)
+}
+
+/// The given inputs, or each line of stdin when no input was provided
+fn inputs_or_stdin(args: &[String]) -> Result<Vec<String>> {
+    if args.is_empty() {
+        io::stdin()
+            .lock()
+            .lines()
+            .map(|line| Ok(line?))
+            .collect::<Result<Vec<_>>>()
+    } else {
+        Ok(args.to_vec())
+    }
+}
+
+fn encode(args: &[String]) -> Result<()> {
+    let tokenizer = load_tokenizer(args)?;
+    for input in inputs_or_stdin(&args[1..])? {
+        let encoding = tokenizer.encode(input.as_str(), true)?;
+        let offsets: Vec<Vec<usize>> = encoding
+            .get_offsets()
+            .iter()
+            .map(|(start, end)| vec![*start, *end])
+            .collect();
+        println!(
+            "{}",
+            serde_json::json!({
+                "ids": encoding.get_ids(),
+                "tokens": encoding.get_tokens(),
+                "offsets": offsets,
+            })
+        );
+    }
+    Ok(())
+}
+
+fn decode(args: &[String]) -> Result<()> {
+    let tokenizer = load_tokenizer(args)?;
+    for input in inputs_or_stdin(&args[1..])? {
+        let ids = input
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse::<u32>().map_err(|_| format!("Invalid id {s:?}")))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        println!("{}", tokenizer.decode(&ids, true)?);
+    }
+    Ok(())
+}
+
+fn inspect(args: &[String]) -> Result<()> {
+    let tokenizer = load_tokenizer(args)?;
+    let dump_vocab = args[1..].iter().any(|arg| arg == "--vocab");
+
+    fn type_of<T: serde::Serialize>(component: Option<&T>) -> String {
+        component
+            .and_then(|c| serde_json::to_value(c).ok())
+            .and_then(|v| v.get("type").and_then(|t| t.as_str()).map(String::from))
+            .unwrap_or_else(|| "None".to_string())
+    }
+
+    let model_type = serde_json::to_value(tokenizer.get_model())?
+        .get("type")
+        .and_then(|t| t.as_str())
+        .unwrap_or("Unknown")
+        .to_string();
+
+    println!("model: {model_type}");
+    println!("normalizer: {}", type_of(tokenizer.get_normalizer()));
+    println!("pre_tokenizer: {}", type_of(tokenizer.get_pre_tokenizer()));
+    println!(
+        "post_processor: {}",
+        type_of(tokenizer.get_post_processor())
+    );
+    println!("decoder: {}", type_of(tokenizer.get_decoder()));
+    println!("vocab_size: {}", tokenizer.get_vocab_size(true));
+    println!(
+        "added_tokens: {}",
+        tokenizer.get_added_vocabulary().get_vocab().len()
+    );
+
+    if dump_vocab {
+        for (id, token) in tokenizer.get_model().get_vocab_r() {
+            println!("{id}\t{token}");
+        }
+    }
+    Ok(())
+}
+
+/// A few sentences of varied scripts, casing and punctuation, repeated to a
+/// measurable size, so benchmarks can run without any dataset at hand
+const BUILTIN_SENTENCES: &str = "\
+The quick brown fox jumps over the lazy dog, twice: once in 2023 and once in 2024!
+Il était une fois, dans une forêt lointaine, un renard qui ne sautait jamais.
+Die Größe des Wortschatzes beeinflusst die Geschwindigkeit des Tokenizers erheblich.
+¿Cuántos tokens produce una frase corta? Depende del modelo, claro está.
+Numbers (3.14159, 1e-10) and symbols ($, €, ~, @) stress the pre-tokenizer.
+";
+
+fn bench(args: &[String]) -> Result<()> {
+    let tokenizer = load_tokenizer(args)?;
+    let mut iterations: usize = 5;
+    let mut train = false;
+    let mut corpora: Vec<(String, String)> =
+        vec![("builtin".into(), BUILTIN_SENTENCES.repeat(512))];
+    let mut rest = args[1..].iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--corpus" => {
+                let file = rest
+                    .next()
+                    .ok_or_else(|| format!("Missing value for --corpus\n\n{USAGE}"))?;
+                corpora.push((file.clone(), std::fs::read_to_string(file)?));
+            }
+            "--dataset" => {
+                let spec = rest
+                    .next()
+                    .ok_or_else(|| format!("Missing value for --dataset\n\n{USAGE}"))?;
+                #[cfg(feature = "http")]
+                {
+                    let path = download_dataset_file(spec)?;
+                    corpora.push((spec.clone(), std::fs::read_to_string(path)?));
+                }
+                #[cfg(not(feature = "http"))]
+                {
+                    let _ = spec;
+                    return Err("--dataset requires building with the `http` feature".into());
+                }
+            }
+            "--iterations" => {
+                iterations = rest
+                    .next()
+                    .ok_or_else(|| format!("Missing value for --iterations\n\n{USAGE}"))?
+                    .parse()
+                    .map_err(|_| "Invalid value for --iterations")?;
+            }
+            "--train" => train = true,
+            other => return Err(format!("Unknown bench option {other:?}\n\n{USAGE}").into()),
+        }
+    }
+    if iterations == 0 {
+        return Err("--iterations must be at least 1".into());
+    }
+
+    let mut results = vec![];
+    for (name, text) in &corpora {
+        let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+        let bytes: usize = lines.iter().map(|l| l.len()).sum();
+
+        // Warm up, keeping the ids for the decode benchmark
+        let encoded: Vec<Vec<u32>> = lines
+            .iter()
+            .map(|line| Ok(tokenizer.encode(*line, true)?.get_ids().to_vec()))
+            .collect::<Result<_>>()?;
+        let n_tokens: usize = encoded.iter().map(|ids| ids.len()).sum();
+
+        let start = Instant::now();
+        for _ in 0..iterations {
+            for line in &lines {
+                tokenizer.encode(*line, true)?;
+            }
+        }
+        let encode_secs = start.elapsed().as_secs_f64() / iterations as f64;
+
+        let start = Instant::now();
+        for _ in 0..iterations {
+            for ids in &encoded {
+                tokenizer.decode(ids, true)?;
+            }
+        }
+        let decode_secs = start.elapsed().as_secs_f64() / iterations as f64;
+
+        let train_secs = if train {
+            let mut trained = tokenizer.clone();
+            let mut trainer: TrainerWrapper = trained.get_model().get_trainer();
+            let start = Instant::now();
+            trained.train(&mut trainer, lines.iter())?;
+            Some(start.elapsed().as_secs_f64())
+        } else {
+            None
+        };
+
+        results.push(serde_json::json!({
+            "corpus": name,
+            "bytes": bytes,
+            "tokens": n_tokens,
+            "encode_mb_per_s": bytes as f64 / (1024.0 * 1024.0) / encode_secs,
+            "decode_tokens_per_s": n_tokens as f64 / decode_secs,
+            "train_s": train_secs,
+        }));
+    }
+    println!(
+        "{}",
+        serde_json::json!({ "iterations": iterations, "results": results })
+    );
+    Ok(())
+}
+
+/// Download the file of a `<repo_id>:<filename>` dataset spec from the
+/// Hugging Face Hub, returning the local path of the cached file
+#[cfg(feature = "http")]
+fn download_dataset_file(spec: &str) -> Result<std::path::PathBuf> {
+    use hf_hub::{api::sync::ApiBuilder, Repo, RepoType};
+    let (repo_id, filename) = spec
+        .split_once(':')
+        .ok_or_else(|| format!("Invalid dataset spec {spec:?}, expected <repo_id>:<filename>"))?;
+    let api = ApiBuilder::new().build()?;
+    let repo = Repo::new(repo_id.to_string(), RepoType::Dataset);
+    Ok(api.repo(repo).get(filename)?)
+}
+
+fn train(args: &[String]) -> Result<()> {
+    let mut tokenizer = load_tokenizer(args)?;
+    let output = args
+        .get(1)
+        .ok_or_else(|| format!("Missing output file\n\n{USAGE}"))?;
+    let mut json_field: Option<String> = None;
+    let mut files: Vec<String> = vec![];
+    let mut rest = args[2..].iter();
+    while let Some(arg) = rest.next() {
+        if arg == "--json-field" {
+            json_field = Some(
+                rest.next()
+                    .ok_or_else(|| format!("Missing value for --json-field\n\n{USAGE}"))?
+                    .clone(),
+            );
+        } else {
+            files.push(arg.clone());
+        }
+    }
+    if files.is_empty() {
+        return Err(format!("Missing training files\n\n{USAGE}").into());
+    }
+
+    let mut trainer: TrainerWrapper = tokenizer.get_model().get_trainer();
+    match json_field {
+        #[cfg(feature = "compression")]
+        Some(field) => {
+            tokenizer.train_from_corpus_files(&mut trainer, files, Some(&field))?;
+        }
+        #[cfg(not(feature = "compression"))]
+        Some(_) => {
+            return Err("--json-field requires building with the `compression` feature".into());
+        }
+        None => {
+            tokenizer.train_from_files(&mut trainer, files)?;
+        }
+    }
+    tokenizer.save(output, true)?;
+    Ok(())
+}
@@ -0,0 +1,133 @@
+//! Compare two tokenizers over a corpus, typically before swapping a new
+//! tokenizer version into a production pipeline: which tokens they do not
+//! share, how their token counts drift, and examples of diverging
+//! segmentations.
+
+use crate::tokenizer::{Result, Tokenizer};
+use serde::{Deserialize, Serialize};
+
+/// The maximum number of diverging segmentations collected in a [`DiffReport`]
+const MAX_EXAMPLES: usize = 10;
+
+/// A structured comparison of two tokenizers, produced by [`diff`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffReport {
+    /// The number of tokens present in both vocabularies
+    pub common_tokens: usize,
+    /// The tokens only the first tokenizer knows, sorted
+    pub only_in_a: Vec<String>,
+    /// The tokens only the second tokenizer knows, sorted
+    pub only_in_b: Vec<String>,
+    /// For each document of the corpus, the token count of the second
+    /// tokenizer minus the one of the first
+    pub token_count_deltas: Vec<i64>,
+    /// Up to [`MAX_EXAMPLES`] documents that the two tokenizers segment
+    /// differently, in corpus order
+    pub examples: Vec<SegmentationDifference>,
+}
+
+/// A document that two tokenizers segment differently
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SegmentationDifference {
+    /// The index of the document in the corpus
+    pub document: usize,
+    /// The tokens produced by the first tokenizer
+    pub tokens_a: Vec<String>,
+    /// The tokens produced by the second tokenizer
+    pub tokens_b: Vec<String>,
+}
+
+/// Compare the vocabularies of the two tokenizers, and their segmentations of
+/// the given corpus. Documents are encoded without special tokens, so the
+/// report only reflects how the tokenizers actually split text.
+pub fn diff<I, S>(a: &Tokenizer, b: &Tokenizer, corpus: I) -> Result<DiffReport>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let vocab_a = a.get_vocab(true);
+    let vocab_b = b.get_vocab(true);
+
+    let common_tokens = vocab_a.keys().filter(|t| vocab_b.contains_key(*t)).count();
+    let mut only_in_a: Vec<String> = vocab_a
+        .keys()
+        .filter(|t| !vocab_b.contains_key(*t))
+        .cloned()
+        .collect();
+    let mut only_in_b: Vec<String> = vocab_b
+        .keys()
+        .filter(|t| !vocab_a.contains_key(*t))
+        .cloned()
+        .collect();
+    only_in_a.sort_unstable();
+    only_in_b.sort_unstable();
+
+    let mut token_count_deltas = vec![];
+    let mut examples = vec![];
+    for (document, sequence) in corpus.into_iter().enumerate() {
+        let encoding_a = a.encode(sequence.as_ref(), false)?;
+        let encoding_b = b.encode(sequence.as_ref(), false)?;
+        let tokens_a = encoding_a.get_tokens();
+        let tokens_b = encoding_b.get_tokens();
+
+        token_count_deltas.push(tokens_b.len() as i64 - tokens_a.len() as i64);
+        if tokens_a != tokens_b && examples.len() < MAX_EXAMPLES {
+            examples.push(SegmentationDifference {
+                document,
+                tokens_a: tokens_a.to_vec(),
+                tokens_b: tokens_b.to_vec(),
+            });
+        }
+    }
+
+    Ok(DiffReport {
+        common_tokens,
+        only_in_a,
+        only_in_b,
+        token_count_deltas,
+        examples,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::wordpiece::WordPiece;
+    use crate::pre_tokenizers::whitespace::Whitespace;
+
+    fn tokenizer(vocab: &[(&str, u32)]) -> Tokenizer {
+        let wordpiece = WordPiece::builder()
+            .vocab(
+                vocab
+                    .iter()
+                    .map(|(token, id)| (token.to_string(), *id))
+                    .collect::<std::collections::HashMap<_, _>>()
+                    .into(),
+            )
+            .build()
+            .unwrap();
+        let mut tokenizer = Tokenizer::new(wordpiece);
+        tokenizer.with_pre_tokenizer(Some(Whitespace {}));
+        tokenizer
+    }
+
+    #[test]
+    fn test_diff() {
+        let a = tokenizer(&[("[UNK]", 0), ("hello", 1), ("world", 2)]);
+        let b = tokenizer(&[("[UNK]", 0), ("hello", 1), ("wor", 2), ("##ld", 3)]);
+
+        let report = diff(&a, &b, ["hello hello", "hello world"]).unwrap();
+        assert_eq!(report.common_tokens, 2);
+        assert_eq!(report.only_in_a, vec!["world"]);
+        assert_eq!(report.only_in_b, vec!["##ld", "wor"]);
+        assert_eq!(report.token_count_deltas, vec![0, 1]);
+        assert_eq!(
+            report.examples,
+            vec![SegmentationDifference {
+                document: 1,
+                tokens_a: vec!["hello".into(), "world".into()],
+                tokens_b: vec!["hello".into(), "wor".into(), "##ld".into()],
+            }]
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use crate::tokenizer::{Decoder, Result};
+
+use serde::{Deserialize, Serialize};
+
+/// The typographic rules to apply when re-attaching punctuation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CleanUpLocale {
+    /// Attach every punctuation sign to the preceding word (`" ?"` to `"?"`)
+    #[default]
+    English,
+    /// Keep the space before the two-part punctuation signs (`?`, `!`, `;`,
+    /// `:`), as French typography requires, and only attach `.` and `,`
+    French,
+}
+
+/// A decoder cleaning up the spacing artifacts of decoding, implementing the
+/// same rules as the `clean_up_tokenization_spaces` option of the Python
+/// `transformers` library: punctuation is re-attached to the preceding word,
+/// and the English contractions split by pre-tokenization (`" 's"`, `" n't"`,
+/// ...) are glued back. Like [`super::fuse::Fuse`], it fuses the tokens into
+/// a single string, so it usually comes last in a decoder sequence.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[non_exhaustive]
+pub struct CleanUp {
+    /// The typographic rules to apply when re-attaching punctuation
+    pub locale: CleanUpLocale,
+    /// Whether to glue the English contractions back to the preceding word
+    pub contractions: bool,
+}
+
+impl Default for CleanUp {
+    fn default() -> Self {
+        Self {
+            locale: CleanUpLocale::default(),
+            contractions: true,
+        }
+    }
+}
+
+impl CleanUp {
+    pub fn new(locale: CleanUpLocale, contractions: bool) -> Self {
+        Self {
+            locale,
+            contractions,
+        }
+    }
+
+    /// Apply the cleanup rules to the fused decoded string, mirroring
+    /// `clean_up_tokenization` from `transformers` byte-for-byte in the
+    /// `English` locale
+    fn clean(&self, input: &str) -> String {
+        let mut output = input.replace(" .", ".").replace(" ,", ",");
+        if self.locale == CleanUpLocale::English {
+            output = output.replace(" ?", "?").replace(" !", "!");
+        }
+        if self.contractions {
+            output = output
+                .replace(" ' ", "'")
+                .replace(" n't", "n't")
+                .replace(" 'm", "'m")
+                .replace(" 's", "'s")
+                .replace(" 've", "'ve")
+                .replace(" 're", "'re");
+        }
+        output
+    }
+}
+
+impl Decoder for CleanUp {
+    fn decode_chain(&self, tokens: Vec<String>) -> Result<Vec<String>> {
+        Ok(vec![self.clean(&tokens.join(""))])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cleanup_english() {
+        let decoder = CleanUp::default();
+        let res = decoder
+            .decode_chain(vec![
+                "I".into(),
+                " 'm".into(),
+                " sure".into(),
+                " ,".into(),
+                " are".into(),
+                " n't".into(),
+                " you".into(),
+                " ?".into(),
+            ])
+            .unwrap();
+        assert_eq!(res, vec!["I'm sure, aren't you?"]);
+    }
+
+    #[test]
+    fn actual_words_are_left_untouched() {
+        // Only the split contraction suffixes are glued back: regular words
+        // like "do not" pass through unchanged
+        let decoder = CleanUp::default();
+        let res = decoder
+            .decode_chain(vec![
+                "please".into(),
+                " do".into(),
+                " not".into(),
+                " go".into(),
+            ])
+            .unwrap();
+        assert_eq!(res, vec!["please do not go"]);
+    }
+
+    #[test]
+    fn cleanup_french_locale() {
+        let decoder = CleanUp::new(CleanUpLocale::French, false);
+        let res = decoder
+            .decode_chain(vec![
+                "Vraiment".into(),
+                " ?".into(),
+                " Oui".into(),
+                " .".into(),
+            ])
+            .unwrap();
+        // The space before the two-part punctuation is kept
+        assert_eq!(res, vec!["Vraiment ? Oui."]);
+    }
+
+    #[test]
+    fn cleanup_serde() {
+        let decoder = CleanUp::default();
+        let decoder_s = r#"{"type":"CleanUp","locale":"English","contractions":true}"#;
+        assert_eq!(serde_json::to_string(&decoder).unwrap(), decoder_s);
+        let deserialized: CleanUp = serde_json::from_str(decoder_s).unwrap();
+        assert_eq!(serde_json::to_string(&deserialized).unwrap(), decoder_s);
+    }
+}
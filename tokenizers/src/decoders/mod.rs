@@ -1,9 +1,11 @@
 pub mod bpe;
 pub mod byte_fallback;
+pub mod cleanup;
 pub mod ctc;
 pub mod fuse;
 pub mod sequence;
 pub mod strip;
+pub mod true_case;
 pub mod wordpiece;
 
 // Re-export these as decoders
@@ -14,15 +16,17 @@ use serde::{Deserialize, Deserializer, Serialize};
 
 use crate::decoders::bpe::BPEDecoder;
 use crate::decoders::byte_fallback::ByteFallback;
+use crate::decoders::cleanup::CleanUp;
 use crate::decoders::ctc::CTC;
 use crate::decoders::fuse::Fuse;
 use crate::decoders::sequence::Sequence;
 use crate::decoders::strip::Strip;
+use crate::decoders::true_case::TrueCase;
 use crate::decoders::wordpiece::WordPiece;
 use crate::normalizers::replace::Replace;
 use crate::pre_tokenizers::byte_level::ByteLevel;
 use crate::pre_tokenizers::metaspace::Metaspace;
-use crate::{Decoder, Result};
+use crate::{DecodedWithAlignment, Decoder, Result};
 
 #[derive(Serialize, Clone, Debug)]
 #[serde(untagged)]
@@ -37,6 +41,8 @@ pub enum DecoderWrapper {
     Fuse(Fuse),
     Strip(Strip),
     ByteFallback(ByteFallback),
+    CleanUp(CleanUp),
+    TrueCase(TrueCase),
 }
 
 impl<'de> Deserialize<'de> for DecoderWrapper {
@@ -63,6 +69,8 @@ impl<'de> Deserialize<'de> for DecoderWrapper {
             Fuse,
             Strip,
             ByteFallback,
+            CleanUp,
+            TrueCase,
         }
 
         #[derive(Deserialize)]
@@ -85,6 +93,8 @@ impl<'de> Deserialize<'de> for DecoderWrapper {
             Fuse(Fuse),
             Strip(Strip),
             ByteFallback(ByteFallback),
+            CleanUp(CleanUp),
+            TrueCase(TrueCase),
         }
 
         let helper = DecoderHelper::deserialize(deserializer).expect("Helper");
@@ -128,6 +138,12 @@ impl<'de> Deserialize<'de> for DecoderWrapper {
                     EnumType::ByteFallback => DecoderWrapper::ByteFallback(
                         serde_json::from_value(values).map_err(serde::de::Error::custom)?,
                     ),
+                    EnumType::CleanUp => DecoderWrapper::CleanUp(
+                        serde_json::from_value(values).map_err(serde::de::Error::custom)?,
+                    ),
+                    EnumType::TrueCase => DecoderWrapper::TrueCase(
+                        serde_json::from_value(values).map_err(serde::de::Error::custom)?,
+                    ),
                 }
             }
             DecoderHelper::Legacy(value) => {
@@ -143,6 +159,8 @@ impl<'de> Deserialize<'de> for DecoderWrapper {
                     DecoderUntagged::Fuse(dec) => DecoderWrapper::Fuse(dec),
                     DecoderUntagged::Strip(dec) => DecoderWrapper::Strip(dec),
                     DecoderUntagged::ByteFallback(dec) => DecoderWrapper::ByteFallback(dec),
+                    DecoderUntagged::CleanUp(dec) => DecoderWrapper::CleanUp(dec),
+                    DecoderUntagged::TrueCase(dec) => DecoderWrapper::TrueCase(dec),
                 }
             }
         })
@@ -162,6 +180,43 @@ impl Decoder for DecoderWrapper {
             Self::ByteFallback(bf) => bf.decode_chain(tokens),
             Self::Strip(bf) => bf.decode_chain(tokens),
             Self::Fuse(bf) => bf.decode_chain(tokens),
+            Self::CleanUp(cu) => cu.decode_chain(tokens),
+            Self::TrueCase(tc) => tc.decode_chain(tokens),
+        }
+    }
+
+    fn decode_with_alignment(&self, tokens: Vec<String>) -> Result<DecodedWithAlignment> {
+        match self {
+            Self::BPE(bpe) => bpe.decode_with_alignment(tokens),
+            Self::ByteLevel(bl) => bl.decode_with_alignment(tokens),
+            Self::Metaspace(ms) => ms.decode_with_alignment(tokens),
+            Self::WordPiece(wp) => wp.decode_with_alignment(tokens),
+            Self::CTC(ctc) => ctc.decode_with_alignment(tokens),
+            Self::Sequence(seq) => seq.decode_with_alignment(tokens),
+            Self::Replace(seq) => seq.decode_with_alignment(tokens),
+            Self::ByteFallback(bf) => bf.decode_with_alignment(tokens),
+            Self::Strip(bf) => bf.decode_with_alignment(tokens),
+            Self::Fuse(bf) => bf.decode_with_alignment(tokens),
+            Self::CleanUp(cu) => cu.decode_with_alignment(tokens),
+            Self::TrueCase(tc) => tc.decode_with_alignment(tokens),
+        }
+    }
+
+    fn prefix_space(&self) -> Option<bool> {
+        match self {
+            Self::ByteLevel(bl) => bl.prefix_space(),
+            Self::Metaspace(ms) => ms.prefix_space(),
+            Self::Sequence(seq) => seq.prefix_space(),
+            _ => None,
+        }
+    }
+
+    fn set_prefix_space(&mut self, prefix_space: bool) {
+        match self {
+            Self::ByteLevel(bl) => bl.set_prefix_space(prefix_space),
+            Self::Metaspace(ms) => ms.set_prefix_space(prefix_space),
+            Self::Sequence(seq) => seq.set_prefix_space(prefix_space),
+            _ => {}
         }
     }
 }
@@ -169,8 +224,10 @@ impl Decoder for DecoderWrapper {
 impl_enum_from!(BPEDecoder, DecoderWrapper, BPE);
 impl_enum_from!(ByteLevel, DecoderWrapper, ByteLevel);
 impl_enum_from!(ByteFallback, DecoderWrapper, ByteFallback);
+impl_enum_from!(CleanUp, DecoderWrapper, CleanUp);
 impl_enum_from!(Fuse, DecoderWrapper, Fuse);
 impl_enum_from!(Strip, DecoderWrapper, Strip);
+impl_enum_from!(TrueCase, DecoderWrapper, TrueCase);
 impl_enum_from!(Metaspace, DecoderWrapper, Metaspace);
 impl_enum_from!(WordPiece, DecoderWrapper, WordPiece);
 impl_enum_from!(CTC, DecoderWrapper, CTC);
@@ -186,7 +243,7 @@ mod tests {
         let oldjson = r#"{"type":"Sequence","decoders":[{"type":"ByteFallback"},{"type":"Metaspace","replacement":"▁","add_prefix_space":true,"prepend_scheme":"always"}]}"#;
         let olddecoder: DecoderWrapper = serde_json::from_str(oldjson).unwrap();
         let oldserialized = serde_json::to_string(&olddecoder).unwrap();
-        let json = r#"{"type":"Sequence","decoders":[{"type":"ByteFallback"},{"type":"Metaspace","replacement":"▁","prepend_scheme":"always","split":true}]}"#;
+        let json = r#"{"type":"Sequence","decoders":[{"type":"ByteFallback"},{"type":"Metaspace","replacement":"▁","prepend_scheme":"always","split":true,"collapse_whitespace":false}]}"#;
         assert_eq!(oldserialized, json);
 
         let decoder: DecoderWrapper = serde_json::from_str(json).unwrap();
@@ -195,12 +252,35 @@ mod tests {
     }
     #[test]
     fn decoder_serialization_other_no_arg() {
-        let json = r#"{"type":"Sequence","decoders":[{"type":"Fuse"},{"type":"Metaspace","replacement":"▁","prepend_scheme":"always","split":true}]}"#;
+        let json = r#"{"type":"Sequence","decoders":[{"type":"Fuse"},{"type":"Metaspace","replacement":"▁","prepend_scheme":"always","split":true,"collapse_whitespace":false}]}"#;
         let decoder: DecoderWrapper = serde_json::from_str(json).unwrap();
         let serialized = serde_json::to_string(&decoder).unwrap();
         assert_eq!(serialized, json);
     }
 
+    #[test]
+    fn default_decode_with_alignment() {
+        // Length-preserving decoders attribute each piece to its token
+        let decoder = WordPiece::new("##".to_string(), false);
+        let decoded = decoder
+            .decode_with_alignment(vec!["Hey".into(), "##o".into(), "friend".into()])
+            .unwrap();
+        assert_eq!(decoded.text, "Heyo friend");
+        assert_eq!(
+            decoded.alignments,
+            vec![Some((0, 3)), Some((3, 4)), Some((4, 11))]
+        );
+        assert_eq!(decoded.byte_to_token(3), Some(1));
+        assert_eq!(decoded.char_to_token(4), Some(2));
+
+        // Decoders that fuse their tokens give up on attribution
+        let decoded = Fuse::new()
+            .decode_with_alignment(vec!["Hey".into(), " friend".into()])
+            .unwrap();
+        assert_eq!(decoded.text, "Hey friend");
+        assert_eq!(decoded.alignments, vec![None, None]);
+    }
+
     #[test]
     fn decoder_serialization_no_decode() {
         let json = r#"{"type":"Sequence","decoders":[{},{"type":"Metaspace","replacement":"▁","prepend_scheme":"always"}]}"#;
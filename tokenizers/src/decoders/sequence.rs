@@ -21,6 +21,18 @@ impl Sequence {
     pub fn get_decoders_mut(&mut self) -> &mut [DecoderWrapper] {
         &mut self.decoders
     }
+
+    /// Decode the given tokens, returning the intermediate string obtained
+    /// after each decoder of the sequence. This is mostly useful to debug a
+    /// pipeline, to pinpoint which decoder introduces an unexpected output.
+    pub fn decode_debug(&self, mut tokens: Vec<String>) -> Result<Vec<String>> {
+        let mut steps = Vec::with_capacity(self.decoders.len());
+        for decoder in &self.decoders {
+            tokens = decoder.decode_chain(tokens)?;
+            steps.push(tokens.concat());
+        }
+        Ok(steps)
+    }
 }
 
 impl Decoder for Sequence {
@@ -30,6 +42,18 @@ impl Decoder for Sequence {
         }
         Ok(tokens)
     }
+
+    fn prefix_space(&self) -> Option<bool> {
+        self.decoders
+            .iter()
+            .find_map(|decoder| decoder.prefix_space())
+    }
+
+    fn set_prefix_space(&mut self, prefix_space: bool) {
+        for decoder in &mut self.decoders {
+            decoder.set_prefix_space(prefix_space);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -52,4 +76,19 @@ mod tests {
         let out_tokens = decoder.decode(tokens).unwrap();
         assert_eq!(out_tokens, "Hi you");
     }
+
+    #[test]
+    fn sequence_decode_debug() {
+        let decoders = vec![
+            DecoderWrapper::CTC(CTC::default()),
+            DecoderWrapper::Metaspace(Metaspace::default()),
+        ];
+        let decoder = Sequence::new(decoders);
+        let tokens: Vec<String> = vec!["▁", "▁", "H", "H", "i", "i", "▁", "y", "o", "u"]
+            .into_iter()
+            .map(|s| s.to_string())
+            .collect();
+        let steps = decoder.decode_debug(tokens).unwrap();
+        assert_eq!(steps, vec!["▁Hi▁you", "Hi you"]);
+    }
 }
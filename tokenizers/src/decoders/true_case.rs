@@ -0,0 +1,286 @@
+use std::collections::BTreeMap;
+
+use monostate::MustBe;
+use serde::{Deserialize, Serialize};
+
+use crate::tokenizer::{Decoder, Result};
+
+/// Restores capitalization of a lowercased pipeline's output, so that decoded
+/// text can be surfaced to users with readable casing: the first word of each
+/// sentence is capitalized, and words found in the lexicon take their known
+/// cased spelling (`"paris"` -> `"Paris"`).
+///
+/// When the original input is at hand, [`TrueCase::restore`] instead copies
+/// the casing straight from it, aligning the decoded words to the original
+/// ones case-insensitively, and only falls back to the lexicon and sentence
+/// rules for words the original does not contain.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[non_exhaustive]
+pub struct TrueCase {
+    #[serde(rename = "type")]
+    type_: MustBe!("TrueCase"),
+    /// Known cased spellings, keyed by their lowercase form
+    #[serde(default)]
+    pub lexicon: BTreeMap<String, String>,
+    /// Whether to capitalize the first word of each sentence
+    #[serde(default = "default_capitalize_sentences")]
+    pub capitalize_sentences: bool,
+}
+
+fn default_capitalize_sentences() -> bool {
+    true
+}
+
+/// The lowercased form of the original string, with a byte-offset map back to
+/// it: `str::to_lowercase` is not byte-length-stable (e.g. 'İ' lowercases to
+/// `"i\u{307}"`), so offsets found in the lowercased string cannot index the
+/// original directly
+struct OriginalAlignment<'a> {
+    original: &'a str,
+    lower: String,
+    /// `(lower_offset, original_offset)` at each character boundary of the
+    /// original, plus the final `(lower.len(), original.len())`
+    offsets: Vec<(usize, usize)>,
+}
+
+impl<'a> OriginalAlignment<'a> {
+    fn new(original: &'a str) -> Self {
+        let mut lower = String::with_capacity(original.len());
+        let mut offsets = Vec::with_capacity(original.len() + 1);
+        for (offset, c) in original.char_indices() {
+            offsets.push((lower.len(), offset));
+            lower.extend(c.to_lowercase());
+        }
+        offsets.push((lower.len(), original.len()));
+        Self {
+            original,
+            lower,
+            offsets,
+        }
+    }
+
+    /// Find the next occurrence of the lowercased `word` from `cursor` (a byte
+    /// offset in the lowercased string) and return the matching slice of the
+    /// original, advancing the cursor past the occurrence. Occurrences not
+    /// covering whole characters of the original are skipped
+    fn find(&self, word: &str, cursor: &mut usize) -> Option<&'a str> {
+        let mut search = *cursor;
+        while let Some(found) = self.lower[search..].find(word) {
+            let (lower_start, lower_end) = (search + found, search + found + word.len());
+            if let (Some(start), Some(end)) = (self.offset(lower_start), self.offset(lower_end)) {
+                *cursor = lower_end;
+                return Some(&self.original[start..end]);
+            }
+            search = lower_start
+                + self.lower[lower_start..]
+                    .chars()
+                    .next()
+                    .map_or(1, char::len_utf8);
+        }
+        None
+    }
+
+    /// The offset in the original of the given offset in its lowercased form,
+    /// when it falls on a character boundary of the original
+    fn offset(&self, lower_offset: usize) -> Option<usize> {
+        self.offsets
+            .binary_search_by_key(&lower_offset, |(lower, _)| *lower)
+            .ok()
+            .map(|found| self.offsets[found].1)
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+impl TrueCase {
+    pub fn new() -> Self {
+        Self {
+            type_: MustBe!("TrueCase"),
+            lexicon: BTreeMap::new(),
+            capitalize_sentences: true,
+        }
+    }
+
+    /// Set the known cased spellings, keyed by their lowercase form.
+    #[must_use]
+    pub fn lexicon(mut self, lexicon: BTreeMap<String, String>) -> Self {
+        self.lexicon = lexicon;
+        self
+    }
+
+    /// The cased form of a single word: its lexicon spelling when known,
+    /// capitalized when it opens a sentence, unchanged otherwise
+    fn case_word(&self, word: &str, sentence_start: bool) -> String {
+        if let Some(cased) = self.lexicon.get(word) {
+            return cased.clone();
+        }
+        if sentence_start && self.capitalize_sentences {
+            capitalize(word)
+        } else {
+            word.to_owned()
+        }
+    }
+
+    /// Restore the casing of `text`. When `original` is given (typically the
+    /// pre-normalization input, available to the caller through the encoding
+    /// offsets), the words of `text` are aligned to it case-insensitively,
+    /// left to right, and copy its casing; only unaligned words fall back to
+    /// the lexicon and sentence rules.
+    pub fn restore(&self, text: &str, original: Option<&str>) -> String {
+        let alignment = original.map(OriginalAlignment::new);
+        let mut cursor = 0;
+        let mut restored = String::with_capacity(text.len());
+        let mut sentence_start = true;
+        let mut word = String::new();
+        for c in text.chars().chain([' ']) {
+            if c.is_alphanumeric() || c == '\'' {
+                word.push(c);
+                continue;
+            }
+            if !word.is_empty() {
+                // Words found in the original (left to right, so repeated
+                // words map to distinct occurrences) keep its exact casing
+                let aligned = alignment
+                    .as_ref()
+                    .and_then(|alignment| alignment.find(&word.to_lowercase(), &mut cursor));
+                match aligned {
+                    Some(cased) => restored.push_str(cased),
+                    None => restored.push_str(&self.case_word(&word, sentence_start)),
+                }
+                sentence_start = false;
+                word.clear();
+            }
+            if matches!(c, '.' | '!' | '?' | '…') {
+                sentence_start = true;
+            }
+            restored.push(c);
+        }
+        restored.pop();
+        restored
+    }
+}
+
+impl Decoder for TrueCase {
+    fn decode_chain(&self, tokens: Vec<String>) -> Result<Vec<String>> {
+        Ok(self.restore_pieces(tokens))
+    }
+}
+
+impl TrueCase {
+    /// Restore the casing of the tokens, keeping one output piece per input
+    /// token. The sentence state carries across pieces, but a word has to fit
+    /// within one piece to match the lexicon, so this decoder belongs after
+    /// the steps reassembling words (BPE/WordPiece decoding, cleanup).
+    fn restore_pieces(&self, tokens: Vec<String>) -> Vec<String> {
+        let mut sentence_start = true;
+        let mut previous_in_word = false;
+        tokens
+            .into_iter()
+            .map(|piece| {
+                let mut restored = String::with_capacity(piece.len());
+                let mut word = String::new();
+                let mut continuation = previous_in_word;
+                for c in piece.chars().chain([' ']) {
+                    if c.is_alphanumeric() || c == '\'' {
+                        word.push(c);
+                        continue;
+                    }
+                    if !word.is_empty() {
+                        if continuation {
+                            // The tail of a word started in a previous piece:
+                            // never re-capitalized
+                            restored.push_str(&word);
+                        } else {
+                            restored.push_str(&self.case_word(&word, sentence_start));
+                            sentence_start = false;
+                        }
+                        word.clear();
+                    }
+                    continuation = false;
+                    if matches!(c, '.' | '!' | '?' | '…') {
+                        sentence_start = true;
+                    }
+                    restored.push(c);
+                }
+                restored.pop();
+                previous_in_word = restored
+                    .chars()
+                    .last()
+                    .map(|c| c.is_alphanumeric() || c == '\'')
+                    .unwrap_or(previous_in_word);
+                restored
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sentences_and_lexicon() {
+        let decoder = TrueCase::new()
+            .lexicon(std::iter::once(("paris".to_string(), "Paris".to_string())).collect());
+        let pieces = decoder
+            .decode_chain(vec![
+                "hello world.".into(),
+                " i live".into(),
+                " in paris".into(),
+            ])
+            .unwrap();
+        assert_eq!(pieces, ["Hello world.", " I live", " in Paris"]);
+    }
+
+    #[test]
+    fn words_split_across_pieces_are_not_recapitalized() {
+        let decoder = TrueCase::new();
+        let pieces = decoder
+            .decode_chain(vec!["hel".into(), "lo there. bye".into()])
+            .unwrap();
+        assert_eq!(pieces, ["Hel", "lo there. Bye"]);
+    }
+
+    #[test]
+    fn restore_copies_the_original_casing() {
+        let decoder = TrueCase::new();
+        let restored = decoder.restore(
+            "anne McNamara visited openai today",
+            Some("Anne McNamara visited OpenAI yesterday"),
+        );
+        // Words aligned to the original copy its casing, the others fall
+        // back to the sentence rules
+        assert_eq!(restored, "Anne McNamara visited OpenAI today");
+    }
+
+    #[test]
+    fn restore_aligns_non_length_stable_casing() {
+        let decoder = TrueCase::new();
+        // 'İ' (U+0130) lowercases to "i\u{307}", one byte longer, so offsets
+        // found in the lowercased original drift from the original's
+        assert_eq!(decoder.restore("b c", Some("İ B C")), "B C");
+        assert_eq!(decoder.restore("c", Some("aİb c")), "c");
+    }
+
+    #[test]
+    fn serialization() {
+        let decoder = TrueCase::new()
+            .lexicon(std::iter::once(("paris".to_string(), "Paris".to_string())).collect());
+        let json = serde_json::to_string(&decoder).unwrap();
+        assert_eq!(
+            json,
+            r#"{"type":"TrueCase","lexicon":{"paris":"Paris"},"capitalize_sentences":true}"#
+        );
+        let decoder: TrueCase = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            decoder.lexicon.get("paris").map(String::as_str),
+            Some("Paris")
+        );
+    }
+}
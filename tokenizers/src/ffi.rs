@@ -0,0 +1,314 @@
+//! A stable, cbindgen-friendly C ABI over the tokenizer runtime, so Go,
+//! Java, Swift or C++ services can embed the crate without going through the
+//! Python bindings.
+//!
+//! The API follows the usual C conventions: opaque handles behind raw
+//! pointers, caller-provided output arrays with a two-call pattern to size
+//! them, integer status codes, and a thread-local last-error message.
+//!
+//! # Thread safety
+//!
+//! A [`Tokenizer`] handle is immutable once created and the underlying type
+//! is `Sync`: the same handle can be shared across threads and used from all
+//! of them concurrently without locking. Creation and destruction are the
+//! only exceptions: `tokenizers_free` must not race with any other call on
+//! the same handle. Error messages are thread-local, so
+//! `tokenizers_last_error` returns the message of the last failed call on
+//! the calling thread.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+
+use crate::Tokenizer;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(err: impl std::fmt::Display) {
+    let msg = CString::new(err.to_string().replace('\0', " "))
+        .unwrap_or_else(|_| CString::new("invalid error message").expect("No interior nul"));
+    LAST_ERROR.with(|slot| *slot.borrow_mut() = Some(msg));
+}
+
+fn clear_last_error() {
+    LAST_ERROR.with(|slot| *slot.borrow_mut() = None);
+}
+
+/// Returns the message of the last error raised on the calling thread, or
+/// null when the last call succeeded. The pointer stays valid until the next
+/// failing call on the same thread; do not free it.
+#[no_mangle]
+pub extern "C" fn tokenizers_last_error() -> *const c_char {
+    LAST_ERROR.with(|slot| {
+        slot.borrow()
+            .as_ref()
+            .map_or(std::ptr::null(), |msg| msg.as_ptr())
+    })
+}
+
+/// Loads a tokenizer from a `tokenizer.json` file. Returns an owned handle,
+/// or null on failure (see `tokenizers_last_error`). Free the handle with
+/// `tokenizers_free`.
+///
+/// # Safety
+///
+/// `path` must be a valid nul-terminated UTF-8 string.
+#[no_mangle]
+pub unsafe extern "C" fn tokenizers_from_file(path: *const c_char) -> *mut Tokenizer {
+    clear_last_error();
+    if path.is_null() {
+        set_last_error("path is null");
+        return std::ptr::null_mut();
+    }
+    let path = match CStr::from_ptr(path).to_str() {
+        Ok(path) => path,
+        Err(err) => {
+            set_last_error(err);
+            return std::ptr::null_mut();
+        }
+    };
+    match Tokenizer::from_file(path) {
+        Ok(tokenizer) => Box::into_raw(Box::new(tokenizer)),
+        Err(err) => {
+            set_last_error(err);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Loads a tokenizer from an in-memory `tokenizer.json` buffer. Returns an
+/// owned handle, or null on failure. The buffer can be freed as soon as this
+/// call returns.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn tokenizers_from_buffer(data: *const u8, len: usize) -> *mut Tokenizer {
+    clear_last_error();
+    if data.is_null() {
+        set_last_error("data is null");
+        return std::ptr::null_mut();
+    }
+    let bytes = std::slice::from_raw_parts(data, len);
+    match Tokenizer::from_bytes(bytes) {
+        Ok(tokenizer) => Box::into_raw(Box::new(tokenizer)),
+        Err(err) => {
+            set_last_error(err);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Frees a tokenizer handle. Passing null is a no-op.
+///
+/// # Safety
+///
+/// `tokenizer` must be a handle returned by `tokenizers_from_file` or
+/// `tokenizers_from_buffer`, not freed before, and no other thread may be
+/// using it.
+#[no_mangle]
+pub unsafe extern "C" fn tokenizers_free(tokenizer: *mut Tokenizer) {
+    if !tokenizer.is_null() {
+        drop(Box::from_raw(tokenizer));
+    }
+}
+
+/// Encodes `text` and writes the token ids (and, when `offsets_out` is not
+/// null, the byte offsets as interleaved start/end pairs) into the
+/// caller-provided arrays.
+///
+/// At most `capacity` tokens are written; `*len_out` always receives the
+/// full number of tokens of the encoding, so a first call with a capacity of
+/// zero sizes the arrays for a second call. Returns 0 on success, -1 on
+/// failure (see `tokenizers_last_error`).
+///
+/// # Safety
+///
+/// `text` must be a valid nul-terminated UTF-8 string, `len_out` must be a
+/// valid pointer, `ids_out` must point to `capacity` writable `u32` and,
+/// unless null, `offsets_out` to `2 * capacity` writable `usize`.
+#[no_mangle]
+pub unsafe extern "C" fn tokenizers_encode(
+    tokenizer: *const Tokenizer,
+    text: *const c_char,
+    add_special_tokens: bool,
+    ids_out: *mut u32,
+    offsets_out: *mut usize,
+    capacity: usize,
+    len_out: *mut usize,
+) -> c_int {
+    clear_last_error();
+    if tokenizer.is_null() || text.is_null() || len_out.is_null() {
+        set_last_error("tokenizer, text and len_out must not be null");
+        return -1;
+    }
+    let text = match CStr::from_ptr(text).to_str() {
+        Ok(text) => text,
+        Err(err) => {
+            set_last_error(err);
+            return -1;
+        }
+    };
+    let encoding = match (*tokenizer).encode(text, add_special_tokens) {
+        Ok(encoding) => encoding,
+        Err(err) => {
+            set_last_error(err);
+            return -1;
+        }
+    };
+    let ids = encoding.get_ids();
+    *len_out = ids.len();
+    let written = ids.len().min(capacity);
+    if written > 0 {
+        std::ptr::copy_nonoverlapping(ids.as_ptr(), ids_out, written);
+        if !offsets_out.is_null() {
+            for (i, &(start, end)) in encoding.get_offsets()[..written].iter().enumerate() {
+                *offsets_out.add(2 * i) = start;
+                *offsets_out.add(2 * i + 1) = end;
+            }
+        }
+    }
+    0
+}
+
+/// Decodes a sequence of token ids into a newly allocated nul-terminated
+/// string. Returns null on failure. Free the result with
+/// `tokenizers_string_free`.
+///
+/// # Safety
+///
+/// `ids` must point to `len` readable `u32`.
+#[no_mangle]
+pub unsafe extern "C" fn tokenizers_decode(
+    tokenizer: *const Tokenizer,
+    ids: *const u32,
+    len: usize,
+    skip_special_tokens: bool,
+) -> *mut c_char {
+    clear_last_error();
+    if tokenizer.is_null() || ids.is_null() {
+        set_last_error("tokenizer and ids must not be null");
+        return std::ptr::null_mut();
+    }
+    let ids = std::slice::from_raw_parts(ids, len);
+    match (*tokenizer).decode(ids, skip_special_tokens) {
+        Ok(decoded) => match CString::new(decoded) {
+            Ok(decoded) => decoded.into_raw(),
+            Err(err) => {
+                set_last_error(err);
+                std::ptr::null_mut()
+            }
+        },
+        Err(err) => {
+            set_last_error(err);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Frees a string returned by `tokenizers_decode`. Passing null is a no-op.
+///
+/// # Safety
+///
+/// `s` must be a pointer returned by `tokenizers_decode`, not freed before.
+#[no_mangle]
+pub unsafe extern "C" fn tokenizers_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::wordlevel::WordLevel;
+    use crate::pre_tokenizers::whitespace::WhitespaceSplit;
+    use std::collections::HashMap;
+
+    fn tokenizer_json() -> CString {
+        let vocab: HashMap<String, u32> = [("hello", 0u32), ("world", 1), ("<unk>", 2)]
+            .iter()
+            .map(|(k, v)| (k.to_string(), *v))
+            .collect();
+        let mut tokenizer = Tokenizer::new(
+            WordLevel::builder()
+                .vocab(vocab.into())
+                .unk_token("<unk>".into())
+                .build()
+                .unwrap(),
+        );
+        tokenizer.with_pre_tokenizer(Some(WhitespaceSplit));
+        CString::new(tokenizer.to_string(false).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn ffi_round_trip() {
+        let json = tokenizer_json();
+        let tokenizer =
+            unsafe { tokenizers_from_buffer(json.as_bytes().as_ptr(), json.as_bytes().len()) };
+        assert!(!tokenizer.is_null());
+
+        let text = CString::new("hello world").unwrap();
+        let mut len = 0usize;
+
+        // First call sizes the arrays, second call fills them
+        let status = unsafe {
+            tokenizers_encode(
+                tokenizer,
+                text.as_ptr(),
+                false,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                0,
+                &mut len,
+            )
+        };
+        assert_eq!(status, 0);
+        assert_eq!(len, 2);
+
+        let mut ids = vec![0u32; len];
+        let mut offsets = vec![0usize; 2 * len];
+        let status = unsafe {
+            tokenizers_encode(
+                tokenizer,
+                text.as_ptr(),
+                false,
+                ids.as_mut_ptr(),
+                offsets.as_mut_ptr(),
+                ids.len(),
+                &mut len,
+            )
+        };
+        assert_eq!(status, 0);
+        assert_eq!(ids, vec![0, 1]);
+        assert_eq!(offsets, vec![0, 5, 6, 11]);
+
+        let decoded = unsafe { tokenizers_decode(tokenizer, ids.as_ptr(), ids.len(), true) };
+        assert!(!decoded.is_null());
+        assert_eq!(
+            unsafe { CStr::from_ptr(decoded) }.to_str().unwrap(),
+            "hello world"
+        );
+        unsafe { tokenizers_string_free(decoded) };
+        unsafe { tokenizers_free(tokenizer) };
+    }
+
+    #[test]
+    fn ffi_errors() {
+        let garbage = b"not json";
+        let tokenizer = unsafe { tokenizers_from_buffer(garbage.as_ptr(), garbage.len()) };
+        assert!(tokenizer.is_null());
+        let err = tokenizers_last_error();
+        assert!(!err.is_null());
+
+        let json = tokenizer_json();
+        let tokenizer =
+            unsafe { tokenizers_from_buffer(json.as_bytes().as_ptr(), json.as_bytes().len()) };
+        // A successful call clears the thread-local error
+        assert!(tokenizers_last_error().is_null());
+        unsafe { tokenizers_free(tokenizer) };
+    }
+}
@@ -135,7 +135,12 @@ extern crate derive_builder;
 
 #[macro_use]
 pub mod utils;
+pub mod analysis;
+pub mod augment;
+pub mod compare;
 pub mod decoders;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 pub mod models;
 pub mod normalizers;
 pub mod pre_tokenizers;
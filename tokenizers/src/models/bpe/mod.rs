@@ -33,6 +33,12 @@ pub enum Error {
     /// Dropout not between 0 and 1.
     #[error("Dropout should be between 0 and 1, inclusive")]
     InvalidDropout,
+    /// Digit chunks must hold at least one digit.
+    #[error("digit_chunking should be at least 1")]
+    InvalidDigitChunking,
+    /// A constrained tokenization could not avoid the excluded token
+    #[error("Token `{0}` cannot be segmented using only the allowed subset of the vocabulary")]
+    TokenExcluded(String),
 }
 
 /// Provides access to the `FirstLastIterator` to any Iterator
@@ -1,11 +1,13 @@
 use super::{super::OrderedVocabIter, trainer::BpeTrainer, Error, Pair, Word};
-use crate::tokenizer::{Model, Result, Token};
-use crate::utils::cache::{Cache, DEFAULT_CACHE_CAPACITY};
+use crate::tokenizer::{Model, Result, Token, TokenInfo, VocabMask};
+pub use crate::utils::cache::CacheStats;
+use crate::utils::cache::{Cache, SharedCache, DEFAULT_CACHE_CAPACITY};
 use crate::utils::iter::ResultShunt;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::borrow::Cow;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fs::File,
     io::prelude::*,
     io::{BufRead, BufReader},
@@ -22,13 +24,16 @@ struct Config {
     vocab: Vocab,
     merges: Merges,
     cache_capacity: usize,
+    shared_cache_capacity: usize,
     dropout: Option<f32>,
+    digit_chunking: Option<usize>,
     unk_token: Option<String>,
     continuing_subword_prefix: Option<String>,
     end_of_word_suffix: Option<String>,
     fuse_unk: bool,
     byte_fallback: bool,
     ignore_merges: bool,
+    repair: bool,
 }
 
 /// A `BpeBuilder` can be used to create a `BPE` model with a custom configuration.
@@ -44,13 +49,16 @@ impl Default for BpeBuilder {
                 vocab: HashMap::new(),
                 merges: vec![],
                 cache_capacity: DEFAULT_CACHE_CAPACITY,
+                shared_cache_capacity: 0,
                 dropout: None,
+                digit_chunking: None,
                 unk_token: None,
                 continuing_subword_prefix: None,
                 end_of_word_suffix: None,
                 fuse_unk: false,
                 byte_fallback: false,
                 ignore_merges: false,
+                repair: false,
             },
         }
     }
@@ -84,6 +92,19 @@ impl BpeBuilder {
         self
     }
 
+    /// Enable a cache shared across the clones of the built model, bounded to
+    /// the given number of words, with approximate least-recently-used
+    /// eviction. Unlike the regular cache, which every clone starts afresh, it
+    /// keeps serving a multithreaded server that clones its tokenizer per
+    /// thread, and it replaces the regular cache entirely when enabled. Its
+    /// hit rate can be monitored with [`BPE::shared_cache_stats`]. Set to 0
+    /// (the default) to disable it.
+    #[must_use]
+    pub fn shared_cache_capacity(mut self, capacity: usize) -> Self {
+        self.config.shared_cache_capacity = capacity;
+        self
+    }
+
     /// Use [dropout](https://arxiv.org/abs/1910.13267) with the model.
     #[must_use]
     pub fn dropout(mut self, dropout: f32) -> Self {
@@ -91,6 +112,15 @@ impl BpeBuilder {
         self
     }
 
+    /// Segment runs of digits into fixed-size chunks, from left to right,
+    /// so that merges never cross a chunk boundary and number tokens never
+    /// exceed `digit_chunking` digits.
+    #[must_use]
+    pub fn digit_chunking(mut self, digit_chunking: usize) -> Self {
+        self.config.digit_chunking = Some(digit_chunking);
+        self
+    }
+
     /// Set the `UNK` token for the vocab.
     #[must_use]
     pub fn unk_token(mut self, unk_token: String) -> Self {
@@ -132,6 +162,17 @@ impl BpeBuilder {
         self
     }
 
+    /// Set the `repair` option: when set, merges referencing a symbol absent
+    /// from the vocabulary are dropped (with a warning) instead of failing
+    /// the build, so slightly corrupted legacy files can still be loaded
+    /// through [`BPE::from_file`]. See [`BPE::validate`] for a full check of
+    /// the resulting model.
+    #[must_use]
+    pub fn repair(mut self, repair: bool) -> Self {
+        self.config.repair = repair;
+        self
+    }
+
     /// Returns a `BPE` model that uses the `BpeBuilder`'s configuration.
     pub fn build(mut self) -> Result<BPE> {
         // Validate dropout.
@@ -141,6 +182,11 @@ impl BpeBuilder {
             }
         }
 
+        // Validate digit_chunking.
+        if self.config.digit_chunking == Some(0) {
+            return Err(Error::InvalidDigitChunking.into());
+        }
+
         // Read files if necessary
         if let Some((vocab, merges)) = self.config.files {
             let (v, m) = BPE::read_file(&vocab, &merges)?;
@@ -158,6 +204,10 @@ impl BpeBuilder {
             0 => None,
             capacity => Some(Cache::new(capacity)),
         };
+        let shared_cache = match self.config.shared_cache_capacity {
+            0 => None,
+            capacity => Some(SharedCache::new(capacity)),
+        };
 
         let vocab = self.config.vocab;
         let prefix_len = if let Some(prefix) = &self.config.continuing_subword_prefix {
@@ -165,25 +215,24 @@ impl BpeBuilder {
         } else {
             0
         };
-        let merge_map: MergeMap = self
-            .config
-            .merges
-            .into_iter()
-            .enumerate()
-            .map(|(i, (a, b))| -> Result<(Pair, (u32, u32))> {
-                let a_id = vocab
-                    .get(&a)
-                    .ok_or_else(|| Error::MergeTokenOutOfVocabulary(a.to_owned()))?;
-                let b_id = vocab
-                    .get(&b)
-                    .ok_or_else(|| Error::MergeTokenOutOfVocabulary(b.to_owned()))?;
-                let new_token = format!("{}{}", a, &b[prefix_len..]);
-                let new_id = vocab
-                    .get(&new_token)
-                    .ok_or(Error::MergeTokenOutOfVocabulary(new_token))?;
-                Ok(((*a_id, *b_id), (i as u32, *new_id)))
-            })
-            .collect::<Result<MergeMap>>()?;
+        let mut merge_map = MergeMap::with_capacity(self.config.merges.len());
+        for (i, (a, b)) in self.config.merges.into_iter().enumerate() {
+            let new_token = format!("{}{}", a, &b[prefix_len..]);
+            match (vocab.get(&a), vocab.get(&b), vocab.get(&new_token)) {
+                (Some(a_id), Some(b_id), Some(new_id)) => {
+                    merge_map.insert((*a_id, *b_id), (i as u32, *new_id));
+                }
+                _ if self.config.repair => {
+                    warn!(
+                        "Dropping the merge `{} {}`: it references a token missing from the vocabulary",
+                        a, b
+                    );
+                }
+                (None, _, _) => return Err(Error::MergeTokenOutOfVocabulary(a).into()),
+                (_, None, _) => return Err(Error::MergeTokenOutOfVocabulary(b).into()),
+                _ => return Err(Error::MergeTokenOutOfVocabulary(new_token).into()),
+            }
+        }
 
         // merges.insert(pair, (rank as u32, *new_id));
 
@@ -192,7 +241,9 @@ impl BpeBuilder {
             vocab_r,
             merges: merge_map,
             cache,
+            shared_cache,
             dropout: self.config.dropout,
+            digit_chunking: self.config.digit_chunking,
             unk_token: self.config.unk_token,
             continuing_subword_prefix: self.config.continuing_subword_prefix,
             end_of_word_suffix: self.config.end_of_word_suffix,
@@ -214,9 +265,16 @@ pub struct BPE {
     pub(crate) merges: MergeMap,
     /// Contains the cache for optimizing the encoding step.
     cache: Option<Cache<String, Word>>,
+    /// An optional cache shared across the clones of this model, replacing
+    /// `cache` when enabled. Unlike `cache`, which every clone starts afresh,
+    /// clones keep sharing its storage and its hit rate metrics.
+    shared_cache: Option<SharedCache<String, Word>>,
     /// Dropout probability for merges. 0.0 = no dropout is the default. At 1.0, tokenization will
     /// perform no merges, so the result will just be characters.
     pub dropout: Option<f32>,
+    /// When set, runs of digits are segmented into chunks of this many
+    /// digits, from left to right, and merges never cross a chunk boundary
+    pub digit_chunking: Option<usize>,
     /// The unknown token to be used when we encounter an unknown char
     pub unk_token: Option<String>,
     /// An optional prefix to use on any subword that exist only behind another one
@@ -236,6 +294,7 @@ impl std::fmt::Debug for BPE {
     fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
         fmt.debug_struct("BPE")
             .field("dropout", &self.dropout)
+            .field("digit_chunking", &self.digit_chunking)
             .field("unk_token", &self.unk_token)
             .field("continuing_subword_prefix", &self.continuing_subword_prefix)
             .field("end_of_word_suffix", &self.end_of_word_suffix)
@@ -264,7 +323,10 @@ impl Clone for BPE {
             vocab_r: self.vocab_r.clone(),
             merges: self.merges.clone(),
             cache: fresh_cache,
+            // The shared cache, on the other hand, keeps being shared
+            shared_cache: self.shared_cache.clone(),
             dropout: self.dropout,
+            digit_chunking: self.digit_chunking,
             unk_token: self.unk_token.clone(),
             continuing_subword_prefix: self.continuing_subword_prefix.clone(),
             end_of_word_suffix: self.end_of_word_suffix.clone(),
@@ -275,6 +337,29 @@ impl Clone for BPE {
     }
 }
 
+/// The outcome of [`BPE::validate`] (and what [`BPE::repair`] fixed): the
+/// inconsistencies found in the merges and the vocabulary of a model
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ValidationReport {
+    /// Merges referencing an id the vocabulary does not contain, rendered as
+    /// `"a b -> ab"` with `<id N>` placeholders for the missing tokens
+    pub invalid_merges: Vec<String>,
+    /// Ranks shared by more than one merge
+    pub duplicate_ranks: Vec<u32>,
+    /// Multi-character tokens that no merge produces: they stay in the
+    /// vocabulary but tokenization can never output them
+    pub unreachable_tokens: Vec<String>,
+}
+
+impl ValidationReport {
+    /// Whether tokenization behaves deterministically: no merge references a
+    /// missing id and no rank is ambiguous. Unreachable tokens do not affect
+    /// tokenization, so they do not count against consistency.
+    pub fn is_consistent(&self) -> bool {
+        self.invalid_merges.is_empty() && self.duplicate_ranks.is_empty()
+    }
+}
+
 /// Converts the merges strings (for example from `merges.txt` file) with the format
 /// "{pair_a} {pair_b}" into the format expected by the BPE struct
 pub(crate) fn convert_merges_to_hashmap<I: Iterator<Item = String>>(
@@ -315,6 +400,76 @@ impl BPE {
         Self::builder().files(vocab.to_owned(), merges.to_owned())
     }
 
+    /// Create a `BPE` model from a `WordPiece` model, deriving the merges
+    /// from its vocabulary so that legacy BERT vocabularies can be used by
+    /// systems that only apply BPE-style merges.
+    ///
+    /// Each multi-character token is registered as the merge of its longest
+    /// splittable left side with the matching continuation, approximating the
+    /// greedy longest-match-first behaviour of WordPiece. Also returns the
+    /// vocabulary tokens that no sequence of merges can produce (typically
+    /// the special tokens like `[CLS]`): they keep their ids, but have to be
+    /// registered as added tokens to be extracted from the input.
+    pub fn from_wordpiece(
+        wordpiece: &crate::models::wordpiece::WordPiece,
+    ) -> Result<(BPE, Vec<String>)> {
+        let vocab = wordpiece.get_vocab();
+        let prefix = &wordpiece.continuing_subword_prefix;
+
+        // The core of a token: its text without the continuation prefix
+        let split_prefix = |token: &str| -> (bool, String) {
+            match token.strip_prefix(prefix.as_str()) {
+                Some(stripped) if !prefix.is_empty() && !stripped.is_empty() => {
+                    (true, stripped.to_owned())
+                }
+                _ => (false, token.to_owned()),
+            }
+        };
+
+        // Order the tokens by the length of their core then by id, so that
+        // both sides of any candidate split are processed before the token
+        // they form
+        let mut tokens: Vec<(&String, u32)> = vocab.iter().map(|(t, id)| (t, *id)).collect();
+        tokens.sort_by_key(|(token, id)| (split_prefix(token).1.chars().count(), *id));
+
+        let mut derivable: HashSet<String> = HashSet::new();
+        let mut merges: Merges = vec![];
+        let mut unrepresentable: Vec<String> = vec![];
+        for (token, _) in tokens {
+            let (is_continuation, core) = split_prefix(token);
+            if core.chars().count() <= 1 {
+                derivable.insert(token.clone());
+                continue;
+            }
+            let boundaries: Vec<usize> = core.char_indices().skip(1).map(|(i, _)| i).collect();
+            let split = boundaries.into_iter().rev().find_map(|i| {
+                let left = if is_continuation {
+                    format!("{}{}", prefix, &core[..i])
+                } else {
+                    core[..i].to_owned()
+                };
+                let right = format!("{}{}", prefix, &core[i..]);
+                (derivable.contains(&left) && derivable.contains(&right)).then_some((left, right))
+            });
+            match split {
+                Some(pair) => {
+                    merges.push(pair);
+                    derivable.insert(token.clone());
+                }
+                None => unrepresentable.push(token.clone()),
+            }
+        }
+        unrepresentable.sort();
+
+        let mut builder = Self::builder()
+            .vocab_and_merges(vocab, merges)
+            .unk_token(wordpiece.unk_token.clone());
+        if !prefix.is_empty() {
+            builder = builder.continuing_subword_prefix(prefix.clone());
+        }
+        Ok((builder.build()?, unrepresentable))
+    }
+
     /// Read the given files to extract the vocab and merges
     pub fn read_file(vocab: &str, merges: &str) -> Result<(Vocab, Merges)> {
         // Read vocab.json
@@ -352,12 +507,138 @@ impl BPE {
         if let Some(ref cache) = self.cache {
             cache.clear()
         }
+        if let Some(ref cache) = self.shared_cache {
+            cache.clear()
+        }
+    }
+
+    /// Check the internal consistency of the model: merges referencing an id
+    /// the vocabulary does not contain, ranks shared by several merges (which
+    /// make the merge order ambiguous), and multi-character tokens that no
+    /// merge produces (which tokenization can therefore never output). The
+    /// first two classes make tokenization misbehave and can be fixed with
+    /// [`BPE::repair`]; unreachable tokens are only reported, as removing
+    /// them is a pruning decision.
+    pub fn validate(&self) -> ValidationReport {
+        let name = |id: &u32| {
+            self.vocab_r
+                .get(id)
+                .cloned()
+                .unwrap_or_else(|| format!("<id {}>", id))
+        };
+        let mut invalid_merges = vec![];
+        let mut rank_counts: HashMap<u32, usize> = HashMap::new();
+        for ((a, b), (rank, new_id)) in &self.merges {
+            *rank_counts.entry(*rank).or_insert(0) += 1;
+            if !self.vocab_r.contains_key(a)
+                || !self.vocab_r.contains_key(b)
+                || !self.vocab_r.contains_key(new_id)
+            {
+                invalid_merges.push(format!("{} {} -> {}", name(a), name(b), name(new_id)));
+            }
+        }
+        invalid_merges.sort_unstable();
+        let mut duplicate_ranks: Vec<u32> = rank_counts
+            .into_iter()
+            .filter(|(_, count)| *count > 1)
+            .map(|(rank, _)| rank)
+            .collect();
+        duplicate_ranks.sort_unstable();
+
+        let mut unreachable_tokens = vec![];
+        // With `ignore_merges`, any vocabulary token matching a whole word is
+        // output directly, so every token is reachable
+        if !self.ignore_merges {
+            let produced: HashSet<u32> = self.merges.values().map(|(_, new_id)| *new_id).collect();
+            let prefix = self.continuing_subword_prefix.as_deref().unwrap_or("");
+            let suffix = self.end_of_word_suffix.as_deref().unwrap_or("");
+            for (token, id) in &self.vocab {
+                let core = token.strip_prefix(prefix).unwrap_or(token);
+                let core = core.strip_suffix(suffix).unwrap_or(core);
+                if produced.contains(id)
+                    || core.chars().count() <= 1
+                    || self.unk_token.as_ref() == Some(token)
+                    || (self.byte_fallback && token.starts_with("<0x") && token.ends_with('>'))
+                {
+                    continue;
+                }
+                unreachable_tokens.push(token.clone());
+            }
+            unreachable_tokens.sort_unstable();
+        }
+
+        ValidationReport {
+            invalid_merges,
+            duplicate_ranks,
+            unreachable_tokens,
+        }
+    }
+
+    /// Bring the model back to a consistent state, returning what
+    /// [`BPE::validate`] found: merges referencing a missing id are dropped,
+    /// and when several merges share a rank, all the merges are re-ranked
+    /// consecutively in their current order (ties broken by produced id, for
+    /// determinism). Unreachable tokens are reported but kept, so ids do not
+    /// shift. The caches are cleared whenever the merges changed.
+    pub fn repair(&mut self) -> ValidationReport {
+        let report = self.validate();
+        if !report.invalid_merges.is_empty() {
+            let vocab_r = &self.vocab_r;
+            self.merges.retain(|(a, b), (_, new_id)| {
+                vocab_r.contains_key(a) && vocab_r.contains_key(b) && vocab_r.contains_key(new_id)
+            });
+        }
+        if !report.duplicate_ranks.is_empty() {
+            let mut entries: Vec<(Pair, (u32, u32))> = self.merges.drain().collect();
+            entries.sort_by_key(|(_, (rank, new_id))| (*rank, *new_id));
+            self.merges = entries
+                .into_iter()
+                .enumerate()
+                .map(|(i, (pair, (_, new_id)))| (pair, (i as u32, new_id)))
+                .collect();
+        }
+        if !report.invalid_merges.is_empty() || !report.duplicate_ranks.is_empty() {
+            self.clear_cache();
+        }
+        report
+    }
+
+    /// The lookup statistics of the shared cache, aggregated over every clone
+    /// of this model, or `None` when it is not enabled
+    pub fn shared_cache_stats(&self) -> Option<CacheStats> {
+        self.shared_cache.as_ref().map(|cache| cache.stats())
     }
 
     pub fn get_vocab(&self) -> Vocab {
         self.vocab.clone()
     }
 
+    /// Add `token` to the vocabulary, returning its newly assigned id, or
+    /// the id it already has. The caches are cleared, as the new token can
+    /// change how already seen words split.
+    pub fn add_token(&mut self, token: &str) -> u32 {
+        if let Some(id) = self.vocab.get(token) {
+            return *id;
+        }
+        let id = self.vocab_r.keys().max().map_or(0, |max| max + 1);
+        self.vocab.insert(token.to_owned(), id);
+        self.vocab_r.insert(id, token.to_owned());
+        self.clear_cache();
+        id
+    }
+
+    /// Remove the token with the given id from the vocabulary, returning it.
+    /// The merges using or producing the token are dropped along with it,
+    /// and the caches are cleared.
+    pub fn remove_token(&mut self, id: u32) -> Option<String> {
+        let token = self.vocab_r.remove(&id)?;
+        self.vocab.remove(&token);
+        self.merges
+            .retain(|(a, b), (_, new_id)| *a != id && *b != id && *new_id != id);
+        self.clear_cache();
+        Some(token)
+    }
+
     pub fn get_unk_token(&self) -> &Option<String> {
         &self.unk_token
     }
@@ -366,7 +647,8 @@ impl BPE {
         &self.continuing_subword_prefix
     }
 
-    fn merge_word(&self, w: &str) -> Result<Word> {
+    /// Split `w` into its initial in-vocabulary symbols, before any merge
+    fn split_word(&self, w: &str) -> Result<Word> {
         let mut indices = w.char_indices().map(|(idx, _)| idx).peekable();
         let mut word = Word::with_capacity(w.len());
         let mut unk: Option<(u32, usize)> = None;
@@ -448,8 +730,37 @@ impl BPE {
             word.add(unk_id, unk_len);
         }
 
-        word.merge_all(&self.merges, self.dropout);
+        Ok(word)
+    }
+
+    /// The byte offsets in `w` where a new digit chunk starts, i.e. every
+    /// `chunk`-th digit of each digit run, counted from the start of the run
+    fn digit_boundaries(w: &str, chunk: usize) -> Vec<usize> {
+        let mut boundaries = vec![];
+        let mut run = 0;
+        for (offset, c) in w.char_indices() {
+            if c.is_numeric() {
+                if run > 0 && run % chunk == 0 {
+                    boundaries.push(offset);
+                }
+                run += 1;
+            } else {
+                run = 0;
+            }
+        }
+        boundaries
+    }
 
+    fn merge_word(&self, w: &str) -> Result<Word> {
+        let mut word = self.split_word(w)?;
+        match self.digit_chunking {
+            Some(chunk) => word.merge_all_within(
+                &self.merges,
+                self.dropout,
+                &Self::digit_boundaries(w, chunk),
+            ),
+            None => word.merge_all(&self.merges, self.dropout),
+        }
         Ok(word)
     }
 
@@ -465,16 +776,67 @@ impl BPE {
                 return Ok(vec![Token::new(*id, sequence.to_string().clone(), (0, 0))]);
             }
         }
-        if let Some(ref hit) = self.cache.as_ref().and_then(|c| c.get(sequence)) {
+        if let Some(ref shared_cache) = self.shared_cache {
+            if let Some(ref hit) = shared_cache.get(sequence) {
+                return Ok(self.word_to_tokens(hit).collect());
+            }
+        } else if let Some(ref hit) = self.cache.as_ref().and_then(|c| c.get(sequence)) {
             return Ok(self.word_to_tokens(hit).collect());
         }
         let word = self.merge_word(sequence)?;
         let ret = self.word_to_tokens(&word).collect();
-        if let Some(ref cache) = self.cache {
+        if let Some(ref cache) = self.shared_cache {
+            cache.set(sequence.to_owned(), word);
+        } else if let Some(ref cache) = self.cache {
             cache.set(sequence.to_owned(), word);
         }
         Ok(ret)
     }
+
+    /// Tokenize `word` like [`Model::tokenize`] does, additionally returning
+    /// the sequence of merges that was applied, in order, so that the
+    /// segmentation of the word can be displayed step by step
+    pub fn tokenize_with_trace(&self, word: &str) -> Result<(Vec<Token>, Vec<MergeStep>)> {
+        if word.is_empty() {
+            return Ok((vec![], vec![]));
+        }
+        if self.ignore_merges {
+            if let Some(id) = self.vocab.get(word) {
+                return Ok((vec![Token::new(*id, word.to_string(), (0, 0))], vec![]));
+            }
+        }
+        let mut trace = vec![];
+        let boundaries = match self.digit_chunking {
+            Some(chunk) => Self::digit_boundaries(word, chunk),
+            None => vec![],
+        };
+        let mut w = self.split_word(word)?;
+        w.merge_all_within_with(
+            &self.merges,
+            self.dropout,
+            &boundaries,
+            |pair, rank, new_id| {
+                trace.push(MergeStep {
+                    pair: (self.vocab_r[&pair.0].clone(), self.vocab_r[&pair.1].clone()),
+                    rank,
+                    new_token: self.vocab_r[&new_id].clone(),
+                });
+            },
+        );
+        Ok((self.word_to_tokens(&w).collect(), trace))
+    }
+}
+
+/// One merge applied while tokenizing a word, as returned by
+/// [`BPE::tokenize_with_trace`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeStep {
+    /// The pair of tokens that was merged
+    pub pair: (String, String),
+    /// The rank of the merge, lower ranks being applied first
+    pub rank: u32,
+    /// The token resulting from the merge
+    pub new_token: String,
 }
 
 impl Model for BPE {
@@ -484,6 +846,34 @@ impl Model for BPE {
         self.vocab.clone()
     }
 
+    fn get_vocab_iter(&self) -> Box<dyn Iterator<Item = (Cow<'_, str>, u32)> + '_> {
+        let mut entries: Vec<_> = self
+            .vocab
+            .iter()
+            .map(|(token, id)| (token.as_str(), *id))
+            .collect();
+        entries.sort_unstable_by_key(|(_, id)| *id);
+        Box::new(
+            entries
+                .into_iter()
+                .map(|(token, id)| (Cow::Borrowed(token), id)),
+        )
+    }
+
+    fn get_vocab_r(&self) -> Box<dyn Iterator<Item = (u32, Cow<'_, str>)> + '_> {
+        let mut entries: Vec<_> = self
+            .vocab_r
+            .iter()
+            .map(|(id, token)| (*id, token.as_str()))
+            .collect();
+        entries.sort_unstable_by_key(|(id, _)| *id);
+        Box::new(
+            entries
+                .into_iter()
+                .map(|(id, token)| (id, Cow::Borrowed(token))),
+        )
+    }
+
     fn get_vocab_size(&self) -> usize {
         self.vocab.len()
     }
@@ -501,6 +891,29 @@ impl Model for BPE {
         }
     }
 
+    fn tokenize_constrained(&self, sequence: &str, allowed: &VocabMask) -> Result<Vec<Token>> {
+        if sequence.is_empty() {
+            return Ok(vec![]);
+        }
+        // The segmentation depends on the mask, so the word caches are
+        // bypassed entirely
+        let mut word = self.split_word(sequence)?;
+        let boundaries = match self.digit_chunking {
+            Some(chunk) => Self::digit_boundaries(sequence, chunk),
+            None => vec![],
+        };
+        word.merge_all_within_when(&self.merges, self.dropout, &boundaries, |id| {
+            allowed.contains(id)
+        });
+        let tokens: Vec<Token> = self.word_to_tokens(&word).collect();
+        // The base symbols themselves may be excluded, in which case no
+        // segmentation can satisfy the mask
+        if let Some(token) = tokens.iter().find(|token| !allowed.contains(token.id)) {
+            return Err(Error::TokenExcluded(token.value.clone()).into());
+        }
+        Ok(tokens)
+    }
+
     fn token_to_id(&self, token: &str) -> Option<u32> {
         self.vocab.get(token).copied()
     }
@@ -556,6 +969,64 @@ impl Model for BPE {
     fn get_trainer(&self) -> BpeTrainer {
         BpeTrainer::default()
     }
+
+    fn token_info(&self, id: u32) -> Option<TokenInfo> {
+        self.vocab_r.get(&id)?;
+        let rank = self
+            .merges
+            .values()
+            .find(|(_, new_id)| *new_id == id)
+            .map(|(rank, _)| *rank);
+        Some(TokenInfo {
+            rank,
+            ..TokenInfo::default()
+        })
+    }
+
+    fn token_scores(&self, ids: &[u32]) -> Option<Vec<Option<f64>>> {
+        // The score of a token is the sum of the ranks of all the merges
+        // needed to build it from the base alphabet, 0 for alphabet tokens:
+        // lower sums mean earlier, more frequent merges
+        let produced_by: HashMap<u32, (u32, Pair)> = self
+            .merges
+            .iter()
+            .map(|(pair, (rank, new_id))| (*new_id, (*rank, *pair)))
+            .collect();
+        fn rank_sum(
+            id: u32,
+            produced_by: &HashMap<u32, (u32, Pair)>,
+            cache: &mut HashMap<u32, u64>,
+        ) -> u64 {
+            if let Some(sum) = cache.get(&id) {
+                return *sum;
+            }
+            let sum = produced_by
+                .get(&id)
+                .copied()
+                .map(|(rank, pair)| {
+                    rank as u64
+                        + rank_sum(pair.0, produced_by, cache)
+                        + rank_sum(pair.1, produced_by, cache)
+                })
+                .unwrap_or(0);
+            cache.insert(id, sum);
+            sum
+        }
+        let mut cache = HashMap::new();
+        Some(
+            ids.iter()
+                .map(|id| {
+                    self.vocab_r
+                        .contains_key(id)
+                        .then(|| rank_sum(*id, &produced_by, &mut cache) as f64)
+                })
+                .collect(),
+        )
+    }
+
+    fn unk_token(&self) -> Option<String> {
+        self.unk_token.clone()
+    }
 }
 
 #[cfg(test)]
@@ -579,6 +1050,263 @@ mod tests {
         assert_eq!(serialized, "{\"a\":0,\"b\":1,\"c\":2,\"ab\":3}");
     }
 
+    #[test]
+    fn test_validate_and_repair() {
+        let vocab: Vocab = [
+            ("a".into(), 0),
+            ("b".into(), 1),
+            ("c".into(), 2),
+            ("ab".into(), 3),
+            ("zzz".into(), 4),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let merges = vec![("a".to_string(), "b".to_string())];
+        let mut bpe = BPE::new(vocab, merges);
+
+        // The freshly built model is consistent, but nothing produces "zzz"
+        let report = bpe.validate();
+        assert!(report.is_consistent());
+        assert_eq!(report.unreachable_tokens, ["zzz"]);
+
+        // Corrupt the merges: one referencing a missing id, one reusing an
+        // existing rank
+        bpe.merges.insert((0, 99), (7, 99));
+        bpe.merges.insert((1, 2), (0, 4));
+        let report = bpe.validate();
+        assert_eq!(report.invalid_merges, ["a <id 99> -> <id 99>"]);
+        assert_eq!(report.duplicate_ranks, [0]);
+        assert!(!report.is_consistent());
+
+        let fixed = bpe.repair();
+        assert_eq!(fixed, report);
+        assert!(bpe.validate().is_consistent());
+        assert_eq!(bpe.merges.len(), 2);
+        // The re-ranked merges keep their order, and tokenization still works
+        assert_eq!(bpe.merges[&(0, 1)], (0, 3));
+        assert_eq!(bpe.merges[&(1, 2)], (1, 4));
+        let tokens = bpe.tokenize("ab").unwrap();
+        assert_eq!(tokens[0].value, "ab");
+    }
+
+    #[test]
+    fn test_repair_on_build() {
+        let vocab: Vocab = [("a".into(), 0), ("b".into(), 1), ("ab".into(), 2)]
+            .iter()
+            .cloned()
+            .collect();
+        let merges = vec![
+            ("a".to_string(), "b".to_string()),
+            ("a".to_string(), "missing".to_string()),
+        ];
+
+        // The default build refuses the dangling merge, the repairing one
+        // drops it
+        assert!(BPE::builder()
+            .vocab_and_merges(vocab.clone(), merges.clone())
+            .build()
+            .is_err());
+        let bpe = BPE::builder()
+            .vocab_and_merges(vocab, merges)
+            .repair(true)
+            .build()
+            .unwrap();
+        assert_eq!(bpe.merges.len(), 1);
+        assert!(bpe.validate().is_consistent());
+    }
+
+    #[test]
+    fn test_from_wordpiece() {
+        use crate::models::wordpiece::WordPiece;
+
+        let vocab: Vocab = [
+            ("[UNK]".into(), 0),
+            ("[CLS]".into(), 1),
+            ("h".into(), 2),
+            ("##e".into(), 3),
+            ("##l".into(), 4),
+            ("##o".into(), 5),
+            ("##ll".into(), 6),
+            ("##llo".into(), 7),
+            ("he".into(), 8),
+            ("hello".into(), 9),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let wordpiece = WordPiece::builder()
+            .vocab(vocab.clone().into())
+            .unk_token("[UNK]".into())
+            .build()
+            .unwrap();
+
+        let (bpe, unrepresentable) = BPE::from_wordpiece(&wordpiece).unwrap();
+
+        // The special tokens cannot be derived from any merge sequence
+        assert_eq!(unrepresentable, vec!["[CLS]".to_string(), "[UNK]".into()]);
+
+        // The vocabulary and ids are preserved, and the derived merges
+        // rebuild the multi-character tokens
+        assert_eq!(bpe.get_vocab(), vocab);
+        let tokens = bpe.tokenize("hello").unwrap();
+        assert_eq!(tokens, vec![Token::new(9u32, "hello".into(), (0, 5))]);
+        let tokens = bpe.tokenize("hole").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::new(2u32, "h".into(), (0, 1)),
+                Token::new(5u32, "##o".into(), (1, 2)),
+                Token::new(4u32, "##l".into(), (2, 3)),
+                Token::new(3u32, "##e".into(), (3, 4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_token_info() {
+        let vocab: Vocab = [("a".into(), 0), ("b".into(), 1), ("ab".into(), 2)]
+            .iter()
+            .cloned()
+            .collect();
+        let bpe = BpeBuilder::default()
+            .vocab_and_merges(vocab, vec![("a".to_string(), "b".to_string())])
+            .build()
+            .unwrap();
+        // Base alphabet tokens have no merge rank
+        assert_eq!(bpe.token_info(0), Some(TokenInfo::default()));
+        // Merged tokens report the rank of the merge producing them
+        assert_eq!(
+            bpe.token_info(2),
+            Some(TokenInfo {
+                rank: Some(0),
+                ..TokenInfo::default()
+            })
+        );
+        assert_eq!(bpe.token_info(42), None);
+    }
+
+    #[test]
+    fn test_tokenize_with_trace() {
+        let vocab: Vocab = [
+            ("a".into(), 0),
+            ("b".into(), 1),
+            ("c".into(), 2),
+            ("ab".into(), 3),
+            ("abc".into(), 4),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let bpe = BpeBuilder::default()
+            .vocab_and_merges(
+                vocab,
+                vec![
+                    ("a".to_string(), "b".to_string()),
+                    ("ab".to_string(), "c".to_string()),
+                ],
+            )
+            .build()
+            .unwrap();
+
+        // The trace lists the merges in the order they were applied
+        let (tokens, trace) = bpe.tokenize_with_trace("abc").unwrap();
+        assert_eq!(
+            tokens.into_iter().map(|t| t.value).collect::<Vec<_>>(),
+            vec!["abc"]
+        );
+        assert_eq!(
+            trace,
+            vec![
+                MergeStep {
+                    pair: ("a".into(), "b".into()),
+                    rank: 0,
+                    new_token: "ab".into(),
+                },
+                MergeStep {
+                    pair: ("ab".into(), "c".into()),
+                    rank: 1,
+                    new_token: "abc".into(),
+                },
+            ]
+        );
+
+        // A word segmented without any merge has an empty trace
+        let (tokens, trace) = bpe.tokenize_with_trace("cb").unwrap();
+        assert_eq!(
+            tokens.into_iter().map(|t| t.value).collect::<Vec<_>>(),
+            vec!["c", "b"]
+        );
+        assert!(trace.is_empty());
+    }
+
+    #[test]
+    fn test_save_deterministic_order() {
+        // `save` must not depend on the vocab HashMap iteration order:
+        // vocab.json is sorted by id and merges.txt by merge rank, so that
+        // re-saving a model never pollutes git diffs
+        let vocab: Vocab = [
+            ("<unk>".into(), 0),
+            ("a".into(), 1),
+            ("b".into(), 2),
+            ("ab".into(), 3),
+            ("abb".into(), 4),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let bpe = BpeBuilder::default()
+            .vocab_and_merges(
+                vocab,
+                vec![
+                    ("a".to_string(), "b".to_string()),
+                    ("ab".to_string(), "b".to_string()),
+                ],
+            )
+            .build()
+            .unwrap();
+
+        let folder = tempfile::tempdir().unwrap();
+        bpe.save(folder.path(), None).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(folder.path().join("vocab.json")).unwrap(),
+            r#"{"<unk>":0,"a":1,"b":2,"ab":3,"abb":4}"#
+        );
+        assert_eq!(
+            std::fs::read_to_string(folder.path().join("merges.txt")).unwrap(),
+            "#version: 0.2\na b\nab b\n"
+        );
+    }
+
+    #[test]
+    fn test_token_scores() {
+        let vocab: Vocab = [
+            ("a".into(), 0),
+            ("b".into(), 1),
+            ("ab".into(), 2),
+            ("abab".into(), 3),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let bpe = BpeBuilder::default()
+            .vocab_and_merges(
+                vocab,
+                vec![
+                    ("a".to_string(), "b".to_string()),
+                    ("ab".to_string(), "ab".to_string()),
+                ],
+            )
+            .build()
+            .unwrap();
+        // Alphabet tokens sum to 0, merged tokens to the ranks of all the
+        // merges building them; unknown ids are not scored
+        assert_eq!(
+            bpe.token_scores(&[0, 2, 3, 42]),
+            Some(vec![Some(0.0), Some(0.0), Some(1.0), None])
+        );
+    }
+
     #[test]
     fn test_unk_not_fused() {
         let vocab: Vocab = [("<unk>".into(), 0), ("a".into(), 1), ("b".into(), 2)]
@@ -714,6 +1442,55 @@ mod tests {
         assert!(!tokens.is_empty() && tokens.len() <= 9);
     }
 
+    #[test]
+    fn test_tokenize_constrained() {
+        let vocab: Vocab = [
+            ("a".into(), 0),
+            ("b".into(), 1),
+            ("c".into(), 2),
+            ("ab".into(), 3),
+            ("abc".into(), 4),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let merges = vec![
+            ("a".to_string(), "b".to_string()),
+            ("ab".to_string(), "c".to_string()),
+        ];
+        let bpe = BPE::new(vocab, merges);
+
+        assert_eq!(
+            bpe.tokenize("abc").unwrap(),
+            vec![Token::new(4u32, "abc".into(), (0, 3))]
+        );
+
+        // Excluding `abc` stops the merges at `ab` + `c`
+        let allowed: VocabMask = [0u32, 1, 2, 3].iter().copied().collect();
+        assert_eq!(
+            bpe.tokenize_constrained("abc", &allowed).unwrap(),
+            vec![
+                Token::new(3u32, "ab".into(), (0, 2)),
+                Token::new(2u32, "c".into(), (2, 3)),
+            ]
+        );
+
+        // Excluding `ab` as well leaves the base characters
+        let allowed: VocabMask = [0u32, 1, 2].iter().copied().collect();
+        assert_eq!(
+            bpe.tokenize_constrained("abc", &allowed).unwrap(),
+            vec![
+                Token::new(0u32, "a".into(), (0, 1)),
+                Token::new(1u32, "b".into(), (1, 2)),
+                Token::new(2u32, "c".into(), (2, 3)),
+            ]
+        );
+
+        // An excluded base character cannot be worked around
+        let allowed: VocabMask = [0u32, 1, 3].iter().copied().collect();
+        assert!(bpe.tokenize_constrained("abc", &allowed).is_err());
+    }
+
     #[test]
     // Ensure `BPE::from_file` works as expected.
     fn test_bpe_from_file() {
@@ -751,6 +1528,91 @@ mod tests {
         assert_eq!(bpe.dropout, Some(0.0));
     }
 
+    #[test]
+    fn test_digit_chunking() {
+        let vocab: Vocab = vec![
+            ("1".to_string(), 0),
+            ("2".to_string(), 1),
+            ("3".to_string(), 2),
+            ("4".to_string(), 3),
+            ("a".to_string(), 4),
+            ("12".to_string(), 5),
+            ("123".to_string(), 6),
+            ("1234".to_string(), 7),
+        ]
+        .into_iter()
+        .collect();
+        let merges = vec![
+            ("1".to_string(), "2".to_string()),
+            ("12".to_string(), "3".to_string()),
+            ("123".to_string(), "4".to_string()),
+        ];
+
+        // Without chunking, the whole run merges
+        let bpe = BPE::new(vocab.clone(), merges.clone());
+        let tokens = bpe.tokenize("1234").unwrap();
+        assert_eq!(tokens, vec![Token::new(7u32, "1234".into(), (0, 4))]);
+
+        // With chunks of 3 digits, merges stop at the chunk boundary
+        let bpe = BPE::builder()
+            .vocab_and_merges(vocab, merges)
+            .digit_chunking(3)
+            .build()
+            .unwrap();
+        let tokens = bpe.tokenize("1234").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::new(6u32, "123".into(), (0, 3)),
+                Token::new(3u32, "4".into(), (3, 4)),
+            ]
+        );
+
+        // A non-digit resets the run
+        let tokens = bpe.tokenize("12a1234").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::new(5u32, "12".into(), (0, 2)),
+                Token::new(4u32, "a".into(), (2, 3)),
+                Token::new(6u32, "123".into(), (3, 6)),
+                Token::new(3u32, "4".into(), (6, 7)),
+            ]
+        );
+
+        // Empty chunks are rejected
+        assert!(BPE::builder().digit_chunking(0).build().is_err());
+    }
+
+    #[test]
+    fn test_add_remove_token() {
+        let vocab: Vocab = vec![
+            ("a".to_string(), 0),
+            ("b".to_string(), 1),
+            ("ab".to_string(), 2),
+        ]
+        .into_iter()
+        .collect();
+        let merges = vec![("a".to_string(), "b".to_string())];
+        let mut bpe = BPE::new(vocab, merges);
+
+        // Warm the cache
+        assert_eq!(bpe.tokenize("ab").unwrap()[0].id, 2);
+
+        // Removing a token drops the merges producing it and clears the cache
+        assert_eq!(bpe.remove_token(2), Some("ab".to_string()));
+        let tokens = bpe.tokenize("ab").unwrap();
+        let values: Vec<_> = tokens.iter().map(|t| t.value.as_str()).collect();
+        assert_eq!(values, ["a", "b"]);
+        assert!(bpe.merges.is_empty());
+
+        // A new token gets the next free id
+        assert_eq!(bpe.add_token("c"), 2);
+        assert_eq!(bpe.token_to_id("c"), Some(2));
+        // While an existing one keeps its id
+        assert_eq!(bpe.add_token("a"), 0);
+    }
+
     #[test]
     // Ensure `BPE::from_file` works as expected.
     fn test_bpe_with_continuing_subword_prefix() {
@@ -984,4 +1846,29 @@ mod tests {
             ]
         )
     }
+
+    #[test]
+    fn test_shared_cache() {
+        let vocab: Vocab = [("a".into(), 0), ("b".into(), 1), ("ab".into(), 2)]
+            .iter()
+            .cloned()
+            .collect();
+        let bpe = BpeBuilder::default()
+            .vocab_and_merges(vocab, vec![("a".to_string(), "b".to_string())])
+            .shared_cache_capacity(100)
+            .build()
+            .unwrap();
+        let clone = bpe.clone();
+
+        let tokens = bpe.tokenize("ab").unwrap();
+        assert_eq!(tokens, vec![Token::new(2u32, "ab".into(), (0, 2))]);
+        // The clone is served from the shared cache the original warmed up
+        assert_eq!(clone.tokenize("ab").unwrap(), tokens);
+        let stats = clone.shared_cache_stats().unwrap();
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+        assert_eq!(stats.hit_rate(), 0.5);
+
+        // Without a shared cache, there are no statistics
+        assert!(BPE::default().shared_cache_stats().is_none());
+    }
 }
@@ -22,6 +22,11 @@ impl Serialize for BPE {
         model.serialize_field("fuse_unk", &self.fuse_unk)?;
         model.serialize_field("byte_fallback", &self.byte_fallback)?;
         model.serialize_field("ignore_merges", &self.ignore_merges)?;
+        // Only written when set, to keep older consumers able to read the
+        // common case
+        if let Some(digit_chunking) = self.digit_chunking {
+            model.serialize_field("digit_chunking", &digit_chunking)?;
+        }
 
         // Then the large ones
         let mut merges: Vec<(&Pair, &u32)> = self
@@ -59,6 +64,7 @@ impl<'de> Deserialize<'de> for BPE {
                 "fuse_unk",
                 "byte_fallback",
                 "ignore_merges",
+                "digit_chunking",
                 "vocab",
                 "merges",
             ],
@@ -126,6 +132,11 @@ impl<'de> Visitor<'de> for BPEVisitor {
                         builder = builder.ignore_merges(suffix);
                     }
                 }
+                "digit_chunking" => {
+                    if let Some(chunk) = map.next_value()? {
+                        builder = builder.digit_chunking(chunk);
+                    }
+                }
                 "vocab" => vocab = Some(map.next_value()?),
                 "merges" => merges = Some(map.next_value()?),
                 "type" => match map.next_value()? {
@@ -235,4 +246,23 @@ mod test {
         let bpe_string = r#"{"type":"BPE","dropout":null,"unk_token":"<unk>","continuing_subword_prefix":null,"end_of_word_suffix":null,"fuse_unk":false,"byte_fallback":false,"vocab":{"<unk>":0,"a":1,"b":2},"merges":[]}"#;
         assert_eq!(serde_json::from_str::<BPE>(bpe_string).unwrap(), bpe);
     }
+
+    #[test]
+    fn test_serialization_digit_chunking() {
+        let vocab: Vocab = [("a".into(), 0), ("1".into(), 1)].iter().cloned().collect();
+        let bpe = BpeBuilder::default()
+            .vocab_and_merges(vocab, vec![])
+            .digit_chunking(3)
+            .build()
+            .unwrap();
+
+        // The field is only serialized when set
+        let data = serde_json::to_string(&bpe).unwrap();
+        assert!(data.contains(r#""digit_chunking":3"#));
+        let reconstructed: BPE = serde_json::from_str(&data).unwrap();
+        assert_eq!(reconstructed, bpe);
+        assert!(!serde_json::to_string(&BPE::default())
+            .unwrap()
+            .contains("digit_chunking"));
+    }
 }
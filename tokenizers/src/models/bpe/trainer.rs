@@ -2,11 +2,39 @@
 
 use super::{Pair, WithFirstLastIterator, Word, BPE};
 use crate::parallelism::*;
-use crate::tokenizer::{AddedToken, Result, Trainer};
+use crate::pre_tokenizers::byte_level::bytes_char;
+use crate::tokenizer::{AddedToken, Model, Result, Trainer, TrainingReport};
 use crate::utils::progress::{ProgressBar, ProgressStyle};
+use crate::utils::sketch::SpaceSavingCounter;
+use crate::utils::spill::SpillingWordCounter;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::path::Path;
+
+lazy_static! {
+    static ref BYTES_CHAR: HashMap<u8, char> = bytes_char();
+    /// The byte-level mappings of the whitespace bytes, e.g. `Ġ` for the space
+    static ref WHITESPACE_BYTE_CHARS: HashSet<char> = bytes_char()
+        .into_iter()
+        .filter(|(b, _)| (*b as char).is_whitespace())
+        .map(|(_, c)| c)
+        .collect();
+}
+
+/// Whether the token contains a whitespace marker: an actual whitespace, the
+/// byte-level mapping of one, or the `▁` of Metaspace
+fn has_whitespace_marker(token: &str) -> bool {
+    token
+        .chars()
+        .any(|c| c.is_whitespace() || c == '\u{2581}' || WHITESPACE_BYTE_CHARS.contains(&c))
+}
+
+/// Map a sequence to the byte-level alphabet, one char per input byte
+fn to_byte_level(sequence: &str) -> String {
+    sequence.bytes().map(|b| BYTES_CHAR[&b]).collect()
+}
 
 #[derive(Debug, Eq)]
 struct Merge {
@@ -35,6 +63,21 @@ impl Ord for Merge {
     }
 }
 
+/// A single merge learnt during training, as recorded when the trainer is
+/// configured with [`BpeTrainerBuilder::merge_history`] or
+/// [`BpeTrainerBuilder::merge_history_file`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MergeRecord {
+    /// The pair of tokens that got merged
+    pub pair: (String, String),
+    /// The token produced by the merge
+    pub new_token: String,
+    /// The frequency of the pair at the time it was merged
+    pub frequency: u64,
+    /// The index of the merge in the history, starting at 0
+    pub step: usize,
+}
+
 struct Config {
     min_frequency: u64,
     vocab_size: usize,
@@ -45,6 +88,17 @@ struct Config {
     continuing_subword_prefix: Option<String>,
     end_of_word_suffix: Option<String>,
     max_token_length: Option<usize>,
+    max_token_length_in_bytes: bool,
+    max_whitespace_token_length: Option<usize>,
+    deterministic: bool,
+    blocked_tokens: HashSet<String>,
+    blocked_pattern: Option<String>,
+    report: bool,
+    merge_history: bool,
+    merge_history_file: Option<String>,
+    byte_level: bool,
+    max_memory_mb: Option<usize>,
+    max_tracked_words: Option<usize>,
 }
 
 /// A `BpeTrainerBuilder` can be used to create a `BpeTrainer` with a custom
@@ -66,6 +120,17 @@ impl Default for BpeTrainerBuilder {
                 continuing_subword_prefix: None,
                 end_of_word_suffix: None,
                 max_token_length: None,
+                max_token_length_in_bytes: false,
+                max_whitespace_token_length: None,
+                deterministic: false,
+                blocked_tokens: HashSet::new(),
+                blocked_pattern: None,
+                report: false,
+                merge_history: false,
+                merge_history_file: None,
+                byte_level: false,
+                max_memory_mb: None,
+                max_tracked_words: None,
             },
         }
     }
@@ -139,6 +204,86 @@ impl BpeTrainerBuilder {
         self
     }
 
+    /// Set whether the token length limits count UTF-8 bytes instead of chars
+    #[must_use]
+    pub fn max_token_length_in_bytes(mut self, in_bytes: bool) -> Self {
+        self.config.max_token_length_in_bytes = in_bytes;
+        self
+    }
+
+    /// Set a separate length limit for tokens containing a whitespace marker
+    #[must_use]
+    pub fn max_whitespace_token_length(mut self, max_length: usize) -> Self {
+        self.config.max_whitespace_token_length = Some(max_length);
+        self
+    }
+
+    /// Set whether the training should be deterministic
+    #[must_use]
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.config.deterministic = deterministic;
+        self
+    }
+
+    /// Set the tokens that must never be added to the vocabulary
+    #[must_use]
+    pub fn blocked_tokens(mut self, tokens: HashSet<String>) -> Self {
+        self.config.blocked_tokens = tokens;
+        self
+    }
+
+    /// Set the pattern that candidate tokens must not match
+    #[must_use]
+    pub fn blocked_pattern(mut self, pattern: String) -> Self {
+        self.config.blocked_pattern = Some(pattern);
+        self
+    }
+
+    /// Set whether `train_with_report` should produce a `TrainingReport`
+    #[must_use]
+    pub fn report(mut self, report: bool) -> Self {
+        self.config.report = report;
+        self
+    }
+
+    /// Set whether [`BpeTrainer::do_train_with_history`] should record the
+    /// full merge history
+    #[must_use]
+    pub fn merge_history(mut self, merge_history: bool) -> Self {
+        self.config.merge_history = merge_history;
+        self
+    }
+
+    /// Set a sidecar JSON file where the merge history gets written at the
+    /// end of training
+    #[must_use]
+    pub fn merge_history_file(mut self, path: String) -> Self {
+        self.config.merge_history_file = Some(path);
+        self
+    }
+
+    /// Set whether to train at the byte level, on whole sequences
+    #[must_use]
+    pub fn byte_level(mut self, byte_level: bool) -> Self {
+        self.config.byte_level = byte_level;
+        self
+    }
+
+    /// Set the memory budget of the word counting phase, in megabytes
+    #[must_use]
+    pub fn max_memory_mb(mut self, max_memory_mb: usize) -> Self {
+        self.config.max_memory_mb = Some(max_memory_mb);
+        self
+    }
+
+    /// Set the number of words the counting phase may track, making the
+    /// counts approximate
+    #[must_use]
+    pub fn max_tracked_words(mut self, max_tracked_words: usize) -> Self {
+        self.config.max_tracked_words = Some(max_tracked_words);
+        self
+    }
+
     /// Constructs the final BpeTrainer
     pub fn build(self) -> BpeTrainer {
         BpeTrainer {
@@ -151,7 +296,19 @@ impl BpeTrainerBuilder {
             continuing_subword_prefix: self.config.continuing_subword_prefix,
             end_of_word_suffix: self.config.end_of_word_suffix,
             max_token_length: self.config.max_token_length,
+            max_token_length_in_bytes: self.config.max_token_length_in_bytes,
+            max_whitespace_token_length: self.config.max_whitespace_token_length,
+            deterministic: self.config.deterministic,
+            blocked_tokens: self.config.blocked_tokens,
+            blocked_pattern: self.config.blocked_pattern,
+            report: self.config.report,
+            merge_history: self.config.merge_history,
+            merge_history_file: self.config.merge_history_file,
+            byte_level: self.config.byte_level,
+            max_memory_mb: self.config.max_memory_mb,
+            max_tracked_words: self.config.max_tracked_words,
             words: HashMap::new(),
+            validation: vec![],
         }
     }
 }
@@ -194,8 +351,75 @@ pub struct BpeTrainer {
     pub end_of_word_suffix: Option<String>,
     /// An optional parameter to limit the max length of any single token
     pub max_token_length: Option<usize>,
+    /// Whether `max_token_length` and `max_whitespace_token_length` count
+    /// UTF-8 bytes instead of chars, for parity with byte-oriented toolchains
+    #[serde(default)]
+    pub max_token_length_in_bytes: bool,
+    /// An optional separate length limit for tokens containing a whitespace
+    /// marker: an actual whitespace, the byte-level mapping of one (e.g. `Ġ`),
+    /// or the `▁` of Metaspace. Defaults to `max_token_length`, and uses the
+    /// same unit. This helps keeping multi-word tokens out of the vocabulary
+    /// without constraining the in-word ones
+    #[serde(default)]
+    pub max_whitespace_token_length: Option<usize>,
+    /// Whether to enforce run-to-run reproducible results, even when training with
+    /// multiple threads. Words are then processed in lexicographic order, so that token
+    /// ids are assigned in a stable way, and pairs with the same frequency are always
+    /// merged in ascending order of their token ids.
+    #[serde(default)]
+    pub deterministic: bool,
+    /// Tokens that must never be produced by a merge. A merge that would result in
+    /// one of them is simply skipped, and the next best pair is considered instead
+    #[serde(default)]
+    pub blocked_tokens: HashSet<String>,
+    /// A regex pattern that merged tokens must NOT match, e.g. `[a-z]+[0-9]` to
+    /// prevent tokens mixing letters and digits. Like `blocked_tokens`, this is
+    /// applied during merge selection, not as a post-hoc pruning
+    #[serde(default)]
+    pub blocked_pattern: Option<String>,
+    /// Whether [`Trainer::train_with_report`] should produce a [`TrainingReport`]
+    #[serde(default)]
+    pub report: bool,
+    /// Whether [`BpeTrainer::do_train_with_history`] should record the full
+    /// merge history: each merge with its pair, its frequency at merge time,
+    /// and its step index. This lets researchers analyze merge dynamics or
+    /// derive vocabulary curriculum schedules
+    #[serde(default)]
+    pub merge_history: bool,
+    /// An optional sidecar JSON file where the merge history gets written at
+    /// the end of training, as an array of records
+    #[serde(default)]
+    pub merge_history_file: Option<String>,
+    /// Whether to train directly on the byte-level representation of the
+    /// sequences, SentencePiece-style: each sequence fed to the trainer is
+    /// mapped through the byte-to-char alphabet of the ByteLevel pre-tokenizer
+    /// and counted as a single whole-sentence entry, without any word
+    /// splitting, so merges can cross whitespace. The full byte alphabet is
+    /// always kept in the vocabulary, and `max_token_length` defaults to 16 in
+    /// this mode to keep merges from swallowing whole sentences
+    #[serde(default)]
+    pub byte_level: bool,
+    /// An optional memory budget, in megabytes, for the word counting phase.
+    /// When the estimated size of the in-progress word-count map exceeds it,
+    /// the map is spilled to a sorted run on disk, and the runs are merged
+    /// back with an external sort once the feeding ends. This bounds the
+    /// transient memory used while counting huge corpora (the final map of
+    /// unique words still has to fit in memory), at the cost of sequential
+    /// counting
+    #[serde(default)]
+    pub max_memory_mb: Option<usize>,
+    /// An optional cap on the number of words the counting phase may track.
+    /// When set, the counts are approximated with the space-saving algorithm:
+    /// only the heaviest words are kept, with counts overestimated by at most
+    /// `total / max_tracked_words`. This bounds the counting memory to
+    /// hundreds of MB even on corpora with billions of unique words, at a
+    /// slight quality cost
+    #[serde(default)]
+    pub max_tracked_words: Option<usize>,
 
     words: HashMap<String, u64>,
+    #[serde(default)]
+    validation: Vec<String>,
 }
 
 impl Default for BpeTrainer {
@@ -217,6 +441,19 @@ impl BpeTrainer {
         BpeTrainerBuilder::new()
     }
 
+    /// Build the [`TrainingReport`] for a freshly trained model, if the
+    /// trainer is configured to produce one
+    pub(crate) fn make_report<M: Model>(
+        &self,
+        model: &M,
+        unk_token: Option<&str>,
+    ) -> Result<Option<TrainingReport>> {
+        if !self.report {
+            return Ok(None);
+        }
+        TrainingReport::compute(&self.words, &self.validation, model, unk_token).map(Some)
+    }
+
     /// Setup a progress bar if asked to show progress
     fn setup_progress(&self) -> Option<ProgressBar> {
         if self.show_progress {
@@ -286,6 +523,17 @@ impl BpeTrainer {
                 .or_insert(usize::MAX);
         }
 
+        // In byte-level mode, every byte must keep a token so that any input
+        // stays representable
+        if self.byte_level {
+            for c in BYTES_CHAR.values() {
+                alphabet
+                    .entry(*c)
+                    .and_modify(|cnt| *cnt = usize::MAX)
+                    .or_insert(usize::MAX);
+            }
+        }
+
         let mut kept = alphabet.iter().collect::<Vec<_>>();
 
         // Compute the number of chars to remove from the alphabet
@@ -304,7 +552,13 @@ impl BpeTrainer {
 
         // Remove the unwanted chars
         if to_remove > 0 {
-            kept.sort_unstable_by_key(|k| *k.1);
+            if self.deterministic {
+                // Break count ties by the char itself, so that the kept alphabet does not
+                // depend on the HashMap iteration order
+                kept.sort_unstable_by_key(|k| (*k.1, *k.0 as u32));
+            } else {
+                kept.sort_unstable_by_key(|k| *k.1);
+            }
             kept.drain(..to_remove);
         }
 
@@ -330,7 +584,15 @@ impl BpeTrainer {
         let mut words: Vec<Word> = Vec::with_capacity(wc.len());
         let mut counts: Vec<u64> = Vec::with_capacity(wc.len());
 
-        for (word, count) in wc {
+        // When deterministic, process the words in lexicographic order so that the new
+        // subwords always get the same ids, making the merge tie-breaking (ascending pair
+        // ids) reproducible from one run to the other
+        let mut entries: Vec<(&String, &u64)> = wc.iter().collect();
+        if self.deterministic {
+            entries.sort_unstable_by_key(|(word, _)| *word);
+        }
+
+        for (word, count) in entries {
             let mut current_word = Word::new();
             counts.push(*count);
 
@@ -357,7 +619,14 @@ impl BpeTrainer {
                         id2w.push(s.clone());
                         w2id.insert(s.clone(), (id2w.len() - 1) as u32);
                     }
-                    current_word.add(w2id[&s], 1); // We do not care about the len here
+                    // The symbol length is only compared against the length
+                    // limits: in chars by default, or in bytes when configured
+                    let len = if self.max_token_length_in_bytes {
+                        c.len_utf8()
+                    } else {
+                        1
+                    };
+                    current_word.add(w2id[&s], len);
                 }
             }
             words.push(current_word);
@@ -434,9 +703,36 @@ impl BpeTrainer {
         word_counts: &HashMap<String, u64>,
         model: &mut BPE,
     ) -> Result<Vec<AddedToken>> {
+        self.do_train_with_history(word_counts, model)
+            .map(|(special_tokens, _)| special_tokens)
+    }
+
+    /// Same as [`BpeTrainer::do_train`], additionally returning the merge
+    /// history when the trainer is configured with `merge_history`. When
+    /// `merge_history_file` is set, the history also gets written there as
+    /// JSON, whichever entry point was used
+    pub fn do_train_with_history(
+        &self,
+        word_counts: &HashMap<String, u64>,
+        model: &mut BPE,
+    ) -> Result<(Vec<AddedToken>, Option<Vec<MergeRecord>>)> {
+        let record_history = self.merge_history || self.merge_history_file.is_some();
+        let mut history: Vec<MergeRecord> = vec![];
         let mut word_to_id: HashMap<String, u32> = HashMap::with_capacity(self.vocab_size);
         let mut id_to_word: Vec<String> = Vec::with_capacity(self.vocab_size);
-        let max_token_length: usize = self.max_token_length.unwrap_or(usize::MAX);
+        // Without word boundaries, unbounded merges would end up swallowing whole
+        // sentences, so byte-level training caps tokens at the SentencePiece
+        // default length unless configured otherwise
+        let max_token_length: usize =
+            self.max_token_length
+                .unwrap_or(if self.byte_level { 16 } else { usize::MAX });
+        let max_whitespace_token_length =
+            self.max_whitespace_token_length.unwrap_or(max_token_length);
+        let blocked_pattern = self
+            .blocked_pattern
+            .as_deref()
+            .map(Regex::new)
+            .transpose()?;
 
         let progress = self.setup_progress();
 
@@ -481,6 +777,12 @@ impl BpeTrainer {
         // 5. Do merges
         //
         self.update_progress(&progress, self.vocab_size, "Compute merges");
+        // Track which tokens contain a whitespace marker, so that pairs
+        // involving them can be held to `max_whitespace_token_length`
+        let mut has_marker: Vec<bool> = id_to_word
+            .iter()
+            .map(|token| has_whitespace_marker(token))
+            .collect();
         let mut merges: Vec<(Pair, u32)> = vec![];
         loop {
             // Stop as soon as we have a big enough vocabulary
@@ -514,10 +816,15 @@ impl BpeTrainer {
                 }
             }
             let new_token = format!("{}{}", part_a, part_b);
-            // implement sentencepiece-like merge.
-            // if this code were to be merged, integrate a way in the python bindings to communicate this variable
-            // default should be 0/None to maintain previous behavior. 16 is the spm default.
-
+            // Skip merges that would produce a blocked token, and keep going with the
+            // next best pair
+            if self.blocked_tokens.contains(&new_token)
+                || blocked_pattern
+                    .as_ref()
+                    .is_some_and(|re| re.is_match(&new_token))
+            {
+                continue;
+            }
             // Insert new token if it does not already exist
             let new_token_id = word_to_id
                 .get(&new_token)
@@ -526,10 +833,30 @@ impl BpeTrainer {
             if !word_to_id.contains_key(&new_token) {
                 id_to_word.push(new_token.clone());
                 word_to_id.insert(new_token.clone(), new_token_id);
+                has_marker.push(has_whitespace_marker(&new_token));
             }
             merges.push((top.pair, new_token_id));
+            if record_history {
+                history.push(MergeRecord {
+                    pair: (
+                        id_to_word[top.pair.0 as usize].clone(),
+                        id_to_word[top.pair.1 as usize].clone(),
+                    ),
+                    new_token: new_token.clone(),
+                    frequency: top.count,
+                    step: history.len(),
+                });
+            }
 
             // Merge the new pair in every words
+            // The length limit of a candidate pair depends on its content
+            let max_length = |a: u32, b: u32| {
+                if has_marker[a as usize] || has_marker[b as usize] {
+                    max_whitespace_token_length
+                } else {
+                    max_token_length
+                }
+            };
             let changes = top
                 .pos
                 .maybe_par_iter()
@@ -540,7 +867,7 @@ impl BpeTrainer {
                     unsafe {
                         // let word: &mut Word = &mut (*word);
                         (*word)
-                            .merge(top.pair.0, top.pair.1, new_token_id, max_token_length)
+                            .merge(top.pair.0, top.pair.1, new_token_id, max_length)
                             .into_iter()
                             .map(|c| (c, i))
                             .collect::<Vec<_>>()
@@ -609,7 +936,14 @@ impl BpeTrainer {
             model.end_of_word_suffix = None;
         }
 
-        Ok(self.special_tokens.clone())
+        if let Some(path) = &self.merge_history_file {
+            std::fs::write(path, serde_json::to_string_pretty(&history)?)?;
+        }
+
+        Ok((
+            self.special_tokens.clone(),
+            self.merge_history.then_some(history),
+        ))
     }
 }
 
@@ -632,10 +966,51 @@ impl Trainer for BpeTrainer {
         S: AsRef<str> + Send,
         F: Fn(&str) -> Result<Vec<String>> + Sync,
     {
+        let byte_level = self.byte_level;
+        if let Some(max_tracked_words) = self.max_tracked_words {
+            // Approximate counting: keep only the heaviest words, evicting
+            // the rarest ones whenever the counters are full
+            let mut counter = SpaceSavingCounter::new(max_tracked_words);
+            for sequence in iterator {
+                let words = if byte_level {
+                    vec![to_byte_level(sequence.as_ref())]
+                } else {
+                    process(sequence.as_ref())?
+                };
+                for word in words {
+                    counter.add(word, 1u64);
+                }
+            }
+            self.words = counter.finish();
+            return Ok(());
+        }
+        if let Some(max_memory_mb) = self.max_memory_mb {
+            // Memory-bounded counting: accumulate sequentially, spilling the
+            // counts to disk whenever the budget is exceeded
+            let mut counter = SpillingWordCounter::new(max_memory_mb);
+            for sequence in iterator {
+                let words = if byte_level {
+                    vec![to_byte_level(sequence.as_ref())]
+                } else {
+                    process(sequence.as_ref())?
+                };
+                for word in words {
+                    counter.add(word, 1u64)?;
+                }
+            }
+            self.words = counter.finish()?;
+            return Ok(());
+        }
         let words: Result<HashMap<String, u64>> = iterator
             .maybe_par_bridge()
             .map(|sequence| {
-                let words = process(sequence.as_ref())?;
+                let words = if byte_level {
+                    // Whole-sentence training: bypass the word splitting and keep
+                    // each full sequence, mapped to the byte-level alphabet
+                    vec![to_byte_level(sequence.as_ref())]
+                } else {
+                    process(sequence.as_ref())?
+                };
                 let mut map = HashMap::new();
                 for word in words {
                     map.entry(word).and_modify(|c| *c += 1).or_insert(1);
@@ -656,12 +1031,230 @@ impl Trainer for BpeTrainer {
         self.words = words?;
         Ok(())
     }
+
+    fn feed_weighted<I, S, F>(&mut self, iterator: I, process: F) -> Result<()>
+    where
+        I: Iterator<Item = (S, f64)> + Send,
+        S: AsRef<str> + Send,
+        F: Fn(&str) -> Result<Vec<String>> + Sync,
+    {
+        let byte_level = self.byte_level;
+        if let Some(max_tracked_words) = self.max_tracked_words {
+            let mut counter = SpaceSavingCounter::new(max_tracked_words);
+            for (sequence, weight) in iterator {
+                let words = if byte_level {
+                    vec![to_byte_level(sequence.as_ref())]
+                } else {
+                    process(sequence.as_ref())?
+                };
+                let weight = weight.round() as u64;
+                if weight == 0 {
+                    continue;
+                }
+                for word in words {
+                    counter.add(word, weight);
+                }
+            }
+            self.words = counter.finish();
+            return Ok(());
+        }
+        if let Some(max_memory_mb) = self.max_memory_mb {
+            let mut counter = SpillingWordCounter::new(max_memory_mb);
+            for (sequence, weight) in iterator {
+                let words = if byte_level {
+                    vec![to_byte_level(sequence.as_ref())]
+                } else {
+                    process(sequence.as_ref())?
+                };
+                for word in words {
+                    counter.add(word, weight)?;
+                }
+            }
+            self.words = counter
+                .finish()?
+                .into_iter()
+                .filter_map(|(word, count)| {
+                    let count = count.round() as u64;
+                    (count > 0).then_some((word, count))
+                })
+                .collect();
+            return Ok(());
+        }
+        let words: Result<HashMap<String, f64>> = iterator
+            .maybe_par_bridge()
+            .map(|(sequence, weight)| {
+                let words = if byte_level {
+                    vec![to_byte_level(sequence.as_ref())]
+                } else {
+                    process(sequence.as_ref())?
+                };
+                let mut map = HashMap::new();
+                for word in words {
+                    map.entry(word)
+                        .and_modify(|c| *c += weight)
+                        .or_insert(weight);
+                }
+                Ok(map)
+            })
+            .reduce(
+                || Ok(HashMap::new()),
+                |acc, ws| {
+                    let mut acc = acc?;
+                    for (k, v) in ws? {
+                        acc.entry(k).and_modify(|c| *c += v).or_insert(v);
+                    }
+                    Ok(acc)
+                },
+            );
+
+        self.words = words?
+            .into_iter()
+            .filter_map(|(word, count)| {
+                let count = count.round() as u64;
+                (count > 0).then_some((word, count))
+            })
+            .collect();
+        Ok(())
+    }
+
+    fn feed_validation<I, S>(&mut self, iterator: I) -> Result<()>
+    where
+        I: Iterator<Item = S> + Send,
+        S: AsRef<str> + Send,
+    {
+        self.validation = iterator.map(|s| s.as_ref().to_owned()).collect();
+        Ok(())
+    }
+
+    fn train_with_report(
+        &self,
+        model: &mut BPE,
+    ) -> Result<(Vec<AddedToken>, Option<TrainingReport>)> {
+        let special_tokens = self.do_train(&self.words, model)?;
+        let report = self.make_report(model, model.unk_token.as_deref())?;
+        Ok((special_tokens, report))
+    }
+
+    fn save_checkpoint(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    fn resume_from_checkpoint(&mut self, path: &Path) -> Result<()> {
+        *self = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{BpeTrainer, Pair, BPE};
-    use std::collections::HashMap;
+    use super::{BpeTrainer, MergeRecord, Pair, BPE};
+    use crate::Trainer;
+    use std::collections::{HashMap, HashSet};
+    use std::iter::FromIterator;
+
+    #[test]
+    fn test_feed_weighted() {
+        let mut trainer = BpeTrainer::builder().show_progress(false).build();
+        trainer
+            .feed_weighted(
+                vec![
+                    ("roses are red".to_string(), 0.7),
+                    ("roses are blue".to_string(), 0.7),
+                    ("so is big".to_string(), 0.3),
+                ]
+                .into_iter(),
+                |sequence| Ok(sequence.split_whitespace().map(|s| s.to_owned()).collect()),
+            )
+            .unwrap();
+
+        // Fractional weights accumulate before being rounded, and words rounding
+        // to a zero count are dropped
+        let expected: HashMap<String, u64> = [
+            ("roses".into(), 1),
+            ("are".into(), 1),
+            ("red".into(), 1),
+            ("blue".into(), 1),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        assert_eq!(trainer.words, expected);
+    }
+
+    #[test]
+    fn test_max_memory_mb() {
+        let sequences = ["roses are red", "roses are blue", "so is big"];
+        let process =
+            |sequence: &str| Ok(sequence.split_whitespace().map(|s| s.to_owned()).collect());
+
+        let mut trainer = BpeTrainer::builder().show_progress(false).build();
+        trainer.feed(sequences.iter(), process).unwrap();
+
+        // A zero budget spills to disk constantly, and still produces the
+        // same counts as the in-memory path
+        let mut spilling_trainer = BpeTrainer::builder()
+            .show_progress(false)
+            .max_memory_mb(0)
+            .build();
+        spilling_trainer.feed(sequences.iter(), process).unwrap();
+        assert_eq!(spilling_trainer.words, trainer.words);
+    }
+
+    #[test]
+    fn test_max_tracked_words() {
+        let sequences = ["roses are red", "roses are blue", "roses are watered"];
+        let process =
+            |sequence: &str| Ok(sequence.split_whitespace().map(|s| s.to_owned()).collect());
+
+        // With as many counters as unique words, the counts are exact
+        let mut trainer = BpeTrainer::builder()
+            .show_progress(false)
+            .max_tracked_words(5)
+            .build();
+        trainer.feed(sequences.iter(), process).unwrap();
+        assert_eq!(trainer.words["roses"], 3);
+        assert_eq!(trainer.words["are"], 3);
+
+        // With fewer, only the heaviest words survive
+        let mut trainer = BpeTrainer::builder()
+            .show_progress(false)
+            .max_tracked_words(3)
+            .build();
+        trainer.feed(sequences.iter(), process).unwrap();
+        assert_eq!(trainer.words.len(), 3);
+        assert_eq!(trainer.words["roses"], 3);
+        assert_eq!(trainer.words["are"], 3);
+    }
+
+    #[test]
+    fn test_byte_level() {
+        let mut trainer = BpeTrainer::builder()
+            .show_progress(false)
+            .byte_level(true)
+            .vocab_size(300)
+            .build();
+        trainer
+            .feed(["ab ab", "ab ab"].iter(), |sequence| {
+                Ok(sequence.split_whitespace().map(|s| s.to_owned()).collect())
+            })
+            .unwrap();
+
+        // Whole sequences are counted, mapped to the byte-level alphabet: the
+        // word splitting of the `process` callback is bypassed
+        let expected: HashMap<String, u64> = [("ab\u{120}ab".into(), 2)].iter().cloned().collect();
+        assert_eq!(trainer.words, expected);
+
+        let mut model = BPE::default();
+        trainer.train(&mut model).unwrap();
+
+        // The full byte alphabet is kept, even though the sequences only use a
+        // few characters
+        assert!(model.vocab.len() >= 256);
+        // Merges are free to cross the whitespace between the two words
+        assert!(model.vocab.contains_key("ab"));
+        assert!(model.vocab.contains_key("ab\u{120}ab"));
+    }
 
     #[test]
     fn test_train() {
@@ -736,6 +1329,214 @@ mod tests {
         .collect();
         assert_eq!(model.merges, expected_merges);
     }
+    #[test]
+    fn test_checkpoint_roundtrip() {
+        let word_counts: HashMap<String, u64> = [("roses".into(), 22), ("are".into(), 24)]
+            .iter()
+            .cloned()
+            .collect();
+        let mut trainer = BpeTrainer::builder()
+            .show_progress(false)
+            .min_frequency(2)
+            .build();
+        trainer.words = word_counts;
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        trainer.save_checkpoint(file.path()).unwrap();
+
+        let mut resumed = BpeTrainer::default();
+        resumed.resume_from_checkpoint(file.path()).unwrap();
+        assert_eq!(resumed, trainer);
+    }
+
+    #[test]
+    fn test_merge_history() {
+        let word_counts: HashMap<String, u64> =
+            [("roses".into(), 1), ("are".into(), 2), ("red".into(), 1)]
+                .iter()
+                .cloned()
+                .collect();
+        let trainer = BpeTrainer::builder()
+            .show_progress(false)
+            .min_frequency(2)
+            .merge_history(true)
+            .build();
+        let mut model = BPE::default();
+        let (_, history) = trainer
+            .do_train_with_history(&word_counts, &mut model)
+            .unwrap();
+        let history = history.unwrap();
+
+        // 're' appears twice in 'are' and once in 'red', then 'a' + 're'
+        assert_eq!(
+            history,
+            vec![
+                MergeRecord {
+                    pair: ("r".into(), "e".into()),
+                    new_token: "re".into(),
+                    frequency: 3,
+                    step: 0,
+                },
+                MergeRecord {
+                    pair: ("a".into(), "re".into()),
+                    new_token: "are".into(),
+                    frequency: 2,
+                    step: 1,
+                },
+            ]
+        );
+
+        // The history is not recorded unless asked for
+        let trainer = BpeTrainer::builder().show_progress(false).build();
+        let (_, history) = trainer
+            .do_train_with_history(&word_counts, &mut BPE::default())
+            .unwrap();
+        assert!(history.is_none());
+
+        // With a sidecar file, the history gets written as JSON even through
+        // the plain `do_train` entry point
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let trainer = BpeTrainer::builder()
+            .show_progress(false)
+            .min_frequency(2)
+            .merge_history_file(file.path().to_str().unwrap().to_owned())
+            .build();
+        trainer.do_train(&word_counts, &mut BPE::default()).unwrap();
+        let written: Vec<MergeRecord> =
+            serde_json::from_str(&std::fs::read_to_string(file.path()).unwrap()).unwrap();
+        assert_eq!(written, history_expected(&word_counts));
+    }
+
+    fn history_expected(word_counts: &HashMap<String, u64>) -> Vec<MergeRecord> {
+        let trainer = BpeTrainer::builder()
+            .show_progress(false)
+            .min_frequency(2)
+            .merge_history(true)
+            .build();
+        trainer
+            .do_train_with_history(word_counts, &mut BPE::default())
+            .unwrap()
+            .1
+            .unwrap()
+    }
+
+    #[test]
+    fn test_training_report() {
+        let mut trainer = BpeTrainer::builder()
+            .show_progress(false)
+            .special_tokens(vec![crate::AddedToken::from("<unk>", true)])
+            .limit_alphabet(3)
+            .report(true)
+            .build();
+        trainer.words = [
+            ("roses".into(), 20),
+            ("are".into(), 15),
+            ("red".into(), 10),
+            ("x".into(), 1),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        trainer.feed_validation(["roses", "xyz"].iter()).unwrap();
+
+        let mut model = BPE::builder().unk_token("<unk>".into()).build().unwrap();
+        let (special_tokens, report) = trainer.train_with_report(&mut model).unwrap();
+        assert_eq!(special_tokens.len(), 1);
+        let report = report.unwrap();
+
+        // The alphabet limit keeps only 'r', 's' and 'e'; the remaining
+        // characters are dropped, most frequent first
+        assert_eq!(
+            report.dropped_characters,
+            vec![('o', 20), ('a', 15), ('d', 10), ('x', 1)]
+        );
+        assert!((report.character_coverage - 130.0 / 176.0).abs() < 1e-9);
+        // Every vocab token is accounted for in the histogram
+        assert_eq!(
+            report.token_length_histogram.values().sum::<usize>(),
+            model.get_vocab().len()
+        );
+        // The validation split contains characters unknown to the model
+        assert!(report.unk_rate.unwrap() > 0.0);
+
+        // Serializable, and not produced when the option is off
+        let _ = serde_json::to_string(&report).unwrap();
+        trainer.report = false;
+        let (_, report) = trainer.train_with_report(&mut model).unwrap();
+        assert!(report.is_none());
+    }
+
+    #[test]
+    fn test_blocked_tokens() {
+        let word_counts: HashMap<String, u64> = [
+            ("roses".into(), 1),
+            ("are".into(), 2),
+            ("red".into(), 1),
+            ("voilets".into(), 1),
+            ("blue".into(), 1),
+            ("BERT".into(), 1),
+            ("is".into(), 2),
+            ("big".into(), 1),
+            ("and".into(), 1),
+            ("so".into(), 1),
+            ("GPT-2".into(), 1),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let trainer = BpeTrainer::builder()
+            .show_progress(false)
+            .min_frequency(2)
+            .blocked_tokens(HashSet::from_iter(vec!["re".to_string()]))
+            .blocked_pattern("^is$".into())
+            .build();
+        let mut model = BPE::default();
+        trainer.do_train(&word_counts, &mut model).unwrap();
+
+        // Neither the exact blocked token nor any token matching the pattern made it
+        // into the vocabulary
+        assert!(!model.vocab.contains_key("re"));
+        assert!(!model.vocab.contains_key("is"));
+        // But the selection went on with the next best pairs: since 're' cannot be
+        // formed, 'are' is built from 'ar' + 'e' instead
+        assert!(model.vocab.contains_key("ar"));
+        assert!(model.vocab.contains_key("are"));
+    }
+
+    #[test]
+    fn test_deterministic_training() {
+        // All the words have the same count, and the continuing_subword_prefix makes
+        // `tokenize_words` assign new subword ids, so without the deterministic option the
+        // resulting ids (and merge tie-breaking) depend on the HashMap iteration order
+        let word_counts: HashMap<String, u64> = [
+            ("apple".into(), 1),
+            ("maple".into(), 1),
+            ("ample".into(), 1),
+            ("apples".into(), 1),
+            ("mapled".into(), 1),
+            ("amples".into(), 1),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+
+        let train = || {
+            let trainer = BpeTrainer::builder()
+                .show_progress(false)
+                .continuing_subword_prefix("##".into())
+                .deterministic(true)
+                .build();
+            let mut model = BPE::default();
+            trainer.do_train(&word_counts, &mut model).unwrap();
+            (model.vocab.clone(), model.merges.clone())
+        };
+
+        let reference = train();
+        for _ in 0..3 {
+            assert_eq!(train(), reference);
+        }
+    }
+
     #[test]
     fn bpe_test_max_token_length_16() {
         /* bpe_test_max_token_length series of tests test the max_token_length flag of bpetrainer
@@ -778,6 +1579,50 @@ mod tests {
             )
         }
     }
+    #[test]
+    fn bpe_test_max_token_length_in_bytes() {
+        let word_counts: HashMap<String, u64> =
+            [("一二三四五六七八九十", 5), ("十九八七六五四三二一", 5)]
+                .iter()
+                .map(|(key, value)| (key.to_string(), *value))
+                .collect();
+        let trainer = BpeTrainer::builder()
+            .show_progress(false)
+            .max_token_length(Some(6))
+            .max_token_length_in_bytes(true)
+            .build();
+        let mut model = BPE::default();
+        trainer.do_train(&word_counts, &mut model).unwrap();
+
+        // Every token stays within 6 bytes, i.e. two of these chars, while the
+        // same limit counted in chars would have allowed 18-byte tokens
+        let vocab = model.get_vocab();
+        assert!(vocab.keys().all(|token| token.len() <= 6));
+        assert!(vocab.keys().any(|token| token.chars().count() == 2));
+    }
+
+    #[test]
+    fn bpe_test_max_whitespace_token_length() {
+        let word_counts: HashMap<String, u64> = [("hello", 5), ("Ġhello", 5), ("Ġworld", 5)]
+            .iter()
+            .map(|(key, value)| (key.to_string(), *value))
+            .collect();
+        let trainer = BpeTrainer::builder()
+            .show_progress(false)
+            .max_whitespace_token_length(3)
+            .build();
+        let mut model = BPE::default();
+        trainer.do_train(&word_counts, &mut model).unwrap();
+
+        // Tokens holding a whitespace marker stay short, while the others are
+        // not limited at all
+        let vocab = model.get_vocab();
+        assert!(vocab
+            .keys()
+            .all(|token| !super::has_whitespace_marker(token) || token.chars().count() <= 3));
+        assert!(vocab.contains_key("hello"));
+    }
+
     #[test]
     fn bpe_test_max_token_length_direct_assert() {
         /* more direct version of bpe_test_max_token_length test
@@ -103,12 +103,15 @@ impl Word {
         });
     }
 
+    /// `max_length` gives the length limit applying to a candidate pair of
+    /// symbols, so that the caller can enforce different limits depending on
+    /// the symbols' content
     pub(super) fn merge(
         &mut self,
         c1: u32,
         c2: u32,
         replacement: u32,
-        max_length: usize,
+        max_length: impl Fn(u32, u32) -> usize,
     ) -> Vec<(Pair, i32)> {
         let mut changes: Vec<(Pair, i32)> = vec![];
         let mut i = 0;
@@ -134,7 +137,9 @@ impl Word {
                 // If there are other characters before the pair
                 if i > 0 {
                     changes.push(((self.symbols[i - 1].c, first.c), -1));
-                    if self.symbols[i - 1].len + new_s.len < max_length {
+                    if self.symbols[i - 1].len + new_s.len
+                        < max_length(self.symbols[i - 1].c, replacement)
+                    {
                         changes.push(((self.symbols[i - 1].c, replacement), 1));
                     }
                 }
@@ -146,7 +151,9 @@ impl Word {
                 // If there are other characters after the pair
                 if i < self.symbols.len() - 1 {
                     changes.push(((second.c, self.symbols[i + 1].c), -1));
-                    if self.symbols[i + 1].len + new_s.len < max_length {
+                    if self.symbols[i + 1].len + new_s.len
+                        < max_length(replacement, self.symbols[i + 1].c)
+                    {
                         changes.push(((replacement, self.symbols[i + 1].c), 1));
                     }
                 }
@@ -159,6 +166,98 @@ impl Word {
     }
 
     pub(super) fn merge_all(&mut self, merges: &HashMap<Pair, (u32, u32)>, dropout: Option<f32>) {
+        self.merge_all_with(merges, dropout, |_, _, _| {});
+    }
+
+    /// Like [`Self::merge_all`], except that merges may not cross the given
+    /// byte offsets: each segment between two consecutive boundaries is
+    /// merged on its own
+    pub(super) fn merge_all_within(
+        &mut self,
+        merges: &HashMap<Pair, (u32, u32)>,
+        dropout: Option<f32>,
+        boundaries: &[usize],
+    ) {
+        self.merge_all_within_with(merges, dropout, boundaries, |_, _, _| {});
+    }
+
+    /// Like [`Self::merge_all_within`], additionally calling `on_merge` the
+    /// way [`Self::merge_all_with`] does
+    pub(super) fn merge_all_within_with(
+        &mut self,
+        merges: &HashMap<Pair, (u32, u32)>,
+        dropout: Option<f32>,
+        boundaries: &[usize],
+        mut on_merge: impl FnMut(Pair, u32, u32),
+    ) {
+        if boundaries.is_empty() {
+            return self.merge_all_with(merges, dropout, on_merge);
+        }
+        let symbols = std::mem::take(&mut self.symbols);
+        let mut segment = Self::new();
+        let mut offset = 0;
+        for symbol in symbols {
+            // A boundary falling inside a symbol (e.g. a fused unknown
+            // token) is simply ignored
+            if boundaries.contains(&offset) && !segment.symbols.is_empty() {
+                segment.merge_all_with(merges, dropout, &mut on_merge);
+                self.symbols.append(&mut segment.symbols);
+            }
+            offset += symbol.len;
+            segment.add(symbol.c, symbol.len);
+        }
+        segment.merge_all_with(merges, dropout, on_merge);
+        self.symbols.append(&mut segment.symbols);
+    }
+
+    /// Like [`Self::merge_all_within`], only applying the merges whose
+    /// resulting token id satisfies `allowed`
+    pub(super) fn merge_all_within_when(
+        &mut self,
+        merges: &HashMap<Pair, (u32, u32)>,
+        dropout: Option<f32>,
+        boundaries: &[usize],
+        allowed: impl Fn(u32) -> bool + Copy,
+    ) {
+        if boundaries.is_empty() {
+            return self.merge_all_when(merges, dropout, |_, _, _| {}, allowed);
+        }
+        let symbols = std::mem::take(&mut self.symbols);
+        let mut segment = Self::new();
+        let mut offset = 0;
+        for symbol in symbols {
+            if boundaries.contains(&offset) && !segment.symbols.is_empty() {
+                segment.merge_all_when(merges, dropout, |_, _, _| {}, allowed);
+                self.symbols.append(&mut segment.symbols);
+            }
+            offset += symbol.len;
+            segment.add(symbol.c, symbol.len);
+        }
+        segment.merge_all_when(merges, dropout, |_, _, _| {}, allowed);
+        self.symbols.append(&mut segment.symbols);
+    }
+
+    /// Like [`Self::merge_all`], additionally calling `on_merge` with the
+    /// merged pair, its rank and the resulting symbol, in the order the
+    /// merges are applied
+    pub(super) fn merge_all_with(
+        &mut self,
+        merges: &HashMap<Pair, (u32, u32)>,
+        dropout: Option<f32>,
+        on_merge: impl FnMut(Pair, u32, u32),
+    ) {
+        self.merge_all_when(merges, dropout, on_merge, |_| true);
+    }
+
+    /// Like [`Self::merge_all_with`], only applying the merges whose
+    /// resulting token id satisfies `allowed`
+    fn merge_all_when(
+        &mut self,
+        merges: &HashMap<Pair, (u32, u32)>,
+        dropout: Option<f32>,
+        mut on_merge: impl FnMut(Pair, u32, u32),
+        allowed: impl Fn(u32) -> bool,
+    ) {
         let mut queue = BinaryHeap::with_capacity(self.symbols.len());
         let mut skip = Vec::with_capacity(queue.len());
 
@@ -168,11 +267,14 @@ impl Word {
                 .enumerate()
                 .filter_map(|(index, window)| {
                     let pair = (window[0].c, window[1].c);
-                    merges.get(&pair).map(|m| Merge {
-                        pos: index,
-                        rank: m.0,
-                        new_id: m.1,
-                    })
+                    merges
+                        .get(&pair)
+                        .filter(|(_, new_id)| allowed(*new_id))
+                        .map(|m| Merge {
+                            pos: index,
+                            rank: m.0,
+                            new_id: m.1,
+                        })
                 }),
         );
 
@@ -207,6 +309,7 @@ impl Word {
                 }
 
                 // Otherwise, let's merge
+                on_merge(target_new_pair, top.rank, top.new_id);
                 self.symbols[top.pos].merge_with(&right, top.new_id);
                 // Tag the right part as removed
                 self.symbols[next_pos].len = 0;
@@ -222,7 +325,9 @@ impl Word {
                     let prev = current.prev as usize;
                     let prev_symbol = self.symbols[prev];
                     let new_pair = (prev_symbol.c, current.c);
-                    if let Some((rank, new_id)) = merges.get(&new_pair) {
+                    if let Some((rank, new_id)) =
+                        merges.get(&new_pair).filter(|(_, new_id)| allowed(*new_id))
+                    {
                         queue.push(Merge {
                             pos: current.prev as usize,
                             rank: *rank,
@@ -236,7 +341,9 @@ impl Word {
                 if next < self.symbols.len() {
                     let next_symbol = self.symbols[next];
                     let new_pair = (current.c, next_symbol.c);
-                    if let Some((rank, new_id)) = merges.get(&new_pair) {
+                    if let Some((rank, new_id)) =
+                        merges.get(&new_pair).filter(|(_, new_id)| allowed(*new_id))
+                    {
                         queue.push(Merge {
                             pos: top.pos,
                             rank: *rank,
@@ -287,7 +394,7 @@ mod tests {
 
         // We're going to perform a merge on the pair ('l', 'l') ~= (2, 2). Let's
         // say that 'll' has the ID of 4 in the updated word-to-id vocab.
-        let changes = word.merge(2, 2, 4, usize::MAX);
+        let changes = word.merge(2, 2, 4, |_, _| usize::MAX);
 
         // So the word should now look like this:
         assert_eq!(
@@ -331,7 +438,7 @@ mod tests {
 
         // We're going to perform a merge on the pair ('l', 'l') ~= (2, 2). Let's
         // say that 'll' has the ID of 4 in the updated word-to-id vocab.
-        let changes = word.merge(2, 2, 4, 2);
+        let changes = word.merge(2, 2, 4, |_, _| 2);
         assert_eq!(
             word.get_chars(),
             &[
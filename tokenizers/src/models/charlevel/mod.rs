@@ -0,0 +1,399 @@
+//! A character-level model, mapping each character (or grapheme cluster, or
+//! byte) of the input to an id from a trainable vocabulary.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::OrderedVocabIter;
+use crate::tokenizer::{Model, Result, Token};
+
+mod serialization;
+mod trainer;
+
+// Re-export
+pub use trainer::*;
+
+pub use crate::models::vocab::Vocab;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("CharLevel error: Missing [UNK] token from the vocabulary")]
+    MissingUnkToken,
+}
+
+/// The unit a [`CharLevel`] model maps to an id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CharGranularity {
+    /// One token per Unicode code point
+    #[default]
+    Char,
+    /// One token per extended grapheme cluster, keeping e.g. combining
+    /// sequences and emoji together
+    Grapheme,
+    /// One token per byte, using the `<0xXX>` byte-token convention
+    Byte,
+}
+
+impl CharGranularity {
+    /// Split `sequence` into its units, with their byte offsets
+    pub(super) fn split<'a>(&self, sequence: &'a str) -> Vec<(Cow<'a, str>, (usize, usize))> {
+        match self {
+            Self::Char => sequence
+                .char_indices()
+                .map(|(i, c)| {
+                    let end = i + c.len_utf8();
+                    (Cow::Borrowed(&sequence[i..end]), (i, end))
+                })
+                .collect(),
+            Self::Grapheme => sequence
+                .grapheme_indices(true)
+                .map(|(i, g)| (Cow::Borrowed(g), (i, i + g.len())))
+                .collect(),
+            Self::Byte => sequence
+                .bytes()
+                .enumerate()
+                .map(|(i, b)| (Cow::Owned(format!("<{:#04X}>", b)), (i, i + 1)))
+                .collect(),
+        }
+    }
+}
+
+struct Config {
+    vocab: Vocab,
+    unk_token: String,
+    granularity: CharGranularity,
+    byte_fallback: bool,
+}
+
+/// A `CharLevelBuilder` can be used to create a `CharLevel` model with a
+/// custom configuration.
+pub struct CharLevelBuilder {
+    config: Config,
+}
+
+impl Default for CharLevelBuilder {
+    fn default() -> Self {
+        Self {
+            config: Config {
+                vocab: Vocab::new(),
+                unk_token: String::from("<unk>"),
+                granularity: CharGranularity::default(),
+                byte_fallback: false,
+            },
+        }
+    }
+}
+
+impl CharLevelBuilder {
+    /// Construct a new `CharLevelBuilder`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the vocab (token -> ID) mapping.
+    #[must_use]
+    pub fn vocab(mut self, vocab: Vocab) -> Self {
+        self.config.vocab = vocab;
+        self
+    }
+
+    /// Set the `UNK` token for the vocab.
+    #[must_use]
+    pub fn unk_token(mut self, unk_token: String) -> Self {
+        self.config.unk_token = unk_token;
+        self
+    }
+
+    /// Set the unit the model maps to an id.
+    #[must_use]
+    pub fn granularity(mut self, granularity: CharGranularity) -> Self {
+        self.config.granularity = granularity;
+        self
+    }
+
+    /// Set whether units missing from the vocabulary decompose into their
+    /// `<0xXX>` byte tokens before falling back on the unk token.
+    #[must_use]
+    pub fn byte_fallback(mut self, byte_fallback: bool) -> Self {
+        self.config.byte_fallback = byte_fallback;
+        self
+    }
+
+    /// Constructs a `CharLevel` model that uses the `CharLevelBuilder`'s
+    /// configuration.
+    pub fn build(self) -> Result<CharLevel> {
+        let vocab_r = self
+            .config
+            .vocab
+            .iter()
+            .map(|(key, val)| (*val, key.to_owned()))
+            .collect();
+
+        Ok(CharLevel {
+            vocab: self.config.vocab,
+            vocab_r,
+            unk_token: self.config.unk_token,
+            granularity: self.config.granularity,
+            byte_fallback: self.config.byte_fallback,
+        })
+    }
+}
+
+#[derive(PartialEq, Clone, Eq)]
+pub struct CharLevel {
+    vocab: Vocab,
+    vocab_r: HashMap<u32, String>,
+    pub unk_token: String,
+    /// The unit mapped to an id: code point, grapheme cluster or byte
+    pub granularity: CharGranularity,
+    /// Whether units missing from the vocabulary decompose into their
+    /// `<0xXX>` byte tokens before falling back on the unk token
+    pub byte_fallback: bool,
+}
+
+impl std::fmt::Debug for CharLevel {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.debug_struct("CharLevel")
+            .field("unk_token", &self.unk_token)
+            .field("granularity", &self.granularity)
+            .field("byte_fallback", &self.byte_fallback)
+            .field("vocab", &self.vocab.len())
+            .finish()
+    }
+}
+
+impl CharLevel {
+    pub fn builder() -> CharLevelBuilder {
+        CharLevelBuilder::new()
+    }
+}
+
+impl Default for CharLevel {
+    fn default() -> Self {
+        Self {
+            vocab: Vocab::new(),
+            vocab_r: HashMap::new(),
+            unk_token: String::from("<unk>"),
+            granularity: CharGranularity::default(),
+            byte_fallback: false,
+        }
+    }
+}
+
+impl Model for CharLevel {
+    type Trainer = CharLevelTrainer;
+
+    fn tokenize(&self, sequence: &str) -> Result<Vec<Token>> {
+        let mut tokens = vec![];
+        for (unit, offsets) in self.granularity.split(sequence) {
+            if let Some(id) = self.vocab.get(unit.as_ref()) {
+                tokens.push(Token::new(id, unit.into_owned(), offsets));
+                continue;
+            }
+            if self.byte_fallback {
+                let ids: Option<Vec<_>> = unit
+                    .bytes()
+                    .map(|b| self.vocab.get(&format!("<{:#04X}>", b)))
+                    .collect();
+                if let Some(ids) = ids {
+                    for (i, (id, b)) in ids.into_iter().zip(unit.bytes()).enumerate() {
+                        tokens.push(Token::new(
+                            id,
+                            format!("<{:#04X}>", b),
+                            (offsets.0 + i, offsets.0 + i + 1),
+                        ));
+                    }
+                    continue;
+                }
+            }
+            if let Some(unk_id) = self.vocab.get(&self.unk_token) {
+                tokens.push(Token::new(unk_id, self.unk_token.clone(), offsets));
+            } else {
+                return Err(Box::new(Error::MissingUnkToken));
+            }
+        }
+        Ok(tokens)
+    }
+
+    fn token_to_id(&self, token: &str) -> Option<u32> {
+        self.vocab.get(token)
+    }
+
+    fn id_to_token(&self, id: u32) -> Option<String> {
+        self.vocab_r.get(&id).cloned()
+    }
+
+    fn get_vocab(&self) -> HashMap<String, u32> {
+        (&self.vocab).into()
+    }
+
+    fn get_vocab_iter(&self) -> Box<dyn Iterator<Item = (Cow<'_, str>, u32)> + '_> {
+        let mut entries: Vec<_> = self
+            .vocab
+            .iter()
+            .map(|(token, id)| (token.as_str(), *id))
+            .collect();
+        entries.sort_unstable_by_key(|(_, id)| *id);
+        Box::new(
+            entries
+                .into_iter()
+                .map(|(token, id)| (Cow::Borrowed(token), id)),
+        )
+    }
+
+    fn get_vocab_r(&self) -> Box<dyn Iterator<Item = (u32, Cow<'_, str>)> + '_> {
+        let mut entries: Vec<_> = self
+            .vocab_r
+            .iter()
+            .map(|(id, token)| (*id, token.as_str()))
+            .collect();
+        entries.sort_unstable_by_key(|(id, _)| *id);
+        Box::new(
+            entries
+                .into_iter()
+                .map(|(id, token)| (id, Cow::Borrowed(token))),
+        )
+    }
+
+    fn get_vocab_size(&self) -> usize {
+        self.vocab.len()
+    }
+
+    fn save(&self, folder: &Path, name: Option<&str>) -> Result<Vec<PathBuf>> {
+        let vocab_file_name = match name {
+            Some(name) => format!("{}-vocab.json", name),
+            None => "vocab.json".to_string(),
+        };
+
+        // Write vocab.json
+        let vocab_path: PathBuf = [folder, Path::new(vocab_file_name.as_str())]
+            .iter()
+            .collect();
+        let mut vocab_file = File::create(&vocab_path)?;
+        let order_vocab_iter = OrderedVocabIter::new(&self.vocab_r);
+        let serialized = serde_json::to_string(&order_vocab_iter)?;
+        vocab_file.write_all(serialized.as_bytes())?;
+
+        Ok(vec![vocab_path])
+    }
+
+    fn get_trainer(&self) -> Self::Trainer {
+        let mut trainer = CharLevelTrainer::default();
+        trainer.granularity = self.granularity;
+        trainer
+    }
+
+    fn unk_token(&self) -> Option<String> {
+        Some(self.unk_token.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vocab(tokens: &[&str]) -> Vocab {
+        tokens
+            .iter()
+            .enumerate()
+            .map(|(i, t)| (t.to_string(), i as u32))
+            .collect()
+    }
+
+    #[test]
+    fn test_tokenize_chars() {
+        let charlevel = CharLevelBuilder::default()
+            .vocab(vocab(&["<unk>", "a", "b", "é"]))
+            .build()
+            .unwrap();
+
+        let tokens = charlevel.tokenize("abé").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::new(1u32, "a".into(), (0, 1)),
+                Token::new(2u32, "b".into(), (1, 2)),
+                Token::new(3u32, "é".into(), (2, 4)),
+            ]
+        );
+
+        // Characters missing from the vocabulary fall back on the unk token
+        let tokens = charlevel.tokenize("ac").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::new(1u32, "a".into(), (0, 1)),
+                Token::new(0u32, "<unk>".into(), (1, 2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_graphemes() {
+        let charlevel = CharLevelBuilder::default()
+            .vocab(vocab(&["<unk>", "e\u{301}", "x"]))
+            .granularity(CharGranularity::Grapheme)
+            .build()
+            .unwrap();
+
+        // The combining sequence stays a single token
+        let tokens = charlevel.tokenize("e\u{301}x").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::new(1u32, "e\u{301}".into(), (0, 3)),
+                Token::new(2u32, "x".into(), (3, 4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_bytes_and_fallback() {
+        let charlevel = CharLevelBuilder::default()
+            .vocab(vocab(&["<0x61>", "<0x62>"]))
+            .granularity(CharGranularity::Byte)
+            .build()
+            .unwrap();
+        let tokens = charlevel.tokenize("ab").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::new(0u32, "<0x61>".into(), (0, 1)),
+                Token::new(1u32, "<0x62>".into(), (1, 2)),
+            ]
+        );
+
+        // With byte_fallback, an unknown char decomposes into its byte tokens
+        // instead of the unk token
+        let charlevel = CharLevelBuilder::default()
+            .vocab(vocab(&["<unk>", "a", "<0xC3>", "<0xA9>"]))
+            .byte_fallback(true)
+            .build()
+            .unwrap();
+        let tokens = charlevel.tokenize("aé").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::new(1u32, "a".into(), (0, 1)),
+                Token::new(2u32, "<0xC3>".into(), (1, 2)),
+                Token::new(3u32, "<0xA9>".into(), (2, 3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_missing_unk_token() {
+        let charlevel = CharLevelBuilder::default()
+            .vocab(vocab(&["a"]))
+            .build()
+            .unwrap();
+        let error = charlevel.tokenize("b").err().unwrap();
+        assert!(error.is::<Error>());
+    }
+}
@@ -0,0 +1,126 @@
+use super::{super::OrderedVocabIter, CharLevel, CharLevelBuilder};
+use serde::{
+    de::{MapAccess, Visitor},
+    ser::SerializeStruct,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::collections::HashSet;
+
+impl Serialize for CharLevel {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut model = serializer.serialize_struct("CharLevel", 5)?;
+        let ordered_vocab = OrderedVocabIter::new(&self.vocab_r);
+        model.serialize_field("type", "CharLevel")?;
+        model.serialize_field("granularity", &self.granularity)?;
+        model.serialize_field("unk_token", &self.unk_token)?;
+        model.serialize_field("byte_fallback", &self.byte_fallback)?;
+        model.serialize_field("vocab", &ordered_vocab)?;
+        model.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for CharLevel {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_struct(
+            "CharLevel",
+            &["type", "granularity", "unk_token", "byte_fallback", "vocab"],
+            CharLevelVisitor,
+        )
+    }
+}
+
+struct CharLevelVisitor;
+impl<'de> Visitor<'de> for CharLevelVisitor {
+    type Value = CharLevel;
+
+    fn expecting(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(fmt, "struct CharLevel")
+    }
+
+    fn visit_map<V>(self, mut map: V) -> std::result::Result<Self::Value, V::Error>
+    where
+        V: MapAccess<'de>,
+    {
+        let mut builder = CharLevelBuilder::new();
+        let mut missing_fields = vec!["vocab"].into_iter().collect::<HashSet<_>>();
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_ref() {
+                "vocab" => builder = builder.vocab(map.next_value()?),
+                "unk_token" => builder = builder.unk_token(map.next_value()?),
+                "granularity" => builder = builder.granularity(map.next_value()?),
+                "byte_fallback" => builder = builder.byte_fallback(map.next_value()?),
+                "type" => match map.next_value()? {
+                    "CharLevel" => {}
+                    u => {
+                        return Err(serde::de::Error::invalid_value(
+                            serde::de::Unexpected::Str(u),
+                            &"CharLevel",
+                        ))
+                    }
+                },
+                _ => {}
+            }
+            missing_fields.remove::<str>(&key);
+        }
+
+        if !missing_fields.is_empty() {
+            Err(serde::de::Error::missing_field(
+                missing_fields.iter().next().unwrap(),
+            ))
+        } else {
+            Ok(builder.build().map_err(serde::de::Error::custom)?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::charlevel::{CharGranularity, CharLevel, CharLevelBuilder, Vocab};
+
+    #[test]
+    fn serde() {
+        let cl = CharLevel::default();
+        let cl_s = r#"{"type":"CharLevel","granularity":"Char","unk_token":"<unk>","byte_fallback":false,"vocab":{}}"#;
+
+        assert_eq!(serde_json::to_string(&cl).unwrap(), cl_s);
+        assert_eq!(serde_json::from_str::<CharLevel>(cl_s).unwrap(), cl);
+    }
+
+    #[test]
+    fn serde_roundtrip() {
+        let vocab: Vocab = [("<unk>".into(), 0), ("a".into(), 1)]
+            .iter()
+            .cloned()
+            .collect();
+        let cl = CharLevelBuilder::default()
+            .vocab(vocab)
+            .granularity(CharGranularity::Grapheme)
+            .byte_fallback(true)
+            .build()
+            .unwrap();
+        let cl_s = r#"{"type":"CharLevel","granularity":"Grapheme","unk_token":"<unk>","byte_fallback":true,"vocab":{"<unk>":0,"a":1}}"#;
+        assert_eq!(serde_json::to_string(&cl).unwrap(), cl_s);
+        assert_eq!(serde_json::from_str::<CharLevel>(cl_s).unwrap(), cl);
+    }
+
+    #[test]
+    fn deserialization_should_fail() {
+        let missing_vocab = r#"{"type":"CharLevel","unk_token":"<unk>"}"#;
+        assert!(serde_json::from_str::<CharLevel>(missing_vocab)
+            .unwrap_err()
+            .to_string()
+            .starts_with("missing field `vocab`"));
+
+        let wrong_type = r#"{"type":"WordLevel","vocab":{}}"#;
+        assert!(serde_json::from_str::<CharLevel>(wrong_type)
+            .unwrap_err()
+            .to_string()
+            .starts_with("invalid value: string \"WordLevel\", expected CharLevel"));
+    }
+}
@@ -0,0 +1,327 @@
+use super::super::assign_ids;
+use super::{CharGranularity, CharLevel};
+use crate::utils::parallelism::*;
+use crate::{AddedToken, Result, Trainer, TrainingReport};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::Path;
+
+#[non_exhaustive]
+#[derive(Debug, Clone, Builder, Serialize, Deserialize)]
+pub struct CharLevelTrainer {
+    /// The minimum frequency a unit must have to be part of the vocabulary
+    #[builder(default = "0")]
+    pub min_frequency: u64,
+    /// The target vocabulary size
+    #[builder(default = "1_000")]
+    pub vocab_size: usize,
+    /// A corpus coverage target in `[0, 1]` used instead of `vocab_size` when
+    /// set: units are taken by decreasing frequency until they account for
+    /// this fraction of all the unit occurrences of the corpus, and the
+    /// alphabet takes whatever size that requires, like the SentencePiece
+    /// `character_coverage` option
+    #[builder(default)]
+    #[serde(default)]
+    pub coverage: Option<f64>,
+    /// Whether to show progress while training
+    #[builder(default = "true")]
+    pub show_progress: bool,
+    /// A list of special tokens that the model should know of
+    #[builder(default)]
+    pub special_tokens: Vec<AddedToken>,
+    /// The unit counted over the corpus, applied to the model when training
+    #[builder(default)]
+    #[serde(default)]
+    pub granularity: CharGranularity,
+    /// The units we want absolutely to include, even when the corpus never
+    /// produces them
+    #[builder(default)]
+    #[serde(default)]
+    pub initial_alphabet: Vec<String>,
+    /// Whether [`Trainer::train_with_report`] should produce a [`TrainingReport`]
+    #[builder(default = "false")]
+    #[serde(default)]
+    pub report: bool,
+    /// A previous vocabulary, typically [`crate::Model::get_vocab`] of the
+    /// model being refreshed on new data: units also present in it keep
+    /// their old ids, and only new units get fresh ids appended after the
+    /// highest previous id, so an existing embedding matrix can be reused
+    #[builder(default)]
+    #[serde(default)]
+    pub preserve_ids_from: Option<HashMap<String, u32>>,
+
+    #[builder(default, private)]
+    words: HashMap<String, u64>,
+    #[builder(default, private)]
+    #[serde(default)]
+    validation: Vec<String>,
+}
+
+impl Default for CharLevelTrainer {
+    fn default() -> Self {
+        Self::builder().build().unwrap()
+    }
+}
+
+impl CharLevelTrainer {
+    pub fn builder() -> CharLevelTrainerBuilder {
+        CharLevelTrainerBuilder::default()
+    }
+
+    fn do_train(
+        &self,
+        unit_counts: &HashMap<String, u64>,
+        model: &mut CharLevel,
+    ) -> Result<Vec<AddedToken>> {
+        let mut ordered_counts = unit_counts.iter().collect::<Vec<_>>();
+
+        // Sort the unit counts first by inverse counts and then by unit, in
+        // order to keep the sorting deterministic in case of equal counts
+        let cmp = |l: &(&String, &u64), r: &(&String, &u64)| -> Ordering {
+            let count_comp: Ordering = l.1.cmp(r.1);
+            if count_comp != Ordering::Equal {
+                return count_comp.reverse();
+            }
+            l.0.cmp(r.0)
+        };
+
+        ordered_counts.sort_by(cmp);
+
+        let corpus_units = ordered_counts
+            .into_iter()
+            .filter(|(_, n)| **n >= self.min_frequency);
+        let corpus_units: Box<dyn Iterator<Item = String>> = match self.coverage {
+            // Stop once the selected units cover the target fraction of the
+            // corpus occurrences, including the unit crossing the threshold
+            Some(coverage) => {
+                let total: u64 = unit_counts.values().sum();
+                let target = (coverage.clamp(0.0, 1.0) * total as f64).ceil() as u64;
+                let mut covered = 0u64;
+                Box::new(
+                    corpus_units
+                        .take_while(move |(_, n)| {
+                            let keep = covered < target;
+                            covered += **n;
+                            keep
+                        })
+                        .map(|(u, _)| u.to_owned()),
+                )
+            }
+            None => Box::new(corpus_units.map(|(u, _)| u.to_owned())),
+        };
+        let mut seen = std::collections::HashSet::new();
+        let units = self
+            .special_tokens
+            .iter()
+            .map(|token| token.content.clone())
+            .chain(self.initial_alphabet.iter().cloned())
+            .chain(corpus_units)
+            .filter(|unit| seen.insert(unit.clone()))
+            .take(match self.coverage {
+                Some(_) => usize::MAX,
+                None => self.vocab_size,
+            });
+
+        let char_level = CharLevel::builder()
+            .vocab(assign_ids(units, self.preserve_ids_from.as_ref()).into())
+            .build()?;
+
+        // Transfer the vocab
+        model.vocab = char_level.vocab;
+        model.vocab_r = char_level.vocab_r;
+        model.granularity = self.granularity;
+
+        Ok(self.special_tokens.clone())
+    }
+}
+
+impl Trainer for CharLevelTrainer {
+    type Model = CharLevel;
+
+    /// Train a CharLevel model
+    fn train(&self, model: &mut CharLevel) -> Result<Vec<AddedToken>> {
+        self.do_train(&self.words, model)
+    }
+
+    /// Whether we should show progress
+    fn should_show_progress(&self) -> bool {
+        self.show_progress
+    }
+
+    fn feed<I, S, F>(&mut self, iterator: I, process: F) -> Result<()>
+    where
+        I: Iterator<Item = S> + Send,
+        S: AsRef<str> + Send,
+        F: Fn(&str) -> Result<Vec<String>> + Sync,
+    {
+        let granularity = self.granularity;
+        let words: Result<HashMap<String, u64>> = iterator
+            .maybe_par_bridge()
+            .map(|sequence| {
+                let words = process(sequence.as_ref())?;
+                let mut map = HashMap::new();
+                for word in words {
+                    for (unit, _) in granularity.split(&word) {
+                        map.entry(unit.into_owned())
+                            .and_modify(|c| *c += 1)
+                            .or_insert(1);
+                    }
+                }
+                Ok(map)
+            })
+            .reduce(
+                || Ok(HashMap::new()),
+                |acc, ws| {
+                    let mut acc = acc?;
+                    for (k, v) in ws? {
+                        acc.entry(k).and_modify(|c| *c += v).or_insert(v);
+                    }
+                    Ok(acc)
+                },
+            );
+
+        self.words = words?;
+        Ok(())
+    }
+
+    fn feed_weighted<I, S, F>(&mut self, iterator: I, process: F) -> Result<()>
+    where
+        I: Iterator<Item = (S, f64)> + Send,
+        S: AsRef<str> + Send,
+        F: Fn(&str) -> Result<Vec<String>> + Sync,
+    {
+        let granularity = self.granularity;
+        let words: Result<HashMap<String, f64>> = iterator
+            .maybe_par_bridge()
+            .map(|(sequence, weight)| {
+                let words = process(sequence.as_ref())?;
+                let mut map = HashMap::new();
+                for word in words {
+                    for (unit, _) in granularity.split(&word) {
+                        map.entry(unit.into_owned())
+                            .and_modify(|c| *c += weight)
+                            .or_insert(weight);
+                    }
+                }
+                Ok(map)
+            })
+            .reduce(
+                || Ok(HashMap::new()),
+                |acc, ws| {
+                    let mut acc = acc?;
+                    for (k, v) in ws? {
+                        acc.entry(k).and_modify(|c| *c += v).or_insert(v);
+                    }
+                    Ok(acc)
+                },
+            );
+
+        self.words = words?
+            .into_iter()
+            .filter_map(|(unit, count)| {
+                let count = count.round() as u64;
+                (count > 0).then_some((unit, count))
+            })
+            .collect();
+        Ok(())
+    }
+
+    fn feed_validation<I, S>(&mut self, iterator: I) -> Result<()>
+    where
+        I: Iterator<Item = S> + Send,
+        S: AsRef<str> + Send,
+    {
+        self.validation = iterator.map(|s| s.as_ref().to_owned()).collect();
+        Ok(())
+    }
+
+    fn train_with_report(
+        &self,
+        model: &mut CharLevel,
+    ) -> Result<(Vec<AddedToken>, Option<TrainingReport>)> {
+        let special_tokens = self.do_train(&self.words, model)?;
+        let report = if self.report {
+            Some(TrainingReport::compute(
+                &self.words,
+                &self.validation,
+                model,
+                Some(&model.unk_token),
+            )?)
+        } else {
+            None
+        };
+        Ok((special_tokens, report))
+    }
+
+    fn save_checkpoint(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    fn resume_from_checkpoint(&mut self, path: &Path) -> Result<()> {
+        *self = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Model;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_train() {
+        let mut trainer = CharLevelTrainer {
+            vocab_size: 4,
+            show_progress: false,
+            ..Default::default()
+        };
+        trainer
+            .feed(["abba", "abc"].iter(), |sequence| {
+                Ok(vec![sequence.to_string()])
+            })
+            .unwrap();
+
+        let mut model = CharLevel::default();
+        trainer.train(&mut model).unwrap();
+
+        // The alphabet is ordered by descending frequency, ties broken by
+        // the unit itself
+        let expected_vocab: HashMap<String, u32> =
+            [("a".into(), 0), ("b".into(), 1), ("c".into(), 2)]
+                .iter()
+                .cloned()
+                .collect();
+        assert_eq!(model.get_vocab(), expected_vocab);
+    }
+
+    #[test]
+    fn test_train_bytes() {
+        let mut trainer = CharLevelTrainer {
+            granularity: CharGranularity::Byte,
+            show_progress: false,
+            ..Default::default()
+        };
+        trainer
+            .feed(["aé"].iter(), |sequence| Ok(vec![sequence.to_string()]))
+            .unwrap();
+
+        let mut model = CharLevel::default();
+        trainer.train(&mut model).unwrap();
+
+        // The corpus units are the `<0xXX>` byte tokens, and the granularity
+        // is transferred to the model
+        assert_eq!(model.granularity, CharGranularity::Byte);
+        let expected_vocab: HashMap<String, u32> = [
+            ("<0x61>".into(), 0),
+            ("<0xA9>".into(), 1),
+            ("<0xC3>".into(), 2),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        assert_eq!(model.get_vocab(), expected_vocab);
+    }
+}
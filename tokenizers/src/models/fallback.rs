@@ -0,0 +1,275 @@
+//! A wrapper chaining a primary model with a fallback model for unknown tokens.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use monostate::MustBe;
+use serde::{Deserialize, Serialize};
+
+use crate::{Model, Result, Token, TokenInfo};
+
+type Tag = MustBe!("Fallback");
+
+/// Tokenizes with a primary model and re-tokenizes every token it mapped to
+/// its unknown token with a fallback model, e.g. a `WordLevel` backed by a
+/// character-level `BPE` for out-of-vocabulary words.
+///
+/// The two models keep their own vocabularies and the ids of the fallback are
+/// offset by the size of the primary vocabulary, so the pair forms a single
+/// unified id space and serializes as a single `tokenizer.json`. A token
+/// present in both vocabularies encodes to its primary id, but both of its
+/// ids decode back to it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "FallbackModelHelper<M>")]
+pub struct FallbackModel<M> {
+    #[serde(rename = "type")]
+    type_: Tag,
+    pub primary: M,
+    pub fallback: M,
+}
+
+#[derive(Deserialize)]
+struct FallbackModelHelper<M> {
+    // The "type" field is consumed by the `ModelWrapper` tagged deserializer,
+    // so it cannot be mandatory here
+    #[serde(rename = "type", default)]
+    _type: Option<Tag>,
+    primary: M,
+    fallback: M,
+}
+
+impl<M> From<FallbackModelHelper<M>> for FallbackModel<M> {
+    fn from(helper: FallbackModelHelper<M>) -> Self {
+        Self::new(helper.primary, helper.fallback)
+    }
+}
+
+impl<M> FallbackModel<M> {
+    pub fn new(primary: M, fallback: M) -> Self {
+        Self {
+            type_: MustBe!("Fallback"),
+            primary,
+            fallback,
+        }
+    }
+}
+
+impl<M: Model> FallbackModel<M> {
+    /// The ids of the fallback model are shifted past the primary vocabulary
+    fn offset(&self) -> u32 {
+        self.primary.get_vocab_size() as u32
+    }
+
+    /// The id the primary model assigns to its unknown token, if any
+    fn primary_unk_id(&self) -> Option<u32> {
+        self.primary
+            .unk_token()
+            .and_then(|unk| self.primary.token_to_id(&unk))
+    }
+
+    /// Replace every unknown token of the primary with the fallback tokens
+    /// covering the same span of `sequence`
+    fn apply_fallback(&self, primary_tokens: Vec<Token>, sequence: &str) -> Result<Vec<Token>> {
+        let Some(unk_id) = self.primary_unk_id() else {
+            // Without an unknown token the primary never gives up
+            return Ok(primary_tokens);
+        };
+        let offset = self.offset();
+        let mut tokens = Vec::with_capacity(primary_tokens.len());
+        for token in primary_tokens {
+            if token.id != unk_id {
+                tokens.push(token);
+                continue;
+            }
+            let (start, end) = token.offsets;
+            let fallback_tokens = self.fallback.tokenize(&sequence[start..end])?;
+            if fallback_tokens.is_empty() {
+                tokens.push(token);
+                continue;
+            }
+            tokens.extend(fallback_tokens.into_iter().map(|mut fallback_token| {
+                fallback_token.id += offset;
+                fallback_token.offsets.0 += start;
+                fallback_token.offsets.1 += start;
+                fallback_token
+            }));
+        }
+        Ok(tokens)
+    }
+}
+
+impl<M: Model> Model for FallbackModel<M> {
+    type Trainer = M::Trainer;
+
+    fn tokenize(&self, sequence: &str) -> Result<Vec<Token>> {
+        self.apply_fallback(self.primary.tokenize(sequence)?, sequence)
+    }
+
+    fn tokenize_with_context(
+        &self,
+        prev: Option<&str>,
+        sequence: &str,
+        next: Option<&str>,
+    ) -> Result<Vec<Token>> {
+        let primary_tokens = self.primary.tokenize_with_context(prev, sequence, next)?;
+        self.apply_fallback(primary_tokens, sequence)
+    }
+
+    fn token_to_id(&self, token: &str) -> Option<u32> {
+        self.primary.token_to_id(token).or_else(|| {
+            self.fallback
+                .token_to_id(token)
+                .map(|id| id + self.offset())
+        })
+    }
+
+    fn id_to_token(&self, id: u32) -> Option<String> {
+        let offset = self.offset();
+        if id < offset {
+            self.primary.id_to_token(id)
+        } else {
+            self.fallback.id_to_token(id - offset)
+        }
+    }
+
+    fn get_vocab(&self) -> HashMap<String, u32> {
+        let offset = self.offset();
+        let mut vocab: HashMap<String, u32> = self
+            .fallback
+            .get_vocab()
+            .into_iter()
+            .map(|(token, id)| (token, id + offset))
+            .collect();
+        // A token present in both vocabularies encodes to its primary id
+        vocab.extend(self.primary.get_vocab());
+        vocab
+    }
+
+    fn get_vocab_iter(&self) -> Box<dyn Iterator<Item = (Cow<'_, str>, u32)> + '_> {
+        let offset = self.offset();
+        Box::new(
+            self.primary.get_vocab_iter().chain(
+                self.fallback
+                    .get_vocab_iter()
+                    .map(move |(token, id)| (token, id + offset)),
+            ),
+        )
+    }
+
+    fn get_vocab_r(&self) -> Box<dyn Iterator<Item = (u32, Cow<'_, str>)> + '_> {
+        let offset = self.offset();
+        Box::new(
+            self.primary.get_vocab_r().chain(
+                self.fallback
+                    .get_vocab_r()
+                    .map(move |(id, token)| (id + offset, token)),
+            ),
+        )
+    }
+
+    fn get_vocab_size(&self) -> usize {
+        // The size of the unified id space, counting duplicated tokens twice
+        self.primary.get_vocab_size() + self.fallback.get_vocab_size()
+    }
+
+    fn save(&self, folder: &Path, name: Option<&str>) -> Result<Vec<PathBuf>> {
+        let prefixed = |part: &str| match name {
+            Some(name) => format!("{name}-{part}"),
+            None => part.to_owned(),
+        };
+        let mut paths = self.primary.save(folder, Some(&prefixed("primary")))?;
+        paths.extend(self.fallback.save(folder, Some(&prefixed("fallback")))?);
+        Ok(paths)
+    }
+
+    fn get_trainer(&self) -> Self::Trainer {
+        self.primary.get_trainer()
+    }
+
+    fn token_info(&self, id: u32) -> Option<TokenInfo> {
+        let offset = self.offset();
+        if id < offset {
+            self.primary.token_info(id)
+        } else {
+            self.fallback.token_info(id - offset)
+        }
+    }
+
+    fn unk_token(&self) -> Option<String> {
+        // The chain only outputs an unknown token when the fallback does
+        self.fallback
+            .unk_token()
+            .or_else(|| self.primary.unk_token())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::wordlevel::WordLevel;
+    use crate::models::ModelWrapper;
+
+    fn wordlevel(tokens: &[&str]) -> WordLevel {
+        let vocab: HashMap<String, u32> = tokens
+            .iter()
+            .enumerate()
+            .map(|(i, token)| (token.to_string(), i as u32))
+            .collect();
+        WordLevel::builder()
+            .vocab(vocab.into())
+            .unk_token("<unk>".into())
+            .build()
+            .unwrap()
+    }
+
+    fn model() -> FallbackModel<WordLevel> {
+        FallbackModel::new(
+            wordlevel(&["hello", "<unk>"]),
+            wordlevel(&["world", "<unk>"]),
+        )
+    }
+
+    #[test]
+    fn fallback_on_unk() {
+        let model = model();
+
+        // Known by the primary
+        let tokens = model.tokenize("hello").unwrap();
+        assert_eq!(tokens, vec![Token::new(0, "hello".into(), (0, 5))]);
+
+        // Unknown to the primary, recovered by the fallback with offset ids
+        let tokens = model.tokenize("world").unwrap();
+        assert_eq!(tokens, vec![Token::new(2, "world".into(), (0, 5))]);
+
+        // Unknown to both: the fallback's unknown token, with its offset id
+        let tokens = model.tokenize("!").unwrap();
+        assert_eq!(tokens, vec![Token::new(3, "<unk>".into(), (0, 1))]);
+    }
+
+    #[test]
+    fn unified_id_space() {
+        let model = model();
+        assert_eq!(model.token_to_id("hello"), Some(0));
+        assert_eq!(model.token_to_id("world"), Some(2));
+        // Present in both vocabularies: the primary id wins
+        assert_eq!(model.token_to_id("<unk>"), Some(1));
+        assert_eq!(model.get_vocab()["<unk>"], 1);
+        // But the fallback copy still decodes
+        assert_eq!(model.id_to_token(3), Some("<unk>".into()));
+        assert_eq!(model.get_vocab_size(), 4);
+    }
+
+    #[test]
+    fn serde() {
+        let model = FallbackModel::new(
+            ModelWrapper::from(wordlevel(&["hello", "<unk>"])),
+            ModelWrapper::from(wordlevel(&["world", "<unk>"])),
+        );
+        let wrapper = ModelWrapper::from(model.clone());
+        let serialized = serde_json::to_string(&wrapper).unwrap();
+        let reconstructed: ModelWrapper = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(reconstructed, wrapper);
+        assert_eq!(reconstructed.tokenize("world").unwrap()[0].id, 2);
+    }
+}
@@ -0,0 +1,337 @@
+//! A vocabulary-free model mapping tokens to ids with the hashing trick:
+//! every token is hashed by `n` seeded hash functions into a fixed id space,
+//! so no vocabulary is stored at all. This trades collisions (unrelated
+//! tokens sharing an id) for a model whose memory footprint does not grow
+//! with the corpus, which is what memory-constrained retrieval and hash
+//! embedding setups want.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::tokenizer::{Model, Result, Token};
+
+mod serialization;
+mod trainer;
+
+// Re-export
+pub use trainer::*;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("HashedVocab error: the id space cannot be empty")]
+    EmptyIdSpace,
+    #[error("HashedVocab error: at least one hash seed is required")]
+    NoSeeds,
+    #[error("HashedVocab error: cannot rehash {0} hashes into {1} distinct ids")]
+    TooManyHashes(usize, u32),
+}
+
+/// What to do when two of the seeded hashes of one token land on the same id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CollisionPolicy {
+    /// Keep the duplicate: the token simply contributes that id twice
+    #[default]
+    Allow,
+    /// Probe forward from the colliding id until a free one is found, so a
+    /// token always maps to as many distinct ids as there are seeds
+    Rehash,
+}
+
+struct Config {
+    num_ids: u32,
+    num_hashes: usize,
+    seed: u64,
+    seeds: Option<Vec<u64>>,
+    collision_policy: CollisionPolicy,
+}
+
+/// A `HashedVocabBuilder` can be used to create a `HashedVocab` model with a
+/// custom configuration.
+pub struct HashedVocabBuilder {
+    config: Config,
+}
+
+impl Default for HashedVocabBuilder {
+    fn default() -> Self {
+        Self {
+            config: Config {
+                num_ids: 1 << 20,
+                num_hashes: 1,
+                seed: 0,
+                seeds: None,
+                collision_policy: CollisionPolicy::default(),
+            },
+        }
+    }
+}
+
+impl HashedVocabBuilder {
+    /// Construct a new `HashedVocabBuilder`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the size of the id space tokens are hashed into.
+    #[must_use]
+    pub fn num_ids(mut self, num_ids: u32) -> Self {
+        self.config.num_ids = num_ids;
+        self
+    }
+
+    /// Set the number of hash functions, each mapping a token to one id. The
+    /// seeds are derived deterministically from the base seed.
+    #[must_use]
+    pub fn num_hashes(mut self, num_hashes: usize) -> Self {
+        self.config.num_hashes = num_hashes;
+        self
+    }
+
+    /// Set the base seed the hash seeds are derived from.
+    #[must_use]
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.seed = seed;
+        self
+    }
+
+    /// Set the hash seeds explicitly, overriding `num_hashes` and `seed`.
+    #[must_use]
+    pub fn seeds(mut self, seeds: Vec<u64>) -> Self {
+        self.config.seeds = Some(seeds);
+        self
+    }
+
+    /// Set what happens when two hashes of one token collide.
+    #[must_use]
+    pub fn collision_policy(mut self, collision_policy: CollisionPolicy) -> Self {
+        self.config.collision_policy = collision_policy;
+        self
+    }
+
+    /// Constructs a `HashedVocab` model that uses the `HashedVocabBuilder`'s
+    /// configuration.
+    pub fn build(self) -> Result<HashedVocab> {
+        if self.config.num_ids == 0 {
+            return Err(Box::new(Error::EmptyIdSpace));
+        }
+        let Config {
+            num_ids,
+            num_hashes,
+            seed,
+            seeds,
+            collision_policy,
+        } = self.config;
+        let seeds = seeds.unwrap_or_else(|| {
+            // Derive one seed per hash from the base seed with splitmix64
+            let mut state = seed;
+            (0..num_hashes)
+                .map(|_| {
+                    state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+                    let mut z = state;
+                    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+                    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+                    z ^ (z >> 31)
+                })
+                .collect()
+        });
+        if seeds.is_empty() {
+            return Err(Box::new(Error::NoSeeds));
+        }
+        if collision_policy == CollisionPolicy::Rehash && seeds.len() > num_ids as usize {
+            return Err(Box::new(Error::TooManyHashes(seeds.len(), num_ids)));
+        }
+        Ok(HashedVocab {
+            num_ids,
+            seeds,
+            collision_policy,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashedVocab {
+    num_ids: u32,
+    seeds: Vec<u64>,
+    collision_policy: CollisionPolicy,
+}
+
+impl Default for HashedVocab {
+    fn default() -> Self {
+        Self::builder().build().unwrap()
+    }
+}
+
+/// A seeded FNV-1a hash over the token bytes. Implemented here instead of
+/// relying on `DefaultHasher`, whose output may change between Rust releases:
+/// hashed ids index persisted embedding tables, so they have to be stable
+/// across platforms and releases.
+fn fnv1a(seed: u64, token: &str) -> u64 {
+    let mut state = 0xCBF2_9CE4_8422_2325u64;
+    for byte in seed.to_le_bytes().iter().chain(token.as_bytes()) {
+        state ^= u64::from(*byte);
+        state = state.wrapping_mul(0x0000_0100_0000_01B3);
+    }
+    state
+}
+
+impl HashedVocab {
+    pub fn builder() -> HashedVocabBuilder {
+        HashedVocabBuilder::new()
+    }
+
+    /// The ids of the given token, one per seed, with the collision policy
+    /// applied.
+    pub fn ids(&self, token: &str) -> Vec<u32> {
+        let mut ids = Vec::with_capacity(self.seeds.len());
+        for seed in &self.seeds {
+            let mut id = (fnv1a(*seed, token) % u64::from(self.num_ids)) as u32;
+            if self.collision_policy == CollisionPolicy::Rehash {
+                while ids.contains(&id) {
+                    id = (id + 1) % self.num_ids;
+                }
+            }
+            ids.push(id);
+        }
+        ids
+    }
+}
+
+impl Model for HashedVocab {
+    type Trainer = HashedVocabTrainer;
+
+    fn tokenize(&self, sequence: &str) -> Result<Vec<Token>> {
+        if sequence.is_empty() {
+            return Ok(vec![]);
+        }
+        Ok(self
+            .ids(sequence)
+            .into_iter()
+            .map(|id| Token::new(id, sequence.to_owned(), (0, sequence.len())))
+            .collect())
+    }
+
+    fn token_to_id(&self, token: &str) -> Option<u32> {
+        Some(self.ids(token)[0])
+    }
+
+    fn id_to_token(&self, _id: u32) -> Option<String> {
+        // Hashing is one-way: there is no token to recover
+        None
+    }
+
+    fn get_vocab(&self) -> HashMap<String, u32> {
+        HashMap::new()
+    }
+
+    fn get_vocab_iter(&self) -> Box<dyn Iterator<Item = (Cow<'_, str>, u32)> + '_> {
+        Box::new(std::iter::empty())
+    }
+
+    fn get_vocab_r(&self) -> Box<dyn Iterator<Item = (u32, Cow<'_, str>)> + '_> {
+        Box::new(std::iter::empty())
+    }
+
+    fn get_vocab_size(&self) -> usize {
+        // The size of the id space, which is what embedding tables should be
+        // dimensioned to
+        self.num_ids as usize
+    }
+
+    fn save(&self, folder: &Path, name: Option<&str>) -> Result<Vec<PathBuf>> {
+        let name = match name {
+            Some(name) => format!("{}-hashedvocab.json", name),
+            None => "hashedvocab.json".to_string(),
+        };
+        let mut fullpath = PathBuf::new();
+        fullpath.push(folder);
+        fullpath.push(name);
+        let string = serde_json::to_string_pretty(self)?;
+        std::fs::write(&fullpath, string)?;
+        Ok(vec![fullpath])
+    }
+
+    fn get_trainer(&self) -> Self::Trainer {
+        HashedVocabTrainer::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_is_deterministic() {
+        let model = HashedVocab::builder()
+            .num_ids(1 << 16)
+            .num_hashes(2)
+            .seed(42)
+            .build()
+            .unwrap();
+
+        let tokens = model.tokenize("hello").unwrap();
+        assert_eq!(tokens.len(), 2);
+        assert!(tokens.iter().all(|token| token.value == "hello"));
+        assert!(tokens.iter().all(|token| token.offsets == (0, 5)));
+        assert!(tokens.iter().all(|token| token.id < (1 << 16)));
+
+        // Same token, same ids; the seeds are derived deterministically
+        let again = HashedVocab::builder()
+            .num_ids(1 << 16)
+            .num_hashes(2)
+            .seed(42)
+            .build()
+            .unwrap();
+        assert_eq!(again.tokenize("hello").unwrap(), tokens);
+        assert_eq!(model.token_to_id("hello"), Some(tokens[0].id));
+    }
+
+    #[test]
+    fn test_collision_policy() {
+        // A single id forces every hash onto the same slot
+        let model = HashedVocab::builder()
+            .num_ids(4)
+            .seeds(vec![1, 1, 1])
+            .build()
+            .unwrap();
+        let ids = model.ids("token");
+        assert_eq!(ids[0], ids[1]);
+        assert_eq!(ids[1], ids[2]);
+
+        let model = HashedVocab::builder()
+            .num_ids(4)
+            .seeds(vec![1, 1, 1])
+            .collision_policy(CollisionPolicy::Rehash)
+            .build()
+            .unwrap();
+        let mut ids = model.ids("token");
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(ids.len(), 3);
+    }
+
+    #[test]
+    fn test_builder_validation() {
+        assert!(HashedVocab::builder()
+            .num_ids(0)
+            .build()
+            .err()
+            .unwrap()
+            .is::<Error>());
+        assert!(HashedVocab::builder()
+            .seeds(vec![])
+            .build()
+            .err()
+            .unwrap()
+            .is::<Error>());
+        assert!(HashedVocab::builder()
+            .num_ids(2)
+            .num_hashes(3)
+            .collision_policy(CollisionPolicy::Rehash)
+            .build()
+            .err()
+            .unwrap()
+            .is::<Error>());
+    }
+}
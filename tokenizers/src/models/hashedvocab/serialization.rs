@@ -0,0 +1,113 @@
+use super::{CollisionPolicy, HashedVocab, HashedVocabBuilder};
+use serde::{
+    de::{MapAccess, Visitor},
+    ser::SerializeStruct,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::collections::HashSet;
+
+impl Serialize for HashedVocab {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut model = serializer.serialize_struct("HashedVocab", 4)?;
+        model.serialize_field("type", "HashedVocab")?;
+        model.serialize_field("num_ids", &self.num_ids)?;
+        model.serialize_field("seeds", &self.seeds)?;
+        model.serialize_field("collision_policy", &self.collision_policy)?;
+        model.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for HashedVocab {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_struct(
+            "HashedVocab",
+            &["type", "num_ids", "seeds", "collision_policy"],
+            HashedVocabVisitor,
+        )
+    }
+}
+
+struct HashedVocabVisitor;
+impl<'de> Visitor<'de> for HashedVocabVisitor {
+    type Value = HashedVocab;
+
+    fn expecting(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(fmt, "struct HashedVocab")
+    }
+
+    fn visit_map<V>(self, mut map: V) -> std::result::Result<Self::Value, V::Error>
+    where
+        V: MapAccess<'de>,
+    {
+        let mut builder = HashedVocabBuilder::new();
+        let mut missing_fields = vec!["num_ids", "seeds"].into_iter().collect::<HashSet<_>>();
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_ref() {
+                "num_ids" => builder = builder.num_ids(map.next_value()?),
+                "seeds" => builder = builder.seeds(map.next_value()?),
+                "collision_policy" => {
+                    builder = builder.collision_policy(map.next_value::<CollisionPolicy>()?)
+                }
+                "type" => match map.next_value()? {
+                    "HashedVocab" => {}
+                    u => {
+                        return Err(serde::de::Error::invalid_value(
+                            serde::de::Unexpected::Str(u),
+                            &"HashedVocab",
+                        ))
+                    }
+                },
+                _ => {}
+            }
+            missing_fields.remove::<str>(&key);
+        }
+
+        if !missing_fields.is_empty() {
+            Err(serde::de::Error::missing_field(
+                missing_fields.iter().next().unwrap(),
+            ))
+        } else {
+            Ok(builder.build().map_err(serde::de::Error::custom)?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serde() {
+        let model = HashedVocab::builder()
+            .num_ids(1 << 16)
+            .seeds(vec![7, 13])
+            .build()
+            .unwrap();
+        let model_s =
+            r#"{"type":"HashedVocab","num_ids":65536,"seeds":[7,13],"collision_policy":"Allow"}"#;
+
+        assert_eq!(serde_json::to_string(&model).unwrap(), model_s);
+        assert_eq!(serde_json::from_str::<HashedVocab>(model_s).unwrap(), model);
+    }
+
+    #[test]
+    fn deserialization_should_fail() {
+        let missing_seeds = r#"{"type":"HashedVocab","num_ids":65536}"#;
+        assert!(serde_json::from_str::<HashedVocab>(missing_seeds)
+            .unwrap_err()
+            .to_string()
+            .starts_with("missing field `seeds`"));
+
+        let wrong_type = r#"{"type":"BPE","num_ids":65536,"seeds":[7]}"#;
+        assert!(serde_json::from_str::<HashedVocab>(wrong_type)
+            .unwrap_err()
+            .to_string()
+            .starts_with("invalid value: string \"BPE\", expected HashedVocab"));
+    }
+}
@@ -0,0 +1,92 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::HashedVocab;
+use crate::{AddedToken, Result, Trainer};
+
+/// A trainer for [`HashedVocab`], in name only: since the model stores no
+/// vocabulary, there is nothing to learn from a corpus. Feeding is a no-op
+/// and training just hands the configured special tokens to the tokenizer,
+/// so `train_from_files` and friends keep working on a hashed pipeline.
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, Builder, Serialize, Deserialize)]
+pub struct HashedVocabTrainer {
+    /// Whether to show progress while training
+    #[builder(default = "false")]
+    #[serde(default)]
+    pub show_progress: bool,
+    /// A list of special tokens that the model should know of
+    #[builder(default)]
+    pub special_tokens: Vec<AddedToken>,
+}
+
+impl HashedVocabTrainer {
+    pub fn builder() -> HashedVocabTrainerBuilder {
+        HashedVocabTrainerBuilder::default()
+    }
+}
+
+impl Trainer for HashedVocabTrainer {
+    type Model = HashedVocab;
+
+    fn should_show_progress(&self) -> bool {
+        self.show_progress
+    }
+
+    fn train(&self, _model: &mut HashedVocab) -> Result<Vec<AddedToken>> {
+        Ok(self.special_tokens.clone())
+    }
+
+    fn feed<I, S, F>(&mut self, _iterator: I, _process: F) -> Result<()>
+    where
+        I: Iterator<Item = S> + Send,
+        S: AsRef<str> + Send,
+        F: Fn(&str) -> Result<Vec<String>> + Sync,
+    {
+        Ok(())
+    }
+
+    fn feed_weighted<I, S, F>(&mut self, _iterator: I, _process: F) -> Result<()>
+    where
+        I: Iterator<Item = (S, f64)> + Send,
+        S: AsRef<str> + Send,
+        F: Fn(&str) -> Result<Vec<String>> + Sync,
+    {
+        Ok(())
+    }
+
+    fn save_checkpoint(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    fn resume_from_checkpoint(&mut self, path: &Path) -> Result<()> {
+        *self = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_train_is_a_no_op() {
+        let mut trainer = HashedVocabTrainer {
+            special_tokens: vec![AddedToken::from("<pad>", true)],
+            ..Default::default()
+        };
+        trainer
+            .feed(["some text"].iter(), |sequence| {
+                Ok(vec![sequence.to_string()])
+            })
+            .unwrap();
+
+        let mut model = HashedVocab::default();
+        let before = model.clone();
+        let special_tokens = trainer.train(&mut model).unwrap();
+        assert_eq!(model, before);
+        assert_eq!(special_tokens, vec![AddedToken::from("<pad>", true)]);
+    }
+}
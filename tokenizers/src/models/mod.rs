@@ -1,20 +1,32 @@
 //! Popular tokenizer models.
 
 pub mod bpe;
+pub mod charlevel;
+pub mod fallback;
+pub mod hashedvocab;
+pub mod morfessor;
+pub mod remapped;
 pub mod unigram;
+pub mod vocab;
 pub mod wordlevel;
 pub mod wordpiece;
 
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::models::bpe::{BpeTrainer, BPE};
+use crate::models::charlevel::{CharLevel, CharLevelTrainer};
+use crate::models::fallback::FallbackModel;
+use crate::models::hashedvocab::{HashedVocab, HashedVocabTrainer};
+use crate::models::morfessor::{Morfessor, MorfessorTrainer};
+use crate::models::remapped::RemappedModel;
 use crate::models::unigram::{Unigram, UnigramTrainer};
 use crate::models::wordlevel::{WordLevel, WordLevelTrainer};
 use crate::models::wordpiece::{WordPiece, WordPieceTrainer};
-use crate::{AddedToken, Model, Result, Token, Trainer};
+use crate::{AddedToken, Model, Result, Token, TokenInfo, Trainer, TrainingReport, VocabMask};
 
 /// Wraps a vocab mapping (ID -> token) to a struct that will be serialized in order
 /// of token ID, smallest to largest.
@@ -57,6 +69,36 @@ impl<'a> Serialize for OrderedVocabIter<'a> {
     }
 }
 
+/// Assign an id to each of the selected tokens, in order. Without a previous
+/// vocabulary the ids are simply the positions; with one, tokens it already
+/// contains keep their old ids and the others get fresh ids appended after
+/// its highest id, so an embedding matrix indexed by the old vocabulary can
+/// be reused
+pub(crate) fn assign_ids(
+    tokens: impl Iterator<Item = String>,
+    preserve_ids_from: Option<&HashMap<String, u32>>,
+) -> HashMap<String, u32> {
+    match preserve_ids_from {
+        Some(old) => {
+            let mut next_id = old.values().max().map(|max| max + 1).unwrap_or(0);
+            tokens
+                .map(|token| {
+                    let id = old.get(&token).copied().unwrap_or_else(|| {
+                        let id = next_id;
+                        next_id += 1;
+                        id
+                    });
+                    (token, id)
+                })
+                .collect()
+        }
+        None => tokens
+            .enumerate()
+            .map(|(i, token)| (token, i as u32))
+            .collect(),
+    }
+}
+
 #[derive(Serialize, Debug, PartialEq, Clone)]
 #[serde(untagged)]
 pub enum ModelWrapper {
@@ -66,6 +108,11 @@ pub enum ModelWrapper {
     WordPiece(WordPiece),
     WordLevel(WordLevel),
     Unigram(Unigram),
+    CharLevel(CharLevel),
+    Morfessor(Morfessor),
+    HashedVocab(HashedVocab),
+    Remapped(Box<RemappedModel<ModelWrapper>>),
+    Fallback(Box<FallbackModel<ModelWrapper>>),
 }
 
 impl<'de> Deserialize<'de> for ModelWrapper {
@@ -86,6 +133,11 @@ impl<'de> Deserialize<'de> for ModelWrapper {
             WordPiece,
             WordLevel,
             Unigram,
+            CharLevel,
+            Morfessor,
+            HashedVocab,
+            Remapped,
+            Fallback,
         }
 
         #[derive(Deserialize)]
@@ -121,6 +173,21 @@ impl<'de> Deserialize<'de> for ModelWrapper {
                 EnumType::Unigram => ModelWrapper::Unigram(
                     serde_json::from_value(model.rest).map_err(serde::de::Error::custom)?,
                 ),
+                EnumType::CharLevel => ModelWrapper::CharLevel(
+                    serde_json::from_value(model.rest).map_err(serde::de::Error::custom)?,
+                ),
+                EnumType::Morfessor => ModelWrapper::Morfessor(
+                    serde_json::from_value(model.rest).map_err(serde::de::Error::custom)?,
+                ),
+                EnumType::HashedVocab => ModelWrapper::HashedVocab(
+                    serde_json::from_value(model.rest).map_err(serde::de::Error::custom)?,
+                ),
+                EnumType::Remapped => ModelWrapper::Remapped(
+                    serde_json::from_value(model.rest).map_err(serde::de::Error::custom)?,
+                ),
+                EnumType::Fallback => ModelWrapper::Fallback(
+                    serde_json::from_value(model.rest).map_err(serde::de::Error::custom)?,
+                ),
             },
             ModelHelper::Legacy(value) => {
                 let untagged = serde_json::from_value(value).map_err(serde::de::Error::custom)?;
@@ -139,6 +206,21 @@ impl_enum_from!(WordLevel, ModelWrapper, WordLevel);
 impl_enum_from!(WordPiece, ModelWrapper, WordPiece);
 impl_enum_from!(BPE, ModelWrapper, BPE);
 impl_enum_from!(Unigram, ModelWrapper, Unigram);
+impl_enum_from!(CharLevel, ModelWrapper, CharLevel);
+impl_enum_from!(Morfessor, ModelWrapper, Morfessor);
+impl_enum_from!(HashedVocab, ModelWrapper, HashedVocab);
+
+impl From<RemappedModel<ModelWrapper>> for ModelWrapper {
+    fn from(model: RemappedModel<ModelWrapper>) -> Self {
+        Self::Remapped(Box::new(model))
+    }
+}
+
+impl From<FallbackModel<ModelWrapper>> for ModelWrapper {
+    fn from(model: FallbackModel<ModelWrapper>) -> Self {
+        Self::Fallback(Box::new(model))
+    }
+}
 
 impl Model for ModelWrapper {
     type Trainer = TrainerWrapper;
@@ -149,6 +231,44 @@ impl Model for ModelWrapper {
             Self::WordPiece(t) => t.tokenize(tokens),
             Self::BPE(t) => t.tokenize(tokens),
             Self::Unigram(t) => t.tokenize(tokens),
+            Self::CharLevel(t) => t.tokenize(tokens),
+            Self::Morfessor(t) => t.tokenize(tokens),
+            Self::HashedVocab(t) => t.tokenize(tokens),
+            Self::Remapped(t) => t.tokenize(tokens),
+            Self::Fallback(t) => t.tokenize(tokens),
+        }
+    }
+
+    fn tokenize_with_context(
+        &self,
+        prev: Option<&str>,
+        tokens: &str,
+        next: Option<&str>,
+    ) -> Result<Vec<Token>> {
+        match self {
+            Self::WordLevel(t) => t.tokenize_with_context(prev, tokens, next),
+            Self::WordPiece(t) => t.tokenize_with_context(prev, tokens, next),
+            Self::BPE(t) => t.tokenize_with_context(prev, tokens, next),
+            Self::Unigram(t) => t.tokenize_with_context(prev, tokens, next),
+            Self::CharLevel(t) => t.tokenize_with_context(prev, tokens, next),
+            Self::Morfessor(t) => t.tokenize_with_context(prev, tokens, next),
+            Self::HashedVocab(t) => t.tokenize_with_context(prev, tokens, next),
+            Self::Remapped(t) => t.tokenize_with_context(prev, tokens, next),
+            Self::Fallback(t) => t.tokenize_with_context(prev, tokens, next),
+        }
+    }
+
+    fn tokenize_constrained(&self, tokens: &str, allowed: &VocabMask) -> Result<Vec<Token>> {
+        match self {
+            Self::WordLevel(t) => t.tokenize_constrained(tokens, allowed),
+            Self::WordPiece(t) => t.tokenize_constrained(tokens, allowed),
+            Self::BPE(t) => t.tokenize_constrained(tokens, allowed),
+            Self::Unigram(t) => t.tokenize_constrained(tokens, allowed),
+            Self::CharLevel(t) => t.tokenize_constrained(tokens, allowed),
+            Self::Morfessor(t) => t.tokenize_constrained(tokens, allowed),
+            Self::HashedVocab(t) => t.tokenize_constrained(tokens, allowed),
+            Self::Remapped(t) => t.tokenize_constrained(tokens, allowed),
+            Self::Fallback(t) => t.tokenize_constrained(tokens, allowed),
         }
     }
 
@@ -158,6 +278,11 @@ impl Model for ModelWrapper {
             Self::WordPiece(t) => t.token_to_id(token),
             Self::BPE(t) => t.token_to_id(token),
             Self::Unigram(t) => t.token_to_id(token),
+            Self::CharLevel(t) => t.token_to_id(token),
+            Self::Morfessor(t) => t.token_to_id(token),
+            Self::HashedVocab(t) => t.token_to_id(token),
+            Self::Remapped(t) => t.token_to_id(token),
+            Self::Fallback(t) => t.token_to_id(token),
         }
     }
 
@@ -167,6 +292,11 @@ impl Model for ModelWrapper {
             Self::WordPiece(t) => t.id_to_token(id),
             Self::BPE(t) => t.id_to_token(id),
             Self::Unigram(t) => t.id_to_token(id),
+            Self::CharLevel(t) => t.id_to_token(id),
+            Self::Morfessor(t) => t.id_to_token(id),
+            Self::HashedVocab(t) => t.id_to_token(id),
+            Self::Remapped(t) => t.id_to_token(id),
+            Self::Fallback(t) => t.id_to_token(id),
         }
     }
 
@@ -176,6 +306,39 @@ impl Model for ModelWrapper {
             Self::WordPiece(t) => t.get_vocab(),
             Self::BPE(t) => t.get_vocab(),
             Self::Unigram(t) => t.get_vocab(),
+            Self::CharLevel(t) => t.get_vocab(),
+            Self::Morfessor(t) => t.get_vocab(),
+            Self::HashedVocab(t) => t.get_vocab(),
+            Self::Remapped(t) => t.get_vocab(),
+            Self::Fallback(t) => t.get_vocab(),
+        }
+    }
+
+    fn get_vocab_iter(&self) -> Box<dyn Iterator<Item = (Cow<'_, str>, u32)> + '_> {
+        match self {
+            Self::WordLevel(t) => t.get_vocab_iter(),
+            Self::WordPiece(t) => t.get_vocab_iter(),
+            Self::BPE(t) => t.get_vocab_iter(),
+            Self::Unigram(t) => t.get_vocab_iter(),
+            Self::CharLevel(t) => t.get_vocab_iter(),
+            Self::Morfessor(t) => t.get_vocab_iter(),
+            Self::HashedVocab(t) => t.get_vocab_iter(),
+            Self::Remapped(t) => t.get_vocab_iter(),
+            Self::Fallback(t) => t.get_vocab_iter(),
+        }
+    }
+
+    fn get_vocab_r(&self) -> Box<dyn Iterator<Item = (u32, Cow<'_, str>)> + '_> {
+        match self {
+            Self::WordLevel(t) => t.get_vocab_r(),
+            Self::WordPiece(t) => t.get_vocab_r(),
+            Self::BPE(t) => t.get_vocab_r(),
+            Self::Unigram(t) => t.get_vocab_r(),
+            Self::CharLevel(t) => t.get_vocab_r(),
+            Self::Morfessor(t) => t.get_vocab_r(),
+            Self::HashedVocab(t) => t.get_vocab_r(),
+            Self::Remapped(t) => t.get_vocab_r(),
+            Self::Fallback(t) => t.get_vocab_r(),
         }
     }
 
@@ -185,6 +348,11 @@ impl Model for ModelWrapper {
             Self::WordPiece(t) => t.get_vocab_size(),
             Self::BPE(t) => t.get_vocab_size(),
             Self::Unigram(t) => t.get_vocab_size(),
+            Self::CharLevel(t) => t.get_vocab_size(),
+            Self::Morfessor(t) => t.get_vocab_size(),
+            Self::HashedVocab(t) => t.get_vocab_size(),
+            Self::Remapped(t) => t.get_vocab_size(),
+            Self::Fallback(t) => t.get_vocab_size(),
         }
     }
 
@@ -194,6 +362,11 @@ impl Model for ModelWrapper {
             Self::WordPiece(t) => t.save(folder, name),
             Self::BPE(t) => t.save(folder, name),
             Self::Unigram(t) => t.save(folder, name),
+            Self::CharLevel(t) => t.save(folder, name),
+            Self::Morfessor(t) => t.save(folder, name),
+            Self::HashedVocab(t) => t.save(folder, name),
+            Self::Remapped(t) => t.save(folder, name),
+            Self::Fallback(t) => t.save(folder, name),
         }
     }
 
@@ -203,6 +376,53 @@ impl Model for ModelWrapper {
             Self::WordPiece(t) => t.get_trainer().into(),
             Self::BPE(t) => t.get_trainer().into(),
             Self::Unigram(t) => t.get_trainer().into(),
+            Self::CharLevel(t) => t.get_trainer().into(),
+            Self::Morfessor(t) => t.get_trainer().into(),
+            Self::HashedVocab(t) => t.get_trainer().into(),
+            Self::Remapped(t) => t.get_trainer(),
+            Self::Fallback(t) => t.get_trainer(),
+        }
+    }
+
+    fn token_info(&self, id: u32) -> Option<TokenInfo> {
+        match self {
+            Self::WordLevel(t) => t.token_info(id),
+            Self::WordPiece(t) => t.token_info(id),
+            Self::BPE(t) => t.token_info(id),
+            Self::Unigram(t) => t.token_info(id),
+            Self::CharLevel(t) => t.token_info(id),
+            Self::Morfessor(t) => t.token_info(id),
+            Self::HashedVocab(t) => t.token_info(id),
+            Self::Remapped(t) => t.token_info(id),
+            Self::Fallback(t) => t.token_info(id),
+        }
+    }
+
+    fn token_scores(&self, ids: &[u32]) -> Option<Vec<Option<f64>>> {
+        match self {
+            Self::WordLevel(t) => t.token_scores(ids),
+            Self::WordPiece(t) => t.token_scores(ids),
+            Self::BPE(t) => t.token_scores(ids),
+            Self::Unigram(t) => t.token_scores(ids),
+            Self::CharLevel(t) => t.token_scores(ids),
+            Self::Morfessor(t) => t.token_scores(ids),
+            Self::HashedVocab(t) => t.token_scores(ids),
+            Self::Remapped(t) => t.token_scores(ids),
+            Self::Fallback(t) => t.token_scores(ids),
+        }
+    }
+
+    fn unk_token(&self) -> Option<String> {
+        match self {
+            Self::WordLevel(t) => t.unk_token(),
+            Self::WordPiece(t) => t.unk_token(),
+            Self::BPE(t) => t.unk_token(),
+            Self::Unigram(t) => t.unk_token(),
+            Self::CharLevel(t) => t.unk_token(),
+            Self::Morfessor(t) => t.unk_token(),
+            Self::HashedVocab(t) => t.unk_token(),
+            Self::Remapped(t) => t.unk_token(),
+            Self::Fallback(t) => t.unk_token(),
         }
     }
 }
@@ -213,6 +433,9 @@ pub enum TrainerWrapper {
     WordPieceTrainer(WordPieceTrainer),
     WordLevelTrainer(WordLevelTrainer),
     UnigramTrainer(UnigramTrainer),
+    CharLevelTrainer(CharLevelTrainer),
+    MorfessorTrainer(MorfessorTrainer),
+    HashedVocabTrainer(HashedVocabTrainer),
 }
 
 impl Trainer for TrainerWrapper {
@@ -224,6 +447,9 @@ impl Trainer for TrainerWrapper {
             Self::WordPieceTrainer(wpt) => wpt.should_show_progress(),
             Self::WordLevelTrainer(wpt) => wpt.should_show_progress(),
             Self::UnigramTrainer(wpt) => wpt.should_show_progress(),
+            Self::CharLevelTrainer(wpt) => wpt.should_show_progress(),
+            Self::MorfessorTrainer(t) => t.should_show_progress(),
+            Self::HashedVocabTrainer(t) => t.should_show_progress(),
         }
     }
 
@@ -245,6 +471,18 @@ impl Trainer for TrainerWrapper {
                 ModelWrapper::Unigram(u) => t.train(u),
                 _ => Err("UnigramTrainer can only train a Unigram".into()),
             },
+            Self::CharLevelTrainer(t) => match model {
+                ModelWrapper::CharLevel(cl) => t.train(cl),
+                _ => Err("CharLevelTrainer can only train a CharLevel".into()),
+            },
+            Self::MorfessorTrainer(t) => match model {
+                ModelWrapper::Morfessor(m) => t.train(m),
+                _ => Err("MorfessorTrainer can only train a Morfessor".into()),
+            },
+            Self::HashedVocabTrainer(t) => match model {
+                ModelWrapper::HashedVocab(m) => t.train(m),
+                _ => Err("HashedVocabTrainer can only train a HashedVocab".into()),
+            },
         }
     }
 
@@ -259,6 +497,102 @@ impl Trainer for TrainerWrapper {
             Self::WordPieceTrainer(wpt) => wpt.feed(iterator, process),
             Self::WordLevelTrainer(wpt) => wpt.feed(iterator, process),
             Self::UnigramTrainer(wpt) => wpt.feed(iterator, process),
+            Self::CharLevelTrainer(wpt) => wpt.feed(iterator, process),
+            Self::MorfessorTrainer(t) => t.feed(iterator, process),
+            Self::HashedVocabTrainer(t) => t.feed(iterator, process),
+        }
+    }
+
+    fn feed_weighted<I, S, F>(&mut self, iterator: I, process: F) -> Result<()>
+    where
+        I: Iterator<Item = (S, f64)> + Send,
+        S: AsRef<str> + Send,
+        F: Fn(&str) -> Result<Vec<String>> + Sync,
+    {
+        match self {
+            Self::BpeTrainer(bpe) => bpe.feed_weighted(iterator, process),
+            Self::WordPieceTrainer(wpt) => wpt.feed_weighted(iterator, process),
+            Self::WordLevelTrainer(wpt) => wpt.feed_weighted(iterator, process),
+            Self::UnigramTrainer(wpt) => wpt.feed_weighted(iterator, process),
+            Self::CharLevelTrainer(wpt) => wpt.feed_weighted(iterator, process),
+            Self::MorfessorTrainer(t) => t.feed_weighted(iterator, process),
+            Self::HashedVocabTrainer(t) => t.feed_weighted(iterator, process),
+        }
+    }
+
+    fn feed_validation<I, S>(&mut self, iterator: I) -> Result<()>
+    where
+        I: Iterator<Item = S> + Send,
+        S: AsRef<str> + Send,
+    {
+        match self {
+            Self::BpeTrainer(bpe) => bpe.feed_validation(iterator),
+            Self::WordPieceTrainer(wpt) => wpt.feed_validation(iterator),
+            Self::WordLevelTrainer(wpt) => wpt.feed_validation(iterator),
+            Self::UnigramTrainer(wpt) => wpt.feed_validation(iterator),
+            Self::CharLevelTrainer(wpt) => wpt.feed_validation(iterator),
+            Self::MorfessorTrainer(t) => t.feed_validation(iterator),
+            Self::HashedVocabTrainer(t) => t.feed_validation(iterator),
+        }
+    }
+
+    fn train_with_report(
+        &self,
+        model: &mut ModelWrapper,
+    ) -> Result<(Vec<AddedToken>, Option<TrainingReport>)> {
+        match self {
+            Self::BpeTrainer(t) => match model {
+                ModelWrapper::BPE(bpe) => t.train_with_report(bpe),
+                _ => Err("BpeTrainer can only train a BPE".into()),
+            },
+            Self::WordPieceTrainer(t) => match model {
+                ModelWrapper::WordPiece(wp) => t.train_with_report(wp),
+                _ => Err("WordPieceTrainer can only train a WordPiece".into()),
+            },
+            Self::WordLevelTrainer(t) => match model {
+                ModelWrapper::WordLevel(wl) => t.train_with_report(wl),
+                _ => Err("WordLevelTrainer can only train a WordLevel".into()),
+            },
+            Self::UnigramTrainer(t) => match model {
+                ModelWrapper::Unigram(u) => t.train_with_report(u),
+                _ => Err("UnigramTrainer can only train a Unigram".into()),
+            },
+            Self::CharLevelTrainer(t) => match model {
+                ModelWrapper::CharLevel(cl) => t.train_with_report(cl),
+                _ => Err("CharLevelTrainer can only train a CharLevel".into()),
+            },
+            Self::MorfessorTrainer(t) => match model {
+                ModelWrapper::Morfessor(m) => t.train_with_report(m),
+                _ => Err("MorfessorTrainer can only train a Morfessor".into()),
+            },
+            Self::HashedVocabTrainer(t) => match model {
+                ModelWrapper::HashedVocab(m) => t.train_with_report(m),
+                _ => Err("HashedVocabTrainer can only train a HashedVocab".into()),
+            },
+        }
+    }
+
+    fn save_checkpoint(&self, path: &Path) -> Result<()> {
+        match self {
+            Self::BpeTrainer(bpe) => bpe.save_checkpoint(path),
+            Self::WordPieceTrainer(wpt) => wpt.save_checkpoint(path),
+            Self::WordLevelTrainer(wpt) => wpt.save_checkpoint(path),
+            Self::UnigramTrainer(wpt) => wpt.save_checkpoint(path),
+            Self::CharLevelTrainer(wpt) => wpt.save_checkpoint(path),
+            Self::MorfessorTrainer(t) => t.save_checkpoint(path),
+            Self::HashedVocabTrainer(t) => t.save_checkpoint(path),
+        }
+    }
+
+    fn resume_from_checkpoint(&mut self, path: &Path) -> Result<()> {
+        match self {
+            Self::BpeTrainer(bpe) => bpe.resume_from_checkpoint(path),
+            Self::WordPieceTrainer(wpt) => wpt.resume_from_checkpoint(path),
+            Self::WordLevelTrainer(wpt) => wpt.resume_from_checkpoint(path),
+            Self::UnigramTrainer(wpt) => wpt.resume_from_checkpoint(path),
+            Self::CharLevelTrainer(wpt) => wpt.resume_from_checkpoint(path),
+            Self::MorfessorTrainer(t) => t.resume_from_checkpoint(path),
+            Self::HashedVocabTrainer(t) => t.resume_from_checkpoint(path),
         }
     }
 }
@@ -267,6 +601,9 @@ impl_enum_from!(BpeTrainer, TrainerWrapper, BpeTrainer);
 impl_enum_from!(WordPieceTrainer, TrainerWrapper, WordPieceTrainer);
 impl_enum_from!(UnigramTrainer, TrainerWrapper, UnigramTrainer);
 impl_enum_from!(WordLevelTrainer, TrainerWrapper, WordLevelTrainer);
+impl_enum_from!(CharLevelTrainer, TrainerWrapper, CharLevelTrainer);
+impl_enum_from!(MorfessorTrainer, TrainerWrapper, MorfessorTrainer);
+impl_enum_from!(HashedVocabTrainer, TrainerWrapper, HashedVocabTrainer);
 
 #[cfg(test)]
 mod tests {
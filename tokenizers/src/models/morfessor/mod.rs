@@ -0,0 +1,352 @@
+//! A Morfessor-style morphological segmentation model: words are split into
+//! morphs learned by minimizing a Minimum Description Length cost over the
+//! corpus, instead of greedy merges. On agglutinative languages (Finnish,
+//! Turkish, ...) this keeps segmentations aligned with morph boundaries
+//! where BPE merges happily cross them.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::tokenizer::{Model, Offsets, Result, Token, TokenInfo};
+
+mod serialization;
+mod trainer;
+
+// Re-export
+pub use trainer::*;
+
+type Vocab = Vec<(String, f64)>;
+
+/// The score penalty of a single character emitted as the unknown token,
+/// relative to the worst morph of the vocabulary, so that known morphs are
+/// always preferred
+const UNK_PENALTY: f64 = 10.0;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Morfessor error: the vocabulary contains `{0}` twice")]
+    DuplicateMorph(String),
+    #[error("Morfessor error: the unk token `{0}` is missing from the vocabulary")]
+    UnkTokenNotInVocab(String),
+    #[error("Morfessor error: no segmentation for `{0}`, and no unk token was set")]
+    NoSegmentation(String),
+}
+
+struct Config {
+    vocab: Vocab,
+    unk_token: Option<String>,
+}
+
+/// A `MorfessorBuilder` can be used to create a `Morfessor` model with a
+/// custom configuration.
+pub struct MorfessorBuilder {
+    config: Config,
+}
+
+impl Default for MorfessorBuilder {
+    fn default() -> Self {
+        Self {
+            config: Config {
+                vocab: vec![],
+                unk_token: None,
+            },
+        }
+    }
+}
+
+impl MorfessorBuilder {
+    /// Construct a new `MorfessorBuilder`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the morphs and their log-probabilities; the id of a morph is its
+    /// position.
+    #[must_use]
+    pub fn vocab(mut self, vocab: Vocab) -> Self {
+        self.config.vocab = vocab;
+        self
+    }
+
+    /// Set the token emitted for characters no morph covers.
+    #[must_use]
+    pub fn unk_token(mut self, unk_token: Option<String>) -> Self {
+        self.config.unk_token = unk_token;
+        self
+    }
+
+    /// Constructs a `Morfessor` model that uses the `MorfessorBuilder`'s
+    /// configuration.
+    pub fn build(self) -> Result<Morfessor> {
+        let mut token_to_ids = HashMap::new();
+        for (id, (morph, _)) in self.config.vocab.iter().enumerate() {
+            if token_to_ids.insert(morph.clone(), id as u32).is_some() {
+                return Err(Box::new(Error::DuplicateMorph(morph.clone())));
+            }
+        }
+        if let Some(unk) = &self.config.unk_token {
+            if !token_to_ids.contains_key(unk) {
+                return Err(Box::new(Error::UnkTokenNotInVocab(unk.clone())));
+            }
+        }
+        Ok(Morfessor {
+            vocab: self.config.vocab,
+            token_to_ids,
+            unk_token: self.config.unk_token,
+        })
+    }
+}
+
+#[derive(PartialEq, Clone, Default)]
+pub struct Morfessor {
+    vocab: Vocab,
+    token_to_ids: HashMap<String, u32>,
+    pub unk_token: Option<String>,
+}
+
+impl std::fmt::Debug for Morfessor {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.debug_struct("Morfessor")
+            .field("unk_token", &self.unk_token)
+            .field("vocab", &self.vocab.len())
+            .finish()
+    }
+}
+
+impl Morfessor {
+    pub fn builder() -> MorfessorBuilder {
+        MorfessorBuilder::new()
+    }
+
+    /// The best segmentation of the sequence into morphs, as `(morph id or
+    /// None for an unknown character, byte range)` entries, by Viterbi search
+    /// over the morph log-probabilities
+    fn segment(&self, sequence: &str) -> Result<Vec<(Option<u32>, Offsets)>> {
+        // The byte offset of every char boundary
+        let boundaries: Vec<usize> = sequence
+            .char_indices()
+            .map(|(offset, _)| offset)
+            .chain([sequence.len()])
+            .collect();
+        let unk_score = self
+            .vocab
+            .iter()
+            .map(|(_, score)| *score)
+            .fold(f64::INFINITY, f64::min)
+            .min(0.0)
+            - UNK_PENALTY;
+
+        // best[i]: the score of the best segmentation of the first i chars,
+        // with the char index it comes from and the morph id used
+        let mut best: Vec<Option<(f64, usize, Option<u32>)>> = vec![None; boundaries.len()];
+        best[0] = Some((0.0, 0, None));
+        for end in 1..boundaries.len() {
+            for start in 0..end {
+                let Some((score, _, _)) = best[start] else {
+                    continue;
+                };
+                let morph = &sequence[boundaries[start]..boundaries[end]];
+                if let Some(&id) = self.token_to_ids.get(morph) {
+                    let score = score + self.vocab[id as usize].1;
+                    if best[end].is_none_or(|(s, _, _)| score > s) {
+                        best[end] = Some((score, start, Some(id)));
+                    }
+                }
+            }
+            // A character no morph covers becomes one unknown token
+            if best[end].is_none() {
+                if let Some((score, _, _)) = best[end - 1] {
+                    best[end] = Some((score + unk_score, end - 1, None));
+                }
+            }
+        }
+
+        let mut path = vec![];
+        let mut end = boundaries.len() - 1;
+        while end > 0 {
+            let (_, start, id) = best[end].expect("every prefix has a segmentation");
+            path.push((id, (boundaries[start], boundaries[end])));
+            end = start;
+        }
+        path.reverse();
+        Ok(path)
+    }
+}
+
+impl Model for Morfessor {
+    type Trainer = MorfessorTrainer;
+
+    fn tokenize(&self, sequence: &str) -> Result<Vec<Token>> {
+        if sequence.is_empty() {
+            return Ok(vec![]);
+        }
+        self.segment(sequence)?
+            .into_iter()
+            .map(|(id, offsets)| match id {
+                Some(id) => Ok(Token::new(id, self.vocab[id as usize].0.clone(), offsets)),
+                None => {
+                    let unk = self
+                        .unk_token
+                        .as_ref()
+                        .ok_or_else(|| Error::NoSegmentation(sequence.to_owned()))?;
+                    let id = self.token_to_ids[unk];
+                    Ok(Token::new(id, unk.clone(), offsets))
+                }
+            })
+            .collect()
+    }
+
+    fn token_to_id(&self, token: &str) -> Option<u32> {
+        self.token_to_ids.get(token).copied()
+    }
+
+    fn id_to_token(&self, id: u32) -> Option<String> {
+        self.vocab.get(id as usize).map(|(morph, _)| morph.clone())
+    }
+
+    fn get_vocab(&self) -> HashMap<String, u32> {
+        self.token_to_ids.clone()
+    }
+
+    fn get_vocab_iter(&self) -> Box<dyn Iterator<Item = (Cow<'_, str>, u32)> + '_> {
+        Box::new(
+            self.vocab
+                .iter()
+                .enumerate()
+                .map(|(id, (morph, _))| (Cow::Borrowed(morph.as_str()), id as u32)),
+        )
+    }
+
+    fn get_vocab_r(&self) -> Box<dyn Iterator<Item = (u32, Cow<'_, str>)> + '_> {
+        Box::new(
+            self.vocab
+                .iter()
+                .enumerate()
+                .map(|(id, (morph, _))| (id as u32, Cow::Borrowed(morph.as_str()))),
+        )
+    }
+
+    fn get_vocab_size(&self) -> usize {
+        self.vocab.len()
+    }
+
+    fn save(&self, folder: &Path, name: Option<&str>) -> Result<Vec<PathBuf>> {
+        let name = match name {
+            Some(name) => format!("{}-morfessor.json", name),
+            None => "morfessor.json".to_string(),
+        };
+        let mut fullpath = PathBuf::new();
+        fullpath.push(folder);
+        fullpath.push(name);
+        let string = serde_json::to_string_pretty(self)?;
+        std::fs::write(&fullpath, string)?;
+        Ok(vec![fullpath])
+    }
+
+    fn get_trainer(&self) -> Self::Trainer {
+        MorfessorTrainer::default()
+    }
+
+    fn token_info(&self, id: u32) -> Option<TokenInfo> {
+        let (_, score) = self.vocab.get(id as usize)?;
+        Some(TokenInfo {
+            score: Some(*score),
+            ..TokenInfo::default()
+        })
+    }
+
+    fn token_scores(&self, ids: &[u32]) -> Option<Vec<Option<f64>>> {
+        Some(
+            ids.iter()
+                .map(|id| self.vocab.get(*id as usize).map(|(_, score)| *score))
+                .collect(),
+        )
+    }
+
+    fn unk_token(&self) -> Option<String> {
+        self.unk_token.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_model(vocab: &[(&str, f64)], unk: Option<&str>) -> Morfessor {
+        Morfessor::builder()
+            .vocab(
+                vocab
+                    .iter()
+                    .map(|(morph, score)| (morph.to_string(), *score))
+                    .collect(),
+            )
+            .unk_token(unk.map(|u| u.to_string()))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_tokenize_morphs() {
+        let model = build_model(
+            &[
+                ("<unk>", -10.0),
+                ("talo", -1.0),
+                ("ssa", -1.5),
+                ("auto", -1.2),
+                ("talossa", -5.0),
+            ],
+            Some("<unk>"),
+        );
+
+        // The two-morph path beats the whole-word morph
+        assert_eq!(
+            model.tokenize("talossa").unwrap(),
+            vec![
+                Token::new(1u32, "talo".into(), (0, 4)),
+                Token::new(2u32, "ssa".into(), (4, 7)),
+            ]
+        );
+        assert_eq!(
+            model.tokenize("autossa").unwrap(),
+            vec![
+                Token::new(3u32, "auto".into(), (0, 4)),
+                Token::new(2u32, "ssa".into(), (4, 7)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_unknown_chars() {
+        let model = build_model(&[("<unk>", -10.0), ("ab", -1.0)], Some("<unk>"));
+        // Each uncovered character becomes one unk token
+        assert_eq!(
+            model.tokenize("abxy").unwrap(),
+            vec![
+                Token::new(1u32, "ab".into(), (0, 2)),
+                Token::new(0u32, "<unk>".into(), (2, 3)),
+                Token::new(0u32, "<unk>".into(), (3, 4)),
+            ]
+        );
+
+        let model = build_model(&[("ab", -1.0)], None);
+        let error = model.tokenize("abx").err().unwrap();
+        assert!(error.is::<Error>());
+    }
+
+    #[test]
+    fn test_builder_validation() {
+        assert!(Morfessor::builder()
+            .vocab(vec![("a".into(), -1.0), ("a".into(), -2.0)])
+            .build()
+            .err()
+            .unwrap()
+            .is::<Error>());
+        assert!(Morfessor::builder()
+            .unk_token(Some("<unk>".into()))
+            .build()
+            .err()
+            .unwrap()
+            .is::<Error>());
+    }
+}
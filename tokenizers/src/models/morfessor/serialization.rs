@@ -0,0 +1,117 @@
+use super::{Morfessor, MorfessorBuilder};
+use serde::{
+    de::{MapAccess, Visitor},
+    ser::SerializeStruct,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::collections::HashSet;
+
+impl Serialize for Morfessor {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut model = serializer.serialize_struct("Morfessor", 3)?;
+        model.serialize_field("type", "Morfessor")?;
+        model.serialize_field("unk_token", &self.unk_token)?;
+        model.serialize_field("vocab", &self.vocab)?;
+        model.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Morfessor {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_struct(
+            "Morfessor",
+            &["type", "unk_token", "vocab"],
+            MorfessorVisitor,
+        )
+    }
+}
+
+struct MorfessorVisitor;
+impl<'de> Visitor<'de> for MorfessorVisitor {
+    type Value = Morfessor;
+
+    fn expecting(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(fmt, "struct Morfessor")
+    }
+
+    fn visit_map<V>(self, mut map: V) -> std::result::Result<Self::Value, V::Error>
+    where
+        V: MapAccess<'de>,
+    {
+        let mut builder = MorfessorBuilder::new();
+        let mut missing_fields = vec!["vocab"].into_iter().collect::<HashSet<_>>();
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_ref() {
+                "vocab" => builder = builder.vocab(map.next_value()?),
+                "unk_token" => builder = builder.unk_token(map.next_value()?),
+                "type" => match map.next_value()? {
+                    "Morfessor" => {}
+                    u => {
+                        return Err(serde::de::Error::invalid_value(
+                            serde::de::Unexpected::Str(u),
+                            &"Morfessor",
+                        ))
+                    }
+                },
+                _ => {}
+            }
+            missing_fields.remove::<str>(&key);
+        }
+
+        if !missing_fields.is_empty() {
+            Err(serde::de::Error::missing_field(
+                missing_fields.iter().next().unwrap(),
+            ))
+        } else {
+            Ok(builder.build().map_err(serde::de::Error::custom)?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::morfessor::Morfessor;
+
+    #[test]
+    fn serde() {
+        let model = Morfessor::default();
+        let model_s = r#"{"type":"Morfessor","unk_token":null,"vocab":[]}"#;
+
+        assert_eq!(serde_json::to_string(&model).unwrap(), model_s);
+        assert_eq!(serde_json::from_str::<Morfessor>(model_s).unwrap(), model);
+    }
+
+    #[test]
+    fn serde_roundtrip() {
+        let model = Morfessor::builder()
+            .vocab(vec![("<unk>".into(), -10.0), ("talo".into(), -1.0)])
+            .unk_token(Some("<unk>".into()))
+            .build()
+            .unwrap();
+        let model_s =
+            r#"{"type":"Morfessor","unk_token":"<unk>","vocab":[["<unk>",-10.0],["talo",-1.0]]}"#;
+        assert_eq!(serde_json::to_string(&model).unwrap(), model_s);
+        assert_eq!(serde_json::from_str::<Morfessor>(model_s).unwrap(), model);
+    }
+
+    #[test]
+    fn deserialization_should_fail() {
+        let missing_vocab = r#"{"type":"Morfessor","unk_token":null}"#;
+        assert!(serde_json::from_str::<Morfessor>(missing_vocab)
+            .unwrap_err()
+            .to_string()
+            .starts_with("missing field `vocab`"));
+
+        let wrong_type = r#"{"type":"Unigram","vocab":[]}"#;
+        assert!(serde_json::from_str::<Morfessor>(wrong_type)
+            .unwrap_err()
+            .to_string()
+            .starts_with("invalid value: string \"Unigram\", expected Morfessor"));
+    }
+}
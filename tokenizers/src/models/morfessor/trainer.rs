@@ -0,0 +1,449 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::Morfessor;
+use crate::utils::parallelism::*;
+use crate::{AddedToken, Result, Trainer, TrainingReport};
+
+/// The running Minimum Description Length cost of a morph lexicon over the
+/// corpus: the corpus coding cost `N ln N - Σ c ln c` plus a lexicon cost
+/// proportional to the characters spelling the distinct morphs. Counts are
+/// added and removed incrementally, so candidate splits can be evaluated and
+/// reverted cheaply.
+struct MdlCost {
+    counts: HashMap<String, u64>,
+    total: u64,
+    sum_c_ln_c: f64,
+    lexicon_chars: u64,
+    /// The per-character lexicon coding cost, `lexicon_weight * ln(A + 1)`
+    /// for an alphabet of `A` characters
+    char_cost: f64,
+}
+
+impl MdlCost {
+    fn new(alphabet_size: usize, lexicon_weight: f64) -> Self {
+        Self {
+            counts: HashMap::new(),
+            total: 0,
+            sum_c_ln_c: 0.0,
+            lexicon_chars: 0,
+            char_cost: lexicon_weight * ((alphabet_size + 1) as f64).ln(),
+        }
+    }
+
+    fn x_ln_x(x: u64) -> f64 {
+        if x == 0 {
+            0.0
+        } else {
+            let x = x as f64;
+            x * x.ln()
+        }
+    }
+
+    fn add(&mut self, morph: &str, count: u64) {
+        let entry = self.counts.entry(morph.to_owned()).or_insert(0);
+        if *entry == 0 {
+            self.lexicon_chars += morph.chars().count() as u64 + 1;
+        }
+        self.sum_c_ln_c += Self::x_ln_x(*entry + count) - Self::x_ln_x(*entry);
+        *entry += count;
+        self.total += count;
+    }
+
+    fn remove(&mut self, morph: &str, count: u64) {
+        let entry = self.counts.get_mut(morph).expect("morph was added");
+        self.sum_c_ln_c += Self::x_ln_x(*entry - count) - Self::x_ln_x(*entry);
+        *entry -= count;
+        self.total -= count;
+        if *entry == 0 {
+            self.lexicon_chars -= morph.chars().count() as u64 + 1;
+            self.counts.remove(morph);
+        }
+    }
+
+    fn cost(&self) -> f64 {
+        Self::x_ln_x(self.total) - self.sum_c_ln_c + self.lexicon_chars as f64 * self.char_cost
+    }
+}
+
+#[non_exhaustive]
+#[derive(Debug, Clone, Builder, Serialize, Deserialize)]
+pub struct MorfessorTrainer {
+    /// The minimum count a morph must have to be part of the vocabulary
+    #[builder(default = "0")]
+    pub min_frequency: u64,
+    /// The maximum number of morphs kept in the vocabulary; the MDL search
+    /// itself decides how many morphs are worth storing, this only caps the
+    /// result
+    #[builder(default = "10_000")]
+    pub vocab_size: usize,
+    /// The weight of the lexicon cost against the corpus coding cost: higher
+    /// values make storing a morph more expensive, favoring fewer, more
+    /// reusable morphs and more aggressive splitting
+    #[builder(default = "1.0")]
+    #[serde(default = "default_lexicon_weight")]
+    pub lexicon_weight: f64,
+    /// The maximum number of passes re-segmenting the words; training stops
+    /// earlier once no segmentation changes
+    #[builder(default = "5")]
+    #[serde(default = "default_max_iterations")]
+    pub max_iterations: usize,
+    /// Whether to show progress while training
+    #[builder(default = "true")]
+    pub show_progress: bool,
+    /// A list of special tokens that the model should know of
+    #[builder(default)]
+    pub special_tokens: Vec<AddedToken>,
+    /// The unknown token set on the model, emitted for characters no morph
+    /// covers, and prepended to the vocabulary when set
+    #[builder(default)]
+    #[serde(default)]
+    pub unk_token: Option<String>,
+    /// Whether [`Trainer::train_with_report`] should produce a [`TrainingReport`]
+    #[builder(default = "false")]
+    #[serde(default)]
+    pub report: bool,
+
+    #[builder(default, private)]
+    words: HashMap<String, u64>,
+    #[builder(default, private)]
+    #[serde(default)]
+    validation: Vec<String>,
+}
+
+fn default_lexicon_weight() -> f64 {
+    1.0
+}
+
+fn default_max_iterations() -> usize {
+    5
+}
+
+impl Default for MorfessorTrainer {
+    fn default() -> Self {
+        Self::builder().build().unwrap()
+    }
+}
+
+impl MorfessorTrainer {
+    pub fn builder() -> MorfessorTrainerBuilder {
+        MorfessorTrainerBuilder::default()
+    }
+
+    /// The best segmentation of `morph`, recursively comparing the cost of
+    /// keeping it whole against every binary split, as in the Morfessor
+    /// baseline algorithm. The counts of the returned morphs are added to
+    /// `state` along the way.
+    fn resplit(state: &mut MdlCost, morph: &str, count: u64) -> Vec<String> {
+        state.add(morph, count);
+        let whole_cost = state.cost();
+        state.remove(morph, count);
+
+        let mut best: Option<(f64, usize)> = None;
+        for (split, _) in morph.char_indices().skip(1) {
+            let (left, right) = morph.split_at(split);
+            state.add(left, count);
+            state.add(right, count);
+            let cost = state.cost();
+            state.remove(left, count);
+            state.remove(right, count);
+            // The epsilon keeps cost ties from flip-flopping between passes
+            if cost + 1e-9 < best.map_or(whole_cost, |(best_cost, _)| best_cost) {
+                best = Some((cost, split));
+            }
+        }
+
+        match best {
+            None => {
+                state.add(morph, count);
+                vec![morph.to_owned()]
+            }
+            Some((_, split)) => {
+                let (left, right) = morph.split_at(split);
+                let mut morphs = Self::resplit(state, left, count);
+                morphs.extend(Self::resplit(state, right, count));
+                morphs
+            }
+        }
+    }
+
+    fn do_train(
+        &self,
+        word_counts: &HashMap<String, u64>,
+        model: &mut Morfessor,
+    ) -> Result<Vec<AddedToken>> {
+        // Process the words by decreasing count, ties broken by the word
+        // itself, to keep training deterministic
+        let mut words: Vec<(&String, u64)> = word_counts
+            .iter()
+            .map(|(word, count)| (word, *count))
+            .collect();
+        words.sort_by(|l, r| match r.1.cmp(&l.1) {
+            Ordering::Equal => l.0.cmp(r.0),
+            ordering => ordering,
+        });
+
+        let alphabet_size = word_counts
+            .keys()
+            .flat_map(|word| word.chars())
+            .collect::<std::collections::HashSet<char>>()
+            .len();
+        let mut state = MdlCost::new(alphabet_size, self.lexicon_weight);
+
+        // Start from unsegmented words, then re-segment each word in turn
+        // until the lexicon settles
+        let mut segmentations: HashMap<&String, Vec<String>> = HashMap::new();
+        for (word, count) in &words {
+            state.add(word, *count);
+            segmentations.insert(word, vec![(*word).clone()]);
+        }
+        for _ in 0..self.max_iterations {
+            let mut changed = false;
+            for (word, count) in &words {
+                for morph in &segmentations[word] {
+                    state.remove(morph, *count);
+                }
+                let morphs = Self::resplit(&mut state, word, *count);
+                if segmentations[word] != morphs {
+                    changed = true;
+                }
+                segmentations.insert(word, morphs);
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        // The vocabulary: special tokens and the unk token first, then the
+        // morphs by decreasing count, scored by their log-probability
+        let mut morphs: Vec<(&String, u64)> = state
+            .counts
+            .iter()
+            .filter(|(_, count)| **count >= self.min_frequency)
+            .map(|(morph, count)| (morph, *count))
+            .collect();
+        morphs.sort_by(|l, r| match r.1.cmp(&l.1) {
+            Ordering::Equal => l.0.cmp(r.0),
+            ordering => ordering,
+        });
+        let total = state.total as f64;
+        let mut seen = std::collections::HashSet::new();
+        let vocab: Vec<(String, f64)> = self
+            .special_tokens
+            .iter()
+            .map(|token| (token.content.clone(), 0.0))
+            .chain(self.unk_token.iter().map(|unk| (unk.clone(), 0.0)))
+            .chain(
+                morphs
+                    .into_iter()
+                    .map(|(morph, count)| (morph.clone(), (count as f64 / total).ln())),
+            )
+            .filter(|(morph, _)| seen.insert(morph.clone()))
+            .take(self.vocab_size)
+            .collect();
+
+        let morfessor = Morfessor::builder()
+            .vocab(vocab)
+            .unk_token(self.unk_token.clone())
+            .build()?;
+        model.vocab = morfessor.vocab;
+        model.token_to_ids = morfessor.token_to_ids;
+        model.unk_token = morfessor.unk_token;
+
+        Ok(self.special_tokens.clone())
+    }
+}
+
+impl Trainer for MorfessorTrainer {
+    type Model = Morfessor;
+
+    /// Train a Morfessor model
+    fn train(&self, model: &mut Morfessor) -> Result<Vec<AddedToken>> {
+        self.do_train(&self.words, model)
+    }
+
+    /// Whether we should show progress
+    fn should_show_progress(&self) -> bool {
+        self.show_progress
+    }
+
+    fn feed<I, S, F>(&mut self, iterator: I, process: F) -> Result<()>
+    where
+        I: Iterator<Item = S> + Send,
+        S: AsRef<str> + Send,
+        F: Fn(&str) -> Result<Vec<String>> + Sync,
+    {
+        let words: Result<HashMap<String, u64>> = iterator
+            .maybe_par_bridge()
+            .map(|sequence| {
+                let words = process(sequence.as_ref())?;
+                let mut map = HashMap::new();
+                for word in words {
+                    map.entry(word).and_modify(|c| *c += 1).or_insert(1);
+                }
+                Ok(map)
+            })
+            .reduce(
+                || Ok(HashMap::new()),
+                |acc, ws| {
+                    let mut acc = acc?;
+                    for (k, v) in ws? {
+                        acc.entry(k).and_modify(|c| *c += v).or_insert(v);
+                    }
+                    Ok(acc)
+                },
+            );
+
+        self.words = words?;
+        Ok(())
+    }
+
+    fn feed_weighted<I, S, F>(&mut self, iterator: I, process: F) -> Result<()>
+    where
+        I: Iterator<Item = (S, f64)> + Send,
+        S: AsRef<str> + Send,
+        F: Fn(&str) -> Result<Vec<String>> + Sync,
+    {
+        let words: Result<HashMap<String, f64>> = iterator
+            .maybe_par_bridge()
+            .map(|(sequence, weight)| {
+                let words = process(sequence.as_ref())?;
+                let mut map = HashMap::new();
+                for word in words {
+                    map.entry(word)
+                        .and_modify(|c| *c += weight)
+                        .or_insert(weight);
+                }
+                Ok(map)
+            })
+            .reduce(
+                || Ok(HashMap::new()),
+                |acc, ws| {
+                    let mut acc = acc?;
+                    for (k, v) in ws? {
+                        acc.entry(k).and_modify(|c| *c += v).or_insert(v);
+                    }
+                    Ok(acc)
+                },
+            );
+
+        self.words = words?
+            .into_iter()
+            .filter_map(|(word, count)| {
+                let count = count.round() as u64;
+                (count > 0).then_some((word, count))
+            })
+            .collect();
+        Ok(())
+    }
+
+    fn feed_validation<I, S>(&mut self, iterator: I) -> Result<()>
+    where
+        I: Iterator<Item = S> + Send,
+        S: AsRef<str> + Send,
+    {
+        self.validation = iterator.map(|s| s.as_ref().to_owned()).collect();
+        Ok(())
+    }
+
+    fn train_with_report(
+        &self,
+        model: &mut Morfessor,
+    ) -> Result<(Vec<AddedToken>, Option<TrainingReport>)> {
+        let special_tokens = self.do_train(&self.words, model)?;
+        let report = if self.report {
+            Some(TrainingReport::compute(
+                &self.words,
+                &self.validation,
+                model,
+                self.unk_token.as_deref(),
+            )?)
+        } else {
+            None
+        };
+        Ok((special_tokens, report))
+    }
+
+    fn save_checkpoint(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    fn resume_from_checkpoint(&mut self, path: &Path) -> Result<()> {
+        *self = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Model;
+
+    #[test]
+    fn test_train_splits_morphs() {
+        let mut trainer = MorfessorTrainer {
+            show_progress: false,
+            unk_token: Some("<unk>".into()),
+            // Favor a small lexicon, so that the shared suffixes get split off
+            // even on this tiny corpus
+            lexicon_weight: 3.0,
+            ..Default::default()
+        };
+        // A toy agglutinative corpus: stems and suffixes recombine, so the
+        // MDL cost is lower with a lexicon of shared morphs
+        let corpus: Vec<String> = ["talo", "talossa", "talon", "auto", "autossa", "auton"]
+            .iter()
+            .flat_map(|word| std::iter::repeat(word.to_string()).take(10))
+            .collect();
+        trainer
+            .feed(corpus.iter(), |sequence| Ok(vec![sequence.to_string()]))
+            .unwrap();
+
+        let mut model = Morfessor::default();
+        trainer.train(&mut model).unwrap();
+
+        // The shared suffixes were isolated as morphs of their own
+        assert!(
+            model.token_to_id("ssa").is_some(),
+            "{:?}",
+            model.get_vocab()
+        );
+        let tokens: Vec<String> = model
+            .tokenize("autossa")
+            .unwrap()
+            .into_iter()
+            .map(|token| token.value)
+            .collect();
+        assert_eq!(tokens, vec!["auto", "ssa"]);
+
+        // And unseen stems reuse them
+        let tokens: Vec<String> = model
+            .tokenize("xyssa")
+            .unwrap()
+            .into_iter()
+            .map(|token| token.value)
+            .collect();
+        assert_eq!(tokens, vec!["<unk>", "<unk>", "ssa"]);
+    }
+
+    #[test]
+    fn test_train_respects_vocab_size() {
+        let mut trainer = MorfessorTrainer {
+            show_progress: false,
+            vocab_size: 3,
+            ..Default::default()
+        };
+        trainer
+            .feed(["abc", "abd", "abe"].iter(), |sequence| {
+                Ok(vec![sequence.to_string()])
+            })
+            .unwrap();
+        let mut model = Morfessor::default();
+        trainer.train(&mut model).unwrap();
+        assert!(model.get_vocab_size() <= 3);
+    }
+}
@@ -0,0 +1,205 @@
+//! A wrapper remapping the ids of an underlying model on the fly.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use monostate::MustBe;
+use serde::{Deserialize, Serialize};
+
+use crate::{Model, Result, Token, TokenInfo};
+
+type Tag = MustBe!("Remapped");
+
+/// Applies an id→id mapping on top of another model, e.g. to align token ids
+/// with a reordered embedding matrix.
+///
+/// The mapping is applied at `tokenize`/`token_to_id` time and reversed by
+/// `id_to_token`, without touching the wrapped model: ids absent from the
+/// mapping are forwarded unchanged. The mapping is expected to be injective,
+/// otherwise remapped ids cannot be reversed unambiguously.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "RemappedModelHelper<M>")]
+pub struct RemappedModel<M> {
+    #[serde(rename = "type")]
+    type_: Tag,
+    pub model: M,
+    pub id_mapping: HashMap<u32, u32>,
+    #[serde(skip)]
+    reverse_mapping: HashMap<u32, u32>,
+}
+
+#[derive(Deserialize)]
+struct RemappedModelHelper<M> {
+    // The "type" field is consumed by the `ModelWrapper` tagged deserializer,
+    // so it cannot be mandatory here
+    #[serde(rename = "type", default)]
+    _type: Option<Tag>,
+    model: M,
+    id_mapping: HashMap<u32, u32>,
+}
+
+impl<M> From<RemappedModelHelper<M>> for RemappedModel<M> {
+    fn from(helper: RemappedModelHelper<M>) -> Self {
+        Self::new(helper.model, helper.id_mapping)
+    }
+}
+
+impl<M> RemappedModel<M> {
+    pub fn new(model: M, id_mapping: HashMap<u32, u32>) -> Self {
+        let reverse_mapping = id_mapping.iter().map(|(&old, &new)| (new, old)).collect();
+        Self {
+            type_: MustBe!("Remapped"),
+            model,
+            id_mapping,
+            reverse_mapping,
+        }
+    }
+
+    fn remap(&self, id: u32) -> u32 {
+        self.id_mapping.get(&id).copied().unwrap_or(id)
+    }
+
+    fn unmap(&self, id: u32) -> u32 {
+        self.reverse_mapping.get(&id).copied().unwrap_or(id)
+    }
+}
+
+impl<M: Model> Model for RemappedModel<M> {
+    type Trainer = M::Trainer;
+
+    fn tokenize(&self, sequence: &str) -> Result<Vec<Token>> {
+        let mut tokens = self.model.tokenize(sequence)?;
+        for token in tokens.iter_mut() {
+            token.id = self.remap(token.id);
+        }
+        Ok(tokens)
+    }
+
+    fn tokenize_with_context(
+        &self,
+        prev: Option<&str>,
+        sequence: &str,
+        next: Option<&str>,
+    ) -> Result<Vec<Token>> {
+        let mut tokens = self.model.tokenize_with_context(prev, sequence, next)?;
+        for token in tokens.iter_mut() {
+            token.id = self.remap(token.id);
+        }
+        Ok(tokens)
+    }
+
+    fn token_to_id(&self, token: &str) -> Option<u32> {
+        self.model.token_to_id(token).map(|id| self.remap(id))
+    }
+
+    fn id_to_token(&self, id: u32) -> Option<String> {
+        self.model.id_to_token(self.unmap(id))
+    }
+
+    fn get_vocab(&self) -> HashMap<String, u32> {
+        self.model
+            .get_vocab()
+            .into_iter()
+            .map(|(token, id)| (token, self.remap(id)))
+            .collect()
+    }
+
+    fn get_vocab_iter(&self) -> Box<dyn Iterator<Item = (Cow<'_, str>, u32)> + '_> {
+        let mut entries: Vec<_> = self
+            .model
+            .get_vocab_iter()
+            .map(|(token, id)| (token, self.remap(id)))
+            .collect();
+        entries.sort_by_key(|(_, id)| *id);
+        Box::new(entries.into_iter())
+    }
+
+    fn get_vocab_r(&self) -> Box<dyn Iterator<Item = (u32, Cow<'_, str>)> + '_> {
+        let mut entries: Vec<_> = self
+            .model
+            .get_vocab_r()
+            .map(|(id, token)| (self.remap(id), token))
+            .collect();
+        entries.sort_by_key(|(id, _)| *id);
+        Box::new(entries.into_iter())
+    }
+
+    fn get_vocab_size(&self) -> usize {
+        self.model.get_vocab_size()
+    }
+
+    fn save(&self, folder: &Path, name: Option<&str>) -> Result<Vec<PathBuf>> {
+        self.model.save(folder, name)
+    }
+
+    fn get_trainer(&self) -> Self::Trainer {
+        self.model.get_trainer()
+    }
+
+    fn token_info(&self, id: u32) -> Option<TokenInfo> {
+        self.model.token_info(self.unmap(id))
+    }
+
+    fn token_scores(&self, ids: &[u32]) -> Option<Vec<Option<f64>>> {
+        let ids: Vec<u32> = ids.iter().map(|id| self.unmap(*id)).collect();
+        self.model.token_scores(&ids)
+    }
+
+    fn unk_token(&self) -> Option<String> {
+        self.model.unk_token()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::wordlevel::WordLevel;
+
+    fn model() -> RemappedModel<WordLevel> {
+        let vocab: HashMap<String, u32> = [("a".into(), 0), ("b".into(), 1), ("<unk>".into(), 2)]
+            .iter()
+            .cloned()
+            .collect();
+        let wordlevel = WordLevel::builder()
+            .vocab(vocab.into())
+            .unk_token("<unk>".into())
+            .build()
+            .unwrap();
+        RemappedModel::new(wordlevel, [(0, 10), (1, 11)].iter().copied().collect())
+    }
+
+    #[test]
+    fn remapping() {
+        let model = model();
+        assert_eq!(model.token_to_id("a"), Some(10));
+        assert_eq!(model.token_to_id("b"), Some(11));
+        // Ids absent from the mapping are forwarded unchanged
+        assert_eq!(model.token_to_id("<unk>"), Some(2));
+        assert_eq!(model.id_to_token(10), Some("a".into()));
+        assert_eq!(model.id_to_token(2), Some("<unk>".into()));
+
+        let tokens = model.tokenize("b").unwrap();
+        assert_eq!(tokens[0].id, 11);
+
+        assert_eq!(model.get_vocab()["a"], 10);
+        assert_eq!(
+            model.get_vocab_r().collect::<Vec<_>>(),
+            vec![
+                (2, Cow::Borrowed("<unk>")),
+                (10, Cow::Borrowed("a")),
+                (11, Cow::Borrowed("b"))
+            ]
+        );
+    }
+
+    #[test]
+    fn serde() {
+        let model = model();
+        let serialized = serde_json::to_string(&model).unwrap();
+        let reconstructed: RemappedModel<WordLevel> = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(reconstructed, model);
+        // The reverse mapping is rebuilt on deserialization
+        assert_eq!(reconstructed.id_to_token(11), Some("b".into()));
+    }
+}
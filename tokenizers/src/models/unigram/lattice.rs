@@ -1,5 +1,6 @@
 use rand::distributions::WeightedIndex;
 use rand::prelude::*;
+use serde::Serialize;
 use std::cell::RefCell;
 use std::cmp::{min, Ordering};
 use std::collections::BinaryHeap;
@@ -428,6 +429,129 @@ impl<'a> Lattice<'a> {
             .map(|node| self.piece(&node.borrow()))
             .collect()
     }
+
+    /// Export the lattice as a serializable graph, running Viterbi to mark the
+    /// best path
+    pub fn to_graph(&mut self) -> LatticeGraph {
+        let viterbi = self
+            .viterbi()
+            .iter()
+            .map(|node| node.borrow().node_id)
+            .collect();
+
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let node = node.borrow();
+                LatticeGraphNode {
+                    node_id: node.node_id,
+                    id: node.id,
+                    piece: self.piece(&node),
+                    pos: node.pos,
+                    length: node.length,
+                    score: node.score,
+                }
+            })
+            .collect();
+
+        // A node ending at some position connects to every node starting there
+        let mut edges = vec![];
+        for pos in 0..=self.len {
+            for lnode in &self.end_nodes[pos] {
+                for rnode in &self.begin_nodes[pos] {
+                    edges.push(LatticeGraphEdge {
+                        from: lnode.borrow().node_id,
+                        to: rnode.borrow().node_id,
+                    });
+                }
+            }
+        }
+
+        LatticeGraph {
+            sentence: self.sentence.to_owned(),
+            nodes,
+            edges,
+            viterbi,
+        }
+    }
+}
+
+/// A serializable view of a [`Lattice`], to inspect why a particular
+/// segmentation won. It can be serialized to JSON with serde, or rendered in
+/// Graphviz DOT format with [`LatticeGraph::to_dot`].
+#[derive(Debug, Clone, Serialize)]
+pub struct LatticeGraph {
+    pub sentence: String,
+    pub nodes: Vec<LatticeGraphNode>,
+    pub edges: Vec<LatticeGraphEdge>,
+    /// The `node_id`s of the best (Viterbi) path, in order, without BOS/EOS
+    pub viterbi: Vec<usize>,
+}
+
+/// A scored node of a [`LatticeGraph`]. The BOS and EOS sentinels are always
+/// the nodes with `node_id` 0 and 1, and have an empty `piece`.
+#[derive(Debug, Clone, Serialize)]
+pub struct LatticeGraphNode {
+    /// Local identifier within the lattice
+    pub node_id: usize,
+    /// Vocabulary id of the piece
+    pub id: usize,
+    pub piece: String,
+    /// Starting position in the sentence, in bytes
+    pub pos: usize,
+    /// Length of the piece, in bytes
+    pub length: usize,
+    pub score: f64,
+}
+
+/// An edge of a [`LatticeGraph`], between `node_id`s
+#[derive(Debug, Clone, Serialize)]
+pub struct LatticeGraphEdge {
+    pub from: usize,
+    pub to: usize,
+}
+
+impl LatticeGraph {
+    /// Render the graph in Graphviz DOT format, with the Viterbi path in bold
+    pub fn to_dot(&self) -> String {
+        use std::fmt::Write;
+
+        // The full best path goes BOS -> viterbi nodes -> EOS
+        let mut path = Vec::with_capacity(self.viterbi.len() + 2);
+        path.push(0);
+        path.extend(self.viterbi.iter().copied());
+        path.push(1);
+
+        let mut dot = String::from("digraph lattice {\n    rankdir = LR;\n");
+        for node in &self.nodes {
+            let label = match node.node_id {
+                0 => "<s>".to_string(),
+                1 => "</s>".to_string(),
+                _ => format!("{} ({:.3})", node.piece.escape_default(), node.score),
+            };
+            let emphasis = if path.contains(&node.node_id) {
+                " penwidth=2"
+            } else {
+                ""
+            };
+            writeln!(
+                dot,
+                "    {} [label=\"{}\"{}];",
+                node.node_id, label, emphasis
+            )
+            .unwrap();
+        }
+        for edge in &self.edges {
+            let on_path = path
+                .windows(2)
+                .any(|w| w[0] == edge.from && w[1] == edge.to);
+            let emphasis = if on_path { " [penwidth=2]" } else { "" };
+            writeln!(dot, "    {} -> {}{};", edge.from, edge.to, emphasis).unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
 }
 
 #[cfg(test)]
@@ -1,10 +1,11 @@
 use super::{
-    lattice::Lattice,
+    lattice::{Lattice, LatticeGraph},
     trainer::UnigramTrainer,
     trie::{Trie, TrieBuilder},
 };
-use crate::tokenizer::{Model, Result, Token};
+use crate::tokenizer::{Model, Result, Token, TokenInfo, VocabMask};
 use crate::utils::cache::Cache;
+use std::borrow::Cow;
 
 use std::collections::HashMap;
 use std::convert::TryInto;
@@ -28,6 +29,7 @@ pub struct Unigram {
     fuse_unk: bool,
     is_optimized: bool,
     byte_fallback: bool,
+    user_defined_symbols: Vec<String>,
 }
 impl PartialEq for Unigram {
     fn eq(&self, other: &Self) -> bool {
@@ -52,6 +54,7 @@ impl Clone for Unigram {
             fuse_unk: self.fuse_unk,
             is_optimized: self.is_optimized,
             byte_fallback: self.byte_fallback,
+            user_defined_symbols: self.user_defined_symbols.clone(),
         }
     }
 }
@@ -76,6 +79,8 @@ pub enum UnigramError {
     UnkIdNotInVocabulary,
     #[error("Encountered an unknown token but `unk_id` is missing")]
     MissingUnkId,
+    #[error("`{0}` cannot be segmented using only the allowed subset of the vocabulary")]
+    NoAllowedSegmentation(String),
 }
 
 impl Default for Unigram {
@@ -137,6 +142,7 @@ impl Unigram {
             cache: Cache::default(),
             is_optimized,
             byte_fallback,
+            user_defined_symbols: vec![],
         })
     }
 
@@ -153,11 +159,97 @@ impl Unigram {
     pub fn byte_fallback(&self) -> bool {
         self.byte_fallback
     }
+
+    /// Declare the given pieces as user defined symbols, as SentencePiece
+    /// calls them: they are always segmented as a single piece regardless of
+    /// scores. Each symbol must already be part of the vocabulary.
+    pub fn set_user_defined_symbols(&mut self, symbols: Vec<String>) -> Result<()> {
+        for symbol in &symbols {
+            if !self.token_to_ids.contains_key(symbol) {
+                return Err(
+                    format!("user defined symbol `{}` is not in the vocabulary", symbol).into(),
+                );
+            }
+        }
+        self.user_defined_symbols = symbols;
+        self.cache = self.cache.fresh();
+        Ok(())
+    }
+
+    pub fn user_defined_symbols(&self) -> &[String] {
+        &self.user_defined_symbols
+    }
+
+    /// Recompute the auxiliary structures after a change to `vocab`
+    fn rebuild(&mut self) {
+        let mut token_to_ids: TokenMap = HashMap::new();
+        let mut builder = TrieBuilder::default();
+        let mut min_score = f64::INFINITY;
+        for (id, (token, score)) in self.vocab.iter().enumerate() {
+            token_to_ids.insert(token.to_string(), id as u32);
+            let bytes: Vec<u8> = token.bytes().collect();
+            builder.push(&bytes);
+            if score < &min_score {
+                min_score = *score;
+            }
+        }
+        self.token_to_ids = token_to_ids;
+        self.trie = builder.build();
+        self.min_score = min_score;
+        self.bos_id = self.vocab.len() + 1;
+        self.eos_id = self.vocab.len() + 2;
+        self.cache = self.cache.fresh();
+    }
+
+    /// Add `token` to the vocabulary with the current minimum score, so that
+    /// it takes part in segmentation without being preferred over existing
+    /// pieces, returning its newly assigned id, or the id it already has
+    pub fn add_token(&mut self, token: &str) -> u32 {
+        self.add_token_with_score(token, self.min_score)
+    }
+
+    /// Add `token` to the vocabulary with the given score, returning its
+    /// newly assigned id, or, leaving its score untouched, the id it
+    /// already has
+    pub fn add_token_with_score(&mut self, token: &str, score: f64) -> u32 {
+        if let Some(id) = self.token_to_ids.get(token) {
+            return *id;
+        }
+        self.vocab.push((token.to_owned(), score));
+        self.rebuild();
+        (self.vocab.len() - 1) as u32
+    }
+
+    /// Remove the token with the given id, returning it. The ids of a
+    /// `Unigram` are positions in its vocabulary, so every subsequent token
+    /// sees its id shifted down by one; removing the unknown token leaves
+    /// the model without one.
+    pub fn remove_token(&mut self, id: u32) -> Option<String> {
+        let id = id as usize;
+        if id >= self.vocab.len() {
+            return None;
+        }
+        let (token, _) = self.vocab.remove(id);
+        self.unk_id = match self.unk_id {
+            Some(unk_id) if unk_id == id => None,
+            Some(unk_id) if unk_id > id => Some(unk_id - 1),
+            unk_id => unk_id,
+        };
+        self.rebuild();
+        Some(token)
+    }
     pub(super) fn len(&self) -> usize {
         self.vocab.len()
     }
 
     pub(super) fn populate_nodes(&self, lattice: &mut Lattice) {
+        self.populate_nodes_masked(lattice, None)
+    }
+
+    /// Like [`Self::populate_nodes`], only inserting the pieces whose id is
+    /// contained in `allowed` when a mask is given. The unknown token is also
+    /// subject to the mask, so the lattice may end up without any path
+    fn populate_nodes_masked(&self, lattice: &mut Lattice, allowed: Option<&VocabMask>) {
         let unk_score = self.min_score - K_UNK_PENALTY;
 
         let len = lattice.len();
@@ -179,6 +271,9 @@ impl Unigram {
                 let n = bytes.len();
                 let tok = String::from_utf8(bytes).unwrap();
                 let id = *self.token_to_ids.get(&tok).unwrap();
+                if allowed.is_some_and(|mask| !mask.contains(id)) {
+                    continue;
+                }
 
                 let item = &self.vocab[id as usize];
                 assert_eq!(item.0, tok);
@@ -191,7 +286,9 @@ impl Unigram {
 
             if !has_single_node {
                 if let Some(unk_id) = self.unk_id {
-                    lattice.insert(begin_pos, mblen, unk_score, unk_id);
+                    if allowed.is_none_or(|mask| mask.contains(unk_id as u32)) {
+                        lattice.insert(begin_pos, mblen, unk_score, unk_id);
+                    }
                 }
             }
             begin_pos += mblen
@@ -225,16 +322,49 @@ impl Unigram {
         if let Some(result) = self.cache.get(sentence) {
             Ok(result.to_vec())
         } else {
-            let result = if self.is_optimized {
-                self.encode_optimized(sentence)?
-            } else {
-                self.encode_unoptimized(sentence)?
-            };
+            let mut result = vec![];
+            for (segment, is_symbol) in self.split_on_user_defined_symbols(sentence) {
+                if is_symbol {
+                    result.push(segment.to_string());
+                } else if self.is_optimized {
+                    result.extend(self.encode_optimized(segment)?);
+                } else {
+                    result.extend(self.encode_unoptimized(segment)?);
+                }
+            }
             self.cache.set(sentence.to_owned(), result.clone());
             Ok(result)
         }
     }
 
+    /// Split the sentence on the occurrences of the user defined symbols,
+    /// leftmost and longest first, flagging the segments that are a symbol
+    fn split_on_user_defined_symbols<'a>(&self, sentence: &'a str) -> Vec<(&'a str, bool)> {
+        let mut splits = vec![];
+        let mut rest = sentence;
+        while !rest.is_empty() {
+            let occurrence = self
+                .user_defined_symbols
+                .iter()
+                .filter_map(|symbol| rest.find(symbol.as_str()).map(|pos| (pos, symbol.len())))
+                .min_by_key(|(pos, len)| (*pos, std::cmp::Reverse(*len)));
+            match occurrence {
+                Some((pos, len)) => {
+                    if pos > 0 {
+                        splits.push((&rest[..pos], false));
+                    }
+                    splits.push((&rest[pos..pos + len], true));
+                    rest = &rest[pos + len..];
+                }
+                None => {
+                    splits.push((rest, false));
+                    rest = "";
+                }
+            }
+        }
+        splits
+    }
+
     fn encode_optimized(&self, sentence: &str) -> Result<Vec<String>> {
         // https://github.com/google/sentencepiece/blob/d48247191a6d50e469ed1a4a36e877befffd1851/src/unigram_model.cc#L600
         #[derive(Debug, Clone)]
@@ -360,6 +490,54 @@ impl Unigram {
         }
     }
 
+    /// Return the `n` best tokenizations of `sentence`, ordered by decreasing
+    /// score.
+    /// ```
+    /// use tokenizers::models::unigram::Unigram;
+    ///
+    /// let pieces = vec![
+    ///     ("<unk>".to_string(), 0.0),
+    ///     ("a".to_string(), 0.0),
+    ///     ("b".to_string(), 0.0),
+    ///     ("ab".to_string(), 1.0),
+    /// ];
+    /// let model = Unigram::from(pieces, Some(0), false).unwrap();
+    /// let results = model.encode_nbest("ab", 2).unwrap();
+    /// assert_eq!(results, vec![vec!["ab"], vec!["a", "b"]]);
+    /// ```
+    pub fn encode_nbest(&self, sentence: &str, n: usize) -> Result<Vec<Vec<String>>> {
+        if sentence.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut lattice = Lattice::from(sentence, self.bos_id, self.eos_id);
+        self.populate_nodes(&mut lattice);
+        Ok(lattice.nbest_tokens(n))
+    }
+
+    /// Sample a tokenization of `sentence` from the lattice, using the given
+    /// smoothing parameter `theta` (often called alpha in the subword
+    /// regularization literature). With a small `theta` the distribution over
+    /// tokenizations is close to uniform, while a large one concentrates it on
+    /// the best-scoring paths.
+    pub fn encode_sample(&self, sentence: &str, theta: f64) -> Result<Vec<String>> {
+        if sentence.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut lattice = Lattice::from(sentence, self.bos_id, self.eos_id);
+        self.populate_nodes(&mut lattice);
+        Ok(lattice.sample_token(theta))
+    }
+
+    /// Build the full Viterbi lattice for the given token, and export it as a
+    /// [`LatticeGraph`] of nodes, edges and scores, to inspect why a
+    /// particular segmentation won. The graph serializes to JSON with serde,
+    /// or to Graphviz DOT with [`LatticeGraph::to_dot`].
+    pub fn lattice(&self, token: &str) -> LatticeGraph {
+        let mut lattice = Lattice::from(token, self.bos_id, self.eos_id);
+        self.populate_nodes(&mut lattice);
+        lattice.to_graph()
+    }
+
     /// Iterate of vocabulary of the model as a pair of `(token, score)`.
     pub fn iter(&self) -> UnigramIterator {
         UnigramIterator { model: self, i: 0 }
@@ -407,6 +585,24 @@ impl Model for Unigram {
         self.token_to_ids.clone()
     }
 
+    fn get_vocab_iter(&self) -> Box<dyn Iterator<Item = (Cow<'_, str>, u32)> + '_> {
+        Box::new(
+            self.vocab
+                .iter()
+                .enumerate()
+                .map(|(id, (token, _))| (Cow::Borrowed(token.as_str()), id as u32)),
+        )
+    }
+
+    fn get_vocab_r(&self) -> Box<dyn Iterator<Item = (u32, Cow<'_, str>)> + '_> {
+        Box::new(
+            self.vocab
+                .iter()
+                .enumerate()
+                .map(|(id, (token, _))| (id as u32, Cow::Borrowed(token.as_str()))),
+        )
+    }
+
     fn get_vocab_size(&self) -> usize {
         self.vocab.len()
     }
@@ -447,6 +643,52 @@ impl Model for Unigram {
         Ok(tokens)
     }
 
+    fn tokenize_constrained(&self, sentence: &str, allowed: &VocabMask) -> Result<Vec<Token>> {
+        if sentence.is_empty() {
+            return Ok(vec![]);
+        }
+        // The segmentation depends on the mask, so the sentence cache is
+        // bypassed and the full lattice is used instead of the optimized
+        // encoder
+        let mut str_tokens = vec![];
+        for (segment, is_symbol) in self.split_on_user_defined_symbols(sentence) {
+            if is_symbol {
+                str_tokens.push(segment.to_string());
+                continue;
+            }
+            let mut lattice = Lattice::from(segment, self.bos_id, self.eos_id);
+            self.populate_nodes_masked(&mut lattice, Some(allowed));
+            let pieces = lattice.tokens();
+            // An empty Viterbi path means some position is not covered by
+            // any allowed piece
+            if pieces.is_empty() {
+                return Err(Box::new(UnigramError::NoAllowedSegmentation(
+                    segment.to_string(),
+                )));
+            }
+            str_tokens.extend(pieces);
+        }
+
+        let mut offset = 0;
+        let mut tokens = Vec::with_capacity(str_tokens.len());
+        for string in str_tokens {
+            let len = string.len();
+            let offsets = (offset, offset + len);
+            offset += len;
+            let id = match self.token_to_ids.get(&string) {
+                Some(id) => *id,
+                None => self.unk_id.ok_or(UnigramError::MissingUnkId)? as u32,
+            };
+            // User-defined symbols and unknown pieces bypass the lattice, so
+            // their ids still have to be checked against the mask
+            if !allowed.contains(id) {
+                return Err(Box::new(UnigramError::NoAllowedSegmentation(string)));
+            }
+            tokens.push(Token::new(id, string, offsets));
+        }
+        Ok(tokens)
+    }
+
     fn token_to_id(&self, token: &str) -> Option<u32> {
         self.token_to_ids.get(token).copied()
     }
@@ -471,12 +713,55 @@ impl Model for Unigram {
     fn get_trainer(&self) -> Self::Trainer {
         UnigramTrainer::default()
     }
+
+    fn token_info(&self, id: u32) -> Option<TokenInfo> {
+        let (_, score) = self.vocab.get(id as usize)?;
+        Some(TokenInfo {
+            score: Some(*score),
+            ..TokenInfo::default()
+        })
+    }
+
+    fn token_scores(&self, ids: &[u32]) -> Option<Vec<Option<f64>>> {
+        Some(
+            ids.iter()
+                .map(|id| self.vocab.get(*id as usize).map(|(_, score)| *score))
+                .collect(),
+        )
+    }
+
+    fn unk_token(&self) -> Option<String> {
+        self.unk_id
+            .and_then(|id| self.vocab.get(id))
+            .map(|(token, _)| token.clone())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_token_info() {
+        let pieces = vec![("<unk>".to_string(), 0.0), ("a".to_string(), -0.5)];
+        let model = Unigram::from(pieces, Some(0), false).unwrap();
+        assert_eq!(
+            model.token_info(1),
+            Some(TokenInfo {
+                score: Some(-0.5),
+                ..TokenInfo::default()
+            })
+        );
+        assert_eq!(model.token_info(10), None);
+    }
+
+    #[test]
+    fn test_token_scores() {
+        let pieces = vec![("<unk>".to_string(), 0.0), ("a".to_string(), -0.5)];
+        let model = Unigram::from(pieces, Some(0), false).unwrap();
+        assert_eq!(model.token_scores(&[1, 10]), Some(vec![Some(-0.5), None]));
+    }
+
     #[test]
     fn test_populate_nodes_unk() {
         let pieces = vec![("<unk>".to_string(), 0.0)];
@@ -547,6 +832,68 @@ mod tests {
         assert_eq!(result, vec!["abcd"]);
     }
 
+    #[test]
+    fn test_tokenize_constrained() {
+        let sentencepieces = vec![
+            ("<unk>".to_string(), 0.0),
+            ("a".to_string(), 0.0),
+            ("b".to_string(), 0.0),
+            ("c".to_string(), 0.0),
+            ("d".to_string(), 0.0),
+            ("cd".to_string(), 1.0),
+            ("ab".to_string(), 2.0),
+            ("abc".to_string(), 5.0),
+            ("abcd".to_string(), 10.0),
+        ];
+        let model = Unigram::from(sentencepieces, Some(0), false).unwrap();
+
+        // Masking out `abcd` makes the next best path win
+        let allowed: VocabMask = (0u32..8).collect();
+        let tokens = model.tokenize_constrained("abcd", &allowed).unwrap();
+        assert_eq!(
+            tokens
+                .iter()
+                .map(|token| token.value.as_str())
+                .collect::<Vec<_>>(),
+            vec!["abc", "d"]
+        );
+
+        // With the unknown token also excluded, a sentence needing it has no
+        // allowed segmentation
+        let allowed: VocabMask = (1u32..8).collect();
+        assert!(model.tokenize_constrained("axcd", &allowed).is_err());
+    }
+
+    #[test]
+    fn test_add_remove_token() {
+        let sentencepieces = vec![
+            ("<unk>".to_string(), 0.0),
+            ("a".to_string(), -1.0),
+            ("b".to_string(), -2.0),
+            ("ab".to_string(), -2.5),
+        ];
+        let mut model = Unigram::from(sentencepieces, Some(0), false).unwrap();
+        assert_eq!(model.encode("ab").unwrap(), vec!["ab"]);
+
+        // The removed piece stops taking part in segmentation
+        assert_eq!(model.remove_token(3), Some("ab".to_string()));
+        assert_eq!(model.encode("ab").unwrap(), vec!["a", "b"]);
+
+        // Removing an earlier piece shifts the following ids down
+        assert_eq!(model.remove_token(1), Some("a".to_string()));
+        assert_eq!(model.token_to_id("b"), Some(1));
+
+        // A new piece gets the next id and the current minimum score
+        assert_eq!(model.add_token("c"), 2);
+        assert_eq!(model.token_to_id("c"), Some(2));
+        assert_eq!(model.vocab[2], ("c".to_string(), -2.0));
+        assert_eq!(model.add_token_with_score("d", -0.5), 3);
+        assert_eq!(model.vocab[3], ("d".to_string(), -0.5));
+        // While an existing one keeps its id and score
+        assert_eq!(model.add_token("b"), 1);
+        assert_eq!(model.vocab[1], ("b".to_string(), -2.0));
+    }
+
     #[test]
     fn test_encode2() {
         let sentencepieces = vec![
@@ -632,4 +979,100 @@ mod tests {
         let tokens = unigram.tokenize("?é").unwrap();
         assert_eq!(tokens[0].id, 0);
     }
+
+    #[test]
+    fn test_encode_nbest_and_sample() {
+        let pieces = vec![
+            ("<unk>".to_string(), 0.0),
+            ("a".to_string(), 0.0),
+            ("b".to_string(), 0.0),
+            ("c".to_string(), 0.0),
+            ("ab".to_string(), 2.0),
+            ("bc".to_string(), 1.0),
+            ("abc".to_string(), 4.0),
+        ];
+        let model = Unigram::from(pieces, Some(0), false).unwrap();
+
+        let nbest = model.encode_nbest("abc", 3).unwrap();
+        assert_eq!(nbest, vec![vec!["abc"], vec!["ab", "c"], vec!["a", "bc"]]);
+
+        // Sampling always returns one of the valid tokenizations of the input
+        for _ in 0..10 {
+            let sampled = model.encode_sample("abc", 1.0).unwrap();
+            assert_eq!(sampled.join(""), "abc");
+        }
+
+        assert!(model.encode_nbest("", 3).unwrap().is_empty());
+        assert!(model.encode_sample("", 1.0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_lattice_graph() {
+        let pieces = vec![
+            ("<unk>".to_string(), 0.0),
+            ("a".to_string(), 0.0),
+            ("b".to_string(), 0.0),
+            ("c".to_string(), 0.0),
+            ("ab".to_string(), 2.0),
+            ("bc".to_string(), 1.0),
+            ("abc".to_string(), 4.0),
+        ];
+        let model = Unigram::from(pieces, Some(0), false).unwrap();
+
+        let graph = model.lattice("abc");
+        assert_eq!(graph.sentence, "abc");
+
+        // The Viterbi path follows the best segmentation
+        let viterbi_pieces: Vec<_> = graph
+            .viterbi
+            .iter()
+            .map(|node_id| graph.nodes[*node_id].piece.as_str())
+            .collect();
+        assert_eq!(viterbi_pieces, vec!["abc"]);
+
+        // All the single characters and matching pieces show up as nodes
+        let mut pieces: Vec<_> = graph
+            .nodes
+            .iter()
+            .map(|node| node.piece.as_str())
+            .filter(|piece| !piece.is_empty())
+            .collect();
+        pieces.sort_unstable();
+        assert_eq!(pieces, vec!["a", "ab", "abc", "b", "bc", "c"]);
+
+        // Serializable to JSON and DOT
+        let json = serde_json::to_value(&graph).unwrap();
+        assert_eq!(json["viterbi"], serde_json::json!(graph.viterbi));
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph lattice {"));
+        assert!(dot.contains("abc (4.000)"));
+    }
+
+    #[test]
+    fn test_user_defined_symbols() {
+        let pieces = vec![
+            ("<unk>".to_string(), 0.0),
+            ("a".to_string(), 0.0),
+            ("b".to_string(), 0.0),
+            ("ab".to_string(), -10.0),
+        ];
+        let mut model = Unigram::from(pieces, Some(0), false).unwrap();
+        // "ab" scores much worse than "a" + "b", so it is never picked
+        assert_eq!(model.encode("aabb").unwrap(), vec!["a", "a", "b", "b"]);
+
+        model
+            .set_user_defined_symbols(vec!["ab".to_string()])
+            .unwrap();
+        assert_eq!(model.encode("aabb").unwrap(), vec!["a", "ab", "b"]);
+        let tokens = model.tokenize("aabb").unwrap();
+        assert_eq!(
+            tokens.iter().map(|t| t.offsets).collect::<Vec<_>>(),
+            vec![(0, 1), (1, 3), (3, 4)]
+        );
+
+        // Symbols must be part of the vocabulary
+        assert!(model
+            .set_user_defined_symbols(vec!["missing".to_string()])
+            .is_err());
+    }
 }
@@ -16,6 +16,9 @@ impl Serialize for Unigram {
         model.serialize_field("unk_id", &self.unk_id)?;
         model.serialize_field("vocab", &self.vocab)?;
         model.serialize_field("byte_fallback", &self.byte_fallback())?;
+        if !self.user_defined_symbols().is_empty() {
+            model.serialize_field("user_defined_symbols", &self.user_defined_symbols())?;
+        }
 
         model.end()
     }
@@ -28,7 +31,13 @@ impl<'de> Deserialize<'de> for Unigram {
     {
         deserializer.deserialize_struct(
             "Unigram",
-            &["type", "vocab", "unk_id", "byte_fallback"],
+            &[
+                "type",
+                "vocab",
+                "unk_id",
+                "byte_fallback",
+                "user_defined_symbols",
+            ],
             UnigramVisitor,
         )
     }
@@ -49,12 +58,14 @@ impl<'de> Visitor<'de> for UnigramVisitor {
         let mut vocab: Option<Vec<(String, f64)>> = None;
         let mut unk_id: Option<usize> = None;
         let mut byte_fallback: bool = false;
+        let mut user_defined_symbols: Vec<String> = vec![];
         while let Some(key) = map.next_key::<String>()? {
             match key.as_ref() {
                 "unk_id" => {
                     unk_id = map.next_value()?;
                 }
                 "byte_fallback" => byte_fallback = map.next_value()?,
+                "user_defined_symbols" => user_defined_symbols = map.next_value()?,
                 "vocab" => vocab = Some(map.next_value()?),
                 "type" => match map.next_value()? {
                     "Unigram" => {}
@@ -69,8 +80,14 @@ impl<'de> Visitor<'de> for UnigramVisitor {
             }
         }
         match (vocab, unk_id, byte_fallback) {
-            (Some(vocab), unk_id, byte_fallback) => Ok(Unigram::from(vocab, unk_id, byte_fallback)
-                .map_err(|err| Error::custom(format!("Unable to load vocab {:?}", err)))?),
+            (Some(vocab), unk_id, byte_fallback) => {
+                let mut model = Unigram::from(vocab, unk_id, byte_fallback)
+                    .map_err(|err| Error::custom(format!("Unable to load vocab {:?}", err)))?;
+                model
+                    .set_user_defined_symbols(user_defined_symbols)
+                    .map_err(Error::custom)?;
+                Ok(model)
+            }
             (None, _, _) => Err(Error::custom("Missing vocab")),
         }
     }
@@ -102,6 +119,24 @@ mod test {
         assert_eq!(model, reconstructed);
     }
 
+    #[test]
+    fn test_serialization_user_defined_symbols() {
+        let vocab = vec![("<unk>".to_string(), 0.0), ("a".to_string(), -0.5)];
+        let mut model = Unigram::from(vocab, Some(0), false).unwrap();
+
+        // Without symbols the serialized form is unchanged
+        let data = serde_json::to_string(&model).unwrap();
+        assert!(!data.contains("user_defined_symbols"));
+
+        model
+            .set_user_defined_symbols(vec!["a".to_string()])
+            .unwrap();
+        let data = serde_json::to_string(&model).unwrap();
+        assert!(data.contains(r#""user_defined_symbols":["a"]"#));
+        let reconstructed: Unigram = serde_json::from_str(&data).unwrap();
+        assert_eq!(reconstructed.user_defined_symbols(), ["a".to_string()]);
+    }
+
     #[test]
     fn test_serialization_no_unk_id() {
         let vocab = vec![("a".to_string(), -0.5)];
@@ -1,12 +1,16 @@
 use crate::models::unigram::{lattice::Lattice, model::Unigram};
-use crate::tokenizer::{AddedToken, Result, Trainer};
+use crate::tokenizer::{AddedToken, Result, Trainer, TrainingReport};
 use crate::utils::parallelism::*;
 use crate::utils::progress::{ProgressBar, ProgressStyle};
+use crate::utils::sketch::SpaceSavingCounter;
+use crate::utils::spill::SpillingWordCounter;
 use log::debug;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::cmp::Reverse;
 use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
+use std::path::Path;
 
 // A token and a score
 type SentencePiece = (String, f64);
@@ -65,10 +69,43 @@ pub struct UnigramTrainer {
 
     #[builder(default = "16")]
     pub max_piece_length: usize,
+    /// Tokens that must never be part of the vocabulary. They are filtered out of the
+    /// seed sentence pieces, before the EM vocabulary selection happens
+    #[builder(default = "HashSet::new()")]
+    #[serde(default)]
+    pub blocked_tokens: HashSet<String>,
+    /// A regex pattern that candidate pieces must NOT match, e.g. `[a-z]+[0-9]` to
+    /// prevent pieces mixing letters and digits
+    #[builder(default = "None")]
+    #[serde(default)]
+    pub blocked_pattern: Option<String>,
+    /// An optional memory budget, in megabytes, for the word counting phase:
+    /// above it, the in-progress word-count map is spilled to sorted runs on
+    /// disk which are merged back once the feeding ends. This bounds the
+    /// transient memory used while counting huge corpora, at the cost of
+    /// sequential counting
+    #[builder(default = "None")]
+    #[serde(default)]
+    pub max_memory_mb: Option<usize>,
+    /// An optional cap on the number of words the counting phase may track.
+    /// When set, the counts are approximated with the space-saving algorithm:
+    /// only the heaviest words are kept, with counts overestimated by at most
+    /// `total / max_tracked_words`, bounding the counting memory on corpora
+    /// with too many unique words to count exactly
+    #[builder(default = "None")]
+    #[serde(default)]
+    pub max_tracked_words: Option<usize>,
     #[builder(default = "1_000_000")]
     seed_size: usize,
     #[builder(default = "HashMap::new()")]
     words: HashMap<String, u32>,
+    /// Whether [`Trainer::train_with_report`] should produce a [`TrainingReport`]
+    #[builder(default = "false")]
+    #[serde(default)]
+    pub report: bool,
+    #[builder(default, private)]
+    #[serde(default)]
+    validation: Vec<String>,
 }
 
 impl Default for UnigramTrainer {
@@ -197,7 +234,18 @@ impl UnigramTrainer {
         &self,
         sentences: &[Sentence],
         _progress: &Option<ProgressBar>,
-    ) -> Vec<SentencePiece> {
+    ) -> Result<Vec<SentencePiece>> {
+        let blocked_pattern = self
+            .blocked_pattern
+            .as_deref()
+            .map(Regex::new)
+            .transpose()?;
+        let is_blocked = |piece: &str| {
+            self.blocked_tokens.contains(piece)
+                || blocked_pattern
+                    .as_ref()
+                    .is_some_and(|re| re.is_match(piece))
+        };
         // Put all sentences in a string, separated by \0
         let total: usize = sentences
             .iter()
@@ -266,13 +314,16 @@ impl UnigramTrainer {
             // Just in case
             assert!(self.is_valid_sentencepiece(char_string));
             let string: String = char_string.iter().collect();
+            if is_blocked(&string) {
+                continue;
+            }
             seed_sentencepieces.push((string, score.into()));
             if seed_sentencepieces.len() >= self.seed_size {
                 break;
             }
         }
         to_log_prob(&mut seed_sentencepieces);
-        seed_sentencepieces
+        Ok(seed_sentencepieces)
     }
     fn prune_sentence_pieces(
         &self,
@@ -541,7 +592,7 @@ impl UnigramTrainer {
 
         // We use a UNK token when training, whatever the `self.unk_token`
         pieces.push(("<UNK>".into(), f64::NAN));
-        pieces.extend(self.make_seed_sentence_pieces(&sentences, &progress));
+        pieces.extend(self.make_seed_sentence_pieces(&sentences, &progress)?);
         self.finalize_progress(&progress, sentences.len());
 
         // Useful to check compatibility with spm.
@@ -631,6 +682,30 @@ impl Trainer for UnigramTrainer {
         S: AsRef<str> + Send,
         F: Fn(&str) -> Result<Vec<String>> + Sync,
     {
+        if let Some(max_tracked_words) = self.max_tracked_words {
+            // Approximate counting: keep only the heaviest words, evicting
+            // the rarest ones whenever the counters are full
+            let mut counter = SpaceSavingCounter::new(max_tracked_words);
+            for sequence in iterator {
+                for word in process(sequence.as_ref())? {
+                    counter.add(word, 1u32);
+                }
+            }
+            self.words = counter.finish();
+            return Ok(());
+        }
+        if let Some(max_memory_mb) = self.max_memory_mb {
+            // Memory-bounded counting: accumulate sequentially, spilling the
+            // counts to disk whenever the budget is exceeded
+            let mut counter = SpillingWordCounter::new(max_memory_mb);
+            for sequence in iterator {
+                for word in process(sequence.as_ref())? {
+                    counter.add(word, 1u32)?;
+                }
+            }
+            self.words = counter.finish()?;
+            return Ok(());
+        }
         let words: Result<HashMap<String, u32>> = iterator
             .maybe_par_bridge()
             .map(|sequence| {
@@ -655,6 +730,118 @@ impl Trainer for UnigramTrainer {
         self.words = words?;
         Ok(())
     }
+
+    fn feed_weighted<I, S, F>(&mut self, iterator: I, process: F) -> Result<()>
+    where
+        I: Iterator<Item = (S, f64)> + Send,
+        S: AsRef<str> + Send,
+        F: Fn(&str) -> Result<Vec<String>> + Sync,
+    {
+        if let Some(max_tracked_words) = self.max_tracked_words {
+            let mut counter = SpaceSavingCounter::new(max_tracked_words);
+            for (sequence, weight) in iterator {
+                let weight = weight.round() as u32;
+                if weight == 0 {
+                    continue;
+                }
+                for word in process(sequence.as_ref())? {
+                    counter.add(word, weight);
+                }
+            }
+            self.words = counter.finish();
+            return Ok(());
+        }
+        if let Some(max_memory_mb) = self.max_memory_mb {
+            let mut counter = SpillingWordCounter::new(max_memory_mb);
+            for (sequence, weight) in iterator {
+                for word in process(sequence.as_ref())? {
+                    counter.add(word, weight)?;
+                }
+            }
+            self.words = counter
+                .finish()?
+                .into_iter()
+                .filter_map(|(word, count)| {
+                    let count = count.round() as u32;
+                    (count > 0).then_some((word, count))
+                })
+                .collect();
+            return Ok(());
+        }
+        let words: Result<HashMap<String, f64>> = iterator
+            .maybe_par_bridge()
+            .map(|(sequence, weight)| {
+                let words = process(sequence.as_ref())?;
+                let mut map = HashMap::new();
+                for word in words {
+                    map.entry(word)
+                        .and_modify(|c| *c += weight)
+                        .or_insert(weight);
+                }
+                Ok(map)
+            })
+            .reduce(
+                || Ok(HashMap::new()),
+                |acc, ws| {
+                    let mut acc = acc?;
+                    for (k, v) in ws? {
+                        acc.entry(k).and_modify(|c| *c += v).or_insert(v);
+                    }
+                    Ok(acc)
+                },
+            );
+
+        self.words = words?
+            .into_iter()
+            .filter_map(|(word, count)| {
+                let count = count.round() as u32;
+                (count > 0).then_some((word, count))
+            })
+            .collect();
+        Ok(())
+    }
+
+    fn feed_validation<I, S>(&mut self, iterator: I) -> Result<()>
+    where
+        I: Iterator<Item = S> + Send,
+        S: AsRef<str> + Send,
+    {
+        self.validation = iterator.map(|s| s.as_ref().to_owned()).collect();
+        Ok(())
+    }
+
+    fn train_with_report(
+        &self,
+        model: &mut Unigram,
+    ) -> Result<(Vec<AddedToken>, Option<TrainingReport>)> {
+        let special_tokens = self.train(model)?;
+        let report = if self.report {
+            let word_counts = self
+                .words
+                .iter()
+                .map(|(word, count)| (word.clone(), *count as u64))
+                .collect();
+            Some(TrainingReport::compute(
+                &word_counts,
+                &self.validation,
+                model,
+                self.unk_token.as_deref(),
+            )?)
+        } else {
+            None
+        };
+        Ok((special_tokens, report))
+    }
+
+    fn save_checkpoint(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    fn resume_from_checkpoint(&mut self, path: &Path) -> Result<()> {
+        *self = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -679,7 +866,9 @@ mod tests {
         assert_eq!(required_chars.len(), 13);
 
         let progress = None;
-        let table = trainer.make_seed_sentence_pieces(&sentences, &progress);
+        let table = trainer
+            .make_seed_sentence_pieces(&sentences, &progress)
+            .unwrap();
 
         let target_strings = vec![
             "s", "i", " ", "達", "友", "ん", "は", "に", "ち", "こ", "h", "a", "T", "is ", "s ",
@@ -712,6 +901,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_blocked_tokens() {
+        let trainer = UnigramTrainerBuilder::default()
+            .show_progress(false)
+            .blocked_tokens(HashSet::from_iter(vec!["is ".to_string()]))
+            .blocked_pattern(Some("^s $".into()))
+            .build()
+            .unwrap();
+
+        let sentences = vec![
+            ("This is a".to_string(), 1),
+            ("こんにちは友達".to_string(), 1),
+        ];
+
+        let progress = None;
+        let table = trainer
+            .make_seed_sentence_pieces(&sentences, &progress)
+            .unwrap();
+        let strings: Vec<_> = table.iter().map(|(string, _)| string.as_str()).collect();
+
+        // Compared to `test_unigram_chars`, the two blocked pieces are excluded from
+        // the seed vocabulary, but the single chars are still all there
+        assert!(!strings.contains(&"is "));
+        assert!(!strings.contains(&"s "));
+        assert!(strings.contains(&"T"));
+        assert!(strings.contains(&"達"));
+    }
+
     #[test]
     fn test_initial_alphabet() {
         let trainer = UnigramTrainerBuilder::default()
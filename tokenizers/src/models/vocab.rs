@@ -0,0 +1,156 @@
+//! A vocabulary mapping tokens to their ids, with allocation-free lookups.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::iter::FromIterator;
+
+/// A vocabulary, mapping tokens to their ids.
+///
+/// Compared to a plain `HashMap<String, u32>`, a `Vocab` can look tokens up from
+/// borrowed and composite keys, so that `Model::tokenize` implementations do not
+/// need to allocate a fresh `String` for every candidate sub-token. See
+/// [`get_with_prefix`] for the typical hot-path usage.
+///
+/// We keep the `RandomState` hasher from the standard library: it processes bytes
+/// as a plain stream, which lets [`get_with_prefix`] hash a composite key in
+/// several `write` calls while still matching the hash of the stored `String`.
+///
+/// [`get_with_prefix`]: #method.get_with_prefix
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Vocab {
+    map: hashbrown::HashMap<String, u32, RandomState>,
+}
+
+impl Vocab {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Get the id associated to the given token
+    pub fn get(&self, token: &str) -> Option<u32> {
+        self.map.get(token).copied()
+    }
+
+    /// Whether the given token is part of the vocabulary
+    pub fn contains(&self, token: &str) -> bool {
+        self.map.contains_key(token)
+    }
+
+    /// Get the id associated to the concatenation of `prefix` and `token`, without
+    /// actually building the concatenated `String`
+    pub fn get_with_prefix(&self, prefix: &str, token: &str) -> Option<u32> {
+        if prefix.is_empty() {
+            return self.get(token);
+        }
+        // `Hash for str` writes the bytes of the string followed by a single 0xff
+        // byte, and `RandomState`'s hasher only depends on the resulting byte
+        // stream, so we can hash both parts as if they were one string
+        let mut hasher = self.map.hasher().build_hasher();
+        hasher.write(prefix.as_bytes());
+        hasher.write(token.as_bytes());
+        hasher.write_u8(0xff);
+        let hash = hasher.finish();
+
+        self.map
+            .raw_entry()
+            .from_hash(hash, |key| {
+                key.len() == prefix.len() + token.len()
+                    && key.starts_with(prefix)
+                    && key.ends_with(token)
+            })
+            .map(|(_, id)| *id)
+    }
+
+    pub fn insert(&mut self, token: String, id: u32) -> Option<u32> {
+        self.map.insert(token, id)
+    }
+
+    /// Remove the given token, returning its id if it was present
+    pub fn remove(&mut self, token: &str) -> Option<u32> {
+        self.map.remove(token)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &u32)> {
+        self.map.iter()
+    }
+}
+
+impl PartialEq<HashMap<String, u32>> for Vocab {
+    fn eq(&self, other: &HashMap<String, u32>) -> bool {
+        self.map.len() == other.len()
+            && self
+                .map
+                .iter()
+                .all(|(k, v)| other.get(k).is_some_and(|id| id == v))
+    }
+}
+
+impl From<HashMap<String, u32>> for Vocab {
+    fn from(map: HashMap<String, u32>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+impl From<&Vocab> for HashMap<String, u32> {
+    fn from(vocab: &Vocab) -> Self {
+        vocab.iter().map(|(k, v)| (k.clone(), *v)).collect()
+    }
+}
+
+impl FromIterator<(String, u32)> for Vocab {
+    fn from_iter<I: IntoIterator<Item = (String, u32)>>(iter: I) -> Self {
+        Self {
+            map: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl Serialize for Vocab {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_map(self.map.iter())
+    }
+}
+
+impl<'de> Deserialize<'de> for Vocab {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(HashMap::<String, u32>::deserialize(deserializer)?.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_with_prefix() {
+        let vocab: Vocab = [("hello".into(), 0), ("##llo".into(), 1)]
+            .iter()
+            .cloned()
+            .collect::<HashMap<String, u32>>()
+            .into();
+
+        assert_eq!(vocab.get("hello"), Some(0));
+        assert_eq!(vocab.get_with_prefix("", "hello"), Some(0));
+        assert_eq!(vocab.get_with_prefix("##", "llo"), Some(1));
+        assert_eq!(vocab.get_with_prefix("##", "lo"), None);
+        // The concatenation must match the full key, not a prefix or suffix of it
+        assert_eq!(vocab.get_with_prefix("##", "hello"), None);
+        assert_eq!(vocab.get_with_prefix("he", "llo"), Some(0));
+    }
+}
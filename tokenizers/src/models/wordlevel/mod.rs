@@ -1,6 +1,7 @@
 use super::OrderedVocabIter;
 use crate::tokenizer::{Model, Result, Token};
 use serde_json::Value;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufReader, Read, Write};
@@ -12,7 +13,7 @@ mod trainer;
 // Re-export
 pub use trainer::*;
 
-type Vocab = HashMap<String, u32>;
+pub use crate::models::vocab::Vocab;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -24,8 +25,9 @@ pub enum Error {
 
 struct Config {
     files: Option<String>,
-    vocab: HashMap<String, u32>,
+    vocab: Vocab,
     unk_token: String,
+    keep_whitespace: bool,
 }
 
 /// A `WordLevelBuilder` can be used to create a `WordLevel`
@@ -39,8 +41,9 @@ impl Default for WordLevelBuilder {
         Self {
             config: Config {
                 files: None,
-                vocab: HashMap::new(),
+                vocab: Vocab::new(),
                 unk_token: String::from("<unk>"),
+                keep_whitespace: false,
             },
         }
     }
@@ -61,7 +64,7 @@ impl WordLevelBuilder {
 
     /// Set the vocab (token -> ID) mapping.
     #[must_use]
-    pub fn vocab(mut self, vocab: HashMap<String, u32>) -> Self {
+    pub fn vocab(mut self, vocab: Vocab) -> Self {
         self.config.vocab = vocab;
         self
     }
@@ -73,6 +76,14 @@ impl WordLevelBuilder {
         self
     }
 
+    /// Emit separate tokens for whitespace runs, looked up in the vocabulary
+    /// like any other word, instead of treating the whole input as one word.
+    #[must_use]
+    pub fn keep_whitespace(mut self, keep_whitespace: bool) -> Self {
+        self.config.keep_whitespace = keep_whitespace;
+        self
+    }
+
     /// Contructs a `WordLevel` model that uses the `WordLevelBuilder`'s configuration.
     pub fn build(mut self) -> Result<WordLevel> {
         if let Some(vocab) = self.config.files {
@@ -90,15 +101,21 @@ impl WordLevelBuilder {
             vocab: self.config.vocab,
             vocab_r,
             unk_token: self.config.unk_token,
+            keep_whitespace: self.config.keep_whitespace,
         })
     }
 }
 
 #[derive(PartialEq, Clone, Eq)]
 pub struct WordLevel {
-    vocab: HashMap<String, u32>,
+    vocab: Vocab,
     vocab_r: HashMap<u32, String>,
     pub unk_token: String,
+    /// Whether whitespace runs become tokens of their own (with their own
+    /// vocabulary entries) instead of the whole input being one word, so that
+    /// detokenization is exact. Expects a pre-tokenizer that keeps whitespace
+    /// in the pre-tokens, or none at all.
+    pub keep_whitespace: bool,
 }
 
 impl std::fmt::Debug for WordLevel {
@@ -119,7 +136,7 @@ impl WordLevel {
         let vocab_file = File::open(vocab_path)?;
         let mut vocab_file = BufReader::new(vocab_file);
         let mut buffer = String::new();
-        let mut vocab = HashMap::new();
+        let mut vocab = Vocab::new();
 
         vocab_file.read_to_string(&mut buffer)?;
         let json: Value = serde_json::from_str(&buffer)?;
@@ -143,14 +160,53 @@ impl WordLevel {
         let vocab = WordLevel::read_file(vocab_path)?;
         Self::builder().vocab(vocab).unk_token(unk_token).build()
     }
+
+    /// Add `token` to the vocabulary, returning its newly assigned id, or
+    /// the id it already has
+    pub fn add_token(&mut self, token: &str) -> u32 {
+        if let Some(id) = self.vocab.get(token) {
+            return id;
+        }
+        let id = self.vocab_r.keys().max().map_or(0, |max| max + 1);
+        self.vocab.insert(token.to_owned(), id);
+        self.vocab_r.insert(id, token.to_owned());
+        id
+    }
+
+    /// Remove the token with the given id from the vocabulary, returning it
+    pub fn remove_token(&mut self, id: u32) -> Option<String> {
+        let token = self.vocab_r.remove(&id)?;
+        self.vocab.remove(&token);
+        Some(token)
+    }
+
+    /// Lookup a single word in the vocabulary, falling back on the unk token
+    fn token_for(&self, word: &str, offsets: (usize, usize)) -> Result<Token> {
+        if let Some(id) = self.vocab.get(word) {
+            Ok(Token {
+                id,
+                value: word.to_owned(),
+                offsets,
+            })
+        } else if let Some(unk_id) = self.vocab.get(&self.unk_token) {
+            Ok(Token {
+                id: unk_id,
+                value: self.unk_token.to_owned(),
+                offsets,
+            })
+        } else {
+            Err(Box::new(Error::MissingUnkToken))
+        }
+    }
 }
 
 impl Default for WordLevel {
     fn default() -> Self {
         Self {
-            vocab: HashMap::new(),
+            vocab: Vocab::new(),
             vocab_r: HashMap::new(),
             unk_token: String::from("<unk>"),
+            keep_whitespace: false,
         }
     }
 }
@@ -159,25 +215,25 @@ impl Model for WordLevel {
     type Trainer = WordLevelTrainer;
 
     fn tokenize(&self, token: &str) -> Result<Vec<Token>> {
-        if let Some(&id) = self.vocab.get(token) {
-            Ok(vec![Token {
-                id,
-                value: token.to_owned(),
-                offsets: (0, token.len()),
-            }])
-        } else if let Some(&unk_id) = self.vocab.get(&self.unk_token) {
-            Ok(vec![Token {
-                id: unk_id,
-                value: self.unk_token.to_owned(),
-                offsets: (0, token.len()),
-            }])
-        } else {
-            Err(Box::new(Error::MissingUnkToken))
+        if !self.keep_whitespace || token.is_empty() {
+            return Ok(vec![self.token_for(token, (0, token.len()))?]);
+        }
+        let mut tokens = vec![];
+        let mut start = 0;
+        let mut was_whitespace = token.chars().next().unwrap().is_whitespace();
+        for (i, c) in token.char_indices() {
+            if c.is_whitespace() != was_whitespace {
+                tokens.push(self.token_for(&token[start..i], (start, i))?);
+                start = i;
+                was_whitespace = !was_whitespace;
+            }
         }
+        tokens.push(self.token_for(&token[start..], (start, token.len()))?);
+        Ok(tokens)
     }
 
     fn token_to_id(&self, token: &str) -> Option<u32> {
-        self.vocab.get(token).copied()
+        self.vocab.get(token)
     }
 
     fn id_to_token(&self, id: u32) -> Option<String> {
@@ -185,11 +241,39 @@ impl Model for WordLevel {
     }
 
     fn get_vocab(&self) -> HashMap<String, u32> {
-        self.vocab.clone()
+        (&self.vocab).into()
+    }
+
+    fn get_vocab_iter(&self) -> Box<dyn Iterator<Item = (Cow<'_, str>, u32)> + '_> {
+        let mut entries: Vec<_> = self
+            .vocab
+            .iter()
+            .map(|(token, id)| (token.as_str(), *id))
+            .collect();
+        entries.sort_unstable_by_key(|(_, id)| *id);
+        Box::new(
+            entries
+                .into_iter()
+                .map(|(token, id)| (Cow::Borrowed(token), id)),
+        )
+    }
+
+    fn get_vocab_r(&self) -> Box<dyn Iterator<Item = (u32, Cow<'_, str>)> + '_> {
+        let mut entries: Vec<_> = self
+            .vocab_r
+            .iter()
+            .map(|(id, token)| (*id, token.as_str()))
+            .collect();
+        entries.sort_unstable_by_key(|(id, _)| *id);
+        Box::new(
+            entries
+                .into_iter()
+                .map(|(id, token)| (id, Cow::Borrowed(token))),
+        )
     }
 
     fn get_vocab_size(&self) -> usize {
-        self.vocab.keys().len()
+        self.vocab.len()
     }
 
     fn save(&self, folder: &Path, name: Option<&str>) -> Result<Vec<PathBuf>> {
@@ -213,6 +297,10 @@ impl Model for WordLevel {
     fn get_trainer(&self) -> Self::Trainer {
         WordLevelTrainer::default()
     }
+
+    fn unk_token(&self) -> Option<String> {
+        Some(self.unk_token.clone())
+    }
 }
 
 #[cfg(test)]
@@ -237,6 +325,72 @@ mod tests {
         assert_eq!(tokens, vec![Token::new(1u32, "a".into(), (0, 1)),]);
     }
 
+    #[test]
+    fn test_add_remove_token() {
+        let vocab: Vocab = [("<unk>".into(), 0), ("a".into(), 1), ("b".into(), 2)]
+            .iter()
+            .cloned()
+            .collect();
+        let mut wordlevel = WordLevelBuilder::default()
+            .vocab(vocab)
+            .unk_token("<unk>".to_string())
+            .build()
+            .unwrap();
+
+        assert_eq!(wordlevel.remove_token(2), Some("b".to_string()));
+        let tokens = wordlevel.tokenize("b").unwrap();
+        assert_eq!(tokens, vec![Token::new(0u32, "<unk>".into(), (0, 1))]);
+
+        // A new token gets the next free id
+        assert_eq!(wordlevel.add_token("c"), 2);
+        let tokens = wordlevel.tokenize("c").unwrap();
+        assert_eq!(tokens, vec![Token::new(2u32, "c".into(), (0, 1))]);
+        // While an existing one keeps its id
+        assert_eq!(wordlevel.add_token("a"), 1);
+    }
+
+    #[test]
+    fn test_tokenize_keep_whitespace() {
+        let vocab: Vocab = [
+            ("<unk>".into(), 0),
+            ("hello".into(), 1),
+            (" ".into(), 2),
+            ("world".into(), 3),
+            ("  ".into(), 4),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let wordlevel = WordLevelBuilder::default()
+            .vocab(vocab)
+            .unk_token("<unk>".to_string())
+            .keep_whitespace(true)
+            .build()
+            .unwrap();
+
+        // Whitespace runs become tokens of their own, with exact offsets
+        let tokens = wordlevel.tokenize("hello world").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::new(1u32, "hello".into(), (0, 5)),
+                Token::new(2u32, " ".into(), (5, 6)),
+                Token::new(3u32, "world".into(), (6, 11)),
+            ]
+        );
+
+        // Runs missing from the vocabulary fall back on the unk token
+        let tokens = wordlevel.tokenize("hi  world").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::new(0u32, "<unk>".into(), (0, 2)),
+                Token::new(4u32, "  ".into(), (2, 4)),
+                Token::new(3u32, "world".into(), (4, 9)),
+            ]
+        );
+    }
+
     #[test]
     fn test_tokenize_missing_unk_token() {
         let vocab: Vocab = [("a".into(), 0), ("b".into(), 1)].iter().cloned().collect();
@@ -247,4 +401,31 @@ mod tests {
         let error = wordlevel.tokenize("c").err().unwrap();
         assert!(error.is::<Error>());
     }
+
+    #[test]
+    fn test_get_vocab_iter() {
+        let vocab: Vocab = [("b".into(), 1), ("a".into(), 0), ("c".into(), 2)]
+            .iter()
+            .cloned()
+            .collect();
+        let wordlevel = WordLevelBuilder::default().vocab(vocab).build().unwrap();
+
+        // Both iterators are sorted by ascending id
+        assert_eq!(
+            wordlevel.get_vocab_iter().collect::<Vec<_>>(),
+            vec![
+                (Cow::Borrowed("a"), 0),
+                (Cow::Borrowed("b"), 1),
+                (Cow::Borrowed("c"), 2)
+            ]
+        );
+        assert_eq!(
+            wordlevel.get_vocab_r().collect::<Vec<_>>(),
+            vec![
+                (0, Cow::Borrowed("a")),
+                (1, Cow::Borrowed("b")),
+                (2, Cow::Borrowed("c"))
+            ]
+        );
+    }
 }
@@ -16,6 +16,12 @@ impl Serialize for WordLevel {
         model.serialize_field("type", "WordLevel")?;
         model.serialize_field("vocab", &ordered_vocab)?;
         model.serialize_field("unk_token", &self.unk_token)?;
+        // Only serialized when enabled, to keep older files byte-stable
+        if self.keep_whitespace {
+            model.serialize_field("keep_whitespace", &self.keep_whitespace)?;
+        } else {
+            model.skip_field("keep_whitespace")?;
+        }
         model.end()
     }
 }
@@ -27,7 +33,7 @@ impl<'de> Deserialize<'de> for WordLevel {
     {
         deserializer.deserialize_struct(
             "WordLevel",
-            &["type", "vocab", "unk_token"],
+            &["type", "vocab", "unk_token", "keep_whitespace"],
             WordLevelVisitor,
         )
     }
@@ -57,6 +63,7 @@ impl<'de> Visitor<'de> for WordLevelVisitor {
             match key.as_ref() {
                 "vocab" => builder = builder.vocab(map.next_value()?),
                 "unk_token" => builder = builder.unk_token(map.next_value()?),
+                "keep_whitespace" => builder = builder.keep_whitespace(map.next_value()?),
                 "type" => match map.next_value()? {
                     "WordLevel" => {}
                     u => {
@@ -94,6 +101,20 @@ mod tests {
         assert_eq!(serde_json::from_str::<WordLevel>(wl_s).unwrap(), wl);
     }
 
+    #[test]
+    fn serde_keep_whitespace() {
+        // The option is only serialized when enabled, to keep older files
+        // byte-stable
+        let wl = WordLevelBuilder::default()
+            .keep_whitespace(true)
+            .build()
+            .unwrap();
+        let wl_s = r#"{"type":"WordLevel","vocab":{},"unk_token":"<unk>","keep_whitespace":true}"#;
+
+        assert_eq!(serde_json::to_string(&wl).unwrap(), wl_s);
+        assert_eq!(serde_json::from_str::<WordLevel>(wl_s).unwrap(), wl);
+    }
+
     #[test]
     fn incomplete_vocab() {
         let vocab: Vocab = [("<unk>".into(), 0), ("b".into(), 2)]
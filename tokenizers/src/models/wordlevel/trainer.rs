@@ -1,9 +1,11 @@
+use super::super::assign_ids;
 use super::WordLevel;
 use crate::utils::parallelism::*;
-use crate::{AddedToken, Result, Trainer};
+use crate::{AddedToken, Result, Trainer, TrainingReport};
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::path::Path;
 
 #[non_exhaustive]
 #[derive(Debug, Clone, Builder, Serialize, Deserialize)]
@@ -14,15 +16,38 @@ pub struct WordLevelTrainer {
     /// The target vocabulary size
     #[builder(default = "30_000")]
     pub vocab_size: usize,
+    /// A corpus coverage target in `[0, 1]` used instead of `vocab_size` when
+    /// set: words are taken by decreasing frequency until they account for
+    /// this fraction of all the word occurrences of the corpus (e.g. `0.9995`
+    /// to cover 99.95% of the occurrences), and the vocabulary takes whatever
+    /// size that requires. This mirrors the SentencePiece
+    /// `character_coverage` option, at the word level
+    #[builder(default)]
+    #[serde(default)]
+    pub coverage: Option<f64>,
     /// Whether to show progress while training
     #[builder(default = "true")]
     pub show_progress: bool,
     /// A list of special tokens that the model should know of
     #[builder(default)]
     pub special_tokens: Vec<AddedToken>,
+    /// Whether [`Trainer::train_with_report`] should produce a [`TrainingReport`]
+    #[builder(default = "false")]
+    #[serde(default)]
+    pub report: bool,
+    /// A previous vocabulary, typically [`crate::Model::get_vocab`] of the
+    /// model being refreshed on new data: tokens also present in it keep
+    /// their old ids, and only new tokens get fresh ids appended after the
+    /// highest previous id, so an existing embedding matrix can be reused
+    #[builder(default)]
+    #[serde(default)]
+    pub preserve_ids_from: Option<HashMap<String, u32>>,
 
     #[builder(default, private)]
     words: HashMap<String, u64>,
+    #[builder(default, private)]
+    #[serde(default)]
+    validation: Vec<String>,
 }
 
 impl Default for WordLevelTrainer {
@@ -36,6 +61,52 @@ impl WordLevelTrainer {
         WordLevelTrainerBuilder::default()
     }
 
+    /// Merge pre-computed word counts into the trainer, scaling each count by
+    /// `weight` (rounded to the nearest integer). Unlike [`Trainer::feed`], which
+    /// replaces any previously accumulated counts, this accumulates on top of
+    /// them, so statistics from multiple feeds can be combined with different
+    /// weights.
+    pub fn feed_counts<I>(&mut self, counts: I, weight: f64)
+    where
+        I: IntoIterator<Item = (String, u64)>,
+    {
+        for (word, count) in counts {
+            let count = (count as f64 * weight).round() as u64;
+            if count > 0 {
+                self.words
+                    .entry(word)
+                    .and_modify(|c| *c += count)
+                    .or_insert(count);
+            }
+        }
+    }
+
+    /// Read a pre-computed word-count file (one `word<TAB>count` entry per line,
+    /// as typically produced by a MapReduce job) and merge its counts into the
+    /// trainer, scaled by `weight`. Empty lines are skipped.
+    pub fn feed_count_file<P: AsRef<std::path::Path>>(
+        &mut self,
+        file: P,
+        weight: f64,
+    ) -> Result<()> {
+        let content = std::fs::read_to_string(file)?;
+        let counts = content
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let (word, count) = line
+                    .rsplit_once('\t')
+                    .ok_or_else(|| format!("Invalid word-count line: {line:?}"))?;
+                let count: u64 = count
+                    .parse()
+                    .map_err(|_| format!("Invalid count in line: {line:?}"))?;
+                Ok((word.to_owned(), count))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.feed_counts(counts, weight);
+        Ok(())
+    }
+
     fn do_train(
         &self,
         word_counts: &HashMap<String, u64>,
@@ -55,22 +126,40 @@ impl WordLevelTrainer {
 
         ordered_counts.sort_by(cmp);
 
+        let words = ordered_counts
+            .into_iter()
+            .filter(|(_, n)| **n >= self.min_frequency);
+        let words: Box<dyn Iterator<Item = String>> = match self.coverage {
+            // Stop once the selected words cover the target fraction of the
+            // corpus occurrences, including the word crossing the threshold
+            Some(coverage) => {
+                let total: u64 = word_counts.values().sum();
+                let target = (coverage.clamp(0.0, 1.0) * total as f64).ceil() as u64;
+                let mut covered = 0u64;
+                Box::new(
+                    words
+                        .take_while(move |(_, n)| {
+                            let keep = covered < target;
+                            covered += **n;
+                            keep
+                        })
+                        .map(|(w, _)| w.to_owned()),
+                )
+            }
+            None => Box::new(words.map(|(w, _)| w.to_owned())),
+        };
+        let tokens = self
+            .special_tokens
+            .iter()
+            .map(|token| token.content.clone())
+            .chain(words)
+            .take(match self.coverage {
+                Some(_) => usize::MAX,
+                None => self.vocab_size,
+            });
+
         let word_level = WordLevel::builder()
-            .vocab(
-                self.special_tokens
-                    .iter()
-                    .map(|token| token.content.clone())
-                    .chain(
-                        ordered_counts
-                            .into_iter()
-                            .filter(|(_, n)| **n >= self.min_frequency)
-                            .map(|(w, _)| w.to_owned()),
-                    )
-                    .take(self.vocab_size)
-                    .enumerate()
-                    .map(|(i, w)| (w, i as u32))
-                    .collect(),
-            )
+            .vocab(assign_ids(tokens, self.preserve_ids_from.as_ref()).into())
             .build()?;
 
         // Transfer the vocab
@@ -124,6 +213,82 @@ impl Trainer for WordLevelTrainer {
         self.words = words?;
         Ok(())
     }
+
+    fn feed_weighted<I, S, F>(&mut self, iterator: I, process: F) -> Result<()>
+    where
+        I: Iterator<Item = (S, f64)> + Send,
+        S: AsRef<str> + Send,
+        F: Fn(&str) -> Result<Vec<String>> + Sync,
+    {
+        let words: Result<HashMap<String, f64>> = iterator
+            .maybe_par_bridge()
+            .map(|(sequence, weight)| {
+                let words = process(sequence.as_ref())?;
+                let mut map = HashMap::new();
+                for word in words {
+                    map.entry(word)
+                        .and_modify(|c| *c += weight)
+                        .or_insert(weight);
+                }
+                Ok(map)
+            })
+            .reduce(
+                || Ok(HashMap::new()),
+                |acc, ws| {
+                    let mut acc = acc?;
+                    for (k, v) in ws? {
+                        acc.entry(k).and_modify(|c| *c += v).or_insert(v);
+                    }
+                    Ok(acc)
+                },
+            );
+
+        self.words = words?
+            .into_iter()
+            .filter_map(|(word, count)| {
+                let count = count.round() as u64;
+                (count > 0).then_some((word, count))
+            })
+            .collect();
+        Ok(())
+    }
+
+    fn feed_validation<I, S>(&mut self, iterator: I) -> Result<()>
+    where
+        I: Iterator<Item = S> + Send,
+        S: AsRef<str> + Send,
+    {
+        self.validation = iterator.map(|s| s.as_ref().to_owned()).collect();
+        Ok(())
+    }
+
+    fn train_with_report(
+        &self,
+        model: &mut WordLevel,
+    ) -> Result<(Vec<AddedToken>, Option<TrainingReport>)> {
+        let special_tokens = self.do_train(&self.words, model)?;
+        let report = if self.report {
+            Some(TrainingReport::compute(
+                &self.words,
+                &self.validation,
+                model,
+                Some(&model.unk_token),
+            )?)
+        } else {
+            None
+        };
+        Ok((special_tokens, report))
+    }
+
+    fn save_checkpoint(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    fn resume_from_checkpoint(&mut self, path: &Path) -> Result<()> {
+        *self = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -179,4 +344,128 @@ mod tests {
 
         assert_eq!(model.vocab, expected_vocab);
     }
+
+    #[test]
+    fn test_coverage() {
+        let word_counts: HashMap<String, u64> = [
+            ("the".into(), 25),
+            ("are".into(), 24),
+            ("roses".into(), 22),
+            ("red".into(), 12),
+            ("voilets".into(), 10),
+            ("blue".into(), 16),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+
+        // 109 occurrences in total: covering half of them takes "the" (25),
+        // "are" (49) and "roses" (71), whatever the configured vocab_size
+        let trainer = WordLevelTrainer {
+            vocab_size: 1,
+            coverage: Some(0.5),
+            ..Default::default()
+        };
+        let mut model = WordLevel::default();
+        trainer.do_train(&word_counts, &mut model).unwrap();
+        let expected_vocab: HashMap<String, u32> =
+            [("the".into(), 0), ("are".into(), 1), ("roses".into(), 2)]
+                .iter()
+                .cloned()
+                .collect();
+        assert_eq!(model.vocab, expected_vocab);
+
+        // Full coverage keeps every word
+        let trainer = WordLevelTrainer {
+            coverage: Some(1.0),
+            ..Default::default()
+        };
+        let mut model = WordLevel::default();
+        trainer.do_train(&word_counts, &mut model).unwrap();
+        assert_eq!(model.vocab.len(), 6);
+    }
+
+    #[test]
+    fn test_preserve_ids_from() {
+        use crate::Model;
+
+        let word_counts: HashMap<String, u64> =
+            [("the".into(), 25), ("roses".into(), 22), ("are".into(), 24)]
+                .iter()
+                .cloned()
+                .collect();
+
+        let trainer = WordLevelTrainer::default();
+        let mut model = WordLevel::default();
+        trainer.do_train(&word_counts, &mut model).unwrap();
+        assert_eq!(model.vocab.get("roses"), Some(2));
+
+        // Retrain on new data: "roses" dropped out, "blue" and "red" are new
+        let word_counts: HashMap<String, u64> =
+            [("blue".into(), 30), ("the".into(), 25), ("are".into(), 24)]
+                .iter()
+                .cloned()
+                .collect();
+        let trainer = WordLevelTrainer {
+            preserve_ids_from: Some(model.get_vocab()),
+            ..Default::default()
+        };
+        let mut model = WordLevel::default();
+        trainer.do_train(&word_counts, &mut model).unwrap();
+
+        // The surviving tokens keep their old ids, the new one is appended
+        // after the highest previous id
+        let expected_vocab: HashMap<String, u32> =
+            [("the".into(), 0), ("are".into(), 1), ("blue".into(), 3)]
+                .iter()
+                .cloned()
+                .collect();
+        assert_eq!(model.get_vocab(), expected_vocab);
+    }
+
+    #[test]
+    fn test_feed_counts() {
+        let mut trainer = WordLevelTrainer {
+            vocab_size: 5,
+            ..Default::default()
+        };
+
+        trainer.feed_counts(vec![("the".into(), 10), ("roses".into(), 4)], 1.0);
+        // Weighted feeds accumulate on top of previous counts
+        trainer.feed_counts(vec![("roses".into(), 4), ("red".into(), 2)], 0.5);
+
+        let mut model = WordLevel::default();
+        trainer.train(&mut model).unwrap();
+        let expected_vocab: HashMap<String, u32> =
+            [("the".into(), 0), ("roses".into(), 1), ("red".into(), 2)]
+                .iter()
+                .cloned()
+                .collect();
+        assert_eq!(model.vocab, expected_vocab);
+    }
+
+    #[test]
+    fn test_feed_count_file() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), "the\t25\nroses\t22\n\nare\t24\n").unwrap();
+
+        let mut trainer = WordLevelTrainer {
+            vocab_size: 3,
+            ..Default::default()
+        };
+        trainer.feed_count_file(file.path(), 1.0).unwrap();
+
+        let mut model = WordLevel::default();
+        trainer.train(&mut model).unwrap();
+        let expected_vocab: HashMap<String, u32> =
+            [("the".into(), 0), ("are".into(), 1), ("roses".into(), 2)]
+                .iter()
+                .cloned()
+                .collect();
+        assert_eq!(model.vocab, expected_vocab);
+
+        // A malformed file is rejected
+        std::fs::write(file.path(), "the25\n").unwrap();
+        assert!(trainer.feed_count_file(file.path(), 1.0).is_err());
+    }
 }
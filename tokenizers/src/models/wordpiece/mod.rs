@@ -3,8 +3,9 @@
 
 use crate::models::bpe::BPE;
 use crate::tokenizer::{Model, Result, Token};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::{
-    borrow::Cow,
     collections::HashMap,
     fs::File,
     io::prelude::*,
@@ -14,7 +15,9 @@ use std::{
 
 mod serialization;
 mod trainer;
+mod trie;
 pub use trainer::*;
+use trie::PrefixTrie;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -22,15 +25,35 @@ pub enum Error {
     MissingUnkToken,
 }
 
-type Vocab = HashMap<String, u32>;
+pub use crate::models::vocab::Vocab;
 type VocabR = HashMap<u32, String>;
 
+/// How to tokenize words longer than `max_input_chars_per_word`, instead of
+/// silently collapsing them into a single unknown token
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum LongWordFallback {
+    /// Output a single unknown token for the whole word (the historical
+    /// behavior)
+    #[default]
+    Unk,
+    /// Tokenize the first `max_input_chars_per_word` characters of the word
+    /// and drop the rest
+    Truncate,
+    /// Output one `<0xXX>` token per byte of the word, falling back to the
+    /// unknown token when the byte tokens are not part of the vocabulary
+    ByteFallback,
+    /// Split the word into chunks of the given number of characters, and
+    /// tokenize each chunk on its own
+    Chunk(usize),
+}
+
 struct Config {
     files: Option<String>,
     vocab: Vocab,
     unk_token: String,
     continuing_subword_prefix: String,
     max_input_chars_per_word: usize,
+    long_word_fallback: LongWordFallback,
 }
 
 /// A `WordPieceBuilder` can be used to create a `WordPiece` model with a custom configuration.
@@ -43,10 +66,11 @@ impl Default for WordPieceBuilder {
         Self {
             config: Config {
                 files: None,
-                vocab: HashMap::new(),
+                vocab: Vocab::new(),
                 unk_token: String::from("[UNK]"),
                 continuing_subword_prefix: String::from("##"),
                 max_input_chars_per_word: 100,
+                long_word_fallback: LongWordFallback::default(),
             },
         }
     }
@@ -93,6 +117,13 @@ impl WordPieceBuilder {
         self
     }
 
+    /// Set what to do with words longer than `max_input_chars_per_word`.
+    #[must_use]
+    pub fn long_word_fallback(mut self, long_word_fallback: LongWordFallback) -> Self {
+        self.config.long_word_fallback = long_word_fallback;
+        self
+    }
+
     /// Contructs a `WordPiece` model that uses the `WordPieceBuilder`'s configuration.
     pub fn build(mut self) -> Result<WordPiece> {
         if let Some(vocab) = self.config.files {
@@ -106,12 +137,29 @@ impl WordPieceBuilder {
             .map(|(key, val)| (*val, key.to_owned()))
             .collect();
 
+        // Index the vocab for longest-prefix matching: word-initial tokens
+        // as-is, continuation tokens with their prefix stripped, as they are
+        // matched against the raw characters of the word
+        let mut word_initial_trie = PrefixTrie::new();
+        let mut continuation_trie = PrefixTrie::new();
+        for (token, id) in self.config.vocab.iter() {
+            word_initial_trie.insert(token, *id);
+            if let Some(stripped) = token.strip_prefix(&self.config.continuing_subword_prefix) {
+                if !stripped.is_empty() {
+                    continuation_trie.insert(stripped, *id);
+                }
+            }
+        }
+
         Ok(WordPiece {
             vocab: self.config.vocab,
             vocab_r,
+            word_initial_trie,
+            continuation_trie,
             unk_token: self.config.unk_token,
             continuing_subword_prefix: self.config.continuing_subword_prefix,
             max_input_chars_per_word: self.config.max_input_chars_per_word,
+            long_word_fallback: self.config.long_word_fallback,
         })
     }
 }
@@ -119,21 +167,39 @@ impl WordPieceBuilder {
 /// A
 /// [WordPiece](https://static.googleusercontent.com/media/research.google.com/en//pubs/archive/37842.pdf)
 /// model.
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone)]
 pub struct WordPiece {
     vocab: Vocab,
     vocab_r: VocabR,
+    word_initial_trie: PrefixTrie,
+    continuation_trie: PrefixTrie,
     pub unk_token: String,
     pub continuing_subword_prefix: String,
     pub max_input_chars_per_word: usize,
+    pub long_word_fallback: LongWordFallback,
 }
 
+// The tries are derived from the vocab, and their layout depends on its
+// iteration order: only the configuration takes part in equality
+impl PartialEq for WordPiece {
+    fn eq(&self, other: &Self) -> bool {
+        self.vocab == other.vocab
+            && self.unk_token == other.unk_token
+            && self.continuing_subword_prefix == other.continuing_subword_prefix
+            && self.max_input_chars_per_word == other.max_input_chars_per_word
+            && self.long_word_fallback == other.long_word_fallback
+    }
+}
+
+impl Eq for WordPiece {}
+
 impl std::fmt::Debug for WordPiece {
     fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
         fmt.debug_struct("WordPiece")
             .field("unk_token", &self.unk_token)
             .field("continuing_subword_prefix", &self.continuing_subword_prefix)
             .field("max_input_chars_per_word", &self.max_input_chars_per_word)
+            .field("long_word_fallback", &self.long_word_fallback)
             .field("vocab", &self.vocab.len())
             .finish()
     }
@@ -142,11 +208,14 @@ impl std::fmt::Debug for WordPiece {
 impl Default for WordPiece {
     fn default() -> Self {
         Self {
-            vocab: HashMap::new(),
+            vocab: Vocab::new(),
             vocab_r: HashMap::new(),
+            word_initial_trie: PrefixTrie::new(),
+            continuation_trie: PrefixTrie::new(),
             unk_token: String::from("[UNK]"),
             continuing_subword_prefix: String::from("##"),
             max_input_chars_per_word: 100,
+            long_word_fallback: LongWordFallback::default(),
         }
     }
 }
@@ -162,7 +231,7 @@ impl WordPiece {
         let file = File::open(vocab)?;
         let file = BufReader::new(file);
 
-        let mut vocab = HashMap::new();
+        let mut vocab = Vocab::new();
         for (index, line) in file.lines().enumerate() {
             let line = line?;
             vocab.insert(line.trim_end().to_owned(), index as u32);
@@ -178,14 +247,96 @@ impl WordPiece {
 
     /// Create a `WordPiece` model from a `BPE` model.
     pub fn from_bpe(bpe: &BPE) -> Self {
-        let mut wp = Self::builder().vocab(bpe.get_vocab()).build().unwrap();
+        let mut builder = Self::builder().vocab(bpe.get_vocab().into());
         if let Some(unk) = bpe.get_unk_token() {
-            unk.clone_into(&mut wp.unk_token);
+            builder = builder.unk_token(unk.clone());
         }
         if let Some(prefix) = bpe.get_continuing_subword_prefix() {
-            prefix.clone_into(&mut wp.continuing_subword_prefix);
+            builder = builder.continuing_subword_prefix(prefix.clone());
+        }
+        builder.build().unwrap()
+    }
+
+    /// Add `token` to the vocabulary and the matching tries, returning its
+    /// newly assigned id, or the id it already has
+    pub fn add_token(&mut self, token: &str) -> u32 {
+        if let Some(id) = self.vocab.get(token) {
+            return id;
+        }
+        let id = self.vocab_r.keys().max().map_or(0, |max| max + 1);
+        self.vocab.insert(token.to_owned(), id);
+        self.vocab_r.insert(id, token.to_owned());
+        self.word_initial_trie.insert(token, id);
+        if let Some(stripped) = token.strip_prefix(&self.continuing_subword_prefix) {
+            if !stripped.is_empty() {
+                self.continuation_trie.insert(stripped, id);
+            }
         }
-        wp
+        id
+    }
+
+    /// Remove the token with the given id from the vocabulary and the
+    /// matching tries, returning it
+    pub fn remove_token(&mut self, id: u32) -> Option<String> {
+        let token = self.vocab_r.remove(&id)?;
+        self.vocab.remove(&token);
+        self.word_initial_trie.remove(&token);
+        if let Some(stripped) = token.strip_prefix(&self.continuing_subword_prefix) {
+            if !stripped.is_empty() {
+                self.continuation_trie.remove(stripped);
+            }
+        }
+        Some(token)
+    }
+
+    /// A `Token` standing for the unknown token over the given byte range
+    fn unk(&self, offsets: (usize, usize)) -> Result<Token> {
+        Ok(Token {
+            value: self.unk_token.clone(),
+            id: self
+                .vocab
+                .get(&self.unk_token)
+                .ok_or(Error::MissingUnkToken)?,
+            offsets,
+        })
+    }
+
+    /// Run the greedy longest-match-first loop over the given slice of a
+    /// word, pushing the sub-tokens with their offsets shifted by
+    /// `byte_offset`. Returns `false` when some part of the slice could not be
+    /// covered by any sub-token.
+    fn tokenize_slice(
+        &self,
+        sequence: &str,
+        byte_offset: usize,
+        sub_tokens: &mut Vec<Token>,
+    ) -> bool {
+        let mut start = 0;
+
+        while start < sequence.len() {
+            let (trie, prefix) = if start > 0 || byte_offset > 0 {
+                (
+                    &self.continuation_trie,
+                    self.continuing_subword_prefix.as_str(),
+                )
+            } else {
+                (&self.word_initial_trie, "")
+            };
+
+            match trie.longest_prefix(&sequence[start..]) {
+                Some((id, len)) => {
+                    sub_tokens.push(Token {
+                        id,
+                        value: format!("{}{}", prefix, &sequence[start..start + len]),
+                        offsets: (byte_offset + start, byte_offset + start + len),
+                    });
+                    start += len;
+                }
+                None => return false,
+            }
+        }
+
+        true
     }
 }
 
@@ -193,7 +344,35 @@ impl Model for WordPiece {
     type Trainer = WordPieceTrainer;
 
     fn get_vocab(&self) -> HashMap<String, u32> {
-        self.vocab.clone()
+        (&self.vocab).into()
+    }
+
+    fn get_vocab_iter(&self) -> Box<dyn Iterator<Item = (Cow<'_, str>, u32)> + '_> {
+        let mut entries: Vec<_> = self
+            .vocab
+            .iter()
+            .map(|(token, id)| (token.as_str(), *id))
+            .collect();
+        entries.sort_unstable_by_key(|(_, id)| *id);
+        Box::new(
+            entries
+                .into_iter()
+                .map(|(token, id)| (Cow::Borrowed(token), id)),
+        )
+    }
+
+    fn get_vocab_r(&self) -> Box<dyn Iterator<Item = (u32, Cow<'_, str>)> + '_> {
+        let mut entries: Vec<_> = self
+            .vocab_r
+            .iter()
+            .map(|(id, token)| (*id, token.as_str()))
+            .collect();
+        entries.sort_unstable_by_key(|(id, _)| *id);
+        Box::new(
+            entries
+                .into_iter()
+                .map(|(id, token)| (id, Cow::Borrowed(token))),
+        )
     }
 
     fn get_vocab_size(&self) -> usize {
@@ -203,66 +382,68 @@ impl Model for WordPiece {
     fn tokenize(&self, sequence: &str) -> Result<Vec<Token>> {
         let char_len = sequence.chars().count();
         if char_len > self.max_input_chars_per_word {
-            return Ok(vec![Token {
-                value: self.unk_token.clone(),
-                id: *self
-                    .vocab
-                    .get(&self.unk_token)
-                    .ok_or(Error::MissingUnkToken)?,
-                offsets: (0, sequence.len()),
-            }]);
-        }
-
-        let mut is_bad = false;
-        let mut start = 0;
-        let mut sub_tokens: Vec<Token> = vec![];
-
-        while start < sequence.len() {
-            let mut end = sequence.len();
-            let mut cur_str = None;
-
-            while start < end {
-                let mut substr: Cow<str> = Cow::Borrowed(&sequence[start..end]);
-
-                if start > 0 {
-                    substr = Cow::Owned(format!("{}{}", self.continuing_subword_prefix, substr));
+            return match self.long_word_fallback {
+                LongWordFallback::Unk => Ok(vec![self.unk((0, sequence.len()))?]),
+                LongWordFallback::Truncate => {
+                    let end = sequence
+                        .char_indices()
+                        .nth(self.max_input_chars_per_word)
+                        .map_or(sequence.len(), |(i, _)| i);
+                    let mut sub_tokens = vec![];
+                    if !self.tokenize_slice(&sequence[..end], 0, &mut sub_tokens) {
+                        sub_tokens = vec![self.unk((0, end))?];
+                    }
+                    Ok(sub_tokens)
                 }
-                if self.vocab.contains_key(substr.as_ref()) {
-                    cur_str = Some(Token {
-                        id: self.vocab[substr.as_ref()],
-                        value: substr.to_string(),
-                        offsets: (start, end),
-                    });
-                    break;
+                LongWordFallback::ByteFallback => {
+                    let sub_tokens: Option<Vec<Token>> = sequence
+                        .bytes()
+                        .enumerate()
+                        .map(|(i, b)| {
+                            let value = format!("<{:#04X}>", b);
+                            self.vocab.get(&value).map(|id| Token {
+                                id,
+                                value,
+                                offsets: (i, i + 1),
+                            })
+                        })
+                        .collect();
+                    match sub_tokens {
+                        Some(sub_tokens) => Ok(sub_tokens),
+                        None => Ok(vec![self.unk((0, sequence.len()))?]),
+                    }
                 }
-                end -= substr.chars().last().map_or(1, |c| c.len_utf8());
-            }
-
-            if cur_str.is_none() {
-                is_bad = true;
-                break;
-            }
-
-            sub_tokens.push(cur_str.unwrap());
-            start = end;
+                LongWordFallback::Chunk(size) => {
+                    let mut starts: Vec<usize> = sequence
+                        .char_indices()
+                        .step_by(size.max(1))
+                        .map(|(i, _)| i)
+                        .collect();
+                    starts.push(sequence.len());
+                    let mut sub_tokens = vec![];
+                    for chunk in starts.windows(2) {
+                        let (start, end) = (chunk[0], chunk[1]);
+                        let first_sub_token = sub_tokens.len();
+                        if !self.tokenize_slice(&sequence[start..end], start, &mut sub_tokens) {
+                            // Chunks are independent: only this one becomes unknown
+                            sub_tokens.truncate(first_sub_token);
+                            sub_tokens.push(self.unk((start, end))?);
+                        }
+                    }
+                    Ok(sub_tokens)
+                }
+            };
         }
 
-        if is_bad {
-            Ok(vec![Token {
-                value: self.unk_token.clone(),
-                id: *self
-                    .vocab
-                    .get(&self.unk_token)
-                    .ok_or(Error::MissingUnkToken)?,
-                offsets: (0, sequence.len()),
-            }])
-        } else {
-            Ok(sub_tokens)
+        let mut sub_tokens = vec![];
+        if !self.tokenize_slice(sequence, 0, &mut sub_tokens) {
+            sub_tokens = vec![self.unk((0, sequence.len()))?];
         }
+        Ok(sub_tokens)
     }
 
     fn token_to_id(&self, token: &str) -> Option<u32> {
-        self.vocab.get(token).copied()
+        self.vocab.get(token)
     }
 
     fn id_to_token(&self, id: u32) -> Option<String> {
@@ -295,12 +476,153 @@ impl Model for WordPiece {
     fn get_trainer(&self) -> Self::Trainer {
         WordPieceTrainer::builder().build()
     }
+
+    fn unk_token(&self) -> Option<String> {
+        Some(self.unk_token.clone())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_save_deterministic_order() {
+        // vocab.txt is written sorted by id, independently of the vocab
+        // HashMap iteration order
+        let vocab: Vocab = [
+            ("[UNK]".into(), 0),
+            ("b".into(), 1),
+            ("a".into(), 2),
+            ("##ab".into(), 3),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let wordpiece = WordPiece::builder().vocab(vocab).build().unwrap();
+
+        let folder = tempfile::tempdir().unwrap();
+        wordpiece.save(folder.path(), None).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(folder.path().join("vocab.txt")).unwrap(),
+            "[UNK]\nb\na\n##ab\n"
+        );
+    }
+
+    #[test]
+    fn test_add_remove_token() {
+        let vocab: Vocab = [
+            ("[UNK]".into(), 0),
+            ("a".into(), 1),
+            ("##b".into(), 2),
+            ("ab".into(), 3),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let mut wordpiece = WordPiece::builder().vocab(vocab).build().unwrap();
+        assert_eq!(wordpiece.tokenize("ab").unwrap()[0].id, 3);
+
+        // The removed token no longer matches, the remaining ones still do
+        assert_eq!(wordpiece.remove_token(3), Some("ab".to_string()));
+        let tokens = wordpiece.tokenize("ab").unwrap();
+        let values: Vec<_> = tokens.iter().map(|t| t.value.as_str()).collect();
+        assert_eq!(values, ["a", "##b"]);
+
+        // A new continuation token takes part in matching right away
+        assert_eq!(wordpiece.add_token("##c"), 3);
+        let tokens = wordpiece.tokenize("ac").unwrap();
+        let values: Vec<_> = tokens.iter().map(|t| t.value.as_str()).collect();
+        assert_eq!(values, ["a", "##c"]);
+    }
+
+    #[test]
+    fn test_long_word_fallback() {
+        let vocab: Vocab = [
+            ("[UNK]", 0),
+            ("ab", 1),
+            ("##cd", 2),
+            ("##ab", 3),
+            ("<0x61>", 4),
+            ("<0x62>", 5),
+            ("<0x63>", 6),
+            ("<0x64>", 7),
+        ]
+        .iter()
+        .map(|(token, id)| (token.to_string(), *id))
+        .collect::<HashMap<_, _>>()
+        .into();
+        let builder = || {
+            WordPiece::builder()
+                .vocab(vocab.clone())
+                .max_input_chars_per_word(4)
+        };
+        let offsets = |tokens: Vec<Token>| {
+            tokens
+                .into_iter()
+                .map(|token| (token.value, token.offsets))
+                .collect::<Vec<_>>()
+        };
+
+        // The default keeps the historical behavior: a single unknown token
+        let wordpiece = builder().build().unwrap();
+        assert_eq!(
+            offsets(wordpiece.tokenize("abcdab").unwrap()),
+            vec![("[UNK]".into(), (0, 6))]
+        );
+
+        let wordpiece = builder()
+            .long_word_fallback(LongWordFallback::Truncate)
+            .build()
+            .unwrap();
+        assert_eq!(
+            offsets(wordpiece.tokenize("abcdab").unwrap()),
+            vec![("ab".into(), (0, 2)), ("##cd".into(), (2, 4))]
+        );
+
+        let wordpiece = builder()
+            .long_word_fallback(LongWordFallback::ByteFallback)
+            .build()
+            .unwrap();
+        assert_eq!(
+            offsets(wordpiece.tokenize("abcda").unwrap()),
+            vec![
+                ("<0x61>".into(), (0, 1)),
+                ("<0x62>".into(), (1, 2)),
+                ("<0x63>".into(), (2, 3)),
+                ("<0x64>".into(), (3, 4)),
+                ("<0x61>".into(), (4, 5)),
+            ]
+        );
+        // Bytes missing from the vocabulary fall back to the unknown token
+        assert_eq!(
+            offsets(wordpiece.tokenize("abcdez").unwrap()),
+            vec![("[UNK]".into(), (0, 6))]
+        );
+
+        let wordpiece = builder()
+            .long_word_fallback(LongWordFallback::Chunk(2))
+            .build()
+            .unwrap();
+        assert_eq!(
+            offsets(wordpiece.tokenize("abcdab").unwrap()),
+            vec![
+                ("ab".into(), (0, 2)),
+                ("##cd".into(), (2, 4)),
+                ("##ab".into(), (4, 6)),
+            ]
+        );
+        // A chunk that cannot be tokenized becomes unknown on its own
+        assert_eq!(
+            offsets(wordpiece.tokenize("abcdez").unwrap()),
+            vec![
+                ("ab".into(), (0, 2)),
+                ("##cd".into(), (2, 4)),
+                ("[UNK]".into(), (4, 6)),
+            ]
+        );
+    }
+
     #[test]
     fn test_error_display() {
         assert!(format!("{}", Error::MissingUnkToken).contains("Missing [UNK] token"));
@@ -1,4 +1,4 @@
-use super::{super::OrderedVocabIter, WordPiece, WordPieceBuilder};
+use super::{super::OrderedVocabIter, LongWordFallback, WordPiece, WordPieceBuilder};
 use serde::{
     de::{MapAccess, Visitor},
     ser::SerializeStruct,
@@ -11,13 +11,18 @@ impl Serialize for WordPiece {
     where
         S: Serializer,
     {
-        let mut model = serializer.serialize_struct("WordPiece", 5)?;
+        let serialize_fallback = self.long_word_fallback != LongWordFallback::default();
+        let mut model =
+            serializer.serialize_struct("WordPiece", 5 + usize::from(serialize_fallback))?;
 
         // Small fields first
         model.serialize_field("type", "WordPiece")?;
         model.serialize_field("unk_token", &self.unk_token)?;
         model.serialize_field("continuing_subword_prefix", &self.continuing_subword_prefix)?;
         model.serialize_field("max_input_chars_per_word", &self.max_input_chars_per_word)?;
+        if serialize_fallback {
+            model.serialize_field("long_word_fallback", &self.long_word_fallback)?;
+        }
 
         // Then large ones
         let ordered_vocab = OrderedVocabIter::new(&self.vocab_r);
@@ -39,6 +44,7 @@ impl<'de> Deserialize<'de> for WordPiece {
                 "unk_token",
                 "continuing_subword_prefix",
                 "max_input_chars_per_word",
+                "long_word_fallback",
                 "vocab",
             ],
             WordPieceVisitor,
@@ -78,6 +84,7 @@ impl<'de> Visitor<'de> for WordPieceVisitor {
                 "max_input_chars_per_word" => {
                     builder = builder.max_input_chars_per_word(map.next_value()?)
                 }
+                "long_word_fallback" => builder = builder.long_word_fallback(map.next_value()?),
                 "vocab" => builder = builder.vocab(map.next_value()?),
                 "type" => match map.next_value()? {
                     "WordPiece" => {}
@@ -122,6 +129,27 @@ mod tests {
         assert_eq!(serde_json::from_str::<WordPiece>(wp_s).unwrap(), wp);
     }
 
+    #[test]
+    fn serde_long_word_fallback() {
+        // The default fallback is not serialized, so default models keep
+        // their historical serialization (checked above); others roundtrip
+        let wp = WordPieceBuilder::new()
+            .long_word_fallback(LongWordFallback::Chunk(50))
+            .build()
+            .unwrap();
+        let wp_s = "{\
+            \"type\":\"WordPiece\",\
+            \"unk_token\":\"[UNK]\",\
+            \"continuing_subword_prefix\":\"##\",\
+            \"max_input_chars_per_word\":100,\
+            \"long_word_fallback\":{\"Chunk\":50},\
+            \"vocab\":{}\
+        }";
+
+        assert_eq!(serde_json::to_string(&wp).unwrap(), wp_s);
+        assert_eq!(serde_json::from_str::<WordPiece>(wp_s).unwrap(), wp);
+    }
+
     #[test]
     fn deserialization_should_fail() {
         let missing_unk = "{\
@@ -1,8 +1,9 @@
 use super::WordPiece;
 use crate::models::bpe::{BpeTrainer, BpeTrainerBuilder, BPE};
-use crate::tokenizer::{AddedToken, Result, Trainer};
+use crate::tokenizer::{AddedToken, Result, Trainer, TrainingReport};
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
+use std::path::Path;
 
 /// A `WordPieceTrainerBuilder` can be used to create a `WordPieceTrainer` with a custom
 /// configuration.
@@ -80,6 +81,34 @@ impl WordPieceTrainerBuilder {
         self
     }
 
+    /// Set whether the training should be deterministic
+    #[must_use]
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.bpe_trainer_builder = self.bpe_trainer_builder.deterministic(deterministic);
+        self
+    }
+
+    /// Set the tokens that must never be added to the vocabulary
+    #[must_use]
+    pub fn blocked_tokens(mut self, tokens: HashSet<String>) -> Self {
+        self.bpe_trainer_builder = self.bpe_trainer_builder.blocked_tokens(tokens);
+        self
+    }
+
+    /// Set the pattern that candidate tokens must not match
+    #[must_use]
+    pub fn blocked_pattern(mut self, pattern: String) -> Self {
+        self.bpe_trainer_builder = self.bpe_trainer_builder.blocked_pattern(pattern);
+        self
+    }
+
+    /// Set whether `train_with_report` should produce a `TrainingReport`
+    #[must_use]
+    pub fn report(mut self, report: bool) -> Self {
+        self.bpe_trainer_builder = self.bpe_trainer_builder.report(report);
+        self
+    }
+
     /// Constructs the final BpeTrainer
     pub fn build(self) -> WordPieceTrainer {
         let bpe_trainer = self.bpe_trainer_builder.build();
@@ -158,6 +187,38 @@ impl WordPieceTrainer {
         self.bpe_trainer.end_of_word_suffix = suffix;
     }
 
+    pub fn deterministic(&self) -> bool {
+        self.bpe_trainer.deterministic
+    }
+
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        self.bpe_trainer.deterministic = deterministic;
+    }
+
+    pub fn blocked_tokens(&self) -> &HashSet<String> {
+        &self.bpe_trainer.blocked_tokens
+    }
+
+    pub fn set_blocked_tokens(&mut self, tokens: HashSet<String>) {
+        self.bpe_trainer.blocked_tokens = tokens;
+    }
+
+    pub fn blocked_pattern(&self) -> &Option<String> {
+        &self.bpe_trainer.blocked_pattern
+    }
+
+    pub fn set_blocked_pattern(&mut self, pattern: Option<String>) {
+        self.bpe_trainer.blocked_pattern = pattern;
+    }
+
+    pub fn report(&self) -> bool {
+        self.bpe_trainer.report
+    }
+
+    pub fn set_report(&mut self, report: bool) {
+        self.bpe_trainer.report = report;
+    }
+
     pub fn builder() -> WordPieceTrainerBuilder {
         WordPieceTrainerBuilder::default()
     }
@@ -196,4 +257,40 @@ impl Trainer for WordPieceTrainer {
     {
         self.bpe_trainer.feed(iterator, process)
     }
+
+    fn feed_weighted<I, S, F>(&mut self, iterator: I, process: F) -> Result<()>
+    where
+        I: Iterator<Item = (S, f64)> + Send,
+        S: AsRef<str> + Send,
+        F: Fn(&str) -> Result<Vec<String>> + Sync,
+    {
+        self.bpe_trainer.feed_weighted(iterator, process)
+    }
+
+    fn feed_validation<I, S>(&mut self, iterator: I) -> Result<()>
+    where
+        I: Iterator<Item = S> + Send,
+        S: AsRef<str> + Send,
+    {
+        self.bpe_trainer.feed_validation(iterator)
+    }
+
+    fn train_with_report(
+        &self,
+        model: &mut WordPiece,
+    ) -> Result<(Vec<AddedToken>, Option<TrainingReport>)> {
+        let special_tokens = self.train(model)?;
+        let report = self
+            .bpe_trainer
+            .make_report(model, Some(&model.unk_token))?;
+        Ok((special_tokens, report))
+    }
+
+    fn save_checkpoint(&self, path: &Path) -> Result<()> {
+        self.bpe_trainer.save_checkpoint(path)
+    }
+
+    fn resume_from_checkpoint(&mut self, path: &Path) -> Result<()> {
+        self.bpe_trainer.resume_from_checkpoint(path)
+    }
 }
@@ -0,0 +1,95 @@
+//! A byte-level prefix trie over the vocabulary, so that `tokenize` can find
+//! the longest matching sub-token in a single left-to-right pass (one node
+//! lookup per byte), instead of hashing every candidate substring from the
+//! longest down (quadratic in the word length).
+
+/// A prefix trie mapping tokens to their ids
+#[derive(Debug, Clone, Default)]
+pub(super) struct PrefixTrie {
+    /// The nodes of the trie; the root is at index 0
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Node {
+    /// The children of the node, sorted by byte for binary search
+    children: Vec<(u8, u32)>,
+    /// The id of the token ending on this node, if any
+    id: Option<u32>,
+}
+
+impl PrefixTrie {
+    pub(super) fn new() -> Self {
+        Self {
+            nodes: vec![Node::default()],
+        }
+    }
+
+    pub(super) fn insert(&mut self, token: &str, id: u32) {
+        let mut node = 0;
+        for b in token.bytes() {
+            node = match self.nodes[node].children.binary_search_by_key(&b, |c| c.0) {
+                Ok(pos) => self.nodes[node].children[pos].1 as usize,
+                Err(pos) => {
+                    let child = self.nodes.len();
+                    self.nodes.push(Node::default());
+                    self.nodes[node].children.insert(pos, (b, child as u32));
+                    child
+                }
+            };
+        }
+        self.nodes[node].id = Some(id);
+    }
+
+    /// Remove the given token, so that lookups no longer find it. The nodes
+    /// it created are kept, only its id is cleared.
+    pub(super) fn remove(&mut self, token: &str) {
+        let mut node = 0;
+        for b in token.bytes() {
+            match self.nodes[node].children.binary_search_by_key(&b, |c| c.0) {
+                Ok(pos) => node = self.nodes[node].children[pos].1 as usize,
+                Err(_) => return,
+            }
+        }
+        self.nodes[node].id = None;
+    }
+
+    /// The longest prefix of `sequence` that is a token of the trie, as its id
+    /// and byte length. Tokens are valid UTF-8, so a match never ends in the
+    /// middle of a character of `sequence`.
+    pub(super) fn longest_prefix(&self, sequence: &str) -> Option<(u32, usize)> {
+        let mut node = 0;
+        let mut longest = None;
+        for (i, b) in sequence.bytes().enumerate() {
+            match self.nodes[node].children.binary_search_by_key(&b, |c| c.0) {
+                Ok(pos) => node = self.nodes[node].children[pos].1 as usize,
+                Err(_) => break,
+            }
+            if let Some(id) = self.nodes[node].id {
+                longest = Some((id, i + 1));
+            }
+        }
+        longest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn longest_prefix() {
+        let mut trie = PrefixTrie::new();
+        trie.insert("a", 0);
+        trie.insert("abc", 1);
+        trie.insert("b", 2);
+        trie.insert("é", 3);
+
+        assert_eq!(trie.longest_prefix("abcd"), Some((1, 3)));
+        assert_eq!(trie.longest_prefix("abd"), Some((0, 1)));
+        assert_eq!(trie.longest_prefix("ba"), Some((2, 1)));
+        assert_eq!(trie.longest_prefix("étage"), Some((3, 2)));
+        assert_eq!(trie.longest_prefix("déjà"), None);
+        assert_eq!(trie.longest_prefix(""), None);
+    }
+}
@@ -0,0 +1,179 @@
+use crate::tokenizer::{NormalizedString, Normalizer, Result};
+
+use serde::{Deserialize, Serialize};
+use unicode_categories::UnicodeCategories;
+use unicode_normalization_alignments::UnicodeNormalization;
+
+/// The tatweel (kashida), only used to stretch words for justification
+const TATWEEL: char = '\u{0640}';
+
+/// Checks whether a character belongs to the Arabic presentation forms
+/// blocks (A and B): the contextual and ligature forms that pre-Unicode
+/// encodings used, and that OCR or legacy corpora still produce
+fn is_presentation_form(c: char) -> bool {
+    matches!(c as u32, 0xFB50..=0xFDFF | 0xFE70..=0xFEFF)
+}
+
+/// Checks whether a character is an Arabic combining mark: the harakat
+/// (fatha, damma, kasra, ...), the shadda, the sukun, superscript alef, and
+/// the Quranic annotation signs
+fn is_arabic_diacritic(c: char) -> bool {
+    matches!(c as u32, 0x0600..=0x06FF | 0x0750..=0x077F | 0x08A0..=0x08FF)
+        && c.is_mark_nonspacing()
+}
+
+/// An Arabic-focused normalizer, covering the usual preprocessing of Arabic
+/// NLP pipelines (what Farasa or camel-tools do in Python), with correct
+/// alignments:
+///   1. Fold the presentation forms (contextual and ligature forms, e.g.
+///      `ﻻ`) back to the regular letters, through their compatibility
+///      decomposition
+///   2. Remove the tatweel used to stretch words
+///   3. Optionally strip the diacritics (harakat, shadda, Quranic signs)
+///   4. Normalize the alef variants (`أ`, `إ`, `آ`, `ٱ`) to the bare alef,
+///      the alef maksura to yeh, and optionally the teh marbuta to heh
+#[derive(Copy, Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "type")]
+#[non_exhaustive]
+pub struct ArabicNormalizer {
+    /// Whether to fold the presentation forms into regular letters
+    pub fold_presentation_forms: bool,
+    /// Whether to remove the tatweel (kashida)
+    pub remove_tatweel: bool,
+    /// Whether to strip the Arabic diacritics
+    pub strip_diacritics: bool,
+    /// Whether to normalize the alef variants to the bare alef and the alef
+    /// maksura to yeh
+    pub normalize_alef: bool,
+    /// Whether to normalize the teh marbuta (`ة`) to heh (`ه`)
+    pub normalize_teh_marbuta: bool,
+}
+
+impl Default for ArabicNormalizer {
+    fn default() -> Self {
+        Self {
+            fold_presentation_forms: true,
+            remove_tatweel: true,
+            strip_diacritics: false,
+            normalize_alef: true,
+            normalize_teh_marbuta: false,
+        }
+    }
+}
+
+impl ArabicNormalizer {
+    pub fn new(
+        fold_presentation_forms: bool,
+        remove_tatweel: bool,
+        strip_diacritics: bool,
+        normalize_alef: bool,
+        normalize_teh_marbuta: bool,
+    ) -> Self {
+        Self {
+            fold_presentation_forms,
+            remove_tatweel,
+            strip_diacritics,
+            normalize_alef,
+            normalize_teh_marbuta,
+        }
+    }
+
+    /// Apply the compatibility decomposition to the presentation forms only,
+    /// leaving every other character (e.g. latin ligatures) untouched.
+    /// Ligature forms expand to several letters, all aligned with the
+    /// original character
+    fn do_fold_presentation_forms(&self, normalized: &mut NormalizedString) {
+        let mut new_chars: Vec<(char, isize)> = vec![];
+        normalized.for_each(|c| {
+            if is_presentation_form(c) {
+                for (i, (new_c, _)) in std::iter::once(c).nfkd().enumerate() {
+                    new_chars.push((new_c, i.min(1) as isize));
+                }
+            } else {
+                new_chars.push((c, 0));
+            }
+        });
+        normalized.transform(new_chars, 0);
+    }
+
+    fn do_normalize_letters(&self, normalized: &mut NormalizedString) {
+        normalized.map(|c| match c {
+            'أ' | 'إ' | 'آ' | 'ٱ' if self.normalize_alef => 'ا',
+            'ى' if self.normalize_alef => 'ي',
+            'ة' if self.normalize_teh_marbuta => 'ه',
+            _ => c,
+        });
+    }
+}
+
+impl Normalizer for ArabicNormalizer {
+    fn normalize(&self, normalized: &mut NormalizedString) -> Result<()> {
+        if self.fold_presentation_forms {
+            self.do_fold_presentation_forms(normalized);
+        }
+        if self.remove_tatweel {
+            normalized.filter(|c| c != TATWEEL);
+        }
+        if self.strip_diacritics {
+            normalized.filter(|c| !is_arabic_diacritic(c));
+        }
+        if self.normalize_alef || self.normalize_teh_marbuta {
+            self.do_normalize_letters(normalized);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::normalizer::Range;
+
+    #[test]
+    fn arabic_defaults() {
+        let normalizer = ArabicNormalizer::default();
+
+        // The `ﻻ` ligature unfolds to lam + alef, both mapped back to the
+        // single original character
+        let mut n = NormalizedString::from("ﻻ");
+        normalizer.normalize(&mut n).unwrap();
+        assert_eq!(n.get(), "لا");
+        assert_eq!(n.get_range_original(Range::Normalized(0..4)), Some("ﻻ"));
+
+        // Tatweels are removed, and the alef variants are unified
+        let mut n = NormalizedString::from("كتــاب أحمد");
+        normalizer.normalize(&mut n).unwrap();
+        assert_eq!(n.get(), "كتاب احمد");
+
+        // The diacritics are kept by default
+        let mut n = NormalizedString::from("مُحَمَّد");
+        normalizer.normalize(&mut n).unwrap();
+        assert_eq!(n.get(), "مُحَمَّد");
+    }
+
+    #[test]
+    fn arabic_toggles() {
+        let normalizer = ArabicNormalizer {
+            strip_diacritics: true,
+            normalize_teh_marbuta: true,
+            ..Default::default()
+        };
+
+        let mut n = NormalizedString::from("مُحَمَّد");
+        normalizer.normalize(&mut n).unwrap();
+        assert_eq!(n.get(), "محمد");
+
+        let mut n = NormalizedString::from("مدرسة مصطفى");
+        normalizer.normalize(&mut n).unwrap();
+        assert_eq!(n.get(), "مدرسه مصطفي");
+    }
+
+    #[test]
+    fn arabic_serde() {
+        let normalizer = ArabicNormalizer::default();
+        let normalizer_s = r#"{"type":"ArabicNormalizer","fold_presentation_forms":true,"remove_tatweel":true,"strip_diacritics":false,"normalize_alef":true,"normalize_teh_marbuta":false}"#;
+        assert_eq!(serde_json::to_string(&normalizer).unwrap(), normalizer_s);
+        let deserialized: ArabicNormalizer = serde_json::from_str(normalizer_s).unwrap();
+        assert_eq!(serde_json::to_string(&deserialized).unwrap(), normalizer_s);
+    }
+}
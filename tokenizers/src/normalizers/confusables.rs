@@ -0,0 +1,175 @@
+use crate::tokenizer::{NormalizedString, Normalizer, Result};
+
+use serde::{Deserialize, Serialize};
+
+/// Fold a confusable character to its Latin skeleton, following the Unicode
+/// TR39 confusables data. This covers the whole-script confusables that show
+/// up in adversarial or noisy text: the Cyrillic and Greek letters that are
+/// visually identical to a Latin letter in most fonts
+fn fold_confusable(c: char) -> Option<char> {
+    Some(match c {
+        // Cyrillic lowercase
+        'а' => 'a',
+        'е' => 'e',
+        'о' => 'o',
+        'р' => 'p',
+        'с' => 'c',
+        'у' => 'y',
+        'х' => 'x',
+        'ѕ' => 's',
+        'і' => 'i',
+        'ј' => 'j',
+        'һ' => 'h',
+        'ԁ' => 'd',
+        'ԛ' => 'q',
+        'ԝ' => 'w',
+        // Cyrillic uppercase
+        'А' => 'A',
+        'В' => 'B',
+        'Е' => 'E',
+        'К' => 'K',
+        'М' => 'M',
+        'Н' => 'H',
+        'О' => 'O',
+        'Р' => 'P',
+        'С' => 'C',
+        'Т' => 'T',
+        'У' => 'Y',
+        'Х' => 'X',
+        'Ѕ' => 'S',
+        'І' => 'I',
+        'Ј' => 'J',
+        // Greek lowercase
+        'ο' => 'o',
+        'ν' => 'v',
+        'ρ' => 'p',
+        'ϲ' => 'c',
+        // Greek uppercase
+        'Α' => 'A',
+        'Β' => 'B',
+        'Ε' => 'E',
+        'Ζ' => 'Z',
+        'Η' => 'H',
+        'Ι' => 'I',
+        'Κ' => 'K',
+        'Μ' => 'M',
+        'Ν' => 'N',
+        'Ο' => 'O',
+        'Ρ' => 'P',
+        'Τ' => 'T',
+        'Υ' => 'Y',
+        'Χ' => 'X',
+        _ => return None,
+    })
+}
+
+/// Fold a fullwidth form back to its ASCII counterpart
+fn fold_fullwidth(c: char) -> Option<char> {
+    match c as u32 {
+        0xFF01..=0xFF5E => char::from_u32(c as u32 - 0xFEE0),
+        // Ideographic space
+        0x3000 => Some(' '),
+        _ => None,
+    }
+}
+
+/// A normalizer folding Unicode confusables (homoglyphs) to their Latin
+/// skeleton, based on the TR39 confusables data: the Cyrillic and Greek
+/// letters rendered identically to a Latin letter (e.g. Cyrillic `а` to Latin
+/// `a`), and optionally the fullwidth forms (`Ａ` to `A`). This keeps
+/// adversarial or noisy text from fragmenting the vocabulary into rare
+/// lookalike tokens.
+///
+/// Characters in the `allowlist` are never folded, so that pipelines
+/// processing legitimate Cyrillic or Greek text can exempt them.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "type")]
+#[non_exhaustive]
+pub struct ConfusablesFold {
+    /// Whether to also fold the fullwidth forms to their ASCII counterpart
+    pub fold_fullwidth: bool,
+    /// The characters exempt from folding
+    #[serde(default)]
+    pub allowlist: Vec<char>,
+}
+
+impl Default for ConfusablesFold {
+    fn default() -> Self {
+        Self {
+            fold_fullwidth: true,
+            allowlist: vec![],
+        }
+    }
+}
+
+impl ConfusablesFold {
+    pub fn new(fold_fullwidth: bool, allowlist: Vec<char>) -> Self {
+        Self {
+            fold_fullwidth,
+            allowlist,
+        }
+    }
+}
+
+impl Normalizer for ConfusablesFold {
+    fn normalize(&self, normalized: &mut NormalizedString) -> Result<()> {
+        normalized.map(|c| {
+            if self.allowlist.contains(&c) {
+                return c;
+            }
+            fold_confusable(c)
+                .or_else(|| self.fold_fullwidth.then(|| fold_fullwidth(c)).flatten())
+                .unwrap_or(c)
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confusables_defaults() {
+        let normalizer = ConfusablesFold::default();
+
+        // Cyrillic А, р and е fold to their Latin skeleton
+        let mut n = NormalizedString::from("Аррlе");
+        normalizer.normalize(&mut n).unwrap();
+        assert_eq!(n.get(), "Apple");
+
+        // Fullwidth forms fold back to ASCII
+        let mut n = NormalizedString::from("Ｈｅｌｌｏ！");
+        normalizer.normalize(&mut n).unwrap();
+        assert_eq!(n.get(), "Hello!");
+
+        // Regular Latin text is untouched
+        let mut n = NormalizedString::from("Apple!");
+        normalizer.normalize(&mut n).unwrap();
+        assert_eq!(n.get(), "Apple!");
+    }
+
+    #[test]
+    fn confusables_allowlist() {
+        let normalizer = ConfusablesFold::new(false, vec!['о']);
+
+        // The allowlisted Cyrillic о is kept, the others are folded
+        let mut n = NormalizedString::from("обор");
+        normalizer.normalize(&mut n).unwrap();
+        assert_eq!(n.get(), "обоp");
+
+        // Fullwidth folding is disabled
+        let mut n = NormalizedString::from("Ａ");
+        normalizer.normalize(&mut n).unwrap();
+        assert_eq!(n.get(), "Ａ");
+    }
+
+    #[test]
+    fn confusables_serde() {
+        let normalizer = ConfusablesFold::default();
+        let normalizer_s = r#"{"type":"ConfusablesFold","fold_fullwidth":true,"allowlist":[]}"#;
+        assert_eq!(serde_json::to_string(&normalizer).unwrap(), normalizer_s);
+        let deserialized: ConfusablesFold = serde_json::from_str(normalizer_s).unwrap();
+        assert_eq!(serde_json::to_string(&deserialized).unwrap(), normalizer_s);
+    }
+}
@@ -1,19 +1,30 @@
+pub mod arabic;
 pub mod bert;
 pub mod byte_level;
+pub mod confusables;
+pub mod numeric;
 pub mod precompiled;
 pub mod prepend;
+pub mod presets;
 pub mod replace;
 pub mod strip;
 pub mod unicode;
 pub mod utils;
+#[cfg(feature = "wasm-plugin")]
+pub mod wasm;
+pub use crate::normalizers::arabic::ArabicNormalizer;
 pub use crate::normalizers::bert::BertNormalizer;
 pub use crate::normalizers::byte_level::ByteLevel;
-pub use crate::normalizers::precompiled::Precompiled;
+pub use crate::normalizers::confusables::ConfusablesFold;
+pub use crate::normalizers::numeric::{NumericCanonicalize, NumericStrategy};
+pub use crate::normalizers::precompiled::{compile_charsmap, precompiled_from_rules, Precompiled};
 pub use crate::normalizers::prepend::Prepend;
-pub use crate::normalizers::replace::Replace;
+pub use crate::normalizers::replace::{Replace, ReplaceMany};
 pub use crate::normalizers::strip::{Strip, StripAccents};
 pub use crate::normalizers::unicode::{Nmt, NFC, NFD, NFKC, NFKD};
 pub use crate::normalizers::utils::{Lowercase, Sequence};
+#[cfg(feature = "wasm-plugin")]
+pub use crate::normalizers::wasm::WasmNormalizer;
 use serde::{Deserialize, Deserializer, Serialize};
 
 use crate::{NormalizedString, Normalizer};
@@ -23,6 +34,8 @@ use crate::{NormalizedString, Normalizer};
 #[serde(untagged)]
 pub enum NormalizerWrapper {
     BertNormalizer(BertNormalizer),
+    ArabicNormalizer(ArabicNormalizer),
+    ConfusablesFold(ConfusablesFold),
     StripNormalizer(Strip),
     StripAccents(StripAccents),
     NFC(NFC),
@@ -34,8 +47,12 @@ pub enum NormalizerWrapper {
     Nmt(Nmt),
     Precompiled(Precompiled),
     Replace(Replace),
+    ReplaceMany(ReplaceMany),
     Prepend(Prepend),
     ByteLevel(ByteLevel),
+    NumericCanonicalize(NumericCanonicalize),
+    #[cfg(feature = "wasm-plugin")]
+    Wasm(WasmNormalizer),
 }
 
 impl<'de> Deserialize<'de> for NormalizerWrapper {
@@ -53,6 +70,8 @@ impl<'de> Deserialize<'de> for NormalizerWrapper {
         #[derive(Serialize, Deserialize)]
         pub enum EnumType {
             Bert,
+            ArabicNormalizer,
+            ConfusablesFold,
             Strip,
             StripAccents,
             NFC,
@@ -64,14 +83,20 @@ impl<'de> Deserialize<'de> for NormalizerWrapper {
             Nmt,
             Precompiled,
             Replace,
+            ReplaceMany,
             Prepend,
             ByteLevel,
+            NumericCanonicalize,
+            #[cfg(feature = "wasm-plugin")]
+            Wasm,
         }
 
         #[derive(Deserialize)]
         #[serde(untagged)]
         pub enum NormalizerHelper {
             Tagged(Tagged),
+            // A bare string selects a preset from `normalizers::presets`
+            PresetName(String),
             Legacy(serde_json::Value),
         }
 
@@ -79,6 +104,8 @@ impl<'de> Deserialize<'de> for NormalizerWrapper {
         #[serde(untagged)]
         pub enum NormalizerUntagged {
             BertNormalizer(BertNormalizer),
+            ArabicNormalizer(ArabicNormalizer),
+            ConfusablesFold(ConfusablesFold),
             StripNormalizer(Strip),
             StripAccents(StripAccents),
             NFC(NFC),
@@ -90,8 +117,12 @@ impl<'de> Deserialize<'de> for NormalizerWrapper {
             Nmt(Nmt),
             Precompiled(Precompiled),
             Replace(Replace),
+            ReplaceMany(ReplaceMany),
             Prepend(Prepend),
             ByteLevel(ByteLevel),
+            NumericCanonicalize(NumericCanonicalize),
+            #[cfg(feature = "wasm-plugin")]
+            Wasm(WasmNormalizer),
         }
 
         let helper = NormalizerHelper::deserialize(deserializer)?;
@@ -108,6 +139,12 @@ impl<'de> Deserialize<'de> for NormalizerWrapper {
                     EnumType::Bert => NormalizerWrapper::BertNormalizer(
                         serde_json::from_value(values).map_err(serde::de::Error::custom)?,
                     ),
+                    EnumType::ArabicNormalizer => NormalizerWrapper::ArabicNormalizer(
+                        serde_json::from_value(values).map_err(serde::de::Error::custom)?,
+                    ),
+                    EnumType::ConfusablesFold => NormalizerWrapper::ConfusablesFold(
+                        serde_json::from_value(values).map_err(serde::de::Error::custom)?,
+                    ),
                     EnumType::Strip => NormalizerWrapper::StripNormalizer(
                         serde_json::from_value(values).map_err(serde::de::Error::custom)?,
                     ),
@@ -142,6 +179,9 @@ impl<'de> Deserialize<'de> for NormalizerWrapper {
                         // .map_err(serde::de::Error::custom)
                         .expect("Precompiled"),
                     ),
+                    EnumType::ReplaceMany => NormalizerWrapper::ReplaceMany(
+                        serde_json::from_value(values).map_err(serde::de::Error::custom)?,
+                    ),
                     EnumType::Replace => NormalizerWrapper::Replace(
                         serde_json::from_value(values).map_err(serde::de::Error::custom)?,
                     ),
@@ -151,15 +191,34 @@ impl<'de> Deserialize<'de> for NormalizerWrapper {
                     EnumType::ByteLevel => NormalizerWrapper::ByteLevel(
                         serde_json::from_value(values).map_err(serde::de::Error::custom)?,
                     ),
+                    EnumType::NumericCanonicalize => NormalizerWrapper::NumericCanonicalize(
+                        serde_json::from_value(values).map_err(serde::de::Error::custom)?,
+                    ),
+                    #[cfg(feature = "wasm-plugin")]
+                    EnumType::Wasm => NormalizerWrapper::Wasm(
+                        serde_json::from_value(values).map_err(serde::de::Error::custom)?,
+                    ),
                 }
             }
 
+            NormalizerHelper::PresetName(name) => {
+                NormalizerWrapper::Sequence(presets::from_name(&name).ok_or_else(|| {
+                    serde::de::Error::custom(format!("Unknown normalizer preset: {}", name))
+                })?)
+            }
+
             NormalizerHelper::Legacy(value) => {
                 let untagged = serde_json::from_value(value).map_err(serde::de::Error::custom)?;
                 match untagged {
                     NormalizerUntagged::BertNormalizer(bpe) => {
                         NormalizerWrapper::BertNormalizer(bpe)
                     }
+                    NormalizerUntagged::ArabicNormalizer(bpe) => {
+                        NormalizerWrapper::ArabicNormalizer(bpe)
+                    }
+                    NormalizerUntagged::ConfusablesFold(bpe) => {
+                        NormalizerWrapper::ConfusablesFold(bpe)
+                    }
                     NormalizerUntagged::StripNormalizer(bpe) => {
                         NormalizerWrapper::StripNormalizer(bpe)
                     }
@@ -173,8 +232,14 @@ impl<'de> Deserialize<'de> for NormalizerWrapper {
                     NormalizerUntagged::Nmt(bpe) => NormalizerWrapper::Nmt(bpe),
                     NormalizerUntagged::Precompiled(bpe) => NormalizerWrapper::Precompiled(bpe),
                     NormalizerUntagged::Replace(bpe) => NormalizerWrapper::Replace(bpe),
+                    NormalizerUntagged::ReplaceMany(bpe) => NormalizerWrapper::ReplaceMany(bpe),
                     NormalizerUntagged::Prepend(bpe) => NormalizerWrapper::Prepend(bpe),
                     NormalizerUntagged::ByteLevel(bpe) => NormalizerWrapper::ByteLevel(bpe),
+                    NormalizerUntagged::NumericCanonicalize(bpe) => {
+                        NormalizerWrapper::NumericCanonicalize(bpe)
+                    }
+                    #[cfg(feature = "wasm-plugin")]
+                    NormalizerUntagged::Wasm(bpe) => NormalizerWrapper::Wasm(bpe),
                 }
             }
         })
@@ -185,6 +250,8 @@ impl Normalizer for NormalizerWrapper {
     fn normalize(&self, normalized: &mut NormalizedString) -> crate::Result<()> {
         match self {
             Self::BertNormalizer(bn) => bn.normalize(normalized),
+            Self::ArabicNormalizer(an) => an.normalize(normalized),
+            Self::ConfusablesFold(cf) => cf.normalize(normalized),
             Self::StripNormalizer(sn) => sn.normalize(normalized),
             Self::StripAccents(sn) => sn.normalize(normalized),
             Self::NFC(nfc) => nfc.normalize(normalized),
@@ -196,13 +263,19 @@ impl Normalizer for NormalizerWrapper {
             Self::Nmt(lc) => lc.normalize(normalized),
             Self::Precompiled(lc) => lc.normalize(normalized),
             Self::Replace(lc) => lc.normalize(normalized),
+            Self::ReplaceMany(lc) => lc.normalize(normalized),
             Self::Prepend(lc) => lc.normalize(normalized),
             Self::ByteLevel(lc) => lc.normalize(normalized),
+            Self::NumericCanonicalize(nc) => nc.normalize(normalized),
+            #[cfg(feature = "wasm-plugin")]
+            Self::Wasm(wasm) => wasm.normalize(normalized),
         }
     }
 }
 
 impl_enum_from!(BertNormalizer, NormalizerWrapper, BertNormalizer);
+impl_enum_from!(ArabicNormalizer, NormalizerWrapper, ArabicNormalizer);
+impl_enum_from!(ConfusablesFold, NormalizerWrapper, ConfusablesFold);
 impl_enum_from!(NFKD, NormalizerWrapper, NFKD);
 impl_enum_from!(NFKC, NormalizerWrapper, NFKC);
 impl_enum_from!(NFC, NormalizerWrapper, NFC);
@@ -214,8 +287,12 @@ impl_enum_from!(Lowercase, NormalizerWrapper, Lowercase);
 impl_enum_from!(Nmt, NormalizerWrapper, Nmt);
 impl_enum_from!(Precompiled, NormalizerWrapper, Precompiled);
 impl_enum_from!(Replace, NormalizerWrapper, Replace);
+impl_enum_from!(ReplaceMany, NormalizerWrapper, ReplaceMany);
 impl_enum_from!(Prepend, NormalizerWrapper, Prepend);
 impl_enum_from!(ByteLevel, NormalizerWrapper, ByteLevel);
+impl_enum_from!(NumericCanonicalize, NormalizerWrapper, NumericCanonicalize);
+#[cfg(feature = "wasm-plugin")]
+impl_enum_from!(WasmNormalizer, NormalizerWrapper, Wasm);
 
 #[cfg(test)]
 mod tests {
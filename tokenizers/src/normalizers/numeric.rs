@@ -0,0 +1,235 @@
+use crate::tokenizer::{NormalizedString, Normalizer, Result};
+
+use serde::{Deserialize, Serialize};
+
+/// Checks whether a character is one of the group separators we accept
+/// between digit groups: the regular space, the no-break spaces (common in
+/// French-formatted amounts), the dot and the comma
+fn is_group_sep(c: char) -> bool {
+    matches!(c, '.' | ',' | ' ' | '\u{00A0}' | '\u{202F}')
+}
+
+/// What to do with the numbers found in the input
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NumericStrategy {
+    /// Leave the numbers untouched
+    Keep,
+    /// Rewrite locale-formatted numbers into the canonical form: no group
+    /// separators, and a dot as the decimal separator
+    #[default]
+    Canonicalize,
+    /// Replace every number with the given placeholder token
+    Placeholder(String),
+}
+
+/// A normalizer rewriting locale-formatted numbers (`1.234,56`, `1 234,56`,
+/// `1,234.56`) into a single canonical form (`1234.56`), with correct
+/// alignments. This reduces the vocab fragmentation caused by the many ways
+/// financial text formats the same amount.
+///
+/// The decimal separator is the last dot or comma when it appears exactly
+/// once; a lone comma followed by exactly three digits is read as a group
+/// separator (`1,234` -> `1234`), while a lone dot is kept as a decimal
+/// (`1.234` is left untouched since it already matches the canonical form).
+/// Digit runs whose groups do not line up (dates like `01.09.2026`, IP
+/// addresses, ...) are left intact, as are digits glued to letters
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[non_exhaustive]
+pub struct NumericCanonicalize {
+    /// The rewriting strategy for the numbers found in the input
+    pub strategy: NumericStrategy,
+}
+
+impl NumericCanonicalize {
+    pub fn new(strategy: NumericStrategy) -> Self {
+        Self { strategy }
+    }
+}
+
+/// Parses a run of digits and group separators and returns its canonical
+/// form, or `None` when the separators do not describe a number (mismatched
+/// group lengths, mixed group separators, ...)
+fn canonicalize_run(run: &str) -> Option<String> {
+    let mut groups: Vec<&str> = vec![];
+    let mut seps: Vec<char> = vec![];
+    let mut start = 0;
+    for (i, c) in run.char_indices() {
+        if !c.is_ascii_digit() {
+            groups.push(&run[start..i]);
+            seps.push(if c == '\u{00A0}' || c == '\u{202F}' {
+                ' '
+            } else {
+                c
+            });
+            start = i + c.len_utf8();
+        }
+    }
+    groups.push(&run[start..]);
+    if seps.is_empty() {
+        return Some(run.to_string());
+    }
+
+    // The last separator is the decimal one when it is a dot or a comma
+    // appearing exactly once. Without other separators to disambiguate, a
+    // comma followed by exactly three digits is read as grouping instead
+    let last = *seps.last().expect("At least one separator");
+    let last_count = seps.iter().filter(|&&c| c == last).count();
+    let has_other = seps.iter().any(|&c| c != last);
+    let decimal = last != ' '
+        && last_count == 1
+        && (has_other || groups.last().expect("At least one group").len() != 3 || last == '.');
+
+    let (int_groups, fraction) = if decimal {
+        (&groups[..groups.len() - 1], groups.last().copied())
+    } else {
+        (&groups[..], None)
+    };
+    let group_seps = if decimal {
+        &seps[..seps.len() - 1]
+    } else {
+        &seps[..]
+    };
+    if !group_seps.is_empty() {
+        // All the group separators must agree, the leading group must have
+        // one to three digits, and every other group exactly three
+        let sep = group_seps[0];
+        if group_seps.iter().any(|&c| c != sep)
+            || int_groups[0].is_empty()
+            || int_groups[0].len() > 3
+            || int_groups[1..].iter().any(|g| g.len() != 3)
+        {
+            return None;
+        }
+    }
+
+    let mut canonical = int_groups.concat();
+    if let Some(fraction) = fraction {
+        canonical.push('.');
+        canonical.push_str(fraction);
+    }
+    Some(canonical)
+}
+
+impl Normalizer for NumericCanonicalize {
+    fn normalize(&self, normalized: &mut NormalizedString) -> Result<()> {
+        if self.strategy == NumericStrategy::Keep {
+            return Ok(());
+        }
+        let text = normalized.get();
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        let mut replacements: Vec<((usize, usize), String)> = vec![];
+        let mut i = 0;
+        while i < chars.len() {
+            if !chars[i].1.is_ascii_digit() {
+                i += 1;
+                continue;
+            }
+            // Extend the run through digits, and separators followed by a
+            // digit
+            let mut j = i;
+            while j + 1 < chars.len() {
+                let next = chars[j + 1].1;
+                if next.is_ascii_digit() {
+                    j += 1;
+                } else if is_group_sep(next)
+                    && j + 2 < chars.len()
+                    && chars[j + 2].1.is_ascii_digit()
+                {
+                    j += 2;
+                } else {
+                    break;
+                }
+            }
+            let start = chars[i].0;
+            let end = chars[j].0 + chars[j].1.len_utf8();
+            let prev_ok = i == 0 || !chars[i - 1].1.is_alphanumeric();
+            let next_ok = chars.get(j + 1).is_none_or(|&(_, c)| !c.is_alphanumeric());
+            if prev_ok && next_ok {
+                if let Some(canonical) = canonicalize_run(&text[start..end]) {
+                    match &self.strategy {
+                        NumericStrategy::Canonicalize => {
+                            if canonical != text[start..end] {
+                                replacements.push(((start, end), canonical));
+                            }
+                        }
+                        NumericStrategy::Placeholder(token) => {
+                            replacements.push(((start, end), token.clone()));
+                        }
+                        NumericStrategy::Keep => unreachable!(),
+                    }
+                }
+            }
+            i = j + 1;
+        }
+        if !replacements.is_empty() {
+            normalized.replace_ranges(replacements.iter().map(|(range, s)| (*range, s.as_str())));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::normalizer::Range;
+
+    #[test]
+    fn numeric_canonicalize() {
+        let normalizer = NumericCanonicalize::default();
+
+        let mut n = NormalizedString::from("total: 1.234,56 EUR");
+        normalizer.normalize(&mut n).unwrap();
+        assert_eq!(n.get(), "total: 1234.56 EUR");
+        // The alignments still map back to the original text
+        assert_eq!(
+            n.get_range_original(Range::Normalized(0..18)),
+            Some("total: 1.234,56 EUR")
+        );
+
+        let mut n = NormalizedString::from("1 234,56 and 1,234.56 and 1 234 567");
+        normalizer.normalize(&mut n).unwrap();
+        assert_eq!(n.get(), "1234.56 and 1234.56 and 1234567");
+
+        // A lone comma before three digits is grouping, otherwise decimal
+        let mut n = NormalizedString::from("1,234 vs 3,14");
+        normalizer.normalize(&mut n).unwrap();
+        assert_eq!(n.get(), "1234 vs 3.14");
+    }
+
+    #[test]
+    fn numeric_leaves_non_numbers_alone() {
+        let normalizer = NumericCanonicalize::default();
+        for text in ["01.09.2026", "192.168.0.1", "v1.2 beta", "1.234", "12 34"] {
+            let mut n = NormalizedString::from(text);
+            normalizer.normalize(&mut n).unwrap();
+            assert_eq!(n.get(), text);
+        }
+    }
+
+    #[test]
+    fn numeric_strategies() {
+        let normalizer = NumericCanonicalize::new(NumericStrategy::Keep);
+        let mut n = NormalizedString::from("1.234,56");
+        normalizer.normalize(&mut n).unwrap();
+        assert_eq!(n.get(), "1.234,56");
+
+        let normalizer = NumericCanonicalize::new(NumericStrategy::Placeholder("<num>".into()));
+        let mut n = NormalizedString::from("pay 1 234,56 by 2026");
+        normalizer.normalize(&mut n).unwrap();
+        assert_eq!(n.get(), "pay <num> by <num>");
+    }
+
+    #[test]
+    fn numeric_serde() {
+        let normalizer = NumericCanonicalize::default();
+        let normalizer_s = r#"{"type":"NumericCanonicalize","strategy":"Canonicalize"}"#;
+        assert_eq!(serde_json::to_string(&normalizer).unwrap(), normalizer_s);
+        let deserialized: NumericCanonicalize = serde_json::from_str(normalizer_s).unwrap();
+        assert_eq!(serde_json::to_string(&deserialized).unwrap(), normalizer_s);
+
+        let normalizer = NumericCanonicalize::new(NumericStrategy::Placeholder("<num>".into()));
+        let normalizer_s = r#"{"type":"NumericCanonicalize","strategy":{"Placeholder":"<num>"}}"#;
+        assert_eq!(serde_json::to_string(&normalizer).unwrap(), normalizer_s);
+    }
+}
@@ -1,8 +1,142 @@
 use crate::tokenizer::{NormalizedString, Normalizer, Result};
 pub use spm_precompiled::Precompiled;
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use unicode_segmentation::UnicodeSegmentation;
 
+/// Compile a list of `(source, replacement)` normalization rules into the
+/// `precompiled_charsmap` binary format consumed by [`Precompiled`]:
+/// `[u32 (length of trie), trie: [u32], normalized: String]` where the trie is a
+/// darts-clone style double-array over the source byte sequences, and the values
+/// point at '\0'-terminated entries in the normalized blob.
+///
+/// Sources must be non-empty, free of NUL bytes, and unique. Note that
+/// [`Precompiled`] only ever looks up single characters and graphemes shorter
+/// than 6 bytes, so longer sources will compile fine but never match.
+pub fn compile_charsmap<S, R>(rules: &[(S, R)]) -> Result<Vec<u8>>
+where
+    S: AsRef<str>,
+    R: AsRef<str>,
+{
+    // Deduplicate the replacement strings to build the normalized blob
+    let mut normalized = String::new();
+    let mut replacement_offsets: BTreeMap<&str, u32> = BTreeMap::new();
+    // Sources mapped to their value, sorted so shared prefixes sit together
+    let mut entries: BTreeMap<&[u8], u32> = BTreeMap::new();
+    for (source, replacement) in rules {
+        let (source, replacement) = (source.as_ref(), replacement.as_ref());
+        if source.is_empty() {
+            return Err("Cannot compile an empty source string".into());
+        }
+        if source.bytes().any(|b| b == 0) {
+            return Err("Cannot compile a source string containing a NUL byte".into());
+        }
+        let offset = *replacement_offsets.entry(replacement).or_insert_with(|| {
+            let offset = normalized.len() as u32;
+            normalized.push_str(replacement);
+            normalized.push('\0');
+            offset
+        });
+        if entries.insert(source.as_bytes(), offset).is_some() {
+            return Err(format!("Duplicate source string in charsmap: {source:?}").into());
+        }
+    }
+
+    let trie = build_double_array(&entries)?;
+    let mut blob = Vec::with_capacity(4 + 4 * trie.len() + normalized.len());
+    blob.extend_from_slice(&(4 * trie.len() as u32).to_le_bytes());
+    for unit in trie {
+        blob.extend_from_slice(&unit.to_le_bytes());
+    }
+    blob.extend_from_slice(normalized.as_bytes());
+    Ok(blob)
+}
+
+/// Compile a list of `(source, replacement)` rules directly into a usable
+/// [`Precompiled`] normalizer. See [`compile_charsmap`].
+pub fn precompiled_from_rules<S, R>(rules: &[(S, R)]) -> Result<Precompiled>
+where
+    S: AsRef<str>,
+    R: AsRef<str>,
+{
+    Ok(Precompiled::from(&compile_charsmap(rules)?)?)
+}
+
+/// Build a darts-clone compatible double-array from sorted `(key, value)` pairs.
+///
+/// Units are encoded the way `spm_precompiled` reads them back:
+/// - value unit: bit 31 set, value in the low 31 bits
+/// - node unit: label in bits 0..8, has_leaf in bit 8, offset in bits 10..31
+///
+/// Children of the node stored at index `i` with offset `o` live at `i ^ o ^ c`
+/// for each child label `c`, and the value unit of a terminal node at `i ^ o`.
+/// We always pick the XOR base as a multiple of 256 so that a node's children
+/// occupy a single 256-slot block.
+fn build_double_array(entries: &BTreeMap<&[u8], u32>) -> Result<Vec<u32>> {
+    let keys: Vec<&[u8]> = entries.keys().copied().collect();
+    if keys.is_empty() {
+        return Ok(vec![0u32; 256]);
+    }
+
+    let mut units = vec![0u32; 256];
+    let mut used = vec![false; 256];
+    used[0] = true;
+
+    // Process one trie node per iteration: the sorted key range [start, end) of
+    // keys sharing the prefix of length `depth`, stored at `index`
+    let mut stack = vec![(0usize, keys.len(), 0usize, 0usize)];
+    while let Some((start, end, depth, index)) = stack.pop() {
+        // A key ending at this node makes it terminal
+        let terminal = keys[start].len() == depth;
+        // Gather the child labels and their sub-ranges
+        let mut children: Vec<(u8, usize, usize)> = Vec::new();
+        let mut pos = if terminal { start + 1 } else { start };
+        while pos < end {
+            let label = keys[pos][depth];
+            let sub_end = keys[pos..end]
+                .iter()
+                .position(|k| k[depth] != label)
+                .map_or(end, |p| pos + p);
+            children.push((label, pos, sub_end));
+            pos = sub_end;
+        }
+
+        // Find a free 256-slot aligned base for the value unit and the children
+        let mut base = 0;
+        loop {
+            if base + 256 > used.len() {
+                used.resize(base + 256, false);
+                units.resize(base + 256, 0);
+            }
+            let fits = (!terminal || !used[base])
+                && children.iter().all(|&(c, _, _)| !used[base ^ c as usize]);
+            if fits {
+                break;
+            }
+            base += 256;
+        }
+
+        let offset = index ^ base;
+        if offset >= 1 << 21 {
+            return Err("Charsmap is too large to be compiled".into());
+        }
+        units[index] |= (offset as u32) << 10;
+        if terminal {
+            used[base] = true;
+            units[base] = (1 << 31) | entries[keys[start]];
+            units[index] |= 1 << 8;
+        }
+        for &(label, sub_start, sub_end) in &children {
+            let child = base ^ label as usize;
+            used[child] = true;
+            units[child] = label as u32;
+            stack.push((sub_start, sub_end, depth + 1, child));
+        }
+    }
+
+    Ok(units)
+}
+
 fn replace(transformations: &mut Vec<(char, isize)>, old_part: &str, new_part: &str) {
     let old_count = old_part.chars().count() as isize;
     let new_count = new_part.chars().count() as isize;
@@ -86,4 +220,47 @@ mod tests {
 
         assert_eq!(n.get(), "TMg");
     }
+
+    #[test]
+    fn compile_and_transform() {
+        let rules = [("™", "TM"), ("…", "..."), ("T", "t"), ("\u{00a0}", " ")];
+        let precompiled = precompiled_from_rules(&rules).unwrap();
+
+        assert_eq!(precompiled.transform("™"), Some("TM"));
+        assert_eq!(precompiled.transform("…"), Some("..."));
+        assert_eq!(precompiled.transform("T"), Some("t"));
+        assert_eq!(precompiled.transform("\u{00a0}"), Some(" "));
+        assert_eq!(precompiled.transform("g"), None);
+
+        let mut n = NormalizedString::from("a™…\u{00a0}b");
+        precompiled.normalize(&mut n).unwrap();
+        assert_eq!(n.get(), "aTM... b");
+    }
+
+    #[test]
+    fn compile_shared_prefixes() {
+        // Multi-byte sources sharing prefixes must not collide in the trie
+        let rules = [("ab", "1"), ("abc", "2"), ("abd", "3"), ("b", "4")];
+        let precompiled = precompiled_from_rules(&rules).unwrap();
+
+        // `transform` returns the shortest matching prefix
+        assert_eq!(precompiled.transform("ab"), Some("1"));
+        assert_eq!(precompiled.transform("b"), Some("4"));
+        assert_eq!(precompiled.transform("c"), None);
+    }
+
+    #[test]
+    fn compile_rejects_invalid_rules() {
+        assert!(compile_charsmap(&[("", "x")]).is_err());
+        assert!(compile_charsmap(&[("a\0b", "x")]).is_err());
+        assert!(compile_charsmap(&[("a", "x"), ("a", "y")]).is_err());
+    }
+
+    #[test]
+    fn compiled_blob_round_trips() {
+        let rules = [("é", "e")];
+        let blob = compile_charsmap(&rules).unwrap();
+        let precompiled = Precompiled::from(&blob).unwrap();
+        assert_eq!(precompiled.transform("é"), Some("e"));
+    }
 }
@@ -0,0 +1,106 @@
+//! Curated, ready-made normalization pipelines, shareable by name.
+//!
+//! Each preset returns a pre-built [`Sequence`], so it behaves and
+//! serializes like any hand-assembled pipeline. A serialized normalizer can
+//! also be a plain string naming a preset, e.g. `"normalizer": "nmt_nfkc"`,
+//! which deserializes through [`from_name`] to the same `Sequence`, so
+//! pipelines can be shared by identifier rather than copy-pasted config
+//! blocks.
+
+use crate::normalizers::{
+    BertNormalizer, ByteLevel, ConfusablesFold, Lowercase, Nmt, ReplaceMany, Sequence, NFKC,
+};
+
+/// The names of the available presets, as accepted by [`from_name`]
+pub const NAMES: &[&str] = &["bert_uncased", "nmt_nfkc", "gpt2", "clinical_fr"];
+
+/// Look a preset up by name
+pub fn from_name(name: &str) -> Option<Sequence> {
+    match name {
+        "bert_uncased" => Some(bert_uncased()),
+        "nmt_nfkc" => Some(nmt_nfkc()),
+        "gpt2" => Some(gpt2()),
+        "clinical_fr" => Some(clinical_fr()),
+        _ => None,
+    }
+}
+
+/// The default BERT cleanup: control character removal, whitespace
+/// unification, Chinese character isolation, accent stripping and
+/// lowercasing
+pub fn bert_uncased() -> Sequence {
+    Sequence::new(vec![
+        BertNormalizer::new(true, true, Some(true), true).into()
+    ])
+}
+
+/// The SentencePiece `nmt_nfkc` normalization: NMT cleanup followed by NFKC
+pub fn nmt_nfkc() -> Sequence {
+    Sequence::new(vec![Nmt.into(), NFKC.into()])
+}
+
+/// The GPT-2 byte-to-unicode mapping, without any other transformation
+pub fn gpt2() -> Sequence {
+    Sequence::new(vec![ByteLevel::new().into()])
+}
+
+/// A pipeline for French clinical text: NFKC, confusables folding, the
+/// replacements that generic Unicode normalization leaves untouched
+/// (ligatures, typographic apostrophes, non-breaking spaces), and
+/// lowercasing. Accents are kept, as they distinguish medical terms.
+pub fn clinical_fr() -> Sequence {
+    let replacements = ReplaceMany::new(vec![
+        ("œ", "oe"),
+        ("Œ", "Oe"),
+        ("æ", "ae"),
+        ("Æ", "Ae"),
+        ("’", "'"),
+        ("\u{00a0}", " "),
+        ("\u{202f}", " "),
+    ])
+    .expect("valid preset replacements");
+    Sequence::new(vec![
+        NFKC.into(),
+        ConfusablesFold::default().into(),
+        replacements.into(),
+        Lowercase.into(),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalizers::NormalizerWrapper;
+    use crate::{NormalizedString, Normalizer};
+
+    #[test]
+    fn every_name_resolves() {
+        for name in NAMES {
+            assert!(from_name(name).is_some(), "missing preset `{}`", name);
+        }
+        assert!(from_name("unknown").is_none());
+    }
+
+    #[test]
+    fn deserialize_by_name() {
+        let by_name: NormalizerWrapper = serde_json::from_str(r#""nmt_nfkc""#).unwrap();
+        // The name resolves to the same pipeline as the preset function
+        assert_eq!(
+            serde_json::to_string(&by_name).unwrap(),
+            serde_json::to_string(&NormalizerWrapper::from(nmt_nfkc())).unwrap()
+        );
+
+        let unknown = serde_json::from_str::<NormalizerWrapper>(r#""not_a_preset""#);
+        assert!(unknown
+            .unwrap_err()
+            .to_string()
+            .contains("Unknown normalizer preset"));
+    }
+
+    #[test]
+    fn clinical_fr_normalizes() {
+        let mut normalized = NormalizedString::from("Œdème de\u{00a0}Quincke à l’admission");
+        clinical_fr().normalize(&mut normalized).unwrap();
+        assert_eq!(normalized.get(), "oedème de quincke à l'admission");
+    }
+}
@@ -1,14 +1,79 @@
 use crate::tokenizer::{NormalizedString, Normalizer, Result};
 use crate::utils::macro_rules_attribute;
-use serde::{Deserialize, Serialize};
+use crate::utils::SysRegex;
+use serde::{Deserialize, Deserializer, Serialize};
 use unicode_normalization_alignments::char::is_combining_mark;
 
-#[derive(Copy, Clone, Debug, Deserialize, Serialize)]
+/// The characters that `Strip` should remove, when not stripping whitespace
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Eq)]
+pub enum StripCharacters {
+    /// An explicit set of characters
+    Set(Vec<char>),
+    /// A regex character class, e.g. `\p{Punctuation}` or `[\u{200b}\u{200c}]`
+    Regex(String),
+}
+
+#[derive(Debug, Serialize)]
 #[serde(tag = "type")]
 #[non_exhaustive]
 pub struct Strip {
     pub strip_left: bool,
     pub strip_right: bool,
+    /// The characters to strip; whitespace when `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub characters: Option<StripCharacters>,
+    #[serde(skip)]
+    regex: Option<SysRegex>,
+}
+
+impl<'de> Deserialize<'de> for Strip {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        enum Type {
+            Strip,
+        }
+
+        #[derive(Deserialize)]
+        pub struct StripHelper {
+            // Optional to keep accepting the legacy, untagged serialization
+            #[serde(rename = "type", default)]
+            _type: Option<Type>,
+            strip_left: bool,
+            strip_right: bool,
+            #[serde(default)]
+            characters: Option<StripCharacters>,
+        }
+
+        let helper = StripHelper::deserialize(deserializer)?;
+        let strip = Self::new(helper.strip_left, helper.strip_right);
+        match helper.characters {
+            Some(characters) => strip
+                .characters(characters)
+                .map_err(serde::de::Error::custom),
+            None => Ok(strip),
+        }
+    }
+}
+
+impl Clone for Strip {
+    fn clone(&self) -> Self {
+        let strip = Self::new(self.strip_left, self.strip_right);
+        match &self.characters {
+            Some(characters) => strip.characters(characters.clone()).unwrap(),
+            None => strip,
+        }
+    }
+}
+
+impl PartialEq for Strip {
+    fn eq(&self, other: &Self) -> bool {
+        self.strip_left == other.strip_left
+            && self.strip_right == other.strip_right
+            && self.characters == other.characters
+    }
 }
 
 impl Strip {
@@ -16,6 +81,30 @@ impl Strip {
         Self {
             strip_left,
             strip_right,
+            characters: None,
+            regex: None,
+        }
+    }
+
+    /// Strip the given characters instead of whitespace
+    pub fn characters(mut self, characters: StripCharacters) -> Result<Self> {
+        self.regex = match &characters {
+            StripCharacters::Regex(r) => Some(SysRegex::new(r)?),
+            StripCharacters::Set(_) => None,
+        };
+        self.characters = Some(characters);
+        Ok(self)
+    }
+
+    fn is_stripped(&self, c: char) -> bool {
+        match (&self.characters, &self.regex) {
+            (Some(StripCharacters::Set(set)), _) => set.contains(&c),
+            (Some(StripCharacters::Regex(_)), Some(regex)) => {
+                let mut buf = [0u8; 4];
+                let s: &str = c.encode_utf8(&mut buf);
+                regex.find_iter(s).next() == Some((0, s.len()))
+            }
+            _ => c.is_whitespace(),
         }
     }
 }
@@ -23,7 +112,9 @@ impl Strip {
 impl Normalizer for Strip {
     /// Strip the normalized string inplace
     fn normalize(&self, normalized: &mut NormalizedString) -> Result<()> {
-        if self.strip_left && self.strip_right {
+        if self.characters.is_some() {
+            normalized.strip_matching(self.strip_left, self.strip_right, |c| self.is_stripped(c));
+        } else if self.strip_left && self.strip_right {
             // Fast path
             normalized.strip();
         } else {
@@ -63,6 +154,37 @@ mod tests {
     use crate::normalizers::NFKD;
     use unicode_normalization_alignments::UnicodeNormalization;
 
+    #[test]
+    fn test_strip_characters() {
+        // An explicit character set
+        let strip = Strip::new(true, true)
+            .characters(StripCharacters::Set(vec!['.', ',', '!']))
+            .unwrap();
+        let mut n = NormalizedString::from("...Hey friend!!");
+        strip.normalize(&mut n).unwrap();
+        assert_eq!(n.get(), "Hey friend");
+
+        // A regex character class, stripping only on the right
+        let strip = Strip::new(false, true)
+            .characters(StripCharacters::Regex("\\p{Punctuation}".into()))
+            .unwrap();
+        let mut n = NormalizedString::from("¿Qué tal?");
+        strip.normalize(&mut n).unwrap();
+        assert_eq!(n.get(), "¿Qué tal");
+
+        // Serialization keeps the characters, and the default stays unchanged
+        let strip_s = r#"{"type":"Strip","strip_left":true,"strip_right":true,"characters":{"Set":[".",",","!"]}}"#;
+        let strip = Strip::new(true, true)
+            .characters(StripCharacters::Set(vec!['.', ',', '!']))
+            .unwrap();
+        assert_eq!(serde_json::to_string(&strip).unwrap(), strip_s);
+        assert_eq!(serde_json::from_str::<Strip>(strip_s).unwrap(), strip);
+        assert_eq!(
+            serde_json::to_string(&Strip::new(true, true)).unwrap(),
+            r#"{"type":"Strip","strip_left":true,"strip_right":true}"#
+        );
+    }
+
     #[test]
     fn test_strip_accents() {
         // Unicode combining char
@@ -24,6 +24,10 @@ impl Sequence {
     pub fn get_normalizers_mut(&mut self) -> &mut [NormalizerWrapper] {
         &mut self.normalizers
     }
+
+    pub fn into_normalizers(self) -> Vec<NormalizerWrapper> {
+        self.normalizers
+    }
 }
 
 impl Normalizer for Sequence {
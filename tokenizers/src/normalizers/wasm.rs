@@ -0,0 +1,247 @@
+//! A normalizer whose logic ships as a small WASM module, embedded in
+//! `tokenizer.json` as base64. This lets users bundle language-specific
+//! normalization with their tokenizer and run it sandboxed: the module gets no
+//! host imports, and execution is fuel-limited so it always terminates.
+
+use crate::tokenizer::{NormalizedString, Normalizer, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::convert::{TryFrom, TryInto};
+
+/// The maximum amount of fuel a single `normalize` call may consume, roughly
+/// proportional to the number of executed WASM instructions
+const FUEL_PER_CALL: u64 = 1_000_000_000;
+
+/// A [`Normalizer`] evaluating a user-provided WASM module.
+///
+/// The module must export its linear `memory`, along with two functions:
+///  - `alloc(len: i32) -> i32`: return a pointer to `len` writable bytes,
+///    where the host places the UTF-8 input sequence;
+///  - `normalize(ptr: i32, len: i32) -> i64`: return `(ptr << 32) | len` of a
+///    buffer of edits, each edit being 8 little-endian bytes: a `u32` unicode
+///    codepoint followed by an `i32` offset change, with the same meaning as
+///    the transformations of [`NormalizedString::transform`].
+pub struct WasmNormalizer {
+    /// The raw bytes of the WASM module
+    module: Vec<u8>,
+    engine: wasmi::Engine,
+    compiled: wasmi::Module,
+}
+
+impl WasmNormalizer {
+    /// Compile the given WASM module bytes
+    pub fn new(module: Vec<u8>) -> Result<Self> {
+        let mut config = wasmi::Config::default();
+        config.consume_fuel(true);
+        let engine = wasmi::Engine::new(&config);
+        let compiled = wasmi::Module::new(&engine, &module[..])?;
+        Ok(Self {
+            module,
+            engine,
+            compiled,
+        })
+    }
+
+    /// The raw bytes of the WASM module
+    pub fn get_module(&self) -> &[u8] {
+        &self.module
+    }
+
+    /// Instantiate the module and run its `normalize` export on the given
+    /// sequence, returning the edits to apply
+    fn call(&self, sequence: &str) -> Result<Vec<(char, isize)>> {
+        let mut store = wasmi::Store::new(&self.engine, ());
+        store.set_fuel(FUEL_PER_CALL).map_err(|e| e.to_string())?;
+        let linker = wasmi::Linker::new(&self.engine);
+        let instance = linker
+            .instantiate(&mut store, &self.compiled)?
+            .start(&mut store)?;
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or("The WASM module must export its `memory`")?;
+        let alloc = instance.get_typed_func::<i32, i32>(&store, "alloc")?;
+        let normalize = instance.get_typed_func::<(i32, i32), i64>(&store, "normalize")?;
+
+        let len = i32::try_from(sequence.len())?;
+        let ptr = alloc.call(&mut store, len)?;
+        memory
+            .write(&mut store, ptr as usize, sequence.as_bytes())
+            .map_err(|e| e.to_string())?;
+        let packed = normalize.call(&mut store, (ptr, len))?;
+
+        let (edits_ptr, edits_len) = ((packed >> 32) as u32 as usize, packed as u32 as usize);
+        if edits_len % 8 != 0 {
+            return Err("The WASM `normalize` function must return 8-byte edit records".into());
+        }
+        let mut buffer = vec![0u8; edits_len];
+        memory
+            .read(&store, edits_ptr, &mut buffer)
+            .map_err(|e| e.to_string())?;
+
+        buffer
+            .chunks_exact(8)
+            .map(|record| {
+                let codepoint = u32::from_le_bytes(record[..4].try_into()?);
+                let change = i32::from_le_bytes(record[4..].try_into()?);
+                let c = char::from_u32(codepoint)
+                    .ok_or_else(|| format!("Invalid codepoint {codepoint:#x} in WASM edit"))?;
+                Ok((c, change as isize))
+            })
+            .collect()
+    }
+}
+
+impl Normalizer for WasmNormalizer {
+    fn normalize(&self, normalized: &mut NormalizedString) -> Result<()> {
+        let edits = self.call(normalized.get())?;
+        normalized.transform(edits, 0);
+        Ok(())
+    }
+}
+
+impl Clone for WasmNormalizer {
+    fn clone(&self) -> Self {
+        Self::new(self.module.clone()).expect("The module compiled before, it should compile again")
+    }
+}
+
+impl PartialEq for WasmNormalizer {
+    fn eq(&self, other: &Self) -> bool {
+        self.module == other.module
+    }
+}
+
+impl std::fmt::Debug for WasmNormalizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WasmNormalizer")
+            .field("module", &format_args!("<{} bytes>", self.module.len()))
+            .finish()
+    }
+}
+
+impl Serialize for WasmNormalizer {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut normalizer = serializer.serialize_struct("WasmNormalizer", 2)?;
+        normalizer.serialize_field("type", "Wasm")?;
+        normalizer.serialize_field("module", &BASE64.encode(&self.module))?;
+        normalizer.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for WasmNormalizer {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        enum Type {
+            Wasm,
+        }
+
+        #[derive(Deserialize)]
+        pub struct WasmNormalizerHelper {
+            #[serde(rename = "type")]
+            _type: Type,
+            module: String,
+        }
+
+        let helper = WasmNormalizerHelper::deserialize(deserializer)?;
+        let module = BASE64
+            .decode(&helper.module)
+            .map_err(serde::de::Error::custom)?;
+        Self::new(module).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A module lowercasing ASCII text, one edit per input byte
+    const LOWERCASE_WAT: &str = r#"
+        (module
+          (memory (export "memory") 2)
+          (global $next (mut i32) (i32.const 1024))
+          (func (export "alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            global.get $next
+            local.set $ptr
+            global.get $next
+            local.get $len
+            i32.add
+            global.set $next
+            local.get $ptr)
+          (func (export "normalize") (param $ptr i32) (param $len i32) (result i64)
+            (local $i i32) (local $out i32) (local $c i32)
+            i32.const 65536
+            local.set $out
+            block $done
+              loop $bytes
+                local.get $i
+                local.get $len
+                i32.ge_u
+                br_if $done
+                local.get $ptr
+                local.get $i
+                i32.add
+                i32.load8_u
+                local.set $c
+                (i32.and
+                  (i32.ge_u (local.get $c) (i32.const 65))
+                  (i32.le_u (local.get $c) (i32.const 90)))
+                if
+                  local.get $c
+                  i32.const 32
+                  i32.add
+                  local.set $c
+                end
+                (i32.store
+                  (i32.add (local.get $out) (i32.mul (local.get $i) (i32.const 8)))
+                  (local.get $c))
+                local.get $i
+                i32.const 1
+                i32.add
+                local.set $i
+                br $bytes
+              end
+            end
+            (i64.or
+              (i64.shl (i64.extend_i32_u (local.get $out)) (i64.const 32))
+              (i64.extend_i32_u (i32.mul (local.get $len) (i32.const 8))))))
+    "#;
+
+    #[test]
+    fn wasm_normalizer() {
+        let module = wat::parse_str(LOWERCASE_WAT).unwrap();
+        let normalizer = WasmNormalizer::new(module).unwrap();
+
+        let mut n = NormalizedString::from("Hello WORLD!");
+        normalizer.normalize(&mut n).unwrap();
+        assert_eq!(n.get(), "hello world!");
+    }
+
+    #[test]
+    fn wasm_normalizer_serialization() {
+        let module = wat::parse_str(LOWERCASE_WAT).unwrap();
+        let normalizer = WasmNormalizer::new(module).unwrap();
+
+        let serialized = serde_json::to_string(&normalizer).unwrap();
+        assert!(serialized.starts_with(r#"{"type":"Wasm","module":""#));
+        let reconstructed: WasmNormalizer = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(reconstructed, normalizer);
+
+        let mut n = NormalizedString::from("Hey");
+        reconstructed.normalize(&mut n).unwrap();
+        assert_eq!(n.get(), "hey");
+    }
+
+    #[test]
+    fn wasm_normalizer_invalid_module() {
+        assert!(WasmNormalizer::new(vec![0, 1, 2, 3]).is_err());
+    }
+}
@@ -1,19 +1,85 @@
+use std::collections::HashSet;
+
 use crate::tokenizer::{PreTokenizedString, PreTokenizer, Result, SplitDelimiterBehavior};
 use crate::utils::macro_rules_attribute;
+use serde::{Deserialize, Serialize};
 use unicode_categories::UnicodeCategories;
 
 fn is_bert_punc(x: char) -> bool {
     char::is_ascii_punctuation(&x) || x.is_punctuation()
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+/// The CJK ranges the original BERT tokenizes into single characters
+fn is_chinese_char(c: char) -> bool {
+    matches!(
+        c as usize,
+        0x4E00..=0x9FFF |
+        0x3400..=0x4DBF |
+        0x20000..=0x2A6DF |
+        0x2A700..=0x2B73F |
+        0x2B740..=0x2B81F |
+        0x2B920..=0x2CEAF |
+        0xF900..=0xFAFF |
+        0x2F800..=0x2FA1F
+    )
+}
+
+fn is_false(b: &bool) -> bool {
+    !b
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
 #[macro_rules_attribute(impl_serde_type!)]
-pub struct BertPreTokenizer;
+pub struct BertPreTokenizer {
+    /// Whether to split CJK characters into single-character tokens, like the
+    /// Python `BertTokenizer` with `tokenize_chinese_chars=True`. Off by
+    /// default: this is usually handled by the `BertNormalizer`.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub tokenize_chinese_chars: bool,
+    /// Whether to strip accents (NFD + removal of non-spacing marks). `None`,
+    /// the default, leaves the text untouched.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strip_accents: Option<bool>,
+    /// Whitespace-delimited tokens that should never be split on punctuation,
+    /// e.g. `[UNK]`
+    #[serde(default, skip_serializing_if = "HashSet::is_empty")]
+    pub never_split: HashSet<String>,
+}
+
+impl BertPreTokenizer {
+    pub fn new(
+        tokenize_chinese_chars: bool,
+        strip_accents: Option<bool>,
+        never_split: HashSet<String>,
+    ) -> Self {
+        Self {
+            tokenize_chinese_chars,
+            strip_accents,
+            never_split,
+        }
+    }
+}
 
 impl PreTokenizer for BertPreTokenizer {
     fn pre_tokenize(&self, pretokenized: &mut PreTokenizedString) -> Result<()> {
+        if self.strip_accents.unwrap_or(false) {
+            pretokenized.normalize(|normalized| {
+                normalized.nfd().filter(|c| !c.is_mark_nonspacing());
+                Ok(())
+            })?;
+        }
+        if self.tokenize_chinese_chars {
+            pretokenized
+                .split(|_, s| s.split(is_chinese_char, SplitDelimiterBehavior::Isolated))?;
+        }
         pretokenized.split(|_, s| s.split(char::is_whitespace, SplitDelimiterBehavior::Removed))?;
-        pretokenized.split(|_, s| s.split(is_bert_punc, SplitDelimiterBehavior::Isolated))
+        pretokenized.split(|_, s| {
+            if self.never_split.contains(s.get()) {
+                Ok(vec![s])
+            } else {
+                s.split(is_bert_punc, SplitDelimiterBehavior::Isolated)
+            }
+        })
     }
 }
 
@@ -24,7 +90,7 @@ mod tests {
 
     #[test]
     fn basic() {
-        let pretok = BertPreTokenizer;
+        let pretok = BertPreTokenizer::default();
         let mut pretokenized: PreTokenizedString = "Hey friend!     How are you?!?".into();
         pretok.pre_tokenize(&mut pretokenized).unwrap();
         assert_eq!(
@@ -61,7 +127,7 @@ mod tests {
             0,
         );
         let mut pretokenized = n.into();
-        let pretok = BertPreTokenizer;
+        let pretok = BertPreTokenizer::default();
         pretok.pre_tokenize(&mut pretokenized).unwrap();
         assert_eq!(
             pretokenized
@@ -79,4 +145,59 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn parity_options() {
+        // tokenize_chinese_chars isolates CJK characters without a normalizer
+        let pretok = BertPreTokenizer::new(true, None, HashSet::new());
+        let mut pretokenized: PreTokenizedString = "野口里佳 Noguchi".into();
+        pretok.pre_tokenize(&mut pretokenized).unwrap();
+        assert_eq!(
+            pretokenized
+                .get_splits(OffsetReferential::Original, OffsetType::Byte)
+                .into_iter()
+                .map(|(s, _, _)| s)
+                .collect::<Vec<_>>(),
+            vec!["野", "口", "里", "佳", "Noguchi"]
+        );
+
+        // strip_accents removes the non-spacing marks
+        let pretok = BertPreTokenizer::new(false, Some(true), HashSet::new());
+        let mut pretokenized: PreTokenizedString = "Héllò".into();
+        pretok.pre_tokenize(&mut pretokenized).unwrap();
+        assert_eq!(
+            pretokenized
+                .get_splits(OffsetReferential::Normalized, OffsetType::Byte)
+                .into_iter()
+                .map(|(s, _, _)| s)
+                .collect::<Vec<_>>(),
+            vec!["Hello"]
+        );
+
+        // never_split protects special markers from the punctuation split
+        let never_split: HashSet<String> = ["[UNK]".to_string()].iter().cloned().collect();
+        let pretok = BertPreTokenizer::new(false, None, never_split);
+        let mut pretokenized: PreTokenizedString = "Hey [UNK] !".into();
+        pretok.pre_tokenize(&mut pretokenized).unwrap();
+        assert_eq!(
+            pretokenized
+                .get_splits(OffsetReferential::Original, OffsetType::Byte)
+                .into_iter()
+                .map(|(s, _, _)| s)
+                .collect::<Vec<_>>(),
+            vec!["Hey", "[UNK]", "!"]
+        );
+    }
+
+    #[test]
+    fn serde_stability() {
+        // The default configuration keeps the historical serialization
+        let pretok = BertPreTokenizer::default();
+        let pretok_s = r#"{"type":"BertPreTokenizer"}"#;
+        assert_eq!(serde_json::to_string(&pretok).unwrap(), pretok_s);
+        assert_eq!(
+            serde_json::from_str::<BertPreTokenizer>(pretok_s).unwrap(),
+            pretok
+        );
+    }
 }
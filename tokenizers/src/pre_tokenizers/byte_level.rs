@@ -1,11 +1,11 @@
 use std::collections::{HashMap, HashSet};
 
 use crate::utils::SysRegex;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::tokenizer::{
-    Decoder, Encoding, PostProcessor, PreTokenizedString, PreTokenizer, Result,
-    SplitDelimiterBehavior,
+    DecodedWithAlignment, Decoder, Encoding, PostProcessor, PreTokenizedString, PreTokenizer,
+    Result, SplitDelimiterBehavior,
 };
 use crate::utils::macro_rules_attribute;
 
@@ -34,6 +34,65 @@ pub(crate) fn bytes_char() -> HashMap<u8, char> {
         .collect()
 }
 
+/// A custom, reversible byte <-> unicode mapping: entry `b` of the table is the
+/// character representing byte `b`. All 256 entries must be distinct, so that
+/// the decoder can map every character back to its original byte.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ByteMap {
+    chars: Vec<char>,
+    reverse: HashMap<char, u8>,
+}
+
+impl ByteMap {
+    pub fn new(chars: Vec<char>) -> Result<Self> {
+        if chars.len() != 256 {
+            return Err(
+                format!("ByteMap requires exactly 256 entries, got {}", chars.len()).into(),
+            );
+        }
+        let reverse: HashMap<char, u8> = chars
+            .iter()
+            .enumerate()
+            .map(|(b, &c)| (c, b as u8))
+            .collect();
+        if reverse.len() != 256 {
+            return Err("ByteMap entries must all be distinct".into());
+        }
+        Ok(Self { chars, reverse })
+    }
+
+    pub fn byte_to_char(&self, b: u8) -> char {
+        self.chars[b as usize]
+    }
+
+    pub fn char_to_byte(&self, c: char) -> Option<u8> {
+        self.reverse.get(&c).copied()
+    }
+
+    pub fn alphabet(&self) -> HashSet<char> {
+        self.chars.iter().copied().collect()
+    }
+}
+
+impl Serialize for ByteMap {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.chars.iter().collect::<String>())
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteMap {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let chars = String::deserialize(deserializer)?;
+        Self::new(chars.chars().collect()).map_err(serde::de::Error::custom)
+    }
+}
+
 lazy_static! {
     /// Regex that matches exactly one token.
     /// See https://github.com/openai/gpt-2/blob/master/src/encoder.py#L98
@@ -42,11 +101,21 @@ lazy_static! {
     )
     .unwrap();
     static ref BYTES_CHAR: HashMap<u8, char> = bytes_char();
-    static ref CHAR_BYTES: HashMap<char, u8> =
-        bytes_char().into_iter().map(|(c, b)| (b, c)).collect();
+    /// The inverse of [`BYTES_CHAR`] as a dense lookup table: the GPT2 mapping
+    /// only produces chars below [`CHAR_BYTES_TABLE_LEN`], so decoding can
+    /// index instead of hashing
+    static ref CHAR_BYTES: [Option<u8>; CHAR_BYTES_TABLE_LEN] = {
+        let mut table = [None; CHAR_BYTES_TABLE_LEN];
+        for (b, c) in bytes_char() {
+            table[c as usize] = Some(b);
+        }
+        table
+    };
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+const CHAR_BYTES_TABLE_LEN: usize = 512;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 /// Provides all the necessary steps to handle the BPE tokenization at the byte-level. Takes care
 /// of all the required processing steps to transform a UTF-8 string as needed before and after the
 /// BPE model does its job.
@@ -63,6 +132,11 @@ pub struct ByteLevel {
     /// Set it to False if you want to use your own splitting.
     #[serde(default = "default_true")]
     pub use_regex: bool,
+
+    /// An optional custom byte <-> unicode mapping replacing the GPT2 one, for
+    /// vocabularies built with a different byte alphabet.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub byte_map: Option<ByteMap>,
 }
 
 fn default_true() -> bool {
@@ -75,6 +149,7 @@ impl Default for ByteLevel {
             add_prefix_space: true,
             trim_offsets: true,
             use_regex: true,
+            byte_map: None,
         }
     }
 }
@@ -85,6 +160,7 @@ impl ByteLevel {
             add_prefix_space,
             trim_offsets,
             use_regex,
+            byte_map: None,
         }
     }
 
@@ -109,6 +185,26 @@ impl ByteLevel {
         self.use_regex = v;
         self
     }
+
+    #[must_use]
+    pub fn byte_map(mut self, v: ByteMap) -> Self {
+        self.byte_map = Some(v);
+        self
+    }
+
+    fn byte_char(&self, b: u8) -> char {
+        match &self.byte_map {
+            Some(map) => map.byte_to_char(b),
+            None => BYTES_CHAR[&b],
+        }
+    }
+
+    fn char_byte(&self, c: char) -> Option<u8> {
+        match &self.byte_map {
+            Some(map) => map.char_to_byte(c),
+            None => CHAR_BYTES.get(c as usize).copied().flatten(),
+        }
+    }
 }
 
 /// As a `PreTokenizer`, `ByteLevel` is in charge of transforming all the unicode characters into
@@ -139,13 +235,21 @@ impl PreTokenizer for ByteLevel {
                     bytes
                         .iter()
                         .enumerate()
-                        .map(|(i, b)| (BYTES_CHAR[b], isize::from(i > 0))),
+                        .map(|(i, b)| (self.byte_char(*b), isize::from(i > 0))),
                 );
             }
             normalized.transform(transformations, 0);
             Ok(())
         })
     }
+
+    fn prefix_space(&self) -> Option<bool> {
+        Some(self.add_prefix_space)
+    }
+
+    fn set_prefix_space(&mut self, prefix_space: bool) {
+        self.add_prefix_space = prefix_space;
+    }
 }
 
 /// As a `Decoder`, `ByteLevel` is in charge of converting any byte-level characters to their
@@ -155,20 +259,87 @@ impl PreTokenizer for ByteLevel {
 /// as String.
 impl Decoder for ByteLevel {
     fn decode_chain(&self, tokens: Vec<String>) -> Result<Vec<String>> {
-        let toks = tokens
+        let (bytes, _) = self.decode_bytes(&tokens);
+        Ok(vec![String::from_utf8_lossy(&bytes).into_owned()])
+    }
+
+    fn decode_with_alignment(&self, tokens: Vec<String>) -> Result<DecodedWithAlignment> {
+        let (bytes, ranges) = self.decode_bytes(&tokens);
+
+        // Lossy UTF-8 conversion, keeping a mapping from every position in
+        // `bytes` to its position in the text: bytes of an invalid sequence
+        // all map to the replacement character standing for them
+        let mut text = String::with_capacity(bytes.len());
+        let mut mapping = Vec::with_capacity(bytes.len() + 1);
+        let mut input = bytes.as_slice();
+        loop {
+            let (valid, invalid_len) = match std::str::from_utf8(input) {
+                Ok(valid) => (valid, 0),
+                Err(error) => {
+                    let error_len = error
+                        .error_len()
+                        .unwrap_or(input.len() - error.valid_up_to());
+                    (
+                        std::str::from_utf8(&input[..error.valid_up_to()]).unwrap(),
+                        error_len,
+                    )
+                }
+            };
+            mapping.extend((0..valid.len()).map(|i| text.len() + i));
+            text.push_str(valid);
+            if invalid_len == 0 {
+                break;
+            }
+            mapping.extend(std::iter::repeat_n(text.len(), invalid_len));
+            text.push(char::REPLACEMENT_CHARACTER);
+            input = &input[valid.len() + invalid_len..];
+        }
+        mapping.push(text.len());
+
+        let alignments = ranges
             .into_iter()
-            .flat_map(|t| {
-                t.chars()
-                    .try_fold(vec![], |mut acc, c| {
-                        CHAR_BYTES.get(&c).map(|b| {
-                            acc.push(*b);
-                            acc
-                        })
-                    })
-                    .unwrap_or_else(|| t.as_bytes().to_vec())
-            })
-            .collect::<Vec<u8>>();
-        Ok(vec![String::from_utf8_lossy(&toks).to_string()])
+            .map(|(start, end)| Some((mapping[start], mapping[end])))
+            .collect();
+        Ok(DecodedWithAlignment { text, alignments })
+    }
+
+    fn prefix_space(&self) -> Option<bool> {
+        Some(self.add_prefix_space)
+    }
+
+    fn set_prefix_space(&mut self, prefix_space: bool) {
+        self.add_prefix_space = prefix_space;
+    }
+}
+
+impl ByteLevel {
+    /// Decode every token into a single pre-allocated buffer (a byte-level
+    /// char is never shorter than the byte it stands for, so the total UTF-8
+    /// length of the tokens is a cheap upper bound), also returning the range
+    /// of bytes each token produced
+    fn decode_bytes(&self, tokens: &[String]) -> (Vec<u8>, Vec<(usize, usize)>) {
+        let mut bytes = Vec::with_capacity(tokens.iter().map(|t| t.len()).sum());
+        let mut ranges = Vec::with_capacity(tokens.len());
+        for token in tokens {
+            let start = bytes.len();
+            let mut mapped = true;
+            for c in token.chars() {
+                match self.char_byte(c) {
+                    Some(b) => bytes.push(b),
+                    None => {
+                        mapped = false;
+                        break;
+                    }
+                }
+            }
+            if !mapped {
+                // Tokens outside the byte alphabet are kept as-is
+                bytes.truncate(start);
+                bytes.extend_from_slice(token.as_bytes());
+            }
+            ranges.push((start, bytes.len()));
+        }
+        (bytes, ranges)
     }
 }
 
@@ -184,12 +355,15 @@ impl PostProcessor for ByteLevel {
         _add_special_tokens: bool,
     ) -> Result<Vec<Encoding>> {
         if self.trim_offsets {
+            let space = self.byte_char(b' ');
             for encoding in encodings.iter_mut() {
-                process_offsets(encoding, self.add_prefix_space);
+                process_offsets_with_space(encoding, self.add_prefix_space, space);
                 encoding
                     .get_overflowing_mut()
                     .iter_mut()
-                    .for_each(|encoding| process_offsets(encoding, self.add_prefix_space));
+                    .for_each(|encoding| {
+                        process_offsets_with_space(encoding, self.add_prefix_space, space)
+                    });
             }
         }
         for (i, encoding) in encodings.iter_mut().enumerate() {
@@ -198,18 +372,30 @@ impl PostProcessor for ByteLevel {
         Ok(encodings)
         //<dyn PostProcessor>::default_process(encodings, add_special_tokens)
     }
+
+    fn prefix_space(&self) -> Option<bool> {
+        Some(self.add_prefix_space)
+    }
+
+    fn set_prefix_space(&mut self, prefix_space: bool) {
+        self.add_prefix_space = prefix_space;
+    }
 }
 
 pub fn process_offsets(encoding: &mut Encoding, add_prefix_space: bool) {
+    process_offsets_with_space(encoding, add_prefix_space, BYTES_CHAR[&b' ']);
+}
+
+fn process_offsets_with_space(encoding: &mut Encoding, add_prefix_space: bool, space: char) {
     encoding.process_tokens_with_offsets_mut(|(i, (token, offsets))| {
         let mut leading_spaces = token
             .chars()
-            .take_while(|c| *c == BYTES_CHAR[&b' '] || c.is_whitespace())
+            .take_while(|c| *c == space || c.is_whitespace())
             .count();
         let trailing_spaces = token
             .chars()
             .rev()
-            .take_while(|c| *c == BYTES_CHAR[&b' '] || c.is_whitespace())
+            .take_while(|c| *c == space || c.is_whitespace())
             .count();
 
         if leading_spaces > 0 || trailing_spaces > 0 {
@@ -358,6 +544,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn decode_with_alignment_tracks_tokens() {
+        let bytelevel = ByteLevel::default();
+        let decoded = bytelevel
+            .decode_with_alignment(vec!["Hello".into(), "Ġthere".into(), "Ċ".into()])
+            .unwrap();
+        assert_eq!(decoded.text, "Hello there\n");
+        assert_eq!(
+            decoded.alignments,
+            vec![Some((0, 5)), Some((5, 11)), Some((11, 12))]
+        );
+        assert_eq!(decoded.byte_to_token(5), Some(1));
+        assert_eq!(decoded.char_to_token(11), Some(2));
+
+        // The bytes of an invalid sequence are attributed to the replacement
+        // character standing for them
+        let decoded = bytelevel
+            .decode_with_alignment(vec!["Hey".into(), "ó".into()])
+            .unwrap();
+        assert_eq!(decoded.text, "Hey\u{fffd}");
+        assert_eq!(decoded.alignments, vec![Some((0, 3)), Some((3, 6))]);
+    }
+
     #[test]
     fn handling_of_newlines() {
         let mut pretokenized = PreTokenizedString::from("Hello there\nHello there");
@@ -591,4 +800,50 @@ mod tests {
         .unwrap();
         assert!(!byte_level.use_regex);
     }
+
+    #[test]
+    fn custom_byte_map() {
+        // Map every byte `b` to the character 0x100 + b
+        let map = ByteMap::new(
+            (0u32..256)
+                .map(|b| char::from_u32(0x100 + b).unwrap())
+                .collect(),
+        )
+        .unwrap();
+        let bytelevel = ByteLevel::default()
+            .add_prefix_space(false)
+            .byte_map(map.clone());
+
+        let mut pretokenized = PreTokenizedString::from("ab");
+        bytelevel.pre_tokenize(&mut pretokenized).unwrap();
+        let encoded: Vec<_> = pretokenized
+            .get_splits(OffsetReferential::Original, OffsetType::Byte)
+            .into_iter()
+            .map(|(s, _, _)| s.to_owned())
+            .collect();
+        assert_eq!(encoded, vec!["\u{161}\u{162}"]);
+
+        // The decoder uses the same mapping, so encoding and decoding are
+        // symmetric
+        assert_eq!(
+            bytelevel.decode_chain(encoded).unwrap(),
+            vec!["ab".to_string()]
+        );
+        assert_eq!(map.alphabet().len(), 256);
+
+        let serialized = serde_json::to_string(&bytelevel).unwrap();
+        let reconstructed: ByteLevel = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(reconstructed, bytelevel);
+    }
+
+    #[test]
+    fn invalid_byte_maps() {
+        assert!(ByteMap::new(vec!['a'; 3]).is_err());
+        // Duplicated entries cannot be reversed
+        let mut chars: Vec<char> = (0u32..256)
+            .map(|b| char::from_u32(0x100 + b).unwrap())
+            .collect();
+        chars[1] = chars[0];
+        assert!(ByteMap::new(chars).is_err());
+    }
 }
@@ -0,0 +1,230 @@
+use serde::{Deserialize, Serialize};
+
+use crate::normalizer::Range;
+use crate::tokenizer::{OffsetReferential, OffsetType, PreTokenizedString, PreTokenizer, Result};
+use crate::utils::macro_rules_attribute;
+
+/// The kind of edit to apply at a boundary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum BoundaryEdit {
+    /// Leave the boundary untouched
+    #[default]
+    None,
+    /// Make sure the boundary character is present
+    Ensure,
+    /// Remove the boundary character(s)
+    Strip,
+}
+
+/// The splits an [`EditBoundaries`] applies its edits to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum Scope {
+    /// Edit the boundaries of every split
+    #[default]
+    PerSplit,
+    /// Only edit the boundaries of the whole sequence: the start of the first
+    /// split and the end of the last one
+    WholeSequence,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Ensures or strips a boundary character at the edges of the splits, e.g. to
+/// guarantee a leading space before encoding, or to drop a trailing newline.
+/// Empty splits are left untouched: they have no boundary to edit.
+#[non_exhaustive]
+#[macro_rules_attribute(impl_serde_type!)]
+pub struct EditBoundaries {
+    /// What to do at the start of a split
+    #[serde(default)]
+    pub leading: BoundaryEdit,
+    /// What to do at the end of a split
+    #[serde(default)]
+    pub trailing: BoundaryEdit,
+    /// The boundary character to ensure or strip, a space by default
+    #[serde(default = "default_boundary")]
+    pub boundary: char,
+    #[serde(default)]
+    pub scope: Scope,
+}
+
+fn default_boundary() -> char {
+    ' '
+}
+
+impl EditBoundaries {
+    pub fn new(
+        leading: BoundaryEdit,
+        trailing: BoundaryEdit,
+        boundary: char,
+        scope: Scope,
+    ) -> Self {
+        Self {
+            leading,
+            trailing,
+            boundary,
+            scope,
+        }
+    }
+}
+
+impl Default for EditBoundaries {
+    fn default() -> Self {
+        Self::new(
+            BoundaryEdit::Ensure,
+            BoundaryEdit::None,
+            ' ',
+            Scope::PerSplit,
+        )
+    }
+}
+
+impl PreTokenizer for EditBoundaries {
+    fn pre_tokenize(&self, pretokenized: &mut PreTokenizedString) -> Result<()> {
+        // An empty input has no boundary to edit. `PreTokenizedString::split`
+        // removes empty splits, so we have to bail out before calling it.
+        if pretokenized
+            .get_splits(OffsetReferential::Original, OffsetType::Byte)
+            .iter()
+            .all(|(s, _, _)| s.is_empty())
+        {
+            return Ok(());
+        }
+
+        let boundary_str = self.boundary.to_string();
+        let n_splits = match self.scope {
+            Scope::PerSplit => 0,
+            Scope::WholeSequence => pretokenized
+                .get_splits(OffsetReferential::Original, OffsetType::Byte)
+                .len(),
+        };
+        pretokenized.split(|i, mut normalized| {
+            // An empty split has no boundary to edit
+            if normalized.is_empty() {
+                return Ok(vec![normalized]);
+            }
+
+            let (leading, trailing) = match self.scope {
+                Scope::PerSplit => (self.leading, self.trailing),
+                Scope::WholeSequence => (
+                    if i == 0 {
+                        self.leading
+                    } else {
+                        BoundaryEdit::None
+                    },
+                    if i + 1 == n_splits {
+                        self.trailing
+                    } else {
+                        BoundaryEdit::None
+                    },
+                ),
+            };
+
+            if leading == BoundaryEdit::Ensure && !normalized.get().starts_with(self.boundary) {
+                normalized.prepend(&boundary_str);
+            }
+            if trailing == BoundaryEdit::Ensure && !normalized.get().ends_with(self.boundary) {
+                normalized.append(&boundary_str);
+            }
+
+            // Both strips are applied in one go, so that a split made only of
+            // boundary characters is removed altogether
+            let s = normalized.get();
+            let start = if leading == BoundaryEdit::Strip {
+                s.len() - s.trim_start_matches(self.boundary).len()
+            } else {
+                0
+            };
+            let end = if trailing == BoundaryEdit::Strip {
+                s.trim_end_matches(self.boundary).len()
+            } else {
+                s.len()
+            };
+            if start == 0 && end == s.len() {
+                Ok(vec![normalized])
+            } else if start >= end {
+                Ok(vec![])
+            } else {
+                Ok(vec![normalized
+                    .slice(Range::Normalized(start..end))
+                    .expect("EditBoundaries bug: invalid strip range")])
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pre_tokenizers::whitespace::WhitespaceSplit;
+
+    fn splits(pretokenized: &PreTokenizedString) -> Vec<&str> {
+        pretokenized
+            .get_splits(OffsetReferential::Original, OffsetType::Byte)
+            .into_iter()
+            .map(|(s, _, _)| s)
+            .collect()
+    }
+
+    #[test]
+    fn ensure_leading_per_split() {
+        let pretok = EditBoundaries::default();
+        let mut pretokenized = PreTokenizedString::from("Hey friend!");
+        WhitespaceSplit.pre_tokenize(&mut pretokenized).unwrap();
+        pretok.pre_tokenize(&mut pretokenized).unwrap();
+        assert_eq!(
+            pretokenized
+                .get_splits(OffsetReferential::Normalized, OffsetType::Byte)
+                .into_iter()
+                .map(|(s, o, _)| (s, o))
+                .collect::<Vec<_>>(),
+            vec![(" Hey", (0, 4)), (" friend!", (4, 12))]
+        );
+    }
+
+    #[test]
+    fn whole_sequence_scope() {
+        let pretok = EditBoundaries::new(
+            BoundaryEdit::Ensure,
+            BoundaryEdit::None,
+            ' ',
+            Scope::WholeSequence,
+        );
+        let mut pretokenized = PreTokenizedString::from("Hey friend!");
+        WhitespaceSplit.pre_tokenize(&mut pretokenized).unwrap();
+        pretok.pre_tokenize(&mut pretokenized).unwrap();
+        // Only the first split is edited
+        assert_eq!(splits(&pretokenized), vec![" Hey", "friend!"]);
+    }
+
+    #[test]
+    fn strip_other_boundary_chars() {
+        let pretok = EditBoundaries::new(
+            BoundaryEdit::None,
+            BoundaryEdit::Strip,
+            '\n',
+            Scope::WholeSequence,
+        );
+        let mut pretokenized = PreTokenizedString::from("Hello world\n\n");
+        pretok.pre_tokenize(&mut pretokenized).unwrap();
+        assert_eq!(splits(&pretokenized), vec!["Hello world"]);
+
+        // Stripping both sides of a boundary-only split removes it
+        let pretok = EditBoundaries::new(
+            BoundaryEdit::Strip,
+            BoundaryEdit::Strip,
+            '\n',
+            Scope::PerSplit,
+        );
+        let mut pretokenized = PreTokenizedString::from("\n\n");
+        pretok.pre_tokenize(&mut pretokenized).unwrap();
+        assert_eq!(splits(&pretokenized), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn empty_input_is_a_noop() {
+        let pretok = EditBoundaries::default();
+        let mut pretokenized = PreTokenizedString::from("");
+        pretok.pre_tokenize(&mut pretokenized).unwrap();
+        assert_eq!(splits(&pretokenized), vec![""]);
+    }
+}
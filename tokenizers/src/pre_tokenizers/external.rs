@@ -0,0 +1,232 @@
+use std::fmt;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::normalizer::Range;
+use crate::tokenizer::{Offsets, PreTokenizedString, PreTokenizer, Result};
+use crate::utils::macro_rules_attribute;
+
+type SegmentFn = dyn Fn(&str) -> Result<Vec<Offsets>> + Send + Sync;
+
+/// A pre-tokenizer backed by an external word segmenter (Jieba, MeCab, ...),
+/// for languages where word boundaries cannot be found with character rules
+/// alone. The user-supplied callback receives the text of each split and
+/// returns the byte ranges of the words it found; each word becomes its own
+/// split, gaps between words are kept as splits of their own, and offsets map
+/// back to the original input like for any other pre-tokenizer.
+///
+/// An arbitrary callback cannot be represented in `tokenizer.json`, so only
+/// the segmenter name and version are serialized. Deserializing yields a
+/// detached `External` that remembers both but fails with a clear error when
+/// used; call [`External::attach`] to plug the callback back in:
+///
+/// ```ignore
+/// let pretok: External = serde_json::from_str(json)?;
+/// assert_eq!(pretok.segmenter, "jieba");
+/// let pretok = pretok.attach(|text| Ok(my_jieba.cut(text)));
+/// ```
+#[derive(Clone)]
+#[macro_rules_attribute(impl_serde_type!)]
+pub struct External {
+    /// The name of the external segmenter, e.g. `"jieba"` or `"mecab"`
+    pub segmenter: String,
+    /// The version of the external segmenter, recorded so that loading a
+    /// tokenizer against a different segmenter release can be detected
+    pub version: String,
+    #[serde(skip)]
+    segment_fn: Option<Arc<SegmentFn>>,
+}
+
+impl External {
+    pub fn new(
+        segmenter: impl Into<String>,
+        version: impl Into<String>,
+        segment_fn: impl Fn(&str) -> Result<Vec<Offsets>> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            segmenter: segmenter.into(),
+            version: version.into(),
+            segment_fn: Some(Arc::new(segment_fn)),
+        }
+    }
+
+    /// Plug a segmentation callback into a detached `External`, typically
+    /// after deserializing it.
+    #[must_use]
+    pub fn attach(
+        mut self,
+        segment_fn: impl Fn(&str) -> Result<Vec<Offsets>> + Send + Sync + 'static,
+    ) -> Self {
+        self.segment_fn = Some(Arc::new(segment_fn));
+        self
+    }
+
+    /// Whether a segmentation callback is attached. `false` after
+    /// deserialization, until [`External::attach`] is called.
+    pub fn is_attached(&self) -> bool {
+        self.segment_fn.is_some()
+    }
+
+    /// The ascending byte offsets at which `text` should be cut, from the
+    /// word ranges returned by the segmenter, validated to be in-bounds,
+    /// non-overlapping and aligned on char boundaries.
+    fn cuts(&self, segment_fn: &SegmentFn, text: &str) -> Result<Vec<usize>> {
+        let mut cuts = vec![];
+        let mut previous = 0;
+        for (start, end) in segment_fn(text)? {
+            if start > end || start < previous || end > text.len() {
+                return Err(format!(
+                    "External segmenter `{}` returned an invalid word range {}..{} \
+                     for a text of {} bytes",
+                    self.segmenter,
+                    start,
+                    end,
+                    text.len()
+                )
+                .into());
+            }
+            if !text.is_char_boundary(start) || !text.is_char_boundary(end) {
+                return Err(format!(
+                    "External segmenter `{}` returned the range {}..{}, which does not \
+                     fall on char boundaries",
+                    self.segmenter, start, end
+                )
+                .into());
+            }
+            cuts.push(start);
+            cuts.push(end);
+            previous = end;
+        }
+        cuts.dedup();
+        Ok(cuts)
+    }
+}
+
+impl fmt::Debug for External {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("External")
+            .field("segmenter", &self.segmenter)
+            .field("version", &self.version)
+            .field("attached", &self.is_attached())
+            .finish()
+    }
+}
+
+impl PartialEq for External {
+    fn eq(&self, other: &Self) -> bool {
+        self.segmenter == other.segmenter
+            && self.version == other.version
+            && match (&self.segment_fn, &other.segment_fn) {
+                (Some(left), Some(right)) => Arc::ptr_eq(left, right),
+                (None, None) => true,
+                _ => false,
+            }
+    }
+}
+
+impl PreTokenizer for External {
+    fn pre_tokenize(&self, pretokenized: &mut PreTokenizedString) -> Result<()> {
+        let segment_fn = self.segment_fn.as_deref().ok_or_else(|| {
+            format!(
+                "The `{}` (version {}) external segmenter is not attached: this tokenizer \
+                 was deserialized, re-attach the segmentation callback with `External::attach`",
+                self.segmenter, self.version
+            )
+        })?;
+        pretokenized.split(|_, normalized| {
+            let text = normalized.get();
+            let mut words = vec![];
+            let mut start = 0;
+            for cut in self.cuts(segment_fn, text)?.into_iter().chain([text.len()]) {
+                if cut > start {
+                    words.push(
+                        normalized
+                            .slice(Range::Normalized(start..cut))
+                            .ok_or("External produced an invalid split")?,
+                    );
+                    start = cut;
+                }
+            }
+            Ok(words)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OffsetReferential, OffsetType};
+
+    fn jieba_like() -> External {
+        // A stand-in for a real CJK segmenter, hard-coding the words of the
+        // test sentence
+        External::new("jieba", "0.6", |text| {
+            Ok(["我", "爱", "北京"]
+                .iter()
+                .filter_map(|word| text.find(word).map(|start| (start, start + word.len())))
+                .collect())
+        })
+    }
+
+    #[test]
+    fn external_words_become_splits() {
+        let pretok = jieba_like();
+        let mut pretokenized: PreTokenizedString = "我爱北京天安门".into();
+        pretok.pre_tokenize(&mut pretokenized).unwrap();
+        // The words found by the segmenter become splits, and the uncovered
+        // tail is kept as a split of its own
+        assert_eq!(
+            pretokenized
+                .get_splits(OffsetReferential::Original, OffsetType::Byte)
+                .into_iter()
+                .map(|(s, o, _)| (s, o))
+                .collect::<Vec<_>>(),
+            vec![
+                ("我", (0, 3)),
+                ("爱", (3, 6)),
+                ("北京", (6, 12)),
+                ("天安门", (12, 21)),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_ranges_error_out() {
+        let pretok = External::new("broken", "1.0", |_| Ok(vec![(1, 2)]));
+        let mut pretokenized: PreTokenizedString = "我爱".into();
+        let error = pretok.pre_tokenize(&mut pretokenized).unwrap_err();
+        assert!(error.to_string().contains("char boundaries"));
+
+        let pretok = External::new("broken", "1.0", |_| Ok(vec![(3, 6), (0, 3)]));
+        let mut pretokenized: PreTokenizedString = "我爱".into();
+        let error = pretok.pre_tokenize(&mut pretokenized).unwrap_err();
+        assert!(error.to_string().contains("invalid word range"));
+    }
+
+    #[test]
+    fn serialization_records_the_segmenter() {
+        let pretok = jieba_like();
+        let json = serde_json::to_string(&pretok).unwrap();
+        assert_eq!(
+            json,
+            r#"{"type":"External","segmenter":"jieba","version":"0.6"}"#
+        );
+
+        // Deserializing yields a detached pre-tokenizer that fails with a
+        // clear error until the callback is re-attached
+        let detached: External = serde_json::from_str(&json).unwrap();
+        assert!(!detached.is_attached());
+        let mut pretokenized: PreTokenizedString = "我爱".into();
+        let error = pretok
+            .clone()
+            .pre_tokenize(&mut pretokenized)
+            .and_then(|()| detached.pre_tokenize(&mut pretokenized))
+            .unwrap_err();
+        assert!(error.to_string().contains("`jieba`"));
+        assert!(error.to_string().contains("not attached"));
+
+        let reattached = detached.attach(|_| Ok(vec![]));
+        assert!(reattached.is_attached());
+    }
+}
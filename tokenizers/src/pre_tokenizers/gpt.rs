@@ -0,0 +1,364 @@
+//! Hand-written scanners reproducing the split patterns of the GPT family of
+//! tokenizers (GPT-2, `cl100k_base`, `o200k_base`).
+//!
+//! The reference patterns rely on a negative lookahead (`\s+(?!\S)`) and thus
+//! need a backtracking regex engine. These pre-tokenizers implement the exact
+//! same split rules as single left-to-right scans over the input, which makes
+//! them both portable (no `onig` requirement) and much faster on long inputs.
+
+use unicode_categories::UnicodeCategories;
+
+use crate::tokenizer::pattern::Pattern;
+use crate::tokenizer::{PreTokenizedString, PreTokenizer, Result, SplitDelimiterBehavior};
+use crate::utils::macro_rules_attribute;
+
+/// Splits like the GPT-2 pattern:
+/// `'s|'t|'re|'ve|'m|'ll|'d| ?\p{L}+| ?\p{N}+| ?[^\s\p{L}\p{N}]+|\s+(?!\S)|\s+`
+///
+/// This is the splitting performed by [`super::byte_level::ByteLevel`], without
+/// the byte-level alphabet remapping, so it can be combined freely with other
+/// pre-tokenizers and models.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[macro_rules_attribute(impl_serde_type!)]
+pub struct Gpt2Pattern;
+
+impl PreTokenizer for Gpt2Pattern {
+    fn pre_tokenize(&self, pretokenized: &mut PreTokenizedString) -> Result<()> {
+        pretokenized.split(|_, normalized| {
+            normalized.split(Scanner(gpt2_next), SplitDelimiterBehavior::Isolated)
+        })
+    }
+}
+
+/// Splits like the `cl100k_base` (GPT-3.5/GPT-4) pattern: case-insensitive
+/// contractions, letters with one optional leading non-letter, digits in
+/// groups of at most 3, punctuation with trailing newlines, and the GPT-2
+/// whitespace rules.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[macro_rules_attribute(impl_serde_type!)]
+pub struct Cl100kPattern;
+
+impl PreTokenizer for Cl100kPattern {
+    fn pre_tokenize(&self, pretokenized: &mut PreTokenizedString) -> Result<()> {
+        pretokenized.split(|_, normalized| {
+            normalized.split(Scanner(cl100k_next), SplitDelimiterBehavior::Isolated)
+        })
+    }
+}
+
+/// Splits like the `o200k_base` (GPT-4o) pattern, which refines `cl100k_base`
+/// by segmenting letter runs on case changes (an uppercase prefix may be
+/// followed by lowercase letters, as in `Hello`, but `HELLOworld` splits)
+/// and by letting punctuation swallow trailing slashes and newlines.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[macro_rules_attribute(impl_serde_type!)]
+pub struct O200kPattern;
+
+impl PreTokenizer for O200kPattern {
+    fn pre_tokenize(&self, pretokenized: &mut PreTokenizedString) -> Result<()> {
+        pretokenized.split(|_, normalized| {
+            normalized.split(Scanner(o200k_next), SplitDelimiterBehavior::Isolated)
+        })
+    }
+}
+
+/// A [`Pattern`] slicing the string with a scanner function returning, for a
+/// token starting at a given char index, the char index right past its end.
+///
+/// The scanners below always make progress and jointly cover every char, so
+/// every produced slice is a match.
+struct Scanner(fn(&[char], usize) -> usize);
+
+impl Pattern for Scanner {
+    fn find_matches(&self, inside: &str) -> Result<Vec<(crate::Offsets, bool)>> {
+        if inside.is_empty() {
+            return Ok(vec![((0, 0), false)]);
+        }
+
+        let (byte_offsets, chars): (Vec<usize>, Vec<char>) = inside.char_indices().unzip();
+        let mut splits = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let end = (self.0)(&chars, i);
+            let byte_end = byte_offsets.get(end).copied().unwrap_or(inside.len());
+            splits.push(((byte_offsets[i], byte_end), true));
+            i = end;
+        }
+        Ok(splits)
+    }
+}
+
+fn is_letter(c: char) -> bool {
+    c.is_letter()
+}
+
+fn is_number(c: char) -> bool {
+    c.is_number()
+}
+
+/// Neither whitespace, letter nor number: `[^\s\p{L}\p{N}]`
+fn is_other(c: char) -> bool {
+    !c.is_whitespace() && !c.is_letter() && !c.is_number()
+}
+
+fn is_newline(c: char) -> bool {
+    c == '\r' || c == '\n'
+}
+
+/// Length of a `'s|'t|'re|'ve|'m|'ll|'d` contraction starting at `i`, or 0
+fn contraction_len(chars: &[char], i: usize, case_insensitive: bool) -> usize {
+    if chars.get(i) != Some(&'\'') {
+        return 0;
+    }
+    let eq = |j: usize, expected: char| {
+        chars.get(j).is_some_and(|&c| {
+            c == expected || (case_insensitive && c.to_ascii_lowercase() == expected)
+        })
+    };
+    if (eq(i + 1, 'r') || eq(i + 1, 'v')) && eq(i + 2, 'e') || eq(i + 1, 'l') && eq(i + 2, 'l') {
+        3
+    } else if eq(i + 1, 's') || eq(i + 1, 't') || eq(i + 1, 'm') || eq(i + 1, 'd') {
+        2
+    } else {
+        0
+    }
+}
+
+/// Extends the run of chars matching `pred` starting at `i`
+fn run(chars: &[char], mut i: usize, pred: fn(char) -> bool) -> usize {
+    while i < chars.len() && pred(chars[i]) {
+        i += 1;
+    }
+    i
+}
+
+/// End of the whitespace token starting at `i`, implementing the
+/// `\s+(?!\S)|\s+` tail of the patterns: a whitespace run followed by a
+/// non-whitespace char leaves its last char to the next token (usually a
+/// ` ?...`-prefixed one), unless it only contains that char.
+///
+/// When `newlines` is true, the `\s*[\r\n]+` alternative of the newer patterns
+/// is applied first: a run containing newlines stops right after the last one.
+fn whitespace_next(chars: &[char], i: usize, newlines: bool) -> usize {
+    let end = run(chars, i + 1, char::is_whitespace);
+    if newlines {
+        if let Some(last) = (i..end).rev().find(|&j| is_newline(chars[j])) {
+            return last + 1;
+        }
+    }
+    if end == chars.len() || end - i == 1 {
+        end
+    } else {
+        end - 1
+    }
+}
+
+/// `'s|'t|'re|'ve|'m|'ll|'d| ?\p{L}+| ?\p{N}+| ?[^\s\p{L}\p{N}]+|\s+(?!\S)|\s+`
+fn gpt2_next(chars: &[char], i: usize) -> usize {
+    let contraction = contraction_len(chars, i, false);
+    if contraction > 0 {
+        return i + contraction;
+    }
+    let start = if chars[i] == ' ' { i + 1 } else { i };
+    if let Some(&c) = chars.get(start) {
+        for pred in [is_letter, is_number, is_other] {
+            if pred(c) {
+                return run(chars, start + 1, pred);
+            }
+        }
+    }
+    whitespace_next(chars, i, false)
+}
+
+/// `(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}`
+/// `| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+(?!\S)|\s+`
+fn cl100k_next(chars: &[char], i: usize) -> usize {
+    let contraction = contraction_len(chars, i, true);
+    if contraction > 0 {
+        return i + contraction;
+    }
+    let c = chars[i];
+    // `[^\r\n\p{L}\p{N}]?\p{L}+`: the optional leading char covers spaces,
+    // punctuation, ... so that `"!word"` or `" word"` are single tokens
+    if is_letter(c) {
+        return run(chars, i + 1, is_letter);
+    }
+    if !is_newline(c) && !is_number(c) && chars.get(i + 1).is_some_and(|&next| is_letter(next)) {
+        return run(chars, i + 2, is_letter);
+    }
+    // `\p{N}{1,3}`
+    if is_number(c) {
+        return run(chars, i + 1, is_number).min(i + 3);
+    }
+    // ` ?[^\s\p{L}\p{N}]+[\r\n]*`
+    let start = if c == ' ' { i + 1 } else { i };
+    if chars.get(start).copied().is_some_and(is_other) {
+        let end = run(chars, start + 1, is_other);
+        return run(chars, end, is_newline);
+    }
+    whitespace_next(chars, i, true)
+}
+
+/// `[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]`: may start or continue a capitalized word
+fn is_upper_segment(c: char) -> bool {
+    c.is_letter_uppercase()
+        || c.is_letter_titlecase()
+        || c.is_letter_modifier()
+        || c.is_letter_other()
+        || c.is_mark()
+}
+
+/// `[\p{Ll}\p{Lm}\p{Lo}\p{M}]`: may continue a word after its capitalized part
+fn is_lower_segment(c: char) -> bool {
+    c.is_letter_lowercase() || c.is_letter_modifier() || c.is_letter_other() || c.is_mark()
+}
+
+/// `[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]*[\p{Ll}\p{Lm}\p{Lo}\p{M}]+` starting at
+/// `p`: the longest capitalized prefix still followed by a lowercase part,
+/// exactly as the backtracking of the reference greedy quantifiers resolves it
+fn o200k_capitalized_word(chars: &[char], p: usize) -> Option<usize> {
+    let uppers = run(chars, p, is_upper_segment);
+    let lower_start = (p..=uppers)
+        .rev()
+        .find(|&j| chars.get(j).copied().is_some_and(is_lower_segment))?;
+    Some(run(chars, lower_start + 1, is_lower_segment))
+}
+
+/// `[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]+[\p{Ll}\p{Lm}\p{Lo}\p{M}]*` starting at `p`
+fn o200k_uppercase_word(chars: &[char], p: usize) -> Option<usize> {
+    let uppers = run(chars, p, is_upper_segment);
+    (uppers > p).then(|| run(chars, uppers, is_lower_segment))
+}
+
+/// The `o200k_base` pattern: two case-aware word alternatives with an optional
+/// leading non-letter and an optional trailing contraction, then
+/// `\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n/]*|\s*[\r\n]+|\s+(?!\S)|\s+`
+fn o200k_next(chars: &[char], i: usize) -> usize {
+    let c = chars[i];
+    let prefix = !is_newline(c) && !is_letter(c) && !is_number(c);
+    for word in [o200k_capitalized_word, o200k_uppercase_word] {
+        // The optional `[^\r\n\p{L}\p{N}]` leading char is tried first
+        let starts = prefix.then_some(i + 1).into_iter().chain([i]);
+        for start in starts {
+            if let Some(end) = word(chars, start) {
+                return end + contraction_len(chars, end, true);
+            }
+        }
+    }
+    if is_number(c) {
+        return run(chars, i + 1, is_number).min(i + 3);
+    }
+    // ` ?[^\s\p{L}\p{N}]+[\r\n/]*`
+    let start = if c == ' ' { i + 1 } else { i };
+    if chars.get(start).copied().is_some_and(is_other) {
+        let end = run(chars, start + 1, is_other);
+        return run(chars, end, |c| is_newline(c) || c == '/');
+    }
+    whitespace_next(chars, i, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::SysRegex;
+    use crate::{OffsetReferential, OffsetType};
+
+    const GPT2_PATTERN: &str =
+        r"'s|'t|'re|'ve|'m|'ll|'d| ?\p{L}+| ?\p{N}+| ?[^\s\p{L}\p{N}]+|\s+(?!\S)|\s+";
+    const CL100K_PATTERN: &str = r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+(?!\S)|\s+";
+    const O200K_PATTERN: &str = r"[^\r\n\p{L}\p{N}]?[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]*[\p{Ll}\p{Lm}\p{Lo}\p{M}]+(?i:'s|'t|'re|'ve|'m|'ll|'d)?|[^\r\n\p{L}\p{N}]?[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]+[\p{Ll}\p{Lm}\p{Lo}\p{M}]*(?i:'s|'t|'re|'ve|'m|'ll|'d)?|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n/]*|\s*[\r\n]+|\s+(?!\S)|\s+";
+
+    const SAMPLES: &[&str] = &[
+        "Hello world",
+        "I'm sure you'll do it, AREN'T YOU?",
+        "there are 12 345 6789 apples",
+        "a  b   c    ",
+        " \t\r\n x",
+        "word!!!\n\nnext",
+        "self.fn_call(x);",
+        "café ÉTÉ Été HELLOworld ABc",
+        "https://example.com/path/to/file\n",
+        "    indented\n\tcode",
+        "'s 'S '' don't DON'T",
+        "...",
+        "",
+        " ",
+        "\n",
+    ];
+
+    fn assert_parity(scanner: fn(&[char], usize) -> usize, pattern: &str) {
+        let re = SysRegex::new(pattern).unwrap();
+        for sample in SAMPLES {
+            let expected = (&re)
+                .find_matches(sample)
+                .unwrap()
+                .into_iter()
+                .filter(|(_, is_match)| *is_match)
+                .collect::<Vec<_>>();
+            let actual = Scanner(scanner)
+                .find_matches(sample)
+                .unwrap()
+                .into_iter()
+                .filter(|(_, is_match)| *is_match)
+                .collect::<Vec<_>>();
+            assert_eq!(actual, expected, "on {sample:?}");
+        }
+    }
+
+    #[test]
+    fn gpt2_matches_reference_pattern() {
+        assert_parity(gpt2_next, GPT2_PATTERN);
+    }
+
+    #[test]
+    fn cl100k_matches_reference_pattern() {
+        assert_parity(cl100k_next, CL100K_PATTERN);
+    }
+
+    #[test]
+    fn o200k_matches_reference_pattern() {
+        assert_parity(o200k_next, O200K_PATTERN);
+    }
+
+    #[test]
+    fn gpt2_splits() {
+        let mut pretokenized = PreTokenizedString::from("I'm  sure, you're not 42!");
+        Gpt2Pattern.pre_tokenize(&mut pretokenized).unwrap();
+        assert_eq!(
+            pretokenized
+                .get_splits(OffsetReferential::Normalized, OffsetType::Byte)
+                .into_iter()
+                .map(|(s, _, _)| s)
+                .collect::<Vec<_>>(),
+            vec!["I", "'m", " ", " sure", ",", " you", "'re", " not", " 42", "!"]
+        );
+    }
+
+    #[test]
+    fn cl100k_splits() {
+        let mut pretokenized = PreTokenizedString::from("You'Ve counted 12345 times!\n\n");
+        Cl100kPattern.pre_tokenize(&mut pretokenized).unwrap();
+        assert_eq!(
+            pretokenized
+                .get_splits(OffsetReferential::Normalized, OffsetType::Byte)
+                .into_iter()
+                .map(|(s, _, _)| s)
+                .collect::<Vec<_>>(),
+            vec!["You", "'Ve", " counted", " ", "123", "45", " times", "!\n\n"]
+        );
+    }
+
+    #[test]
+    fn o200k_splits() {
+        let mut pretokenized = PreTokenizedString::from("McDuff said HE'd visit o200k");
+        O200kPattern.pre_tokenize(&mut pretokenized).unwrap();
+        assert_eq!(
+            pretokenized
+                .get_splits(OffsetReferential::Normalized, OffsetType::Byte)
+                .into_iter()
+                .map(|(s, _, _)| s)
+                .collect::<Vec<_>>(),
+            vec!["Mc", "Duff", " said", " HE'd", " visit", " o", "200", "k"]
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use crate::tokenizer::{Decoder, PreTokenizedString, PreTokenizer, Result, SplitDelimiterBehavior};
+use regex::Regex;
 use serde::{de, Deserialize, Deserializer, Serialize};
 
 /// Enum representing options for the metaspace prepending scheme.
@@ -21,6 +22,11 @@ pub struct Metaspace {
     replacement: char,
     pub prepend_scheme: PrependScheme,
     pub split: bool,
+    /// Collapse runs of whitespace into a single replacement char. The resulting
+    /// char still points back into the original whitespace run, so offsets stay
+    /// usable for chunked long-document encoding.
+    #[serde(default)]
+    pub collapse_whitespace: bool,
     #[serde(skip)]
     str_rep: String,
 }
@@ -49,6 +55,8 @@ impl<'de> Deserialize<'de> for Metaspace {
             #[serde(default = "default_prepend_scheme_value")]
             pub prepend_scheme: PrependScheme,
             pub split: Option<bool>,
+            #[serde(default)]
+            pub collapse_whitespace: bool,
             #[serde(rename = "str_rep")]
             _str_rep: Option<String>,
         }
@@ -62,11 +70,12 @@ impl<'de> Deserialize<'de> for Metaspace {
             }
             helper.prepend_scheme = PrependScheme::Never;
         }
-        let instance = Self::new(
+        let mut instance = Self::new(
             helper.replacement,
             helper.prepend_scheme,
             helper.split.unwrap_or(true),
         );
+        instance.collapse_whitespace = helper.collapse_whitespace;
         Ok(instance)
     }
 }
@@ -78,6 +87,7 @@ impl Metaspace {
             str_rep: replacement.to_string(),
             prepend_scheme,
             split,
+            collapse_whitespace: false,
         }
     }
 
@@ -105,19 +115,27 @@ impl Metaspace {
     pub fn set_prepend_scheme(&mut self, scheme: PrependScheme) {
         self.prepend_scheme = scheme;
     }
-}
 
-impl Default for Metaspace {
-    fn default() -> Self {
-        Self::new('▁', PrependScheme::Always, true)
-    }
-}
-
-impl PreTokenizer for Metaspace {
-    fn pre_tokenize(&self, pretokenized: &mut PreTokenizedString) -> Result<()> {
+    /// Pre-tokenize with a one-off prepend scheme, overriding the configured one.
+    ///
+    /// This is mainly useful when encoding continuation chunks of a long document:
+    /// a chunk that does not actually start the document can be encoded with
+    /// `PrependScheme::Never` while the first chunk keeps the configured scheme.
+    pub fn pre_tokenize_with_prepend_scheme(
+        &self,
+        pretokenized: &mut PreTokenizedString,
+        prepend_scheme: PrependScheme,
+    ) -> Result<()> {
+        lazy_static! {
+            static ref WHITESPACE_RUN: Regex = Regex::new(r" {2,}").unwrap();
+        }
         pretokenized.split(|_, mut normalized| {
+            if self.collapse_whitespace {
+                let re_ref: &Regex = &WHITESPACE_RUN;
+                normalized.replace(re_ref, " ")?;
+            }
             normalized.replace(' ', &self.str_rep)?;
-            match self.prepend_scheme {
+            match prepend_scheme {
                 PrependScheme::Always => {
                     if !normalized.get().starts_with(self.replacement) {
                         normalized.prepend(&self.str_rep);
@@ -141,6 +159,31 @@ impl PreTokenizer for Metaspace {
     }
 }
 
+impl Default for Metaspace {
+    fn default() -> Self {
+        Self::new('▁', PrependScheme::Always, true)
+    }
+}
+
+impl PreTokenizer for Metaspace {
+    fn pre_tokenize(&self, pretokenized: &mut PreTokenizedString) -> Result<()> {
+        self.pre_tokenize_with_prepend_scheme(pretokenized, self.prepend_scheme)
+    }
+
+    fn prefix_space(&self) -> Option<bool> {
+        Some(self.prepend_scheme != PrependScheme::Never)
+    }
+
+    fn set_prefix_space(&mut self, prefix_space: bool) {
+        self.prepend_scheme = match (prefix_space, self.prepend_scheme) {
+            // `First` already prepends, keep it
+            (true, PrependScheme::Never) => PrependScheme::Always,
+            (true, scheme) => scheme,
+            (false, _) => PrependScheme::Never,
+        };
+    }
+}
+
 impl Decoder for Metaspace {
     fn decode_chain(&self, tokens: Vec<String>) -> Result<Vec<String>> {
         Ok(tokens
@@ -164,6 +207,14 @@ impl Decoder for Metaspace {
             })
             .collect())
     }
+
+    fn prefix_space(&self) -> Option<bool> {
+        Some(self.prepend_scheme != PrependScheme::Never)
+    }
+
+    fn set_prefix_space(&mut self, prefix_space: bool) {
+        <Self as PreTokenizer>::set_prefix_space(self, prefix_space);
+    }
 }
 
 #[cfg(test)]
@@ -176,9 +227,16 @@ mod tests {
     #[test]
     fn serialization() {
         let metaspace = Metaspace::new('_', PrependScheme::Always, true);
+        let metaspace_s = r#"{"type":"Metaspace","replacement":"_","prepend_scheme":"always","split":true,"collapse_whitespace":false}"#;
+        assert_eq!(serde_json::to_string(&metaspace).unwrap(), metaspace_s);
+        assert_eq!(
+            serde_json::from_str::<Metaspace>(metaspace_s).unwrap(),
+            metaspace
+        );
+
+        // `collapse_whitespace` is optional when deserializing
         let metaspace_s =
             r#"{"type":"Metaspace","replacement":"_","prepend_scheme":"always","split":true}"#;
-        assert_eq!(serde_json::to_string(&metaspace).unwrap(), metaspace_s);
         assert_eq!(
             serde_json::from_str::<Metaspace>(metaspace_s).unwrap(),
             metaspace
@@ -347,6 +405,50 @@ mod tests {
             ]
         );
     }
+    #[test]
+    fn collapse_whitespace() {
+        let mut pretok = Metaspace::new('▁', PrependScheme::Always, true);
+        pretok.collapse_whitespace = true;
+        let mut pretokenized = PreTokenizedString::from("Hey   friend!");
+        pretok.pre_tokenize(&mut pretokenized).unwrap();
+        assert_eq!(
+            pretokenized
+                .get_splits(OffsetReferential::Normalized, OffsetType::Byte)
+                .into_iter()
+                .map(|(s, o, _)| (s, o))
+                .collect::<Vec<_>>(),
+            vec![("▁Hey", (0, 6)), ("▁friend!", (6, 16))]
+        );
+        // The single replacement char points back into the original whitespace run
+        assert_eq!(
+            pretokenized
+                .get_splits(OffsetReferential::Original, OffsetType::Byte)
+                .into_iter()
+                .map(|(s, o, _)| (s, o))
+                .collect::<Vec<_>>(),
+            vec![("▁Hey", (0, 3)), ("▁friend!", (5, 13))]
+        );
+    }
+
+    #[test]
+    fn prepend_scheme_override() {
+        let pretok = Metaspace::new('▁', PrependScheme::Always, true);
+
+        // Encoding a continuation chunk: do not prepend, whatever the config says
+        let mut pretokenized = PreTokenizedString::from("more text");
+        pretok
+            .pre_tokenize_with_prepend_scheme(&mut pretokenized, PrependScheme::Never)
+            .unwrap();
+        assert_eq!(
+            pretokenized
+                .get_splits(OffsetReferential::Normalized, OffsetType::Byte)
+                .into_iter()
+                .map(|(s, o, _)| (s, o))
+                .collect::<Vec<_>>(),
+            vec![("more", (0, 4)), ("▁text", (4, 11))]
+        );
+    }
+
     #[test]
     fn decode() {
         let decoder = Metaspace::new('▁', PrependScheme::Always, true);
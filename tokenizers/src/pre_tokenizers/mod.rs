@@ -2,8 +2,15 @@ pub mod bert;
 pub mod byte_level;
 pub mod delimiter;
 pub mod digits;
+pub mod edit_boundaries;
+#[cfg(feature = "external-segmenters")]
+pub mod external;
+pub mod gpt;
 pub mod metaspace;
+pub mod protected;
 pub mod punctuation;
+pub mod routing;
+pub mod sentence_split;
 pub mod sequence;
 pub mod split;
 pub mod unicode_scripts;
@@ -15,11 +22,18 @@ use crate::pre_tokenizers::bert::BertPreTokenizer;
 use crate::pre_tokenizers::byte_level::ByteLevel;
 use crate::pre_tokenizers::delimiter::CharDelimiterSplit;
 use crate::pre_tokenizers::digits::Digits;
+use crate::pre_tokenizers::edit_boundaries::EditBoundaries;
+#[cfg(feature = "external-segmenters")]
+use crate::pre_tokenizers::external::External;
+use crate::pre_tokenizers::gpt::{Cl100kPattern, Gpt2Pattern, O200kPattern};
 use crate::pre_tokenizers::metaspace::Metaspace;
+use crate::pre_tokenizers::protected::ProtectedPatterns;
 use crate::pre_tokenizers::punctuation::Punctuation;
+use crate::pre_tokenizers::routing::RoutingPreTokenizer;
+use crate::pre_tokenizers::sentence_split::SentenceSplit;
 use crate::pre_tokenizers::sequence::Sequence;
-use crate::pre_tokenizers::split::Split;
-use crate::pre_tokenizers::unicode_scripts::UnicodeScripts;
+use crate::pre_tokenizers::split::{MultiSplit, Split};
+use crate::pre_tokenizers::unicode_scripts::{ScriptSplit, UnicodeScripts};
 use crate::pre_tokenizers::whitespace::{Whitespace, WhitespaceSplit};
 use crate::{PreTokenizedString, PreTokenizer};
 
@@ -37,6 +51,17 @@ pub enum PreTokenizerWrapper {
     WhitespaceSplit(WhitespaceSplit),
     Digits(Digits),
     UnicodeScripts(UnicodeScripts),
+    ProtectedPatterns(ProtectedPatterns),
+    EditBoundaries(EditBoundaries),
+    ScriptSplit(ScriptSplit),
+    MultiSplit(MultiSplit),
+    RoutingPreTokenizer(RoutingPreTokenizer),
+    SentenceSplit(SentenceSplit),
+    #[cfg(feature = "external-segmenters")]
+    External(External),
+    Gpt2Pattern(Gpt2Pattern),
+    Cl100kPattern(Cl100kPattern),
+    O200kPattern(O200kPattern),
 }
 
 impl PreTokenizer for PreTokenizerWrapper {
@@ -53,6 +78,37 @@ impl PreTokenizer for PreTokenizerWrapper {
             Self::WhitespaceSplit(wspt) => wspt.pre_tokenize(normalized),
             Self::Digits(wspt) => wspt.pre_tokenize(normalized),
             Self::UnicodeScripts(us) => us.pre_tokenize(normalized),
+            Self::ProtectedPatterns(pp) => pp.pre_tokenize(normalized),
+            Self::EditBoundaries(eb) => eb.pre_tokenize(normalized),
+            Self::ScriptSplit(ss) => ss.pre_tokenize(normalized),
+            Self::MultiSplit(ms) => ms.pre_tokenize(normalized),
+            Self::RoutingPreTokenizer(routing) => routing.pre_tokenize(normalized),
+            Self::SentenceSplit(ss) => ss.pre_tokenize(normalized),
+            #[cfg(feature = "external-segmenters")]
+            Self::External(ext) => ext.pre_tokenize(normalized),
+            Self::Gpt2Pattern(gpt) => gpt.pre_tokenize(normalized),
+            Self::Cl100kPattern(gpt) => gpt.pre_tokenize(normalized),
+            Self::O200kPattern(gpt) => gpt.pre_tokenize(normalized),
+        }
+    }
+
+    fn prefix_space(&self) -> Option<bool> {
+        match self {
+            Self::ByteLevel(bpt) => bpt.prefix_space(),
+            Self::Metaspace(mspt) => mspt.prefix_space(),
+            Self::Sequence(tok) => tok.prefix_space(),
+            Self::RoutingPreTokenizer(routing) => routing.prefix_space(),
+            _ => None,
+        }
+    }
+
+    fn set_prefix_space(&mut self, prefix_space: bool) {
+        match self {
+            Self::ByteLevel(bpt) => bpt.set_prefix_space(prefix_space),
+            Self::Metaspace(mspt) => mspt.set_prefix_space(prefix_space),
+            Self::Sequence(tok) => tok.set_prefix_space(prefix_space),
+            Self::RoutingPreTokenizer(routing) => routing.set_prefix_space(prefix_space),
+            _ => {}
         }
     }
 }
@@ -82,6 +138,17 @@ impl<'de> Deserialize<'de> for PreTokenizerWrapper {
             WhitespaceSplit,
             Digits,
             UnicodeScripts,
+            ProtectedPatterns,
+            EditBoundaries,
+            ScriptSplit,
+            MultiSplit,
+            RoutingPreTokenizer,
+            SentenceSplit,
+            #[cfg(feature = "external-segmenters")]
+            External,
+            Gpt2Pattern,
+            Cl100kPattern,
+            O200kPattern,
         }
 
         #[derive(Deserialize)]
@@ -105,6 +172,17 @@ impl<'de> Deserialize<'de> for PreTokenizerWrapper {
             WhitespaceSplit(WhitespaceSplit),
             Digits(Digits),
             UnicodeScripts(UnicodeScripts),
+            ProtectedPatterns(ProtectedPatterns),
+            EditBoundaries(EditBoundaries),
+            ScriptSplit(ScriptSplit),
+            MultiSplit(MultiSplit),
+            RoutingPreTokenizer(RoutingPreTokenizer),
+            SentenceSplit(SentenceSplit),
+            #[cfg(feature = "external-segmenters")]
+            External(External),
+            Gpt2Pattern(Gpt2Pattern),
+            Cl100kPattern(Cl100kPattern),
+            O200kPattern(O200kPattern),
         }
 
         let helper = PreTokenizerHelper::deserialize(deserializer)?;
@@ -152,6 +230,37 @@ impl<'de> Deserialize<'de> for PreTokenizerWrapper {
                     EnumType::UnicodeScripts => PreTokenizerWrapper::UnicodeScripts(
                         serde_json::from_value(values).map_err(serde::de::Error::custom)?,
                     ),
+                    EnumType::ProtectedPatterns => PreTokenizerWrapper::ProtectedPatterns(
+                        serde_json::from_value(values).map_err(serde::de::Error::custom)?,
+                    ),
+                    EnumType::EditBoundaries => PreTokenizerWrapper::EditBoundaries(
+                        serde_json::from_value(values).map_err(serde::de::Error::custom)?,
+                    ),
+                    EnumType::ScriptSplit => PreTokenizerWrapper::ScriptSplit(
+                        serde_json::from_value(values).map_err(serde::de::Error::custom)?,
+                    ),
+                    EnumType::MultiSplit => PreTokenizerWrapper::MultiSplit(
+                        serde_json::from_value(values).map_err(serde::de::Error::custom)?,
+                    ),
+                    EnumType::RoutingPreTokenizer => PreTokenizerWrapper::RoutingPreTokenizer(
+                        serde_json::from_value(values).map_err(serde::de::Error::custom)?,
+                    ),
+                    EnumType::SentenceSplit => PreTokenizerWrapper::SentenceSplit(
+                        serde_json::from_value(values).map_err(serde::de::Error::custom)?,
+                    ),
+                    #[cfg(feature = "external-segmenters")]
+                    EnumType::External => PreTokenizerWrapper::External(
+                        serde_json::from_value(values).map_err(serde::de::Error::custom)?,
+                    ),
+                    EnumType::Gpt2Pattern => PreTokenizerWrapper::Gpt2Pattern(
+                        serde_json::from_value(values).map_err(serde::de::Error::custom)?,
+                    ),
+                    EnumType::Cl100kPattern => PreTokenizerWrapper::Cl100kPattern(
+                        serde_json::from_value(values).map_err(serde::de::Error::custom)?,
+                    ),
+                    EnumType::O200kPattern => PreTokenizerWrapper::O200kPattern(
+                        serde_json::from_value(values).map_err(serde::de::Error::custom)?,
+                    ),
                 }
             }
 
@@ -187,6 +296,35 @@ impl<'de> Deserialize<'de> for PreTokenizerWrapper {
                     PreTokenizerUntagged::UnicodeScripts(unicode_scripts) => {
                         PreTokenizerWrapper::UnicodeScripts(unicode_scripts)
                     }
+                    PreTokenizerUntagged::ProtectedPatterns(protected) => {
+                        PreTokenizerWrapper::ProtectedPatterns(protected)
+                    }
+                    PreTokenizerUntagged::EditBoundaries(edit_boundaries) => {
+                        PreTokenizerWrapper::EditBoundaries(edit_boundaries)
+                    }
+                    PreTokenizerUntagged::ScriptSplit(script_split) => {
+                        PreTokenizerWrapper::ScriptSplit(script_split)
+                    }
+                    PreTokenizerUntagged::MultiSplit(multi_split) => {
+                        PreTokenizerWrapper::MultiSplit(multi_split)
+                    }
+                    PreTokenizerUntagged::RoutingPreTokenizer(routing) => {
+                        PreTokenizerWrapper::RoutingPreTokenizer(routing)
+                    }
+                    PreTokenizerUntagged::SentenceSplit(sentence_split) => {
+                        PreTokenizerWrapper::SentenceSplit(sentence_split)
+                    }
+                    #[cfg(feature = "external-segmenters")]
+                    PreTokenizerUntagged::External(external) => {
+                        PreTokenizerWrapper::External(external)
+                    }
+                    PreTokenizerUntagged::Gpt2Pattern(gpt) => PreTokenizerWrapper::Gpt2Pattern(gpt),
+                    PreTokenizerUntagged::Cl100kPattern(gpt) => {
+                        PreTokenizerWrapper::Cl100kPattern(gpt)
+                    }
+                    PreTokenizerUntagged::O200kPattern(gpt) => {
+                        PreTokenizerWrapper::O200kPattern(gpt)
+                    }
                 }
             }
         })
@@ -204,6 +342,21 @@ impl_enum_from!(Metaspace, PreTokenizerWrapper, Metaspace);
 impl_enum_from!(WhitespaceSplit, PreTokenizerWrapper, WhitespaceSplit);
 impl_enum_from!(Digits, PreTokenizerWrapper, Digits);
 impl_enum_from!(UnicodeScripts, PreTokenizerWrapper, UnicodeScripts);
+impl_enum_from!(ProtectedPatterns, PreTokenizerWrapper, ProtectedPatterns);
+impl_enum_from!(EditBoundaries, PreTokenizerWrapper, EditBoundaries);
+impl_enum_from!(ScriptSplit, PreTokenizerWrapper, ScriptSplit);
+impl_enum_from!(MultiSplit, PreTokenizerWrapper, MultiSplit);
+impl_enum_from!(SentenceSplit, PreTokenizerWrapper, SentenceSplit);
+#[cfg(feature = "external-segmenters")]
+impl_enum_from!(External, PreTokenizerWrapper, External);
+impl_enum_from!(Gpt2Pattern, PreTokenizerWrapper, Gpt2Pattern);
+impl_enum_from!(Cl100kPattern, PreTokenizerWrapper, Cl100kPattern);
+impl_enum_from!(O200kPattern, PreTokenizerWrapper, O200kPattern);
+impl_enum_from!(
+    RoutingPreTokenizer,
+    PreTokenizerWrapper,
+    RoutingPreTokenizer
+);
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,179 @@
+use crate::pre_tokenizers::split::{SplitPattern, SplitRegex};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::normalizer::Range;
+use crate::tokenizer::{PreTokenizedString, PreTokenizer, Result, Split as PreTokenizedSplit};
+
+/// Marks every span matching one of the given patterns (literal strings or
+/// regexes) as atomic: the span is isolated as its own split, and subsequent
+/// pre-tokenizers leave it untouched. This is typically used as the first
+/// member of a `pre_tokenizers::Sequence`, to protect URLs, emails or chemical
+/// formulas from being split, while keeping correct offsets.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type")]
+pub struct ProtectedPatterns {
+    patterns: Vec<SplitPattern>,
+    #[serde(skip)]
+    regexes: Vec<SplitRegex>,
+}
+
+impl<'de> Deserialize<'de> for ProtectedPatterns {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        enum Type {
+            ProtectedPatterns,
+        }
+
+        #[derive(Deserialize)]
+        pub struct ProtectedPatternsHelper {
+            #[serde(rename = "type")]
+            _type: Type,
+            patterns: Vec<SplitPattern>,
+        }
+
+        let helper = ProtectedPatternsHelper::deserialize(deserializer)?;
+        Self::new(helper.patterns).map_err(serde::de::Error::custom)
+    }
+}
+
+impl Clone for ProtectedPatterns {
+    fn clone(&self) -> Self {
+        Self::new(self.patterns.clone()).unwrap()
+    }
+}
+
+impl PartialEq for ProtectedPatterns {
+    fn eq(&self, other: &Self) -> bool {
+        self.patterns == other.patterns
+    }
+}
+
+impl ProtectedPatterns {
+    pub fn new<I: Into<SplitPattern>>(patterns: Vec<I>) -> Result<Self> {
+        let patterns: Vec<SplitPattern> = patterns.into_iter().map(|p| p.into()).collect();
+        let regexes = patterns
+            .iter()
+            .map(SplitRegex::compile)
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(Self { patterns, regexes })
+    }
+}
+
+impl PreTokenizer for ProtectedPatterns {
+    fn pre_tokenize(&self, pretokenized: &mut PreTokenizedString) -> Result<()> {
+        // Each pattern is applied in turn: the spans it matches become atomic
+        // splits, which the following patterns (and any subsequent
+        // pre-tokenizer) will skip
+        for regex in &self.regexes {
+            pretokenized.split(|_, normalized| {
+                regex
+                    .find_matches(normalized.get())?
+                    .into_iter()
+                    .map(|((start, end), is_match)| {
+                        let slice = normalized
+                            .slice(Range::Normalized(start..end))
+                            .ok_or("Pattern produced an invalid split")?;
+                        Ok(if is_match {
+                            PreTokenizedSplit::atomic(slice)
+                        } else {
+                            slice.into()
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()
+            })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pre_tokenizers::whitespace::Whitespace;
+    use crate::{OffsetReferential, OffsetType};
+
+    fn splits(pretokenized: &PreTokenizedString) -> Vec<(&str, (usize, usize))> {
+        pretokenized
+            .get_splits(OffsetReferential::Original, OffsetType::Byte)
+            .into_iter()
+            .map(|(s, o, _)| (s, o))
+            .collect()
+    }
+
+    #[test]
+    fn protected_spans_are_not_split() {
+        let pretok = crate::pre_tokenizers::sequence::Sequence::new(vec![
+            ProtectedPatterns::new(vec![SplitPattern::Regex(r"\S+@\S+\.\w+".into())])
+                .unwrap()
+                .into(),
+            Whitespace {}.into(),
+        ]);
+
+        let mut pretokenized = PreTokenizedString::from("Contact me at who@example.com today");
+        pretok.pre_tokenize(&mut pretokenized).unwrap();
+        assert_eq!(
+            splits(&pretokenized),
+            vec![
+                ("Contact", (0, 7)),
+                ("me", (8, 10)),
+                ("at", (11, 13)),
+                ("who@example.com", (14, 29)),
+                ("today", (30, 35)),
+            ]
+        );
+    }
+
+    #[test]
+    fn literal_and_regex_patterns() {
+        let pretok = ProtectedPatterns::new(vec![
+            SplitPattern::String("H2O".into()),
+            SplitPattern::Regex(r"https?://\S+".into()),
+        ])
+        .unwrap();
+
+        let mut pretokenized = PreTokenizedString::from("H2O is on https://example.com now");
+        pretok.pre_tokenize(&mut pretokenized).unwrap();
+        assert_eq!(
+            splits(&pretokenized),
+            vec![
+                ("H2O", (0, 3)),
+                (" is on ", (3, 10)),
+                ("https://example.com", (10, 29)),
+                (" now", (29, 33)),
+            ]
+        );
+
+        // The protected spans are left untouched by a subsequent pre-tokenizer
+        Whitespace {}.pre_tokenize(&mut pretokenized).unwrap();
+        assert_eq!(
+            splits(&pretokenized),
+            vec![
+                ("H2O", (0, 3)),
+                ("is", (4, 6)),
+                ("on", (7, 9)),
+                ("https://example.com", (10, 29)),
+                ("now", (30, 33)),
+            ]
+        );
+    }
+
+    #[test]
+    fn serialization() {
+        let pretok = ProtectedPatterns::new(vec![
+            SplitPattern::String("H2O".into()),
+            SplitPattern::Regex(r"\s+".into()),
+        ])
+        .unwrap();
+        let pretok_s =
+            r#"{"type":"ProtectedPatterns","patterns":[{"String":"H2O"},{"Regex":"\\s+"}]}"#;
+        assert_eq!(serde_json::to_string(&pretok).unwrap(), pretok_s);
+        assert_eq!(
+            serde_json::from_str::<ProtectedPatterns>(pretok_s).unwrap(),
+            pretok
+        );
+    }
+}
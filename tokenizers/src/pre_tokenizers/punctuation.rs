@@ -4,15 +4,40 @@ use crate::tokenizer::{PreTokenizedString, PreTokenizer, Result, SplitDelimiterB
 use crate::utils::macro_rules_attribute;
 use unicode_categories::UnicodeCategories;
 
-fn is_punc(x: char) -> bool {
-    char::is_ascii_punctuation(&x) || x.is_punctuation()
+/// Whether the character belongs to the Unicode general category with the
+/// given two-letter code ("Pd", "Po", "Sm", ...). Unknown codes match no
+/// character.
+fn char_in_category(x: char, category: &str) -> bool {
+    match category {
+        "Pc" => x.is_punctuation_connector(),
+        "Pd" => x.is_punctuation_dash(),
+        "Pe" => x.is_punctuation_close(),
+        "Pf" => x.is_punctuation_final_quote(),
+        "Pi" => x.is_punctuation_initial_quote(),
+        "Po" => x.is_punctuation_other(),
+        "Ps" => x.is_punctuation_open(),
+        "Sc" => x.is_symbol_currency(),
+        "Sk" => x.is_symbol_modifier(),
+        "Sm" => x.is_symbol_math(),
+        "So" => x.is_symbol_other(),
+        _ => false,
+    }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 #[macro_rules_attribute(impl_serde_type!)]
 pub struct Punctuation {
     #[serde(default = "default_split")]
     behavior: SplitDelimiterBehavior,
+    /// Limit punctuation to these Unicode general categories ("Pd", "Po",
+    /// "Sm", ...), instead of the default definition covering every
+    /// punctuation category plus ASCII punctuation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    categories: Option<Vec<String>>,
+    /// Characters never treated as punctuation, e.g. to keep the apostrophe
+    /// of French elisions ("l'été") inside the word
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    exceptions: Vec<char>,
 }
 
 fn default_split() -> SplitDelimiterBehavior {
@@ -21,7 +46,38 @@ fn default_split() -> SplitDelimiterBehavior {
 
 impl Punctuation {
     pub fn new(behavior: SplitDelimiterBehavior) -> Self {
-        Self { behavior }
+        Self {
+            behavior,
+            categories: None,
+            exceptions: vec![],
+        }
+    }
+
+    /// Only count the given Unicode general categories as punctuation, by
+    /// their two-letter code ("Pd", "Ps", "Pe", "Pc", "Po", "Sm", ...)
+    #[must_use]
+    pub fn categories(mut self, categories: Vec<String>) -> Self {
+        self.categories = Some(categories);
+        self
+    }
+
+    /// Never treat the given characters as punctuation
+    #[must_use]
+    pub fn exceptions(mut self, exceptions: Vec<char>) -> Self {
+        self.exceptions = exceptions;
+        self
+    }
+
+    fn is_punc(&self, x: char) -> bool {
+        if self.exceptions.contains(&x) {
+            return false;
+        }
+        match &self.categories {
+            Some(categories) => categories
+                .iter()
+                .any(|category| char_in_category(x, category)),
+            None => char::is_ascii_punctuation(&x) || x.is_punctuation(),
+        }
     }
 }
 
@@ -33,7 +89,7 @@ impl Default for Punctuation {
 
 impl PreTokenizer for Punctuation {
     fn pre_tokenize(&self, pretokenized: &mut PreTokenizedString) -> Result<()> {
-        pretokenized.split(|_, s| s.split(is_punc, self.behavior))
+        pretokenized.split(|_, s| s.split(|x| self.is_punc(x), self.behavior))
     }
 }
 
@@ -42,6 +98,16 @@ mod tests {
     use super::*;
     use crate::{OffsetReferential, OffsetType};
 
+    fn splits(pretok: &Punctuation, s: &str) -> Vec<String> {
+        let mut pretokenized: PreTokenizedString = s.into();
+        pretok.pre_tokenize(&mut pretokenized).unwrap();
+        pretokenized
+            .get_splits(OffsetReferential::Original, OffsetType::Byte)
+            .into_iter()
+            .map(|(s, _, _)| s.to_string())
+            .collect()
+    }
+
     #[test]
     fn punctuation_basic() {
         let pretok = Punctuation::default();
@@ -64,6 +130,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn categories_and_exceptions() {
+        // The default definition splits French elisions poorly
+        let pretok = Punctuation::default();
+        assert_eq!(splits(&pretok, "l'heure!"), vec!["l", "'", "heure", "!"]);
+
+        // Keeping the apostrophe inside words fixes them
+        let pretok = Punctuation::default().exceptions(vec!['\'']);
+        assert_eq!(splits(&pretok, "l'heure!"), vec!["l'heure", "!"]);
+
+        // Only the listed categories count as punctuation
+        let pretok = Punctuation::default().categories(vec!["Pd".into(), "Sm".into()]);
+        assert_eq!(
+            splits(&pretok, "well-known, 1+2"),
+            vec!["well", "-", "known, 1", "+", "2"]
+        );
+    }
+
     #[test]
     fn deserialization() {
         let punctuation: Punctuation = serde_json::from_str(r#"{"type": "Punctuation"}"#).unwrap();
@@ -72,6 +156,24 @@ mod tests {
             punctuation,
             Punctuation::new(SplitDelimiterBehavior::Isolated)
         );
+
+        // The new fields are only serialized when set
+        assert_eq!(
+            serde_json::to_string(&Punctuation::default()).unwrap(),
+            r#"{"type":"Punctuation","behavior":"Isolated"}"#
+        );
+        let punctuation = Punctuation::default()
+            .categories(vec!["Po".into()])
+            .exceptions(vec!['\'']);
+        let serialized = serde_json::to_string(&punctuation).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"type":"Punctuation","behavior":"Isolated","categories":["Po"],"exceptions":["'"]}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<Punctuation>(&serialized).unwrap(),
+            punctuation
+        );
     }
 
     #[test]
@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::normalizers::NormalizerWrapper;
+use crate::pre_tokenizers::unicode_scripts::{get_script, Script};
+use crate::pre_tokenizers::PreTokenizerWrapper;
+use crate::tokenizer::{
+    Normalizer, OffsetReferential, OffsetType, PreTokenizedString, PreTokenizer, Result,
+};
+
+/// A per-document classifier returning the name of the route to take, or
+/// `None` to fall back to the script-ratio heuristic
+type Classifier = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+/// One sub-pipeline of a [`RoutingPreTokenizer`]: an optional normalizer and
+/// an optional pre-tokenizer, applied when the route is selected
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Route {
+    /// The name of the route, as returned by a classifier callback
+    pub name: String,
+    /// The scripts this route handles, used by the script-ratio heuristic
+    #[serde(default)]
+    pub scripts: Vec<Script>,
+    /// The normalizer applied when this route is selected
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normalizer: Option<NormalizerWrapper>,
+    /// The pre-tokenizer applied when this route is selected
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_tokenizer: Option<PreTokenizerWrapper>,
+}
+
+impl Route {
+    pub fn new(name: impl Into<String>, scripts: Vec<Script>) -> Self {
+        Self {
+            name: name.into(),
+            scripts,
+            normalizer: None,
+            pre_tokenizer: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_normalizer(mut self, normalizer: impl Into<NormalizerWrapper>) -> Self {
+        self.normalizer = Some(normalizer.into());
+        self
+    }
+
+    #[must_use]
+    pub fn with_pre_tokenizer(mut self, pre_tokenizer: impl Into<PreTokenizerWrapper>) -> Self {
+        self.pre_tokenizer = Some(pre_tokenizer.into());
+        self
+    }
+}
+
+/// Selects one of several sub-pipelines per document, so a single tokenizer
+/// can encode mixed-language corpora with language-appropriate normalization.
+///
+/// The route is picked by a user-provided classifier callback when one is
+/// set, and by a script-ratio heuristic otherwise: the route whose `scripts`
+/// cover the most characters of the document wins, and the first route is
+/// the default when no route matches. The classifier is a runtime setting:
+/// it is not serialized in the tokenizer files.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub struct RoutingPreTokenizer {
+    pub routes: Vec<Route>,
+    #[serde(skip)]
+    classifier: Option<Classifier>,
+}
+
+impl std::fmt::Debug for RoutingPreTokenizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RoutingPreTokenizer")
+            .field("routes", &self.routes)
+            .field(
+                "classifier",
+                &self.classifier.as_ref().map(|_| "<callback>"),
+            )
+            .finish()
+    }
+}
+
+impl PartialEq for RoutingPreTokenizer {
+    fn eq(&self, other: &Self) -> bool {
+        // The normalizers have no PartialEq, compare their configuration
+        self.routes.len() == other.routes.len()
+            && self.routes.iter().zip(&other.routes).all(|(a, b)| {
+                a.name == b.name
+                    && a.scripts == b.scripts
+                    && a.pre_tokenizer == b.pre_tokenizer
+                    && serde_json::to_value(&a.normalizer).ok()
+                        == serde_json::to_value(&b.normalizer).ok()
+            })
+    }
+}
+
+impl RoutingPreTokenizer {
+    pub fn new(routes: Vec<Route>) -> Self {
+        Self {
+            routes,
+            classifier: None,
+        }
+    }
+
+    /// Set a classifier callback, called with the full document and returning
+    /// the name of the route to take, or `None` to fall back to the
+    /// script-ratio heuristic. Not serialized: a tokenizer loaded from a file
+    /// uses the heuristic until a classifier is set again
+    #[must_use]
+    pub fn with_classifier<F>(mut self, classifier: F) -> Self
+    where
+        F: Fn(&str) -> Option<String> + Send + Sync + 'static,
+    {
+        self.classifier = Some(Arc::new(classifier));
+        self
+    }
+
+    /// The route whose scripts cover the most characters of the document,
+    /// defaulting to the first route when no route scores
+    fn script_route(&self, pretokenized: &PreTokenizedString) -> &Route {
+        let mut counts: HashMap<Script, usize> = HashMap::new();
+        for (split, _, _) in
+            pretokenized.get_splits(OffsetReferential::Normalized, OffsetType::None)
+        {
+            for c in split.chars() {
+                let script = get_script(c);
+                if !matches!(script, Script::Common | Script::Inherited | Script::Any) {
+                    *counts.entry(script).or_default() += 1;
+                }
+            }
+        }
+        self.routes
+            .iter()
+            .map(|route| {
+                let score: usize = route
+                    .scripts
+                    .iter()
+                    .map(|script| counts.get(script).copied().unwrap_or(0))
+                    .sum();
+                (route, score)
+            })
+            .filter(|(_, score)| *score > 0)
+            .max_by_key(|(_, score)| *score)
+            .map(|(route, _)| route)
+            .unwrap_or(&self.routes[0])
+    }
+
+    fn select_route(&self, pretokenized: &PreTokenizedString) -> Result<&Route> {
+        if let Some(classifier) = &self.classifier {
+            let document: String = pretokenized
+                .get_splits(OffsetReferential::Normalized, OffsetType::None)
+                .into_iter()
+                .map(|(split, _, _)| split)
+                .collect();
+            if let Some(name) = classifier(&document) {
+                return self
+                    .routes
+                    .iter()
+                    .find(|route| route.name == name)
+                    .ok_or_else(|| {
+                        format!("The classifier selected an unknown route `{name}`").into()
+                    });
+            }
+        }
+        Ok(self.script_route(pretokenized))
+    }
+}
+
+impl PreTokenizer for RoutingPreTokenizer {
+    fn pre_tokenize(&self, pretokenized: &mut PreTokenizedString) -> Result<()> {
+        if self.routes.is_empty() {
+            return Ok(());
+        }
+        let route = self.select_route(pretokenized)?;
+        if let Some(normalizer) = &route.normalizer {
+            pretokenized.normalize(|normalized| normalizer.normalize(normalized))?;
+        }
+        if let Some(pre_tokenizer) = &route.pre_tokenizer {
+            pre_tokenizer.pre_tokenize(pretokenized)?;
+        }
+        Ok(())
+    }
+
+    fn prefix_space(&self) -> Option<bool> {
+        self.routes
+            .iter()
+            .filter_map(|route| route.pre_tokenizer.as_ref())
+            .find_map(|pre_tokenizer| pre_tokenizer.prefix_space())
+    }
+
+    fn set_prefix_space(&mut self, prefix_space: bool) {
+        for route in &mut self.routes {
+            if let Some(pre_tokenizer) = route.pre_tokenizer.as_mut() {
+                pre_tokenizer.set_prefix_space(prefix_space);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalizers::Lowercase;
+    use crate::pre_tokenizers::unicode_scripts::UnicodeScripts;
+    use crate::pre_tokenizers::whitespace::Whitespace;
+
+    fn routes() -> Vec<Route> {
+        vec![
+            Route::new("latin", vec![Script::Latin])
+                .with_normalizer(Lowercase)
+                .with_pre_tokenizer(Whitespace),
+            Route::new("cjk", vec![Script::Han, Script::Hiragana, Script::Katakana])
+                .with_pre_tokenizer(UnicodeScripts::default()),
+        ]
+    }
+
+    fn tokens(pretok: &RoutingPreTokenizer, input: &str) -> Vec<String> {
+        let mut pretokenized = PreTokenizedString::from(input);
+        pretok.pre_tokenize(&mut pretokenized).unwrap();
+        pretokenized
+            .get_splits(OffsetReferential::Original, OffsetType::Byte)
+            .into_iter()
+            .map(|(s, _, _)| s.to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn routes_by_dominant_script() {
+        let pretok = RoutingPreTokenizer::new(routes());
+
+        // Mostly latin: lowercased and split on whitespace
+        assert_eq!(tokens(&pretok, "Hello 世界"), vec!["hello", "世界"]);
+        // Mostly Han: the latin normalizer is not applied
+        assert_eq!(tokens(&pretok, "你好的世界 Hi"), vec!["你好的世界 ", "Hi"]);
+        // No script matches any route: the first route is the default
+        assert_eq!(tokens(&pretok, "Ωμέγα Α"), vec!["ωμέγα", "α"]);
+    }
+
+    #[test]
+    fn classifier_overrides_heuristic() {
+        let pretok =
+            RoutingPreTokenizer::new(routes()).with_classifier(|_| Some("cjk".to_string()));
+        assert_eq!(tokens(&pretok, "Hello You"), vec!["Hello You"]);
+
+        // A `None` answer falls back to the heuristic
+        let pretok = RoutingPreTokenizer::new(routes()).with_classifier(|_| None);
+        assert_eq!(tokens(&pretok, "Hello You"), vec!["hello", "you"]);
+
+        // An unknown route name is an error
+        let pretok =
+            RoutingPreTokenizer::new(routes()).with_classifier(|_| Some("klingon".to_string()));
+        let mut pretokenized = PreTokenizedString::from("Hello");
+        assert_eq!(
+            pretok
+                .pre_tokenize(&mut pretokenized)
+                .unwrap_err()
+                .to_string(),
+            "The classifier selected an unknown route `klingon`"
+        );
+    }
+
+    #[test]
+    fn routing_serde() {
+        let pretok = RoutingPreTokenizer::new(routes()).with_classifier(|_| None);
+        let serialized = serde_json::to_string(&pretok).unwrap();
+        let deserialized: RoutingPreTokenizer = serde_json::from_str(&serialized).unwrap();
+        // The routes survive the round-trip, the classifier does not
+        assert_eq!(deserialized, pretok);
+        assert_eq!(tokens(&deserialized, "Hello You"), vec!["hello", "you"]);
+    }
+}
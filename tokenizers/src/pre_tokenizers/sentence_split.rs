@@ -0,0 +1,240 @@
+use serde::{Deserialize, Serialize};
+
+use crate::normalizer::Range;
+use crate::tokenizer::{PreTokenizedString, PreTokenizer, Result};
+use crate::utils::macro_rules_attribute;
+
+/// Splits the input on sentence boundaries, detected with rule-based,
+/// SRX-style heuristics: a run of sentence terminators closes a sentence when
+/// it is followed by whitespace and a sentence-opening character, unless the
+/// word it ends is a known abbreviation or a single initial ("J. Smith").
+/// Each sentence becomes its own split, with the inter-sentence whitespace
+/// attached to the start of the following sentence, so `encode_chunks` and
+/// downstream segmenters can align chunks to sentences within the pipeline.
+///
+/// The abbreviation list is configurable per language: see
+/// [`SentenceSplit::for_language`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[macro_rules_attribute(impl_serde_type!)]
+pub struct SentenceSplit {
+    /// The characters that may end a sentence
+    #[serde(default = "default_terminators")]
+    terminators: Vec<char>,
+    /// Words ending with a terminator that do not close a sentence
+    #[serde(default = "default_abbreviations")]
+    abbreviations: Vec<String>,
+}
+
+fn default_terminators() -> Vec<char> {
+    vec!['.', '!', '?', '…']
+}
+
+fn default_abbreviations() -> Vec<String> {
+    [
+        "Mr.", "Mrs.", "Ms.", "Dr.", "Prof.", "St.", "vs.", "etc.", "e.g.", "i.e.", "cf.", "al.",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()
+}
+
+impl Default for SentenceSplit {
+    fn default() -> Self {
+        Self {
+            terminators: default_terminators(),
+            abbreviations: default_abbreviations(),
+        }
+    }
+}
+
+impl SentenceSplit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A splitter with the abbreviation list of the given language
+    /// (`"en"` or `"fr"`), or `None` for an unknown language
+    pub fn for_language(language: &str) -> Option<Self> {
+        let abbreviations: &[&str] = match language {
+            "en" => {
+                return Some(Self::default());
+            }
+            "fr" => &[
+                "M.", "MM.", "Mme.", "Mlle.", "Dr.", "Pr.", "St.", "cf.", "etc.", "ex.", "p.",
+                "art.", "chap.", "vol.",
+            ],
+            _ => return None,
+        };
+        Some(Self::default().abbreviations(abbreviations.iter().map(|s| s.to_string()).collect()))
+    }
+
+    /// Use the given characters as sentence terminators
+    #[must_use]
+    pub fn terminators(mut self, terminators: Vec<char>) -> Self {
+        self.terminators = terminators;
+        self
+    }
+
+    /// Use the given words as abbreviations, written with their final
+    /// terminator ("Dr.", "etc.")
+    #[must_use]
+    pub fn abbreviations(mut self, abbreviations: Vec<String>) -> Self {
+        self.abbreviations = abbreviations;
+        self
+    }
+
+    /// Whether a sentence can start with this character: uppercase letters,
+    /// digits, and opening quotes, parentheses or inverted marks
+    fn opens_sentence(c: char) -> bool {
+        c.is_uppercase()
+            || c.is_numeric()
+            || matches!(
+                c,
+                '"' | '\'' | '“' | '‘' | '«' | '(' | '[' | '¿' | '¡' | '—' | '-'
+            )
+    }
+
+    /// Whether the word ending at `period_end` (exclusive, just after its
+    /// final period) is an abbreviation or a single initial
+    fn is_abbreviation(&self, text: &str, period_end: usize) -> bool {
+        let word_start = text[..period_end]
+            .rfind(char::is_whitespace)
+            .map_or(0, |pos| pos + 1);
+        let word = &text[word_start..period_end];
+        // A single initial, as in "J. Smith"
+        let mut chars = word.chars();
+        if let (Some(initial), Some('.'), None) = (chars.next(), chars.next(), chars.next()) {
+            if initial.is_alphabetic() && initial.is_uppercase() {
+                return true;
+            }
+        }
+        self.abbreviations.iter().any(|abbr| abbr == word)
+    }
+
+    /// The byte offsets at which the given text breaks into sentences
+    fn boundaries(&self, text: &str) -> Vec<usize> {
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        let mut boundaries = vec![];
+        let mut i = 0;
+        while i < chars.len() {
+            let (offset, c) = chars[i];
+            if !self.terminators.contains(&c) {
+                i += 1;
+                continue;
+            }
+            // Extend over the whole terminator run ("?!", "...")
+            let mut end = i + 1;
+            while end < chars.len() && self.terminators.contains(&chars[end].1) {
+                end += 1;
+            }
+            // The boundary needs whitespace and then a sentence opener, so
+            // that "3.14" or "etc. and so on" stay together
+            let mut next = end;
+            while next < chars.len() && chars[next].1.is_whitespace() {
+                next += 1;
+            }
+            let abbreviation =
+                c == '.' && end == i + 1 && self.is_abbreviation(text, offset + c.len_utf8());
+            if next > end
+                && next < chars.len()
+                && Self::opens_sentence(chars[next].1)
+                && !abbreviation
+            {
+                boundaries.push(chars[end].0);
+            }
+            i = end;
+        }
+        boundaries
+    }
+}
+
+impl PreTokenizer for SentenceSplit {
+    fn pre_tokenize(&self, pretokenized: &mut PreTokenizedString) -> Result<()> {
+        pretokenized.split(|_, normalized| {
+            let text = normalized.get();
+            let mut sentences = vec![];
+            let mut start = 0;
+            for boundary in self.boundaries(text).into_iter().chain([text.len()]) {
+                if boundary > start {
+                    sentences.push(
+                        normalized
+                            .slice(Range::Normalized(start..boundary))
+                            .ok_or("SentenceSplit produced an invalid split")?,
+                    );
+                    start = boundary;
+                }
+            }
+            Ok(sentences)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OffsetReferential, OffsetType};
+
+    fn sentences(pretok: &SentenceSplit, s: &str) -> Vec<String> {
+        let mut pretokenized: PreTokenizedString = s.into();
+        pretok.pre_tokenize(&mut pretokenized).unwrap();
+        pretokenized
+            .get_splits(OffsetReferential::Original, OffsetType::Byte)
+            .into_iter()
+            .map(|(s, _, _)| s.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn basic_sentences() {
+        let pretok = SentenceSplit::new();
+        let mut pretokenized: PreTokenizedString =
+            "Hello there. How are you?! Fine... Thanks.".into();
+        pretok.pre_tokenize(&mut pretokenized).unwrap();
+        assert_eq!(
+            pretokenized
+                .get_splits(OffsetReferential::Original, OffsetType::Byte)
+                .into_iter()
+                .map(|(s, o, _)| (s, o))
+                .collect::<Vec<_>>(),
+            vec![
+                ("Hello there.", (0, 12)),
+                (" How are you?!", (12, 26)),
+                (" Fine...", (26, 34)),
+                (" Thanks.", (34, 42)),
+            ]
+        );
+    }
+
+    #[test]
+    fn abbreviations_and_numbers() {
+        let pretok = SentenceSplit::new();
+        // Abbreviations, initials and decimal numbers do not end a sentence
+        assert_eq!(
+            sentences(&pretok, "Dr. J. Smith weighs 3.14 kg. Really."),
+            vec!["Dr. J. Smith weighs 3.14 kg.", " Really."]
+        );
+        // Neither does a terminator followed by a lowercase continuation
+        assert_eq!(
+            sentences(&pretok, "One, two, etc. and so on. Done."),
+            vec!["One, two, etc. and so on.", " Done."]
+        );
+    }
+
+    #[test]
+    fn per_language_abbreviations() {
+        let pretok = SentenceSplit::for_language("fr").unwrap();
+        assert_eq!(
+            sentences(&pretok, "M. Dupont est arrivé. Il dort."),
+            vec!["M. Dupont est arrivé.", " Il dort."]
+        );
+        assert!(SentenceSplit::for_language("xx").is_none());
+    }
+
+    #[test]
+    fn serialization_roundtrip() {
+        let pretok = SentenceSplit::new().abbreviations(vec!["No.".to_string()]);
+        let serialized = serde_json::to_string(&pretok).unwrap();
+        let deserialized: SentenceSplit = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(pretok, deserialized);
+    }
+}
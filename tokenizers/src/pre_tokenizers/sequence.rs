@@ -21,6 +21,10 @@ impl Sequence {
     pub fn get_pre_tokenizers_mut(&mut self) -> &mut [PreTokenizerWrapper] {
         &mut self.pretokenizers
     }
+
+    pub fn into_pre_tokenizers(self) -> Vec<PreTokenizerWrapper> {
+        self.pretokenizers
+    }
 }
 
 impl PreTokenizer for Sequence {
@@ -30,6 +34,18 @@ impl PreTokenizer for Sequence {
         }
         Ok(())
     }
+
+    fn prefix_space(&self) -> Option<bool> {
+        self.pretokenizers
+            .iter()
+            .find_map(|pretokenizer| pretokenizer.prefix_space())
+    }
+
+    fn set_prefix_space(&mut self, prefix_space: bool) {
+        for pretokenizer in &mut self.pretokenizers {
+            pretokenizer.set_prefix_space(prefix_space);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -2,7 +2,10 @@ use crate::utils::SysRegex;
 use serde::{Deserialize, Deserializer, Serialize};
 
 use crate::tokenizer::{
-    pattern::Invert, PreTokenizedString, PreTokenizer, Result, SplitDelimiterBehavior,
+    normalizer::Range,
+    pattern::{Invert, Pattern},
+    pre_tokenizer::Split as PreTokenizedSplit,
+    Offsets, PreTokenizedString, PreTokenizer, Result, SplitDelimiterBehavior,
 };
 
 /// Represents the different patterns that `Split` can use
@@ -10,6 +13,56 @@ use crate::tokenizer::{
 pub enum SplitPattern {
     String(String),
     Regex(String),
+    /// A pattern for the pure-Rust `regex` engine, which does not support
+    /// lookarounds or backreferences but is often much faster than the
+    /// default oniguruma engine on long inputs
+    RustRegex(String),
+    /// A pattern for the `fancy-regex` engine, which supports lookarounds
+    /// (needed e.g. to replicate the GPT-4 pre-tokenization regex exactly), at
+    /// the cost of being slower than the default engine
+    #[cfg(feature = "fancy-regex")]
+    FancyRegex(String),
+}
+
+/// A [`SplitPattern`] compiled with the engine its variant selects
+#[derive(Debug)]
+pub(crate) enum SplitRegex {
+    Default(SysRegex),
+    Rust(crate::utils::rust::SysRegex),
+    #[cfg(feature = "fancy-regex")]
+    Fancy(crate::utils::fancy::SysRegex),
+}
+
+impl SplitRegex {
+    pub(crate) fn compile(pattern: &SplitPattern) -> Result<Self> {
+        Ok(match pattern {
+            SplitPattern::String(s) => Self::Default(SysRegex::new(&regex::escape(s))?),
+            SplitPattern::Regex(r) => Self::Default(SysRegex::new(r)?),
+            SplitPattern::RustRegex(r) => Self::Rust(crate::utils::rust::SysRegex::new(r)?),
+            #[cfg(feature = "fancy-regex")]
+            SplitPattern::FancyRegex(r) => Self::Fancy(crate::utils::fancy::SysRegex::new(r)?),
+        })
+    }
+
+    /// The non-overlapping `(start, end)` matches of the pattern in `inside`
+    fn matches(&self, inside: &str) -> Vec<(usize, usize)> {
+        match self {
+            Self::Default(regex) => regex.find_iter(inside).collect(),
+            Self::Rust(regex) => regex.find_iter(inside).collect(),
+            #[cfg(feature = "fancy-regex")]
+            Self::Fancy(regex) => regex.find_iter(inside).collect(),
+        }
+    }
+
+    /// Find the matches of the pattern in `inside`, as a [`Pattern`] would
+    pub(crate) fn find_matches(&self, inside: &str) -> Result<Vec<(Offsets, bool)>> {
+        match self {
+            Self::Default(regex) => regex.find_matches(inside),
+            Self::Rust(regex) => regex.find_matches(inside),
+            #[cfg(feature = "fancy-regex")]
+            Self::Fancy(regex) => regex.find_matches(inside),
+        }
+    }
 }
 
 impl From<String> for SplitPattern {
@@ -29,7 +82,7 @@ impl From<&str> for SplitPattern {
 pub struct Split {
     pattern: SplitPattern,
     #[serde(skip)]
-    regex: SysRegex,
+    regex: SplitRegex,
     behavior: SplitDelimiterBehavior,
     invert: bool,
 }
@@ -79,10 +132,7 @@ impl Split {
         invert: bool,
     ) -> Result<Self> {
         let pattern: SplitPattern = pattern.into();
-        let regex = match &pattern {
-            SplitPattern::String(s) => SysRegex::new(&regex::escape(s))?,
-            SplitPattern::Regex(r) => SysRegex::new(r)?,
-        };
+        let regex = SplitRegex::compile(&pattern)?;
 
         Ok(Self {
             pattern,
@@ -95,11 +145,177 @@ impl Split {
 
 impl PreTokenizer for Split {
     fn pre_tokenize(&self, pretokenized: &mut PreTokenizedString) -> Result<()> {
-        if self.invert {
-            pretokenized.split(|_, normalized| normalized.split(Invert(&self.regex), self.behavior))
-        } else {
-            pretokenized.split(|_, normalized| normalized.split(&self.regex, self.behavior))
+        match (&self.regex, self.invert) {
+            (SplitRegex::Default(regex), true) => {
+                pretokenized.split(|_, normalized| normalized.split(Invert(regex), self.behavior))
+            }
+            (SplitRegex::Default(regex), false) => {
+                pretokenized.split(|_, normalized| normalized.split(regex, self.behavior))
+            }
+            (SplitRegex::Rust(regex), true) => {
+                pretokenized.split(|_, normalized| normalized.split(Invert(regex), self.behavior))
+            }
+            (SplitRegex::Rust(regex), false) => {
+                pretokenized.split(|_, normalized| normalized.split(regex, self.behavior))
+            }
+            #[cfg(feature = "fancy-regex")]
+            (SplitRegex::Fancy(regex), true) => {
+                pretokenized.split(|_, normalized| normalized.split(Invert(regex), self.behavior))
+            }
+            #[cfg(feature = "fancy-regex")]
+            (SplitRegex::Fancy(regex), false) => {
+                pretokenized.split(|_, normalized| normalized.split(regex, self.behavior))
+            }
+        }
+    }
+}
+
+/// One of the patterns of a [`MultiSplit`], with an optional category label
+/// attached to the sections it matches
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq)]
+pub struct LabeledPattern {
+    pub pattern: SplitPattern,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// An optional metadata tag id attached to the tokens of the matched
+    /// sections, retrievable with `Encoding::get_tags`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<u32>,
+}
+
+impl LabeledPattern {
+    pub fn new<I: Into<SplitPattern>>(pattern: I, label: Option<String>) -> Self {
+        Self {
+            pattern: pattern.into(),
+            label,
+            tag: None,
+        }
+    }
+
+    /// Attach a metadata tag id to the sections this pattern matches
+    #[must_use]
+    pub fn with_tag(mut self, tag: u32) -> Self {
+        self.tag = Some(tag);
+        self
+    }
+}
+
+/// Isolates the sections matched by several patterns at once, resolving
+/// overlapping matches left-most longest across patterns, with ties between
+/// equally long matches broken by the order of the patterns (earlier patterns
+/// have priority).
+///
+/// Matched sections become atomic splits carrying the label of their pattern,
+/// retrievable with [`PreTokenizedString::get_split_labels`], and are left
+/// untouched by subsequent pre-tokenizers. This makes it possible to
+/// categorize sections of the input, e.g. strings vs comments vs identifiers
+/// when tokenizing code.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type")]
+pub struct MultiSplit {
+    patterns: Vec<LabeledPattern>,
+    #[serde(skip)]
+    regexes: Vec<SplitRegex>,
+}
+
+impl<'de> Deserialize<'de> for MultiSplit {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        enum Type {
+            MultiSplit,
+        }
+
+        #[derive(Deserialize)]
+        pub struct MultiSplitHelper {
+            #[serde(rename = "type")]
+            _type: Type,
+            patterns: Vec<LabeledPattern>,
         }
+
+        let helper = MultiSplitHelper::deserialize(deserializer)?;
+        Self::new(helper.patterns).map_err(serde::de::Error::custom)
+    }
+}
+
+impl Clone for MultiSplit {
+    fn clone(&self) -> Self {
+        Self::new(self.patterns.clone()).unwrap()
+    }
+}
+
+impl PartialEq for MultiSplit {
+    fn eq(&self, other: &Self) -> bool {
+        self.patterns == other.patterns
+    }
+}
+
+impl MultiSplit {
+    pub fn new(patterns: Vec<LabeledPattern>) -> Result<Self> {
+        let regexes = patterns
+            .iter()
+            .map(|p| SplitRegex::compile(&p.pattern))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(Self { patterns, regexes })
+    }
+}
+
+impl PreTokenizer for MultiSplit {
+    fn pre_tokenize(&self, pretokenized: &mut PreTokenizedString) -> Result<()> {
+        pretokenized.split(|_, normalized| {
+            // Collect the matches of every pattern, as (start, end, pattern)
+            let mut matches = vec![];
+            for (idx, regex) in self.regexes.iter().enumerate() {
+                for (start, end) in regex.matches(normalized.get()) {
+                    if end > start {
+                        matches.push((start, end, idx));
+                    }
+                }
+            }
+            // Left-most longest selection: earliest start first, then longest
+            // match, then pattern priority
+            matches.sort_unstable_by_key(|&(start, end, idx)| (start, std::cmp::Reverse(end), idx));
+
+            let mut splits = vec![];
+            let mut cursor = 0;
+            for (start, end, idx) in matches {
+                if start < cursor {
+                    continue;
+                }
+                if start > cursor {
+                    splits.push(
+                        normalized
+                            .slice(Range::Normalized(cursor..start))
+                            .ok_or("MultiSplit: invalid split range")?
+                            .into(),
+                    );
+                }
+                let section = normalized
+                    .slice(Range::Normalized(start..end))
+                    .ok_or("MultiSplit: invalid split range")?;
+                let mut split = match &self.patterns[idx].label {
+                    Some(label) => PreTokenizedSplit::labeled(section, label),
+                    None => PreTokenizedSplit::atomic(section),
+                };
+                if let Some(tag) = self.patterns[idx].tag {
+                    split = split.with_tag(tag);
+                }
+                splits.push(split);
+                cursor = end;
+            }
+            if cursor < normalized.len() {
+                splits.push(
+                    normalized
+                        .slice(Range::Normalized(cursor..normalized.len()))
+                        .ok_or("MultiSplit: invalid split range")?
+                        .into(),
+                );
+            }
+            Ok(splits)
+        })
     }
 }
 
@@ -250,4 +466,181 @@ mod tests {
         assert_eq!(serde_json::to_string(&split).unwrap(), split_s);
         assert_eq!(serde_json::from_str::<Split>(split_s).unwrap(), split);
     }
+
+    #[test]
+    fn rust_regex_engine() {
+        // The pure-Rust engine splits the same way as the default one
+        let pretok = Split::new(
+            SplitPattern::RustRegex(r"\s+".into()),
+            SplitDelimiterBehavior::Removed,
+            false,
+        )
+        .unwrap();
+        let mut pretokenized = PreTokenizedString::from("Hello   there friend");
+        pretok.pre_tokenize(&mut pretokenized).unwrap();
+        assert_eq!(
+            pretokenized
+                .get_splits(OffsetReferential::Original, OffsetType::Byte)
+                .into_iter()
+                .map(|(s, _, _)| s)
+                .collect::<Vec<_>>(),
+            vec!["Hello", "there", "friend"]
+        );
+
+        let split_s = r#"{"type":"Split","pattern":{"RustRegex":"\\s+"},"behavior":"Removed","invert":false}"#;
+        assert_eq!(serde_json::to_string(&pretok).unwrap(), split_s);
+        assert_eq!(serde_json::from_str::<Split>(split_s).unwrap(), pretok);
+    }
+
+    #[cfg(feature = "fancy-regex")]
+    #[test]
+    fn fancy_regex_lookarounds() {
+        // Split the digits from the letters they follow, using a lookbehind:
+        // not expressible with the default engine
+        let pretok = Split::new(
+            SplitPattern::FancyRegex(r"(?<=[a-z])(?=\d)".into()),
+            SplitDelimiterBehavior::Isolated,
+            false,
+        )
+        .unwrap();
+        let mut pretokenized = PreTokenizedString::from("abc123 def456");
+        pretok.pre_tokenize(&mut pretokenized).unwrap();
+        assert_eq!(
+            pretokenized
+                .get_splits(OffsetReferential::Original, OffsetType::Byte)
+                .into_iter()
+                .map(|(s, _, _)| s)
+                .collect::<Vec<_>>(),
+            vec!["abc", "123 def", "456"]
+        );
+
+        let split_s = r#"{"type":"Split","pattern":{"FancyRegex":"(?<=[a-z])(?=\\d)"},"behavior":"Isolated","invert":false}"#;
+        assert_eq!(serde_json::to_string(&pretok).unwrap(), split_s);
+        assert_eq!(serde_json::from_str::<Split>(split_s).unwrap(), pretok);
+    }
+
+    #[test]
+    fn multi_split_labels() {
+        let pretok = MultiSplit::new(vec![
+            LabeledPattern::new(
+                SplitPattern::Regex("\"[^\"]*\"".into()),
+                Some("string".into()),
+            ),
+            LabeledPattern::new(
+                SplitPattern::Regex("//[^\n]*".into()),
+                Some("comment".into()),
+            ),
+        ])
+        .unwrap();
+
+        let mut pretokenized = PreTokenizedString::from("print(\"hi\") // say hi");
+        pretok.pre_tokenize(&mut pretokenized).unwrap();
+        assert_eq!(
+            pretokenized
+                .get_splits(OffsetReferential::Original, OffsetType::Byte)
+                .into_iter()
+                .map(|(s, o, _)| (s, o))
+                .collect::<Vec<_>>(),
+            vec![
+                ("print(", (0, 6)),
+                ("\"hi\"", (6, 10)),
+                (") ", (10, 12)),
+                ("// say hi", (12, 21)),
+            ]
+        );
+        assert_eq!(
+            pretokenized.get_split_labels(),
+            vec![None, Some("string"), None, Some("comment")]
+        );
+
+        // Labeled sections are atomic: a subsequent pre-tokenizer only splits
+        // the unlabeled parts
+        pretokenized
+            .split(|_, normalized| normalized.split(' ', SplitDelimiterBehavior::Removed))
+            .unwrap();
+        assert_eq!(
+            pretokenized
+                .get_splits(OffsetReferential::Original, OffsetType::Byte)
+                .into_iter()
+                .map(|(s, _, _)| s)
+                .collect::<Vec<_>>(),
+            vec!["print(", "\"hi\"", ")", "// say hi"]
+        );
+    }
+
+    #[test]
+    fn multi_split_tags() {
+        let pretok = MultiSplit::new(vec![LabeledPattern::new(
+            SplitPattern::Regex("[0-9]+".into()),
+            Some("number".into()),
+        )
+        .with_tag(7)])
+        .unwrap();
+
+        let mut pretokenized = PreTokenizedString::from("ab 12");
+        pretok.pre_tokenize(&mut pretokenized).unwrap();
+        assert_eq!(pretokenized.get_split_tags(), vec![None, Some(7)]);
+
+        // The tags propagate to the tokens of the resulting encoding
+        pretokenized
+            .tokenize(|normalized| {
+                Ok(vec![crate::Token::new(
+                    0,
+                    normalized.get().to_owned(),
+                    (0, normalized.len()),
+                )])
+            })
+            .unwrap();
+        let encoding = pretokenized
+            .into_encoding(None, 0, OffsetType::Byte)
+            .unwrap();
+        assert_eq!(encoding.get_tags(), &[None, Some(7)]);
+
+        let pretok_s = r#"{"type":"MultiSplit","patterns":[{"pattern":{"Regex":"[0-9]+"},"label":"number","tag":7}]}"#;
+        assert_eq!(serde_json::to_string(&pretok).unwrap(), pretok_s);
+        assert_eq!(
+            serde_json::from_str::<MultiSplit>(pretok_s).unwrap(),
+            pretok
+        );
+    }
+
+    #[test]
+    fn multi_split_leftmost_longest() {
+        // "ab" starts earlier than "bcd" and wins; "bc" and "bcd" then compete
+        // at the same position and the longest match wins, whatever the
+        // pattern order
+        let pretok = MultiSplit::new(vec![
+            LabeledPattern::new("bc", Some("short".into())),
+            LabeledPattern::new("bcd", Some("long".into())),
+        ])
+        .unwrap();
+        let mut pretokenized = PreTokenizedString::from("abcd");
+        pretok.pre_tokenize(&mut pretokenized).unwrap();
+        assert_eq!(pretokenized.get_split_labels(), vec![None, Some("long")]);
+
+        // Equally long matches at the same position go to the first pattern
+        let pretok = MultiSplit::new(vec![
+            LabeledPattern::new("ab", Some("first".into())),
+            LabeledPattern::new("ab", Some("second".into())),
+        ])
+        .unwrap();
+        let mut pretokenized = PreTokenizedString::from("ab");
+        pretok.pre_tokenize(&mut pretokenized).unwrap();
+        assert_eq!(pretokenized.get_split_labels(), vec![Some("first")]);
+    }
+
+    #[test]
+    fn multi_split_serialization() {
+        let pretok = MultiSplit::new(vec![
+            LabeledPattern::new(SplitPattern::Regex("[a-z]+".into()), Some("word".into())),
+            LabeledPattern::new("!", None),
+        ])
+        .unwrap();
+        let pretok_s = r#"{"type":"MultiSplit","patterns":[{"pattern":{"Regex":"[a-z]+"},"label":"word"},{"pattern":{"String":"!"}}]}"#;
+        assert_eq!(serde_json::to_string(&pretok).unwrap(), pretok_s);
+        assert_eq!(
+            serde_json::from_str::<MultiSplit>(pretok_s).unwrap(),
+            pretok
+        );
+    }
 }
@@ -1,5 +1,9 @@
 mod pre_tokenizer;
+mod script_split;
 mod scripts;
 
-// Re-export the PreTokenizer
+// Re-export the PreTokenizers
 pub use pre_tokenizer::UnicodeScripts;
+pub use script_split::ScriptSplit;
+pub(crate) use scripts::get_script;
+pub use scripts::Script;
@@ -1,20 +1,30 @@
 use crate::pre_tokenizers::unicode_scripts::scripts::{get_script, Script};
 use crate::tokenizer::{normalizer::Range, PreTokenizedString, PreTokenizer, Result};
 use crate::utils::macro_rules_attribute;
+use serde::{Deserialize, Serialize};
+use unicode_categories::UnicodeCategories;
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
 #[macro_rules_attribute(impl_serde_type!)]
-pub struct UnicodeScripts;
+pub struct UnicodeScripts {
+    /// Whether to keep emoji, including ZWJ sequences like a family emoji
+    /// built from several pictographs, within a single split instead of
+    /// fragmenting them like ordinary symbol characters
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub merge_emoji: bool,
+    /// Whether to group runs of punctuation and symbol characters in splits of
+    /// their own, instead of merging them with any neighboring `Common` chars
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub group_symbols: bool,
+    /// Whether to treat digits as their own class, splitting them from both
+    /// letters and symbols
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub split_digits: bool,
+}
 
 impl UnicodeScripts {
     pub fn new() -> Self {
-        Self {}
-    }
-}
-
-impl Default for UnicodeScripts {
-    fn default() -> Self {
-        Self::new()
+        Self::default()
     }
 }
 
@@ -37,27 +47,68 @@ fn fixed_script(c: char) -> Script {
     }
 }
 
+/// Whether the char takes part in emoji sequences: pictographs themselves, but
+/// also the joiners and modifiers gluing them together (ZWJ, variation
+/// selectors, keycaps)
+fn is_emoji(c: char) -> bool {
+    matches!(c as u32,
+        0x1F000..=0x1FAFF // pictographs, regional indicators, skin tone modifiers
+        | 0x2600..=0x27BF // miscellaneous symbols and dingbats
+        | 0x200D // zero-width joiner
+        | 0xFE0F // variation selector-16
+        | 0x20E3 // combining enclosing keycap
+    )
+}
+
+/// The class a char is grouped by: plain scripts, extended with the classes
+/// enabled on the pre-tokenizer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SplitClass {
+    Script(Script),
+    Emoji,
+    Symbol,
+    Digit,
+    Any,
+}
+
+impl UnicodeScripts {
+    fn classify(&self, c: char) -> SplitClass {
+        if self.merge_emoji && is_emoji(c) {
+            SplitClass::Emoji
+        } else if self.split_digits && c.is_numeric() {
+            SplitClass::Digit
+        } else if self.group_symbols && (c.is_punctuation() || c.is_symbol()) {
+            SplitClass::Symbol
+        } else {
+            match fixed_script(c) {
+                Script::Any => SplitClass::Any,
+                script => SplitClass::Script(script),
+            }
+        }
+    }
+}
+
 impl PreTokenizer for UnicodeScripts {
     fn pre_tokenize(&self, pretokenized: &mut PreTokenizedString) -> Result<()> {
         pretokenized.split(|_, normalized| {
-            let mut last_script = None;
+            let mut last_class = None;
             let mut offset = 0;
             let mut ranges: Vec<_> = normalized
                 .get()
                 .chars()
                 .filter_map(|c| {
-                    let script = Some(fixed_script(c));
-                    let result = if script != Some(Script::Any)
-                        && last_script != Some(Script::Any)
-                        && last_script != script
+                    let class = Some(self.classify(c));
+                    let result = if class != Some(SplitClass::Any)
+                        && last_class != Some(SplitClass::Any)
+                        && last_class != class
                     {
                         Some(offset)
                     } else {
                         None
                     };
                     offset += c.len_utf8();
-                    if script != Some(Script::Any) {
-                        last_script = script;
+                    if class != Some(SplitClass::Any) {
+                        last_class = class;
                     }
 
                     result
@@ -84,7 +135,7 @@ mod tests {
 
     #[test]
     fn basic() {
-        let pretok = UnicodeScripts {};
+        let pretok = UnicodeScripts::default();
         let mut pretokenized = PreTokenizedString::from("どこで生れ。Yes");
         pretok.pre_tokenize(&mut pretokenized).unwrap();
         assert_eq!(
@@ -107,7 +158,7 @@ mod tests {
 
     #[test]
     fn spaces_are_included_in_every_script() {
-        let pretok = UnicodeScripts {};
+        let pretok = UnicodeScripts::default();
         let mut pretokenized = PreTokenizedString::from("Apples are りんご 林檎");
         pretok.pre_tokenize(&mut pretokenized).unwrap();
         assert_eq!(
@@ -128,6 +179,82 @@ mod tests {
         );
     }
 
+    #[test]
+    fn emoji_symbols_and_digits() {
+        let pretok = UnicodeScripts {
+            merge_emoji: true,
+            group_symbols: true,
+            split_digits: true,
+        };
+
+        // The family emoji is a ZWJ sequence of four pictographs: it stays in
+        // one split instead of being fragmented
+        let mut pretokenized =
+            PreTokenizedString::from("hi\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F466}ho");
+        pretok.pre_tokenize(&mut pretokenized).unwrap();
+        assert_eq!(
+            pretokenized
+                .get_splits(OffsetReferential::Normalized, OffsetType::Byte)
+                .into_iter()
+                .map(|(s, _, _)| s)
+                .collect::<Vec<_>>(),
+            vec!["hi", "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F466}", "ho"]
+        );
+
+        // Symbol and punctuation runs group together, and digits form their
+        // own class instead of merging with the surrounding Common chars
+        let mut pretokenized = PreTokenizedString::from("price: $25!!");
+        pretok.pre_tokenize(&mut pretokenized).unwrap();
+        assert_eq!(
+            pretokenized
+                .get_splits(OffsetReferential::Normalized, OffsetType::Byte)
+                .into_iter()
+                .map(|(s, _, _)| s)
+                .collect::<Vec<_>>(),
+            vec!["price", ": $", "25", "!!"]
+        );
+
+        // The default configuration keeps the historical behavior: everything
+        // Common sticks together
+        let pretok = UnicodeScripts::default();
+        let mut pretokenized = PreTokenizedString::from("price: $25!!");
+        pretok.pre_tokenize(&mut pretokenized).unwrap();
+        assert_eq!(
+            pretokenized
+                .get_splits(OffsetReferential::Normalized, OffsetType::Byte)
+                .into_iter()
+                .map(|(s, _, _)| s)
+                .collect::<Vec<_>>(),
+            vec!["price", ": $25!!"]
+        );
+    }
+
+    #[test]
+    fn serialization() {
+        // The default configuration keeps the historical representation
+        let pretok = UnicodeScripts::default();
+        let serialized = serde_json::to_string(&pretok).unwrap();
+        assert_eq!(serialized, r#"{"type":"UnicodeScripts"}"#);
+        assert_eq!(
+            serde_json::from_str::<UnicodeScripts>(&serialized).unwrap(),
+            pretok
+        );
+
+        let pretok = UnicodeScripts {
+            merge_emoji: true,
+            ..UnicodeScripts::default()
+        };
+        let serialized = serde_json::to_string(&pretok).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"type":"UnicodeScripts","merge_emoji":true}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<UnicodeScripts>(&serialized).unwrap(),
+            pretok
+        );
+    }
+
     #[test]
     fn test_unicode_script() {
         assert_eq!(Script::Han, fixed_script('京'));
@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+
+use crate::pre_tokenizers::unicode_scripts::scripts::{get_script, Script};
+use crate::tokenizer::{normalizer::Range, PreTokenizedString, PreTokenizer, Result};
+use crate::utils::macro_rules_attribute;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Splits at Unicode script boundaries, like SentencePiece's
+/// `split_by_unicode_script`.
+///
+/// Unlike [`UnicodeScripts`], the scripts that should be kept merged are
+/// configurable: every script listed in `merged_scripts` is treated as a single
+/// script, so e.g. Hiragana+Katakana+Han Japanese text stays in one split.
+/// Spaces join with any script, as in [`UnicodeScripts`].
+///
+/// [`UnicodeScripts`]: crate::pre_tokenizers::unicode_scripts::UnicodeScripts
+#[macro_rules_attribute(impl_serde_type!)]
+pub struct ScriptSplit {
+    /// The scripts to treat as one, none by default
+    #[serde(default)]
+    pub merged_scripts: Vec<Script>,
+}
+
+impl ScriptSplit {
+    pub fn new(merged_scripts: Vec<Script>) -> Self {
+        Self { merged_scripts }
+    }
+
+    fn effective_script(&self, c: char) -> Script {
+        if c == ' ' {
+            return Script::Any;
+        }
+        let script = get_script(c);
+        if self.merged_scripts.len() > 1 && self.merged_scripts.contains(&script) {
+            // Any representative works, as long as it is the same for every
+            // merged script
+            self.merged_scripts[0]
+        } else {
+            script
+        }
+    }
+}
+
+impl Default for ScriptSplit {
+    fn default() -> Self {
+        Self::new(vec![])
+    }
+}
+
+impl PreTokenizer for ScriptSplit {
+    fn pre_tokenize(&self, pretokenized: &mut PreTokenizedString) -> Result<()> {
+        pretokenized.split(|_, normalized| {
+            let mut last_script = None;
+            let mut offset = 0;
+            let mut ranges: Vec<_> = normalized
+                .get()
+                .chars()
+                .filter_map(|c| {
+                    let script = Some(self.effective_script(c));
+                    let result = if script != Some(Script::Any)
+                        && last_script != Some(Script::Any)
+                        && last_script != script
+                    {
+                        Some(offset)
+                    } else {
+                        None
+                    };
+                    offset += c.len_utf8();
+                    if script != Some(Script::Any) {
+                        last_script = script;
+                    }
+
+                    result
+                })
+                .collect();
+            ranges.push(normalized.get().len());
+            Ok(ranges
+                .windows(2)
+                .map(|item| {
+                    normalized
+                        .slice(Range::Normalized(item[0]..item[1]))
+                        .expect("NormalizedString bad split")
+                })
+                .collect::<Vec<_>>())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OffsetReferential;
+    use crate::OffsetType;
+
+    #[test]
+    fn splits_all_scripts_by_default() {
+        let pretok = ScriptSplit::default();
+        let mut pretokenized = PreTokenizedString::from("カナ生れYes");
+        pretok.pre_tokenize(&mut pretokenized).unwrap();
+        assert_eq!(
+            pretokenized
+                .get_splits(OffsetReferential::Original, OffsetType::Byte)
+                .into_iter()
+                .map(|(s, o, _)| (s, o))
+                .collect::<Vec<_>>(),
+            vec![
+                ("カナ", (0, 6)),
+                ("生", (6, 9)),
+                ("れ", (9, 12)),
+                ("Yes", (12, 15))
+            ]
+        );
+    }
+
+    #[test]
+    fn merged_scripts_stay_together() {
+        let pretok = ScriptSplit::new(vec![Script::Hiragana, Script::Katakana, Script::Han]);
+        let mut pretokenized = PreTokenizedString::from("カナ生れYes");
+        pretok.pre_tokenize(&mut pretokenized).unwrap();
+        assert_eq!(
+            pretokenized
+                .get_splits(OffsetReferential::Original, OffsetType::Byte)
+                .into_iter()
+                .map(|(s, o, _)| (s, o))
+                .collect::<Vec<_>>(),
+            vec![("カナ生れ", (0, 12)), ("Yes", (12, 15))]
+        );
+    }
+
+    #[test]
+    fn spaces_join_with_any_script() {
+        let pretok = ScriptSplit::default();
+        let mut pretokenized = PreTokenizedString::from("Apples are りんご");
+        pretok.pre_tokenize(&mut pretokenized).unwrap();
+        assert_eq!(
+            pretokenized
+                .get_splits(OffsetReferential::Original, OffsetType::Byte)
+                .into_iter()
+                .map(|(s, o, _)| (s, o))
+                .collect::<Vec<_>>(),
+            vec![("Apples are ", (0, 11)), ("りんご", (11, 20))]
+        );
+    }
+}
@@ -0,0 +1,170 @@
+//! A post-processor backed by a user-supplied Rust callback.
+//!
+//! This allows applications to implement bespoke special-token logic without
+//! forking [`PostProcessorWrapper`](crate::processors::PostProcessorWrapper).
+//! Since an arbitrary closure cannot be represented in `tokenizer.json`, a
+//! tokenizer using a [`CustomProcessor`] cannot be serialized: doing so returns
+//! a clear error instead of producing a file that silently drops the processor.
+
+use std::fmt;
+use std::sync::Arc;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{Encoding, PostProcessor, Result};
+
+type CustomProcessorFn = dyn Fn(Encoding, Option<Encoding>, bool) -> Result<Encoding> + Send + Sync;
+
+/// A post-processor calling a user-supplied callback with the encoding, the
+/// optional pair encoding, and whether special tokens should be added.
+#[derive(Clone)]
+pub struct CustomProcessor {
+    process_fn: Arc<CustomProcessorFn>,
+    added_single: usize,
+    added_pair: usize,
+}
+
+impl CustomProcessor {
+    pub fn new(
+        process_fn: impl Fn(Encoding, Option<Encoding>, bool) -> Result<Encoding>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        Self {
+            process_fn: Arc::new(process_fn),
+            added_single: 0,
+            added_pair: 0,
+        }
+    }
+
+    /// Declare how many tokens the callback adds, so that truncation can
+    /// reserve room for them. Defaults to 0 for both single and pair inputs.
+    #[must_use]
+    pub fn with_added_tokens(mut self, single: usize, pair: usize) -> Self {
+        self.added_single = single;
+        self.added_pair = pair;
+        self
+    }
+}
+
+impl fmt::Debug for CustomProcessor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CustomProcessor")
+            .field("added_single", &self.added_single)
+            .field("added_pair", &self.added_pair)
+            .finish()
+    }
+}
+
+impl PartialEq for CustomProcessor {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.process_fn, &other.process_fn)
+            && self.added_single == other.added_single
+            && self.added_pair == other.added_pair
+    }
+}
+impl Eq for CustomProcessor {}
+
+impl Serialize for CustomProcessor {
+    fn serialize<S>(&self, _serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        Err(serde::ser::Error::custom(
+            "Custom post-processors cannot be serialized",
+        ))
+    }
+}
+
+impl<'de> Deserialize<'de> for CustomProcessor {
+    fn deserialize<D>(_deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Err(serde::de::Error::custom(
+            "Custom post-processors cannot be deserialized",
+        ))
+    }
+}
+
+impl PostProcessor for CustomProcessor {
+    fn added_tokens(&self, is_pair: bool) -> usize {
+        if is_pair {
+            self.added_pair
+        } else {
+            self.added_single
+        }
+    }
+
+    fn process(
+        &self,
+        encoding: Encoding,
+        pair_encoding: Option<Encoding>,
+        add_special_tokens: bool,
+    ) -> Result<Encoding> {
+        (self.process_fn)(encoding, pair_encoding, add_special_tokens)
+    }
+
+    fn process_encodings(
+        &self,
+        mut encodings: Vec<Encoding>,
+        add_special_tokens: bool,
+    ) -> Result<Vec<Encoding>> {
+        match encodings.len() {
+            1 => {
+                let encoding = encodings.pop().unwrap();
+                Ok(vec![(self.process_fn)(encoding, None, add_special_tokens)?])
+            }
+            2 => {
+                let pair = encodings.pop().unwrap();
+                let encoding = encodings.pop().unwrap();
+                Ok(vec![(self.process_fn)(
+                    encoding,
+                    Some(pair),
+                    add_special_tokens,
+                )?])
+            }
+            n => {
+                Err(format!("Custom post-processors only support 1 or 2 sequences, got {n}").into())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processors::PostProcessorWrapper;
+
+    fn processor() -> CustomProcessor {
+        CustomProcessor::new(|mut encoding, pair, _| {
+            if let Some(pair) = pair {
+                encoding.merge_with(pair, false);
+            }
+            Ok(encoding)
+        })
+    }
+
+    #[test]
+    fn custom_processor() {
+        let processor = processor().with_added_tokens(1, 2);
+        assert_eq!(processor.added_tokens(false), 1);
+        assert_eq!(processor.added_tokens(true), 2);
+
+        let encoding = Encoding::from_tokens(vec![crate::Token::new(0, "Hello".into(), (0, 5))], 0);
+        let pair = Encoding::from_tokens(vec![crate::Token::new(1, "world".into(), (0, 5))], 1);
+        let output = processor.process(encoding, Some(pair), true).unwrap();
+        assert_eq!(output.get_tokens(), &["Hello", "world"]);
+    }
+
+    #[test]
+    fn serialization_errors_out() {
+        let wrapper = PostProcessorWrapper::Custom(processor());
+        let err = serde_json::to_string(&wrapper).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Custom post-processors cannot be serialized"
+        );
+    }
+}
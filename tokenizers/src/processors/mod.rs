@@ -1,4 +1,6 @@
 pub mod bert;
+#[cfg(feature = "custom-processors")]
+pub mod custom;
 pub mod roberta;
 pub mod sequence;
 pub mod template;
@@ -10,6 +12,8 @@ use serde::{Deserialize, Serialize};
 
 use crate::pre_tokenizers::byte_level::ByteLevel;
 use crate::processors::bert::BertProcessing;
+#[cfg(feature = "custom-processors")]
+use crate::processors::custom::CustomProcessor;
 use crate::processors::roberta::RobertaProcessing;
 use crate::processors::sequence::Sequence;
 use crate::processors::template::TemplateProcessing;
@@ -24,6 +28,10 @@ pub enum PostProcessorWrapper {
     ByteLevel(ByteLevel),
     Template(TemplateProcessing),
     Sequence(Sequence),
+    // Must be last: its Deserialize implementation always errors out, and serde
+    // tries untagged variants in order
+    #[cfg(feature = "custom-processors")]
+    Custom(CustomProcessor),
 }
 
 impl PostProcessor for PostProcessorWrapper {
@@ -34,6 +42,8 @@ impl PostProcessor for PostProcessorWrapper {
             Self::Roberta(roberta) => roberta.added_tokens(is_pair),
             Self::Template(template) => template.added_tokens(is_pair),
             Self::Sequence(bl) => bl.added_tokens(is_pair),
+            #[cfg(feature = "custom-processors")]
+            Self::Custom(custom) => custom.added_tokens(is_pair),
         }
     }
 
@@ -48,6 +58,26 @@ impl PostProcessor for PostProcessorWrapper {
             Self::Roberta(roberta) => roberta.process_encodings(encodings, add_special_tokens),
             Self::Template(template) => template.process_encodings(encodings, add_special_tokens),
             Self::Sequence(bl) => bl.process_encodings(encodings, add_special_tokens),
+            #[cfg(feature = "custom-processors")]
+            Self::Custom(custom) => custom.process_encodings(encodings, add_special_tokens),
+        }
+    }
+
+    fn prefix_space(&self) -> Option<bool> {
+        match self {
+            Self::ByteLevel(bl) => bl.prefix_space(),
+            Self::Roberta(roberta) => roberta.prefix_space(),
+            Self::Sequence(seq) => seq.prefix_space(),
+            _ => None,
+        }
+    }
+
+    fn set_prefix_space(&mut self, prefix_space: bool) {
+        match self {
+            Self::ByteLevel(bl) => bl.set_prefix_space(prefix_space),
+            Self::Roberta(roberta) => roberta.set_prefix_space(prefix_space),
+            Self::Sequence(seq) => seq.set_prefix_space(prefix_space),
+            _ => {}
         }
     }
 }
@@ -57,6 +87,8 @@ impl_enum_from!(ByteLevel, PostProcessorWrapper, ByteLevel);
 impl_enum_from!(RobertaProcessing, PostProcessorWrapper, Roberta);
 impl_enum_from!(TemplateProcessing, PostProcessorWrapper, Template);
 impl_enum_from!(Sequence, PostProcessorWrapper, Sequence);
+#[cfg(feature = "custom-processors")]
+impl_enum_from!(CustomProcessor, PostProcessorWrapper, Custom);
 
 #[cfg(test)]
 mod tests {
@@ -11,6 +11,19 @@ pub struct RobertaProcessing {
     cls: (String, u32),
     trim_offsets: bool,
     add_prefix_space: bool,
+    /// The number of `sep` tokens inserted before each pair sequence. With the
+    /// default of 1, two consecutive sequences are separated by `</s></s>`, as
+    /// in RoBERTa/CamemBERT.
+    #[serde(default = "default_seps_between", skip_serializing_if = "is_one")]
+    seps_between: usize,
+}
+
+fn default_seps_between() -> usize {
+    1
+}
+
+fn is_one(v: &usize) -> bool {
+    *v == 1
 }
 
 impl Default for RobertaProcessing {
@@ -20,6 +33,7 @@ impl Default for RobertaProcessing {
             cls: ("<s>".into(), 0),
             trim_offsets: true,
             add_prefix_space: true,
+            seps_between: 1,
         }
     }
 }
@@ -44,12 +58,18 @@ impl RobertaProcessing {
         self.add_prefix_space = v;
         self
     }
+
+    #[must_use]
+    pub fn seps_between(mut self, v: usize) -> Self {
+        self.seps_between = v;
+        self
+    }
 }
 
 impl PostProcessor for RobertaProcessing {
     fn added_tokens(&self, is_pair: bool) -> usize {
         if is_pair {
-            4
+            2 + self.seps_between + 1
         } else {
             2
         }
@@ -150,23 +170,36 @@ impl PostProcessor for RobertaProcessing {
                         sequence_ranges,
                     )
                 } else {
-                    let pair_ids = [&[self.sep.1], encoding.get_ids(), &[self.sep.1]].concat();
-                    let pair_type_ids = vec![0; encoding.get_ids().len() + 2];
+                    let seps = self.seps_between;
+                    let pair_ids = [
+                        &vec![self.sep.1; seps][..],
+                        encoding.get_ids(),
+                        &[self.sep.1],
+                    ]
+                    .concat();
+                    let pair_type_ids = vec![0; pair_ids.len()];
                     let pair_tokens = [
-                        &[self.sep.0.clone()],
+                        &vec![self.sep.0.clone(); seps][..],
                         encoding.get_tokens(),
                         &[self.sep.0.clone()],
                     ]
                     .concat();
-                    let pair_words = [&[None], encoding.get_word_ids(), &[None]].concat();
-                    let pair_offsets = [&[(0, 0)], encoding.get_offsets(), &[(0, 0)]].concat();
-                    let pair_special_tokens =
-                        [&[1], &vec![0u32; encoding.get_type_ids().len()][..], &[1]].concat();
+                    let pair_words =
+                        [&vec![None; seps][..], encoding.get_word_ids(), &[None]].concat();
+                    let pair_offsets =
+                        [&vec![(0, 0); seps][..], encoding.get_offsets(), &[(0, 0)]].concat();
+                    let pair_special_tokens = [
+                        &vec![1u32; seps][..],
+                        &vec![0u32; encoding.get_type_ids().len()][..],
+                        &[1],
+                    ]
+                    .concat();
                     let pair_attention_mask = vec![1; pair_ids.len()];
 
                     // For compatibility with `TemplateProcessing`, the sequence_ranges shouldn't contain
                     // the special tokens.
-                    let pair_sequence_ranges = HashMap::from_iter(vec![(1, 1..pair_ids.len() - 1)]);
+                    let pair_sequence_ranges =
+                        HashMap::from_iter(vec![(i, seps..pair_ids.len() - 1)]);
                     Encoding::new(
                         pair_ids,
                         pair_type_ids,
@@ -179,28 +212,37 @@ impl PostProcessor for RobertaProcessing {
                             .take_overflowing()
                             .into_iter()
                             .map(|encoding| {
-                                let pair_ids =
-                                    [&[self.sep.1], encoding.get_ids(), &[self.sep.1]].concat();
-                                let pair_type_ids = vec![0; encoding.get_ids().len() + 2];
+                                let pair_ids = [
+                                    &vec![self.sep.1; seps][..],
+                                    encoding.get_ids(),
+                                    &[self.sep.1],
+                                ]
+                                .concat();
+                                let pair_type_ids = vec![0; pair_ids.len()];
                                 let pair_tokens = [
-                                    &[self.sep.0.clone()],
+                                    &vec![self.sep.0.clone(); seps][..],
                                     encoding.get_tokens(),
                                     &[self.sep.0.clone()],
                                 ]
                                 .concat();
                                 let pair_words =
-                                    [&[None], encoding.get_word_ids(), &[None]].concat();
+                                    [&vec![None; seps][..], encoding.get_word_ids(), &[None]]
+                                        .concat();
                                 let pair_offsets =
-                                    [&[(0, 0)], encoding.get_offsets(), &[(0, 0)]].concat();
-                                let pair_special_tokens =
-                                    [&[1], &vec![0u32; encoding.get_type_ids().len()][..], &[1]]
+                                    [&vec![(0, 0); seps][..], encoding.get_offsets(), &[(0, 0)]]
                                         .concat();
+                                let pair_special_tokens = [
+                                    &vec![1u32; seps][..],
+                                    &vec![0u32; encoding.get_type_ids().len()][..],
+                                    &[1],
+                                ]
+                                .concat();
                                 let pair_attention_mask = vec![1; pair_ids.len()];
 
                                 // For compatibility with `TemplateProcessing`, the sequence_ranges
                                 // shouldn't contain the special tokens.
                                 let pair_sequence_ranges =
-                                    HashMap::from_iter(vec![(1, 1..pair_ids.len() - 1)]);
+                                    HashMap::from_iter(vec![(i, seps..pair_ids.len() - 1)]);
                                 Encoding::new(
                                     pair_ids,
                                     pair_type_ids,
@@ -222,6 +264,14 @@ impl PostProcessor for RobertaProcessing {
 
         Ok(encodings)
     }
+
+    fn prefix_space(&self) -> Option<bool> {
+        Some(self.add_prefix_space)
+    }
+
+    fn set_prefix_space(&mut self, prefix_space: bool) {
+        self.add_prefix_space = prefix_space;
+    }
 }
 
 #[cfg(test)]
@@ -329,4 +379,45 @@ mod tests {
         assert_eq!(pair_encoding.token_to_sequence(1), Some(0));
         assert_eq!(pair_encoding.token_to_sequence(2), Some(1));
     }
+
+    #[test]
+    fn roberta_processing_seps_between() {
+        let processor = RobertaProcessing::default().seps_between(2);
+        assert_eq!(processor.added_tokens(true), 5);
+
+        use crate::Token;
+        let encoding = Encoding::from_tokens(
+            vec![
+                Token::new(12, "Hello".into(), (0, 5)),
+                Token::new(14, "there".into(), (6, 11)),
+            ],
+            0,
+        );
+        let pair = Encoding::from_tokens(vec![Token::new(15, "pair".into(), (0, 4))], 0);
+        let pair_encoding = processor.process(encoding, Some(pair), true).unwrap();
+        assert_eq!(pair_encoding.get_ids(), &[0, 12, 14, 2, 2, 2, 15, 2]);
+        assert_eq!(
+            pair_encoding.get_special_tokens_mask(),
+            &[1, 0, 0, 1, 1, 1, 0, 1]
+        );
+        assert_eq!(pair_encoding.token_to_sequence(5), None);
+        assert_eq!(pair_encoding.token_to_sequence(6), Some(1));
+        assert_eq!(pair_encoding.token_to_sequence(7), None);
+
+        // A single `sep` between the two sequences
+        let processor = RobertaProcessing::default().seps_between(0);
+        assert_eq!(processor.added_tokens(true), 3);
+        let encoding = Encoding::from_tokens(
+            vec![
+                Token::new(12, "Hello".into(), (0, 5)),
+                Token::new(14, "there".into(), (6, 11)),
+            ],
+            0,
+        );
+        let pair = Encoding::from_tokens(vec![Token::new(15, "pair".into(), (0, 4))], 0);
+        let pair_encoding = processor.process(encoding, Some(pair), true).unwrap();
+        assert_eq!(pair_encoding.get_ids(), &[0, 12, 14, 2, 15, 2]);
+        assert_eq!(pair_encoding.get_special_tokens_mask(), &[1, 0, 0, 1, 0, 1]);
+        assert_eq!(pair_encoding.token_to_sequence(4), Some(1));
+    }
 }
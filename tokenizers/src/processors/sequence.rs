@@ -33,6 +33,18 @@ impl PostProcessor for Sequence {
         }
         Ok(encodings)
     }
+
+    fn prefix_space(&self) -> Option<bool> {
+        self.processors
+            .iter()
+            .find_map(|processor| processor.prefix_space())
+    }
+
+    fn set_prefix_space(&mut self, prefix_space: bool) {
+        for processor in &mut self.processors {
+            processor.set_prefix_space(prefix_space);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -64,7 +76,7 @@ mod tests {
         );
 
         let bytelevel = ByteLevel::default().trim_offsets(true);
-        let sequence = Sequence::new(vec![PostProcessorWrapper::ByteLevel(bytelevel)]);
+        let sequence = Sequence::new(vec![PostProcessorWrapper::ByteLevel(bytelevel.clone())]);
         let expected = Encoding::new(
             vec![0; 5],
             vec![0; 5],
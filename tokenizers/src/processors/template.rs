@@ -49,6 +49,22 @@
 //!
 //! The same construct is used for special tokens: `<identifier>(:<type_id>)?`.
 //!
+//! More than two sequences (e.g. query, title, body) can be handled by providing a
+//! `multi` template, where sequences are referenced by letter: `$A`, `$B`, `$C`, ...
+//! `$Z`:
+//! ```
+//! # use tokenizers::processors::template::TemplateProcessing;
+//! let template = TemplateProcessing::builder()
+//!     .try_single("[CLS] $0 [SEP]").unwrap()
+//!     .try_pair("[CLS] $A:0 [SEP] $B:1 [SEP]").unwrap()
+//!     .try_multi("[CLS] $A:0 [SEP]:0 $B:1 [SEP]:1 $C:2 [SEP]:2").unwrap()
+//!     .special_tokens(vec![("[CLS]", 1), ("[SEP]", 0)])
+//!     .build()
+//!     .unwrap();
+//! ```
+//! The `multi` template is used by [`PostProcessor::process_encodings`] whenever more
+//! than two encodings are given.
+//!
 //! **Warning**: You must ensure that you are giving the correct tokens/ids as these will
 //! be added to the `Encoding` without any further check. If the given ids correspond to
 //! something totally different in a `Tokenizer` using this `PostProcessor`, it might lead
@@ -56,7 +72,8 @@
 //!
 //! [`TemplateProcessing`]: struct.TemplateProcessing.html
 //!
-use crate::{Encoding, PostProcessor, Result};
+use crate::tokenizer::Model;
+use crate::{Encoding, PostProcessor, Result, TokenizerImpl};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
@@ -70,6 +87,20 @@ pub enum Sequence {
     A,
     /// This is the pair sequence, that is optional
     B,
+    /// Any additional sequence, referenced by its 0-based index. In a template
+    /// string, sequences beyond the pair are written `$C`, `$D`, ... `$Z`.
+    Nth(u32),
+}
+
+impl Sequence {
+    /// The 0-based index of this sequence in the inputs of the PostProcessor
+    pub fn index(&self) -> usize {
+        match self {
+            Self::A => 0,
+            Self::B => 1,
+            Self::Nth(n) => *n as usize,
+        }
+    }
 }
 
 /// Represents the different kind of pieces that constitute a template.
@@ -123,6 +154,13 @@ impl Piece {
                             id: Sequence::A,
                             type_id,
                         })
+                    } else if n.len() == 1 && n.chars().all(|c| c.is_ascii_alphabetic()) {
+                        // Sequences beyond the pair: `$C`, `$D`, ... `$Z`
+                        let index = n.to_uppercase().chars().next().unwrap() as u32 - 'A' as u32;
+                        Some(Self::Sequence {
+                            id: Sequence::Nth(index),
+                            type_id: 0,
+                        })
                     } else {
                         None
                     }
@@ -224,6 +262,23 @@ impl From<(u32, &str)> for SpecialToken {
     }
 }
 
+/// A special token referenced by name only: its ids are left unresolved until
+/// [`TemplateProcessing::bind`] looks them up in a tokenizer's vocabulary
+impl From<String> for SpecialToken {
+    fn from(token: String) -> Self {
+        Self {
+            id: token.clone(),
+            ids: vec![],
+            tokens: vec![token],
+        }
+    }
+}
+impl From<&str> for SpecialToken {
+    fn from(token: &str) -> Self {
+        Self::from(token.to_owned())
+    }
+}
+
 impl SpecialToken {
     pub fn new(id: String, ids: Vec<u32>, tokens: Vec<String>) -> Result<Self> {
         if ids.len() != tokens.len() {
@@ -232,6 +287,12 @@ impl SpecialToken {
             Ok(Self { id, ids, tokens })
         }
     }
+
+    /// Whether the ids of this token are still to be resolved with
+    /// [`TemplateProcessing::bind`]
+    fn is_unresolved(&self) -> bool {
+        self.ids.is_empty() && !self.tokens.is_empty()
+    }
 }
 
 /// A Template represents a Vec<[`Piece`]>.
@@ -315,6 +376,28 @@ impl From<HashMap<String, SpecialToken>> for Tokens {
     }
 }
 
+/// Defines what to do when a template produces a `type_id` greater than the
+/// `max_type_id` of the [`TemplateProcessing`]. This is useful when a `multi`
+/// template targets more segments than the model was trained with.
+///
+/// [`TemplateProcessing`]: struct.TemplateProcessing.html
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum TypeIdOverflow {
+    /// Saturate at `max_type_id`
+    #[default]
+    Clamp,
+    /// Wrap around, using `type_id % (max_type_id + 1)`
+    Wrap,
+    /// Fail with an error while processing
+    Error,
+}
+
+impl TypeIdOverflow {
+    fn is_default(&self) -> bool {
+        *self == Self::Clamp
+    }
+}
+
 /// This PostProcessor takes care of processing each input `Encoding` by applying
 /// the corresponding template, before merging them in the final Encoding.
 ///
@@ -341,6 +424,19 @@ pub struct TemplateProcessing {
     single: Template,
     #[builder(try_setter, default = "\"$A:0 $B:1\".try_into().unwrap()")]
     pair: Template,
+    /// The template used when more than two sequences are given. Sequences are
+    /// referenced by letter: `$A`, `$B`, `$C`, ... `$Z`.
+    #[builder(try_setter, setter(strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    multi: Option<Template>,
+    /// The highest `type_id` supported by the model. When a template produces a
+    /// higher one, it is handled according to `type_id_overflow`.
+    #[builder(setter(strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_type_id: Option<u32>,
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "TypeIdOverflow::is_default")]
+    type_id_overflow: TypeIdOverflow,
     #[builder(setter(skip), default = "self.default_added(true)")]
     #[serde(skip)]
     added_single: usize,
@@ -351,6 +447,73 @@ pub struct TemplateProcessing {
     special_tokens: Tokens,
 }
 
+impl TemplateProcessing {
+    /// Check that every special token referenced by the templates is defined,
+    /// and consistent with the given vocabulary. Used by
+    /// [`Tokenizer::from_file_strict`](crate::Tokenizer::from_file_strict).
+    pub(crate) fn validate_special_tokens(
+        &self,
+        vocab: &HashMap<String, u32>,
+    ) -> std::result::Result<(), crate::tokenizer::StrictValidationError> {
+        use crate::tokenizer::StrictValidationError;
+        let templates = [Some(&self.single), Some(&self.pair), self.multi.as_ref()];
+        for piece in templates.iter().flatten().flat_map(|template| &template.0) {
+            if let Piece::SpecialToken { id, .. } = piece {
+                let token = self
+                    .special_tokens
+                    .0
+                    .get(id)
+                    .ok_or_else(|| StrictValidationError::UndefinedTemplateToken(id.clone()))?;
+                for (tok, tid) in token.tokens.iter().zip(&token.ids) {
+                    if vocab.get(tok) != Some(tid) {
+                        return Err(StrictValidationError::TemplateTokenMismatch(
+                            tok.clone(),
+                            *tid,
+                            vocab.get(tok).copied(),
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolve the ids of the special tokens from the given tokenizer's
+    /// vocabulary, including the added tokens. Every special token is looked
+    /// up, so ids given explicitly to the builder are refreshed too, and the
+    /// call fails if one of the tokens is not part of the vocabulary. This is
+    /// the intended way to build a template before the vocabulary is known,
+    /// e.g. before training: declare the special tokens by name only, and
+    /// `bind` once the tokenizer is ready.
+    pub fn bind<M, N, PT, PP, D>(
+        &mut self,
+        tokenizer: &TokenizerImpl<M, N, PT, PP, D>,
+    ) -> Result<()>
+    where
+        M: Model,
+        N: crate::Normalizer,
+        PT: crate::PreTokenizer,
+        PP: PostProcessor,
+        D: crate::Decoder,
+    {
+        for special_token in self.special_tokens.0.values_mut() {
+            special_token.ids = special_token
+                .tokens
+                .iter()
+                .map(|token| {
+                    tokenizer.token_to_id(token).ok_or_else(|| {
+                        format!("Special token `{}` is not part of the vocabulary", token)
+                    })
+                })
+                .collect::<StdResult<Vec<_>, String>>()?;
+        }
+        // The number of added tokens changes when unresolved ids get filled in
+        self.added_single = count_added(&self.single, Some(&self.special_tokens));
+        self.added_pair = count_added(&self.pair, Some(&self.special_tokens));
+        Ok(())
+    }
+}
+
 impl From<&str> for TemplateProcessingBuilderError {
     fn from(e: &str) -> Self {
         e.to_string().into()
@@ -371,6 +534,12 @@ impl PartialEq for TemplateProcessingBuilderError {
 struct TemplateProcessingDeserializer {
     single: Template,
     pair: Template,
+    #[serde(default)]
+    multi: Option<Template>,
+    #[serde(default)]
+    max_type_id: Option<u32>,
+    #[serde(default)]
+    type_id_overflow: TypeIdOverflow,
     special_tokens: Tokens,
 }
 impl From<TemplateProcessingDeserializer> for TemplateProcessing {
@@ -380,6 +549,9 @@ impl From<TemplateProcessingDeserializer> for TemplateProcessing {
         Self {
             single: t.single,
             pair: t.pair,
+            multi: t.multi,
+            max_type_id: t.max_type_id,
+            type_id_overflow: t.type_id_overflow,
             added_single,
             added_pair,
             special_tokens: t.special_tokens,
@@ -437,38 +609,61 @@ impl TemplateProcessingBuilder {
             return Err("Template for `pair` must use both sequences".into());
         }
 
-        let check = |sp| {
-            let exist = self
-                .special_tokens
-                .as_ref()
-                .map_or(false, |map| map.0.contains_key(sp));
-
-            match exist {
-                false => Some(sp),
-                true => None,
-            }
-        };
-
         let empty = [];
-        let missing: HashSet<&str> = self
+        let referenced: Vec<&str> = self
             .single
             .as_ref()
             .map_or(empty.iter(), |s| s.0.iter())
             .chain(self.pair.as_ref().map_or(empty.iter(), |s| s.0.iter()))
+            .chain(
+                self.multi
+                    .as_ref()
+                    .and_then(|s| s.as_ref())
+                    .map_or(empty.iter(), |s| s.0.iter()),
+            )
             .filter_map(|piece| match piece {
                 Piece::Sequence { .. } => None,
-                Piece::SpecialToken { id, .. } => check(id.as_ref()),
+                Piece::SpecialToken { id, .. } => Some(id.as_str()),
             })
-            .collect::<HashSet<_>>();
+            .collect();
 
-        if missing.is_empty() {
-            Ok(())
-        } else {
-            Err(format!(
+        let missing: HashSet<&str> = referenced
+            .iter()
+            .filter(|sp| {
+                !self
+                    .special_tokens
+                    .as_ref()
+                    .is_some_and(|map| map.0.contains_key(**sp))
+            })
+            .copied()
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!(
                 "Missing SpecialToken(s) with id(s) `{}`",
                 missing.iter().join(", ")
-            ))
+            ));
+        }
+
+        // A special token without any associated `tokens` can never resolve
+        // to ids, not even through `bind`
+        let unresolvable: HashSet<&str> = referenced
+            .iter()
+            .filter(|sp| {
+                self.special_tokens
+                    .as_ref()
+                    .and_then(|map| map.0.get(**sp))
+                    .is_some_and(|token| token.tokens.is_empty())
+            })
+            .copied()
+            .collect();
+        if !unresolvable.is_empty() {
+            return Err(format!(
+                "SpecialToken(s) with id(s) `{}` have no associated tokens, so no ids can \
+                 ever be resolved for them",
+                unresolvable.iter().join(", ")
+            ));
         }
+        Ok(())
     }
 }
 
@@ -477,6 +672,9 @@ impl Default for TemplateProcessing {
         Self {
             single: "$0".try_into().unwrap(),
             pair: "$1".try_into().unwrap(),
+            multi: None,
+            max_type_id: None,
+            type_id_overflow: TypeIdOverflow::default(),
             added_single: 0,
             added_pair: 0,
             special_tokens: Tokens::default(),
@@ -489,20 +687,69 @@ impl TemplateProcessing {
         TemplateProcessingBuilder::default()
     }
 
+    /// Handle a `type_id` exceeding `max_type_id`, according to `type_id_overflow`.
+    /// The `Error` case is checked upfront in `apply_template`, so it clamps here.
+    fn resolve_type_id(&self, type_id: u32) -> u32 {
+        match self.max_type_id {
+            Some(max) if type_id > max => match self.type_id_overflow {
+                TypeIdOverflow::Clamp | TypeIdOverflow::Error => max,
+                TypeIdOverflow::Wrap => type_id % (max + 1),
+            },
+            _ => type_id,
+        }
+    }
+
     fn apply_template(
         &self,
         template: &[Piece],
         mut encodings: Vec<Encoding>,
         add_special_tokens: bool,
     ) -> Result<Vec<Encoding>> {
+        if let (Some(max), TypeIdOverflow::Error) = (self.max_type_id, &self.type_id_overflow) {
+            let overflowed = template
+                .iter()
+                .map(|piece| match piece {
+                    Piece::Sequence { type_id, .. } | Piece::SpecialToken { type_id, .. } => {
+                        *type_id
+                    }
+                })
+                .find(|type_id| *type_id > max);
+            if let Some(type_id) = overflowed {
+                return Err(format!(
+                    "Template uses type_id {} but `max_type_id` is {}",
+                    type_id, max
+                )
+                .into());
+            }
+        }
+        if add_special_tokens {
+            for piece in template {
+                if let Piece::SpecialToken { id, .. } = piece {
+                    if self
+                        .special_tokens
+                        .0
+                        .get(id)
+                        .is_some_and(|token| token.is_unresolved())
+                    {
+                        return Err(format!(
+                            "The ids of the special token `{}` are not resolved yet: provide \
+                             them to the builder, or resolve them with `bind`",
+                            id
+                        )
+                        .into());
+                    }
+                }
+            }
+        }
         let final_encodings: Vec<Encoding> = template
             .iter()
             .flat_map(|piece| {
                 match piece {
                     Piece::Sequence { id, type_id } => {
-                        let i = usize::from(*id != Sequence::A);
+                        let i = id.index();
+                        let type_id = self.resolve_type_id(*type_id);
                         let encoding = &mut encodings[i];
-                        encoding.set_type_ids(vec![*type_id; encoding.len()]);
+                        encoding.set_type_ids(vec![type_id; encoding.len()]);
                         encoding.set_sequence_id(i);
                         Some(encoding.clone())
                     }
@@ -510,10 +757,11 @@ impl TemplateProcessing {
                         if add_special_tokens {
                             let tok = &self.special_tokens.0[id]; // We already checked existance above
                             let len = tok.ids.len();
+                            let type_id = self.resolve_type_id(*type_id);
 
                             let encoding = Encoding::new(
                                 tok.ids.clone(),
-                                std::iter::repeat(*type_id).take(len).collect(),
+                                std::iter::repeat(type_id).take(len).collect(),
                                 tok.tokens.clone(),
                                 // words
                                 std::iter::repeat(None).take(len).collect(),
@@ -624,10 +872,41 @@ impl PostProcessor for TemplateProcessing {
         //     _ => return Err(Box::new(ProcessorError::InvalidEncodingsVecLength)),
         // };
         let template = match encodings.len() {
-            2 => &self.pair.0,
+            0 => return Err("TemplateProcessing expects at least one sequence".into()),
             1 => &self.single.0,
-            _ => todo!(),
+            2 => &self.pair.0,
+            _ => {
+                &self
+                    .multi
+                    .as_ref()
+                    .ok_or_else(|| {
+                        format!(
+                            "TemplateProcessing received {} sequences but no `multi` template \
+                             was provided",
+                            encodings.len()
+                        )
+                    })?
+                    .0
+            }
         };
+        // Make sure the template does not reference a missing sequence
+        let required = template
+            .iter()
+            .filter_map(|piece| match piece {
+                Piece::Sequence { id, .. } => Some(id.index()),
+                Piece::SpecialToken { .. } => None,
+            })
+            .max();
+        if let Some(required) = required {
+            if required >= encodings.len() {
+                return Err(format!(
+                    "Template references sequence {} but only {} sequences were given",
+                    required,
+                    encodings.len()
+                )
+                .into());
+            }
+        }
         let encodings = self.apply_template(template, encodings, add_special_tokens)?;
         Ok(encodings)
     }
@@ -704,7 +983,21 @@ mod tests {
             }),
             "$:1".try_into()
         );
-        assert!(Piece::try_from("$C:1").is_err());
+        assert_eq!(
+            Ok(Piece::Sequence {
+                id: Sequence::Nth(2),
+                type_id: 1
+            }),
+            "$C:1".try_into()
+        );
+        assert_eq!(
+            Ok(Piece::Sequence {
+                id: Sequence::Nth(3),
+                type_id: 0
+            }),
+            "$d".try_into()
+        );
+        assert!(Piece::try_from("$AB:1").is_err());
         assert!(Piece::try_from("$A:").is_err());
     }
 
@@ -833,6 +1126,55 @@ mod tests {
         assert!(processor == err_a || processor == err_b);
     }
 
+    #[test]
+    fn bind_resolves_special_token_ids() {
+        use crate::models::wordlevel::WordLevel;
+        use crate::{AddedToken, Token, Tokenizer};
+
+        // The special tokens are declared by name only: their ids are not
+        // known yet
+        let mut processor = TemplateProcessing::builder()
+            .try_single("[CLS] $0 [SEP]")
+            .unwrap()
+            .special_tokens(vec!["[CLS]", "[SEP]"])
+            .build()
+            .unwrap();
+        assert_eq!(processor.added_tokens(false), 0);
+
+        // Processing fails cleanly instead of producing a wrong encoding
+        let encoding = Encoding::from_tokens(vec![Token::new(12, "Hello".into(), (0, 5))], 0);
+        let err = processor.process(encoding.clone(), None, true).unwrap_err();
+        assert!(err.to_string().contains("resolve them with `bind`"));
+
+        // Binding resolves the ids from the tokenizer's vocabulary
+        let mut tokenizer = Tokenizer::new(WordLevel::default());
+        tokenizer.add_special_tokens(&[
+            AddedToken::from("[SEP]", true),
+            AddedToken::from("[CLS]", true),
+        ]);
+        processor.bind(&tokenizer).unwrap();
+        assert_eq!(processor.added_tokens(false), 2);
+
+        let single = processor.process(encoding, None, true).unwrap();
+        assert_eq!(single.get_ids(), &[1, 12, 0]);
+        assert_eq!(single.get_tokens(), &["[CLS]", "Hello", "[SEP]"]);
+
+        // Binding against a vocabulary missing one of the tokens fails
+        let empty_tokenizer = Tokenizer::new(WordLevel::default());
+        assert!(processor.bind(&empty_tokenizer).is_err());
+
+        // A special token without any associated tokens could never be
+        // resolved, so it is rejected at build time
+        let unresolvable = TemplateProcessing::builder()
+            .try_single("[CLS] $0")
+            .unwrap()
+            .special_tokens(vec![
+                SpecialToken::new("[CLS]".into(), vec![], vec![]).unwrap()
+            ])
+            .build();
+        assert!(unresolvable.is_err());
+    }
+
     #[test]
     fn template_processing() {
         let processor = tests::get_bert_template();
@@ -1074,6 +1416,94 @@ mod tests {
         assert_eq!(pair_encoding.token_to_sequence(5), Some(1));
         assert_eq!(pair_encoding.token_to_sequence(6), None);
     }
+    #[test]
+    fn template_processing_multi() {
+        use crate::Token;
+        let processor = TemplateProcessing::builder()
+            .try_single("[CLS] $0 [SEP]")
+            .unwrap()
+            .try_pair("[CLS] $A:0 [SEP] $B:1 [SEP]")
+            .unwrap()
+            .try_multi("[CLS] $A:0 [SEP]:0 $B:1 [SEP]:1 $C:2 [SEP]:2")
+            .unwrap()
+            .special_tokens(vec![("[CLS]", 1), ("[SEP]", 0)])
+            .build()
+            .unwrap();
+
+        let query = Encoding::from_tokens(vec![Token::new(12, "query".into(), (0, 5))], 0);
+        let title = Encoding::from_tokens(vec![Token::new(13, "title".into(), (0, 5))], 0);
+        let body = Encoding::from_tokens(vec![Token::new(14, "body".into(), (0, 4))], 0);
+
+        let encodings = processor
+            .process_encodings(vec![query, title, body], true)
+            .unwrap();
+        let encoding = Encoding::merge(encodings, false);
+        assert_eq!(encoding.get_ids(), &[1, 12, 0, 13, 0, 14, 0]);
+        assert_eq!(encoding.get_type_ids(), &[0, 0, 0, 1, 1, 2, 2]);
+        assert_eq!(encoding.token_to_sequence(1), Some(0));
+        assert_eq!(encoding.token_to_sequence(3), Some(1));
+        assert_eq!(encoding.token_to_sequence(5), Some(2));
+
+        // Without a `multi` template, more than two sequences is an error
+        let processor = tests::get_bert_template();
+        let query = Encoding::from_tokens(vec![Token::new(12, "query".into(), (0, 5))], 0);
+        let title = Encoding::from_tokens(vec![Token::new(13, "title".into(), (0, 5))], 0);
+        let body = Encoding::from_tokens(vec![Token::new(14, "body".into(), (0, 4))], 0);
+        assert!(processor
+            .process_encodings(vec![query, title, body], true)
+            .is_err());
+    }
+
+    #[test]
+    fn template_processing_type_id_overflow() {
+        use crate::Token;
+        let build = |overflow: TypeIdOverflow| {
+            TemplateProcessing::builder()
+                .try_single("[CLS] $0 [SEP]")
+                .unwrap()
+                .try_pair("[CLS] $A:0 [SEP] $B:1 [SEP]")
+                .unwrap()
+                .try_multi("[CLS] $A:0 [SEP]:0 $B:1 [SEP]:1 $C:2 [SEP]:2")
+                .unwrap()
+                .special_tokens(vec![("[CLS]", 1), ("[SEP]", 0)])
+                .max_type_id(1)
+                .type_id_overflow(overflow)
+                .build()
+                .unwrap()
+        };
+        let encodings = || {
+            vec![
+                Encoding::from_tokens(vec![Token::new(12, "query".into(), (0, 5))], 0),
+                Encoding::from_tokens(vec![Token::new(13, "title".into(), (0, 5))], 0),
+                Encoding::from_tokens(vec![Token::new(14, "body".into(), (0, 4))], 0),
+            ]
+        };
+
+        // `type_id == 2` is clamped down to `max_type_id`
+        let processor = build(TypeIdOverflow::Clamp);
+        let encoding = Encoding::merge(
+            processor.process_encodings(encodings(), true).unwrap(),
+            false,
+        );
+        assert_eq!(encoding.get_type_ids(), &[0, 0, 0, 1, 1, 1, 1]);
+
+        // `type_id == 2` wraps around to 0
+        let processor = build(TypeIdOverflow::Wrap);
+        let encoding = Encoding::merge(
+            processor.process_encodings(encodings(), true).unwrap(),
+            false,
+        );
+        assert_eq!(encoding.get_type_ids(), &[0, 0, 0, 1, 1, 0, 0]);
+
+        // Or we can refuse to process altogether
+        let processor = build(TypeIdOverflow::Error);
+        assert!(processor.process_encodings(encodings(), true).is_err());
+        // The `pair` template stays within bounds, so it is still fine
+        let mut encodings = encodings();
+        encodings.truncate(2);
+        assert!(processor.process_encodings(encodings, true).is_ok());
+    }
+
     #[test]
     fn pair_must_use_both_sequences() {
         let processor = TemplateProcessing::builder()
@@ -2,7 +2,7 @@ use super::{
     normalizer::Range, Model, NormalizedString, Normalizer, Offsets, PreTokenizedString, Token,
 };
 use aho_corasick::{AhoCorasick, AhoCorasickBuilder, MatchKind};
-use regex::Regex;
+use regex::{Regex, RegexSet};
 use serde::{ser::SerializeSeq, Deserialize, Serialize, Serializer};
 use std::collections::{HashMap, HashSet};
 
@@ -25,6 +25,12 @@ pub struct AddedToken {
     pub normalized: bool,
     /// Whether this token is special
     pub special: bool,
+    /// Whether this token should match regardless of (ASCII) casing
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub case_insensitive: bool,
+    /// Whether the content is a regex pattern matching the token instead of a literal string
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_regex: bool,
 }
 
 impl AddedToken {
@@ -72,6 +78,39 @@ impl AddedToken {
         self.special = special;
         self
     }
+    /// Specify whether this token should match regardless of casing (ASCII only), so that
+    /// `<SEP>` and `<sep>` in the input both get extracted. Depending on `normalized`, the
+    /// matching happens on the raw or normalized string.
+    #[must_use]
+    pub fn case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+    /// Specify whether the content is a regex pattern rather than a literal string, so that
+    /// for example `\d{4}-\d{2}-\d{2}` can map any date-looking input to a single placeholder
+    /// token id
+    #[must_use]
+    pub fn is_regex(mut self, is_regex: bool) -> Self {
+        self.is_regex = is_regex;
+        self
+    }
+    /// Expand this token into a family of `count` tokens, replacing every
+    /// `{n}` placeholder of the content with 0, 1, ... `count - 1`, e.g.
+    /// `<extra_id_{n}>` into `<extra_id_0>` ... A content without a
+    /// placeholder gets the index appended. Every other option is copied
+    /// over to each token of the family.
+    pub fn family(&self, count: usize) -> Vec<AddedToken> {
+        (0..count)
+            .map(|n| Self {
+                content: if self.content.contains("{n}") {
+                    self.content.replace("{n}", &n.to_string())
+                } else {
+                    format!("{}{}", self.content, n)
+                },
+                ..self.clone()
+            })
+            .collect()
+    }
 }
 impl Default for AddedToken {
     fn default() -> Self {
@@ -82,6 +121,8 @@ impl Default for AddedToken {
             rstrip: false,
             normalized: true,
             special: false,
+            case_insensitive: false,
+            is_regex: false,
         }
     }
 }
@@ -93,36 +134,119 @@ impl std::hash::Hash for AddedToken {
 }
 
 type MatchingSet = (AhoCorasick, Vec<u32>);
+type RegexMatchingSet = (RegexSet, Vec<Regex>, Vec<u32>);
 
 lazy_static! {
     static ref STARTS_WITH_WORD: Regex = Regex::new(r"^\w").unwrap();
     static ref ENDS_WITH_WORD: Regex = Regex::new(r"\w$").unwrap();
     static ref RIGHTMOST_SPACE_AT_START: Regex = Regex::new(r"^\s*").unwrap();
     static ref LEFTMOST_SPACE_AT_END: Regex = Regex::new(r"\s*$").unwrap();
+    static ref ASCII_RIGHTMOST_SPACE_AT_START: Regex = Regex::new(r"^[ \t\n\r\x0B\x0C]*").unwrap();
+    static ref ASCII_LEFTMOST_SPACE_AT_END: Regex = Regex::new(r"[ \t\n\r\x0B\x0C]*$").unwrap();
+}
+
+/// What counts as a word character when enforcing the `single_word` option
+/// of an [`AddedToken`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum WordBoundaries {
+    /// Word characters are the Unicode `\w` class: letters, digits, and the
+    /// underscore. This is the historical behavior.
+    #[default]
+    Word,
+    /// Word characters are anything but whitespace, so matches glued to
+    /// punctuation are rejected too
+    NonWhitespace,
+}
+
+/// Which whitespace class the `lstrip`/`rstrip` options of an [`AddedToken`]
+/// consume around matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum WhitespaceClass {
+    /// The Unicode `\s` class, including e.g. the no-break space. This is the
+    /// historical behavior.
+    #[default]
+    Unicode,
+    /// ASCII whitespace only, for parity with byte-oriented implementations
+    Ascii,
 }
 
-fn ends_with_word(sentence: &str) -> bool {
-    ENDS_WITH_WORD.is_match(sentence)
+/// Options controlling how added tokens get extracted from the input. They
+/// are serialized in the tokenizer file when they differ from the defaults,
+/// so a tokenizer relying on them behaves consistently across platforms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ExtractionOptions {
+    /// What counts as a word character when enforcing `single_word`
+    #[serde(default)]
+    pub single_word_boundaries: WordBoundaries,
+    /// Which whitespace class `lstrip`/`rstrip` consume
+    #[serde(default)]
+    pub strip_whitespace: WhitespaceClass,
 }
 
-fn starts_with_word(sentence: &str) -> bool {
-    STARTS_WITH_WORD.is_match(sentence)
+fn ends_with_word(sentence: &str, boundaries: WordBoundaries) -> bool {
+    match boundaries {
+        WordBoundaries::Word => ENDS_WITH_WORD.is_match(sentence),
+        WordBoundaries::NonWhitespace => sentence
+            .chars()
+            .next_back()
+            .is_some_and(|c| !c.is_whitespace()),
+    }
+}
+
+fn starts_with_word(sentence: &str, boundaries: WordBoundaries) -> bool {
+    match boundaries {
+        WordBoundaries::Word => STARTS_WITH_WORD.is_match(sentence),
+        WordBoundaries::NonWhitespace => {
+            sentence.chars().next().is_some_and(|c| !c.is_whitespace())
+        }
+    }
 }
 
-fn space_leftmost_at_end(sentence: &str) -> usize {
-    if let Some(match_) = LEFTMOST_SPACE_AT_END.find(sentence) {
+fn space_leftmost_at_end(sentence: &str, class: WhitespaceClass) -> usize {
+    let regex = match class {
+        WhitespaceClass::Unicode => &*LEFTMOST_SPACE_AT_END,
+        WhitespaceClass::Ascii => &*ASCII_LEFTMOST_SPACE_AT_END,
+    };
+    if let Some(match_) = regex.find(sentence) {
         match_.start()
     } else {
         sentence.len()
     }
 }
-fn space_rightmost_at_start(sentence: &str) -> usize {
-    if let Some(match_) = RIGHTMOST_SPACE_AT_START.find(sentence) {
+fn space_rightmost_at_start(sentence: &str, class: WhitespaceClass) -> usize {
+    let regex = match class {
+        WhitespaceClass::Unicode => &*RIGHTMOST_SPACE_AT_START,
+        WhitespaceClass::Ascii => &*ASCII_RIGHTMOST_SPACE_AT_START,
+    };
+    if let Some(match_) = regex.find(sentence) {
         match_.end()
     } else {
         0
     }
 }
+
+/// Compute the Levenshtein distance between the two strings, returning `None`
+/// as soon as it is guaranteed to exceed `max`
+fn levenshtein_within(a: &str, b: &str, max: usize) -> Option<usize> {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut cur = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != cb);
+            cur.push((prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1));
+        }
+        if cur.iter().min().is_some_and(|&d| d > max) {
+            return None;
+        }
+        prev = cur;
+    }
+    (prev[b.len()] <= max).then_some(prev[b.len()])
+}
 ///
 /// A vocabulary built on top of the Model
 ///
@@ -160,9 +284,36 @@ pub struct AddedVocabulary {
     split_trie: MatchingSet,
     /// A RegexSet containing all the normalized patterns used to split on AddedTokens
     split_normalized_trie: MatchingSet,
+    /// Same as `split_trie`, for the tokens matching case-insensitively
+    split_trie_ci: MatchingSet,
+    /// Same as `split_normalized_trie`, for the tokens matching case-insensitively
+    split_normalized_trie_ci: MatchingSet,
+    /// Same as `split_trie`, for the regex-defined tokens
+    split_regex: RegexMatchingSet,
+    /// Same as `split_normalized_trie`, for the regex-defined tokens
+    split_normalized_regex: RegexMatchingSet,
 
     /// Whether or not special tokens should be splitted when encoding. This is equivalent to ignoring them
     encode_special_tokens: bool,
+
+    /// The maximum edit distance allowed when extracting special tokens, so that markers
+    /// slightly corrupted by OCR (e.g. `[SEP ]`, or `[SEР]` with a Cyrillic Р) are still
+    /// recognized. 0 (the default) means exact matching only.
+    fuzzy_max_distance: usize,
+
+    /// The options controlling how the added tokens get extracted from the input
+    extraction_options: ExtractionOptions,
+
+    /// Whether tokens were added with [`AddedVocabulary::add_tokens_lazy`] since the
+    /// matching automata were last rebuilt
+    pending_refresh: bool,
+
+    /// The highest id assigned to an added token so far, kept up to date on
+    /// insertion so that assigning a new id does not scan the whole vocabulary
+    max_added_id: Option<u32>,
+    /// An id range pre-reserved with [`AddedVocabulary::reserve_ids`] for tokens
+    /// that will be added later
+    reserved_ids: Option<std::ops::Range<u32>>,
 }
 
 impl AddedVocabulary {
@@ -175,6 +326,12 @@ impl AddedVocabulary {
             .match_kind(MatchKind::LeftmostLongest)
             .build::<_, &&[u8]>([])
             .expect("The normalized trie should build correctly");
+        let empty_trie = || {
+            AhoCorasickBuilder::new()
+                .match_kind(MatchKind::LeftmostLongest)
+                .build::<_, &&[u8]>([])
+                .expect("The trie should build correctly")
+        };
         Self {
             added_tokens_map: HashMap::new(),
             added_tokens_map_r: HashMap::new(),
@@ -183,7 +340,16 @@ impl AddedVocabulary {
             special_tokens_set: HashSet::new(),
             split_trie: (trie, vec![]),
             split_normalized_trie: (normalized_trie, vec![]),
+            split_trie_ci: (empty_trie(), vec![]),
+            split_normalized_trie_ci: (empty_trie(), vec![]),
+            split_regex: (RegexSet::empty(), vec![], vec![]),
+            split_normalized_regex: (RegexSet::empty(), vec![], vec![]),
             encode_special_tokens: false,
+            fuzzy_max_distance: 0,
+            extraction_options: ExtractionOptions::default(),
+            pending_refresh: false,
+            max_added_id: None,
+            reserved_ids: None,
         }
     }
     /// Size of the additional vocabulary
@@ -240,6 +406,28 @@ impl AddedVocabulary {
         self.encode_special_tokens
     }
 
+    /// Allow special tokens to be extracted with up to `max_distance` character
+    /// edits. Only special tokens are matched fuzzily: regular added tokens are
+    /// usually natural words, for which approximate matches would be wrong far
+    /// more often than not.
+    pub fn set_fuzzy_matching(&mut self, max_distance: usize) {
+        self.fuzzy_max_distance = max_distance;
+    }
+
+    pub fn get_fuzzy_matching(&self) -> usize {
+        self.fuzzy_max_distance
+    }
+
+    /// Set the options controlling how the added tokens get extracted from
+    /// the input. They only affect matching, so no automata rebuild is needed.
+    pub fn set_extraction_options(&mut self, options: ExtractionOptions) {
+        self.extraction_options = options;
+    }
+
+    pub fn get_extraction_options(&self) -> ExtractionOptions {
+        self.extraction_options
+    }
+
     /// Check if a token is a special token
     pub fn is_special_token(&self, token: &str) -> bool {
         self.special_tokens_set.contains(token)
@@ -262,8 +450,68 @@ impl AddedVocabulary {
         model: &impl Model,
         normalizer: Option<&N>,
     ) -> usize {
+        self.add_tokens_bulk(tokens.to_vec(), model, normalizer)
+    }
+
+    /// The id the next new token will be assigned. This lets embedding matrices
+    /// be sized before actually adding the tokens, see
+    /// [`AddedVocabulary::reserve_ids`].
+    pub fn next_free_id(&self, model: &impl Model) -> u32 {
+        let next = self
+            .max_added_id
+            .map_or(model.get_vocab_size() as u32, |max| {
+                if (max >= model.get_vocab_size() as u32) || model.get_vocab_size() == 0 {
+                    max + 1
+                } else {
+                    model.get_vocab_size() as u32
+                }
+            });
+        match &self.reserved_ids {
+            Some(range) if next < range.start => range.start,
+            _ => next,
+        }
+    }
+
+    /// Pre-reserve an id range for `count` future added tokens, returning it.
+    /// The next `count` new tokens are assigned ids from this range in order,
+    /// so an embedding matrix sized to `range.end` ahead of time is guaranteed
+    /// to cover them, as long as the model vocabulary does not grow past the
+    /// range in the meantime.
+    pub fn reserve_ids(&mut self, model: &impl Model, count: u32) -> std::ops::Range<u32> {
+        let start = self.next_free_id(model);
+        let range = start..start + count;
+        self.reserved_ids = Some(range.clone());
+        range
+    }
+
+    /// The id range currently reserved with [`AddedVocabulary::reserve_ids`]
+    pub fn get_reserved_ids(&self) -> Option<&std::ops::Range<u32>> {
+        self.reserved_ids.as_ref()
+    }
+
+    /// Add many tokens to the vocabulary at once. Equivalent to calling
+    /// [`AddedVocabulary::add_tokens`], but takes ownership of the tokens to
+    /// avoid cloning them, and deduplicates and assigns ids in constant time
+    /// per token, which matters when adding tens of thousands of tokens.
+    pub fn add_tokens_bulk<N: Normalizer>(
+        &mut self,
+        tokens: Vec<AddedToken>,
+        model: &impl Model,
+        normalizer: Option<&N>,
+    ) -> usize {
+        let added = self.add_tokens_lazy(tokens, model);
+        self.rebuild(model, normalizer);
+        added
+    }
+
+    /// Add tokens to the vocabulary without rebuilding the matching automata,
+    /// which dominates the cost of adding to a large vocabulary. The tokens
+    /// are not extracted until [`AddedVocabulary::rebuild`] gets called, so
+    /// callers adding thousands of tokens in several batches should add them
+    /// all lazily and rebuild once at the end.
+    pub fn add_tokens_lazy(&mut self, tokens: Vec<AddedToken>, model: &impl Model) -> usize {
         // Handle special tokens (if any)
-        for token in tokens {
+        for token in &tokens {
             if token.special
                 && !token.content.is_empty()
                 && !self.special_tokens_set.contains(&token.content)
@@ -273,10 +521,15 @@ impl AddedVocabulary {
             }
         }
 
-        // Then we delegate to `add_tokens`, that will take care of refreshing added tokens too.
+        let total = tokens.len();
+        let mut next_id = self.next_free_id(model);
         let mut ignored = 0;
         for token in tokens {
-            if token.content.is_empty() || self.added_tokens_map_r.values().any(|val| val == token)
+            if token.content.is_empty()
+                || self
+                    .added_tokens_map
+                    .get(&token.content)
+                    .is_some_and(|id| self.added_tokens_map_r.get(id) == Some(&token))
             {
                 ignored += 1;
                 continue;
@@ -285,39 +538,42 @@ impl AddedVocabulary {
             let new_id = if let Some(new_id) = self.token_to_id(&token.content, model) {
                 new_id
             } else {
-                self.added_tokens_map.values().cloned().max().map_or(
-                    model.get_vocab_size() as u32,
-                    |max| {
-                        if (max >= model.get_vocab_size() as u32) || model.get_vocab_size() == 0 {
-                            max + 1
-                        } else {
-                            model.get_vocab_size() as u32
-                        }
-                    },
-                )
+                let new_id = next_id;
+                next_id += 1;
+                new_id
             };
+            self.max_added_id = Some(self.max_added_id.map_or(new_id, |max| max.max(new_id)));
             // Make sure we modify the previous entry
             self.added_tokens_map
                 .entry(token.content.clone())
                 .and_modify(|old_id| *old_id = new_id)
-                .or_insert_with(|| new_id);
+                .or_insert(new_id);
             // Update the current revert operation
-            self.added_tokens_map_r
-                .entry(new_id)
-                .and_modify(|t| *t = token.clone())
-                .or_insert_with(|| token.clone());
-            // Make sure to remove previous entry (if the token gets a new id)
+            self.added_tokens_map_r.insert(new_id, token.clone());
 
             // Finally add the token to the classic set if special
             if !self.special_tokens_set.contains(&token.content) {
-                self.added_tokens.push(token.clone());
+                self.added_tokens.push(token);
             }
         }
 
-        self.refresh_added_tokens(model, normalizer);
+        self.pending_refresh = true;
 
         // Return the number of added tokens
-        tokens.len() - ignored
+        total - ignored
+    }
+
+    /// Rebuild the matching automata from the current tokens, after one or
+    /// more [`AddedVocabulary::add_tokens_lazy`] calls
+    pub fn rebuild<N: Normalizer>(&mut self, model: &impl Model, normalizer: Option<&N>) {
+        self.refresh_added_tokens(model, normalizer);
+        self.pending_refresh = false;
+    }
+
+    /// Whether tokens were added lazily since the matching automata were
+    /// last rebuilt, in which case they are not extracted yet
+    pub fn needs_rebuild(&self) -> bool {
+        self.pending_refresh
     }
 
     /// Reconstruct our internal RegexSet when new tokens are added to the vocabulary.
@@ -326,7 +582,7 @@ impl AddedVocabulary {
     /// non-normalized string, and one matching against the normalized one.
     fn refresh_added_tokens<N: Normalizer>(&mut self, model: &impl Model, normalizer: Option<&N>) {
         type TupleTokenId<'a> = (&'a AddedToken, u32);
-        let (normalized, non_normalized): (Vec<TupleTokenId>, Vec<TupleTokenId>) = self
+        let (regex, literal): (Vec<TupleTokenId>, Vec<TupleTokenId>) = self
             .special_tokens
             .iter()
             .chain(self.added_tokens.iter())
@@ -337,31 +593,75 @@ impl AddedVocabulary {
                         .expect("Missing additional token"),
                 )
             })
-            .partition(|(token, _)| token.normalized);
+            .partition(|(token, _)| token.is_regex);
+        let (normalized, non_normalized): (Vec<TupleTokenId>, Vec<TupleTokenId>) =
+            literal.into_iter().partition(|(token, _)| token.normalized);
+
+        let build_trie =
+            |tokens: Vec<TupleTokenId>, case_insensitive: bool, normalize: bool| -> MatchingSet {
+                let (tokens, ids): (Vec<&AddedToken>, Vec<u32>) = tokens.into_iter().unzip();
+                let patterns: Vec<_> = tokens
+                    .iter()
+                    .map(|token| {
+                        let mut content = NormalizedString::from(token.content.as_ref());
+                        if normalize {
+                            if let Some(n) = normalizer {
+                                n.normalize(&mut content).unwrap();
+                            }
+                        }
+                        content
+                    })
+                    .collect();
+                let trie = AhoCorasickBuilder::new()
+                    .match_kind(MatchKind::LeftmostLongest)
+                    .ascii_case_insensitive(case_insensitive)
+                    .build(patterns.iter().map(|content| content.get()))
+                    .expect("Failed to build tried when refreshing tokens");
+                (trie, ids)
+            };
 
-        let (tokens, ids): (Vec<&AddedToken>, Vec<u32>) = non_normalized.into_iter().unzip();
-        let trie = AhoCorasickBuilder::new()
-            .match_kind(MatchKind::LeftmostLongest)
-            .build(tokens.iter().map(|token| &token.content))
-            .expect("Failed to build tried when refreshing tokens");
-        self.split_trie = (trie, ids);
+        let (ci, exact): (Vec<TupleTokenId>, Vec<TupleTokenId>) = non_normalized
+            .into_iter()
+            .partition(|(token, _)| token.case_insensitive);
+        self.split_trie = build_trie(exact, false, false);
+        self.split_trie_ci = build_trie(ci, true, false);
 
-        let (ntokens, nids): (Vec<&AddedToken>, Vec<u32>) = normalized.into_iter().unzip();
-        let patterns: Vec<_> = ntokens
-            .iter()
-            .map(|token| {
-                let mut content = NormalizedString::from(token.content.as_ref());
-                if let Some(n) = normalizer {
-                    n.normalize(&mut content).unwrap();
-                }
-                content
-            })
-            .collect();
-        let normalized_trie = AhoCorasickBuilder::new()
-            .match_kind(MatchKind::LeftmostLongest)
-            .build(patterns.iter().map(|content| content.get()))
-            .expect("Failed to build tried when refreshing tokens (normalized)");
-        self.split_normalized_trie = (normalized_trie, nids);
+        let (nci, nexact): (Vec<TupleTokenId>, Vec<TupleTokenId>) = normalized
+            .into_iter()
+            .partition(|(token, _)| token.case_insensitive);
+        self.split_normalized_trie = build_trie(nexact, false, true);
+        self.split_normalized_trie_ci = build_trie(nci, true, true);
+
+        // The regex-defined tokens are combined in a RegexSet, along with the individual
+        // regexes to locate the actual matches. We don't normalize the patterns themselves,
+        // the `normalized` option only decides which representation they match against.
+        let build_regex_set = |tokens: Vec<TupleTokenId>| -> RegexMatchingSet {
+            let (tokens, ids): (Vec<&AddedToken>, Vec<u32>) = tokens.into_iter().unzip();
+            let patterns: Vec<String> = tokens
+                .iter()
+                .map(|token| {
+                    if token.case_insensitive {
+                        format!("(?i:{})", token.content)
+                    } else {
+                        token.content.clone()
+                    }
+                })
+                .collect();
+            let set =
+                RegexSet::new(&patterns).expect("Failed to build regex set when refreshing tokens");
+            let regexes = patterns
+                .iter()
+                .map(|pattern| {
+                    Regex::new(pattern).expect("Failed to build regex when refreshing tokens")
+                })
+                .collect();
+            (set, regexes, ids)
+        };
+
+        let (nregex, regex): (Vec<TupleTokenId>, Vec<TupleTokenId>) =
+            regex.into_iter().partition(|(token, _)| token.normalized);
+        self.split_regex = build_regex_set(regex);
+        self.split_normalized_regex = build_regex_set(nregex);
     }
 
     /// Find any AddedToken in the given sentence, using the provided MatchingSet.
@@ -373,14 +673,112 @@ impl AddedVocabulary {
             return vec![(None, (0, 0))];
         }
 
+        let matches = split_re
+            .0
+            .find_iter(sentence)
+            .map(|mat| (mat.start(), mat.end(), split_re.1[mat.pattern()]));
+        self.matches_to_splits(sentence, matches)
+    }
+
+    /// Find any regex-defined AddedToken in the given sentence. The combined RegexSet tells
+    /// us which patterns match at all before we look for their actual positions, and any
+    /// overlapping matches are resolved leftmost-longest, like with the trie-based matching.
+    fn find_regex_matches(
+        &self,
+        sentence: &str,
+        matching: &RegexMatchingSet,
+    ) -> Vec<(Option<u32>, Offsets)> {
+        if sentence.is_empty() {
+            return vec![(None, (0, 0))];
+        }
+
+        let (set, regexes, ids) = matching;
+        let mut matches: Vec<(usize, usize, u32)> = vec![];
+        for index in set.matches(sentence) {
+            for mat in regexes[index].find_iter(sentence) {
+                matches.push((mat.start(), mat.end(), ids[index]));
+            }
+        }
+        // Leftmost-longest: earlier starts win, and longer matches win ties
+        matches.sort_unstable_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+        let mut last_stop = 0;
+        matches.retain(|(start, stop, _)| {
+            if *start >= last_stop {
+                last_stop = *stop;
+                true
+            } else {
+                false
+            }
+        });
+
+        self.matches_to_splits(sentence, matches)
+    }
+
+    /// Find special tokens matching the sentence within `fuzzy_max_distance` character
+    /// edits. Overlapping candidates are resolved by distance first (exact-most wins),
+    /// then leftmost-longest, like with the trie-based matching.
+    fn find_fuzzy_matches(&self, sentence: &str) -> Vec<(Option<u32>, Offsets)> {
+        if sentence.is_empty() {
+            return vec![(None, (0, 0))];
+        }
+
+        let max_d = self.fuzzy_max_distance;
+        // Byte index of every char boundary, including the end of the sentence
+        let positions: Vec<usize> = sentence
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(sentence.len()))
+            .collect();
+        let n = positions.len() - 1;
+
+        let mut matches: Vec<(usize, usize, u32, usize)> = vec![];
+        for token in &self.special_tokens {
+            let id = self.added_tokens_map[&token.content];
+            let m = token.content.chars().count();
+            for start in 0..n {
+                let min_len = std::cmp::max(m.saturating_sub(max_d), 1);
+                let max_len = std::cmp::min(m + max_d, n - start);
+                for len in min_len..=max_len {
+                    let candidate = &sentence[positions[start]..positions[start + len]];
+                    if let Some(d) = levenshtein_within(candidate, &token.content, max_d) {
+                        matches.push((positions[start], positions[start + len], id, d));
+                    }
+                }
+            }
+        }
+
+        // Smallest distance first, then leftmost-longest
+        matches.sort_unstable_by(|a, b| a.0.cmp(&b.0).then(a.3.cmp(&b.3)).then(b.1.cmp(&a.1)));
+        let mut last_stop = 0;
+        matches.retain(|(start, stop, _, _)| {
+            if *start >= last_stop {
+                last_stop = *stop;
+                true
+            } else {
+                false
+            }
+        });
+
+        self.matches_to_splits(
+            sentence,
+            matches
+                .into_iter()
+                .map(|(start, stop, id, _)| (start, stop, id)),
+        )
+    }
+
+    /// Apply the AddedToken options (single_word, lstrip, rstrip, ...) to the given
+    /// non-overlapping matches, and turn them into a list of splits covering the entire
+    /// input string.
+    fn matches_to_splits(
+        &self,
+        sentence: &str,
+        matches: impl IntoIterator<Item = (usize, usize, u32)>,
+    ) -> Vec<(Option<u32>, Offsets)> {
         let mut start_offset = 0;
         let mut splits = vec![];
 
-        for mat in split_re.0.find_iter(sentence) {
-            let mut start = mat.start();
-            let mut stop = mat.end();
-            let aho_id = mat.pattern();
-            let id = split_re.1[aho_id];
+        for (mut start, mut stop, id) in matches {
             let added_token = &self.added_tokens_map_r.get(&id).unwrap();
 
             if self.encode_special_tokens && self.special_tokens_set.contains(&added_token.content)
@@ -389,8 +787,10 @@ impl AddedVocabulary {
             }
 
             if added_token.single_word {
-                let start_space = start == 0 || !ends_with_word(&sentence[..start]);
-                let stop_space = stop == sentence.len() || !starts_with_word(&sentence[stop..]);
+                let boundaries = self.extraction_options.single_word_boundaries;
+                let start_space = start == 0 || !ends_with_word(&sentence[..start], boundaries);
+                let stop_space =
+                    stop == sentence.len() || !starts_with_word(&sentence[stop..], boundaries);
 
                 if !stop_space || !start_space {
                     // Discard not single word
@@ -399,7 +799,10 @@ impl AddedVocabulary {
             }
             if added_token.lstrip {
                 // This will be strictly inferior to start and in correct sentence offset
-                let newstart = space_leftmost_at_end(&sentence[..start]);
+                let newstart = space_leftmost_at_end(
+                    &sentence[..start],
+                    self.extraction_options.strip_whitespace,
+                );
 
                 // The previous match could have already matched those spaces
                 // Ignore them if it's already matched
@@ -408,7 +811,10 @@ impl AddedVocabulary {
             if added_token.rstrip {
                 // This will starting a the stop+1 character, so we need
                 // to add the previous stop value
-                stop += space_rightmost_at_start(&sentence[stop..])
+                stop += space_rightmost_at_start(
+                    &sentence[stop..],
+                    self.extraction_options.strip_whitespace,
+                )
             }
             if start_offset < start {
                 splits.push((None, (start_offset, start)));
@@ -433,7 +839,18 @@ impl AddedVocabulary {
         sentence: NormalizedString,
         split_re: &MatchingSet,
     ) -> Vec<(NormalizedString, Option<Vec<Token>>)> {
-        self.find_matches(sentence.get(), split_re)
+        let matches = self.find_matches(sentence.get(), split_re);
+        self.split_on_matches(sentence, matches)
+    }
+
+    /// Split the input sentence on the given list of splits, attaching the matching Token
+    /// to the splits that correspond to an AddedToken
+    fn split_on_matches(
+        &self,
+        sentence: NormalizedString,
+        matches: Vec<(Option<u32>, Offsets)>,
+    ) -> Vec<(NormalizedString, Option<Vec<Token>>)> {
+        matches
             .into_iter()
             .map(|(id, byte_offsets)| {
                 let slice = sentence
@@ -467,6 +884,27 @@ impl AddedVocabulary {
         pretokenized
             .split(|_, sequence| Ok(self.split_with_indices(sequence, &self.split_trie)))
             .expect("AddedVocabulary bad split");
+        if !self.split_trie_ci.1.is_empty() {
+            pretokenized
+                .split(|_, sequence| Ok(self.split_with_indices(sequence, &self.split_trie_ci)))
+                .expect("AddedVocabulary bad split");
+        }
+        if !self.split_regex.2.is_empty() {
+            pretokenized
+                .split(|_, sequence| {
+                    let matches = self.find_regex_matches(sequence.get(), &self.split_regex);
+                    Ok(self.split_on_matches(sequence, matches))
+                })
+                .expect("AddedVocabulary bad split");
+        }
+        if self.fuzzy_max_distance > 0 {
+            pretokenized
+                .split(|_, sequence| {
+                    let matches = self.find_fuzzy_matches(sequence.get());
+                    Ok(self.split_on_matches(sequence, matches))
+                })
+                .expect("AddedVocabulary bad split");
+        }
 
         // <s> normalized = False
         // "I read a book   <s>Hey" -> "I read a book", "   <s>", "Hey"
@@ -487,6 +925,23 @@ impl AddedVocabulary {
                 Ok(self.split_with_indices(sequence, &self.split_normalized_trie))
             })
             .expect("AddedVocabulary bad split");
+        // The previous pass already normalized every remaining split
+        if !self.split_normalized_trie_ci.1.is_empty() {
+            pretokenized
+                .split(|_, sequence| {
+                    Ok(self.split_with_indices(sequence, &self.split_normalized_trie_ci))
+                })
+                .expect("AddedVocabulary bad split");
+        }
+        if !self.split_normalized_regex.2.is_empty() {
+            pretokenized
+                .split(|_, sequence| {
+                    let matches =
+                        self.find_regex_matches(sequence.get(), &self.split_normalized_regex);
+                    Ok(self.split_on_matches(sequence, matches))
+                })
+                .expect("AddedVocabulary bad split");
+        }
 
         // ["I read a book", "   <s>", "Hey"] -> ["▁I read a book", "▁   <s>", "▁Hey"]
         // ["▁I read a book", "▁   <s>", "▁Hey"] -> [.., "▁   ", "<s>", "▁Hey"]
@@ -605,6 +1060,14 @@ mod tests {
         {
             unimplemented!()
         }
+        fn feed_weighted<I, S, F>(&mut self, _iterator: I, _process: F) -> Result<()>
+        where
+            I: Iterator<Item = (S, f64)> + Send,
+            S: AsRef<str> + Send,
+            F: Fn(&str) -> Result<Vec<String>> + Sync,
+        {
+            unimplemented!()
+        }
     }
 
     impl Model for ModelMock {
@@ -677,6 +1140,70 @@ mod tests {
         assert_eq!(vocab.get_added_tokens_decoder()[&0], added_token);
     }
 
+    #[test]
+    fn can_add_tokens_in_bulk() {
+        let model = ModelMock::new(&[("test", 0), ("tost", 1)]);
+        let mut vocab = AddedVocabulary::new();
+        let normalizer: Option<&NormalizerWrapper> = None;
+
+        // Deduplicates within the batch, and against previously added tokens
+        assert_eq!(
+            vocab.add_tokens_bulk(
+                vec![
+                    AddedToken::from("added_token_1", false),
+                    AddedToken::from("added_token_2", false),
+                    AddedToken::from("added_token_1", false),
+                ],
+                &model,
+                normalizer
+            ),
+            2
+        );
+        assert_eq!(
+            vocab.add_tokens_bulk(
+                vec![AddedToken::from("added_token_2", false)],
+                &model,
+                normalizer
+            ),
+            0
+        );
+
+        // Ids are assigned contiguously after the model vocabulary
+        assert_eq!(vocab.get_vocab()["added_token_1"], 2);
+        assert_eq!(vocab.get_vocab()["added_token_2"], 3);
+        assert_eq!(vocab.next_free_id(&model), 4);
+
+        // The matching structures are refreshed like with `add_tokens`
+        let result = vocab.extract_and_normalize(normalizer, "added_token_1 test");
+        assert_eq!(
+            simplify_output(&result),
+            vec![("added_token_1", Some(vec![2])), (" test", None)]
+        );
+    }
+
+    #[test]
+    fn can_reserve_ids() {
+        let model = ModelMock::new(&[("test", 0), ("tost", 1)]);
+        let mut vocab = AddedVocabulary::new();
+        let normalizer: Option<&NormalizerWrapper> = None;
+
+        // The reserved range starts right after the model vocabulary, and the
+        // next added tokens consume it in order
+        assert_eq!(vocab.reserve_ids(&model, 3), 2..5);
+        assert_eq!(vocab.get_reserved_ids(), Some(&(2..5)));
+        vocab.add_tokens(
+            &[
+                AddedToken::from("added_token_1", false),
+                AddedToken::from("added_token_2", false),
+            ],
+            &model,
+            normalizer,
+        );
+        assert_eq!(vocab.get_vocab()["added_token_1"], 2);
+        assert_eq!(vocab.get_vocab()["added_token_2"], 3);
+        assert_eq!(vocab.next_free_id(&model), 4);
+    }
+
     #[test]
     fn can_add_special_tokens() {
         let model = ModelMock::new(&[("test", 0), ("tost", 1)]);
@@ -843,6 +1370,166 @@ mod tests {
         );
     }
 
+    #[test]
+    fn extraction_options() {
+        let model = ModelMock::new(&[]);
+        let normalizer: Option<&NormalizerWrapper> = None;
+        let mut vocab = AddedVocabulary::new();
+
+        vocab.add_tokens(
+            &[
+                AddedToken::from("ony", false).single_word(true),
+                AddedToken::from("<tok>", false).lstrip(true),
+            ],
+            &model,
+            normalizer,
+        );
+
+        // With the default `\w` boundaries, a match next to punctuation is a
+        // single word; treating any non-whitespace as a word char rejects it
+        let result = vocab.extract_and_normalize(normalizer, "!ony");
+        assert_eq!(
+            simplify_output(&result),
+            vec![("!", None), ("ony", Some(vec![0]))]
+        );
+        vocab.set_extraction_options(ExtractionOptions {
+            single_word_boundaries: WordBoundaries::NonWhitespace,
+            ..Default::default()
+        });
+        let result = vocab.extract_and_normalize(normalizer, "!ony");
+        assert_eq!(simplify_output(&result), vec![("!ony", None)]);
+
+        // `lstrip` consumes the no-break space by default, but not with the
+        // ASCII whitespace class
+        let result = vocab.extract_and_normalize(normalizer, "a\u{00A0}<tok>");
+        assert_eq!(
+            simplify_output(&result),
+            vec![("a", None), ("\u{00A0}<tok>", Some(vec![1]))]
+        );
+        vocab.set_extraction_options(ExtractionOptions {
+            strip_whitespace: WhitespaceClass::Ascii,
+            ..Default::default()
+        });
+        let result = vocab.extract_and_normalize(normalizer, "a\u{00A0}<tok>");
+        assert_eq!(
+            simplify_output(&result),
+            vec![("a\u{00A0}", None), ("<tok>", Some(vec![1]))]
+        );
+    }
+
+    #[test]
+    fn lazy_rebuild() {
+        let model = ModelMock::new(&[]);
+        let normalizer: Option<&NormalizerWrapper> = None;
+        let mut vocab = AddedVocabulary::new();
+
+        // Lazily added tokens get their ids right away, but are not
+        // extracted until the automata get rebuilt
+        vocab.add_tokens_lazy(vec![AddedToken::from("[CLS]", true)], &model);
+        vocab.add_tokens_lazy(vec![AddedToken::from("[SEP]", true)], &model);
+        assert!(vocab.needs_rebuild());
+        assert_eq!(vocab.token_to_id("[SEP]", &model), Some(1));
+        let result = vocab.extract_and_normalize(normalizer, "[CLS] hello");
+        assert_eq!(simplify_output(&result), vec![("[CLS] hello", None)]);
+
+        vocab.rebuild(&model, normalizer);
+        assert!(!vocab.needs_rebuild());
+        let result = vocab.extract_and_normalize(normalizer, "[CLS] hello");
+        assert_eq!(
+            simplify_output(&result),
+            vec![("[CLS]", Some(vec![0])), (" hello", None)]
+        );
+    }
+
+    #[test]
+    fn case_insensitive_matching() {
+        let model = ModelMock::new(&[]);
+        let mut vocab = AddedVocabulary::new();
+        let normalizer: Option<&NormalizerWrapper> = None;
+
+        vocab.add_special_tokens(
+            &[
+                AddedToken::from("<SEP>", true).case_insensitive(true),
+                AddedToken::from("<CLS>", true),
+            ],
+            &model,
+            normalizer,
+        );
+
+        // `<SEP>` matches in any casing, `<CLS>` only matches exactly
+        let result = vocab.extract_and_normalize(normalizer, "<cls> Hello <Sep> you <SEP> <CLS>");
+        assert_eq!(
+            simplify_output(&result),
+            vec![
+                ("<cls> Hello ", None),
+                ("<Sep>", Some(vec![0])),
+                (" you ", None),
+                ("<SEP>", Some(vec![0])),
+                (" ", None),
+                ("<CLS>", Some(vec![1])),
+            ]
+        );
+
+        // Also works on the normalized string for normalized tokens
+        let normalizer = Lowercase;
+        let mut vocab = AddedVocabulary::new();
+        vocab.add_tokens(
+            &[AddedToken::from("Name", false).case_insensitive(true)],
+            &model,
+            Some(&normalizer),
+        );
+        let result = vocab.extract_and_normalize(Some(&normalizer), "My NAME is");
+        assert_eq!(
+            simplify_output(&result),
+            vec![("my ", None), ("name", Some(vec![0])), (" is", None)]
+        );
+    }
+
+    #[test]
+    fn regex_added_tokens() {
+        let model = ModelMock::new(&[]);
+        let mut vocab = AddedVocabulary::new();
+        let normalizer: Option<&NormalizerWrapper> = None;
+
+        vocab.add_special_tokens(
+            &[AddedToken::from(r"\d{4}-\d{2}-\d{2}", true).is_regex(true)],
+            &model,
+            normalizer,
+        );
+
+        // Every date-looking input maps to the same placeholder id
+        let result = vocab.extract_and_normalize(normalizer, "born 2024-01-31, died 2190-12-01");
+        assert_eq!(
+            simplify_output(&result),
+            vec![
+                ("born ", None),
+                ("2024-01-31", Some(vec![0])),
+                (", died ", None),
+                ("2190-12-01", Some(vec![0])),
+            ]
+        );
+
+        // Overlapping matches from different patterns are resolved leftmost-longest
+        let mut vocab = AddedVocabulary::new();
+        vocab.add_tokens(
+            &[
+                AddedToken::from(r"\d+", false)
+                    .is_regex(true)
+                    .normalized(false),
+                AddedToken::from(r"\d+\.\d+", false)
+                    .is_regex(true)
+                    .normalized(false),
+            ],
+            &model,
+            normalizer,
+        );
+        let result = vocab.extract_and_normalize(normalizer, "pi is 3.14");
+        assert_eq!(
+            simplify_output(&result),
+            vec![("pi is ", None), ("3.14", Some(vec![1]))]
+        );
+    }
+
     #[test]
     fn empty_matches() {
         let vocab = AddedVocabulary::new();
@@ -1029,4 +1716,47 @@ mod tests {
             vec![("my", Some(vec![0])), ("ä»Ĭ", Some(vec![1])),]
         );
     }
+
+    #[test]
+    fn test_levenshtein_within() {
+        assert_eq!(levenshtein_within("[SEP]", "[SEP]", 1), Some(0));
+        assert_eq!(levenshtein_within("[SEP ]", "[SEP]", 1), Some(1));
+        assert_eq!(levenshtein_within("[SEP]", "[CLS]", 2), None);
+        assert_eq!(levenshtein_within("[SEP]", "[CLS]", 3), Some(3));
+    }
+
+    #[test]
+    fn test_fuzzy_special_token_matching() {
+        let model = ModelMock::new(&[]);
+        let mut vocab = AddedVocabulary::new();
+        let normalizer: Option<&NormalizerWrapper> = None;
+        vocab.add_special_tokens(&[AddedToken::from("[SEP]", true)], &model, normalizer);
+
+        // Exact matching by default
+        let result = vocab.extract_and_normalize(normalizer, "Hello [SEP ]world");
+        assert_eq!(simplify_output(&result), vec![("Hello [SEP ]world", None)]);
+
+        vocab.set_fuzzy_matching(1);
+
+        // A spurious space is within distance 1
+        let result = vocab.extract_and_normalize(normalizer, "Hello [SEP ]world");
+        assert_eq!(
+            simplify_output(&result),
+            vec![("Hello ", None), ("[SEP ]", Some(vec![0])), ("world", None)]
+        );
+
+        // So is a Cyrillic Р instead of the Latin P
+        let result = vocab.extract_and_normalize(normalizer, "a[SEР]b");
+        assert_eq!(
+            simplify_output(&result),
+            vec![("a", None), ("[SEР]", Some(vec![0])), ("b", None)]
+        );
+
+        // Exact occurrences are still matched exactly
+        let result = vocab.extract_and_normalize(normalizer, "a[SEP]b");
+        assert_eq!(
+            simplify_output(&result),
+            vec![("a", None), ("[SEP]", Some(vec![0])), ("b", None)]
+        );
+    }
 }
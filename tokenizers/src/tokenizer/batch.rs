@@ -0,0 +1,199 @@
+//! A columnar view over a batch of encodings: one contiguous array per field
+//! plus row lengths, so that ML frameworks can consume whole batches without
+//! per-encoding conversions.
+
+use crate::tokenizer::{Encoding, Offsets};
+
+/// A batch of [`Encoding`]s in columnar form: the per-token fields of all the
+/// encodings are concatenated into contiguous arrays, and the rows delimited
+/// by [`EncodingBatch::lengths`]. Build one with `From<Vec<Encoding>>`, and
+/// slice the rows back out with [`EncodingBatch::ids`] and friends, without
+/// any copy.
+///
+/// Overflowing encodings are not included: truncate with overflow before
+/// batching if you need them as their own rows.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EncodingBatch {
+    /// The token ids of all the encodings, concatenated
+    pub ids: Vec<u32>,
+    /// The type ids of all the encodings, concatenated
+    pub type_ids: Vec<u32>,
+    /// The attention masks of all the encodings, concatenated
+    pub attention_mask: Vec<u32>,
+    /// The special tokens masks of all the encodings, concatenated
+    pub special_tokens_mask: Vec<u32>,
+    /// The offsets of all the encodings, concatenated
+    pub offsets: Vec<Offsets>,
+    /// The number of tokens of each encoding
+    pub lengths: Vec<usize>,
+    /// The start of each row in the concatenated arrays, with a final entry
+    /// holding the total length: `row_offsets[i]..row_offsets[i + 1]` is the
+    /// range of row `i`
+    pub row_offsets: Vec<usize>,
+}
+
+impl EncodingBatch {
+    /// The number of encodings in the batch
+    pub fn len(&self) -> usize {
+        self.lengths.len()
+    }
+
+    /// Whether the batch holds no encoding
+    pub fn is_empty(&self) -> bool {
+        self.lengths.is_empty()
+    }
+
+    fn row(&self, row: usize) -> std::ops::Range<usize> {
+        self.row_offsets[row]..self.row_offsets[row + 1]
+    }
+
+    /// The token ids of the given row
+    pub fn ids(&self, row: usize) -> &[u32] {
+        &self.ids[self.row(row)]
+    }
+
+    /// The type ids of the given row
+    pub fn type_ids(&self, row: usize) -> &[u32] {
+        &self.type_ids[self.row(row)]
+    }
+
+    /// The attention mask of the given row
+    pub fn attention_mask(&self, row: usize) -> &[u32] {
+        &self.attention_mask[self.row(row)]
+    }
+
+    /// The special tokens mask of the given row
+    pub fn special_tokens_mask(&self, row: usize) -> &[u32] {
+        &self.special_tokens_mask[self.row(row)]
+    }
+
+    /// The offsets of the given row
+    pub fn offsets(&self, row: usize) -> &[Offsets] {
+        &self.offsets[self.row(row)]
+    }
+}
+
+impl From<Vec<Encoding>> for EncodingBatch {
+    fn from(encodings: Vec<Encoding>) -> Self {
+        let total: usize = encodings.iter().map(|encoding| encoding.len()).sum();
+        let mut batch = EncodingBatch {
+            ids: Vec::with_capacity(total),
+            type_ids: Vec::with_capacity(total),
+            attention_mask: Vec::with_capacity(total),
+            special_tokens_mask: Vec::with_capacity(total),
+            offsets: Vec::with_capacity(total),
+            lengths: Vec::with_capacity(encodings.len()),
+            row_offsets: Vec::with_capacity(encodings.len() + 1),
+        };
+        batch.row_offsets.push(0);
+        for encoding in encodings {
+            batch.lengths.push(encoding.len());
+            batch.row_offsets.push(batch.ids.len() + encoding.len());
+            batch.ids.extend_from_slice(encoding.get_ids());
+            batch.type_ids.extend_from_slice(encoding.get_type_ids());
+            batch
+                .attention_mask
+                .extend_from_slice(encoding.get_attention_mask());
+            batch
+                .special_tokens_mask
+                .extend_from_slice(encoding.get_special_tokens_mask());
+            batch.offsets.extend_from_slice(encoding.get_offsets());
+        }
+        batch
+    }
+}
+
+#[cfg(feature = "arrow")]
+mod arrow {
+    use super::EncodingBatch;
+    use arrow_array::{LargeListArray, UInt32Array, UInt64Array};
+    use arrow_buffer::OffsetBuffer;
+    use arrow_schema::{DataType, Field};
+    use std::sync::Arc;
+
+    impl EncodingBatch {
+        fn list_offsets(&self) -> OffsetBuffer<i64> {
+            OffsetBuffer::from_lengths(self.lengths.iter().copied())
+        }
+
+        fn list_array(&self, values: UInt32Array) -> LargeListArray {
+            LargeListArray::new(
+                Arc::new(Field::new_list_field(DataType::UInt32, false)),
+                self.list_offsets(),
+                Arc::new(values),
+                None,
+            )
+        }
+
+        /// The token ids as an Arrow large list array of `UInt32`, one list
+        /// per encoding
+        pub fn ids_array(&self) -> LargeListArray {
+            self.list_array(UInt32Array::from(self.ids.clone()))
+        }
+
+        /// The attention masks as an Arrow large list array of `UInt32`, one
+        /// list per encoding
+        pub fn attention_mask_array(&self) -> LargeListArray {
+            self.list_array(UInt32Array::from(self.attention_mask.clone()))
+        }
+
+        /// The offsets as two Arrow large list arrays of `UInt64`, holding the
+        /// start and end of each token respectively
+        pub fn offsets_arrays(&self) -> (LargeListArray, LargeListArray) {
+            let starts: Vec<u64> = self
+                .offsets
+                .iter()
+                .map(|(start, _)| *start as u64)
+                .collect();
+            let ends: Vec<u64> = self.offsets.iter().map(|(_, end)| *end as u64).collect();
+            let field = Arc::new(Field::new_list_field(DataType::UInt64, false));
+            (
+                LargeListArray::new(
+                    field.clone(),
+                    self.list_offsets(),
+                    Arc::new(UInt64Array::from(starts)),
+                    None,
+                ),
+                LargeListArray::new(
+                    field,
+                    self.list_offsets(),
+                    Arc::new(UInt64Array::from(ends)),
+                    None,
+                ),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::Token;
+
+    #[test]
+    fn columnar_batch() {
+        let encodings = vec![
+            Encoding::from_tokens(
+                vec![
+                    Token::new(0, "hello".into(), (0, 5)),
+                    Token::new(1, "world".into(), (6, 11)),
+                ],
+                0,
+            ),
+            Encoding::from_tokens(vec![Token::new(2, "!".into(), (0, 1))], 1),
+        ];
+
+        let batch = EncodingBatch::from(encodings);
+        assert_eq!(batch.len(), 2);
+        assert_eq!(batch.lengths, vec![2, 1]);
+        assert_eq!(batch.row_offsets, vec![0, 2, 3]);
+        assert_eq!(batch.ids, vec![0, 1, 2]);
+        assert_eq!(batch.ids(0), &[0, 1]);
+        assert_eq!(batch.ids(1), &[2]);
+        assert_eq!(batch.type_ids(1), &[1]);
+        assert_eq!(batch.attention_mask(0), &[1, 1]);
+        assert_eq!(batch.offsets(0), &[(0, 5), (6, 11)]);
+
+        assert!(EncodingBatch::from(vec![]).is_empty());
+    }
+}
@@ -0,0 +1,176 @@
+//! Load tokenizers from human-editable TOML or YAML configuration files.
+//!
+//! These hold the same pipeline definition as a `tokenizer.json` file, but are
+//! easier to review in git, and the `model` section may reference external
+//! vocabulary and merge files instead of inlining them:
+//!
+//! ```toml
+//! version = "1.0"
+//!
+//! [pre_tokenizer]
+//! type = "Whitespace"
+//!
+//! [model]
+//! type = "BPE"
+//! vocab_file = "vocab.json"
+//! merges_file = "merges.txt"
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::tokenizer::{Result, Tokenizer};
+
+/// The configuration formats understood by [`Tokenizer::from_config_str`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// The format matching the extension of the given file, e.g. `Yaml` for
+    /// `tokenizer.yaml`
+    fn from_extension(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Ok(Self::Json),
+            Some("toml") => Ok(Self::Toml),
+            Some("yaml") | Some("yml") => Ok(Self::Yaml),
+            _ => Err(format!(
+                "Cannot infer the configuration format of `{}`, expected a .json, .toml, .yaml or .yml file",
+                path.display()
+            )
+            .into()),
+        }
+    }
+
+    fn parse(&self, config: &str) -> Result<Value> {
+        Ok(match self {
+            Self::Json => serde_json::from_str(config)?,
+            Self::Toml => toml::from_str(config)?,
+            Self::Yaml => serde_yaml::from_str(config)?,
+        })
+    }
+}
+
+/// Inline the external files referenced by the `model` section, resolved
+/// relative to `base`: `vocab_file` points to a JSON file inserted as the
+/// `vocab` field, and `merges_file` to a text file with one merge per line
+/// inserted as the `merges` field
+fn resolve_file_references(config: &mut Value, base: &Path) -> Result<()> {
+    if let Some(model) = config.get_mut("model").and_then(|m| m.as_object_mut()) {
+        if let Some(file) = model.remove("vocab_file") {
+            let vocab = std::fs::read_to_string(file_path(&file, "vocab_file", base)?)?;
+            model.insert("vocab".into(), serde_json::from_str(&vocab)?);
+        }
+        if let Some(file) = model.remove("merges_file") {
+            let merges = std::fs::read_to_string(file_path(&file, "merges_file", base)?)?;
+            let merges: Vec<&str> = merges
+                .lines()
+                .filter(|line| !line.is_empty() && !line.starts_with("#version"))
+                .collect();
+            model.insert("merges".into(), serde_json::json!(merges));
+        }
+    }
+    Ok(())
+}
+
+fn file_path(file: &Value, key: &str, base: &Path) -> Result<PathBuf> {
+    let file = file
+        .as_str()
+        .ok_or_else(|| format!("`{}` must be a file path", key))?;
+    Ok(base.join(file))
+}
+
+impl Tokenizer {
+    /// Instantiate a new Tokenizer from the given configuration string, which
+    /// holds the same pipeline definition as a `tokenizer.json` file, in the
+    /// given format. The `model` section may reference external files through
+    /// `vocab_file` and `merges_file` instead of inlining them; such paths are
+    /// resolved relative to the current directory.
+    pub fn from_config_str(format: ConfigFormat, config: &str) -> Result<Self> {
+        Self::from_config_value(format.parse(config)?, Path::new(""))
+    }
+
+    /// Instantiate a new Tokenizer from the configuration file at the given
+    /// path, inferring the format from its extension (`.json`, `.toml`,
+    /// `.yaml` or `.yml`). External vocab/merge files are resolved relative to
+    /// the directory of the configuration file.
+    pub fn from_config_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let config = ConfigFormat::from_extension(path)?.parse(&std::fs::read_to_string(path)?)?;
+        Self::from_config_value(config, path.parent().unwrap_or_else(|| Path::new("")))
+    }
+
+    fn from_config_value(mut config: Value, base: &Path) -> Result<Self> {
+        resolve_file_references(&mut config, base)?;
+        Ok(serde_json::from_value(config)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_config_str() {
+        let config = r#"
+version: "1.0"
+pre_tokenizer:
+  type: Whitespace
+model:
+  type: BPE
+  unk_token: "<unk>"
+  vocab:
+    "<unk>": 0
+    a: 1
+    b: 2
+    ab: 3
+  merges:
+    - "a b"
+"#;
+        let tokenizer = Tokenizer::from_config_str(ConfigFormat::Yaml, config).unwrap();
+        let encoding = tokenizer.encode("ab a", false).unwrap();
+        assert_eq!(encoding.get_ids(), &[3, 1]);
+    }
+
+    #[test]
+    fn test_from_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("vocab.json"),
+            r#"{"<unk>": 0, "a": 1, "b": 2, "ab": 3}"#,
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("merges.txt"), "#version: 0.2\na b\n").unwrap();
+        std::fs::write(
+            dir.path().join("tokenizer.toml"),
+            r#"
+version = "1.0"
+
+[pre_tokenizer]
+type = "Whitespace"
+
+[model]
+type = "BPE"
+unk_token = "<unk>"
+vocab_file = "vocab.json"
+merges_file = "merges.txt"
+"#,
+        )
+        .unwrap();
+
+        let tokenizer = Tokenizer::from_config_file(dir.path().join("tokenizer.toml")).unwrap();
+        let encoding = tokenizer.encode("ab b", false).unwrap();
+        assert_eq!(encoding.get_ids(), &[3, 2]);
+
+        // The external files were inlined: the tokenizer serializes as a
+        // self-contained tokenizer.json
+        let serialized = tokenizer.to_string(false).unwrap();
+        assert!(serialized.contains(r#""merges":[["a","b"]]"#));
+
+        assert!(Tokenizer::from_config_file(dir.path().join("tokenizer.ini")).is_err());
+    }
+}
@@ -4,8 +4,23 @@ use crate::utils::padding::PaddingDirection;
 use crate::utils::truncation::TruncationDirection;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::convert::TryInto;
 use std::ops::Range;
 
+/// A word-level view over an [`Encoding`]: one entry per word of an input
+/// sequence, built by [`Encoding::get_word_views`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WordView {
+    /// The index of the word in its input sequence
+    pub word_id: u32,
+    /// The word, as sliced from the input sequence
+    pub word: String,
+    /// The offsets of the word in the input sequence
+    pub offsets: Offsets,
+    /// The tokens covering the word, with the form (start_token, end_token + 1)
+    pub tokens: (usize, usize),
+}
+
 /// Represents the output of a `Tokenizer`.
 #[derive(Default, PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct Encoding {
@@ -23,6 +38,16 @@ pub struct Encoding {
     special_tokens_mask: Vec<u32>,
     /// Mask identifying padding tokens for the attention mechanism
     attention_mask: Vec<u32>,
+    /// Model-assigned score of each token, filled by `encode_scored`: empty
+    /// unless the encoding was scored. Tokens the model cannot score (added
+    /// tokens, padding) hold `None`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    scores: Vec<Option<f64>>,
+    /// Metadata tag id attached by the pre-tokenizer to the split each token
+    /// comes from (e.g. detected language, is_code): empty unless some split
+    /// was tagged. Tokens from untagged splits hold `None`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<Option<u32>>,
     /// A list of overflowing Encoding generated when we got truncated
     overflowing: Vec<Encoding>,
     /// Ranges of tokens covered by each sequence. If this is empty we consider
@@ -50,6 +75,8 @@ impl Encoding {
             offsets,
             special_tokens_mask,
             attention_mask,
+            scores: vec![],
+            tags: vec![],
             overflowing,
             sequence_ranges,
         }
@@ -64,6 +91,8 @@ impl Encoding {
             offsets: Vec::with_capacity(len),
             special_tokens_mask: Vec::with_capacity(len),
             attention_mask: Vec::with_capacity(len),
+            scores: vec![],
+            tags: vec![],
             overflowing: vec![],
             sequence_ranges: HashMap::new(),
         }
@@ -93,6 +122,8 @@ impl Encoding {
             type_ids: vec![type_id; length],
             attention_mask: vec![1; length],
             special_tokens_mask: vec![0; length],
+            scores: vec![],
+            tags: vec![],
             overflowing: vec![],
             sequence_ranges: HashMap::new(),
         }
@@ -164,6 +195,24 @@ impl Encoding {
         &mut self.offsets
     }
 
+    /// The offsets of the encoding, adjusted to be non-decreasing: any token
+    /// whose offsets would go backwards (like the `(0, 0)` of an inserted
+    /// special token) is given the empty span ending where the previous token
+    /// ends. Downstream span math can rely on this view without special-casing
+    /// the inserted tokens
+    pub fn offsets_monotonic(&self) -> Vec<Offsets> {
+        let mut prev_end = 0;
+        self.offsets
+            .iter()
+            .map(|&(start, end)| {
+                let start = start.max(prev_end);
+                let end = end.max(start);
+                prev_end = end;
+                (start, end)
+            })
+            .collect()
+    }
+
     pub fn get_special_tokens_mask(&self) -> &[u32] {
         &self.special_tokens_mask
     }
@@ -172,6 +221,41 @@ impl Encoding {
         &self.attention_mask
     }
 
+    /// Get the attention mask as bytes, using one byte per token.
+    pub fn get_attention_mask_u8(&self) -> Vec<u8> {
+        self.attention_mask.iter().map(|&v| v as u8).collect()
+    }
+
+    /// Get the attention mask as packed bits, 8 tokens per byte, least significant bit first.
+    /// The last byte is zero-padded when the sequence length is not a multiple of 8.
+    pub fn get_attention_mask_packed(&self) -> Vec<u8> {
+        let mut packed = vec![0u8; self.attention_mask.len().div_ceil(8)];
+        for (i, &v) in self.attention_mask.iter().enumerate() {
+            if v != 0 {
+                packed[i / 8] |= 1 << (i % 8);
+            }
+        }
+        packed
+    }
+
+    /// The model-assigned score of each token, when this encoding was produced
+    /// by `encode_scored`; empty otherwise
+    pub fn get_scores(&self) -> &[Option<f64>] {
+        &self.scores
+    }
+
+    pub fn set_scores(&mut self, scores: Vec<Option<f64>>) {
+        self.scores = scores;
+    }
+
+    pub fn get_tags(&self) -> &[Option<u32>] {
+        &self.tags
+    }
+
+    pub fn set_tags(&mut self, tags: Vec<Option<u32>>) {
+        self.tags = tags;
+    }
+
     pub fn get_overflowing(&self) -> &Vec<Encoding> {
         &self.overflowing
     }
@@ -253,6 +337,39 @@ impl Encoding {
         }
     }
 
+    /// Build a word-level view of the given sequence of this encoding: one
+    /// entry per word, with the word sliced out of the original `sequence`,
+    /// its offsets and the tokens that cover it. This is computed from the
+    /// word ids in a single pass, so sequence-labeling pipelines don't have to
+    /// reconstruct it with repeated [`Encoding::word_to_tokens`] calls. The
+    /// offsets must be byte offsets for the words to be sliced out correctly.
+    pub fn get_word_views(&self, sequence_id: usize, sequence: &str) -> Vec<WordView> {
+        let mut views: Vec<WordView> = vec![];
+        for token in self.sequence_range(sequence_id) {
+            if let Some(word_id) = self.words[token] {
+                match views.last_mut() {
+                    Some(view) if view.word_id == word_id => {
+                        view.offsets.1 = self.offsets[token].1;
+                        view.tokens.1 = token + 1;
+                    }
+                    _ => views.push(WordView {
+                        word_id,
+                        word: String::new(),
+                        offsets: self.offsets[token],
+                        tokens: (token, token + 1),
+                    }),
+                }
+            }
+        }
+        for view in &mut views {
+            view.word = sequence
+                .get(view.offsets.0..view.offsets.1)
+                .unwrap_or_default()
+                .to_owned();
+        }
+        views
+    }
+
     /// Get the offsets of the word at the given index in the input sequence.
     pub fn word_to_chars(&self, word: u32, sequence_id: usize) -> Option<Offsets> {
         self.word_to_tokens(word, sequence_id)
@@ -292,6 +409,46 @@ impl Encoding {
             .map(|pos| sequence_range.start + pos)
     }
 
+    /// Get the tokens that contain the given chars, one entry per position.
+    ///
+    /// Unlike repeated [`Encoding::char_to_token`] calls, each position is
+    /// resolved with a binary search over the offsets, which are sorted
+    /// within a sequence for any encoding produced by the pipeline.
+    pub fn chars_to_tokens(&self, positions: &[usize], sequence_id: usize) -> Vec<Option<usize>> {
+        let sequence_range = self.sequence_range(sequence_id);
+        let offsets = match self.offsets.get(sequence_range.clone()) {
+            Some(offsets) => offsets,
+            None => return vec![None; positions.len()],
+        };
+
+        positions
+            .iter()
+            .map(|pos| {
+                // The first token ending after the position is the only one
+                // that may contain it
+                let token = offsets.partition_point(|(_, end)| *end <= *pos);
+                match offsets.get(token) {
+                    Some((start, end)) if (*start..*end).contains(pos) => {
+                        Some(sequence_range.start + token)
+                    }
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    /// Get the char span covered by the given range of tokens, from the start
+    /// of the first one to the end of the last one. Returns `None` when the
+    /// range is empty or out of bounds.
+    pub fn tokens_to_spans(&self, range: Range<usize>) -> Option<Offsets> {
+        if range.is_empty() {
+            return None;
+        }
+        let (start, _) = self.offsets.get(range.start)?;
+        let (_, end) = self.offsets.get(range.end - 1)?;
+        Some((*start, *end))
+    }
+
     /// Get the word that contains the given char.
     pub fn char_to_word(&self, pos: usize, sequence_id: usize) -> Option<u32> {
         Some(
@@ -301,6 +458,24 @@ impl Encoding {
         )
     }
 
+    /// Slice the scores, which stay empty when the encoding was never scored
+    fn slice_scores(&self, start: usize, stop: usize) -> Vec<Option<f64>> {
+        if self.scores.is_empty() {
+            vec![]
+        } else {
+            self.scores[start..stop].to_vec()
+        }
+    }
+
+    /// Slice the tags, which stay empty when no split was tagged
+    fn slice_tags(&self, start: usize, stop: usize) -> Vec<Option<u32>> {
+        if self.tags.is_empty() {
+            vec![]
+        } else {
+            self.tags[start..stop].to_vec()
+        }
+    }
+
     /// Truncate the current `Encoding`.
     ///
     /// Panics if `stride >= max_len`
@@ -362,6 +537,8 @@ impl Encoding {
             offsets: self.offsets[start..stop].to_vec(),
             special_tokens_mask: self.special_tokens_mask[start..stop].to_vec(),
             attention_mask: self.attention_mask[start..stop].to_vec(),
+            scores: self.slice_scores(start, stop),
+            tags: self.slice_tags(start, stop),
             overflowing: vec![],
             sequence_ranges: HashMap::new(),
         };
@@ -380,6 +557,8 @@ impl Encoding {
                 offsets: self.offsets[start..stop].to_vec(),
                 special_tokens_mask: self.special_tokens_mask[start..stop].to_vec(),
                 attention_mask: self.attention_mask[start..stop].to_vec(),
+                scores: self.slice_scores(start, stop),
+                tags: self.slice_tags(start, stop),
                 overflowing: vec![],
                 sequence_ranges: HashMap::new(),
             });
@@ -434,6 +613,22 @@ impl Encoding {
         // Finish by merging ourself with the other encoding
         let original_self_len = self.len(); // Must be before any modification to self.ids
 
+        // Keep the scores aligned with the tokens when only one side has them
+        if !self.scores.is_empty() || !pair.scores.is_empty() {
+            self.scores.resize(original_self_len, None);
+            let mut pair_scores = pair.scores;
+            pair_scores.resize(pair.ids.len(), None);
+            self.scores.extend(pair_scores);
+        }
+
+        // Same for the tags
+        if !self.tags.is_empty() || !pair.tags.is_empty() {
+            self.tags.resize(original_self_len, None);
+            let mut pair_tags = pair.tags;
+            pair_tags.resize(pair.ids.len(), None);
+            self.tags.extend(pair_tags);
+        }
+
         self.sequence_ranges
             .extend(pair.sequence_ranges.into_iter().map(|(seq_id, range)| {
                 (
@@ -512,6 +707,18 @@ impl Encoding {
                     .map(|_| (0, 0))
                     .chain(self.offsets.drain(..))
                     .collect();
+                if !self.scores.is_empty() {
+                    self.scores = (0..pad_length)
+                        .map(|_| None)
+                        .chain(self.scores.drain(..))
+                        .collect();
+                }
+                if !self.tags.is_empty() {
+                    self.tags = (0..pad_length)
+                        .map(|_| None)
+                        .chain(self.tags.drain(..))
+                        .collect();
+                }
                 self.sequence_ranges
                     .iter_mut()
                     .for_each(|(_seq_id, range)| {
@@ -527,8 +734,204 @@ impl Encoding {
                 self.attention_mask.extend((0..pad_length).map(|_| 0));
                 self.special_tokens_mask.extend((0..pad_length).map(|_| 1));
                 self.offsets.extend((0..pad_length).map(|_| (0, 0)));
+                if !self.scores.is_empty() {
+                    self.scores.extend((0..pad_length).map(|_| None));
+                }
+                if !self.tags.is_empty() {
+                    self.tags.extend((0..pad_length).map(|_| None));
+                }
+            }
+        }
+    }
+
+    /// Serialize this Encoding to a compact, versioned binary layout, much
+    /// smaller and faster to parse than JSON, so precomputed tokenizations of
+    /// large corpora can be cached to disk. Read back with
+    /// [`Encoding::from_bytes`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![BINARY_FORMAT_VERSION];
+        self.write_into(&mut bytes);
+        bytes
+    }
+
+    /// Deserialize an Encoding written by [`Encoding::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> crate::Result<Self> {
+        let mut bytes = bytes;
+        match take_u8(&mut bytes)? {
+            BINARY_FORMAT_VERSION => {}
+            version => {
+                return Err(format!("Unsupported Encoding format version: {}", version).into())
+            }
+        }
+        let encoding = Self::read_from(&mut bytes)?;
+        if !bytes.is_empty() {
+            return Err("Trailing bytes after the serialized Encoding".into());
+        }
+        Ok(encoding)
+    }
+
+    fn write_into(&self, out: &mut Vec<u8>) {
+        put_usize(out, self.ids.len());
+        for id in &self.ids {
+            put_u32(out, *id);
+        }
+        for type_id in &self.type_ids {
+            put_u32(out, *type_id);
+        }
+        for token in &self.tokens {
+            put_usize(out, token.len());
+            out.extend_from_slice(token.as_bytes());
+        }
+        for word in &self.words {
+            put_opt_u32(out, *word);
+        }
+        for (start, end) in &self.offsets {
+            put_usize(out, *start);
+            put_usize(out, *end);
+        }
+        // The masks only ever hold 0 or 1, a single byte each
+        for mask in &self.special_tokens_mask {
+            out.push((*mask != 0) as u8);
+        }
+        for mask in &self.attention_mask {
+            out.push((*mask != 0) as u8);
+        }
+        out.push(!self.scores.is_empty() as u8);
+        for score in &self.scores {
+            match score {
+                Some(score) => {
+                    out.push(1);
+                    out.extend_from_slice(&score.to_le_bytes());
+                }
+                None => out.push(0),
+            }
+        }
+        out.push(!self.tags.is_empty() as u8);
+        for tag in &self.tags {
+            put_opt_u32(out, *tag);
+        }
+        put_usize(out, self.sequence_ranges.len());
+        for (seq_id, range) in &self.sequence_ranges {
+            put_usize(out, *seq_id);
+            put_usize(out, range.start);
+            put_usize(out, range.end);
+        }
+        put_usize(out, self.overflowing.len());
+        for overflowing in &self.overflowing {
+            overflowing.write_into(out);
+        }
+    }
+
+    fn read_from(bytes: &mut &[u8]) -> crate::Result<Self> {
+        let len = take_usize(bytes)?;
+        // Each entry consumes at least one byte, so this also bounds the
+        // allocations below on corrupted input
+        if len > bytes.len() {
+            return Err("Truncated Encoding bytes".into());
+        }
+        let mut encoding = Self::with_capacity(len);
+        for _ in 0..len {
+            encoding.ids.push(take_u32(bytes)?);
+        }
+        for _ in 0..len {
+            encoding.type_ids.push(take_u32(bytes)?);
+        }
+        for _ in 0..len {
+            let token_len = take_usize(bytes)?;
+            let token = take_slice(bytes, token_len)?;
+            encoding.tokens.push(std::str::from_utf8(token)?.to_owned());
+        }
+        for _ in 0..len {
+            encoding.words.push(take_opt_u32(bytes)?);
+        }
+        for _ in 0..len {
+            let start = take_usize(bytes)?;
+            let end = take_usize(bytes)?;
+            encoding.offsets.push((start, end));
+        }
+        for _ in 0..len {
+            encoding.special_tokens_mask.push(take_u8(bytes)? as u32);
+        }
+        for _ in 0..len {
+            encoding.attention_mask.push(take_u8(bytes)? as u32);
+        }
+        if take_u8(bytes)? != 0 {
+            for _ in 0..len {
+                encoding.scores.push(match take_u8(bytes)? {
+                    0 => None,
+                    _ => Some(f64::from_le_bytes(
+                        take_slice(bytes, 8)?.try_into().unwrap(),
+                    )),
+                });
             }
         }
+        if take_u8(bytes)? != 0 {
+            for _ in 0..len {
+                encoding.tags.push(take_opt_u32(bytes)?);
+            }
+        }
+        for _ in 0..take_usize(bytes)? {
+            let seq_id = take_usize(bytes)?;
+            let start = take_usize(bytes)?;
+            let end = take_usize(bytes)?;
+            encoding.sequence_ranges.insert(seq_id, start..end);
+        }
+        for _ in 0..take_usize(bytes)? {
+            encoding.overflowing.push(Self::read_from(bytes)?);
+        }
+        Ok(encoding)
+    }
+}
+
+/// The version byte leading every [`Encoding::to_bytes`] output, bumped
+/// whenever the layout below changes
+const BINARY_FORMAT_VERSION: u8 = 1;
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_usize(out: &mut Vec<u8>, value: usize) {
+    out.extend_from_slice(&(value as u64).to_le_bytes());
+}
+
+fn put_opt_u32(out: &mut Vec<u8>, value: Option<u32>) {
+    match value {
+        Some(value) => {
+            out.push(1);
+            put_u32(out, value);
+        }
+        None => out.push(0),
+    }
+}
+
+fn take_slice<'a>(bytes: &mut &'a [u8], len: usize) -> crate::Result<&'a [u8]> {
+    if len > bytes.len() {
+        return Err("Truncated Encoding bytes".into());
+    }
+    let (taken, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Ok(taken)
+}
+
+fn take_u8(bytes: &mut &[u8]) -> crate::Result<u8> {
+    Ok(take_slice(bytes, 1)?[0])
+}
+
+fn take_u32(bytes: &mut &[u8]) -> crate::Result<u32> {
+    Ok(u32::from_le_bytes(
+        take_slice(bytes, 4)?.try_into().unwrap(),
+    ))
+}
+
+fn take_usize(bytes: &mut &[u8]) -> crate::Result<usize> {
+    Ok(u64::from_le_bytes(take_slice(bytes, 8)?.try_into().unwrap()) as usize)
+}
+
+fn take_opt_u32(bytes: &mut &[u8]) -> crate::Result<Option<u32>> {
+    match take_u8(bytes)? {
+        0 => Ok(None),
+        _ => Ok(Some(take_u32(bytes)?)),
     }
 }
 
@@ -605,6 +1008,62 @@ mod tests {
         );
     }
 
+    #[test]
+    fn monotonic_offsets() {
+        let encoding = Encoding {
+            ids: vec![0, 1, 2, 3],
+            offsets: vec![(0, 0), (0, 5), (6, 11), (0, 0)],
+            special_tokens_mask: vec![1, 0, 0, 1],
+            ..Default::default()
+        };
+        assert_eq!(
+            encoding.offsets_monotonic(),
+            vec![(0, 0), (0, 5), (6, 11), (11, 11)]
+        );
+    }
+
+    #[test]
+    fn word_views() {
+        let encoding = Encoding {
+            ids: vec![0, 1, 2, 3],
+            type_ids: vec![0, 0, 0, 0],
+            tokens: vec![
+                String::from("He"),
+                String::from("llo"),
+                String::from("World"),
+                String::from("!"),
+            ],
+            words: vec![Some(0), Some(0), Some(1), Some(2)],
+            offsets: vec![(0, 2), (2, 5), (6, 11), (11, 12)],
+            special_tokens_mask: vec![0, 0, 0, 0],
+            attention_mask: vec![1, 1, 1, 1],
+            ..Default::default()
+        };
+        assert_eq!(
+            encoding.get_word_views(0, "Hello World!"),
+            vec![
+                WordView {
+                    word_id: 0,
+                    word: String::from("Hello"),
+                    offsets: (0, 5),
+                    tokens: (0, 2),
+                },
+                WordView {
+                    word_id: 1,
+                    word: String::from("World"),
+                    offsets: (6, 11),
+                    tokens: (2, 3),
+                },
+                WordView {
+                    word_id: 2,
+                    word: String::from("!"),
+                    offsets: (11, 12),
+                    tokens: (3, 4),
+                },
+            ]
+        );
+    }
+
     #[test]
     fn truncate() {
         let mut a = Encoding {
@@ -880,6 +1339,44 @@ mod tests {
         assert_eq!(encoding.char_to_word(9, 1), Some(2));
     }
 
+    #[test]
+    fn bulk_mappings() {
+        let encoding = Encoding {
+            ids: vec![0; 11],
+            tokens: vec!["He".into(); 11],
+            offsets: vec![
+                // First sequence:
+                (0, 2),
+                (2, 5),
+                (7, 10),
+                (10, 13),
+                (13, 16),
+                (17, 23),
+                (23, 24),
+                // Second sequence:
+                (0, 3),
+                (4, 7),
+                (8, 11),
+                (11, 12),
+            ],
+            sequence_ranges: HashMap::from_iter(vec![(0, 0..7), (1, 7..11)]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            encoding.chars_to_tokens(&[0, 3, 6, 16, 23], 0),
+            vec![Some(0), Some(1), None, None, Some(6)]
+        );
+        assert_eq!(encoding.chars_to_tokens(&[2, 9], 1), vec![Some(7), Some(9)]);
+        assert_eq!(encoding.chars_to_tokens(&[0], 2), vec![Some(0)]);
+
+        assert_eq!(encoding.tokens_to_spans(0..2), Some((0, 5)));
+        assert_eq!(encoding.tokens_to_spans(2..7), Some((7, 24)));
+        assert_eq!(encoding.tokens_to_spans(7..11), Some((0, 12)));
+        assert_eq!(encoding.tokens_to_spans(3..3), None);
+        assert_eq!(encoding.tokens_to_spans(9..20), None);
+    }
+
     #[test]
     fn padding() {
         let mut a = Encoding {
@@ -906,4 +1403,52 @@ mod tests {
         );
         assert_eq!(a.sequence_ranges, HashMap::from([(0, 1..2)]));
     }
+
+    #[test]
+    fn binary_roundtrip() {
+        let encoding = Encoding {
+            ids: vec![1, 2, 3],
+            type_ids: vec![0, 0, 1],
+            tokens: vec![
+                String::from("Hello"),
+                String::from("World"),
+                String::from("!"),
+            ],
+            words: vec![Some(0), Some(1), None],
+            offsets: vec![(0, 5), (6, 11), (11, 12)],
+            special_tokens_mask: vec![0, 0, 1],
+            attention_mask: vec![1, 1, 1],
+            scores: vec![Some(-0.5), Some(-1.25), None],
+            tags: vec![None, Some(7), None],
+            overflowing: vec![Encoding {
+                ids: vec![4],
+                type_ids: vec![0],
+                tokens: vec![String::from("?")],
+                words: vec![Some(2)],
+                offsets: vec![(12, 13)],
+                special_tokens_mask: vec![0],
+                attention_mask: vec![1],
+                ..Default::default()
+            }],
+            sequence_ranges: HashMap::from([(0, 0..2), (1, 2..3)]),
+        };
+
+        let bytes = encoding.to_bytes();
+        assert_eq!(Encoding::from_bytes(&bytes).unwrap(), encoding);
+        // Considerably more compact than the JSON serialization
+        assert!(bytes.len() < serde_json::to_vec(&encoding).unwrap().len());
+
+        // An empty Encoding roundtrips too
+        let empty = Encoding::default();
+        assert_eq!(Encoding::from_bytes(&empty.to_bytes()).unwrap(), empty);
+
+        // Unknown versions and corrupted buffers are rejected
+        let mut unknown_version = bytes.clone();
+        unknown_version[0] = 99;
+        assert!(Encoding::from_bytes(&unknown_version)
+            .unwrap_err()
+            .to_string()
+            .contains("format version"));
+        assert!(Encoding::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
 }
@@ -0,0 +1,190 @@
+//! Explicit versioning and migration of serialized components.
+//!
+//! Each component section of a `tokenizer.json` file (`"model"`,
+//! `"normalizer"`, ...) may carry an integer `"version"` field. Files written
+//! by older releases load by applying the registered [`MIGRATIONS`] in order,
+//! so every layout change is documented in one place, with the rationale for
+//! why the rewrite preserves the old semantics, instead of being scattered
+//! across `#[serde(default)]`s that silently change what an old file means.
+//!
+//! A missing `"version"` means version 1, the layout of the original release,
+//! so files produced by upstream `huggingface/tokenizers` migrate the same
+//! way. The `"version"` key is consumed here and never reaches the component
+//! deserializers; serialization always writes the current layout.
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::Result;
+
+/// The component sections of a serialized tokenizer that are versioned
+pub const COMPONENTS: &[&str] = &[
+    "model",
+    "normalizer",
+    "pre_tokenizer",
+    "post_processor",
+    "decoder",
+];
+
+/// A documented layout change of one component section, upgrading a
+/// serialized value from `from_version` to `from_version + 1`
+pub struct Migration {
+    /// The component section this migration applies to, one of [`COMPONENTS`]
+    pub component: &'static str,
+    /// The version this migration upgrades from
+    pub from_version: u64,
+    /// What changed, and why the rewrite preserves the old semantics
+    pub description: &'static str,
+    apply: fn(&mut Value),
+}
+
+/// Every registered migration, ordered by component then version
+pub static MIGRATIONS: &[Migration] = &[Migration {
+    component: "normalizer",
+    from_version: 1,
+    description: "`BertNormalizer` sections without a `strip_accents` field \
+                  stripped accents whenever `lowercase` was set; the implied \
+                  value is written explicitly so that the default of the \
+                  field can evolve without changing what these files mean",
+    apply: bert_explicit_strip_accents,
+}];
+
+/// The version [`MIGRATIONS`] upgrades the given component section to
+pub fn current_version(component: &str) -> u64 {
+    MIGRATIONS
+        .iter()
+        .filter(|migration| migration.component == component)
+        .map(|migration| migration.from_version + 1)
+        .max()
+        .unwrap_or(1)
+}
+
+/// Upgrade every component section of a whole serialized tokenizer to its
+/// current version, in place
+pub(crate) fn migrate(tokenizer: &mut Value) -> Result<()> {
+    for component in COMPONENTS {
+        if let Some(section) = tokenizer.get_mut(*component) {
+            if !section.is_null() {
+                migrate_component(component, section)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Deserialize a serialized tokenizer after upgrading its components, the
+/// common body of the `from_str`/`from_bytes`/`from_file` entry points
+pub(crate) fn from_migrated_value<T: DeserializeOwned>(mut value: Value) -> Result<T> {
+    migrate(&mut value)?;
+    Ok(serde_json::from_value(value)?)
+}
+
+fn migrate_component(component: &str, section: &mut Value) -> Result<()> {
+    let declared = match section.get("version") {
+        None => 1,
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| format!("Invalid version {} for the {} section", version, component))?,
+    };
+    let current = current_version(component);
+    if declared > current {
+        return Err(format!(
+            "The {} section uses version {}, produced by a newer release: \
+             the latest version this release reads is {}",
+            component, declared, current
+        )
+        .into());
+    }
+    for version in declared..current {
+        let migration = MIGRATIONS
+            .iter()
+            .find(|m| m.component == component && m.from_version == version)
+            .expect("registered migrations are contiguous");
+        (migration.apply)(section);
+    }
+    // The component deserializers do not know the key
+    if let Some(section) = section.as_object_mut() {
+        section.remove("version");
+    }
+    Ok(())
+}
+
+/// Before `strip_accents` was its own option, `BertNormalizer` stripped
+/// accents whenever `lowercase` was set
+fn bert_explicit_strip_accents(section: &mut Value) {
+    // The normalizer may be nested in a Sequence
+    if let Some(Value::Array(normalizers)) = section.get_mut("normalizers") {
+        for normalizer in normalizers {
+            bert_explicit_strip_accents(normalizer);
+        }
+    }
+    if section.get("type").and_then(Value::as_str) == Some("BertNormalizer")
+        && section.get("strip_accents").is_none_or(Value::is_null)
+    {
+        let lowercase = section
+            .get("lowercase")
+            .and_then(Value::as_bool)
+            .unwrap_or(true);
+        section["strip_accents"] = Value::Bool(lowercase);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn versions() {
+        assert_eq!(current_version("normalizer"), 2);
+        assert_eq!(current_version("model"), 1);
+    }
+
+    #[test]
+    fn bert_strip_accents() {
+        // The implied strip_accents of a version 1 file is made explicit,
+        // including within a Sequence
+        let mut tokenizer = json!({
+            "normalizer": {
+                "type": "Sequence",
+                "normalizers": [
+                    { "type": "BertNormalizer", "clean_text": true,
+                      "handle_chinese_chars": true, "strip_accents": null,
+                      "lowercase": false },
+                ],
+            },
+        });
+        migrate(&mut tokenizer).unwrap();
+        assert_eq!(
+            tokenizer["normalizer"]["normalizers"][0]["strip_accents"],
+            json!(false)
+        );
+
+        // While an explicit choice is left alone
+        let mut tokenizer = json!({
+            "normalizer": { "type": "BertNormalizer", "clean_text": true,
+                            "handle_chinese_chars": true, "strip_accents": true,
+                            "lowercase": false },
+        });
+        migrate(&mut tokenizer).unwrap();
+        assert_eq!(tokenizer["normalizer"]["strip_accents"], json!(true));
+    }
+
+    #[test]
+    fn version_field_is_consumed() {
+        let mut tokenizer = json!({
+            "normalizer": { "version": 2, "type": "Lowercase" },
+        });
+        migrate(&mut tokenizer).unwrap();
+        assert_eq!(tokenizer["normalizer"], json!({ "type": "Lowercase" }));
+    }
+
+    #[test]
+    fn newer_versions_are_rejected() {
+        let mut tokenizer = json!({
+            "model": { "version": 2, "type": "WordLevel", "vocab": {}, "unk_token": "[UNK]" },
+        });
+        let err = migrate(&mut tokenizer).unwrap_err().to_string();
+        assert!(err.contains("produced by a newer release"), "{}", err);
+    }
+}
@@ -10,23 +10,35 @@
 //!     ...).
 
 use std::{
-    collections::HashMap,
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    ops::{Deref, DerefMut, Range},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+#[cfg(not(feature = "runtime-only"))]
+use std::{
     fs::{read_to_string, File},
     io::prelude::*,
     io::BufReader,
-    ops::{Deref, DerefMut},
-    path::{Path, PathBuf},
 };
 
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
+use crate::utils::cache::LruCache;
+#[cfg(not(feature = "runtime-only"))]
 use crate::utils::iter::ResultShunt;
 use crate::utils::parallelism::*;
+#[cfg(not(feature = "runtime-only"))]
 use crate::utils::progress::{ProgressBar, ProgressStyle};
 
 mod added_vocabulary;
+mod batch;
+#[cfg(all(feature = "config-formats", not(feature = "runtime-only")))]
+mod config;
 mod encoding;
+pub mod migrations;
 pub mod normalizer;
 pub mod pattern;
 pub mod pre_tokenizer;
@@ -39,15 +51,31 @@ pub use crate::normalizers::NormalizerWrapper;
 pub use crate::pre_tokenizers::PreTokenizerWrapper;
 pub use crate::processors::PostProcessorWrapper;
 // And some other types
+pub use crate::utils::chunking::{chunk_encoding, ChunkBoundary, ChunkOptions};
 pub use crate::utils::iter::LinesWithEnding;
-pub use crate::utils::padding::{pad_encodings, PaddingDirection, PaddingParams, PaddingStrategy};
+pub use crate::utils::packing::{PackedSequence, SepPolicy};
+pub use crate::utils::padding::{
+    pad_encodings, pad_encodings_with_direction, PaddingDirection, PaddingParams, PaddingStrategy,
+};
+pub use crate::utils::parallelism::Parallelism;
+pub use crate::utils::profiling::{ComponentProfile, EncodeProfile};
+pub use crate::utils::training_report::TrainingReport;
 pub use crate::utils::truncation::{
-    truncate_encodings, TruncationDirection, TruncationParams, TruncationStrategy,
+    truncate_encodings, truncate_with_budget, BudgetTruncation, FieldBudget, TruncationDirection,
+    TruncationParams, TruncationStrategy,
 };
+pub use crate::utils::validation::{ValidationIssue, ValidationReport};
 pub use added_vocabulary::*;
+pub use batch::EncodingBatch;
+#[cfg(all(feature = "config-formats", not(feature = "runtime-only")))]
+pub use config::ConfigFormat;
 pub use encoding::*;
-pub use normalizer::{NormalizedString, OffsetReferential, SplitDelimiterBehavior};
+pub use normalizer::{
+    ascii_fast_path, check_alignments, set_ascii_fast_path, NormalizedString, OffsetReferential,
+    SplitDelimiterBehavior,
+};
 pub use pre_tokenizer::*;
+pub use serialization::StrictValidationError;
 
 pub type Error = Box<dyn std::error::Error + Send + Sync>;
 pub type Result<T> = std::result::Result<T, Error>;
@@ -65,6 +93,64 @@ pub trait Normalizer {
 /// the original string.
 pub trait PreTokenizer {
     fn pre_tokenize(&self, pretokenized: &mut PreTokenizedString) -> Result<()>;
+
+    /// Whether this pre-tokenizer marks a word boundary at the start of the
+    /// sequence by prepending a space (or its replacement). Returns `None`
+    /// when it has no such notion
+    fn prefix_space(&self) -> Option<bool> {
+        None
+    }
+    /// Set whether this pre-tokenizer marks a word boundary at the start of
+    /// the sequence. This is a no-op for pre-tokenizers without such a notion
+    fn set_prefix_space(&mut self, _prefix_space: bool) {}
+}
+
+/// Model-specific information about a token, as reported by [`Model::token_info`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TokenInfo {
+    /// The rank of the merge producing this token, for merge-based models
+    pub rank: Option<u32>,
+    /// The score or log-probability of the token, when available
+    pub score: Option<f64>,
+    /// The number of occurrences seen at training time, when available
+    pub frequency: Option<u64>,
+}
+
+/// A subset of the vocabulary, stored as a bitset over token ids. Used by
+/// [`TokenizerImpl::encode_constrained`] to restrict the ids a model may
+/// produce, e.g. when building an inverted index over a reduced lexicon.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct VocabMask {
+    bits: Vec<u64>,
+}
+
+impl VocabMask {
+    /// Add a token id to the subset
+    pub fn insert(&mut self, id: u32) {
+        let (word, bit) = (id as usize / 64, id as usize % 64);
+        if word >= self.bits.len() {
+            self.bits.resize(word + 1, 0);
+        }
+        self.bits[word] |= 1 << bit;
+    }
+
+    /// Whether the subset contains the given token id
+    pub fn contains(&self, id: u32) -> bool {
+        let (word, bit) = (id as usize / 64, id as usize % 64);
+        self.bits
+            .get(word)
+            .is_some_and(|bits| bits & (1 << bit) != 0)
+    }
+}
+
+impl std::iter::FromIterator<u32> for VocabMask {
+    fn from_iter<I: IntoIterator<Item = u32>>(ids: I) -> Self {
+        let mut mask = Self::default();
+        for id in ids {
+            mask.insert(id);
+        }
+        mask
+    }
 }
 
 /// Represents a model used during Tokenization (like BPE or Word or Unigram).
@@ -73,12 +159,62 @@ pub trait Model {
     /// Tokenize the given sequence into multiple underlying `Token`. The `offsets` on the `Token`
     /// are expected to be relative to the given sequence.
     fn tokenize(&self, sequence: &str) -> Result<Vec<Token>>;
+    /// Tokenize the given sequence with access to the text of the neighboring
+    /// pre-tokenization splits, so context-sensitive models (morphological
+    /// analyzers, lattice models) can disambiguate on context. The default
+    /// implementation ignores the context and falls back to
+    /// [`Model::tokenize`]. The `offsets` on the `Token` are still relative to
+    /// `sequence` alone.
+    fn tokenize_with_context(
+        &self,
+        _prev: Option<&str>,
+        sequence: &str,
+        _next: Option<&str>,
+    ) -> Result<Vec<Token>> {
+        self.tokenize(sequence)
+    }
+    /// Tokenize the given sequence using only the token ids contained in
+    /// `allowed`, choosing an alternative segmentation when the preferred one
+    /// would use an excluded token. The default implementation cannot
+    /// re-segment: it tokenizes normally and errors when an excluded token
+    /// comes out
+    fn tokenize_constrained(&self, sequence: &str, allowed: &VocabMask) -> Result<Vec<Token>> {
+        let tokens = self.tokenize(sequence)?;
+        match tokens.iter().find(|token| !allowed.contains(token.id)) {
+            Some(token) => Err(format!(
+                "Token `{}` is excluded from the allowed subset, and this model \
+                 cannot choose an alternative segmentation",
+                token.value
+            )
+            .into()),
+            None => Ok(tokens),
+        }
+    }
     /// Find the ID associated to a string token
     fn token_to_id(&self, token: &str) -> Option<u32>;
     /// Find the string token associated to an ID
     fn id_to_token(&self, id: u32) -> Option<String>;
     /// Retrieve the entire vocabulary mapping (token -> ID)
     fn get_vocab(&self) -> HashMap<String, u32>;
+    /// Iterate over the vocabulary entries, sorted by ascending ID. The
+    /// default implementation clones the vocabulary through
+    /// [`Model::get_vocab`]; models yield borrowed entries instead where they
+    /// can, so that tools dumping or inspecting large vocabularies don't pay
+    /// an O(vocab) clone per call
+    fn get_vocab_iter(&self) -> Box<dyn Iterator<Item = (Cow<'_, str>, u32)> + '_> {
+        let mut entries: Vec<_> = self.get_vocab().into_iter().collect();
+        entries.sort_unstable_by_key(|(_, id)| *id);
+        Box::new(
+            entries
+                .into_iter()
+                .map(|(token, id)| (Cow::Owned(token), id)),
+        )
+    }
+    /// Iterate over the reversed vocabulary mapping (ID -> token), sorted by
+    /// ascending ID, borrowing the tokens where the model can
+    fn get_vocab_r(&self) -> Box<dyn Iterator<Item = (u32, Cow<'_, str>)> + '_> {
+        Box::new(self.get_vocab_iter().map(|(token, id)| (id, token)))
+    }
     /// Retrieve the size of the vocabulary
     fn get_vocab_size(&self) -> usize;
     /// Save the current `Model` in the given folder, using the given `prefix` for the various
@@ -86,6 +222,24 @@ pub trait Model {
     fn save(&self, folder: &Path, prefix: Option<&str>) -> Result<Vec<PathBuf>>;
     /// Get an instance of a Trainer capable of training this Model
     fn get_trainer(&self) -> <Self as Model>::Trainer;
+    /// Retrieve model-specific information about a token (merge rank, score,
+    /// training frequency), where available. Returns `None` when the id is not
+    /// part of the vocabulary.
+    fn token_info(&self, id: u32) -> Option<TokenInfo> {
+        self.id_to_token(id).map(|_| TokenInfo::default())
+    }
+    /// The scores this model assigns to the given tokens, one entry per id:
+    /// the log-probability of the token for Unigram, the summed ranks of the
+    /// merges building it for BPE. Returns `None` when the model has no notion
+    /// of token score, and a per-token `None` for ids outside its vocabulary
+    /// (e.g. added tokens)
+    fn token_scores(&self, _ids: &[u32]) -> Option<Vec<Option<f64>>> {
+        None
+    }
+    /// The unknown token of this model, if it has one
+    fn unk_token(&self) -> Option<String> {
+        None
+    }
 }
 
 /// A `PostProcessor` has the responsibility to post process an encoded output of the `Tokenizer`.
@@ -124,6 +278,16 @@ pub trait PostProcessor {
         encodings: Vec<Encoding>,
         add_special_tokens: bool,
     ) -> Result<Vec<Encoding>>;
+
+    /// Whether this post-processor expects a word boundary at the start of the
+    /// sequence, marked by a prefix space. Returns `None` when it has no such
+    /// notion
+    fn prefix_space(&self) -> Option<bool> {
+        None
+    }
+    /// Set whether this post-processor expects a word boundary at the start of
+    /// the sequence. This is a no-op for post-processors without such a notion
+    fn set_prefix_space(&mut self, _prefix_space: bool) {}
 }
 impl dyn PostProcessor {
     pub fn default_process(
@@ -150,6 +314,66 @@ pub enum ProcessorError {
     InvalidEncodingsVecLength,
 }
 
+/// The output of a decoding that keeps track of which token produced which
+/// part of the decoded text
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecodedWithAlignment {
+    /// The decoded string
+    pub text: String,
+    /// For each decoded token, the range of bytes of `text` it produced, or
+    /// `None` when the decoder could not attribute a span to it (e.g. for
+    /// tokens skipped during decoding)
+    pub alignments: Vec<Option<Offsets>>,
+}
+
+/// A snapshot of the normalized text one input sequence was tokenized from,
+/// captured by [`TokenizerImpl::encode_with_snapshots`]: the string the model
+/// actually saw (after added-token extraction and normalization), along with
+/// the alignments back to the original input, so audit tooling does not have
+/// to re-run the normalizer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct NormalizedSnapshot {
+    /// The normalized text of the sequence. For pre-tokenized inputs, the
+    /// normalized sub-sequences are concatenated in order
+    pub text: String,
+    /// For each byte of `text`, the (start, end) byte range of the original
+    /// sequence it comes from
+    pub alignments: Vec<Offsets>,
+}
+
+impl NormalizedSnapshot {
+    /// Append the splits of a normalized sequence to the snapshot
+    fn extend(&mut self, pretokenized: &PreTokenizedString) {
+        for normalized in pretokenized.iter_normalized() {
+            let (shift, _) = normalized.offsets_original();
+            self.text.push_str(normalized.get());
+            self.alignments.extend(
+                normalized
+                    .alignments()
+                    .iter()
+                    .map(|&(start, end)| (start + shift, end + shift)),
+            );
+        }
+    }
+}
+
+impl DecodedWithAlignment {
+    /// The index of the token that produced the byte at the given position of
+    /// the text
+    pub fn byte_to_token(&self, pos: usize) -> Option<usize> {
+        self.alignments
+            .iter()
+            .position(|align| align.is_some_and(|(start, end)| start <= pos && pos < end))
+    }
+
+    /// The index of the token that produced the character at the given
+    /// position of the text
+    pub fn char_to_token(&self, pos: usize) -> Option<usize> {
+        let (byte, _) = self.text.char_indices().nth(pos)?;
+        self.byte_to_token(byte)
+    }
+}
+
 /// A `Decoder` changes the raw tokens into its more readable form.
 pub trait Decoder {
     fn decode(&self, tokens: Vec<String>) -> Result<String> {
@@ -157,6 +381,44 @@ pub trait Decoder {
         Ok(results.join(""))
     }
     fn decode_chain(&self, tokens: Vec<String>) -> Result<Vec<String>>;
+
+    /// Decode the given tokens like [`Decoder::decode`], additionally tracking
+    /// the range of bytes of the decoded text that each token produced. The
+    /// default implementation attributes each piece returned by
+    /// [`Decoder::decode_chain`] to the token at the same position, and gives
+    /// up on attribution (all `None`) when the decoder does not keep one piece
+    /// per token.
+    fn decode_with_alignment(&self, tokens: Vec<String>) -> Result<DecodedWithAlignment> {
+        let n_tokens = tokens.len();
+        let pieces = self.decode_chain(tokens)?;
+        let mut text = String::with_capacity(pieces.iter().map(|piece| piece.len()).sum());
+        let alignments = if pieces.len() == n_tokens {
+            pieces
+                .into_iter()
+                .map(|piece| {
+                    let start = text.len();
+                    text.push_str(&piece);
+                    Some((start, text.len()))
+                })
+                .collect()
+        } else {
+            for piece in &pieces {
+                text.push_str(piece);
+            }
+            vec![None; n_tokens]
+        };
+        Ok(DecodedWithAlignment { text, alignments })
+    }
+
+    /// Whether this decoder removes a word boundary marker (a prefix space or
+    /// its replacement) from the start of the sequence. Returns `None` when it
+    /// has no such notion
+    fn prefix_space(&self) -> Option<bool> {
+        None
+    }
+    /// Set whether this decoder removes a word boundary marker from the start
+    /// of the sequence. This is a no-op for decoders without such a notion
+    fn set_prefix_space(&mut self, _prefix_space: bool) {}
 }
 
 /// A `Trainer` has the responsibility to train a model. We feed it with lines/sentences
@@ -175,6 +437,41 @@ pub trait Trainer {
         I: Iterator<Item = S> + Send,
         S: AsRef<str> + Send,
         F: Fn(&str) -> Result<Vec<String>> + Sync;
+    /// Same as [`Trainer::feed`], with a weight attached to each sequence: every word
+    /// occurrence counts as `weight` instead of 1. Fractional weights are accumulated
+    /// before being rounded, so corpus mixes can be expressed without duplicating data.
+    fn feed_weighted<I, S, F>(&mut self, iterator: I, process: F) -> Result<()>
+    where
+        I: Iterator<Item = (S, f64)> + Send,
+        S: AsRef<str> + Send,
+        F: Fn(&str) -> Result<Vec<String>> + Sync;
+    /// Save the current training state (configuration and fed word counts) to the
+    /// given file, so that an interrupted training can later resume from it without
+    /// re-feeding and re-counting the whole corpus.
+    fn save_checkpoint(&self, _path: &Path) -> Result<()> {
+        Err("This Trainer does not support checkpointing".into())
+    }
+    /// Restore the training state saved by [`Trainer::save_checkpoint`]
+    fn resume_from_checkpoint(&mut self, _path: &Path) -> Result<()> {
+        Err("This Trainer does not support checkpointing".into())
+    }
+    /// Record a held-out validation split, used to measure the unk-rate of the
+    /// report returned by [`Trainer::train_with_report`]
+    fn feed_validation<I, S>(&mut self, _iterator: I) -> Result<()>
+    where
+        I: Iterator<Item = S> + Send,
+        S: AsRef<str> + Send,
+    {
+        Err("This Trainer does not support training reports".into())
+    }
+    /// Same as [`Trainer::train`], additionally returning a [`TrainingReport`]
+    /// when the trainer is configured to produce one
+    fn train_with_report(
+        &self,
+        model: &mut Self::Model,
+    ) -> Result<(Vec<AddedToken>, Option<TrainingReport>)> {
+        Ok((self.train(model)?, None))
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -196,6 +493,12 @@ pub enum InputSequence<'s> {
     PreTokenized(Cow<'s, [&'s str]>),
     PreTokenizedOwned(Cow<'s, [String]>),
     PreTokenizedCow(Cow<'s, [Cow<'s, str>]>),
+    /// Pre-tokenized input where each word carries its offsets in the original
+    /// document, so that the offsets of the resulting `Encoding` refer to the
+    /// source document instead of the word itself. The provided offsets must be
+    /// in the same referential as the `encode` method used (bytes for
+    /// [`TokenizerImpl::encode`], chars for [`TokenizerImpl::encode_char_offsets`]).
+    PreTokenizedWithOffsets(Cow<'s, [(&'s str, Offsets)]>),
 }
 
 impl<'s> From<Cow<'s, str>> for InputSequence<'s> {
@@ -252,6 +555,18 @@ impl<'s> From<&'s [Cow<'s, str>]> for InputSequence<'s> {
     }
 }
 
+impl<'s> From<&'s [(&'s str, Offsets)]> for InputSequence<'s> {
+    fn from(input: &'s [(&'s str, Offsets)]) -> Self {
+        Self::PreTokenizedWithOffsets(Cow::Borrowed(input))
+    }
+}
+
+impl<'s> From<Vec<(&'s str, Offsets)>> for InputSequence<'s> {
+    fn from(input: Vec<(&'s str, Offsets)>) -> Self {
+        Self::PreTokenizedWithOffsets(Cow::Owned(input))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum EncodeInput<'s> {
     Single(InputSequence<'s>),
@@ -274,10 +589,224 @@ where
     }
 }
 
+/// The policy used to handle invalid UTF-8 when encoding raw bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidUtf8Policy {
+    /// Replace any invalid sequence with the U+FFFD replacement character
+    Replace,
+    /// Return an error on the first invalid sequence
+    Error,
+    /// Replace each invalid byte with its `<0xXX>` byte-fallback representation, as
+    /// produced by the `ByteFallback` decoder
+    ByteFallback,
+}
+
+/// Decode the given bytes into a string, using the provided `InvalidUtf8Policy`.
+/// No copy is made when the bytes are valid UTF-8.
+fn bytes_to_string(bytes: &[u8], policy: InvalidUtf8Policy) -> Result<Cow<'_, str>> {
+    match std::str::from_utf8(bytes) {
+        Ok(sequence) => Ok(Cow::Borrowed(sequence)),
+        Err(error) => match policy {
+            InvalidUtf8Policy::Error => Err(Box::new(error)),
+            InvalidUtf8Policy::Replace => Ok(String::from_utf8_lossy(bytes)),
+            InvalidUtf8Policy::ByteFallback => {
+                let mut sequence = String::with_capacity(bytes.len());
+                let mut remaining = bytes;
+                loop {
+                    match std::str::from_utf8(remaining) {
+                        Ok(valid) => {
+                            sequence.push_str(valid);
+                            break;
+                        }
+                        Err(error) => {
+                            let (valid, rest) = remaining.split_at(error.valid_up_to());
+                            sequence.push_str(std::str::from_utf8(valid).unwrap());
+                            // `error_len` is only `None` for an unexpected end of input
+                            let invalid_len = error.error_len().unwrap_or(rest.len());
+                            for byte in &rest[..invalid_len] {
+                                sequence.push_str(&format!("<0x{byte:02X}>"));
+                            }
+                            remaining = &rest[invalid_len..];
+                        }
+                    }
+                }
+                Ok(Cow::Owned(sequence))
+            }
+        },
+    }
+}
+
+/// The policy used to handle the unknown tokens produced by the model,
+/// overriding its default behavior of emitting them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnkPolicy {
+    /// Emit the unknown tokens, the default behavior of the models
+    #[default]
+    Emit,
+    /// Remove the unknown tokens from the encoding
+    Skip,
+    /// Return an error on the first unknown token, so that e.g. strict data
+    /// validation jobs fail fast on unexpected characters
+    Error,
+    /// Replace each unknown token with the `<0xXX>` byte-fallback tokens of
+    /// its text, when they are all part of the vocabulary
+    ByteFallback,
+}
+
+/// The pipeline-level policy for empty and whitespace-only input sequences.
+/// By default they go through the regular pipeline, where some components
+/// behave surprisingly on them (e.g. an `EditBoundaries` configured to ensure
+/// a leading space turns a whitespace-only split into more whitespace); the
+/// other policies short-circuit the pipeline before it runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmptyInputPolicy {
+    /// Run the regular pipeline, the legacy behavior
+    #[default]
+    Passthrough,
+    /// Return an empty encoding without running the pipeline
+    Empty,
+    /// Emit the unknown token of the model, spanning the whole input. Fails
+    /// when the model has none
+    Unk,
+    /// Emit the padding token, spanning the whole input. Fails when no
+    /// padding parameters are configured
+    Pad,
+    /// Return an error, so that e.g. strict data validation jobs fail fast
+    /// on blank inputs
+    Error,
+}
+
+/// The offsets given to the tokens inserted by the post-processor and the
+/// padding, which do not correspond to any input text. Their legacy `(0, 0)`
+/// offsets break downstream span math assuming monotone offsets; the other
+/// policies produce values that keep the offsets of the encoding monotone or
+/// easy to filter out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpecialOffsetsPolicy {
+    /// Inserted tokens get `(0, 0)` offsets, the legacy behavior
+    #[default]
+    Zero,
+    /// Inserted tokens get the empty span ending where the previous real
+    /// token ends, or `(0, 0)` when there is none
+    PreviousEnd,
+    /// Inserted tokens get the empty span `(s, s)` at the given sentinel
+    /// position, e.g. `u32::MAX` to make them easy to filter out
+    Sentinel(u32),
+}
+
+/// The pipeline-level policy deciding whether a word boundary is marked at the
+/// start of the sequence, unifying the `add_prefix_space`-style options that
+/// `ByteLevel`, `Metaspace` and the Roberta post-processor each carry.
+/// Applying a policy pushes the choice into every component that has such an
+/// option, so tokenizer files saved with inconsistent legacy settings can be
+/// migrated by loading them, setting a policy and saving them back. The policy
+/// itself is a runtime setting: only the per-component options are serialized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WordBoundaryPolicy {
+    /// Leave each component to its own configuration. Building a tokenizer
+    /// whose components disagree fails
+    #[default]
+    ComponentDefined,
+    /// Every component treats the start of the sequence as a word boundary, as
+    /// if it were preceded by a space
+    PrefixSpace,
+    /// No component prepends anything to the sequence
+    NoPrefixSpace,
+}
+
+/// The role a special token plays in the tokenizer. The names mirror the
+/// `special_tokens_map.json` conventions of the `transformers` library, so
+/// downstream runtimes can rely on the declared roles instead of guessing
+/// them from the post-processor internals
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum SpecialTokenRole {
+    #[serde(rename = "bos_token")]
+    Bos,
+    #[serde(rename = "eos_token")]
+    Eos,
+    #[serde(rename = "unk_token")]
+    Unk,
+    #[serde(rename = "pad_token")]
+    Pad,
+    #[serde(rename = "cls_token")]
+    Cls,
+    #[serde(rename = "sep_token")]
+    Sep,
+    #[serde(rename = "mask_token")]
+    Mask,
+}
+
+/// A special token holding a role, as returned by
+/// [`TokenizerImpl::special_tokens_map`]: its role, its content, and its id
+/// resolved against the current vocabulary, if any
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpecialTokenInfo {
+    pub role: SpecialTokenRole,
+    pub content: String,
+    pub id: Option<u32>,
+}
+
+/// Hard requirements of the model on the runtime padding and truncation
+/// parameters, exported in the tokenizer files, e.g. left padding for most
+/// decoder-only models. They are validated when a file is loaded, and when
+/// the parameters are changed afterwards
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct TokenizerConstraints {
+    /// The padding direction the model requires
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub padding_direction: Option<PaddingDirection>,
+    /// The truncation direction the model requires
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truncation_direction: Option<TruncationDirection>,
+}
+
+impl TokenizerConstraints {
+    /// Check the given runtime parameters against these constraints
+    pub fn validate(
+        &self,
+        padding: Option<&PaddingParams>,
+        truncation: Option<&TruncationParams>,
+    ) -> Result<()> {
+        if let (Some(required), Some(params)) = (&self.padding_direction, padding) {
+            if params.direction != *required {
+                return Err(format!(
+                    "This tokenizer requires {} padding, but {} padding was set",
+                    required.as_ref(),
+                    params.direction.as_ref()
+                )
+                .into());
+            }
+        }
+        if let (Some(required), Some(params)) = (&self.truncation_direction, truncation) {
+            if params.direction != *required {
+                return Err(format!(
+                    "This tokenizer requires {} truncation, but {} truncation was set",
+                    required.as_ref(),
+                    params.direction.as_ref()
+                )
+                .into());
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 #[error("{0}")]
 pub struct BuilderError(String);
 
+/// A separate normalizer/pre-tokenizer configuration for the second sequence
+/// of a pair, set with [`TokenizerImpl::with_pair_pipeline`]. When set, the
+/// second sequence goes through exactly these components (`None` meaning no
+/// normalization or no pre-tokenization) instead of the main ones, so e.g. a
+/// question can be lowercased while the code context it is paired with keeps
+/// its case. The model and the post-processor remain shared.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairPipeline<N, PT> {
+    pub normalizer: Option<N>,
+    pub pre_tokenizer: Option<PT>,
+}
+
 /// Builder for Tokenizer structs.
 ///
 /// `build()` fails if the `model` is missing.
@@ -285,6 +814,7 @@ pub struct TokenizerBuilder<M, N, PT, PP, D> {
     model: Option<M>,
     normalizer: Option<N>,
     pre_tokenizer: Option<PT>,
+    pair_pipeline: Option<PairPipeline<N, PT>>,
     post_processor: Option<PP>,
     decoder: Option<D>,
 
@@ -292,6 +822,11 @@ pub struct TokenizerBuilder<M, N, PT, PP, D> {
 
     truncation: Option<TruncationParams>,
     padding: Option<PaddingParams>,
+    offset_recovery: OffsetRecoveryPolicy,
+    unk_policy: UnkPolicy,
+    empty_input_policy: EmptyInputPolicy,
+    special_offsets_policy: SpecialOffsetsPolicy,
+    word_boundary_policy: WordBoundaryPolicy,
 }
 
 impl<M, N, PT, PP, D> Default for TokenizerBuilder<M, N, PT, PP, D>
@@ -321,31 +856,89 @@ where
             model: None,
             normalizer: None,
             pre_tokenizer: None,
+            pair_pipeline: None,
             post_processor: None,
             decoder: None,
             added_vocabulary: AddedVocabulary::new(),
             truncation: None,
             padding: None,
+            offset_recovery: OffsetRecoveryPolicy::default(),
+            unk_policy: UnkPolicy::default(),
+            empty_input_policy: EmptyInputPolicy::default(),
+            special_offsets_policy: SpecialOffsetsPolicy::default(),
+            word_boundary_policy: WordBoundaryPolicy::default(),
         }
     }
 
     /// Convert the TokenizerBuilder to a Tokenizer.
     ///
-    /// Conversion fails if the `model` is missing.
+    /// Conversion fails if the `model` is missing, or if the components
+    /// disagree on their prefix space configuration while no word boundary
+    /// policy picks one.
     pub fn build(self) -> Result<TokenizerImpl<M, N, PT, PP, D>> {
         let model = self
             .model
             .ok_or_else(|| Box::new(BuilderError("Model missing.".into())))?;
+        let mut pre_tokenizer = self.pre_tokenizer;
+        let mut post_processor = self.post_processor;
+        let mut decoder = self.decoder;
+        match self.word_boundary_policy {
+            WordBoundaryPolicy::ComponentDefined => {
+                let choices = [
+                    pre_tokenizer
+                        .as_ref()
+                        .and_then(|pretok| pretok.prefix_space()),
+                    post_processor
+                        .as_ref()
+                        .and_then(|processor| processor.prefix_space()),
+                    decoder.as_ref().and_then(|dec| dec.prefix_space()),
+                ];
+                let mut choices = choices.iter().flatten();
+                if let Some(first) = choices.next() {
+                    if choices.any(|choice| choice != first) {
+                        return Err(Box::new(BuilderError(
+                            "The pre-tokenizer, post-processor and decoder disagree on \
+                             their prefix space configuration. Set a word boundary policy \
+                             on the builder to pick one."
+                                .into(),
+                        )));
+                    }
+                }
+            }
+            policy => {
+                let prefix_space = policy == WordBoundaryPolicy::PrefixSpace;
+                if let Some(pretok) = pre_tokenizer.as_mut() {
+                    pretok.set_prefix_space(prefix_space);
+                }
+                if let Some(processor) = post_processor.as_mut() {
+                    processor.set_prefix_space(prefix_space);
+                }
+                if let Some(dec) = decoder.as_mut() {
+                    dec.set_prefix_space(prefix_space);
+                }
+            }
+        }
         Ok(TokenizerImpl {
             normalizer: self.normalizer,
-            pre_tokenizer: self.pre_tokenizer,
+            pre_tokenizer,
+            pair_pipeline: self.pair_pipeline,
             model,
 
-            post_processor: self.post_processor,
-            decoder: self.decoder,
+            post_processor,
+            decoder,
             added_vocabulary: self.added_vocabulary,
+            special_tokens_map: BTreeMap::new(),
             truncation: self.truncation,
             padding: self.padding,
+            constraints: None,
+            offset_recovery: self.offset_recovery,
+            unk_policy: self.unk_policy,
+            empty_input_policy: self.empty_input_policy,
+            special_offsets_policy: self.special_offsets_policy,
+            word_boundary_policy: self.word_boundary_policy,
+            encode_cache: None,
+            profiling: None,
+            parallelism: Parallelism::default(),
         })
     }
 
@@ -370,6 +963,13 @@ where
         self
     }
 
+    /// Set the pair pipeline, applied to the second sequence of a pair.
+    #[must_use]
+    pub fn with_pair_pipeline(mut self, pair_pipeline: Option<PairPipeline<N, PT>>) -> Self {
+        self.pair_pipeline = pair_pipeline;
+        self
+    }
+
     /// Set the post-processor.
     #[must_use]
     pub fn with_post_processor(mut self, post_processor: Option<PP>) -> Self {
@@ -403,6 +1003,260 @@ where
         self.padding = padding;
         self
     }
+
+    /// Set the offset recovery policy.
+    #[must_use]
+    pub fn with_offset_recovery_policy(mut self, policy: OffsetRecoveryPolicy) -> Self {
+        self.offset_recovery = policy;
+        self
+    }
+
+    /// Set the unknown token policy.
+    #[must_use]
+    pub fn with_unk_policy(mut self, policy: UnkPolicy) -> Self {
+        self.unk_policy = policy;
+        self
+    }
+
+    /// Set the policy for empty and whitespace-only inputs.
+    #[must_use]
+    pub fn with_empty_input_policy(mut self, policy: EmptyInputPolicy) -> Self {
+        self.empty_input_policy = policy;
+        self
+    }
+
+    /// Set the policy for the offsets of the inserted special tokens.
+    #[must_use]
+    pub fn with_special_offsets_policy(mut self, policy: SpecialOffsetsPolicy) -> Self {
+        self.special_offsets_policy = policy;
+        self
+    }
+
+    /// Set the word boundary policy, applied to the components on `build()`.
+    #[must_use]
+    pub fn with_word_boundary_policy(mut self, policy: WordBoundaryPolicy) -> Self {
+        self.word_boundary_policy = policy;
+        self
+    }
+}
+
+impl<M>
+    TokenizerImpl<M, NormalizerWrapper, PreTokenizerWrapper, PostProcessorWrapper, DecoderWrapper>
+where
+    M: Model,
+{
+    /// Get a mutable view of the normalization and pre-tokenization
+    /// pipelines, to insert, remove or replace individual stages at runtime
+    /// without rebuilding the whole configuration:
+    ///
+    /// ```
+    /// # use tokenizers::Tokenizer;
+    /// # use tokenizers::models::wordlevel::WordLevel;
+    /// # use tokenizers::pre_tokenizers::whitespace::Whitespace;
+    /// # use tokenizers::pre_tokenizers::punctuation::Punctuation;
+    /// let mut tokenizer = Tokenizer::new(WordLevel::default());
+    /// tokenizer.pipeline_mut().insert_pre_tokenizer(0, Whitespace).unwrap();
+    /// tokenizer.pipeline_mut().insert_pre_tokenizer(1, Punctuation::default()).unwrap();
+    /// ```
+    pub fn pipeline_mut(&mut self) -> PipelineMut<'_, M> {
+        PipelineMut { tokenizer: self }
+    }
+}
+
+/// A mutable view of the `Sequence` components of a tokenizer's pipeline,
+/// obtained with [`TokenizerImpl::pipeline_mut`]. The normalizer and the
+/// pre-tokenizer are edited as lists of stages: a bare component counts as a
+/// one-stage list, and after an edit the list is stored as a `Sequence` when
+/// it holds several stages, as the bare component when it holds a single one,
+/// and removed entirely when it is empty. Every successful edit re-applies
+/// the tokenizer's word boundary policy to the components and invalidates the
+/// encode cache.
+pub struct PipelineMut<'t, M> {
+    tokenizer: &'t mut TokenizerImpl<
+        M,
+        NormalizerWrapper,
+        PreTokenizerWrapper,
+        PostProcessorWrapper,
+        DecoderWrapper,
+    >,
+}
+
+impl<M> PipelineMut<'_, M>
+where
+    M: Model,
+{
+    /// The number of stages of the normalization pipeline
+    pub fn normalizer_len(&self) -> usize {
+        match &self.tokenizer.normalizer {
+            None => 0,
+            Some(NormalizerWrapper::Sequence(sequence)) => sequence.get_normalizers().len(),
+            Some(_) => 1,
+        }
+    }
+
+    /// The number of stages of the pre-tokenization pipeline
+    pub fn pre_tokenizer_len(&self) -> usize {
+        match &self.tokenizer.pre_tokenizer {
+            None => 0,
+            Some(PreTokenizerWrapper::Sequence(sequence)) => sequence.get_pre_tokenizers().len(),
+            Some(_) => 1,
+        }
+    }
+
+    fn normalizer_stages(&mut self) -> Vec<NormalizerWrapper> {
+        match self.tokenizer.normalizer.take() {
+            None => vec![],
+            Some(NormalizerWrapper::Sequence(sequence)) => sequence.into_normalizers(),
+            Some(normalizer) => vec![normalizer],
+        }
+    }
+
+    fn set_normalizer_stages(&mut self, mut stages: Vec<NormalizerWrapper>) {
+        self.tokenizer.normalizer = match stages.len() {
+            0 => None,
+            1 => stages.pop(),
+            _ => Some(crate::normalizers::Sequence::new(stages).into()),
+        };
+        self.revalidate();
+    }
+
+    fn pre_tokenizer_stages(&mut self) -> Vec<PreTokenizerWrapper> {
+        match self.tokenizer.pre_tokenizer.take() {
+            None => vec![],
+            Some(PreTokenizerWrapper::Sequence(sequence)) => sequence.into_pre_tokenizers(),
+            Some(pre_tokenizer) => vec![pre_tokenizer],
+        }
+    }
+
+    fn set_pre_tokenizer_stages(&mut self, mut stages: Vec<PreTokenizerWrapper>) {
+        self.tokenizer.pre_tokenizer = match stages.len() {
+            0 => None,
+            1 => stages.pop(),
+            _ => Some(crate::pre_tokenizers::sequence::Sequence::new(stages).into()),
+        };
+        self.revalidate();
+    }
+
+    /// Re-apply the word boundary policy to the new set of components, and
+    /// invalidate the encode cache
+    fn revalidate(&mut self) {
+        let policy = self.tokenizer.word_boundary_policy;
+        self.tokenizer.with_word_boundary_policy(policy);
+    }
+
+    /// Insert a normalization stage at the given index
+    pub fn insert_normalizer(
+        &mut self,
+        index: usize,
+        normalizer: impl Into<NormalizerWrapper>,
+    ) -> Result<()> {
+        if index > self.normalizer_len() {
+            return Err(format!(
+                "Cannot insert a normalizer at index {}: the pipeline has {} stages",
+                index,
+                self.normalizer_len()
+            )
+            .into());
+        }
+        let mut stages = self.normalizer_stages();
+        stages.insert(index, normalizer.into());
+        self.set_normalizer_stages(stages);
+        Ok(())
+    }
+
+    /// Remove and return the normalization stage at the given index
+    pub fn remove_normalizer(&mut self, index: usize) -> Result<NormalizerWrapper> {
+        if index >= self.normalizer_len() {
+            return Err(format!(
+                "Cannot remove the normalizer at index {}: the pipeline has {} stages",
+                index,
+                self.normalizer_len()
+            )
+            .into());
+        }
+        let mut stages = self.normalizer_stages();
+        let removed = stages.remove(index);
+        self.set_normalizer_stages(stages);
+        Ok(removed)
+    }
+
+    /// Replace the normalization stage at the given index, returning the
+    /// previous one
+    pub fn replace_normalizer(
+        &mut self,
+        index: usize,
+        normalizer: impl Into<NormalizerWrapper>,
+    ) -> Result<NormalizerWrapper> {
+        if index >= self.normalizer_len() {
+            return Err(format!(
+                "Cannot replace the normalizer at index {}: the pipeline has {} stages",
+                index,
+                self.normalizer_len()
+            )
+            .into());
+        }
+        let mut stages = self.normalizer_stages();
+        let replaced = std::mem::replace(&mut stages[index], normalizer.into());
+        self.set_normalizer_stages(stages);
+        Ok(replaced)
+    }
+
+    /// Insert a pre-tokenization stage at the given index
+    pub fn insert_pre_tokenizer(
+        &mut self,
+        index: usize,
+        pre_tokenizer: impl Into<PreTokenizerWrapper>,
+    ) -> Result<()> {
+        if index > self.pre_tokenizer_len() {
+            return Err(format!(
+                "Cannot insert a pre-tokenizer at index {}: the pipeline has {} stages",
+                index,
+                self.pre_tokenizer_len()
+            )
+            .into());
+        }
+        let mut stages = self.pre_tokenizer_stages();
+        stages.insert(index, pre_tokenizer.into());
+        self.set_pre_tokenizer_stages(stages);
+        Ok(())
+    }
+
+    /// Remove and return the pre-tokenization stage at the given index
+    pub fn remove_pre_tokenizer(&mut self, index: usize) -> Result<PreTokenizerWrapper> {
+        if index >= self.pre_tokenizer_len() {
+            return Err(format!(
+                "Cannot remove the pre-tokenizer at index {}: the pipeline has {} stages",
+                index,
+                self.pre_tokenizer_len()
+            )
+            .into());
+        }
+        let mut stages = self.pre_tokenizer_stages();
+        let removed = stages.remove(index);
+        self.set_pre_tokenizer_stages(stages);
+        Ok(removed)
+    }
+
+    /// Replace the pre-tokenization stage at the given index, returning the
+    /// previous one
+    pub fn replace_pre_tokenizer(
+        &mut self,
+        index: usize,
+        pre_tokenizer: impl Into<PreTokenizerWrapper>,
+    ) -> Result<PreTokenizerWrapper> {
+        if index >= self.pre_tokenizer_len() {
+            return Err(format!(
+                "Cannot replace the pre-tokenizer at index {}: the pipeline has {} stages",
+                index,
+                self.pre_tokenizer_len()
+            )
+            .into());
+        }
+        let mut stages = self.pre_tokenizer_stages();
+        let replaced = std::mem::replace(&mut stages[index], pre_tokenizer.into());
+        self.set_pre_tokenizer_stages(stages);
+        Ok(replaced)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -434,16 +1288,153 @@ impl Tokenizer {
     > {
         self.0
     }
+    #[cfg(not(feature = "runtime-only"))]
     pub fn from_file<P: AsRef<Path>>(file: P) -> Result<Self> {
         let content = read_to_string(file)?;
-        let tokenizer = serde_json::from_str(&content)?;
-        Ok(tokenizer)
+        migrations::from_migrated_value(serde_json::from_str(&content)?)
     }
     pub fn from_bytes<P: AsRef<[u8]>>(bytes: P) -> Result<Self> {
-        let tokenizer = serde_json::from_slice(bytes.as_ref())?;
+        migrations::from_migrated_value(serde_json::from_slice(bytes.as_ref())?)
+    }
+
+    /// Instantiate a new Tokenizer from the given file, in strict mode. Unlike
+    /// [`Tokenizer::from_file`], fields that the deserializer would silently
+    /// ignore are reported as [`StrictValidationError::UnknownField`], and a
+    /// few component invariants are validated: merges must be consistent with
+    /// the model vocabulary, the template post-processor may only reference
+    /// special tokens it defines and that match the vocabulary, and the
+    /// configured padding token must be in the vocabulary. Note that a field
+    /// explicitly set to its default value can be reported as unknown, since
+    /// default values are not serialized back.
+    #[cfg(not(feature = "runtime-only"))]
+    pub fn from_file_strict<P: AsRef<Path>>(file: P) -> Result<Self> {
+        let content = read_to_string(file)?;
+        Self::from_bytes_strict(content.as_bytes())
+    }
+
+    /// Like [`Tokenizer::from_bytes`], with the strict validations of
+    /// [`Tokenizer::from_file_strict`]
+    pub fn from_bytes_strict<P: AsRef<[u8]>>(bytes: P) -> Result<Self> {
+        // Migrate before comparing, so that `version` fields and migrated
+        // layouts are not reported as unknown fields
+        let mut input: serde_json::Value = serde_json::from_slice(bytes.as_ref())?;
+        migrations::migrate(&mut input)?;
+        let tokenizer: Self = serde_json::from_value(input.clone())?;
+        let output = serde_json::to_value(&tokenizer)?;
+
+        let mut ignored = vec![];
+        serialization::find_ignored_fields(&input, &output, "", &mut ignored);
+        if let Some(path) = ignored.into_iter().next() {
+            return Err(Box::new(StrictValidationError::UnknownField(path)));
+        }
+
+        let vocab = tokenizer.get_vocab(true);
+        if let ModelWrapper::BPE(bpe) = tokenizer.get_model() {
+            for (pair, (_, new_id)) in &bpe.merges {
+                for id in [pair.0, pair.1, *new_id] {
+                    if !bpe.vocab_r.contains_key(&id) {
+                        return Err(Box::new(StrictValidationError::MergeUnknownId(id)));
+                    }
+                }
+            }
+        }
+        if let Some(PostProcessorWrapper::Template(template)) = tokenizer.get_post_processor() {
+            template.validate_special_tokens(&vocab)?;
+        }
+        if let Some(params) = tokenizer.get_padding() {
+            match vocab.get(&params.pad_token) {
+                None => {
+                    return Err(Box::new(StrictValidationError::PaddingTokenMissing(
+                        params.pad_token.clone(),
+                    )))
+                }
+                Some(&id) if id != params.pad_id => {
+                    return Err(Box::new(StrictValidationError::PaddingIdMismatch(
+                        params.pad_token.clone(),
+                        id,
+                        params.pad_id,
+                    )))
+                }
+                _ => {}
+            }
+        }
+
         Ok(tokenizer)
     }
-    #[cfg(feature = "http")]
+    /// Swap the model of the pipeline for a new one, keeping the normalizer,
+    /// pre-tokenizer, post-processor, padding, truncation and added
+    /// vocabulary. The added tokens are re-registered against the new model:
+    /// tokens the new vocabulary already contains take their model id, the
+    /// others get ids allocated after the new vocabulary, and the report lists
+    /// every id that changed. The template post-processor special tokens and
+    /// the padding token are validated against the new vocabulary first, so a
+    /// conflicting swap errors out without modifying the tokenizer.
+    pub fn replace_model(
+        &mut self,
+        model: impl Into<ModelWrapper>,
+    ) -> Result<ModelReplacementReport> {
+        let model = model.into();
+
+        // Re-register the added tokens in id order, so that the re-allocated
+        // ids are deterministic
+        let mut tokens: Vec<(u32, AddedToken)> = self
+            .added_vocabulary
+            .get_added_tokens_decoder()
+            .iter()
+            .map(|(id, token)| (*id, token.clone()))
+            .collect();
+        tokens.sort_unstable_by_key(|(id, _)| *id);
+
+        let mut added_vocabulary = AddedVocabulary::new();
+        let mut remapped = vec![];
+        for (old_id, token) in tokens {
+            added_vocabulary.add_tokens(
+                std::slice::from_ref(&token),
+                &model,
+                self.normalizer.as_ref(),
+            );
+            let new_id = added_vocabulary
+                .token_to_id(&token.content, &model)
+                .expect("The token was just added");
+            if new_id != old_id {
+                remapped.push((token.content, old_id, new_id));
+            }
+        }
+
+        // Validate the pipeline components that hard-code ids against the new
+        // vocabulary, before committing anything
+        let mut vocab = model.get_vocab();
+        for (content, id) in added_vocabulary.get_vocab() {
+            vocab.insert(content.clone(), *id);
+        }
+        if let Some(PostProcessorWrapper::Template(template)) = &self.post_processor {
+            template.validate_special_tokens(&vocab)?;
+        }
+        if let Some(params) = &self.padding {
+            match vocab.get(&params.pad_token) {
+                None => {
+                    return Err(Box::new(StrictValidationError::PaddingTokenMissing(
+                        params.pad_token.clone(),
+                    )))
+                }
+                Some(&id) if id != params.pad_id => {
+                    return Err(Box::new(StrictValidationError::PaddingIdMismatch(
+                        params.pad_token.clone(),
+                        id,
+                        params.pad_id,
+                    )))
+                }
+                _ => {}
+            }
+        }
+
+        self.0.model = model;
+        self.0.added_vocabulary = added_vocabulary;
+        self.0.refresh_encode_cache();
+        Ok(ModelReplacementReport { remapped })
+    }
+
+    #[cfg(all(feature = "http", not(feature = "runtime-only")))]
     pub fn from_pretrained<S: AsRef<str>>(
         identifier: S,
         params: Option<crate::utils::from_pretrained::FromPretrainedParameters>,
@@ -453,11 +1444,18 @@ impl Tokenizer {
     }
 }
 
+/// The outcome of [`Tokenizer::replace_model`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelReplacementReport {
+    /// The added tokens whose id changed, as `(content, old id, new id)`
+    pub remapped: Vec<(String, u32, u32)>,
+}
+
 impl std::str::FromStr for Tokenizer {
     type Err = Box<dyn std::error::Error + Send + Sync>;
 
     fn from_str(s: &str) -> Result<Self> {
-        Ok(serde_json::from_str(s)?)
+        migrations::from_migrated_value(serde_json::from_str(s)?)
     }
 }
 
@@ -474,11 +1472,25 @@ where
             model: t.model.into(),
             normalizer: t.normalizer.map(Into::into),
             pre_tokenizer: t.pre_tokenizer.map(Into::into),
+            pair_pipeline: t.pair_pipeline.map(|pair| PairPipeline {
+                normalizer: pair.normalizer.map(Into::into),
+                pre_tokenizer: pair.pre_tokenizer.map(Into::into),
+            }),
             post_processor: t.post_processor.map(Into::into),
             decoder: t.decoder.map(Into::into),
             added_vocabulary: t.added_vocabulary,
+            special_tokens_map: t.special_tokens_map,
             padding: t.padding,
             truncation: t.truncation,
+            constraints: t.constraints,
+            offset_recovery: t.offset_recovery,
+            unk_policy: t.unk_policy,
+            empty_input_policy: t.empty_input_policy,
+            special_offsets_policy: t.special_offsets_policy,
+            word_boundary_policy: t.word_boundary_policy,
+            encode_cache: t.encode_cache,
+            profiling: t.profiling,
+            parallelism: t.parallelism,
         })
     }
 }
@@ -503,16 +1515,62 @@ impl DerefMut for Tokenizer {
     }
 }
 
-#[derive(thiserror::Error, Debug)]
-#[error("{0}")]
-pub struct TruncationParamError(String);
-
-/// A `Tokenizer` is capable of encoding/decoding any text.
+/// An immutable, cheaply cloneable handle over a [`Tokenizer`], meant to be shared
+/// across threads (e.g. in a server) without an `Arc<Mutex<Tokenizer>>`.
+///
+/// All the configuration (padding, truncation, added vocabulary, ...) is fixed when
+/// the handle is built, and only the `&self` part of the API (`encode`, `decode`,
+/// vocabulary lookups, ...) remains reachable, so no locking is ever involved.
+/// To change the configuration, recover the inner `Tokenizer` with
+/// [`FrozenTokenizer::try_unwrap`], mutate it, and freeze it again.
 #[derive(Clone, Debug)]
-pub struct TokenizerImpl<M, N, PT, PP, D> {
-    // Tokenizer parts
-    normalizer: Option<N>,
+pub struct FrozenTokenizer(Arc<Tokenizer>);
+
+impl FrozenTokenizer {
+    /// Freeze the given `Tokenizer`.
+    pub fn new(tokenizer: Tokenizer) -> Self {
+        Self(Arc::new(tokenizer))
+    }
+
+    /// Recover the inner `Tokenizer`, if this is the last handle to it.
+    pub fn try_unwrap(self) -> std::result::Result<Tokenizer, Self> {
+        Arc::try_unwrap(self.0).map_err(Self)
+    }
+}
+
+impl From<Tokenizer> for FrozenTokenizer {
+    fn from(tokenizer: Tokenizer) -> Self {
+        Self::new(tokenizer)
+    }
+}
+
+impl Deref for FrozenTokenizer {
+    type Target = TokenizerImpl<
+        ModelWrapper,
+        NormalizerWrapper,
+        PreTokenizerWrapper,
+        PostProcessorWrapper,
+        DecoderWrapper,
+    >;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("{0}")]
+pub struct TruncationParamError(String);
+
+/// A `Tokenizer` is capable of encoding/decoding any text.
+#[derive(Clone, Debug)]
+pub struct TokenizerImpl<M, N, PT, PP, D> {
+    // Tokenizer parts
+    normalizer: Option<N>,
     pre_tokenizer: Option<PT>,
+    /// An optional separate normalizer/pre-tokenizer configuration for the
+    /// second sequence of a pair, serialized in the tokenizer files when set
+    pair_pipeline: Option<PairPipeline<N, PT>>,
     model: M,
     post_processor: Option<PP>,
     decoder: Option<D>,
@@ -520,11 +1578,56 @@ pub struct TokenizerImpl<M, N, PT, PP, D> {
     // Added Vocabulary capabilities
     added_vocabulary: AddedVocabulary,
 
+    /// The special tokens holding a role (bos, eos, pad, ...), serialized in
+    /// the tokenizer files when non-empty
+    special_tokens_map: BTreeMap<SpecialTokenRole, String>,
+
     // General processing parameters
     truncation: Option<TruncationParams>,
     padding: Option<PaddingParams>,
+    /// The requirements of the model on the padding and truncation
+    /// parameters, serialized in the tokenizer files when set
+    constraints: Option<TokenizerConstraints>,
+    /// How offsets of tokens whose original content was entirely removed by
+    /// normalization map back to the original string. This is a runtime setting:
+    /// it is not serialized in the tokenizer files.
+    offset_recovery: OffsetRecoveryPolicy,
+    /// How the unknown tokens produced by the model are handled. This is a
+    /// runtime setting: it is not serialized in the tokenizer files.
+    unk_policy: UnkPolicy,
+    /// How empty and whitespace-only inputs are handled. This is a runtime
+    /// setting: it is not serialized in the tokenizer files.
+    empty_input_policy: EmptyInputPolicy,
+    /// The offsets given to the special tokens inserted by the post-processor
+    /// and by the padding. This is a runtime setting: it is not serialized in
+    /// the tokenizer files.
+    special_offsets_policy: SpecialOffsetsPolicy,
+    /// How the components mark a word boundary at the start of the sequence.
+    /// This is a runtime setting: applying it updates the components, and only
+    /// their own configuration is serialized in the tokenizer files.
+    word_boundary_policy: WordBoundaryPolicy,
+
+    /// An optional cache of encode results, for workloads with heavy input
+    /// repetition. This is a runtime setting: it is not serialized in the
+    /// tokenizer files, and clones of the tokenizer share it.
+    encode_cache: Option<LruCache<EncodeCacheKey, Encoding>>,
+
+    /// An optional profile of the encode calls, aggregated per pipeline
+    /// component. This is a runtime setting: it is not serialized in the
+    /// tokenizer files, and clones of the tokenizer share it.
+    profiling: Option<Arc<Mutex<EncodeProfile>>>,
+
+    /// How the batch entry points dispatch their work. This is a runtime
+    /// setting: it is not serialized in the tokenizer files.
+    parallelism: Parallelism,
 }
 
+/// The key of an entry of the encode cache: the raw input, the
+/// `add_special_tokens` flag, and a snapshot of the truncation/padding
+/// parameters taken at encode time, so that reconfiguring the tokenizer never
+/// serves stale encodings
+type EncodeCacheKey = (String, bool, String);
+
 impl<M, N, PT, PP, D> TokenizerImpl<M, N, PT, PP, D>
 where
     M: Model,
@@ -538,20 +1641,34 @@ where
         Self {
             normalizer: None,
             pre_tokenizer: None,
+            pair_pipeline: None,
             model,
             post_processor: None,
             decoder: None,
 
             added_vocabulary: AddedVocabulary::new(),
 
+            special_tokens_map: BTreeMap::new(),
+
             truncation: None,
             padding: None,
+            constraints: None,
+            offset_recovery: OffsetRecoveryPolicy::default(),
+            unk_policy: UnkPolicy::default(),
+            empty_input_policy: EmptyInputPolicy::default(),
+            special_offsets_policy: SpecialOffsetsPolicy::default(),
+            word_boundary_policy: WordBoundaryPolicy::default(),
+
+            encode_cache: None,
+            profiling: None,
+            parallelism: Parallelism::default(),
         }
     }
 
     /// Set the normalizer
     pub fn with_normalizer(&mut self, normalizer: Option<impl Into<N>>) -> &mut Self {
         self.normalizer = normalizer.map(|norm| norm.into());
+        self.refresh_encode_cache();
         self
     }
     /// Get the normalizer
@@ -562,6 +1679,7 @@ where
     /// Set the pre tokenizer
     pub fn with_pre_tokenizer(&mut self, pre_tokenizer: Option<impl Into<PT>>) -> &mut Self {
         self.pre_tokenizer = pre_tokenizer.map(|tok| tok.into());
+        self.refresh_encode_cache();
         self
     }
 
@@ -570,9 +1688,25 @@ where
         self.pre_tokenizer.as_ref()
     }
 
+    /// Set the pair pipeline: a separate normalizer/pre-tokenizer
+    /// configuration for the second sequence of a pair, e.g. to lowercase a
+    /// question but not the code context it is paired with. When unset (the
+    /// default), both sequences go through the main pipeline
+    pub fn with_pair_pipeline(&mut self, pair_pipeline: Option<PairPipeline<N, PT>>) -> &mut Self {
+        self.pair_pipeline = pair_pipeline;
+        self.refresh_encode_cache();
+        self
+    }
+
+    /// Get the pair pipeline
+    pub fn get_pair_pipeline(&self) -> Option<&PairPipeline<N, PT>> {
+        self.pair_pipeline.as_ref()
+    }
+
     /// Set the post processor
     pub fn with_post_processor(&mut self, post_processor: Option<impl Into<PP>>) -> &mut Self {
         self.post_processor = post_processor.map(|post_proc| post_proc.into());
+        self.refresh_encode_cache();
         self
     }
 
@@ -595,6 +1729,7 @@ where
     /// Set the model
     pub fn with_model(&mut self, model: impl Into<M>) -> &mut Self {
         self.model = model.into();
+        self.refresh_encode_cache();
         self
     }
 
@@ -606,6 +1741,7 @@ where
     /// Set the added vocabulary.
     pub fn with_added_vocabulary(&mut self, added_vocabulary: AddedVocabulary) -> &mut Self {
         self.added_vocabulary = added_vocabulary;
+        self.refresh_encode_cache();
         self
     }
 
@@ -614,10 +1750,90 @@ where
         &self.added_vocabulary
     }
 
+    /// Assign a role (bos, eos, pad, ...) to a special token, or clear it
+    /// with `None`. The roles are serialized in the tokenizer files, so
+    /// downstream runtimes don't have to guess them from the post-processor
+    /// internals. Note that this only declares the role: the token itself
+    /// still has to be part of the vocabulary, e.g. through
+    /// [`TokenizerImpl::add_special_tokens`]
+    pub fn with_special_token(
+        &mut self,
+        role: SpecialTokenRole,
+        content: Option<impl Into<String>>,
+    ) -> &mut Self {
+        match content {
+            Some(content) => {
+                self.special_tokens_map.insert(role, content.into());
+            }
+            None => {
+                self.special_tokens_map.remove(&role);
+            }
+        }
+        self
+    }
+
+    /// Get the special tokens holding a role, with their ids resolved against
+    /// the current vocabulary. Tokens declared but absent from the vocabulary
+    /// have no id
+    pub fn special_tokens_map(&self) -> Vec<SpecialTokenInfo> {
+        self.special_tokens_map
+            .iter()
+            .map(|(role, content)| SpecialTokenInfo {
+                role: *role,
+                content: content.clone(),
+                id: self.token_to_id(content),
+            })
+            .collect()
+    }
+
+    /// Get the special token holding the given role, if any, with its id
+    /// resolved against the current vocabulary
+    pub fn special_token(&self, role: SpecialTokenRole) -> Option<SpecialTokenInfo> {
+        self.special_tokens_map
+            .get(&role)
+            .map(|content| SpecialTokenInfo {
+                role,
+                content: content.clone(),
+                id: self.token_to_id(content),
+            })
+    }
+
+    /// Set the whole special tokens map at once, mapping roles to token
+    /// contents
+    pub fn with_special_tokens_map(
+        &mut self,
+        special_tokens_map: BTreeMap<SpecialTokenRole, String>,
+    ) -> &mut Self {
+        self.special_tokens_map = special_tokens_map;
+        self
+    }
+
+    /// Set the model constraints on the padding and truncation parameters,
+    /// validating the currently set parameters against them
+    pub fn with_constraints(
+        &mut self,
+        constraints: Option<TokenizerConstraints>,
+    ) -> Result<&mut Self> {
+        if let Some(constraints) = &constraints {
+            constraints.validate(self.padding.as_ref(), self.truncation.as_ref())?;
+        }
+        self.constraints = constraints;
+        Ok(self)
+    }
+
+    /// Get the currently set model constraints
+    pub fn get_constraints(&self) -> Option<&TokenizerConstraints> {
+        self.constraints.as_ref()
+    }
+
     /// Set the truncation parameters
     ///
-    /// Fails if `stride` is too high relative to `max_length` and `post_processor.added_tokens()`
+    /// Fails if `stride` is too high relative to `max_length` and `post_processor.added_tokens()`,
+    /// or if the parameters violate the model constraints
     pub fn with_truncation(&mut self, trunc: Option<TruncationParams>) -> Result<&mut Self> {
+        if let Some(constraints) = &self.constraints {
+            constraints.validate(None, trunc.as_ref())?;
+        }
         if let Some(trunc_params) = &trunc {
             let n_added_tokens = self.get_n_added_tokens(false);
             let effective_max_length = trunc_params.max_length - n_added_tokens;
@@ -642,12 +1858,131 @@ where
         self.truncation.as_mut()
     }
 
-    /// Set the padding parameters
+    /// Set the padding parameters, warning when they violate the model
+    /// constraints
     pub fn with_padding(&mut self, padding: Option<PaddingParams>) -> &mut Self {
+        if let Some(constraints) = &self.constraints {
+            if let Err(e) = constraints.validate(padding.as_ref(), None) {
+                warn!("{}", e);
+            }
+        }
         self.padding = padding;
         self
     }
 
+    /// Set the offset recovery policy, deciding how tokens whose original content
+    /// was entirely removed by normalization map back to the original string
+    pub fn with_offset_recovery_policy(&mut self, policy: OffsetRecoveryPolicy) -> &mut Self {
+        self.offset_recovery = policy;
+        self.refresh_encode_cache();
+        self
+    }
+
+    /// Get the currently set offset recovery policy
+    pub fn get_offset_recovery_policy(&self) -> OffsetRecoveryPolicy {
+        self.offset_recovery
+    }
+
+    /// Set the unknown token policy, overriding the model default of emitting
+    /// the unknown tokens: they can also be skipped, turned into an error, or
+    /// replaced with the `<0xXX>` byte-fallback tokens of their text
+    pub fn with_unk_policy(&mut self, policy: UnkPolicy) -> &mut Self {
+        self.unk_policy = policy;
+        self.refresh_encode_cache();
+        self
+    }
+
+    /// Get the currently set unknown token policy
+    pub fn get_unk_policy(&self) -> UnkPolicy {
+        self.unk_policy
+    }
+
+    /// Set the policy for empty and whitespace-only inputs, overriding the
+    /// default of running them through the regular pipeline: they can also be
+    /// short-circuited into an empty encoding, the unknown or padding token,
+    /// or an error
+    pub fn with_empty_input_policy(&mut self, policy: EmptyInputPolicy) -> &mut Self {
+        self.empty_input_policy = policy;
+        self.refresh_encode_cache();
+        self
+    }
+
+    /// Get the currently set policy for empty and whitespace-only inputs
+    pub fn get_empty_input_policy(&self) -> EmptyInputPolicy {
+        self.empty_input_policy
+    }
+
+    /// Set the policy for the offsets of the special tokens inserted by the
+    /// post-processor and by the padding, instead of their legacy `(0, 0)`
+    pub fn with_special_offsets_policy(&mut self, policy: SpecialOffsetsPolicy) -> &mut Self {
+        self.special_offsets_policy = policy;
+        self.refresh_encode_cache();
+        self
+    }
+
+    /// Get the currently set policy for the offsets of the inserted special
+    /// tokens
+    pub fn get_special_offsets_policy(&self) -> SpecialOffsetsPolicy {
+        self.special_offsets_policy
+    }
+
+    /// Set the word boundary policy, pushing a single prefix space choice into
+    /// every component that has such an option (`ByteLevel`, `Metaspace`, the
+    /// Roberta post-processor). With [`WordBoundaryPolicy::ComponentDefined`],
+    /// the components are left to their own configuration
+    pub fn with_word_boundary_policy(&mut self, policy: WordBoundaryPolicy) -> &mut Self {
+        self.word_boundary_policy = policy;
+        if policy != WordBoundaryPolicy::ComponentDefined {
+            let prefix_space = policy == WordBoundaryPolicy::PrefixSpace;
+            if let Some(pre_tokenizer) = self.pre_tokenizer.as_mut() {
+                pre_tokenizer.set_prefix_space(prefix_space);
+            }
+            if let Some(post_processor) = self.post_processor.as_mut() {
+                post_processor.set_prefix_space(prefix_space);
+            }
+            if let Some(decoder) = self.decoder.as_mut() {
+                decoder.set_prefix_space(prefix_space);
+            }
+        }
+        self.refresh_encode_cache();
+        self
+    }
+
+    /// Get the currently set word boundary policy
+    pub fn get_word_boundary_policy(&self) -> WordBoundaryPolicy {
+        self.word_boundary_policy
+    }
+
+    /// Enable caching of encode results with the given capacity, or disable it
+    /// with `None`. This speeds up workloads with heavy input repetition
+    /// (duplicated web text, templated prompts): raw single-sequence inputs
+    /// are cached together with the `add_special_tokens` flag and the current
+    /// truncation/padding parameters, with approximate least-recently-used
+    /// eviction. The cache is shared with the clones of this tokenizer
+    pub fn with_encode_cache(&mut self, capacity: Option<usize>) -> &mut Self {
+        self.encode_cache = capacity.map(LruCache::new);
+        self
+    }
+
+    /// The cache key for the given input, if the cache is enabled and the
+    /// input cacheable: only raw single sequences are, keyed together with a
+    /// snapshot of the truncation/padding parameters
+    fn encode_cache_key(
+        &self,
+        input: &EncodeInput,
+        add_special_tokens: bool,
+    ) -> Option<EncodeCacheKey> {
+        self.encode_cache.as_ref()?;
+        match input {
+            EncodeInput::Single(InputSequence::Raw(sequence)) => Some((
+                sequence.to_string(),
+                add_special_tokens,
+                format!("{:?}|{:?}", self.truncation, self.padding),
+            )),
+            _ => None,
+        }
+    }
+
     /// Get the currently set padding parameters
     pub fn get_padding(&self) -> Option<&PaddingParams> {
         self.padding.as_ref()
@@ -706,6 +2041,7 @@ where
     /// set the added bocab's splitting scheme
     pub fn set_encode_special_tokens(&mut self, value: bool) {
         self.added_vocabulary.set_encode_special_tokens(value);
+        self.refresh_encode_cache();
     }
 
     /// Get added token value
@@ -713,6 +2049,31 @@ where
         self.added_vocabulary.get_encode_special_tokens()
     }
 
+    /// Allow special tokens to be extracted even when corrupted by up to
+    /// `max_distance` character edits (e.g. in OCR output). 0, the default,
+    /// means exact matching only.
+    pub fn set_fuzzy_matching(&mut self, max_distance: usize) {
+        self.added_vocabulary.set_fuzzy_matching(max_distance);
+        self.refresh_encode_cache();
+    }
+
+    pub fn get_fuzzy_matching(&self) -> usize {
+        self.added_vocabulary.get_fuzzy_matching()
+    }
+
+    /// Set the options controlling how the added tokens get extracted from
+    /// the input: what counts as a word boundary for `single_word`, and
+    /// which whitespace class `lstrip`/`rstrip` consume. They are serialized
+    /// in the tokenizer file when they differ from the defaults.
+    pub fn set_extraction_options(&mut self, options: ExtractionOptions) {
+        self.added_vocabulary.set_extraction_options(options);
+        self.refresh_encode_cache();
+    }
+
+    pub fn get_extraction_options(&self) -> ExtractionOptions {
+        self.added_vocabulary.get_extraction_options()
+    }
+
     /// Encode a single sequence
     fn encode_single_sequence(
         &self,
@@ -720,20 +2081,69 @@ where
         type_id: u32,
         offsets_type: OffsetType,
     ) -> Result<Encoding> {
-        let encode = |is_pre_tokenized, subseq_idx, subseq| -> Result<Encoding> {
-            let normalized = self
-                .added_vocabulary
-                .extract_and_normalize(self.normalizer.as_ref(), subseq);
-            let pre_tokenized = self.do_pre_tokenize(normalized)?;
-            let subseq_encoding = self.do_tokenize(
-                pre_tokenized,
-                type_id,
-                if is_pre_tokenized {
-                    Some(subseq_idx as u32)
-                } else {
-                    None
+        self.encode_single_sequence_opt(sequence, type_id, offsets_type, true, None, None)
+    }
+
+    /// Encode a single sequence, optionally skipping the added-token
+    /// extraction so that their patterns go through the model as plain text,
+    /// optionally capturing a snapshot of the normalized text, and optionally
+    /// restricting the model to a subset of the vocabulary
+    fn encode_single_sequence_opt(
+        &self,
+        sequence: InputSequence,
+        type_id: u32,
+        offsets_type: OffsetType,
+        extract_added_tokens: bool,
+        snapshot: Option<&RefCell<NormalizedSnapshot>>,
+        allowed: Option<&VocabMask>,
+    ) -> Result<Encoding> {
+        // The second sequence of a pair goes through the pair pipeline when
+        // one is configured, through the main pipeline otherwise
+        let (normalizer, pre_tokenizer) = match &self.pair_pipeline {
+            Some(pair) if type_id > 0 => (pair.normalizer.as_ref(), pair.pre_tokenizer.as_ref()),
+            _ => (self.normalizer.as_ref(), self.pre_tokenizer.as_ref()),
+        };
+        let encode = |is_pre_tokenized, subseq_idx, subseq: &str| -> Result<Encoding> {
+            if subseq.trim().is_empty() {
+                if let Some(encoding) =
+                    self.apply_empty_input_policy(subseq, type_id, offsets_type)?
+                {
+                    return Ok(encoding);
+                }
+            }
+            let normalized = self.profiled(
+                |p| &mut p.normalizer,
+                || -> Result<PreTokenizedString> {
+                    Ok(if extract_added_tokens {
+                        self.added_vocabulary
+                            .extract_and_normalize(normalizer, subseq)
+                    } else {
+                        Self::normalize_with(normalizer, subseq)?.into()
+                    })
+                },
+            )?;
+            if let Some(snapshot) = snapshot {
+                snapshot.borrow_mut().extend(&normalized);
+            }
+            let pre_tokenized = self.profiled(
+                |p| &mut p.pre_tokenizer,
+                || Self::pre_tokenize_with(pre_tokenizer, normalized),
+            )?;
+            let subseq_encoding = self.profiled(
+                |p| &mut p.model,
+                || {
+                    self.do_tokenize(
+                        pre_tokenized,
+                        type_id,
+                        if is_pre_tokenized {
+                            Some(subseq_idx as u32)
+                        } else {
+                            None
+                        },
+                        offsets_type,
+                        allowed,
+                    )
                 },
-                offsets_type,
             )?;
 
             Ok(subseq_encoding)
@@ -755,10 +2165,62 @@ where
                 .enumerate()
                 .map(|(i, sequence)| encode(true, i, sequence))
                 .collect(),
+            InputSequence::PreTokenizedWithOffsets(seq) => seq
+                .iter()
+                .enumerate()
+                .map(|(i, (sequence, offsets))| {
+                    let mut encoding = encode(true, i, sequence)?;
+                    // Shift the word-relative offsets back into the source document
+                    for token_offsets in encoding.get_offsets_mut() {
+                        token_offsets.0 += offsets.0;
+                        token_offsets.1 += offsets.0;
+                    }
+                    Ok(encoding)
+                })
+                .collect(),
             InputSequence::Raw(seq) => encode(false, 0, seq.as_ref()),
         }
     }
 
+    /// Count the tokens of a single sequence without building an `Encoding`:
+    /// run the pipeline up to the model and sum the token counts of the splits
+    fn count_single_sequence(&self, sequence: InputSequence, type_id: u32) -> Result<usize> {
+        let (normalizer, pre_tokenizer) = match &self.pair_pipeline {
+            Some(pair) if type_id > 0 => (pair.normalizer.as_ref(), pair.pre_tokenizer.as_ref()),
+            _ => (self.normalizer.as_ref(), self.pre_tokenizer.as_ref()),
+        };
+        let count = |subseq: &str| -> Result<usize> {
+            if subseq.trim().is_empty() {
+                if let Some(encoding) =
+                    self.apply_empty_input_policy(subseq, type_id, OffsetType::None)?
+                {
+                    return Ok(encoding.len());
+                }
+            }
+            let normalized = self
+                .added_vocabulary
+                .extract_and_normalize(normalizer, subseq);
+            let mut pre_tokenized = Self::pre_tokenize_with(pre_tokenizer, normalized)?;
+            self.tokenize_splits(&mut pre_tokenized, None)?;
+            Ok(pre_tokenized
+                .splits_iter(OffsetReferential::Normalized, OffsetType::None)
+                .map(|(_, _, tokens)| tokens.as_ref().map_or(0, Vec::len))
+                .sum())
+        };
+
+        match sequence {
+            InputSequence::PreTokenized(seq) => seq.iter().map(|subseq| count(subseq)).sum(),
+            InputSequence::PreTokenizedOwned(seq) => {
+                seq.iter().map(|subseq| count(subseq.as_str())).sum()
+            }
+            InputSequence::PreTokenizedCow(seq) => seq.iter().map(|subseq| count(subseq)).sum(),
+            InputSequence::PreTokenizedWithOffsets(seq) => {
+                seq.iter().map(|(subseq, _)| count(subseq)).sum()
+            }
+            InputSequence::Raw(seq) => count(seq.as_ref()),
+        }
+    }
+
     /// Encode the given input. This method accepts both single sequences, as well as pair
     /// sequences. Also, a sequence can be a string, or already pre-tokenized input directly:
     /// Contrarily to `encode`, it does not compute offsets
@@ -823,12 +2285,45 @@ where
     /// // or even both types together:
     /// tokenizer.encode(("A complete sequence", &["And", "a", "tokenized"][..]), false);
     /// ```
+    /// Encode a single long document into overlapping chunks of at most
+    /// `max_tokens` tokens, snapping the cuts to the requested boundary. The
+    /// offsets of every chunk refer to the original document. No special
+    /// tokens are added and no truncation or padding is applied.
+    ///
+    /// ```no_run
+    /// # use tokenizers::Tokenizer;
+    /// # use tokenizers::{ChunkBoundary, ChunkOptions};
+    /// # let tokenizer = Tokenizer::from_file("tokenizer.json").unwrap();
+    /// let chunks = tokenizer
+    ///     .encode_chunks(
+    ///         "Some very long document...",
+    ///         &ChunkOptions {
+    ///             max_tokens: 512,
+    ///             overlap: 64,
+    ///             boundary: ChunkBoundary::Sentence,
+    ///         },
+    ///     )
+    ///     .unwrap();
+    /// ```
+    pub fn encode_chunks(&self, text: &str, options: &ChunkOptions) -> Result<Vec<Encoding>> {
+        let encoding = self.encode_single_sequence(text.into(), 0, OffsetType::Byte)?;
+        chunk_encoding(&encoding, text, options)
+    }
+
     pub fn encode<'s, E>(&self, input: E, add_special_tokens: bool) -> Result<Encoding>
     where
         E: Into<EncodeInput<'s>>,
     {
+        let input = input.into();
+        let cache_key = self.encode_cache_key(&input, add_special_tokens);
+        if let (Some(cache), Some(key)) = (&self.encode_cache, &cache_key) {
+            if let Some(encoding) = cache.get(key) {
+                return Ok(encoding);
+            }
+        }
+
         // Extract sequences from the EncodeInput
-        let (sequence, pair) = match input.into() {
+        let (sequence, pair) = match input {
             EncodeInput::Single(s1) => (s1, None),
             EncodeInput::Dual(s1, s2) => (s1, Some(s2)),
         };
@@ -840,102 +2335,888 @@ where
             .transpose()?;
 
         // And finally post process
-        self.post_process(encoding, pair_encoding, add_special_tokens)
+        let encoding = self.post_process(encoding, pair_encoding, add_special_tokens)?;
+        if let (Some(cache), Some(key)) = (&self.encode_cache, cache_key) {
+            cache.set(key, encoding.clone());
+        }
+        Ok(encoding)
     }
 
-    /// Encode the given input, using offsets relative to chars instead of bytes.
-    /// This method accepts both single sequences, as well as pair sequences. Also,
-    /// a sequence can be a string, or already pre-tokenized input directly:
-    ///
-    /// ```
-    /// # use tokenizers::Tokenizer;
-    /// # use tokenizers::models::bpe::BPE;
-    /// # let mut tokenizer = Tokenizer::new(BPE::default());
-    /// #
-    /// // Sequences:
-    /// tokenizer.encode("Single sequence", false);
-    /// tokenizer.encode(("Sequence A", "Sequence B"), false);
+    /// Count the tokens the given input encodes to, without building an
+    /// `Encoding`: no offsets, no word ids, and no per-token allocation. Use
+    /// it when only the count matters (quota or billing, context-window
+    /// checks), it is noticeably faster than a full [`TokenizerImpl::encode`].
     ///
-    /// // Pre-tokenized sequences:
-    /// tokenizer.encode(&["Single", "sequence"][..], false);
-    /// tokenizer.encode((
-    ///     &["Sequence", "A"][..],
-    ///     &["Sequence", "B"][..]
-    /// ), false);
+    /// The count reflects the whole input: truncation and padding, which
+    /// happen during post-processing, are not applied. Special tokens the
+    /// post-processor would add are included when `add_special_tokens` is
+    /// `true`.
     ///
-    /// // or even both types together:
-    /// tokenizer.encode(("A complete sequence", &["And", "a", "tokenized"][..]), false);
+    /// ```no_run
+    /// # use tokenizers::Tokenizer;
+    /// # let tokenizer = Tokenizer::from_file("tokenizer.json").unwrap();
+    /// let count = tokenizer.count_tokens("Hello, world!", true).unwrap();
     /// ```
-    pub fn encode_char_offsets<'s, E>(&self, input: E, add_special_tokens: bool) -> Result<Encoding>
+    pub fn count_tokens<'s, E>(&self, input: E, add_special_tokens: bool) -> Result<usize>
     where
         E: Into<EncodeInput<'s>>,
     {
-        // Extract sequences from the EncodeInput
         let (sequence, pair) = match input.into() {
             EncodeInput::Single(s1) => (s1, None),
             EncodeInput::Dual(s1, s2) => (s1, Some(s2)),
         };
 
-        // Encode each sequence
-        let encoding = self.encode_single_sequence(sequence, 0, OffsetType::Char)?;
+        let is_pair = pair.is_some();
+        let mut count = self.count_single_sequence(sequence, 0)?;
+        if let Some(sequence) = pair {
+            count += self.count_single_sequence(sequence, 1)?;
+        }
+        if add_special_tokens {
+            if let Some(post_processor) = &self.post_processor {
+                count += post_processor.added_tokens(is_pair);
+            }
+        }
+        Ok(count)
+    }
+
+    /// Encode the given input, like [`TokenizerImpl::encode`], but without
+    /// extracting added tokens: their patterns found in the input go through
+    /// the normalizer, pre-tokenizer and model as plain text. Use it for
+    /// untrusted user text, so that a literal `<|im_start|>` in a message
+    /// cannot inject the corresponding special token. Special tokens added by
+    /// the post-processor are not affected, so templates still apply
+    pub fn encode_without_added_tokens<'s, E>(
+        &self,
+        input: E,
+        add_special_tokens: bool,
+    ) -> Result<Encoding>
+    where
+        E: Into<EncodeInput<'s>>,
+    {
+        let (sequence, pair) = match input.into() {
+            EncodeInput::Single(s1) => (s1, None),
+            EncodeInput::Dual(s1, s2) => (s1, Some(s2)),
+        };
+
+        let encoding =
+            self.encode_single_sequence_opt(sequence, 0, OffsetType::Byte, false, None, None)?;
         let pair_encoding = pair
-            .map(|sequence| self.encode_single_sequence(sequence, 1, OffsetType::Char))
+            .map(|sequence| {
+                self.encode_single_sequence_opt(sequence, 1, OffsetType::Byte, false, None, None)
+            })
             .transpose()?;
 
-        // And finally post process
         self.post_process(encoding, pair_encoding, add_special_tokens)
     }
 
-    /// Decode the given ids, back to a String
-    pub fn decode(&self, ids: &[u32], skip_special_tokens: bool) -> Result<String> {
-        let tokens = ids
-            .iter()
-            .filter_map(|id| {
-                self.added_vocabulary
-                    .simple_id_to_token(*id)
-                    .or_else(|| self.model.id_to_token(*id))
-                    .filter(|token| {
-                        !skip_special_tokens || !self.added_vocabulary.is_special_token(token)
-                    })
+    /// Encode the given input, like [`TokenizerImpl::encode`], restricting
+    /// the output ids to the subset of the vocabulary in `allowed`: the model
+    /// picks an alternative segmentation instead of producing an excluded
+    /// token (BPE skips the merges building one, Unigram masks the
+    /// corresponding lattice nodes), which is useful to build inverted
+    /// indexes over a reduced lexicon. An input that cannot be represented
+    /// with the allowed subset is an error. Added tokens and the special
+    /// tokens of the post-processor bypass the model, so they are not
+    /// constrained
+    pub fn encode_constrained<'s, E>(
+        &self,
+        input: E,
+        allowed: &VocabMask,
+        add_special_tokens: bool,
+    ) -> Result<Encoding>
+    where
+        E: Into<EncodeInput<'s>>,
+    {
+        let (sequence, pair) = match input.into() {
+            EncodeInput::Single(s1) => (s1, None),
+            EncodeInput::Dual(s1, s2) => (s1, Some(s2)),
+        };
+
+        let encoding = self.encode_single_sequence_opt(
+            sequence,
+            0,
+            OffsetType::Byte,
+            true,
+            None,
+            Some(allowed),
+        )?;
+        let pair_encoding = pair
+            .map(|sequence| {
+                self.encode_single_sequence_opt(
+                    sequence,
+                    1,
+                    OffsetType::Byte,
+                    true,
+                    None,
+                    Some(allowed),
+                )
             })
-            .collect::<Vec<_>>();
+            .transpose()?;
 
-        if let Some(decoder) = &self.decoder {
-            decoder.decode(tokens)
-        } else {
-            Ok(tokens.join(" "))
-        }
+        self.post_process(encoding, pair_encoding, add_special_tokens)
     }
-}
 
-impl<M, N, PT, PP, D> TokenizerImpl<M, N, PT, PP, D>
-where
-    M: Model,
-{
-    /// Tokenization logic, makes the bridge between the pre-tokenization phase and the real
-    /// tokenization phase, and converting offsets back to the original referential.
-    fn do_tokenize<P: Into<PreTokenizedString>>(
-        &self,
-        pretokenized: P,
-        type_id: u32,
-        word_idx: Option<u32>,
-        offsets_type: OffsetType,
-    ) -> Result<Encoding> {
-        let mut pretokenized: PreTokenizedString = pretokenized.into();
-        pretokenized.tokenize(|normalized| self.model.tokenize(normalized.get()))?;
-        pretokenized.into_encoding(word_idx, type_id, offsets_type)
+    /// Encode the given input, like [`TokenizerImpl::encode`], additionally
+    /// attaching the model-assigned score of each token to the resulting
+    /// [`Encoding`] when the model supports it: the Unigram log-probabilities,
+    /// or the summed BPE merge ranks. This gives data-quality filters a way to
+    /// spot documents whose tokenization is anomalous, e.g. mostly made of
+    /// rare tokens. Tokens the model cannot score, like added tokens, get a
+    /// `None` score
+    pub fn encode_scored<'s, E>(&self, input: E, add_special_tokens: bool) -> Result<Encoding>
+    where
+        E: Into<EncodeInput<'s>>,
+    {
+        let mut encoding = self.encode(input, add_special_tokens)?;
+        let scores = self.model.token_scores(encoding.get_ids());
+        if let Some(scores) = scores {
+            encoding.set_scores(scores);
+            for overflowing in encoding.get_overflowing_mut() {
+                let scores = self.model.token_scores(overflowing.get_ids());
+                if let Some(scores) = scores {
+                    overflowing.set_scores(scores);
+                }
+            }
+        }
+        Ok(encoding)
+    }
+
+    /// Encode the given input like [`TokenizerImpl::encode`], additionally
+    /// returning a snapshot of the normalized text each sequence was
+    /// tokenized from, with per-byte alignments back to the original input,
+    /// so audit tooling can reconstruct exactly what string reached the
+    /// model without re-running the normalizer
+    pub fn encode_with_snapshots<'s, E>(
+        &self,
+        input: E,
+        add_special_tokens: bool,
+    ) -> Result<(Encoding, Vec<NormalizedSnapshot>)>
+    where
+        E: Into<EncodeInput<'s>>,
+    {
+        let (sequence, pair) = match input.into() {
+            EncodeInput::Single(s1) => (s1, None),
+            EncodeInput::Dual(s1, s2) => (s1, Some(s2)),
+        };
+
+        let mut snapshots = vec![];
+        let snapshot = RefCell::new(NormalizedSnapshot::default());
+        let encoding = self.encode_single_sequence_opt(
+            sequence,
+            0,
+            OffsetType::Byte,
+            true,
+            Some(&snapshot),
+            None,
+        )?;
+        snapshots.push(snapshot.take());
+        let pair_encoding = match pair {
+            Some(sequence) => {
+                let encoding = self.encode_single_sequence_opt(
+                    sequence,
+                    1,
+                    OffsetType::Byte,
+                    true,
+                    Some(&snapshot),
+                    None,
+                )?;
+                snapshots.push(snapshot.take());
+                Some(encoding)
+            }
+            None => None,
+        };
+
+        let encoding = self.post_process(encoding, pair_encoding, add_special_tokens)?;
+        Ok((encoding, snapshots))
+    }
+
+    /// Encode multiple fields under a shared token budget: each field is
+    /// encoded separately with its index as type id, the `max_length` budget
+    /// of `params` is distributed over the fields according to their
+    /// priorities and min/max allocations (see
+    /// [`truncate_with_budget`]), and the truncated fields are merged in
+    /// order into a single encoding. Returns the merged encoding along with
+    /// what was dropped from each field. The post-processor is not applied,
+    /// since it only knows about single sequences and pairs
+    pub fn encode_with_budget<'s, E>(
+        &self,
+        fields: Vec<E>,
+        params: &BudgetTruncation,
+    ) -> Result<(Encoding, Vec<Encoding>)>
+    where
+        E: Into<InputSequence<'s>>,
+    {
+        let encodings = fields
+            .into_iter()
+            .enumerate()
+            .map(|(i, field)| self.encode_single_sequence(field.into(), i as u32, OffsetType::Byte))
+            .collect::<Result<Vec<_>>>()?;
+        let (kept, dropped) = truncate_with_budget(encodings, params)?;
+        Ok((Encoding::merge(kept, false), dropped))
+    }
+
+    /// Tokenize a corpus of documents and pack them into sequences of exactly
+    /// `seq_len` tokens for pretraining, inserting document-boundary tokens
+    /// according to `sep_policy`. Documents are encoded without special
+    /// tokens, concatenated in order, and split whenever `seq_len` is
+    /// reached, so a document can span several packed sequences. Each
+    /// [`PackedSequence`] carries per-position segment ids from which a
+    /// block-diagonal attention mask can be built. The last sequence may be
+    /// shorter than `seq_len`; pad or drop it as the training setup requires
+    pub fn encode_packed<'s, I, E>(
+        &self,
+        corpus: I,
+        seq_len: usize,
+        sep_policy: &SepPolicy,
+    ) -> Result<Vec<PackedSequence>>
+    where
+        I: IntoIterator<Item = E>,
+        E: Into<InputSequence<'s>>,
+    {
+        if seq_len == 0 {
+            return Err(Box::new(crate::utils::packing::PackingError::ZeroSeqLen));
+        }
+        let sep_id = match sep_policy {
+            SepPolicy::None => None,
+            SepPolicy::Between(token) | SepPolicy::Suffix(token) => {
+                Some(self.token_to_id(token).ok_or_else(|| {
+                    Box::new(crate::utils::packing::PackingError::SeparatorNotInVocab(
+                        token.clone(),
+                    ))
+                })?)
+            }
+        };
+
+        let mut sequences = vec![];
+        let mut ids = Vec::with_capacity(seq_len);
+        let mut segment_ids = Vec::with_capacity(seq_len);
+        let mut documents = corpus.into_iter().peekable();
+        while let Some(document) = documents.next() {
+            let encoding = self.encode_single_sequence(document.into(), 0, OffsetType::Byte)?;
+            let separator = match sep_policy {
+                SepPolicy::None => None,
+                SepPolicy::Between(_) if documents.peek().is_none() => None,
+                _ => sep_id,
+            };
+
+            let mut segment = segment_ids.last().map_or(0, |&s: &u32| s + 1);
+            for &id in encoding.get_ids().iter().chain(separator.iter()) {
+                ids.push(id);
+                segment_ids.push(segment);
+                if ids.len() == seq_len {
+                    sequences.push(PackedSequence {
+                        ids: std::mem::replace(&mut ids, Vec::with_capacity(seq_len)),
+                        segment_ids: std::mem::replace(
+                            &mut segment_ids,
+                            Vec::with_capacity(seq_len),
+                        ),
+                    });
+                    // The rest of the document restarts at segment 0 of the
+                    // next sequence
+                    segment = 0;
+                }
+            }
+        }
+        if !ids.is_empty() {
+            sequences.push(PackedSequence { ids, segment_ids });
+        }
+        Ok(sequences)
+    }
+
+    /// Dry-run the pipeline on the given samples, checking the invariants
+    /// each component is supposed to uphold: the normalizer must keep the
+    /// alignments consistent (see [`check_alignments`]), the pre-tokenizer
+    /// must produce non-empty, in-bounds, non-overlapping splits in order,
+    /// and the encoding offsets must stay monotonic. Component errors are
+    /// collected as issues instead of being propagated, and the
+    /// `decode(encode(x))` round-trip distance of every sample is reported,
+    /// so lossy components stand out. Invaluable when composing custom
+    /// normalizers or pre-tokenizers, where an alignment bug only shows up
+    /// much later as corrupted offsets.
+    pub fn validate_pipeline(&self, samples: &[&str]) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        for (index, &sample) in samples.iter().enumerate() {
+            // 1. The normalizer must keep the alignments consistent
+            let mut normalized = NormalizedString::from(sample);
+            let mut normalizer_ok = true;
+            if let Some(normalizer) = &self.normalizer {
+                if let Err(err) = normalizer.normalize(&mut normalized) {
+                    report.issue("normalizer", index, format!("normalization failed: {err}"));
+                    normalizer_ok = false;
+                } else if let Err(err) = check_alignments(sample, &normalized) {
+                    report.issue("normalizer", index, err.to_string());
+                    normalizer_ok = false;
+                }
+            }
+
+            // 2. The pre-tokenizer must produce non-empty, in-bounds,
+            // non-overlapping splits, in order
+            if normalizer_ok {
+                let mut pre_tokenized = PreTokenizedString::from(normalized);
+                if let Some(pre_tokenizer) = &self.pre_tokenizer {
+                    if let Err(err) = pre_tokenizer.pre_tokenize(&mut pre_tokenized) {
+                        report.issue(
+                            "pre_tokenizer",
+                            index,
+                            format!("pre-tokenization failed: {err}"),
+                        );
+                    } else {
+                        let mut previous_end = 0;
+                        for (split, (start, end), _) in
+                            pre_tokenized.get_splits(OffsetReferential::Original, OffsetType::Byte)
+                        {
+                            if split.is_empty() {
+                                report.issue(
+                                    "pre_tokenizer",
+                                    index,
+                                    format!("empty split at offsets {:?}", (start, end)),
+                                );
+                            }
+                            if end < start || end > sample.len() {
+                                report.issue(
+                                    "pre_tokenizer",
+                                    index,
+                                    format!(
+                                        "split offsets {:?} exceed the input length {}",
+                                        (start, end),
+                                        sample.len()
+                                    ),
+                                );
+                            } else if start < previous_end {
+                                report.issue(
+                                        "pre_tokenizer",
+                                        index,
+                                        format!(
+                                            "split at offsets {:?} overlaps the previous one ending at {}",
+                                            (start, end),
+                                            previous_end
+                                        ),
+                                    );
+                            } else {
+                                previous_end = end;
+                            }
+                        }
+                    }
+                }
+            }
+
+            // 3. The whole pipeline must produce monotonic, in-bounds
+            // offsets, and the round-trip through decode should stay close
+            // to the input
+            match self.encode(sample, false) {
+                Ok(encoding) => {
+                    let mut previous_end = 0;
+                    for (&(start, end), &special) in encoding
+                        .get_offsets()
+                        .iter()
+                        .zip(encoding.get_special_tokens_mask())
+                    {
+                        if special == 1 {
+                            continue;
+                        }
+                        if end < start || end > sample.len() {
+                            report.issue(
+                                "model",
+                                index,
+                                format!(
+                                    "token offsets {:?} exceed the input length {}",
+                                    (start, end),
+                                    sample.len()
+                                ),
+                            );
+                        } else if start < previous_end {
+                            report.issue(
+                                "model",
+                                index,
+                                format!(
+                                    "token offsets {:?} go backwards past {}",
+                                    (start, end),
+                                    previous_end
+                                ),
+                            );
+                        } else {
+                            previous_end = end;
+                        }
+                    }
+                    match self.decode(encoding.get_ids(), true) {
+                        Ok(decoded) => report.round_trip_distances.push(
+                            crate::utils::validation::round_trip_distance(sample, &decoded),
+                        ),
+                        Err(err) => {
+                            report.issue("decoder", index, format!("decoding failed: {err}"));
+                            report.round_trip_distances.push(1.0);
+                        }
+                    }
+                }
+                Err(err) => {
+                    report.issue("model", index, format!("encoding failed: {err}"));
+                    report.round_trip_distances.push(1.0);
+                }
+            }
+        }
+        report
+    }
+
+    /// Encode `suffix` as the continuation of an already encoded prompt
+    /// prefix, reusing `prefix_encoding` instead of re-tokenizing the whole
+    /// prompt, so that servers with a large static system prompt only pay
+    /// for the new part. The trailing tokens of the prefix are decoded and
+    /// re-tokenized together with the suffix, so that merges crossing the
+    /// junction come out as if the whole prompt had been encoded in one
+    /// call. The prefix must have been encoded without special tokens; the
+    /// post-processor runs on the combined result when `add_special_tokens`
+    /// is set. The offsets of the re-tokenized part follow the prefix ones,
+    /// and are only exact when the decoder restores the window text to its
+    /// original length.
+    pub fn encode_with_prefix(
+        &self,
+        prefix_encoding: &Encoding,
+        suffix: &str,
+        add_special_tokens: bool,
+    ) -> Result<Encoding> {
+        // The number of trailing prefix tokens re-tokenized with the suffix,
+        // enough to cover the merges crossing the junction in practice
+        const OVERLAP: usize = 8;
+
+        let overlap = prefix_encoding.len().min(OVERLAP);
+        let kept = prefix_encoding.len() - overlap;
+        let window_text = self.decode(&prefix_encoding.get_ids()[kept..], false)?;
+
+        let mut encoding = prefix_encoding.clone();
+        encoding.truncate(kept, 0, TruncationDirection::Right);
+        encoding.take_overflowing();
+
+        let tail = self.encode_single_sequence(
+            format!("{}{}", window_text, suffix).into(),
+            0,
+            OffsetType::Byte,
+        )?;
+        encoding.merge_with(tail, true);
+        encoding.set_sequence_id(0);
+
+        self.post_process(encoding, None, add_special_tokens)
+    }
+
+    /// Prepare `prompt` for token-healed generation: the prompt is encoded
+    /// without special tokens, its final token is trimmed off, and the ids of
+    /// all the model tokens whose string representation extends the trimmed
+    /// one are returned alongside the shortened encoding. Constraining the
+    /// first generated token to that set lets a generation engine regrow the
+    /// boundary token, instead of being locked into the token the prompt
+    /// happened to end in ("token healing"). The comparison uses the model's
+    /// own token strings, so byte-level or metaspace conventions are handled
+    /// transparently; the candidate set always contains the trimmed token
+    /// itself, sorted by ascending id. An empty prompt, or one ending in an
+    /// added token, is returned untrimmed with no candidates.
+    pub fn token_healing(&self, prompt: &str) -> Result<(Encoding, Vec<u32>)> {
+        let mut encoding = self.encode(prompt, false)?;
+        if encoding.is_empty() {
+            return Ok((encoding, vec![]));
+        }
+        let last = encoding.len() - 1;
+        if encoding.get_special_tokens_mask()[last] == 1
+            || self
+                .added_vocabulary
+                .is_special_token(&encoding.get_tokens()[last])
+        {
+            return Ok((encoding, vec![]));
+        }
+        let partial = encoding.get_tokens()[last].clone();
+        encoding.truncate(last, 0, TruncationDirection::Right);
+        encoding.take_overflowing();
+
+        let mut candidates: Vec<u32> = self
+            .model
+            .get_vocab_iter()
+            .filter(|(token, _)| token.starts_with(&partial))
+            .map(|(_, id)| id)
+            .collect();
+        candidates.sort_unstable();
+        Ok((encoding, candidates))
+    }
+
+    /// Encode the given input, using offsets relative to chars instead of bytes.
+    /// This method accepts both single sequences, as well as pair sequences. Also,
+    /// a sequence can be a string, or already pre-tokenized input directly:
+    ///
+    /// ```
+    /// # use tokenizers::Tokenizer;
+    /// # use tokenizers::models::bpe::BPE;
+    /// # let mut tokenizer = Tokenizer::new(BPE::default());
+    /// #
+    /// // Sequences:
+    /// tokenizer.encode("Single sequence", false);
+    /// tokenizer.encode(("Sequence A", "Sequence B"), false);
+    ///
+    /// // Pre-tokenized sequences:
+    /// tokenizer.encode(&["Single", "sequence"][..], false);
+    /// tokenizer.encode((
+    ///     &["Sequence", "A"][..],
+    ///     &["Sequence", "B"][..]
+    /// ), false);
+    ///
+    /// // or even both types together:
+    /// tokenizer.encode(("A complete sequence", &["And", "a", "tokenized"][..]), false);
+    /// ```
+    pub fn encode_char_offsets<'s, E>(&self, input: E, add_special_tokens: bool) -> Result<Encoding>
+    where
+        E: Into<EncodeInput<'s>>,
+    {
+        // Extract sequences from the EncodeInput
+        let (sequence, pair) = match input.into() {
+            EncodeInput::Single(s1) => (s1, None),
+            EncodeInput::Dual(s1, s2) => (s1, Some(s2)),
+        };
+
+        // Encode each sequence
+        let encoding = self.encode_single_sequence(sequence, 0, OffsetType::Char)?;
+        let pair_encoding = pair
+            .map(|sequence| self.encode_single_sequence(sequence, 1, OffsetType::Char))
+            .transpose()?;
+
+        // And finally post process
+        self.post_process(encoding, pair_encoding, add_special_tokens)
+    }
+
+    /// Encode the given input, using offsets relative to extended grapheme
+    /// clusters instead of bytes: the "user-perceived characters" that UIs
+    /// highlight, so a token covering `e` plus a combining accent spans a
+    /// single position. This method accepts the same inputs as
+    /// [`TokenizerImpl::encode`].
+    pub fn encode_grapheme_offsets<'s, E>(
+        &self,
+        input: E,
+        add_special_tokens: bool,
+    ) -> Result<Encoding>
+    where
+        E: Into<EncodeInput<'s>>,
+    {
+        // Extract sequences from the EncodeInput
+        let (sequence, pair) = match input.into() {
+            EncodeInput::Single(s1) => (s1, None),
+            EncodeInput::Dual(s1, s2) => (s1, Some(s2)),
+        };
+
+        // Encode each sequence
+        let encoding = self.encode_single_sequence(sequence, 0, OffsetType::Grapheme)?;
+        let pair_encoding = pair
+            .map(|sequence| self.encode_single_sequence(sequence, 1, OffsetType::Grapheme))
+            .transpose()?;
+
+        // And finally post process
+        self.post_process(encoding, pair_encoding, add_special_tokens)
+    }
+
+    /// Encode the given sequence, also returning a word-level view of the
+    /// resulting encoding: the word strings with their offsets and covering
+    /// tokens, computed in one pass from the word ids.
+    ///
+    /// ```
+    /// # use tokenizers::Tokenizer;
+    /// # use tokenizers::models::bpe::BPE;
+    /// # let tokenizer = Tokenizer::new(BPE::default());
+    /// #
+    /// tokenizer.encode_with_word_views("Single sequence", false);
+    /// ```
+    pub fn encode_with_word_views(
+        &self,
+        sequence: &str,
+        add_special_tokens: bool,
+    ) -> Result<(Encoding, Vec<WordView>)> {
+        let encoding = self.encode(sequence, add_special_tokens)?;
+        let word_views = encoding.get_word_views(0, sequence);
+        Ok((encoding, word_views))
+    }
+
+    /// Encode the given raw bytes as a single sequence, handling invalid UTF-8 with the
+    /// provided [`InvalidUtf8Policy`]. When the bytes are valid UTF-8 (the common case,
+    /// for example when tokenizing directly from an mmap'd corpus), no copy of the input
+    /// is made. The offsets of the resulting `Encoding` refer to the decoded string,
+    /// which only differs from the input bytes around invalid sequences.
+    ///
+    /// ```
+    /// # use tokenizers::{InvalidUtf8Policy, Tokenizer};
+    /// # use tokenizers::models::bpe::BPE;
+    /// # let tokenizer = Tokenizer::new(BPE::default());
+    /// #
+    /// tokenizer.encode_bytes(b"A raw sequence", InvalidUtf8Policy::Replace, false);
+    /// ```
+    pub fn encode_bytes(
+        &self,
+        bytes: &[u8],
+        policy: InvalidUtf8Policy,
+        add_special_tokens: bool,
+    ) -> Result<Encoding> {
+        let sequence = bytes_to_string(bytes, policy)?;
+        self.encode(sequence.as_ref(), add_special_tokens)
+    }
+
+    /// Same as [`TokenizerImpl::encode_bytes`], with offsets relative to chars of the
+    /// decoded string instead of bytes.
+    pub fn encode_bytes_char_offsets(
+        &self,
+        bytes: &[u8],
+        policy: InvalidUtf8Policy,
+        add_special_tokens: bool,
+    ) -> Result<Encoding> {
+        let sequence = bytes_to_string(bytes, policy)?;
+        self.encode_char_offsets(sequence.as_ref(), add_special_tokens)
+    }
+
+    /// Decode the given ids, back to a String
+    pub fn decode(&self, ids: &[u32], skip_special_tokens: bool) -> Result<String> {
+        let tokens = ids
+            .iter()
+            .filter_map(|id| {
+                self.added_vocabulary
+                    .simple_id_to_token(*id)
+                    .or_else(|| self.model.id_to_token(*id))
+                    .filter(|token| {
+                        !skip_special_tokens || !self.added_vocabulary.is_special_token(token)
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        if let Some(decoder) = &self.decoder {
+            decoder.decode(tokens)
+        } else {
+            Ok(tokens.join(" "))
+        }
+    }
+
+    /// Decode the given ids like [`TokenizerImpl::decode`], additionally
+    /// mapping the decoded text back to the ids: `alignments[i]` is the range
+    /// of bytes of the text that `ids[i]` produced, or `None` when the token
+    /// was skipped or when the decoder could not attribute a span to it.
+    pub fn decode_with_alignment(
+        &self,
+        ids: &[u32],
+        skip_special_tokens: bool,
+    ) -> Result<DecodedWithAlignment> {
+        let mut indices = Vec::with_capacity(ids.len());
+        let mut tokens = Vec::with_capacity(ids.len());
+        for (idx, id) in ids.iter().enumerate() {
+            if let Some(token) = self
+                .added_vocabulary
+                .simple_id_to_token(*id)
+                .or_else(|| self.model.id_to_token(*id))
+            {
+                if !skip_special_tokens || !self.added_vocabulary.is_special_token(&token) {
+                    indices.push(idx);
+                    tokens.push(token);
+                }
+            }
+        }
+
+        let decoded = if let Some(decoder) = &self.decoder {
+            decoder.decode_with_alignment(tokens)?
+        } else {
+            // Join with spaces, attributing to each token its own text only
+            let mut text = String::new();
+            let alignments = tokens
+                .into_iter()
+                .map(|token| {
+                    if !text.is_empty() {
+                        text.push(' ');
+                    }
+                    let start = text.len();
+                    text.push_str(&token);
+                    Some((start, text.len()))
+                })
+                .collect();
+            DecodedWithAlignment { text, alignments }
+        };
+
+        // Scatter the alignments back to the positions of `ids`, leaving
+        // `None` for the skipped tokens
+        let mut alignments = vec![None; ids.len()];
+        for (idx, alignment) in indices.into_iter().zip(decoded.alignments) {
+            alignments[idx] = alignment;
+        }
+        Ok(DecodedWithAlignment {
+            text: decoded.text,
+            alignments,
+        })
+    }
+}
+
+impl<M, N, PT, PP, D> TokenizerImpl<M, N, PT, PP, D>
+where
+    M: Model,
+{
+    /// Tokenization logic, makes the bridge between the pre-tokenization phase and the real
+    /// tokenization phase, and converting offsets back to the original referential.
+    fn do_tokenize<P: Into<PreTokenizedString>>(
+        &self,
+        pretokenized: P,
+        type_id: u32,
+        word_idx: Option<u32>,
+        offsets_type: OffsetType,
+        allowed: Option<&VocabMask>,
+    ) -> Result<Encoding> {
+        let mut pretokenized: PreTokenizedString = pretokenized.into();
+        self.tokenize_splits(&mut pretokenized, allowed)?;
+        pretokenized.into_encoding_with_policy(
+            word_idx,
+            type_id,
+            offsets_type,
+            self.offset_recovery,
+        )
+    }
+
+    /// Run the model on every split that does not carry tokens yet (added
+    /// tokens already do), applying the current `UnkPolicy`
+    fn tokenize_splits(
+        &self,
+        pretokenized: &mut PreTokenizedString,
+        allowed: Option<&VocabMask>,
+    ) -> Result<()> {
+        let unk_id = match self.unk_policy {
+            UnkPolicy::Emit => None,
+            _ => self
+                .model
+                .unk_token()
+                .and_then(|token| self.model.token_to_id(&token)),
+        };
+        pretokenized.tokenize_with_context(|prev, normalized, next| {
+            let tokens = match allowed {
+                Some(allowed) => self.model.tokenize_constrained(normalized.get(), allowed)?,
+                None => self
+                    .model
+                    .tokenize_with_context(prev, normalized.get(), next)?,
+            };
+            match unk_id {
+                Some(unk_id) => self.apply_unk_policy(tokens, normalized.get(), unk_id),
+                None => Ok(tokens),
+            }
+        })
+    }
+
+    /// Apply the current `UnkPolicy` to the tokens produced by the model for
+    /// the given sequence, whose unknown token has the given id
+    fn apply_unk_policy(
+        &self,
+        tokens: Vec<Token>,
+        sequence: &str,
+        unk_id: u32,
+    ) -> Result<Vec<Token>> {
+        if !tokens.iter().any(|token| token.id == unk_id) {
+            return Ok(tokens);
+        }
+        match self.unk_policy {
+            UnkPolicy::Emit => Ok(tokens),
+            UnkPolicy::Skip => Ok(tokens.into_iter().filter(|t| t.id != unk_id).collect()),
+            UnkPolicy::Error => {
+                let token = tokens.iter().find(|t| t.id == unk_id).unwrap();
+                Err(format!(
+                    "Unknown token for `{}` at offsets {:?}",
+                    &sequence[token.offsets.0..token.offsets.1],
+                    token.offsets
+                )
+                .into())
+            }
+            UnkPolicy::ByteFallback => Ok(tokens
+                .into_iter()
+                .flat_map(|token| {
+                    if token.id != unk_id {
+                        return vec![token];
+                    }
+                    let text = &sequence[token.offsets.0..token.offsets.1];
+                    text.bytes()
+                        .map(|byte| {
+                            let repr = format!("<0x{byte:02X}>");
+                            self.model
+                                .token_to_id(&repr)
+                                .map(|id| Token::new(id, repr, token.offsets))
+                        })
+                        .collect::<Option<Vec<_>>>()
+                        // Some byte token is missing from the vocabulary:
+                        // keep the unknown token
+                        .unwrap_or_else(|| vec![token])
+                })
+                .collect()),
+        }
+    }
+
+    /// Apply the current `EmptyInputPolicy` to an empty or whitespace-only
+    /// sequence. `None` sends the sequence through the regular pipeline; the
+    /// emitted token, if any, spans the whole input in the requested offset
+    /// type
+    fn apply_empty_input_policy(
+        &self,
+        sequence: &str,
+        type_id: u32,
+        offsets_type: OffsetType,
+    ) -> Result<Option<Encoding>> {
+        use unicode_segmentation::UnicodeSegmentation;
+
+        let end = match offsets_type {
+            OffsetType::Byte | OffsetType::None => sequence.len(),
+            OffsetType::Char => sequence.chars().count(),
+            OffsetType::Grapheme => sequence.graphemes(true).count(),
+        };
+        match self.empty_input_policy {
+            EmptyInputPolicy::Passthrough => Ok(None),
+            EmptyInputPolicy::Empty => Ok(Some(Encoding::default())),
+            EmptyInputPolicy::Unk => {
+                let token = self
+                    .model
+                    .unk_token()
+                    .ok_or("EmptyInputPolicy::Unk requires a model with an unknown token")?;
+                let id = self.model.token_to_id(&token).ok_or(
+                    "EmptyInputPolicy::Unk requires the unknown token to be part of the vocabulary",
+                )?;
+                Ok(Some(Encoding::from_tokens(
+                    vec![Token::new(id, token, (0, end))],
+                    type_id,
+                )))
+            }
+            EmptyInputPolicy::Pad => {
+                let params = self
+                    .padding
+                    .as_ref()
+                    .ok_or("EmptyInputPolicy::Pad requires padding parameters")?;
+                Ok(Some(Encoding::from_tokens(
+                    vec![Token::new(
+                        params.pad_id,
+                        params.pad_token.clone(),
+                        (0, end),
+                    )],
+                    type_id,
+                )))
+            }
+            EmptyInputPolicy::Error => {
+                Err(format!("Cannot encode the empty or whitespace-only input {sequence:?}").into())
+            }
+        }
+    }
+}
+
+impl<M, N, PT, PP, D> TokenizerImpl<M, N, PT, PP, D>
+where
+    N: Normalizer,
+{
+    /// Normalize the given sequence like the encoding pipeline would, going
+    /// through all the normalizers. Useful to inspect an intermediate pipeline
+    /// stage, e.g. in linters or data-inspection tools.
+    pub fn normalize_str(&self, sequence: &str) -> Result<NormalizedString> {
+        self.do_normalize(sequence)
     }
-}
 
-impl<M, N, PT, PP, D> TokenizerImpl<M, N, PT, PP, D>
-where
-    N: Normalizer,
-{
     /// Normalization logic, go through all normalizers
     fn do_normalize<V: Into<NormalizedString>>(&self, normalized: V) -> Result<NormalizedString> {
+        Self::normalize_with(self.normalizer.as_ref(), normalized)
+    }
+
+    /// Normalization logic with an explicit normalizer, so the pair pipeline
+    /// can substitute its own
+    fn normalize_with<V: Into<NormalizedString>>(
+        normalizer: Option<&N>,
+        normalized: V,
+    ) -> Result<NormalizedString> {
         let mut normalized: NormalizedString = normalized.into();
 
-        if let Some(ref normalizer) = self.normalizer {
+        if let Some(normalizer) = normalizer {
             normalizer.normalize(&mut normalized)?;
         }
 
@@ -951,14 +3232,82 @@ where
     /// Register the given tokens as special tokens. This is especially useful for removing
     /// these special tokens while decoding
     pub fn add_special_tokens(&mut self, tokens: &[AddedToken]) -> usize {
-        self.added_vocabulary
-            .add_special_tokens(tokens, &self.model, self.normalizer.as_ref())
+        let added =
+            self.added_vocabulary
+                .add_special_tokens(tokens, &self.model, self.normalizer.as_ref());
+        self.refresh_encode_cache();
+        added
     }
 
     /// Add the given tokens to the added vocabulary
     pub fn add_tokens(&mut self, tokens: &[AddedToken]) -> usize {
+        let added = self
+            .added_vocabulary
+            .add_tokens(tokens, &self.model, self.normalizer.as_ref());
+        self.refresh_encode_cache();
+        added
+    }
+
+    /// Add the given tokens to the added vocabulary without rebuilding the
+    /// matching automata: the tokens are not extracted until
+    /// [`TokenizerImpl::rebuild_added_tokens`] gets called. Adding thousands
+    /// of tokens in several batches this way is much faster than rebuilding
+    /// after every batch.
+    pub fn add_tokens_lazy(&mut self, tokens: Vec<AddedToken>) -> usize {
+        let added = self.added_vocabulary.add_tokens_lazy(tokens, &self.model);
+        self.refresh_encode_cache();
+        added
+    }
+
+    /// Rebuild the added-token matching automata after one or more
+    /// [`TokenizerImpl::add_tokens_lazy`] calls
+    pub fn rebuild_added_tokens(&mut self) {
         self.added_vocabulary
-            .add_tokens(tokens, &self.model, self.normalizer.as_ref())
+            .rebuild(&self.model, self.normalizer.as_ref());
+        self.refresh_encode_cache();
+    }
+
+    /// Register a whole family of special tokens at once and return the range
+    /// of their ids, e.g. `add_special_token_family("<extra_id_{n}>", 100)`
+    /// for the T5 sentinels: the template is expanded with `{n}` running from
+    /// 0 to `count - 1`, as [`AddedToken::family`] does. When none of the
+    /// tokens existed before, they get contiguous ids and the range covers
+    /// exactly the family; tokens registered earlier keep their old ids, and
+    /// the range then spans from the lowest id of the family to the highest.
+    pub fn add_special_token_family(&mut self, template: &str, count: usize) -> Range<u32> {
+        let tokens = AddedToken::from(template, true).family(count);
+        self.add_special_tokens(&tokens);
+        let ids = tokens.iter().filter_map(|token| {
+            self.added_vocabulary
+                .token_to_id(&token.content, &self.model)
+        });
+        match ids.fold(None, |acc: Option<(u32, u32)>, id| match acc {
+            Some((min, max)) => Some((min.min(id), max.max(id))),
+            None => Some((id, id)),
+        }) {
+            Some((min, max)) => min..max + 1,
+            None => 0..0,
+        }
+    }
+}
+
+impl<M, N, PT, PP, D> TokenizerImpl<M, N, PT, PP, D>
+where
+    N: Normalizer,
+    PT: PreTokenizer,
+{
+    /// Normalize then pre-tokenize the given sequence like the encoding
+    /// pipeline would, returning the splits with their offsets in the original
+    /// sequence. Useful to inspect an intermediate pipeline stage, e.g. in
+    /// linters or data-inspection tools.
+    pub fn pre_tokenize_str(&self, sequence: &str) -> Result<Vec<(String, Offsets)>> {
+        let normalized = self.do_normalize(sequence)?;
+        let pre_tokenized = self.do_pre_tokenize(normalized)?;
+        Ok(pre_tokenized
+            .get_splits(OffsetReferential::Original, OffsetType::Byte)
+            .into_iter()
+            .map(|(split, offsets, _)| (split.to_owned(), offsets))
+            .collect())
     }
 }
 
@@ -970,9 +3319,18 @@ where
     fn do_pre_tokenize<P: Into<PreTokenizedString>>(
         &self,
         pretokenized: P,
+    ) -> Result<PreTokenizedString> {
+        Self::pre_tokenize_with(self.pre_tokenizer.as_ref(), pretokenized)
+    }
+
+    /// PreTokenization logic with an explicit pre-tokenizer, so the pair
+    /// pipeline can substitute its own
+    fn pre_tokenize_with<P: Into<PreTokenizedString>>(
+        pre_tokenizer: Option<&PT>,
+        pretokenized: P,
     ) -> Result<PreTokenizedString> {
         let mut pretokenized: PreTokenizedString = pretokenized.into();
-        if let Some(ref pretok) = self.pre_tokenizer {
+        if let Some(pretok) = pre_tokenizer {
             pretok.pre_tokenize(&mut pretokenized)?;
         }
 
@@ -980,6 +3338,65 @@ where
     }
 }
 
+impl<M, N, PT, PP, D> TokenizerImpl<M, N, PT, PP, D> {
+    /// Replace the encode cache with a fresh one of the same capacity, when a
+    /// pipeline change makes its entries stale. The storage is not cleared in
+    /// place, as clones of this tokenizer may still rely on it
+    fn refresh_encode_cache(&mut self) {
+        self.encode_cache = self.encode_cache.as_ref().map(LruCache::fresh);
+    }
+
+    /// Enable or disable profiling of the encode calls. When enabled, every
+    /// encode records the per-component wall time (and allocation counts,
+    /// when [`crate::utils::profiling::CountingAllocator`] is installed) into
+    /// an aggregated [`EncodeProfile`], retrievable with
+    /// [`TokenizerImpl::take_profile`]. Clones of the tokenizer share the
+    /// profile, so batch encodes aggregate into a single one
+    pub fn with_profiling(&mut self, enabled: bool) -> &mut Self {
+        self.profiling = enabled.then(Default::default);
+        self
+    }
+
+    /// Configure how the batch entry points (`encode_batch`, `decode_batch`,
+    /// ...) dispatch their work: on a dedicated thread pool, on the current
+    /// thread, or following the `TOKENIZERS_PARALLELISM` environment variable
+    /// (the default). This lets a tokenizer embedded in an application that
+    /// also uses rayon keep its work off the application's global pool, or
+    /// opt out of parallelism without touching the process environment
+    pub fn with_parallelism(&mut self, parallelism: Parallelism) -> &mut Self {
+        self.parallelism = parallelism;
+        self
+    }
+
+    /// Return the profile aggregated since profiling was enabled or last
+    /// taken, resetting it, or `None` when profiling is disabled
+    pub fn take_profile(&self) -> Option<EncodeProfile> {
+        self.profiling
+            .as_ref()
+            .map(|profile| std::mem::take(&mut *profile.lock().unwrap()))
+    }
+
+    /// Run `f`, attributing its wall time and allocations to the component
+    /// selected by `select` when profiling is enabled
+    fn profiled<T>(
+        &self,
+        select: fn(&mut EncodeProfile) -> &mut ComponentProfile,
+        f: impl FnOnce() -> T,
+    ) -> T {
+        match &self.profiling {
+            Some(profile) => {
+                let (result, wall_time, allocations) = crate::utils::profiling::measure(f);
+                let mut profile = profile.lock().unwrap();
+                let component = select(&mut profile);
+                component.wall_time += wall_time;
+                component.allocations += allocations;
+                result
+            }
+            None => f(),
+        }
+    }
+}
+
 impl<M, N, PT, PP, D> TokenizerImpl<M, N, PT, PP, D>
 where
     PP: PostProcessor,
@@ -990,6 +3407,21 @@ where
         encoding: Encoding,
         pair_encoding: Option<Encoding>,
         add_special_tokens: bool,
+    ) -> Result<Encoding> {
+        if let Some(profile) = &self.profiling {
+            profile.lock().unwrap().encode_calls += 1;
+        }
+        self.profiled(
+            |p| &mut p.post_processor,
+            || self.do_post_process(encoding, pair_encoding, add_special_tokens),
+        )
+    }
+
+    fn do_post_process(
+        &self,
+        encoding: Encoding,
+        pair_encoding: Option<Encoding>,
+        add_special_tokens: bool,
     ) -> Result<Encoding> {
         // 1. First we truncate if needed
         let (encoding, pair_encoding) = {
@@ -1028,7 +3460,7 @@ where
         };
 
         // 3. Then we pad if needed
-        let [final_encoding] = if let Some(params) = &self.padding {
+        let [mut final_encoding] = if let Some(params) = &self.padding {
             let mut arr = [final_encoding];
             pad_encodings(&mut arr, params)?;
             arr
@@ -1036,9 +3468,41 @@ where
             [final_encoding]
         };
 
+        // 4. And finally we rewrite the offsets of the inserted tokens
+        if self.special_offsets_policy != SpecialOffsetsPolicy::Zero {
+            Self::rewrite_special_offsets(&mut final_encoding, self.special_offsets_policy);
+            for overflowing in final_encoding.get_overflowing_mut() {
+                Self::rewrite_special_offsets(overflowing, self.special_offsets_policy);
+            }
+        }
+
         Ok(final_encoding)
     }
 
+    /// Rewrite the `(0, 0)` offsets of the special tokens inserted by the
+    /// post-processor and by the padding, according to the given policy
+    fn rewrite_special_offsets(encoding: &mut Encoding, policy: SpecialOffsetsPolicy) {
+        let mut prev_end = 0;
+        let offsets: Vec<Offsets> = encoding
+            .get_offsets()
+            .iter()
+            .zip(encoding.get_special_tokens_mask())
+            .map(|(&offsets, &special)| {
+                if special == 1 && offsets == (0, 0) {
+                    match policy {
+                        SpecialOffsetsPolicy::Zero => (0, 0),
+                        SpecialOffsetsPolicy::PreviousEnd => (prev_end, prev_end),
+                        SpecialOffsetsPolicy::Sentinel(s) => (s as usize, s as usize),
+                    }
+                } else {
+                    prev_end = offsets.1;
+                    offsets
+                }
+            })
+            .collect();
+        encoding.get_offsets_mut().copy_from_slice(&offsets);
+    }
+
     fn get_n_added_tokens(&self, is_pair: bool) -> usize {
         if let Some(processor) = &self.post_processor {
             processor.added_tokens(is_pair)
@@ -1065,10 +3529,12 @@ where
     where
         E: Into<EncodeInput<'s>> + Send,
     {
-        let mut encodings = inputs
-            .into_maybe_par_iter()
-            .map(|input| self.encode(input, add_special_tokens))
-            .collect::<Result<Vec<Encoding>>>()?;
+        let mut encodings = self.parallelism.install(|| {
+            inputs
+                .into_maybe_par_iter_with(&self.parallelism)
+                .map(|input| self.encode(input, add_special_tokens))
+                .collect::<Result<Vec<Encoding>>>()
+        })??;
 
         if let Some(params) = &self.padding {
             // We do the padding here to make sure we handle the batch padding
@@ -1078,6 +3544,56 @@ where
         Ok(encodings)
     }
 
+    /// Encode all the sentences in parallel like [`TokenizerImpl::encode_batch`],
+    /// then group the encodings by padding bucket and pad each group to its
+    /// bucket size. This requires the padding strategy to be
+    /// [`PaddingStrategy::BucketedToLongest`], and reduces the wasted compute
+    /// of padding every sequence to the batch maximum. The relative order of
+    /// the inputs is preserved within each group.
+    pub fn encode_batch_bucketed<'s, E>(
+        &self,
+        inputs: Vec<E>,
+        add_special_tokens: bool,
+    ) -> Result<Vec<Vec<Encoding>>>
+    where
+        E: Into<EncodeInput<'s>> + Send,
+    {
+        let params = self
+            .padding
+            .as_ref()
+            .ok_or("encode_batch_bucketed requires padding with the BucketedToLongest strategy")?;
+        let buckets = match &params.strategy {
+            PaddingStrategy::BucketedToLongest { buckets } => buckets,
+            _ => {
+                return Err(
+                    "encode_batch_bucketed requires the BucketedToLongest padding strategy".into(),
+                )
+            }
+        };
+
+        let encodings = self.parallelism.install(|| {
+            inputs
+                .into_maybe_par_iter_with(&self.parallelism)
+                .map(|input| self.encode(input, add_special_tokens))
+                .collect::<Result<Vec<Encoding>>>()
+        })??;
+
+        let mut groups: std::collections::BTreeMap<usize, Vec<Encoding>> =
+            std::collections::BTreeMap::new();
+        for encoding in encodings {
+            let len = encoding.get_ids().len();
+            let bucket = crate::utils::padding::smallest_bucket(buckets, len).unwrap_or(len);
+            groups.entry(bucket).or_default().push(encoding);
+        }
+        groups
+            .into_values()
+            .map(|mut group| {
+                pad_encodings(&mut group, params)?;
+                Ok(group)
+            })
+            .collect()
+    }
+
     /// Encode all the sentences in parallel, using multiple threads.
     /// The offsets on each `Encoding` will be relative to chars instead of bytes.
     pub fn encode_batch_char_offsets<'s, E>(
@@ -1088,10 +3604,12 @@ where
     where
         E: Into<EncodeInput<'s>> + Send,
     {
-        let mut encodings = inputs
-            .into_maybe_par_iter()
-            .map(|input| self.encode_char_offsets(input, add_special_tokens))
-            .collect::<Result<Vec<Encoding>>>()?;
+        let mut encodings = self.parallelism.install(|| {
+            inputs
+                .into_maybe_par_iter_with(&self.parallelism)
+                .map(|input| self.encode_char_offsets(input, add_special_tokens))
+                .collect::<Result<Vec<Encoding>>>()
+        })??;
 
         if let Some(params) = &self.padding {
             // We do the padding here to make sure we handle the batch padding
@@ -1110,10 +3628,12 @@ where
     where
         E: Into<EncodeInput<'s>> + Send,
     {
-        let mut encodings = inputs
-            .into_maybe_par_iter()
-            .map(|input| self.encode_fast(input, add_special_tokens))
-            .collect::<Result<Vec<Encoding>>>()?;
+        let mut encodings = self.parallelism.install(|| {
+            inputs
+                .into_maybe_par_iter_with(&self.parallelism)
+                .map(|input| self.encode_fast(input, add_special_tokens))
+                .collect::<Result<Vec<Encoding>>>()
+        })??;
 
         if let Some(params) = &self.padding {
             // We do the padding here to make sure we handle the batch padding
@@ -1123,6 +3643,25 @@ where
         Ok(encodings)
     }
 
+    /// Count the tokens of all the inputs in parallel, like
+    /// [`TokenizerImpl::count_tokens`] does for a single one. Padding is not
+    /// accounted for, since it depends on the batch layout.
+    pub fn count_tokens_batch<'s, E>(
+        &self,
+        inputs: Vec<E>,
+        add_special_tokens: bool,
+    ) -> Result<Vec<usize>>
+    where
+        E: Into<EncodeInput<'s>> + Send,
+    {
+        self.parallelism.install(|| {
+            inputs
+                .into_maybe_par_iter_with(&self.parallelism)
+                .map(|input| self.count_tokens(input, add_special_tokens))
+                .collect::<Result<Vec<usize>>>()
+        })?
+    }
+
     /// Decode all sentences in parallel
     pub fn decode_batch(
         &self,
@@ -1132,17 +3671,25 @@ where
     where
         M: Send + Sync,
     {
-        sentences
-            .into_maybe_par_iter()
-            .map(|sentence| self.decode(sentence, skip_special_tokens))
-            .collect()
+        self.parallelism.install(|| {
+            sentences
+                .into_maybe_par_iter_with(&self.parallelism)
+                .map(|sentence| self.decode(sentence, skip_special_tokens))
+                .collect()
+        })?
     }
 
-    /// Train our Model from files
+    /// Train our Model from files, read in parallel by a pool of worker
+    /// threads so that IO and decompression overlap with the counting done by
+    /// the trainer. Arguments pointing to a directory are expanded to the
+    /// files it contains. With the `compression` feature enabled, `.gz` and
+    /// `.zst` files are decompressed in streaming fashion.
+    #[cfg(not(feature = "runtime-only"))]
     pub fn train_from_files<T>(&mut self, trainer: &mut T, files: Vec<String>) -> Result<&mut Self>
     where
         T: Trainer<Model = M> + Sync,
     {
+        let files = expand_training_files(files)?;
         let mut len = 0;
         for file in files.iter() {
             len += File::open(file)
@@ -1152,65 +3699,133 @@ where
 
         let max_read = 1_000_000;
 
+        let progress = if trainer.should_show_progress() {
+            let progress = ProgressBar::new(len);
+            progress.set_style(
+                ProgressStyle::default_bar()
+                    .template("[{elapsed_precise}] {msg:<30!} {wide_bar} {percent:>18!}%")
+                    .expect("Invalid progress template"),
+            );
+            progress.set_message(format!("Pre-processing files (0/{})", files.len()));
+            Some(progress)
+        } else {
+            None
+        };
+
+        // Each worker thread streams the lines of one file at a time into a
+        // bounded channel feeding the trainer. We read new lines using
+        // `lines_with_ending` instead of the Lines Iterator on purpose: we
+        // want to keep the `\n` and potential `\r` between each lines.
+        let num_workers = files.len().clamp(1, current_num_threads());
+        let (sender, receiver) = std::sync::mpsc::sync_channel(max_read);
+        let next_file = std::sync::atomic::AtomicUsize::new(0);
+        let files_done = std::sync::atomic::AtomicUsize::new(0);
+
+        std::thread::scope(|scope| -> Result<()> {
+            for _ in 0..num_workers {
+                let sender = sender.clone();
+                let progress = progress.clone();
+                let (files, next_file, files_done) = (&files, &next_file, &files_done);
+                scope.spawn(move || loop {
+                    let index = next_file.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    let filename = match files.get(index) {
+                        Some(filename) => filename,
+                        None => break,
+                    };
+                    match open_training_file(filename) {
+                        Ok(file) => {
+                            let file = BufReader::with_capacity(max_read, file);
+                            for line in file.lines_with_ending() {
+                                if let (Some(progress), Ok(line)) = (&progress, &line) {
+                                    progress.inc(line.len() as u64);
+                                }
+                                if sender.send(line).is_err() {
+                                    // The receiving end stopped, on an error
+                                    return;
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            let _ = sender.send(Err(e));
+                            return;
+                        }
+                    }
+                    if let Some(progress) = &progress {
+                        let done = files_done.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                        progress.set_message(format!(
+                            "Pre-processing files ({}/{})",
+                            done + 1,
+                            files.len()
+                        ));
+                    }
+                });
+            }
+            // Drop the original sender so that the channel closes once every
+            // worker is done
+            drop(sender);
+
+            ResultShunt::process(receiver.into_iter(), |sequences| -> Result<()> {
+                trainer.feed(sequences, |seq| {
+                    let normalized = self.do_normalize(seq.as_ref())?;
+                    let pre_tokenized = self.do_pre_tokenize(normalized)?;
+                    Ok(pre_tokenized
+                        .get_splits(OffsetReferential::Original, OffsetType::Byte)
+                        .into_iter()
+                        .map(|(s, _, _)| s.to_owned())
+                        .collect())
+                })
+            })?
+        })?;
+
+        if let Some(pbar) = progress {
+            pbar.finish();
+        }
+        let special_tokens = trainer.train(&mut self.model)?;
+        self.add_special_tokens(&special_tokens);
+
+        Ok(self)
+    }
+
+    /// Train our Model from corpus files, like [`TokenizerImpl::train_from_files`], but
+    /// additionally extracting the given field from each line when the files are
+    /// line-delimited JSON
+    #[cfg(all(not(feature = "runtime-only"), feature = "compression"))]
+    pub fn train_from_corpus_files<T>(
+        &mut self,
+        trainer: &mut T,
+        files: Vec<String>,
+        json_field: Option<&str>,
+    ) -> Result<&mut Self>
+    where
+        T: Trainer<Model = M> + Sync,
+    {
+        let max_read = 1_000_000;
+
         ResultShunt::process(
             files.into_iter().flat_map(|filename| {
-                match File::open(filename) {
+                match crate::utils::corpus::open_corpus_file(&filename) {
                     Ok(file) => {
                         let file = BufReader::with_capacity(max_read, file);
-                        // We read new lines using this API instead of the Lines Iterator
-                        // on purpose. We want to keep the `\n` and potential `\r` between each lines
-                        // We use an iterator to be able to chain with par_bridge.
-                        itertools::Either::Left(file.lines_with_ending())
+                        itertools::Either::Left(file.lines_with_ending().map(move |line| {
+                            let line = line?;
+                            match json_field {
+                                Some(field) => {
+                                    crate::utils::corpus::extract_json_field(&line, field)
+                                }
+                                None => Ok(line),
+                            }
+                        }))
                     }
                     Err(e) => itertools::Either::Right(std::iter::once(Err(e))),
                 }
             }),
-            |sequences| -> Result<()> {
-                let progress = if trainer.should_show_progress() {
-                    let progress = ProgressBar::new(len);
-                    progress.set_style(
-                        ProgressStyle::default_bar()
-                            .template("[{elapsed_precise}] {msg:<30!} {wide_bar} {percent:>18!}%")
-                            .expect("Invalid progress template"),
-                    );
-                    progress
-                        .set_message(format!("Pre-processing files ({:.2} Mo)", len / 1_000_000));
-                    Some(progress)
-                } else {
-                    None
-                };
-
-                trainer.feed(
-                    sequences.map(|s| {
-                        if let Some(progress) = &progress {
-                            progress.inc(s.len() as u64)
-                        }
-                        s
-                    }),
-                    |seq| {
-                        let normalized = self.do_normalize(seq.as_ref())?;
-                        let pre_tokenized = self.do_pre_tokenize(normalized)?;
-                        Ok(pre_tokenized
-                            .get_splits(OffsetReferential::Original, OffsetType::Byte)
-                            .into_iter()
-                            .map(|(s, _, _)| s.to_owned())
-                            .collect())
-                    },
-                )?;
-
-                if let Some(pbar) = progress {
-                    pbar.finish();
-                }
-                let special_tokens = trainer.train(&mut self.model)?;
-                self.add_special_tokens(&special_tokens);
-
-                Ok(())
-            },
+            |sequences| self.train(trainer, sequences).map(|_| ()),
         )??;
         Ok(self)
     }
 
     /// Train our Model, using the given Trainer and iterator
+    #[cfg(not(feature = "runtime-only"))]
     pub fn train<T, I, S>(&mut self, trainer: &mut T, sequences: I) -> Result<&mut Self>
     where
         T: Trainer<Model = M> + Sync,
@@ -1258,6 +3873,65 @@ where
 
         Ok(self)
     }
+
+    /// Train our Model like [`TokenizerImpl::train`], with the given
+    /// deduplicator dropping the sequences that repeat an earlier one from
+    /// the feed, and return statistics about the removed duplicates.
+    /// Duplicated web documents otherwise skew the counted frequencies
+    /// towards their boilerplate.
+    #[cfg(not(feature = "runtime-only"))]
+    pub fn train_dedup<T, I, S>(
+        &mut self,
+        trainer: &mut T,
+        sequences: I,
+        mut dedup: crate::utils::dedup::Deduplicator,
+    ) -> Result<crate::utils::dedup::DedupStats>
+    where
+        T: Trainer<Model = M> + Sync,
+        I: Iterator<Item = S> + Send,
+        S: AsRef<str> + Send,
+    {
+        self.train(trainer, dedup.unique(sequences))?;
+        Ok(dedup.into_stats())
+    }
+}
+
+/// Expand the training file arguments pointing to a directory into the files
+/// it contains, sorted by name, keeping plain files as they are
+#[cfg(not(feature = "runtime-only"))]
+fn expand_training_files(files: Vec<String>) -> Result<Vec<String>> {
+    let mut expanded = vec![];
+    for file in files {
+        if Path::new(&file).is_dir() {
+            let mut entries = std::fs::read_dir(&file)?
+                .map(|entry| Ok(entry?.path()))
+                .collect::<Result<Vec<_>>>()?;
+            entries.sort();
+            expanded.extend(
+                entries
+                    .into_iter()
+                    .filter(|path| path.is_file())
+                    .map(|path| path.to_string_lossy().into_owned()),
+            );
+        } else {
+            expanded.push(file);
+        }
+    }
+    Ok(expanded)
+}
+
+/// Open a training file, decompressing `.gz`/`.zst` files when the
+/// `compression` feature is enabled
+#[cfg(not(feature = "runtime-only"))]
+fn open_training_file(filename: &str) -> std::io::Result<Box<dyn std::io::Read + Send>> {
+    #[cfg(feature = "compression")]
+    {
+        crate::utils::corpus::open_corpus_file(filename)
+    }
+    #[cfg(not(feature = "compression"))]
+    {
+        Ok(Box::new(File::open(filename)?))
+    }
 }
 
 impl<M, N, PT, PP, D> std::str::FromStr for TokenizerImpl<M, N, PT, PP, D>
@@ -1271,7 +3945,7 @@ where
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self> {
-        Ok(serde_json::from_str(s)?)
+        migrations::from_migrated_value(serde_json::from_str(s)?)
     }
 }
 
@@ -1284,10 +3958,10 @@ where
     D: DeserializeOwned + Decoder,
 {
     /// Instantiate a new Tokenizer from the given file
+    #[cfg(not(feature = "runtime-only"))]
     pub fn from_file<P: AsRef<Path>>(file: P) -> Result<Self> {
         let content = read_to_string(file)?;
-        let tokenizer = serde_json::from_str(&content)?;
-        Ok(tokenizer)
+        migrations::from_migrated_value(serde_json::from_str(&content)?)
     }
 }
 
@@ -1301,8 +3975,7 @@ where
 {
     /// Instantiate a new Tokenizer from bytes
     pub fn from_bytes<P: AsRef<[u8]>>(bytes: P) -> Result<Self> {
-        let tokenizer = serde_json::from_slice(bytes.as_ref())?;
-        Ok(tokenizer)
+        migrations::from_migrated_value(serde_json::from_slice(bytes.as_ref())?)
     }
 }
 
@@ -1318,7 +3991,7 @@ where
         since = "0.14.0",
         note = "Users should download the file separately using https://github.com/huggingface/hf-hub instead, which splits concerns of accessing the web, and should use the new cache layout"
     )]
-    #[cfg(feature = "http")]
+    #[cfg(all(feature = "http", not(feature = "runtime-only")))]
     /// Instantiate a new Tokenizer from a file hosted on the Hugging Face Hub.
     /// It expects the `identifier` of a model that includes a `tokenizer.json` file.
     pub fn from_pretrained<S: AsRef<str>>(
@@ -1348,6 +4021,7 @@ where
     }
 
     /// Save the current tokenizer at the given path
+    #[cfg(not(feature = "runtime-only"))]
     pub fn save<P: AsRef<Path>>(&self, path: P, pretty: bool) -> Result<()> {
         let serialized = self.to_string(pretty)?;
 
@@ -1360,6 +4034,397 @@ where
 
 #[cfg(test)]
 mod test {
+    #[test]
+    fn frozen_tokenizer_is_shareable() {
+        use crate::models::wordlevel::WordLevel;
+        use crate::{FrozenTokenizer, Tokenizer};
+        use std::collections::HashMap;
+
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<FrozenTokenizer>();
+
+        let vocab: HashMap<String, u32> = vec![("hello".into(), 0), ("world".into(), 1)]
+            .into_iter()
+            .collect();
+        let tokenizer = Tokenizer::new(WordLevel::builder().vocab(vocab.into()).build().unwrap());
+        let frozen = FrozenTokenizer::new(tokenizer);
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let frozen = frozen.clone();
+                std::thread::spawn(move || frozen.encode("hello", false).unwrap())
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap().get_ids(), &[0]);
+        }
+
+        // The last handle can be thawed back into a mutable `Tokenizer`
+        let tokenizer = frozen.try_unwrap().unwrap();
+        assert_eq!(tokenizer.token_to_id("world"), Some(1));
+    }
+
+    #[test]
+    fn encode_pre_tokenized_with_offsets() {
+        use crate::models::wordlevel::WordLevel;
+        use crate::{Offsets, Tokenizer};
+        use std::collections::HashMap;
+
+        let vocab: HashMap<String, u32> = vec![("hello".into(), 0), ("world".into(), 1)]
+            .into_iter()
+            .collect();
+        let tokenizer = Tokenizer::new(WordLevel::builder().vocab(vocab.into()).build().unwrap());
+
+        // "hello,  world!" with the words at their position in the source document
+        let input: Vec<(&str, Offsets)> = vec![("hello", (0, 5)), ("world", (8, 13))];
+        let encoding = tokenizer.encode(input, false).unwrap();
+
+        assert_eq!(encoding.get_ids(), &[0, 1]);
+        assert_eq!(encoding.get_word_ids(), &[Some(0), Some(1)]);
+        // Offsets refer to the source document, not the concatenated words
+        assert_eq!(encoding.get_offsets(), &[(0, 5), (8, 13)]);
+    }
+
+    #[test]
+    fn replace_model_remaps_added_tokens() {
+        use crate::models::wordlevel::WordLevel;
+        use crate::utils::padding::PaddingParams;
+        use crate::{AddedToken, Tokenizer};
+        use std::collections::HashMap;
+
+        let vocab_a: HashMap<String, u32> = vec![("hello".into(), 0), ("world".into(), 1)]
+            .into_iter()
+            .collect();
+        // The new model has a larger vocabulary, and already knows "[SEP]"
+        let vocab_b: HashMap<String, u32> = vec![
+            ("hello".into(), 0),
+            ("world".into(), 1),
+            ("friend".into(), 2),
+            ("[SEP]".into(), 3),
+        ]
+        .into_iter()
+        .collect();
+
+        let mut tokenizer =
+            Tokenizer::new(WordLevel::builder().vocab(vocab_a.into()).build().unwrap());
+        tokenizer.add_special_tokens(&[
+            AddedToken::from("[CLS]", true),
+            AddedToken::from("[SEP]", true),
+        ]);
+        assert_eq!(tokenizer.token_to_id("[CLS]"), Some(2));
+        assert_eq!(tokenizer.token_to_id("[SEP]"), Some(3));
+
+        let new_model = WordLevel::builder().vocab(vocab_b.into()).build().unwrap();
+
+        // A padding token missing from the new vocabulary is a conflict, and
+        // leaves the tokenizer untouched
+        tokenizer.with_padding(Some(PaddingParams {
+            pad_id: 4,
+            pad_token: "[PAD]".into(),
+            ..Default::default()
+        }));
+        assert_eq!(
+            tokenizer
+                .replace_model(new_model.clone())
+                .unwrap_err()
+                .to_string(),
+            "padding token `[PAD]` is not in the vocabulary"
+        );
+        assert_eq!(tokenizer.token_to_id("hello"), Some(0));
+        assert_eq!(tokenizer.token_to_id("friend"), None);
+        tokenizer.with_padding(None);
+
+        let report = tokenizer.replace_model(new_model).unwrap();
+        // "[SEP]" now is a model token, while "[CLS]" moved after the new
+        // vocabulary
+        assert_eq!(tokenizer.token_to_id("friend"), Some(2));
+        assert_eq!(tokenizer.token_to_id("[SEP]"), Some(3));
+        assert_eq!(tokenizer.token_to_id("[CLS]"), Some(4));
+        assert_eq!(report.remapped, vec![("[CLS]".into(), 2, 4)]);
+        assert!(tokenizer.get_added_vocabulary().is_special_token("[CLS]"));
+    }
+
+    #[test]
+    fn encode_cache_hits_and_invalidation() {
+        use crate::models::wordlevel::WordLevel;
+        use crate::utils::padding::{PaddingParams, PaddingStrategy};
+        use crate::Tokenizer;
+        use std::collections::HashMap;
+
+        let vocab: HashMap<String, u32> = vec![("hello".into(), 0), ("[PAD]".into(), 1)]
+            .into_iter()
+            .collect();
+        let mut tokenizer =
+            Tokenizer::new(WordLevel::builder().vocab(vocab.into()).build().unwrap());
+        tokenizer.with_encode_cache(Some(100));
+
+        let encoding = tokenizer.encode("hello", false).unwrap();
+        assert_eq!(encoding.get_ids(), &[0]);
+        // The second encode is served from the cache
+        assert_eq!(tokenizer.encode("hello", false).unwrap(), encoding);
+
+        // The padding parameters take part in the cache key: changing them
+        // must not serve the unpadded cached encoding
+        tokenizer.with_padding(Some(PaddingParams {
+            strategy: PaddingStrategy::Fixed(3),
+            pad_id: 1,
+            pad_token: "[PAD]".into(),
+            ..Default::default()
+        }));
+        let encoding = tokenizer.encode("hello", false).unwrap();
+        assert_eq!(encoding.get_ids(), &[0, 1, 1]);
+    }
+
+    #[test]
+    fn parallelism_modes_encode_batches() {
+        use crate::models::wordlevel::WordLevel;
+        use crate::Parallelism;
+        use crate::Tokenizer;
+        use std::collections::HashMap;
+
+        let vocab: HashMap<String, u32> = vec![("hello".into(), 0), ("world".into(), 1)]
+            .into_iter()
+            .collect();
+        let mut tokenizer =
+            Tokenizer::new(WordLevel::builder().vocab(vocab.into()).build().unwrap());
+
+        let expected = tokenizer
+            .encode_batch(vec!["hello", "world"], false)
+            .unwrap();
+
+        // Every mode produces the same encodings, whatever the dispatch
+        for parallelism in [Parallelism::Disabled, Parallelism::Threads(2)] {
+            tokenizer.with_parallelism(parallelism);
+            let encodings = tokenizer
+                .encode_batch(vec!["hello", "world"], false)
+                .unwrap();
+            assert_eq!(encodings, expected);
+            assert_eq!(
+                tokenizer.decode_batch(&[&[0], &[1]], false).unwrap(),
+                vec!["hello", "world"]
+            );
+        }
+    }
+
+    #[test]
+    fn encode_with_prefix_matches_full_encode() {
+        use crate::decoders::fuse::Fuse;
+        use crate::models::bpe::BPE;
+        use crate::Tokenizer;
+        use std::collections::HashMap;
+
+        let vocab: HashMap<String, u32> = vec![("a".into(), 0), ("b".into(), 1), ("ab".into(), 2)]
+            .into_iter()
+            .collect();
+        let merges = vec![("a".to_string(), "b".to_string())];
+        let mut tokenizer = Tokenizer::new(BPE::new(vocab, merges));
+        tokenizer.with_decoder(Some(Fuse::new()));
+
+        // The junction merge applies as if the whole prompt had been
+        // encoded in one call
+        let prefix = tokenizer.encode("a", false).unwrap();
+        let encoding = tokenizer.encode_with_prefix(&prefix, "b", false).unwrap();
+        assert_eq!(encoding.get_tokens(), ["ab"]);
+
+        // Same with a prefix longer than the re-tokenized window
+        let prefix = tokenizer.encode("aaaaaaaaa", false).unwrap();
+        let encoding = tokenizer.encode_with_prefix(&prefix, "b", false).unwrap();
+        let expected = tokenizer.encode("aaaaaaaaab", false).unwrap();
+        assert_eq!(encoding.get_ids(), expected.get_ids());
+        assert_eq!(encoding.get_tokens(), expected.get_tokens());
+    }
+
+    #[test]
+    fn token_healing_extends_the_final_token() {
+        use crate::models::bpe::BPE;
+        use crate::Tokenizer;
+        use std::collections::HashMap;
+
+        let vocab: HashMap<String, u32> = vec![
+            ("a".into(), 0),
+            ("b".into(), 1),
+            ("c".into(), 2),
+            ("ab".into(), 3),
+            ("abc".into(), 4),
+        ]
+        .into_iter()
+        .collect();
+        let merges = vec![
+            ("a".to_string(), "b".to_string()),
+            ("ab".to_string(), "c".to_string()),
+        ];
+        let tokenizer = Tokenizer::new(BPE::new(vocab, merges));
+
+        // The prompt ends in "ab": the healed candidates are the tokens
+        // extending it, including "ab" itself
+        let (encoding, candidates) = tokenizer.token_healing("aab").unwrap();
+        assert_eq!(encoding.get_tokens(), ["a"]);
+        assert_eq!(candidates, [3, 4]);
+
+        // A prompt made of a single token is trimmed down to nothing
+        let (encoding, candidates) = tokenizer.token_healing("ab").unwrap();
+        assert!(encoding.is_empty());
+        assert_eq!(candidates, [3, 4]);
+
+        // Nothing to heal in an empty prompt
+        let (encoding, candidates) = tokenizer.token_healing("").unwrap();
+        assert!(encoding.is_empty());
+        assert!(candidates.is_empty());
+    }
+
+    #[test]
+    fn add_special_token_family() {
+        use crate::models::wordlevel::WordLevel;
+        use crate::Tokenizer;
+        use std::collections::HashMap;
+
+        let vocab: HashMap<String, u32> = vec![("hello".into(), 0), ("world".into(), 1)]
+            .into_iter()
+            .collect();
+        let mut tokenizer =
+            Tokenizer::new(WordLevel::builder().vocab(vocab.into()).build().unwrap());
+
+        // The sentinels get contiguous ids right after the vocabulary
+        let ids = tokenizer.add_special_token_family("<extra_id_{n}>", 3);
+        assert_eq!(ids, 2..5);
+        assert_eq!(tokenizer.token_to_id("<extra_id_0>"), Some(2));
+        assert_eq!(tokenizer.token_to_id("<extra_id_2>"), Some(4));
+
+        // Registering the family again keeps the existing ids
+        let ids = tokenizer.add_special_token_family("<extra_id_{n}>", 3);
+        assert_eq!(ids, 2..5);
+
+        // Without a placeholder, the index is appended to the template
+        let ids = tokenizer.add_special_token_family("<sent", 2);
+        assert_eq!(ids, 5..7);
+        assert_eq!(tokenizer.token_to_id("<sent1"), Some(6));
+    }
+
+    #[test]
+    fn profiling_aggregates_encode_calls() {
+        use crate::models::wordlevel::WordLevel;
+        use crate::Tokenizer;
+        use std::collections::HashMap;
+
+        let vocab: HashMap<String, u32> = vec![("hello".into(), 0), ("world".into(), 1)]
+            .into_iter()
+            .collect();
+        let mut tokenizer =
+            Tokenizer::new(WordLevel::builder().vocab(vocab.into()).build().unwrap());
+
+        // Disabled by default
+        tokenizer.encode("hello", false).unwrap();
+        assert!(tokenizer.take_profile().is_none());
+
+        tokenizer.with_profiling(true);
+        tokenizer.encode("hello", false).unwrap();
+        tokenizer.encode(("hello", "world"), false).unwrap();
+
+        let profile = tokenizer.take_profile().unwrap();
+        assert_eq!(profile.encode_calls, 2);
+        assert!(profile.model.wall_time > std::time::Duration::ZERO);
+
+        // Taking the profile resets it
+        let profile = tokenizer.take_profile().unwrap();
+        assert_eq!(profile.encode_calls, 0);
+    }
+
+    #[test]
+    fn encode_without_added_tokens_ignores_special_patterns() {
+        use crate::models::wordlevel::WordLevel;
+        use crate::{AddedToken, Tokenizer};
+        use std::collections::HashMap;
+
+        let vocab: HashMap<String, u32> = vec![("<unk>".into(), 0), ("hello".into(), 1)]
+            .into_iter()
+            .collect();
+        let mut tokenizer = Tokenizer::new(
+            WordLevel::builder()
+                .vocab(vocab.into())
+                .unk_token("<unk>".into())
+                .build()
+                .unwrap(),
+        );
+        tokenizer.add_special_tokens(&[AddedToken::from("<s>", true)]);
+
+        // A literal special-token pattern in the input is normally extracted
+        let encoding = tokenizer.encode("<s>hello", false).unwrap();
+        assert_eq!(encoding.get_ids(), &[2, 1]);
+
+        // With extraction disabled, it goes through the model as plain text
+        let encoding = tokenizer
+            .encode_without_added_tokens("<s>hello", false)
+            .unwrap();
+        assert_eq!(encoding.get_ids(), &[0]);
+        assert_eq!(encoding.get_tokens(), &["<unk>"]);
+    }
+
+    #[test]
+    fn encode_scored_attaches_token_scores() {
+        use crate::models::unigram::Unigram;
+        use crate::models::wordlevel::WordLevel;
+        use crate::{AddedToken, Tokenizer};
+        use std::collections::HashMap;
+
+        let pieces = vec![
+            ("<unk>".to_string(), 0.0),
+            ("hello".to_string(), -1.0),
+            ("world".to_string(), -2.0),
+        ];
+        let mut tokenizer = Tokenizer::new(Unigram::from(pieces, Some(0), false).unwrap());
+        tokenizer.add_special_tokens(&[AddedToken::from("[CLS]", true)]);
+
+        let encoding = tokenizer.encode_scored("hello[CLS]world", false).unwrap();
+        assert_eq!(encoding.get_tokens(), &["hello", "[CLS]", "world"]);
+        // Model tokens carry their log-probability, the added token has no
+        // score
+        assert_eq!(encoding.get_scores(), &[Some(-1.0), None, Some(-2.0)]);
+
+        // Models without a notion of token score leave the scores empty
+        let vocab: HashMap<String, u32> = vec![("hello".into(), 0)].into_iter().collect();
+        let tokenizer = Tokenizer::new(WordLevel::builder().vocab(vocab.into()).build().unwrap());
+        let encoding = tokenizer.encode_scored("hello", false).unwrap();
+        assert!(encoding.get_scores().is_empty());
+    }
+
+    #[test]
+    fn encode_bytes_with_invalid_utf8() {
+        use crate::models::wordlevel::WordLevel;
+        use crate::pre_tokenizers::whitespace::WhitespaceSplit;
+        use crate::{InvalidUtf8Policy, Tokenizer};
+        use std::collections::HashMap;
+
+        let vocab: HashMap<String, u32> = vec![
+            ("hello".into(), 0),
+            ("<0x80>".into(), 1),
+            ("\u{FFFD}".into(), 2),
+        ]
+        .into_iter()
+        .collect();
+        let mut tokenizer =
+            Tokenizer::new(WordLevel::builder().vocab(vocab.into()).build().unwrap());
+        tokenizer.with_pre_tokenizer(Some(WhitespaceSplit));
+
+        // Valid UTF-8 encodes without any copy
+        let encoding = tokenizer
+            .encode_bytes(b"hello", InvalidUtf8Policy::Error, false)
+            .unwrap();
+        assert_eq!(encoding.get_ids(), &[0]);
+
+        let bytes: &[u8] = b"hello \x80";
+        assert!(tokenizer
+            .encode_bytes(bytes, InvalidUtf8Policy::Error, false)
+            .is_err());
+        let encoding = tokenizer
+            .encode_bytes(bytes, InvalidUtf8Policy::Replace, false)
+            .unwrap();
+        assert_eq!(encoding.get_ids(), &[0, 2]);
+        let encoding = tokenizer
+            .encode_bytes(bytes, InvalidUtf8Policy::ByteFallback, false)
+            .unwrap();
+        assert_eq!(encoding.get_ids(), &[0, 1]);
+    }
+
     #[cfg(feature = "http")]
     #[test]
     fn test_decoding_with_added_bpe() {
@@ -1410,4 +4475,682 @@ mod test {
         let decoded = tokenizer.decode(encoded.get_ids(), false);
         assert_eq!(decoded.unwrap(), "Hey! how is this token: д")
     }
+
+    #[test]
+    fn normalize_and_pre_tokenize_str() {
+        use crate::models::wordlevel::WordLevel;
+        use crate::normalizers::utils::Lowercase;
+        use crate::pre_tokenizers::whitespace::WhitespaceSplit;
+        use crate::Tokenizer;
+
+        let mut tokenizer = Tokenizer::new(WordLevel::default());
+        tokenizer.with_normalizer(Some(Lowercase));
+        tokenizer.with_pre_tokenizer(Some(WhitespaceSplit));
+
+        assert_eq!(
+            tokenizer.normalize_str("Hello There").unwrap().get(),
+            "hello there"
+        );
+        assert_eq!(
+            tokenizer.pre_tokenize_str("Hello There").unwrap(),
+            vec![
+                ("hello".to_string(), (0, 5)),
+                ("there".to_string(), (6, 11))
+            ]
+        );
+    }
+
+    #[test]
+    fn encode_with_snapshots_exposes_the_normalized_text() {
+        use crate::models::wordlevel::WordLevel;
+        use crate::normalizers::utils::Lowercase;
+        use crate::pre_tokenizers::whitespace::WhitespaceSplit;
+        use crate::Tokenizer;
+        use std::collections::HashMap;
+
+        let vocab: HashMap<String, u32> = vec![("hello".into(), 0), ("there".into(), 1)]
+            .into_iter()
+            .collect();
+        let mut tokenizer =
+            Tokenizer::new(WordLevel::builder().vocab(vocab.into()).build().unwrap());
+        tokenizer.with_normalizer(Some(Lowercase));
+        tokenizer.with_pre_tokenizer(Some(WhitespaceSplit));
+
+        let (encoding, snapshots) = tokenizer
+            .encode_with_snapshots(("Hello There", "HELLO"), false)
+            .unwrap();
+        assert_eq!(encoding.get_tokens(), &["hello", "there", "hello"]);
+
+        // One snapshot per sequence, holding the text the model saw
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].text, "hello there");
+        assert_eq!(snapshots[1].text, "hello");
+        // Each normalized byte maps back to the original byte it comes from
+        assert!(snapshots[0]
+            .alignments
+            .iter()
+            .enumerate()
+            .all(|(i, &offsets)| offsets == (i, i + 1)));
+    }
+
+    #[test]
+    fn encode_with_budget_protects_high_priority_fields() {
+        use crate::models::wordlevel::WordLevel;
+        use crate::pre_tokenizers::whitespace::WhitespaceSplit;
+        use crate::{BudgetTruncation, FieldBudget, Tokenizer, TruncationDirection};
+        use std::collections::HashMap;
+
+        let vocab: HashMap<String, u32> = ["who", "is", "it", "a", "b", "c", "d"]
+            .iter()
+            .enumerate()
+            .map(|(id, token)| (token.to_string(), id as u32))
+            .collect();
+        let mut tokenizer = Tokenizer::new(
+            WordLevel::builder()
+                .vocab(vocab.into())
+                .unk_token("who".into())
+                .build()
+                .unwrap(),
+        );
+        tokenizer.with_pre_tokenizer(Some(WhitespaceSplit));
+
+        let params = BudgetTruncation {
+            max_length: 5,
+            direction: TruncationDirection::Right,
+            fields: vec![
+                // The question is never truncated
+                FieldBudget {
+                    priority: 1,
+                    min_tokens: 3,
+                    max_tokens: None,
+                },
+                FieldBudget::default(),
+            ],
+        };
+        let (encoding, dropped) = tokenizer
+            .encode_with_budget(vec!["who is it", "a b c d"], &params)
+            .unwrap();
+        assert_eq!(encoding.get_tokens(), &["who", "is", "it", "a", "b"]);
+        assert_eq!(encoding.get_type_ids(), &[0, 0, 0, 1, 1]);
+        assert!(dropped[0].is_empty());
+        assert_eq!(dropped[1].get_tokens(), &["c", "d"]);
+    }
+
+    #[test]
+    fn encode_constrained_restricts_the_ids() {
+        use crate::models::bpe::BPE;
+        use crate::pre_tokenizers::whitespace::WhitespaceSplit;
+        use crate::{Tokenizer, VocabMask};
+        use std::collections::HashMap;
+
+        let vocab: HashMap<String, u32> = vec![("a".into(), 0), ("b".into(), 1), ("ab".into(), 2)]
+            .into_iter()
+            .collect();
+        let model = BPE::builder()
+            .vocab_and_merges(vocab, vec![("a".into(), "b".into())])
+            .build()
+            .unwrap();
+        let mut tokenizer = Tokenizer::new(model);
+        tokenizer.with_pre_tokenizer(Some(WhitespaceSplit));
+
+        assert_eq!(tokenizer.encode("ab ab", false).unwrap().get_ids(), &[2, 2]);
+
+        // Without `ab`, the model falls back to the single characters
+        let allowed: VocabMask = [0u32, 1].iter().copied().collect();
+        let encoding = tokenizer
+            .encode_constrained("ab ab", &allowed, false)
+            .unwrap();
+        assert_eq!(encoding.get_ids(), &[0, 1, 0, 1]);
+    }
+
+    #[test]
+    fn special_offsets_policy_keeps_offsets_monotone() {
+        use crate::models::wordlevel::WordLevel;
+        use crate::pre_tokenizers::whitespace::WhitespaceSplit;
+        use crate::processors::bert::BertProcessing;
+        use crate::{SpecialOffsetsPolicy, Tokenizer};
+        use std::collections::HashMap;
+
+        let vocab: HashMap<String, u32> = vec![
+            ("a".into(), 0),
+            ("b".into(), 1),
+            ("[CLS]".into(), 2),
+            ("[SEP]".into(), 3),
+            ("<unk>".into(), 4),
+        ]
+        .into_iter()
+        .collect();
+        let mut tokenizer = Tokenizer::new(
+            WordLevel::builder()
+                .vocab(vocab.into())
+                .unk_token("<unk>".into())
+                .build()
+                .unwrap(),
+        );
+        tokenizer.with_pre_tokenizer(Some(WhitespaceSplit));
+        tokenizer.with_post_processor(Some(BertProcessing::new(
+            ("[SEP]".into(), 3),
+            ("[CLS]".into(), 2),
+        )));
+
+        // The legacy behavior gives the inserted tokens `(0, 0)` offsets
+        let encoding = tokenizer.encode("a b", true).unwrap();
+        assert_eq!(
+            encoding.get_offsets(),
+            &[(0, 0), (0, 1), (2, 3), (0, 0)][..]
+        );
+
+        tokenizer.with_special_offsets_policy(SpecialOffsetsPolicy::PreviousEnd);
+        let encoding = tokenizer.encode("a b", true).unwrap();
+        assert_eq!(
+            encoding.get_offsets(),
+            &[(0, 0), (0, 1), (2, 3), (3, 3)][..]
+        );
+
+        tokenizer.with_special_offsets_policy(SpecialOffsetsPolicy::Sentinel(9));
+        let encoding = tokenizer.encode("a b", true).unwrap();
+        assert_eq!(
+            encoding.get_offsets(),
+            &[(9, 9), (0, 1), (2, 3), (9, 9)][..]
+        );
+    }
+
+    #[test]
+    fn validate_pipeline_reports_issues() {
+        use crate::models::wordlevel::WordLevel;
+        use crate::pre_tokenizers::whitespace::WhitespaceSplit;
+        use crate::Tokenizer;
+        use std::collections::HashMap;
+
+        let vocab: HashMap<String, u32> = vec![("hello".into(), 0), ("world".into(), 1)]
+            .into_iter()
+            .collect();
+        let mut tokenizer =
+            Tokenizer::new(WordLevel::builder().vocab(vocab.into()).build().unwrap());
+        tokenizer.with_pre_tokenizer(Some(WhitespaceSplit));
+
+        // A healthy pipeline on a well-covered sample: no issue, and a
+        // perfect round-trip
+        let report = tokenizer.validate_pipeline(&["hello world"]);
+        assert!(report.is_ok());
+        assert_eq!(report.round_trip_distances, vec![0.0]);
+
+        // Without an unk token, the model cannot encode an unknown word, and
+        // the failure is attributed to it
+        let report = tokenizer.validate_pipeline(&["hello world", "hello friend"]);
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].component, "model");
+        assert_eq!(report.issues[0].sample, 1);
+        assert_eq!(report.round_trip_distances[1], 1.0);
+    }
+
+    #[test]
+    fn count_tokens_matches_full_encode() {
+        use crate::models::wordlevel::WordLevel;
+        use crate::pre_tokenizers::whitespace::WhitespaceSplit;
+        use crate::processors::bert::BertProcessing;
+        use crate::{AddedToken, Tokenizer};
+        use std::collections::HashMap;
+
+        let vocab: HashMap<String, u32> = vec![
+            ("hello".into(), 0),
+            ("world".into(), 1),
+            ("[CLS]".into(), 2),
+            ("[SEP]".into(), 3),
+            ("<unk>".into(), 4),
+        ]
+        .into_iter()
+        .collect();
+        let mut tokenizer = Tokenizer::new(
+            WordLevel::builder()
+                .vocab(vocab.into())
+                .unk_token("<unk>".into())
+                .build()
+                .unwrap(),
+        );
+        tokenizer.with_pre_tokenizer(Some(WhitespaceSplit));
+        tokenizer.with_post_processor(Some(BertProcessing::new(
+            ("[SEP]".into(), 3),
+            ("[CLS]".into(), 2),
+        )));
+        tokenizer.add_special_tokens(&[AddedToken::from("[MASK]", true)]);
+
+        // The fast path agrees with the full pipeline, with and without the
+        // special tokens added by the post-processor, through the added
+        // tokens, and on pairs
+        for input in ["hello world", "hello [MASK] stranger", ""] {
+            for add_special_tokens in [false, true] {
+                assert_eq!(
+                    tokenizer.count_tokens(input, add_special_tokens).unwrap(),
+                    tokenizer
+                        .encode(input, add_special_tokens)
+                        .unwrap()
+                        .get_ids()
+                        .len(),
+                );
+            }
+        }
+        assert_eq!(
+            tokenizer
+                .count_tokens(("hello", "world world"), true)
+                .unwrap(),
+            tokenizer
+                .encode(("hello", "world world"), true)
+                .unwrap()
+                .get_ids()
+                .len(),
+        );
+
+        // Same for the batched version
+        assert_eq!(
+            tokenizer
+                .count_tokens_batch(vec!["hello world", "hello"], true)
+                .unwrap(),
+            vec![4, 3]
+        );
+    }
+
+    #[test]
+    fn encode_packed_builds_fixed_length_sequences() {
+        use crate::models::wordlevel::WordLevel;
+        use crate::pre_tokenizers::whitespace::WhitespaceSplit;
+        use crate::{SepPolicy, Tokenizer};
+        use std::collections::HashMap;
+
+        let vocab: HashMap<String, u32> = vec![
+            ("a".into(), 0),
+            ("b".into(), 1),
+            ("c".into(), 2),
+            ("<doc>".into(), 3),
+            ("<unk>".into(), 4),
+        ]
+        .into_iter()
+        .collect();
+        let mut tokenizer = Tokenizer::new(
+            WordLevel::builder()
+                .vocab(vocab.into())
+                .unk_token("<unk>".into())
+                .build()
+                .unwrap(),
+        );
+        tokenizer.with_pre_tokenizer(Some(WhitespaceSplit));
+
+        // Every document ends with the separator, and a document crossing a
+        // sequence boundary restarts at segment 0
+        let packed = tokenizer
+            .encode_packed(
+                vec!["a b c", "a b", "c"],
+                4,
+                &SepPolicy::Suffix("<doc>".into()),
+            )
+            .unwrap();
+        assert_eq!(
+            packed
+                .iter()
+                .map(|sequence| (sequence.ids.clone(), sequence.segment_ids.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (vec![0, 1, 2, 3], vec![0, 0, 0, 0]),
+                (vec![0, 1, 3, 2], vec![0, 0, 0, 1]),
+                (vec![3], vec![0]),
+            ]
+        );
+
+        // `Between` skips the separator after the last document
+        let packed = tokenizer
+            .encode_packed(vec!["a b", "c"], 10, &SepPolicy::Between("<doc>".into()))
+            .unwrap();
+        assert_eq!(packed.len(), 1);
+        assert_eq!(packed[0].ids, vec![0, 1, 3, 2]);
+        assert_eq!(packed[0].segment_ids, vec![0, 0, 0, 1]);
+
+        // A separator missing from the vocabulary is an error
+        assert!(tokenizer
+            .encode_packed(vec!["a"], 4, &SepPolicy::Suffix("<eod>".into()))
+            .is_err());
+    }
+
+    #[test]
+    fn pair_pipeline_applies_to_the_second_sequence() {
+        use crate::models::wordlevel::WordLevel;
+        use crate::normalizers::utils::Lowercase;
+        use crate::pre_tokenizers::whitespace::WhitespaceSplit;
+        use crate::{PairPipeline, Tokenizer};
+        use std::collections::HashMap;
+        use std::str::FromStr;
+
+        let vocab: HashMap<String, u32> = vec![
+            ("hello".into(), 0),
+            ("world".into(), 1),
+            ("Hello".into(), 2),
+            ("<unk>".into(), 3),
+        ]
+        .into_iter()
+        .collect();
+        let mut tokenizer = Tokenizer::new(
+            WordLevel::builder()
+                .vocab(vocab.into())
+                .unk_token("<unk>".into())
+                .build()
+                .unwrap(),
+        );
+        tokenizer.with_normalizer(Some(Lowercase));
+        tokenizer.with_pre_tokenizer(Some(WhitespaceSplit));
+
+        // Without a pair pipeline, both sequences are lowercased
+        let encoding = tokenizer
+            .encode(("Hello world", "Hello world"), false)
+            .unwrap();
+        assert_eq!(encoding.get_ids(), &[0, 1, 0, 1]);
+
+        // With one, the second sequence keeps its case
+        tokenizer.with_pair_pipeline(Some(PairPipeline {
+            normalizer: None,
+            pre_tokenizer: Some(WhitespaceSplit.into()),
+        }));
+        let encoding = tokenizer
+            .encode(("Hello world", "Hello world"), false)
+            .unwrap();
+        assert_eq!(encoding.get_ids(), &[0, 1, 2, 1]);
+        // Single sequences always go through the main pipeline
+        let encoding = tokenizer.encode("Hello world", false).unwrap();
+        assert_eq!(encoding.get_ids(), &[0, 1]);
+
+        // The pair pipeline survives a serialization round-trip, and is not
+        // serialized at all when unset
+        let serialized = serde_json::to_string(&tokenizer).unwrap();
+        assert!(serialized.contains(r#""pair_pipeline":{"normalizer":null"#));
+        let reloaded = Tokenizer::from_str(&serialized).unwrap();
+        let encoding = reloaded
+            .encode(("Hello world", "Hello world"), false)
+            .unwrap();
+        assert_eq!(encoding.get_ids(), &[0, 1, 2, 1]);
+
+        tokenizer.with_pair_pipeline(None);
+        assert!(!serde_json::to_string(&tokenizer)
+            .unwrap()
+            .contains("pair_pipeline"));
+    }
+
+    #[test]
+    fn unk_policy_overrides_model_default() {
+        use crate::models::wordlevel::WordLevel;
+        use crate::pre_tokenizers::whitespace::WhitespaceSplit;
+        use crate::{Tokenizer, UnkPolicy};
+        use std::collections::HashMap;
+
+        let vocab: HashMap<String, u32> = vec![
+            ("hello".into(), 0),
+            ("<unk>".into(), 1),
+            ("<0xC3>".into(), 2),
+            ("<0xA9>".into(), 3),
+        ]
+        .into_iter()
+        .collect();
+        let mut tokenizer =
+            Tokenizer::new(WordLevel::builder().vocab(vocab.into()).build().unwrap());
+        tokenizer.with_pre_tokenizer(Some(WhitespaceSplit));
+
+        // The default policy emits the model's unknown token
+        assert_eq!(
+            tokenizer.encode("hello é", false).unwrap().get_ids(),
+            &[0, 1]
+        );
+
+        tokenizer.with_unk_policy(UnkPolicy::Skip);
+        assert_eq!(tokenizer.encode("hello é", false).unwrap().get_ids(), &[0]);
+
+        tokenizer.with_unk_policy(UnkPolicy::Error);
+        assert_eq!(
+            tokenizer.encode("hello é", false).unwrap_err().to_string(),
+            "Unknown token for `é` at offsets (0, 2)"
+        );
+
+        // "é" is the bytes 0xC3 0xA9, both part of the vocabulary
+        tokenizer.with_unk_policy(UnkPolicy::ByteFallback);
+        assert_eq!(
+            tokenizer.encode("hello é", false).unwrap().get_ids(),
+            &[0, 2, 3]
+        );
+        // While a character with missing byte tokens keeps the unknown token
+        assert_eq!(
+            tokenizer.encode("hello ü", false).unwrap().get_ids(),
+            &[0, 1]
+        );
+    }
+
+    #[test]
+    fn empty_input_policy_short_circuits_the_pipeline() {
+        use crate::models::wordlevel::WordLevel;
+        use crate::pre_tokenizers::whitespace::WhitespaceSplit;
+        use crate::utils::padding::PaddingParams;
+        use crate::{EmptyInputPolicy, Tokenizer};
+        use std::collections::HashMap;
+
+        let vocab: HashMap<String, u32> = vec![
+            ("hello".into(), 0),
+            ("<unk>".into(), 1),
+            ("[PAD]".into(), 2),
+        ]
+        .into_iter()
+        .collect();
+        let mut tokenizer =
+            Tokenizer::new(WordLevel::builder().vocab(vocab.into()).build().unwrap());
+        tokenizer.with_pre_tokenizer(Some(WhitespaceSplit));
+
+        // The default policy runs the pipeline, where `WhitespaceSplit`
+        // happens to drop the whole input
+        assert!(tokenizer.encode("   ", false).unwrap().is_empty());
+        assert!(tokenizer.encode("", false).unwrap().is_empty());
+
+        // `Unk` emits the unknown token of the model, spanning the input
+        tokenizer.with_empty_input_policy(EmptyInputPolicy::Unk);
+        let encoding = tokenizer.encode("   ", false).unwrap();
+        assert_eq!(encoding.get_ids(), &[1]);
+        assert_eq!(encoding.get_offsets(), &[(0, 3)]);
+        assert_eq!(tokenizer.count_tokens("   ", false).unwrap(), 1);
+
+        // `Pad` emits the configured padding token
+        tokenizer.with_empty_input_policy(EmptyInputPolicy::Pad);
+        assert!(tokenizer
+            .encode("   ", false)
+            .unwrap_err()
+            .to_string()
+            .contains("requires padding parameters"));
+        tokenizer.with_padding(Some(PaddingParams {
+            pad_id: 2,
+            pad_token: "[PAD]".into(),
+            ..PaddingParams::default()
+        }));
+        assert_eq!(tokenizer.encode("", false).unwrap().get_ids(), &[2]);
+
+        // `Empty` and `Error` short-circuit the pipeline as well
+        tokenizer.with_empty_input_policy(EmptyInputPolicy::Empty);
+        assert!(tokenizer.encode("   ", false).unwrap().is_empty());
+        tokenizer.with_empty_input_policy(EmptyInputPolicy::Error);
+        assert!(tokenizer.encode("   ", false).is_err());
+        assert!(tokenizer.count_tokens("   ", false).is_err());
+
+        // Non-blank inputs are not affected
+        assert_eq!(tokenizer.encode("hello", false).unwrap().get_ids(), &[0]);
+    }
+
+    #[test]
+    fn train_from_files_expands_directories() {
+        use crate::models::wordlevel::{WordLevel, WordLevelTrainer};
+        use crate::pre_tokenizers::whitespace::WhitespaceSplit;
+        use crate::Tokenizer;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "hello world\n").unwrap();
+        std::fs::write(dir.path().join("b.txt"), "hello again\n").unwrap();
+
+        let mut tokenizer = Tokenizer::new(WordLevel::default());
+        tokenizer.with_pre_tokenizer(Some(WhitespaceSplit));
+        let mut trainer = crate::models::TrainerWrapper::from(WordLevelTrainer::default());
+        tokenizer
+            .train_from_files(
+                &mut trainer,
+                vec![dir.path().to_string_lossy().into_owned()],
+            )
+            .unwrap();
+
+        let vocab = tokenizer.get_vocab(false);
+        assert!(vocab.contains_key("world"));
+        assert!(vocab.contains_key("again"));
+        assert_eq!(tokenizer.encode("hello", false).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn train_dedup_drops_duplicates() {
+        use crate::models::wordlevel::{WordLevel, WordLevelTrainer};
+        use crate::pre_tokenizers::whitespace::WhitespaceSplit;
+        use crate::utils::dedup::Deduplicator;
+        use crate::Tokenizer;
+
+        let mut tokenizer = Tokenizer::new(WordLevel::default());
+        tokenizer.with_pre_tokenizer(Some(WhitespaceSplit));
+        let mut trainer = crate::models::TrainerWrapper::from(WordLevelTrainer::default());
+
+        let sequences = ["spam spam", "unique words", "spam spam"];
+        let stats = tokenizer
+            .train_dedup(&mut trainer, sequences.iter(), Deduplicator::new())
+            .unwrap();
+        assert_eq!(stats.total, 3);
+        assert_eq!(stats.exact, 1);
+        assert_eq!(stats.kept, 2);
+
+        // The duplicated document counts only once: "spam" is not more
+        // frequent than the other words
+        let vocab = tokenizer.get_vocab(false);
+        assert!(vocab.contains_key("spam"));
+        assert!(vocab.contains_key("unique"));
+    }
+
+    #[test]
+    fn pipeline_mut_edits_stages() {
+        use crate::models::wordlevel::WordLevel;
+        use crate::normalizers::Lowercase;
+        use crate::pre_tokenizers::punctuation::Punctuation;
+        use crate::pre_tokenizers::whitespace::WhitespaceSplit;
+        use crate::{NormalizerWrapper, PreTokenizerWrapper, Tokenizer};
+
+        let mut tokenizer = Tokenizer::new(WordLevel::default());
+
+        // A bare component counts as a one-stage pipeline
+        tokenizer
+            .pipeline_mut()
+            .insert_pre_tokenizer(0, WhitespaceSplit)
+            .unwrap();
+        assert!(matches!(
+            tokenizer.get_pre_tokenizer(),
+            Some(PreTokenizerWrapper::WhitespaceSplit(_))
+        ));
+
+        // A second stage turns it into a `Sequence`
+        tokenizer
+            .pipeline_mut()
+            .insert_pre_tokenizer(1, Punctuation::default())
+            .unwrap();
+        assert_eq!(tokenizer.pipeline_mut().pre_tokenizer_len(), 2);
+        assert!(matches!(
+            tokenizer.get_pre_tokenizer(),
+            Some(PreTokenizerWrapper::Sequence(_))
+        ));
+        assert_eq!(
+            tokenizer.pre_tokenize_str("hey you!").unwrap(),
+            vec![
+                ("hey".to_string(), (0, 3)),
+                ("you".to_string(), (4, 7)),
+                ("!".to_string(), (7, 8)),
+            ]
+        );
+
+        // Out-of-bounds edits are rejected
+        assert!(tokenizer
+            .pipeline_mut()
+            .insert_pre_tokenizer(5, WhitespaceSplit)
+            .is_err());
+
+        // Replacing returns the previous stage, and removing shrinks the
+        // pipeline back to a bare component, then to nothing
+        let replaced = tokenizer
+            .pipeline_mut()
+            .replace_pre_tokenizer(1, WhitespaceSplit)
+            .unwrap();
+        assert!(matches!(replaced, PreTokenizerWrapper::Punctuation(_)));
+        tokenizer.pipeline_mut().remove_pre_tokenizer(0).unwrap();
+        assert!(matches!(
+            tokenizer.get_pre_tokenizer(),
+            Some(PreTokenizerWrapper::WhitespaceSplit(_))
+        ));
+        tokenizer.pipeline_mut().remove_pre_tokenizer(0).unwrap();
+        assert!(tokenizer.get_pre_tokenizer().is_none());
+
+        // The normalization pipeline is edited the same way
+        tokenizer
+            .pipeline_mut()
+            .insert_normalizer(0, Lowercase)
+            .unwrap();
+        assert_eq!(tokenizer.normalize_str("Hey").unwrap().get(), "hey");
+        let removed = tokenizer.pipeline_mut().remove_normalizer(0).unwrap();
+        assert!(matches!(removed, NormalizerWrapper::Lowercase(_)));
+        assert!(tokenizer.get_normalizer().is_none());
+    }
+
+    #[test]
+    fn word_boundary_policy_unifies_prefix_space() {
+        use crate::models::wordlevel::WordLevel;
+        use crate::pre_tokenizers::byte_level::ByteLevel;
+        use crate::{
+            DecoderWrapper, NormalizerWrapper, PostProcessorWrapper, PreTokenizer,
+            PreTokenizerWrapper, TokenizerBuilder, WordBoundaryPolicy,
+        };
+
+        let builder = || {
+            TokenizerBuilder::<
+                WordLevel,
+                NormalizerWrapper,
+                PreTokenizerWrapper,
+                PostProcessorWrapper,
+                DecoderWrapper,
+            >::new()
+            .with_model(WordLevel::default())
+            .with_pre_tokenizer(Some(ByteLevel::default().into()))
+            .with_decoder(Some(ByteLevel::default().add_prefix_space(false).into()))
+        };
+
+        // The components disagree, and no policy picks a side
+        assert_eq!(
+            builder().build().unwrap_err().to_string(),
+            "The pre-tokenizer, post-processor and decoder disagree on their \
+             prefix space configuration. Set a word boundary policy on the \
+             builder to pick one."
+        );
+
+        // An explicit policy is pushed into every component
+        let tokenizer = builder()
+            .with_word_boundary_policy(WordBoundaryPolicy::NoPrefixSpace)
+            .build()
+            .unwrap();
+        assert_eq!(
+            tokenizer.get_pre_tokenizer().unwrap().prefix_space(),
+            Some(false)
+        );
+
+        // And so is a policy set after the fact
+        let mut tokenizer = builder()
+            .with_word_boundary_policy(WordBoundaryPolicy::PrefixSpace)
+            .build()
+            .unwrap();
+        tokenizer.with_word_boundary_policy(WordBoundaryPolicy::NoPrefixSpace);
+        assert_eq!(
+            tokenizer.get_word_boundary_policy(),
+            WordBoundaryPolicy::NoPrefixSpace
+        );
+        assert_eq!(
+            tokenizer.get_pre_tokenizer().unwrap().prefix_space(),
+            Some(false)
+        );
+    }
 }
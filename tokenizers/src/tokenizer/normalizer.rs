@@ -4,6 +4,23 @@ use std::ops::{Bound, RangeBounds};
 use unicode_normalization_alignments::UnicodeNormalization;
 
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ASCII_FAST_PATH: AtomicBool = AtomicBool::new(false);
+
+/// Enable or disable the ASCII fast path, disabled by default. When enabled,
+/// fully-ASCII strings skip the NFC/NFD/NFKC/NFKD work entirely (ASCII is
+/// invariant under every Unicode normal form) and [`NormalizedString::lowercase`]
+/// uses an in-place byte-level mapping. Unicode normalization dominates
+/// normalizer time on English corpora, while the results are identical.
+pub fn set_ascii_fast_path(enabled: bool) {
+    ASCII_FAST_PATH.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether the ASCII fast path is currently enabled, see [`set_ascii_fast_path`]
+pub fn ascii_fast_path() -> bool {
+    ASCII_FAST_PATH.load(Ordering::Relaxed)
+}
 
 /// The possible offsets referential
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -135,6 +152,12 @@ impl NormalizedString {
         &self.original
     }
 
+    /// Return the alignments: for each byte of the normalized string, the
+    /// (start, end) byte range of the original one it comes from
+    pub fn alignments(&self) -> &[(usize, usize)] {
+        &self.alignments
+    }
+
     /// Return the original offsets
     pub fn offsets_original(&self) -> Offsets {
         (
@@ -436,26 +459,44 @@ impl NormalizedString {
         self.transform_range(Range::Original(..), dest, initial_offset)
     }
 
+    /// Whether the ASCII fast path applies to this string: it must be enabled
+    /// with [`set_ascii_fast_path`], and the string fully ASCII
+    fn use_ascii_fast_path(&self) -> bool {
+        ascii_fast_path() && self.normalized.is_ascii()
+    }
+
     /// Applies NFD normalization
     pub fn nfd(&mut self) -> &mut Self {
+        if self.use_ascii_fast_path() {
+            return self;
+        }
         self.transform(self.get().to_owned().nfd(), 0);
         self
     }
 
     /// Applies NFKD normalization
     pub fn nfkd(&mut self) -> &mut Self {
+        if self.use_ascii_fast_path() {
+            return self;
+        }
         self.transform(self.get().to_owned().nfkd(), 0);
         self
     }
 
     /// Applies NFC normalization
     pub fn nfc(&mut self) -> &mut Self {
+        if self.use_ascii_fast_path() {
+            return self;
+        }
         self.transform(self.get().to_owned().nfc(), 0);
         self
     }
 
     /// Applies NFKC normalization
     pub fn nfkc(&mut self) -> &mut Self {
+        if self.use_ascii_fast_path() {
+            return self;
+        }
         self.transform(self.get().to_owned().nfkc(), 0);
         self
     }
@@ -532,6 +573,12 @@ impl NormalizedString {
 
     /// Lowercase
     pub fn lowercase(&mut self) -> &mut Self {
+        if self.use_ascii_fast_path() {
+            // A byte-level mapping cannot change lengths, so the alignments
+            // are left untouched
+            self.normalized.make_ascii_lowercase();
+            return self;
+        }
         let mut new_chars: Vec<(char, isize)> = vec![];
         self.for_each(|c| {
             c.to_lowercase().enumerate().for_each(|(index, c)| {
@@ -556,101 +603,111 @@ impl NormalizedString {
 
     /// Replace anything that matches the pattern with the given content.
     pub fn replace<P: Pattern>(&mut self, pattern: P, content: &str) -> Result<()> {
+        let matches = pattern
+            .find_matches(&self.normalized)?
+            .into_iter()
+            .filter_map(|(range, is_match)| is_match.then_some((range, content)));
+        self.replace_ranges(matches);
+        Ok(())
+    }
+
+    /// Replace each of the given ranges of the normalized string with its
+    /// associated content, in a single pass. The ranges must be
+    /// non-overlapping and sorted by start position.
+    pub fn replace_ranges<'a, I>(&mut self, matches: I)
+    where
+        I: IntoIterator<Item = ((usize, usize), &'a str)>,
+    {
         let mut new_normalized = String::with_capacity(self.normalized.len()); // Initially allocate for the input size
         let mut new_alignments: Vec<(usize, usize)> = Vec::with_capacity(self.alignments.len());
         let mut last_end = 0; // Keep track of the last end position
 
-        pattern
-            .find_matches(&self.normalized)?
-            .into_iter()
-            .for_each(|((start, end), is_match)| {
-                if is_match {
-                    let range = start..end;
-
-                    let mut new_len = 0;
-                    let removed_chars = self.normalized[range.clone()].chars().count();
-
-                    /* The following code is equivalent to this call, but computationally much more efficient
-                    self.transform_range(
-                        Range::Normalized(range),
-                        content.chars().map(|c| {
-                            new_len += c.len_utf8();
-                            (c, 1)
-                        }),
-                        removed_chars,
-                    ); */
-
-                    // Copy the part of the string that is before the match
-                    new_normalized.push_str(&self.normalized[last_end..start]);
-                    new_alignments.extend(self.alignments[last_end..start].iter().cloned());
-
-                    let n_range = Range::Normalized(range).into_full_range(self.len());
-
-                    // Retrieve the original characters that are being replaced. This let us
-                    // compute the change in byte sizes along the way.
-                    let mut replaced_normalized = self.normalized[n_range.clone()]
-                        .chars()
-                        .collect::<Vec<_>>()
-                        .into_iter();
-                    let initial_removed: usize = (&mut replaced_normalized)
-                        .take(removed_chars)
-                        .map(|c| c.len_utf8())
-                        .sum();
-
-                    let dest = content.chars().map(|c| {
-                        new_len += c.len_utf8();
-                        (c, 1)
-                    });
-                    let mut offset = (initial_removed + n_range.start) as isize;
-                    let normalized = dest
-                        .into_iter()
-                        .map(|(c, changes): (char, i32)| {
-                            let idx = offset as usize;
-                            let align = if changes.is_positive() {
-                                if idx < 1 {
-                                    (0, 0)
-                                } else {
-                                    // This is a newly inserted character, so it shares the same alignment
-                                    // than the previous one
-                                    self.alignments[idx - 1]
-                                }
-                            } else {
-                                self.alignments[idx]
-                            };
+        matches.into_iter().for_each(|((start, end), content)| {
+            let range = start..end;
 
-                            // If we are replacing a character, find it and compute the change in size
-                            let replaced_char = if !changes.is_positive() {
-                                replaced_normalized.next()
-                            } else {
-                                None
-                            };
-                            let replaced_char_size = replaced_char.map_or(0, |c| c.len_utf8());
-
-                            // If we are removing some characters, find them too
-                            let total_bytes_to_remove = if changes.is_negative() {
-                                (&mut replaced_normalized)
-                                    .take(-changes as usize)
-                                    .map(|c| c.len_utf8())
-                                    .sum()
-                            } else {
-                                0
-                            };
+            let mut new_len = 0;
+            let removed_chars = self.normalized[range.clone()].chars().count();
 
-                            // Keep track of the changes for next offsets
-                            offset += replaced_char_size as isize;
-                            offset += total_bytes_to_remove as isize;
+            /* The following code is equivalent to this call, but computationally much more efficient
+            self.transform_range(
+                Range::Normalized(range),
+                content.chars().map(|c| {
+                    new_len += c.len_utf8();
+                    (c, 1)
+                }),
+                removed_chars,
+            ); */
 
-                            new_alignments.extend((0..c.len_utf8()).map(|_| align));
+            // Copy the part of the string that is before the match
+            new_normalized.push_str(&self.normalized[last_end..start]);
+            new_alignments.extend(self.alignments[last_end..start].iter().cloned());
 
-                            // Then we keep only the char for string reconstruction
-                            c
-                        })
-                        .collect::<String>();
+            let n_range = Range::Normalized(range).into_full_range(self.len());
 
-                    new_normalized.push_str(&normalized);
-                    last_end = end;
-                }
+            // Retrieve the original characters that are being replaced. This let us
+            // compute the change in byte sizes along the way.
+            let mut replaced_normalized = self.normalized[n_range.clone()]
+                .chars()
+                .collect::<Vec<_>>()
+                .into_iter();
+            let initial_removed: usize = (&mut replaced_normalized)
+                .take(removed_chars)
+                .map(|c| c.len_utf8())
+                .sum();
+
+            let dest = content.chars().map(|c| {
+                new_len += c.len_utf8();
+                (c, 1)
             });
+            let mut offset = (initial_removed + n_range.start) as isize;
+            let normalized = dest
+                .into_iter()
+                .map(|(c, changes): (char, i32)| {
+                    let idx = offset as usize;
+                    let align = if changes.is_positive() {
+                        if idx < 1 {
+                            (0, 0)
+                        } else {
+                            // This is a newly inserted character, so it shares the same alignment
+                            // than the previous one
+                            self.alignments[idx - 1]
+                        }
+                    } else {
+                        self.alignments[idx]
+                    };
+
+                    // If we are replacing a character, find it and compute the change in size
+                    let replaced_char = if !changes.is_positive() {
+                        replaced_normalized.next()
+                    } else {
+                        None
+                    };
+                    let replaced_char_size = replaced_char.map_or(0, |c| c.len_utf8());
+
+                    // If we are removing some characters, find them too
+                    let total_bytes_to_remove = if changes.is_negative() {
+                        (&mut replaced_normalized)
+                            .take(-changes as usize)
+                            .map(|c| c.len_utf8())
+                            .sum()
+                    } else {
+                        0
+                    };
+
+                    // Keep track of the changes for next offsets
+                    offset += replaced_char_size as isize;
+                    offset += total_bytes_to_remove as isize;
+
+                    new_alignments.extend((0..c.len_utf8()).map(|_| align));
+
+                    // Then we keep only the char for string reconstruction
+                    c
+                })
+                .collect::<String>();
+
+            new_normalized.push_str(&normalized);
+            last_end = end;
+        });
 
         // Copy the remaining part of the input
         new_normalized.push_str(&self.normalized[last_end..]);
@@ -658,7 +715,6 @@ impl NormalizedString {
 
         self.normalized = new_normalized;
         self.alignments = new_alignments;
-        Ok(())
     }
 
     /// Clear the normalized part of the string
@@ -786,17 +842,24 @@ impl NormalizedString {
     }
 
     fn lrstrip(&mut self, left: bool, right: bool) -> &mut Self {
+        self.strip_matching(left, right, char::is_whitespace)
+    }
+
+    /// Remove any leading and/or trailing character matching `matches`, like
+    /// [`NormalizedString::strip`] but for an arbitrary character class
+    pub fn strip_matching<F: Fn(char) -> bool>(
+        &mut self,
+        left: bool,
+        right: bool,
+        matches: F,
+    ) -> &mut Self {
         let leading_spaces = if left {
-            self.get().chars().take_while(|c| c.is_whitespace()).count()
+            self.get().chars().take_while(|c| matches(*c)).count()
         } else {
             0
         };
         let trailing_spaces = if right {
-            self.get()
-                .chars()
-                .rev()
-                .take_while(|c| c.is_whitespace())
-                .count()
+            self.get().chars().rev().take_while(|c| matches(*c)).count()
         } else {
             0
         };
@@ -837,6 +900,14 @@ impl NormalizedString {
         self.normalized.is_empty()
     }
 
+    /// Returns the approximate number of heap bytes held by this `NormalizedString`:
+    /// both versions of the string, plus the alignments table.
+    pub fn allocated_bytes(&self) -> usize {
+        self.original.capacity()
+            + self.normalized.capacity()
+            + self.alignments.capacity() * std::mem::size_of::<(usize, usize)>()
+    }
+
     /// Recalculate original alignments
     #[allow(dead_code)]
     pub(crate) fn alignments_original(&self) -> Vec<(usize, usize)> {
@@ -983,6 +1054,72 @@ pub fn char_to_bytes(s: &str, range: std::ops::Range<usize>) -> Option<std::ops:
     Some(start?..end?)
 }
 
+/// Check that the alignments of the given [`NormalizedString`] are consistent
+/// with the original text it was built from: every byte of the normalized
+/// string maps back to a valid, char-aligned range of `original`, and the
+/// mapping is monotonic. Custom [`Normalizer`] implementations can call this
+/// in debug builds (or from a property-testing suite) to catch alignment bugs
+/// before they corrupt offsets downstream.
+pub fn check_alignments(original: &str, normalized: &NormalizedString) -> Result<()> {
+    if normalized.get_original() != original {
+        return Err(format!(
+            "check_alignments: the NormalizedString original {:?} does not match {:?}",
+            normalized.get_original(),
+            original
+        )
+        .into());
+    }
+    if normalized.alignments.len() != normalized.normalized.len() {
+        return Err(format!(
+            "check_alignments: {} alignments for {} normalized bytes",
+            normalized.alignments.len(),
+            normalized.normalized.len()
+        )
+        .into());
+    }
+
+    let mut last: Offsets = (0, 0);
+    for (i, &(start, end)) in normalized.alignments.iter().enumerate() {
+        if start > end {
+            return Err(format!(
+                "check_alignments: reversed range {:?} at normalized byte {}",
+                (start, end),
+                i
+            )
+            .into());
+        }
+        if end > original.len() {
+            return Err(format!(
+                "check_alignments: range {:?} at normalized byte {} exceeds the original length {}",
+                (start, end),
+                i,
+                original.len()
+            )
+            .into());
+        }
+        if !original.is_char_boundary(start) || !original.is_char_boundary(end) {
+            return Err(format!(
+                "check_alignments: range {:?} at normalized byte {} splits a character",
+                (start, end),
+                i
+            )
+            .into());
+        }
+        if start < last.0 || end < last.1 {
+            return Err(format!(
+                "check_alignments: non monotonic range {:?} after {:?} at normalized byte {}",
+                (start, end),
+                last,
+                i
+            )
+            .into());
+        }
+        last = (start, end);
+    }
+
+    Ok(())
+}
+
 impl From<String> for NormalizedString {
     fn from(s: String) -> Self {
         let alignments = s
@@ -1013,6 +1150,36 @@ mod tests {
     use regex::Regex;
     use unicode_categories::UnicodeCategories;
 
+    #[test]
+    fn test_check_alignments() {
+        let mut n = NormalizedString::from("élégant");
+        n.nfd();
+        check_alignments("élégant", &n).unwrap();
+
+        // Wrong original text
+        assert!(check_alignments("elegant", &n).is_err());
+
+        // A range splitting the 'é' character
+        let n = NormalizedString::new("é".into(), "e".into(), vec![(0, 1)], 0);
+        assert!(check_alignments("é", &n)
+            .unwrap_err()
+            .to_string()
+            .contains("splits a character"));
+
+        // Not one alignment per normalized byte
+        let n = NormalizedString::new("ab".into(), "ab".into(), vec![(0, 1)], 0);
+        assert!(check_alignments("ab", &n).is_err());
+
+        // Reversed and non-monotonic ranges
+        let n = NormalizedString::new("ab".into(), "ab".into(), vec![(1, 0), (1, 2)], 0);
+        assert!(check_alignments("ab", &n).is_err());
+        let n = NormalizedString::new("ab".into(), "ab".into(), vec![(1, 2), (0, 1)], 0);
+        assert!(check_alignments("ab", &n)
+            .unwrap_err()
+            .to_string()
+            .contains("non monotonic"));
+    }
+
     #[test]
     fn nfd_adds_new_chars() {
         let mut n = NormalizedString::from("élégant");
@@ -2262,4 +2429,31 @@ mod tests {
         s.lowercase();
         assert_eq!(s.get(), "a...");
     }
+
+    #[test]
+    fn ascii_fast_path_parity() {
+        // The fast path is only a shortcut: it must produce the exact same
+        // strings and alignments as the regular path. Non-ASCII inputs
+        // (or mixed ones) take the regular path even when it is enabled.
+        for input in [
+            "Hello THERE, friend!",
+            "caf\u{e9} \u{212b}ngstr\u{f6}m",
+            "abc\u{304}",
+        ] {
+            let mut slow = NormalizedString::from(input);
+            slow.nfkc().lowercase();
+            let mut slow_decomposed = NormalizedString::from(input);
+            slow_decomposed.nfd();
+
+            set_ascii_fast_path(true);
+            let mut fast = NormalizedString::from(input);
+            fast.nfkc().lowercase();
+            let mut fast_decomposed = NormalizedString::from(input);
+            fast_decomposed.nfd();
+            set_ascii_fast_path(false);
+
+            assert_eq!(fast, slow);
+            assert_eq!(fast_decomposed, slow_decomposed);
+        }
+    }
 }
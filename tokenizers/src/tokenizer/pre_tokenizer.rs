@@ -8,9 +8,59 @@ use std::collections::HashMap;
 pub enum OffsetType {
     Byte,
     Char,
+    /// Offsets counted in extended grapheme clusters of the original string,
+    /// the "user-perceived characters" that UIs highlight
+    Grapheme,
     None,
 }
 
+/// What to do with the offsets of a region of the original string that was
+/// entirely removed by normalization, and would otherwise map back to an
+/// empty range.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum OffsetRecoveryPolicy {
+    /// Keep the empty range as is
+    #[default]
+    Empty,
+    /// Expand the range to include the previous character of the original string
+    ExpandLeft,
+    /// Expand the range to include the next character of the original string
+    ExpandRight,
+}
+
+/// Apply the given `OffsetRecoveryPolicy` to byte `offsets` into `original`,
+/// expanding empty ranges to the nearest character boundary when asked to.
+fn recover_offsets(offsets: Offsets, original: &str, policy: OffsetRecoveryPolicy) -> Offsets {
+    if offsets.0 < offsets.1 {
+        return offsets;
+    }
+    match policy {
+        OffsetRecoveryPolicy::Empty => offsets,
+        OffsetRecoveryPolicy::ExpandLeft => {
+            let mut start = offsets.0.min(original.len());
+            if start == 0 {
+                return offsets;
+            }
+            start -= 1;
+            while start > 0 && !original.is_char_boundary(start) {
+                start -= 1;
+            }
+            (start, offsets.1)
+        }
+        OffsetRecoveryPolicy::ExpandRight => {
+            let mut end = offsets.1;
+            if end >= original.len() {
+                return offsets;
+            }
+            end += 1;
+            while end < original.len() && !original.is_char_boundary(end) {
+                end += 1;
+            }
+            (offsets.0, end)
+        }
+    }
+}
+
 /// Wrapper for a subpart of a `NormalizedString`.
 ///
 /// This Split contains the underlying `NormalizedString` as well as its offsets
@@ -24,6 +74,50 @@ pub struct Split {
     normalized: NormalizedString,
     /// Optional Tokens associated to this Split
     tokens: Option<Vec<Token>>,
+    /// Whether this Split is atomic: atomic splits are never split any further
+    /// by subsequent calls to `PreTokenizedString::split`
+    atomic: bool,
+    /// An optional category label attached by the pre-tokenizer that produced
+    /// this split
+    label: Option<String>,
+    /// An optional metadata tag id attached by the pre-tokenizer that produced
+    /// this split, propagated to the tokens of the resulting `Encoding`
+    tag: Option<u32>,
+}
+
+impl Split {
+    /// Create a new atomic `Split`, that subsequent pre-tokenizers will leave
+    /// untouched
+    pub fn atomic(normalized: NormalizedString) -> Self {
+        Self {
+            normalized,
+            tokens: None,
+            atomic: true,
+            label: None,
+            tag: None,
+        }
+    }
+
+    /// Create a new atomic `Split` carrying a category label, retrievable with
+    /// [`PreTokenizedString::get_split_labels`]
+    pub fn labeled(normalized: NormalizedString, label: impl Into<String>) -> Self {
+        Self {
+            normalized,
+            tokens: None,
+            atomic: true,
+            label: Some(label.into()),
+            tag: None,
+        }
+    }
+
+    /// Attach a metadata tag id to this split (e.g. a detected language or an
+    /// is_code flag), propagated to the tokens of the resulting `Encoding`
+    /// as a parallel list retrievable with `Encoding::get_tags`
+    #[must_use]
+    pub fn with_tag(mut self, tag: u32) -> Self {
+        self.tag = Some(tag);
+        self
+    }
 }
 
 impl From<NormalizedString> for Split {
@@ -31,6 +125,9 @@ impl From<NormalizedString> for Split {
         Self {
             normalized: n,
             tokens: None,
+            atomic: false,
+            label: None,
+            tag: None,
         }
     }
 }
@@ -40,6 +137,9 @@ impl From<(NormalizedString, Option<Vec<Token>>)> for Split {
         Self {
             normalized: f.0,
             tokens: f.1,
+            atomic: false,
+            label: None,
+            tag: None,
         }
     }
 }
@@ -69,7 +169,31 @@ impl PreTokenizedString {
     /// > same `original` string as the original one given to `split_fn`. This concretely
     /// > means that for the offset tracking to work as expected, `split_fn` must produce
     /// > "splits" of the original string.
-    pub fn split<F, U, R>(&mut self, mut split_fn: F) -> Result<()>
+    pub fn split<F, U, R>(&mut self, split_fn: F) -> Result<()>
+    where
+        F: FnMut(usize, NormalizedString) -> Result<U>,
+        U: IntoIterator<Item = R>,
+        R: Into<Split>,
+    {
+        self.split_impl(split_fn, None)
+    }
+
+    /// Same as [`PreTokenizedString::split`], but fails if more than `max_splits`
+    /// splits would be produced. Since each `Split` carries its own
+    /// `NormalizedString`, pathological inputs combined with aggressive
+    /// pre-tokenizers can allocate orders of magnitude more than the input size;
+    /// this gives callers a way to bound that. When the cap is hit, the splits
+    /// produced so far are kept and an error is returned.
+    pub fn split_capped<F, U, R>(&mut self, max_splits: usize, split_fn: F) -> Result<()>
+    where
+        F: FnMut(usize, NormalizedString) -> Result<U>,
+        U: IntoIterator<Item = R>,
+        R: Into<Split>,
+    {
+        self.split_impl(split_fn, Some(max_splits))
+    }
+
+    fn split_impl<F, U, R>(&mut self, mut split_fn: F, max_splits: Option<usize>) -> Result<()>
     where
         F: FnMut(usize, NormalizedString) -> Result<U>,
         U: IntoIterator<Item = R>,
@@ -78,7 +202,7 @@ impl PreTokenizedString {
         // new_splits is at least as big as self.splits
         let mut new_splits = Vec::with_capacity(self.splits.len());
         for (i, original_split) in self.splits.drain(..).enumerate() {
-            if original_split.tokens.is_some() {
+            if original_split.tokens.is_some() || original_split.atomic {
                 new_splits.push(original_split);
                 continue;
             }
@@ -95,12 +219,42 @@ impl PreTokenizedString {
                         }
                     }),
             );
+
+            if max_splits.is_some_and(|max| new_splits.len() > max) {
+                break;
+            }
         }
         self.splits = new_splits;
 
+        if let Some(max_splits) = max_splits {
+            if self.splits.len() > max_splits {
+                return Err(
+                    format!("Pre-tokenization produced more than {max_splits} splits").into(),
+                );
+            }
+        }
+
         Ok(())
     }
 
+    /// Return the approximate number of heap bytes currently held by this
+    /// `PreTokenizedString` and all its splits. Useful to quantify the memory
+    /// amplification caused by a pre-tokenization pipeline on long documents.
+    pub fn allocated_bytes(&self) -> usize {
+        self.original.capacity()
+            + self
+                .splits
+                .iter()
+                .map(|split| {
+                    split.normalized.allocated_bytes()
+                        + split.tokens.as_ref().map_or(0, |tokens| {
+                            tokens.len() * std::mem::size_of::<Token>()
+                                + tokens.iter().map(|t| t.value.capacity()).sum::<usize>()
+                        })
+                })
+                .sum::<usize>()
+    }
+
     /// Normalized all the splits that do not have attached `Tokens`, using the provided
     /// `normalize` function.
     pub fn normalize<F>(&mut self, normalize: F) -> Result<()>
@@ -126,6 +280,30 @@ impl PreTokenizedString {
         Ok(())
     }
 
+    /// Like [`PreTokenizedString::tokenize`], also passing the text of the
+    /// previous and next splits to the `tokenize` function, for
+    /// context-sensitive models
+    pub fn tokenize_with_context<F>(&mut self, tokenize: F) -> Result<()>
+    where
+        F: Fn(Option<&str>, &NormalizedString, Option<&str>) -> Result<Vec<Token>>,
+    {
+        for idx in 0..self.splits.len() {
+            if self.splits[idx].tokens.is_some() {
+                continue;
+            }
+            let tokens = {
+                let prev = idx
+                    .checked_sub(1)
+                    .map(|prev| self.splits[prev].normalized.get());
+                let next = self.splits.get(idx + 1).map(|s| s.normalized.get());
+                tokenize(prev, &self.splits[idx].normalized, next)?
+            };
+            self.splits[idx].tokens = Some(tokens);
+        }
+
+        Ok(())
+    }
+
     /// Transform the current `PreTokenizedString` into an `Encoding`.
     ///
     /// If a `word_idx` is provided, any word in the generated `Encoding`
@@ -138,17 +316,43 @@ impl PreTokenizedString {
         word_idx: Option<u32>,
         type_id: u32,
         offset_type: OffsetType,
+    ) -> Result<Encoding> {
+        self.into_encoding_with_policy(word_idx, type_id, offset_type, OffsetRecoveryPolicy::Empty)
+    }
+
+    /// Same as [`PreTokenizedString::into_encoding`], with an explicit
+    /// [`OffsetRecoveryPolicy`] deciding how tokens whose original content was
+    /// entirely removed by normalization map back to the original string.
+    pub fn into_encoding_with_policy(
+        self,
+        word_idx: Option<u32>,
+        type_id: u32,
+        offset_type: OffsetType,
+        policy: OffsetRecoveryPolicy,
     ) -> Result<Encoding> {
         if self.splits.is_empty() {
             Ok(Encoding::default())
         } else if !self.splits.iter().all(|split| split.tokens.is_some()) {
             Err("Split has not been tokenized, call `PreTokenizedString::tokenize` first".into())
         } else {
+            // One tag per token, from the split it comes from, unless no split
+            // was tagged at all
+            let tags: Vec<Option<u32>> = if self.splits.iter().any(|split| split.tag.is_some()) {
+                self.splits
+                    .iter()
+                    .flat_map(|split| {
+                        std::iter::repeat_n(split.tag, split.tokens.as_ref().unwrap().len())
+                    })
+                    .collect()
+            } else {
+                vec![]
+            };
             let offset_converter = match offset_type {
                 OffsetType::Char => Some(BytesToCharOffsetConverter::new(&self.original)),
+                OffsetType::Grapheme => Some(BytesToCharOffsetConverter::graphemes(&self.original)),
                 OffsetType::Byte => None,
                 OffsetType::None => {
-                    let tokens = self
+                    let mut encoding: Encoding = self
                         .splits
                         .into_iter()
                         .flat_map(|split| {
@@ -158,11 +362,13 @@ impl PreTokenizedString {
                             })
                         })
                         .collect();
-                    return Ok(tokens);
+                    encoding.set_tags(tags);
+                    return Ok(encoding);
                 }
             };
 
-            Ok(self
+            let original = self.original;
+            let mut encoding: Encoding = self
                 .splits
                 .into_iter()
                 .enumerate()
@@ -170,6 +376,7 @@ impl PreTokenizedString {
                     let normalized = split.normalized;
                     let offsets = normalized.offsets_original();
                     let offset_converter = &offset_converter;
+                    let original = &original;
 
                     split.tokens.unwrap().into_iter().map(move |token| {
                         let mut offsets = normalized
@@ -178,6 +385,8 @@ impl PreTokenizedString {
                                 (offsets.0 + range.start, offsets.0 + range.end)
                             });
 
+                        offsets = recover_offsets(offsets, original, policy);
+
                         // Convert to char offsets if relevant
                         if let Some(converter) = offset_converter {
                             offsets = converter.convert(offsets).unwrap_or(offsets);
@@ -196,7 +405,9 @@ impl PreTokenizedString {
                         )
                     })
                 })
-                .collect())
+                .collect();
+            encoding.set_tags(tags);
+            Ok(encoding)
         }
     }
 
@@ -208,34 +419,93 @@ impl PreTokenizedString {
         offset_ref: OffsetReferential,
         offset_type: OffsetType,
     ) -> Vec<(&str, Offsets, &Option<Vec<Token>>)> {
+        self.get_splits_with_policy(offset_ref, offset_type, OffsetRecoveryPolicy::Empty)
+    }
+
+    /// Same as [`PreTokenizedString::get_splits`], with an explicit
+    /// [`OffsetRecoveryPolicy`] applied to splits mapping back to an empty range
+    /// of the original string.
+    pub fn get_splits_with_policy(
+        &self,
+        offset_ref: OffsetReferential,
+        offset_type: OffsetType,
+        policy: OffsetRecoveryPolicy,
+    ) -> Vec<(&str, Offsets, &Option<Vec<Token>>)> {
+        self.splits_iter_with_policy(offset_ref, offset_type, policy)
+            .collect()
+    }
+
+    /// Iterate over the splits without materializing them into a `Vec`, each
+    /// item being a slice of the normalized string, the associated offsets
+    /// either in original or normalized referential, as well as the potential
+    /// tokens. This is the borrowing counterpart of
+    /// [`PreTokenizedString::get_splits`], meant for scanning huge documents
+    /// without the O(splits) allocation.
+    pub fn splits_iter(
+        &self,
+        offset_ref: OffsetReferential,
+        offset_type: OffsetType,
+    ) -> impl Iterator<Item = (&str, Offsets, &Option<Vec<Token>>)> + '_ {
+        self.splits_iter_with_policy(offset_ref, offset_type, OffsetRecoveryPolicy::Empty)
+    }
+
+    fn splits_iter_with_policy(
+        &self,
+        offset_ref: OffsetReferential,
+        offset_type: OffsetType,
+        policy: OffsetRecoveryPolicy,
+    ) -> impl Iterator<Item = (&str, Offsets, &Option<Vec<Token>>)> + '_ {
         let offset_converter = match offset_type {
             OffsetType::Char => Some(BytesToCharOffsetConverter::new(&self.original)),
+            OffsetType::Grapheme => Some(BytesToCharOffsetConverter::graphemes(&self.original)),
             OffsetType::Byte => None,
             OffsetType::None => None,
         };
 
         let mut offset = 0;
-        self.splits
-            .iter()
-            .map(|split| {
-                let mut offsets = match offset_ref {
-                    OffsetReferential::Original => split.normalized.offsets_original(),
-                    OffsetReferential::Normalized => {
-                        let len = split.normalized.len();
-                        offset += len;
-                        (offset - len, offset)
-                    }
-                };
-
-                // Convert to char offsets if relevant
-                if let Some(ref converter) = offset_converter {
-                    offsets = converter.convert(offsets).unwrap_or(offsets);
+        self.splits.iter().map(move |split| {
+            let mut offsets = match offset_ref {
+                OffsetReferential::Original => {
+                    recover_offsets(split.normalized.offsets_original(), &self.original, policy)
+                }
+                OffsetReferential::Normalized => {
+                    let len = split.normalized.len();
+                    offset += len;
+                    (offset - len, offset)
                 }
+            };
 
-                (split.normalized.get(), offsets, &split.tokens)
-            })
+            // Convert to char offsets if relevant
+            if let Some(ref converter) = offset_converter {
+                offsets = converter.convert(offsets).unwrap_or(offsets);
+            }
+
+            (split.normalized.get(), offsets, &split.tokens)
+        })
+    }
+
+    /// Return the category label of each split, in the same order as
+    /// [`PreTokenizedString::get_splits`]. Labels are attached by
+    /// pre-tokenizers that categorize the sections they isolate, such as
+    /// [`MultiSplit`](crate::pre_tokenizers::split::MultiSplit); unlabeled
+    /// splits yield `None`.
+    pub fn get_split_labels(&self) -> Vec<Option<&str>> {
+        self.splits
+            .iter()
+            .map(|split| split.label.as_deref())
             .collect()
     }
+
+    /// Return the metadata tag id of each split, in the same order as
+    /// [`PreTokenizedString::get_splits`]; untagged splits yield `None`
+    pub fn get_split_tags(&self) -> Vec<Option<u32>> {
+        self.splits.iter().map(|split| split.tag).collect()
+    }
+
+    /// Iterate over the underlying `NormalizedString` of each split, in order
+    pub fn iter_normalized(&self) -> impl Iterator<Item = &NormalizedString> {
+        self.splits.iter().map(|split| &split.normalized)
+    }
 }
 
 impl From<NormalizedString> for PreTokenizedString {
@@ -245,6 +515,9 @@ impl From<NormalizedString> for PreTokenizedString {
             splits: vec![Split {
                 normalized: s,
                 tokens: None,
+                atomic: false,
+                label: None,
+                tag: None,
             }],
         }
     }
@@ -287,6 +560,19 @@ impl BytesToCharOffsetConverter {
         }
     }
 
+    /// Same converter, mapping byte offsets to extended grapheme cluster
+    /// indices instead of char indices
+    pub fn graphemes(sequence: &str) -> Self {
+        use unicode_segmentation::UnicodeSegmentation;
+        Self {
+            map: sequence
+                .grapheme_indices(true)
+                .enumerate()
+                .flat_map(|(i, (b, g))| (b..b + g.len()).map(move |o| (o, i)))
+                .collect(),
+        }
+    }
+
     pub fn convert(&self, offsets: Offsets) -> Option<Offsets> {
         match (self.map.get(&offsets.0), self.map.get(&offsets.1)) {
             (Some(start), Some(end)) => Some((*start, *end)),
@@ -300,3 +586,178 @@ impl BytesToCharOffsetConverter {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SplitDelimiterBehavior;
+
+    #[test]
+    fn tokenize_with_context_passes_neighbors() {
+        let mut pretokenized = PreTokenizedString::from("Hello my friend");
+        pretokenized
+            .split(|_, s| s.split(char::is_whitespace, SplitDelimiterBehavior::Removed))
+            .unwrap();
+        pretokenized
+            .tokenize_with_context(|prev, normalized, next| {
+                Ok(vec![Token::new(
+                    0,
+                    format!(
+                        "{}|{}|{}",
+                        prev.unwrap_or(""),
+                        normalized.get(),
+                        next.unwrap_or("")
+                    ),
+                    (0, normalized.get().len()),
+                )])
+            })
+            .unwrap();
+        let encoding = pretokenized
+            .into_encoding(None, 0, OffsetType::Byte)
+            .unwrap();
+        assert_eq!(
+            encoding.get_tokens(),
+            &["|Hello|my", "Hello|my|friend", "my|friend|"]
+        );
+    }
+
+    #[test]
+    fn split_capped_bounds_splits() {
+        let mut pretokenized = PreTokenizedString::from("a b c d");
+        assert!(pretokenized
+            .split_capped(2, |_, normalized| {
+                normalized.split(' ', SplitDelimiterBehavior::Removed)
+            })
+            .is_err());
+
+        let mut pretokenized = PreTokenizedString::from("a b c d");
+        pretokenized
+            .split_capped(4, |_, normalized| {
+                normalized.split(' ', SplitDelimiterBehavior::Removed)
+            })
+            .unwrap();
+        assert_eq!(
+            pretokenized.get_splits(OffsetReferential::Original, OffsetType::Byte),
+            vec![
+                ("a", (0, 1), &None),
+                ("b", (2, 3), &None),
+                ("c", (4, 5), &None),
+                ("d", (6, 7), &None),
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_iter_matches_get_splits() {
+        let mut pretokenized = PreTokenizedString::from("a b c d");
+        pretokenized
+            .split(|_, normalized| normalized.split(' ', SplitDelimiterBehavior::Removed))
+            .unwrap();
+        for (offset_ref, offset_type) in [
+            (OffsetReferential::Original, OffsetType::Byte),
+            (OffsetReferential::Normalized, OffsetType::Char),
+        ] {
+            assert_eq!(
+                pretokenized
+                    .splits_iter(offset_ref, offset_type)
+                    .collect::<Vec<_>>(),
+                pretokenized.get_splits(offset_ref, offset_type)
+            );
+        }
+    }
+
+    #[test]
+    fn grapheme_offsets() {
+        // "é" as e + combining accent (one grapheme, two chars, three bytes),
+        // then an emoji flag (one grapheme, two chars, eight bytes)
+        let mut pretokenized = PreTokenizedString::from("e\u{301}x 🇫🇷");
+        pretokenized
+            .split(|_, normalized| normalized.split(' ', SplitDelimiterBehavior::Removed))
+            .unwrap();
+        assert_eq!(
+            pretokenized
+                .get_splits(OffsetReferential::Original, OffsetType::Grapheme)
+                .into_iter()
+                .map(|(s, o, _)| (s, o))
+                .collect::<Vec<_>>(),
+            vec![("e\u{301}x", (0, 2)), ("🇫🇷", (3, 4))]
+        );
+        // The same splits measured in chars
+        assert_eq!(
+            pretokenized
+                .get_splits(OffsetReferential::Original, OffsetType::Char)
+                .into_iter()
+                .map(|(s, o, _)| (s, o))
+                .collect::<Vec<_>>(),
+            vec![("e\u{301}x", (0, 3)), ("🇫🇷", (4, 6))]
+        );
+    }
+
+    #[test]
+    fn offset_recovery_policies() {
+        // "a\u{ad}b" with the soft hyphen removed by normalization: the middle
+        // split maps back to an empty range of the original string
+        fn get_pretokenized() -> PreTokenizedString {
+            let mut pretokenized = PreTokenizedString::from("a\u{ad}b");
+            pretokenized
+                .normalize(|normalized| {
+                    normalized.filter(|c| c != '\u{ad}');
+                    Ok(())
+                })
+                .unwrap();
+            pretokenized
+                .split(|_, normalized| {
+                    Ok(vec![
+                        normalized.slice(Range::Normalized(0..1)).unwrap(),
+                        normalized.slice(Range::Normalized(1..2)).unwrap(),
+                    ])
+                })
+                .unwrap();
+            pretokenized
+        }
+
+        let pretokenized = get_pretokenized();
+        let offsets = |policy| {
+            pretokenized
+                .get_splits_with_policy(OffsetReferential::Original, OffsetType::Byte, policy)
+                .into_iter()
+                .map(|(_, o, _)| o)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(offsets(OffsetRecoveryPolicy::Empty), vec![(0, 1), (3, 4)],);
+
+        // A token over a destroyed region maps to an empty range by default,
+        // and gets expanded to a neighboring character otherwise
+        let mut pretokenized = get_pretokenized();
+        pretokenized
+            .tokenize(|normalized| Ok(vec![Token::new(0, normalized.get().to_owned(), (0, 0))]))
+            .unwrap();
+        let encoding = pretokenized
+            .clone()
+            .into_encoding_with_policy(None, 0, OffsetType::Byte, OffsetRecoveryPolicy::Empty)
+            .unwrap();
+        assert_eq!(encoding.get_offsets(), &[(0, 0), (3, 3)]);
+        let encoding = pretokenized
+            .clone()
+            .into_encoding_with_policy(None, 0, OffsetType::Byte, OffsetRecoveryPolicy::ExpandLeft)
+            .unwrap();
+        assert_eq!(encoding.get_offsets(), &[(0, 0), (1, 3)]);
+        let encoding = pretokenized
+            .into_encoding_with_policy(None, 0, OffsetType::Byte, OffsetRecoveryPolicy::ExpandRight)
+            .unwrap();
+        assert_eq!(encoding.get_offsets(), &[(0, 1), (3, 4)]);
+    }
+
+    #[test]
+    fn allocated_bytes_accounts_for_splits() {
+        let input = "Hello there friend";
+        let mut pretokenized = PreTokenizedString::from(input);
+        // At least the `original` copy plus the initial split's two string versions
+        assert!(pretokenized.allocated_bytes() >= input.len() * 3);
+
+        pretokenized
+            .split(|_, normalized| normalized.split(' ', SplitDelimiterBehavior::Isolated))
+            .unwrap();
+        assert!(pretokenized.allocated_bytes() >= input.len() * 3);
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::marker::PhantomData;
 
 use serde::{
@@ -7,7 +8,10 @@ use serde::{
     Deserialize, Deserializer, Serialize, Serializer,
 };
 
-use super::{added_vocabulary::AddedTokenWithId, TokenizerImpl};
+use super::{
+    added_vocabulary::AddedTokenWithId, ExtractionOptions, SpecialTokenRole, TokenizerConstraints,
+    TokenizerImpl,
+};
 use crate::{Decoder, Model, Normalizer, PostProcessor, PreTokenizer, TokenizerBuilder};
 
 static SERIALIZATION_VERSION: &str = "1.0";
@@ -24,7 +28,7 @@ where
     where
         S: Serializer,
     {
-        let mut tokenizer = serializer.serialize_struct("Tokenizer", 9)?;
+        let mut tokenizer = serializer.serialize_struct("Tokenizer", 10)?;
 
         // Start by adding the current version
         tokenizer.serialize_field("version", SERIALIZATION_VERSION)?;
@@ -33,12 +37,46 @@ where
         tokenizer.serialize_field("truncation", &self.truncation)?;
         tokenizer.serialize_field("padding", &self.padding)?;
 
+        // Model constraints on the params, only when some are declared, to
+        // keep the serialization of older files byte-stable
+        if self.constraints.is_none() {
+            tokenizer.skip_field("constraints")?;
+        } else {
+            tokenizer.serialize_field("constraints", &self.constraints)?;
+        }
+
+        // Special token roles, only when some are declared, to keep the
+        // serialization of older files byte-stable
+        if self.special_tokens_map.is_empty() {
+            tokenizer.skip_field("special_tokens_map")?;
+        } else {
+            tokenizer.serialize_field("special_tokens_map", &self.special_tokens_map)?;
+        }
+
         // Added tokens
         tokenizer.serialize_field("added_tokens", &self.added_vocabulary)?;
 
+        // Added-token extraction options, only when they differ from the
+        // defaults, to keep the serialization of older files byte-stable
+        let extraction_options = self.added_vocabulary.get_extraction_options();
+        if extraction_options == ExtractionOptions::default() {
+            tokenizer.skip_field("extraction_options")?;
+        } else {
+            tokenizer.serialize_field("extraction_options", &extraction_options)?;
+        }
+
         // Then add our parts
         tokenizer.serialize_field("normalizer", &self.normalizer)?;
         tokenizer.serialize_field("pre_tokenizer", &self.pre_tokenizer)?;
+
+        // Pair pipeline, only when one is configured, to keep the
+        // serialization of older files byte-stable
+        if self.pair_pipeline.is_none() {
+            tokenizer.skip_field("pair_pipeline")?;
+        } else {
+            tokenizer.serialize_field("pair_pipeline", &self.pair_pipeline)?;
+        }
+
         tokenizer.serialize_field("post_processor", &self.post_processor)?;
         tokenizer.serialize_field("decoder", &self.decoder)?;
         tokenizer.serialize_field("model", &self.model)?;
@@ -65,9 +103,13 @@ where
                 "version",
                 "truncation",
                 "padding",
+                "constraints",
+                "special_tokens_map",
                 "added_tokens",
+                "extraction_options",
                 "normalizer",
                 "pre_tokenizer",
+                "pair_pipeline",
                 "post_processor",
                 "decoder",
                 "model",
@@ -111,6 +153,9 @@ where
     {
         let mut builder = TokenizerBuilder::new();
         let mut tokens: Vec<AddedTokenWithId> = vec![];
+        let mut special_tokens_map: BTreeMap<SpecialTokenRole, String> = BTreeMap::new();
+        let mut constraints: Option<TokenizerConstraints> = None;
+        let mut extraction_options: Option<ExtractionOptions> = None;
         while let Some(key) = map.next_key::<String>()? {
             match key.as_ref() {
                 "version" => {
@@ -125,15 +170,27 @@ where
                 "padding" => {
                     builder = builder.with_padding(map.next_value()?);
                 }
+                "constraints" => {
+                    constraints = map.next_value()?;
+                }
+                "special_tokens_map" => {
+                    special_tokens_map = map.next_value()?;
+                }
                 "added_tokens" => {
                     tokens = map.next_value()?;
                 }
+                "extraction_options" => {
+                    extraction_options = map.next_value()?;
+                }
                 "normalizer" => {
                     builder = builder.with_normalizer(map.next_value()?);
                 }
                 "pre_tokenizer" => {
                     builder = builder.with_pre_tokenizer(map.next_value()?);
                 }
+                "pair_pipeline" => {
+                    builder = builder.with_pair_pipeline(map.next_value()?);
+                }
                 "model" => {
                     builder = builder.with_model(map.next_value()?);
                 }
@@ -168,11 +225,71 @@ where
         }
         let added_tokens: Vec<_> = tokens.into_iter().map(|token| token.token).collect();
         tokenizer.add_tokens(&added_tokens[..]);
+        if let Some(extraction_options) = extraction_options {
+            tokenizer.set_extraction_options(extraction_options);
+        }
+        tokenizer.with_special_tokens_map(special_tokens_map);
+        // Fail on files whose own params already violate their constraints
+        tokenizer
+            .with_constraints(constraints)
+            .map_err(|e| V::Error::custom(e.to_string()))?;
 
         Ok(tokenizer)
     }
 }
 
+/// An invariant violated by a tokenizer file loaded in strict mode, see
+/// [`Tokenizer::from_file_strict`](crate::Tokenizer::from_file_strict)
+#[derive(thiserror::Error, Debug)]
+pub enum StrictValidationError {
+    #[error("unknown field `{0}`, it would be silently ignored")]
+    UnknownField(String),
+    #[error("a merge references token id {0}, which is not in the vocabulary")]
+    MergeUnknownId(u32),
+    #[error("the template post-processor references `{0}`, which is not among its special tokens")]
+    UndefinedTemplateToken(String),
+    #[error("special token `{0}` of the template post-processor has id {1}, but the vocabulary maps it to {2:?}")]
+    TemplateTokenMismatch(String, u32, Option<u32>),
+    #[error("padding token `{0}` is not in the vocabulary")]
+    PaddingTokenMissing(String),
+    #[error(
+        "padding token `{0}` has id {1} in the vocabulary, but padding is configured with id {2}"
+    )]
+    PaddingIdMismatch(String, u32, u32),
+}
+
+/// Collect the path of every field of `input` that does not appear in
+/// `output`, i.e. that a deserialization round-trip would drop
+pub(super) fn find_ignored_fields(
+    input: &serde_json::Value,
+    output: &serde_json::Value,
+    path: &str,
+    ignored: &mut Vec<String>,
+) {
+    use serde_json::Value;
+    match (input, output) {
+        (Value::Object(input), Value::Object(output)) => {
+            for (key, value) in input {
+                let sub_path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+                match output.get(key) {
+                    Some(out) => find_ignored_fields(value, out, &sub_path, ignored),
+                    None => ignored.push(sub_path),
+                }
+            }
+        }
+        (Value::Array(input), Value::Array(output)) if input.len() == output.len() => {
+            for (i, (value, out)) in input.iter().zip(output).enumerate() {
+                find_ignored_fields(value, out, &format!("{path}[{i}]"), ignored);
+            }
+        }
+        _ => {}
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::tokenizer::Tokenizer;
@@ -232,6 +349,195 @@ mod tests {
         assert_eq!(tok_str, tok_json);
     }
 
+    #[test]
+    fn test_special_tokens_map_roundtrip() {
+        use crate::tokenizer::{AddedToken, SpecialTokenInfo, SpecialTokenRole};
+
+        let tok_json = r#"{"model":{"type":"WordLevel","vocab":{"hello":0},"unk_token":"hello"}}"#;
+        let mut tokenizer = Tokenizer::from_str(tok_json).unwrap();
+
+        // An empty map is not serialized, to keep older files byte-stable
+        assert!(tokenizer.special_tokens_map().is_empty());
+        assert!(!serde_json::to_string(&tokenizer)
+            .unwrap()
+            .contains("special_tokens_map"));
+
+        tokenizer.add_special_tokens(&[
+            AddedToken::from("<s>", true),
+            AddedToken::from("</s>", true),
+        ]);
+        tokenizer.with_special_token(SpecialTokenRole::Bos, Some("<s>"));
+        tokenizer.with_special_token(SpecialTokenRole::Eos, Some("</s>"));
+        tokenizer.with_special_token(SpecialTokenRole::Pad, Some("[PAD]"));
+
+        // Ids are resolved against the current vocabulary: `[PAD]` was
+        // declared but never added, so it has none
+        assert_eq!(
+            tokenizer.special_tokens_map(),
+            vec![
+                SpecialTokenInfo {
+                    role: SpecialTokenRole::Bos,
+                    content: "<s>".into(),
+                    id: Some(1),
+                },
+                SpecialTokenInfo {
+                    role: SpecialTokenRole::Eos,
+                    content: "</s>".into(),
+                    id: Some(2),
+                },
+                SpecialTokenInfo {
+                    role: SpecialTokenRole::Pad,
+                    content: "[PAD]".into(),
+                    id: None,
+                },
+            ]
+        );
+
+        // The roles survive a serialization round-trip, with the
+        // `transformers` key names
+        let serialized = serde_json::to_string(&tokenizer).unwrap();
+        assert!(serialized.contains(
+            r#""special_tokens_map":{"bos_token":"<s>","eos_token":"</s>","pad_token":"[PAD]"}"#
+        ));
+        let reloaded = Tokenizer::from_str(&serialized).unwrap();
+        assert_eq!(
+            reloaded.special_tokens_map(),
+            tokenizer.special_tokens_map()
+        );
+
+        // A role can be cleared
+        tokenizer.with_special_token(SpecialTokenRole::Pad, None::<String>);
+        assert!(tokenizer.special_token(SpecialTokenRole::Pad).is_none());
+        assert_eq!(
+            tokenizer.special_token(SpecialTokenRole::Bos).unwrap().id,
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_constraints() {
+        use crate::tokenizer::TokenizerConstraints;
+        use crate::utils::padding::{PaddingDirection, PaddingParams};
+        use crate::utils::truncation::{TruncationDirection, TruncationParams};
+
+        let tok_json = r#"{"model":{"type":"WordLevel","vocab":{"hello":0},"unk_token":"hello"}}"#;
+        let mut tokenizer = Tokenizer::from_str(tok_json).unwrap();
+
+        // No constraints: nothing is serialized, to keep older files byte-stable
+        assert!(!serde_json::to_string(&tokenizer)
+            .unwrap()
+            .contains("constraints"));
+
+        tokenizer
+            .with_constraints(Some(TokenizerConstraints {
+                padding_direction: Some(PaddingDirection::Left),
+                truncation_direction: None,
+            }))
+            .unwrap();
+        let serialized = serde_json::to_string(&tokenizer).unwrap();
+        assert!(serialized.contains(r#""constraints":{"padding_direction":"Left"}"#));
+
+        // The constraints survive a round-trip
+        let reloaded = Tokenizer::from_str(&serialized).unwrap();
+        assert_eq!(reloaded.get_constraints(), tokenizer.get_constraints());
+
+        // Setting a violating truncation direction is an error
+        tokenizer
+            .with_constraints(Some(TokenizerConstraints {
+                padding_direction: Some(PaddingDirection::Left),
+                truncation_direction: Some(TruncationDirection::Left),
+            }))
+            .unwrap();
+        let err = tokenizer
+            .with_truncation(Some(TruncationParams {
+                direction: TruncationDirection::Right,
+                ..TruncationParams::default()
+            }))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "This tokenizer requires left truncation, but right truncation was set"
+        );
+
+        // A violating padding direction only warns, but declaring constraints
+        // that the current parameters violate is an error
+        tokenizer.with_padding(Some(PaddingParams {
+            direction: PaddingDirection::Right,
+            ..PaddingParams::default()
+        }));
+        let err = tokenizer
+            .with_constraints(Some(TokenizerConstraints {
+                padding_direction: Some(PaddingDirection::Left),
+                truncation_direction: None,
+            }))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "This tokenizer requires left padding, but right padding was set"
+        );
+
+        // A file whose own params violate its constraints does not load
+        let serialized = serde_json::to_string(&tokenizer).unwrap();
+        let violating = serialized.replace(
+            r#""padding""#,
+            r#""constraints":{"padding_direction":"Left"},"padding""#,
+        );
+        assert!(Tokenizer::from_str(&violating)
+            .unwrap_err()
+            .to_string()
+            .contains("This tokenizer requires left padding, but right padding was set"));
+    }
+
+    #[test]
+    fn test_strict_deserialization() {
+        let tok_json =
+            r#"{"model":{"type":"WordLevel","vocab":{"the":0,"[PAD]":1},"unk_token":"the"}}"#;
+        assert!(Tokenizer::from_bytes_strict(tok_json).is_ok());
+
+        // Unknown fields fail instead of being silently ignored
+        let tok_json = r#"{"model":{"type":"WordLevel","vocab":{"the":0},"unk_token":"the","unknown_option":true}}"#;
+        let err = Tokenizer::from_bytes_strict(tok_json).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown field `model.unknown_option`, it would be silently ignored"
+        );
+
+        // The padding token must be part of the vocabulary, with the right id
+        let tok_json = r#"{
+            "padding": {"strategy": "BatchLongest", "direction": "Right", "pad_to_multiple_of": null,
+                        "pad_id": 3, "pad_type_id": 0, "pad_token": "[PAD]"},
+            "model": {"type": "WordLevel", "vocab": {"the": 0}, "unk_token": "the"}
+        }"#;
+        let err = Tokenizer::from_bytes_strict(tok_json).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "padding token `[PAD]` is not in the vocabulary"
+        );
+        let tok_json = tok_json.replace(
+            r#""vocab": {"the": 0}"#,
+            r#""vocab": {"the": 0, "[PAD]": 1}"#,
+        );
+        let err = Tokenizer::from_bytes_strict(tok_json).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "padding token `[PAD]` has id 1 in the vocabulary, but padding is configured with id 3"
+        );
+
+        // The template post-processor special tokens must match the vocabulary
+        let tok_json = r#"{
+            "post_processor": {"type": "TemplateProcessing",
+                "single": [{"SpecialToken": {"id": "[CLS]", "type_id": 0}}, {"Sequence": {"id": "A", "type_id": 0}}],
+                "pair": [{"Sequence": {"id": "A", "type_id": 0}}, {"Sequence": {"id": "B", "type_id": 1}}],
+                "special_tokens": {"[CLS]": {"id": "[CLS]", "ids": [5], "tokens": ["[CLS]"]}}},
+            "model": {"type": "WordLevel", "vocab": {"the": 0}, "unk_token": "the"}
+        }"#;
+        let err = Tokenizer::from_bytes_strict(tok_json).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "special token `[CLS]` of the template post-processor has id 5, but the vocabulary maps it to None"
+        );
+    }
+
     #[cfg(feature = "http")]
     #[test]
     fn test_from_pretrained() {
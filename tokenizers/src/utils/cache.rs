@@ -1,7 +1,8 @@
 use std::borrow::Borrow;
 use std::collections::HashMap;
-use std::hash::Hash;
-use std::sync::RwLock;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 
 /// The default capacity for a `BPE`'s internal cache.
 pub static DEFAULT_CACHE_CAPACITY: usize = 10_000;
@@ -116,3 +117,260 @@ where
         self.set_values(std::iter::once((key, value)))
     }
 }
+
+/// A bounded thread-safe cache with approximate least-recently-used eviction.
+/// Unlike [`Cache`], which stops accepting new entries once full, entries are
+/// kept in two generations holding half the capacity each: insertions go to
+/// the young generation, a hit in the old one promotes the entry back, and
+/// when the young generation fills up it replaces the old one, dropping the
+/// entries that were not used recently. Clones share their storage, so a
+/// cloned tokenizer keeps benefiting from an already warm cache. Like
+/// [`Cache`], it never blocks: under contention, gets simply miss and sets are
+/// dropped.
+#[derive(Debug, Clone)]
+pub(crate) struct LruCache<K, V> {
+    generations: Arc<Mutex<Generations<K, V>>>,
+    pub capacity: usize,
+}
+
+#[derive(Debug)]
+struct Generations<K, V> {
+    young: HashMap<K, V>,
+    old: HashMap<K, V>,
+}
+
+impl<K, V> LruCache<K, V>
+where
+    K: Eq + Hash,
+    V: Clone,
+{
+    /// Create a new `LruCache` holding at most `capacity` entries.
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            generations: Arc::new(Mutex::new(Generations {
+                young: HashMap::new(),
+                old: HashMap::new(),
+            })),
+            capacity,
+        }
+    }
+
+    /// Create a fresh, empty `LruCache` with the same capacity, not shared
+    /// with the current one.
+    pub(crate) fn fresh(&self) -> Self {
+        Self::new(self.capacity)
+    }
+
+    /// Half the capacity, so that both generations together stay within it
+    fn generation_capacity(&self) -> usize {
+        (self.capacity / 2).max(1)
+    }
+
+    pub(crate) fn get(&self, key: &K) -> Option<V> {
+        let mut generations = self.generations.try_lock().ok()?;
+        if let Some(value) = generations.young.get(key) {
+            return Some(value.clone());
+        }
+        let (key, value) = generations.old.remove_entry(key)?;
+        let result = value.clone();
+        Self::insert(&mut generations, self.generation_capacity(), key, value);
+        Some(result)
+    }
+
+    pub(crate) fn set(&self, key: K, value: V) {
+        if let Ok(mut generations) = self.generations.try_lock() {
+            Self::insert(&mut generations, self.generation_capacity(), key, value);
+        }
+    }
+
+    fn insert(generations: &mut Generations<K, V>, capacity: usize, key: K, value: V) {
+        if generations.young.len() >= capacity && !generations.young.contains_key(&key) {
+            generations.old = std::mem::take(&mut generations.young);
+        }
+        generations.young.insert(key, value);
+    }
+}
+
+/// The lookup statistics of a [`SharedCache`], to monitor its effectiveness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheStats {
+    /// The number of lookups served from the cache
+    pub hits: u64,
+    /// The number of lookups that missed
+    pub misses: u64,
+}
+
+impl CacheStats {
+    /// The fraction of lookups served from the cache, or `0.0` before any
+    /// lookup
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+/// The number of shards of a [`SharedCache`]
+const SHARED_CACHE_SHARDS: usize = 16;
+
+/// A bounded cache meant to be shared: clones share their storage, so cloning
+/// a model for another thread keeps benefiting from (and contributing to) an
+/// already warm cache. The storage is split into shards to limit contention,
+/// each one using the two-generation approximate least-recently-used eviction
+/// of [`LruCache`], and lookups are counted so the hit rate can be monitored
+/// with [`SharedCache::stats`]. Like the other caches, it never blocks: under
+/// contention, gets simply miss and sets are dropped.
+#[derive(Debug, Clone)]
+pub(crate) struct SharedCache<K, V> {
+    shards: Arc<Shards<K, V>>,
+    pub capacity: usize,
+}
+
+#[derive(Debug)]
+struct Shards<K, V> {
+    maps: Vec<Mutex<Generations<K, V>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+// We dont really care about SharedCache comparison, so let's make them always equal
+impl<K, V> PartialEq for SharedCache<K, V> {
+    fn eq(&self, _other: &SharedCache<K, V>) -> bool {
+        true
+    }
+}
+
+impl<K, V> SharedCache<K, V>
+where
+    K: Eq + Hash,
+    V: Clone,
+{
+    /// Create a new `SharedCache` holding at most `capacity` entries.
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            shards: Arc::new(Shards {
+                maps: (0..SHARED_CACHE_SHARDS)
+                    .map(|_| {
+                        Mutex::new(Generations {
+                            young: HashMap::new(),
+                            old: HashMap::new(),
+                        })
+                    })
+                    .collect(),
+                hits: AtomicU64::new(0),
+                misses: AtomicU64::new(0),
+            }),
+            capacity,
+        }
+    }
+
+    /// The shard holding the given key
+    fn shard<Q>(&self, key: &Q) -> &Mutex<Generations<K, V>>
+    where
+        Q: Hash + ?Sized,
+    {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        key.hash(&mut hasher);
+        &self.shards.maps[hasher.finish() as usize % SHARED_CACHE_SHARDS]
+    }
+
+    /// Half the per-shard capacity, so that both generations of every shard
+    /// together stay within the total capacity
+    fn generation_capacity(&self) -> usize {
+        (self.capacity / (2 * SHARED_CACHE_SHARDS)).max(1)
+    }
+
+    pub(crate) fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let result = (|| {
+            let mut generations = self.shard(key).try_lock().ok()?;
+            if let Some(value) = generations.young.get(key) {
+                return Some(value.clone());
+            }
+            let (key, value) = generations.old.remove_entry(key)?;
+            let result = value.clone();
+            LruCache::insert(&mut generations, self.generation_capacity(), key, value);
+            Some(result)
+        })();
+        let counter = match result {
+            Some(_) => &self.shards.hits,
+            None => &self.shards.misses,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        result
+    }
+
+    pub(crate) fn set(&self, key: K, value: V) {
+        if let Ok(mut generations) = self.shard(&key).try_lock() {
+            LruCache::insert(&mut generations, self.generation_capacity(), key, value);
+        }
+    }
+
+    /// Clear the cache, in every clone sharing it. The lookup counters are
+    /// left untouched.
+    pub(crate) fn clear(&self) {
+        for shard in &self.shards.maps {
+            let mut generations = shard.lock().unwrap();
+            generations.young.clear();
+            generations.old.clear();
+        }
+    }
+
+    /// A snapshot of the lookup counters, aggregated over every clone sharing
+    /// this cache
+    pub(crate) fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.shards.hits.load(Ordering::Relaxed),
+            misses: self.shards.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lru_cache_eviction() {
+        let cache: LruCache<u32, u32> = LruCache::new(4);
+        cache.set(0, 0);
+        cache.set(1, 1);
+        // The young generation is full: this insertion rotates it out
+        cache.set(2, 2);
+        // Entries of the old generation are still reachable, and a hit
+        // promotes them back to the young one
+        assert_eq!(cache.get(&0), Some(0));
+
+        // Two more rotations: 2 and 0 move to the old generation, then get
+        // dropped, while the freshly inserted entries survive
+        cache.set(3, 3);
+        cache.set(4, 4);
+        cache.set(5, 5);
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.get(&0), None);
+        assert_eq!(cache.get(&3), Some(3));
+        assert_eq!(cache.get(&4), Some(4));
+
+        // Clones share their storage
+        assert_eq!(cache.clone().get(&5), Some(5));
+    }
+
+    #[test]
+    fn shared_cache_stats() {
+        let cache: SharedCache<u32, u32> = SharedCache::new(64);
+        assert_eq!(cache.get(&0), None);
+        cache.set(0, 0);
+        // Clones share both the storage and the counters
+        assert_eq!(cache.clone().get(&0), Some(0));
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+        assert_eq!(stats.hit_rate(), 0.5);
+
+        cache.clear();
+        assert_eq!(cache.get(&0), None);
+    }
+}
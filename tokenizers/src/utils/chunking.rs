@@ -0,0 +1,265 @@
+//! Split a long document into overlapping chunks that snap to sentence or
+//! word boundaries, instead of cutting at arbitrary token positions.
+
+use crate::tokenizer::{Encoding, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Where chunk boundaries are allowed to fall.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Eq, Default)]
+pub enum ChunkBoundary {
+    /// Cut before a token that starts a new sentence. Falls back to the
+    /// closest word boundary, then to a plain token cut, when no sentence
+    /// boundary fits in the chunk.
+    Sentence,
+    /// Cut before a token that starts a new word, falling back to a plain
+    /// token cut when a single word exceeds `max_tokens`.
+    #[default]
+    Word,
+    /// Cut at any token position, like stride-based overflowing
+    Token,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkOptions {
+    /// The maximum number of tokens per chunk
+    pub max_tokens: usize,
+    /// The number of tokens shared between two consecutive chunks
+    #[serde(default)]
+    pub overlap: usize,
+    #[serde(default)]
+    pub boundary: ChunkBoundary,
+}
+
+impl Default for ChunkOptions {
+    fn default() -> Self {
+        Self {
+            max_tokens: 512,
+            overlap: 0,
+            boundary: ChunkBoundary::default(),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ChunkingError {
+    #[error("Chunking error: max_tokens must be greater than 0")]
+    ZeroMaxTokens,
+    #[error("Chunking error: overlap must be smaller than max_tokens")]
+    OverlapTooLarge,
+}
+
+/// Whether a cut right before token `i` falls on a word boundary
+fn is_word_boundary(encoding: &Encoding, i: usize) -> bool {
+    let words = encoding.get_word_ids();
+    words[i].is_none() || words[i] != words[i - 1]
+}
+
+/// Whether a cut right before token `i` falls on a sentence boundary: the text
+/// between the two surrounding tokens ends a sentence
+fn is_sentence_boundary(encoding: &Encoding, text: &str, i: usize) -> bool {
+    if !is_word_boundary(encoding, i) {
+        return false;
+    }
+    let before = &text[..encoding.get_offsets()[i].0];
+    before.ends_with('\n') || before.trim_end().ends_with(['.', '!', '?', '…'])
+}
+
+/// Extract the tokens in `range` as a standalone `Encoding`, keeping the
+/// original offsets
+fn slice_encoding(encoding: &Encoding, range: std::ops::Range<usize>) -> Encoding {
+    Encoding::new(
+        encoding.get_ids()[range.clone()].to_vec(),
+        encoding.get_type_ids()[range.clone()].to_vec(),
+        encoding.get_tokens()[range.clone()].to_vec(),
+        encoding.get_word_ids()[range.clone()].to_vec(),
+        encoding.get_offsets()[range.clone()].to_vec(),
+        encoding.get_special_tokens_mask()[range.clone()].to_vec(),
+        encoding.get_attention_mask()[range].to_vec(),
+        vec![],
+        HashMap::new(),
+    )
+}
+
+/// Split `encoding` into chunks of at most `max_tokens` tokens, preferring
+/// cuts that fall on the requested boundary. `text` must be the original
+/// document the encoding was produced from, as its content is used to detect
+/// sentence boundaries.
+pub fn chunk_encoding(
+    encoding: &Encoding,
+    text: &str,
+    options: &ChunkOptions,
+) -> Result<Vec<Encoding>> {
+    if options.max_tokens == 0 {
+        return Err(Box::new(ChunkingError::ZeroMaxTokens));
+    }
+    if options.overlap >= options.max_tokens {
+        return Err(Box::new(ChunkingError::OverlapTooLarge));
+    }
+
+    let n = encoding.len();
+    let mut chunks = vec![];
+    let mut start = 0;
+    while start < n {
+        let hard_end = std::cmp::min(start + options.max_tokens, n);
+        let mut end = hard_end;
+        if end < n {
+            end = match options.boundary {
+                ChunkBoundary::Token => hard_end,
+                ChunkBoundary::Word => (start + 1..=hard_end)
+                    .rev()
+                    .find(|&i| is_word_boundary(encoding, i))
+                    .unwrap_or(hard_end),
+                ChunkBoundary::Sentence => (start + 1..=hard_end)
+                    .rev()
+                    .find(|&i| is_sentence_boundary(encoding, text, i))
+                    .or_else(|| {
+                        (start + 1..=hard_end)
+                            .rev()
+                            .find(|&i| is_word_boundary(encoding, i))
+                    })
+                    .unwrap_or(hard_end),
+            };
+        }
+        chunks.push(slice_encoding(encoding, start..end));
+        if end == n {
+            break;
+        }
+        start = std::cmp::max(end.saturating_sub(options.overlap), start + 1);
+    }
+    Ok(chunks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::Token;
+
+    fn encoding(text: &str) -> Encoding {
+        // One token per whitespace-separated word
+        let mut tokens = vec![];
+        let mut word_start = None;
+        for (i, c) in text
+            .char_indices()
+            .chain(std::iter::once((text.len(), ' ')))
+        {
+            match (word_start, c.is_whitespace()) {
+                (Some(start), true) => {
+                    tokens.push(Token::new(
+                        tokens.len() as u32,
+                        text[start..i].to_string(),
+                        (start, i),
+                    ));
+                    word_start = None;
+                }
+                (None, false) => word_start = Some(i),
+                _ => {}
+            }
+        }
+        let mut encoding = Encoding::from_tokens(tokens, 0);
+        let word_ids: Vec<_> = (0..encoding.len() as u32).map(Some).collect();
+        encoding.get_word_ids_mut().clone_from_slice(&word_ids);
+        encoding
+    }
+
+    #[test]
+    fn chunk_at_word_boundaries() {
+        let text = "one two three four five";
+        let chunks = chunk_encoding(
+            &encoding(text),
+            text,
+            &ChunkOptions {
+                max_tokens: 2,
+                overlap: 0,
+                boundary: ChunkBoundary::Word,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            chunks
+                .iter()
+                .map(|c| c.get_tokens().to_vec())
+                .collect::<Vec<_>>(),
+            vec![vec!["one", "two"], vec!["three", "four"], vec!["five"]]
+        );
+        // Offsets refer to the original document
+        assert_eq!(chunks[1].get_offsets()[0], (8, 13));
+    }
+
+    #[test]
+    fn chunk_at_sentence_boundaries() {
+        let text = "I came. I saw nothing at all. I left";
+        let chunks = chunk_encoding(
+            &encoding(text),
+            text,
+            &ChunkOptions {
+                max_tokens: 6,
+                overlap: 0,
+                boundary: ChunkBoundary::Sentence,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            chunks
+                .iter()
+                .map(|c| c.get_tokens().to_vec())
+                .collect::<Vec<_>>(),
+            vec![
+                vec!["I", "came."],
+                vec!["I", "saw", "nothing", "at", "all."],
+                vec!["I", "left"],
+            ]
+        );
+    }
+
+    #[test]
+    fn chunk_with_overlap() {
+        let text = "one two three four";
+        let chunks = chunk_encoding(
+            &encoding(text),
+            text,
+            &ChunkOptions {
+                max_tokens: 2,
+                overlap: 1,
+                boundary: ChunkBoundary::Token,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            chunks
+                .iter()
+                .map(|c| c.get_tokens().to_vec())
+                .collect::<Vec<_>>(),
+            vec![
+                vec!["one", "two"],
+                vec!["two", "three"],
+                vec!["three", "four"],
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_options() {
+        let text = "one two";
+        let encoding = encoding(text);
+        assert!(chunk_encoding(
+            &encoding,
+            text,
+            &ChunkOptions {
+                max_tokens: 0,
+                ..Default::default()
+            }
+        )
+        .is_err());
+        assert!(chunk_encoding(
+            &encoding,
+            text,
+            &ChunkOptions {
+                max_tokens: 2,
+                overlap: 2,
+                boundary: ChunkBoundary::Token,
+            }
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,65 @@
+//! Corpus reader utilities: stream training text out of compressed files and
+//! line-delimited JSON, without materializing the decompressed corpus.
+
+use std::fs::File;
+use std::io::{Error, ErrorKind, Read, Result};
+use std::path::Path;
+
+/// Open the given file for reading, decompressing it in streaming fashion when
+/// its extension is `.gz` or `.zst`. Any other extension is read as plain text.
+pub fn open_corpus_file(path: &str) -> Result<Box<dyn Read + Send>> {
+    let file = File::open(path)?;
+    Ok(
+        match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Box::new(flate2::read::MultiGzDecoder::new(file)),
+            Some("zst") => Box::new(zstd::stream::read::Decoder::new(file)?),
+            _ => Box::new(file),
+        },
+    )
+}
+
+/// Extract the given string field from a line-delimited JSON record
+pub fn extract_json_field(line: &str, field: &str) -> Result<String> {
+    let record: serde_json::Value = serde_json::from_str(line)
+        .map_err(|e| Error::new(ErrorKind::InvalidData, format!("Invalid JSON line: {e}")))?;
+    record
+        .get(field)
+        .and_then(|value| value.as_str())
+        .map(|value| value.to_owned())
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Missing or non-string field {field:?} in JSON line"),
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_extract_json_field() {
+        let line = r#"{"id": 0, "text": "Hey friend!"}"#;
+        assert_eq!(extract_json_field(line, "text").unwrap(), "Hey friend!");
+        assert!(extract_json_field(line, "id").is_err());
+        assert!(extract_json_field(line, "missing").is_err());
+        assert!(extract_json_field("not json", "text").is_err());
+    }
+
+    #[test]
+    fn test_open_gz_corpus_file() {
+        let mut file = tempfile::Builder::new().suffix(".gz").tempfile().unwrap();
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(b"Hey friend!\nHow are you?\n").unwrap();
+        file.write_all(&encoder.finish().unwrap()).unwrap();
+
+        let mut content = String::new();
+        open_corpus_file(file.path().to_str().unwrap())
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "Hey friend!\nHow are you?\n");
+    }
+}
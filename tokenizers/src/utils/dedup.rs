@@ -0,0 +1,184 @@
+//! Duplicate removal for training corpora. Duplicated web documents skew the
+//! frequencies the trainers count, so that boilerplate gets merged or kept in
+//! the vocabulary ahead of genuinely common words.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+
+/// Statistics about the duplicates a [`Deduplicator`] removed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DedupStats {
+    /// The number of sequences seen
+    pub total: u64,
+    /// The number of sequences kept
+    pub kept: u64,
+    /// The number of sequences dropped as byte-for-byte duplicates
+    pub exact: u64,
+    /// The number of sequences dropped as near-duplicates, when MinHash is
+    /// enabled
+    pub near: u64,
+}
+
+/// Drops the sequences of a training feed that duplicate an earlier one.
+///
+/// Exact duplicates are always detected, from a hash of the whole sequence.
+/// With the `minhash` feature, [`Deduplicator::with_minhash`] additionally
+/// drops near-duplicates: sequences whose MinHash signature over word
+/// shingles collides with an earlier sequence on at least one band, the
+/// usual locality-sensitive-hashing approximation of a Jaccard similarity
+/// threshold.
+#[derive(Debug, Default)]
+pub struct Deduplicator {
+    seen: HashSet<u64>,
+    #[cfg(feature = "minhash")]
+    minhash: Option<MinHash>,
+    stats: DedupStats,
+}
+
+impl Deduplicator {
+    /// An exact deduplicator: only byte-for-byte repeated sequences are
+    /// dropped
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also drop near-duplicates, from MinHash signatures of `bands * rows`
+    /// hashes over word `shingle`-grams, compared band by band: the more
+    /// bands, the lower the similarity needed to count as a duplicate; the
+    /// more rows per band, the sharper the threshold. `MinHash(16, 4, 3)`
+    /// is a reasonable starting point for web documents.
+    #[cfg(feature = "minhash")]
+    #[must_use]
+    pub fn with_minhash(mut self, bands: usize, rows: usize, shingle: usize) -> Self {
+        self.minhash = Some(MinHash {
+            bands,
+            rows,
+            shingle,
+            seen_bands: HashSet::new(),
+        });
+        self
+    }
+
+    /// Whether the given sequence duplicates an earlier one, updating the
+    /// statistics along the way
+    pub fn is_duplicate(&mut self, sequence: &str) -> bool {
+        self.stats.total += 1;
+        let mut hasher = DefaultHasher::new();
+        sequence.hash(&mut hasher);
+        if !self.seen.insert(hasher.finish()) {
+            self.stats.exact += 1;
+            return true;
+        }
+        #[cfg(feature = "minhash")]
+        if let Some(minhash) = &mut self.minhash {
+            if minhash.is_near_duplicate(sequence) {
+                self.stats.near += 1;
+                return true;
+            }
+        }
+        self.stats.kept += 1;
+        false
+    }
+
+    /// The given sequences without the ones duplicating an earlier sequence,
+    /// including the ones this deduplicator saw before this call
+    pub fn unique<'a, I, S>(&'a mut self, sequences: I) -> impl Iterator<Item = S> + 'a
+    where
+        I: Iterator<Item = S> + 'a,
+        S: AsRef<str>,
+    {
+        sequences.filter(move |sequence| !self.is_duplicate(sequence.as_ref()))
+    }
+
+    /// The statistics accumulated so far
+    pub fn stats(&self) -> &DedupStats {
+        &self.stats
+    }
+
+    pub fn into_stats(self) -> DedupStats {
+        self.stats
+    }
+}
+
+#[cfg(feature = "minhash")]
+#[derive(Debug)]
+struct MinHash {
+    bands: usize,
+    rows: usize,
+    shingle: usize,
+    /// The band hashes of every sequence seen so far
+    seen_bands: HashSet<(usize, u64)>,
+}
+
+#[cfg(feature = "minhash")]
+impl MinHash {
+    fn is_near_duplicate(&mut self, sequence: &str) -> bool {
+        let signature = self.signature(sequence);
+        let mut duplicate = false;
+        for (band, rows) in signature.chunks(self.rows).enumerate() {
+            let mut hasher = DefaultHasher::new();
+            rows.hash(&mut hasher);
+            if !self.seen_bands.insert((band, hasher.finish())) {
+                duplicate = true;
+            }
+        }
+        duplicate
+    }
+
+    /// The minimum of each of the `bands * rows` seeded hash functions over
+    /// the word shingles of the sequence
+    fn signature(&self, sequence: &str) -> Vec<u64> {
+        let mut signature = vec![u64::MAX; self.bands * self.rows];
+        let words: Vec<&str> = sequence.split_whitespace().collect();
+        if words.is_empty() {
+            return signature;
+        }
+        for shingle in words.windows(self.shingle.min(words.len())) {
+            for (seed, slot) in signature.iter_mut().enumerate() {
+                let mut hasher = DefaultHasher::new();
+                seed.hash(&mut hasher);
+                shingle.hash(&mut hasher);
+                *slot = (*slot).min(hasher.finish());
+            }
+        }
+        signature
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_duplicates() {
+        let mut dedup = Deduplicator::new();
+        let sequences = ["hello world", "bye", "hello world", "bye", "again"];
+        let kept: Vec<_> = dedup.unique(sequences.iter()).collect();
+        assert_eq!(kept, [&"hello world", &"bye", &"again"]);
+        assert_eq!(
+            *dedup.stats(),
+            DedupStats {
+                total: 5,
+                kept: 3,
+                exact: 2,
+                near: 0,
+            }
+        );
+    }
+
+    #[cfg(feature = "minhash")]
+    #[test]
+    fn near_duplicates() {
+        let mut dedup = Deduplicator::new().with_minhash(32, 2, 3);
+        let document = "the quick brown fox jumps over the lazy dog again and again";
+        assert!(!dedup.is_duplicate(document));
+        // One changed word out of twelve still collides on some band
+        let near = "the quick brown fox jumps over the lazy cat again and again";
+        assert!(dedup.is_duplicate(near));
+        // While an unrelated document does not
+        assert!(!dedup.is_duplicate("completely different content here"));
+        assert_eq!(dedup.stats().near, 1);
+        assert_eq!(dedup.stats().exact, 0);
+    }
+}
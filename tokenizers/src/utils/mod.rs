@@ -2,20 +2,31 @@ pub(crate) mod cache;
 #[cfg(feature = "http")]
 pub(crate) mod from_pretrained;
 
-#[cfg(feature = "unstable_wasm")]
-mod fancy;
+#[cfg(any(feature = "unstable_wasm", feature = "fancy-regex"))]
+pub(crate) mod fancy;
 #[cfg(feature = "unstable_wasm")]
 pub use fancy::SysRegex;
 #[cfg(not(feature = "unstable_wasm"))]
 mod onig;
 #[cfg(not(feature = "unstable_wasm"))]
 pub use crate::utils::onig::SysRegex;
+pub(crate) mod rust;
 
+pub mod chunking;
+#[cfg(feature = "compression")]
+pub mod corpus;
+pub mod dedup;
 pub mod iter;
+pub mod packing;
 pub mod padding;
 pub mod parallelism;
+pub mod profiling;
 pub(crate) mod progress;
+pub(crate) mod sketch;
+pub(crate) mod spill;
+pub mod training_report;
 pub mod truncation;
+pub mod validation;
 
 use serde::{Serialize, Serializer};
 use std::collections::{BTreeMap, HashMap};
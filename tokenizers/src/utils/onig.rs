@@ -1,11 +1,19 @@
 use crate::tokenizer::pattern::Pattern;
+use crate::utils::cache::Cache;
 use crate::{Offsets, Result};
 use onig::Regex;
 use std::error::Error;
+use std::sync::Arc;
 
-#[derive(Debug)]
+lazy_static! {
+    /// Compiled regexes interned by pattern, so that the many tokenizer instances of a
+    /// multi-tenant server share a single compiled automaton per pattern
+    static ref REGEX_CACHE: Cache<String, Arc<Regex>> = Cache::default();
+}
+
+#[derive(Debug, Clone)]
 pub struct SysRegex {
-    regex: Regex,
+    regex: Arc<Regex>,
 }
 
 impl SysRegex {
@@ -16,9 +24,12 @@ impl SysRegex {
     pub fn new(
         regex_str: &str,
     ) -> std::result::Result<Self, Box<dyn Error + Send + Sync + 'static>> {
-        Ok(Self {
-            regex: Regex::new(regex_str)?,
-        })
+        if let Some(regex) = REGEX_CACHE.get(regex_str) {
+            return Ok(Self { regex });
+        }
+        let regex = Arc::new(Regex::new(regex_str)?);
+        REGEX_CACHE.set(regex_str.to_owned(), regex.clone());
+        Ok(Self { regex })
     }
 }
 
@@ -43,3 +54,18 @@ impl Pattern for &Regex {
         Ok(splits)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regexes_are_interned() {
+        let first = SysRegex::new(r"\w+|[^\w\s]+").unwrap();
+        let second = SysRegex::new(r"\w+|[^\w\s]+").unwrap();
+        assert!(Arc::ptr_eq(&first.regex, &second.regex));
+
+        let other = SysRegex::new(r"\s+").unwrap();
+        assert!(!Arc::ptr_eq(&first.regex, &other.regex));
+    }
+}
@@ -0,0 +1,37 @@
+//! Pack tokenized documents into fixed-length sequences for pretraining,
+//! keeping track of which document each position comes from.
+
+use serde::{Deserialize, Serialize};
+
+/// What gets inserted at document boundaries when packing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq, Default)]
+pub enum SepPolicy {
+    /// Documents are concatenated directly, without any boundary token
+    #[default]
+    None,
+    /// The given token is inserted between two consecutive documents
+    Between(String),
+    /// The given token is appended after every document, including the last
+    Suffix(String),
+}
+
+/// A fixed-length sequence of packed documents
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq)]
+pub struct PackedSequence {
+    /// The packed token ids
+    pub ids: Vec<u32>,
+    /// For every position, the index of the document it comes from, counted
+    /// from the start of this sequence. Block-diagonal attention masks can be
+    /// built by masking every pair of positions with different indices.
+    /// Separator tokens carry the index of the document they close, and a
+    /// document continuing from the previous sequence restarts at index 0
+    pub segment_ids: Vec<u32>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum PackingError {
+    #[error("Packing error: seq_len must be greater than 0")]
+    ZeroSeqLen,
+    #[error("Packing error: separator token `{0}` is not part of the vocabulary")]
+    SeparatorNotInVocab(String),
+}
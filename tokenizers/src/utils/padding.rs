@@ -3,7 +3,7 @@ use crate::tokenizer::{Encoding, Result};
 use serde::{Deserialize, Serialize};
 
 /// The various possible padding directions.
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PaddingDirection {
     Left,
     Right,
@@ -45,27 +45,74 @@ impl Default for PaddingParams {
 pub enum PaddingStrategy {
     BatchLongest,
     Fixed(usize),
+    /// Pad each sequence to the smallest bucket that can hold it. Sequences
+    /// longer than the largest bucket are left unpadded.
+    BucketedToLongest {
+        buckets: Vec<usize>,
+    },
+}
+
+/// The smallest of `buckets` that can hold a sequence of `len` tokens
+pub fn smallest_bucket(buckets: &[usize], len: usize) -> Option<usize> {
+    buckets
+        .iter()
+        .copied()
+        .filter(|&bucket| bucket >= len)
+        .min()
+}
+
+fn round_to_multiple(mut pad_length: usize, pad_to_multiple_of: Option<usize>) -> usize {
+    if let Some(multiple) = pad_to_multiple_of {
+        if multiple > 0 && !pad_length.is_multiple_of(multiple) {
+            pad_length += multiple - pad_length % multiple;
+        }
+    }
+    pad_length
 }
 
 pub fn pad_encodings(encodings: &mut [Encoding], params: &PaddingParams) -> Result<()> {
+    pad_encodings_with_direction(encodings, params, params.direction)
+}
+
+/// Pad the encodings like [`pad_encodings`], but override the direction configured in
+/// `params` for this call only.
+pub fn pad_encodings_with_direction(
+    encodings: &mut [Encoding],
+    params: &PaddingParams,
+    direction: PaddingDirection,
+) -> Result<()> {
     if encodings.is_empty() {
         return Ok(());
     }
 
-    let mut pad_length = match params.strategy {
-        PaddingStrategy::Fixed(size) => size,
+    let mut pad_length = match &params.strategy {
+        PaddingStrategy::Fixed(size) => *size,
         PaddingStrategy::BatchLongest => encodings
             .maybe_par_iter()
             .map(|e| e.get_ids().len())
             .max()
             .unwrap(),
+        PaddingStrategy::BucketedToLongest { buckets } => {
+            // Each sequence gets its own padding length here
+            encodings.maybe_par_iter_mut().for_each(|encoding| {
+                let len = encoding.get_ids().len();
+                let pad_length = round_to_multiple(
+                    smallest_bucket(buckets, len).unwrap_or(len),
+                    params.pad_to_multiple_of,
+                );
+                encoding.pad(
+                    pad_length,
+                    params.pad_id,
+                    params.pad_type_id,
+                    &params.pad_token,
+                    direction,
+                )
+            });
+            return Ok(());
+        }
     };
 
-    if let Some(multiple) = params.pad_to_multiple_of {
-        if multiple > 0 && pad_length % multiple > 0 {
-            pad_length += multiple - pad_length % multiple;
-        }
-    }
+    pad_length = round_to_multiple(pad_length, params.pad_to_multiple_of);
 
     encodings.maybe_par_iter_mut().for_each(|encoding| {
         encoding.pad(
@@ -73,7 +120,7 @@ pub fn pad_encodings(encodings: &mut [Encoding], params: &PaddingParams) -> Resu
             params.pad_id,
             params.pad_type_id,
             &params.pad_token,
-            params.direction,
+            direction,
         )
     });
 
@@ -138,5 +185,42 @@ mod tests {
         // Do not crash with 0
         params.pad_to_multiple_of = Some(0);
         pad_encodings(&mut encodings, &params).unwrap();
+
+        // Test bucketed: each sequence is padded to its own bucket, and
+        // sequences exceeding the largest bucket are left unpadded
+        let mut encodings = get_encodings();
+        params.pad_to_multiple_of = None;
+        params.strategy = PaddingStrategy::BucketedToLongest {
+            buckets: vec![4, 6],
+        };
+        pad_encodings(&mut encodings, &params).unwrap();
+        assert_eq!(encodings[0].get_ids().len(), 6);
+        assert_eq!(encodings[1].get_ids().len(), 4);
+
+        params.strategy = PaddingStrategy::BucketedToLongest { buckets: vec![4] };
+        let mut encodings = get_encodings();
+        pad_encodings(&mut encodings, &params).unwrap();
+        assert_eq!(encodings[0].get_ids().len(), 5);
+        assert_eq!(encodings[1].get_ids().len(), 4);
+
+        // Override the direction for this call only
+        let mut encodings = [Encoding::new(
+            vec![0, 1, 2],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![1, 1, 1],
+            vec![],
+            HashMap::new(),
+        )];
+        params.pad_to_multiple_of = None;
+        params.strategy = PaddingStrategy::Fixed(5);
+        pad_encodings_with_direction(&mut encodings, &params, PaddingDirection::Left).unwrap();
+        assert_eq!(encodings[0].get_ids(), &[0, 0, 0, 1, 2]);
+        assert_eq!(encodings[0].get_attention_mask(), &[0, 0, 1, 1, 1]);
+        assert_eq!(encodings[0].get_attention_mask_u8(), vec![0, 0, 1, 1, 1]);
+        assert_eq!(encodings[0].get_attention_mask_packed(), vec![0b0001_1100]);
     }
 }
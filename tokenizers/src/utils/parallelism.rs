@@ -2,15 +2,25 @@
 //! This module defines helpers to allow optional Rayon usage.
 //!
 
+#[cfg(feature = "rayon")]
 use rayon::iter::IterBridge;
+#[cfg(feature = "rayon")]
 use rayon::prelude::*;
+#[cfg(feature = "rayon")]
 use rayon_cond::CondIterator;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 
 // Re-export rayon current_num_threads
+#[cfg(feature = "rayon")]
 pub use rayon::current_num_threads;
 
+/// Without rayon, everything runs on the current thread
+#[cfg(not(feature = "rayon"))]
+pub fn current_num_threads() -> usize {
+    1
+}
+
 pub const ENV_VARIABLE: &str = "TOKENIZERS_PARALLELISM";
 
 static USED_PARALLELISM: AtomicBool = AtomicBool::new(false);
@@ -41,6 +51,62 @@ pub fn set_parallelism(val: bool) {
     std::env::set_var(ENV_VARIABLE, if val { "true" } else { "false" })
 }
 
+/// How the batch entry points (`encode_batch`, `decode_batch`, ...) dispatch
+/// their work, configured per tokenizer with
+/// [`crate::TokenizerImpl::with_parallelism`]. This gives consumers embedded
+/// in applications that also use rayon a per-tokenizer alternative to the
+/// process-wide `TOKENIZERS_PARALLELISM` environment variable.
+#[derive(Debug, Clone, Default)]
+pub enum Parallelism {
+    /// Follow the `TOKENIZERS_PARALLELISM` environment variable (the default)
+    #[default]
+    Env,
+    /// Always run on the current thread
+    Disabled,
+    /// Run on a dedicated pool with this many threads, built for the duration
+    /// of each batch call. To reuse the same threads across calls, build a
+    /// pool once and pass it with [`Parallelism::Rayon`]
+    Threads(usize),
+    /// Run on the given rayon thread pool
+    #[cfg(feature = "rayon")]
+    Rayon(std::sync::Arc<rayon::ThreadPool>),
+}
+
+impl Parallelism {
+    /// Whether this configuration overrides the environment variable, and if
+    /// so whether it enables parallelism
+    fn forced(&self) -> Option<bool> {
+        match self {
+            Self::Env => None,
+            Self::Disabled => Some(false),
+            Self::Threads(_) => Some(true),
+            #[cfg(feature = "rayon")]
+            Self::Rayon(_) => Some(true),
+        }
+    }
+
+    /// Run `f` inside the configured thread pool, or on the current thread
+    /// when no dedicated pool is configured
+    #[cfg(feature = "rayon")]
+    pub(crate) fn install<R: Send>(&self, f: impl FnOnce() -> R + Send) -> crate::Result<R> {
+        match self {
+            Self::Threads(n) => Ok(rayon::ThreadPoolBuilder::new()
+                .num_threads(*n)
+                .build()?
+                .install(f)),
+            Self::Rayon(pool) => Ok(pool.install(f)),
+            _ => Ok(f()),
+        }
+    }
+
+    /// Serial version: there is no thread pool to dispatch to
+    #[cfg(not(feature = "rayon"))]
+    pub(crate) fn install<R>(&self, f: impl FnOnce() -> R) -> crate::Result<R> {
+        Ok(f())
+    }
+}
+
+#[cfg(feature = "rayon")]
 /// Allows to convert into an iterator that can be executed either parallelly or serially.
 ///
 /// The choice is made according to the currently set `TOKENIZERS_PARALLELISM` environment variable.
@@ -60,8 +126,12 @@ where
     /// based on both the `TOKENIZERS_PARALLELISM` environment variable and the provided bool.
     /// Both must be true to run with parallelism activated.
     fn into_maybe_par_iter_cond(self, cond: bool) -> CondIterator<P, S>;
+    /// Convert ourself in a CondIterator driven by the given [`Parallelism`]
+    /// configuration; the environment variable only applies in [`Parallelism::Env`] mode.
+    fn into_maybe_par_iter_with(self, parallelism: &Parallelism) -> CondIterator<P, S>;
 }
 
+#[cfg(feature = "rayon")]
 impl<P, S, I> MaybeParallelIterator<P, S> for I
 where
     I: IntoParallelIterator<Iter = P, Item = P::Item> + IntoIterator<IntoIter = S, Item = S::Item>,
@@ -83,8 +153,21 @@ where
             CondIterator::from_serial(self)
         }
     }
+
+    fn into_maybe_par_iter_with(self, parallelism: &Parallelism) -> CondIterator<P, S> {
+        match parallelism.forced() {
+            None => self.into_maybe_par_iter(),
+            Some(parallel) => {
+                if parallel {
+                    USED_PARALLELISM.store(true, Ordering::SeqCst);
+                }
+                CondIterator::new(self, parallel)
+            }
+        }
+    }
 }
 
+#[cfg(feature = "rayon")]
 /// Shared reference version of MaybeParallelIterator, works the same but returns an iterator
 /// over references, does not consume self
 pub trait MaybeParallelRefIterator<'data, P, S>
@@ -97,6 +180,7 @@ where
     fn maybe_par_iter_cond(&'data self, cond: bool) -> CondIterator<P, S>;
 }
 
+#[cfg(feature = "rayon")]
 impl<'data, P, S, I: 'data + ?Sized> MaybeParallelRefIterator<'data, P, S> for I
 where
     &'data I: MaybeParallelIterator<P, S>,
@@ -113,6 +197,7 @@ where
     }
 }
 
+#[cfg(feature = "rayon")]
 /// Exclusive reference version of MaybeParallelIterator, works the same but returns an iterator
 /// over mutable references, does not consume self
 pub trait MaybeParallelRefMutIterator<'data, P, S>
@@ -125,6 +210,7 @@ where
     fn maybe_par_iter_mut_cond(&'data mut self, cond: bool) -> CondIterator<P, S>;
 }
 
+#[cfg(feature = "rayon")]
 impl<'data, P, S, I: 'data + ?Sized> MaybeParallelRefMutIterator<'data, P, S> for I
 where
     &'data mut I: MaybeParallelIterator<P, S>,
@@ -141,6 +227,7 @@ where
     }
 }
 
+#[cfg(feature = "rayon")]
 /// Converts any serial iterator into a CondIterator, that can either run parallelly or serially.
 pub trait MaybeParallelBridge<T, S>
 where
@@ -151,6 +238,7 @@ where
     fn maybe_par_bridge_cond(self, cond: bool) -> CondIterator<IterBridge<S>, S>;
 }
 
+#[cfg(feature = "rayon")]
 impl<T, S> MaybeParallelBridge<T, S> for S
 where
     S: Iterator<Item = T> + Send,
@@ -176,6 +264,7 @@ where
     }
 }
 
+#[cfg(feature = "rayon")]
 /// Allows to convert into `chunks` that can be executed either parallelly or serially.
 pub trait MaybeParallelSlice<'data, T>
 where
@@ -197,6 +286,7 @@ where
     ) -> CondIterator<rayon::slice::Chunks<'_, T>, std::slice::Chunks<'_, T>>;
 }
 
+#[cfg(feature = "rayon")]
 impl<T> MaybeParallelSlice<'_, T> for [T]
 where
     T: Sync,
@@ -225,6 +315,202 @@ where
     }
 }
 
+/// Serial stand-in for `rayon_cond::CondIterator`, exposing the subset of the
+/// Rayon iterator API that this crate relies on. Everything runs on the
+/// current thread.
+#[cfg(not(feature = "rayon"))]
+pub struct CondIterator<I> {
+    iter: I,
+}
+
+#[cfg(not(feature = "rayon"))]
+impl<I: Iterator> CondIterator<I> {
+    fn from_serial<S: IntoIterator<IntoIter = I>>(iter: S) -> Self {
+        Self {
+            iter: iter.into_iter(),
+        }
+    }
+
+    pub fn map<F, R>(self, f: F) -> CondIterator<std::iter::Map<I, F>>
+    where
+        F: FnMut(I::Item) -> R,
+    {
+        CondIterator::from_serial(self.iter.map(f))
+    }
+
+    pub fn flat_map<F, R>(self, f: F) -> CondIterator<std::iter::FlatMap<I, R, F>>
+    where
+        F: FnMut(I::Item) -> R,
+        R: IntoIterator,
+    {
+        CondIterator::from_serial(self.iter.flat_map(f))
+    }
+
+    pub fn enumerate(self) -> CondIterator<std::iter::Enumerate<I>> {
+        CondIterator::from_serial(self.iter.enumerate())
+    }
+
+    /// Serial version of `ParallelIterator::reduce`: with a single thread the
+    /// identity is only used to seed the accumulator.
+    pub fn reduce<ID, OP>(self, identity: ID, op: OP) -> I::Item
+    where
+        ID: Fn() -> I::Item,
+        OP: Fn(I::Item, I::Item) -> I::Item,
+    {
+        self.iter.fold(identity(), op)
+    }
+}
+
+#[cfg(not(feature = "rayon"))]
+impl<I: Iterator> Iterator for CondIterator<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+#[cfg(not(feature = "rayon"))]
+/// Serial version of MaybeParallelIterator: the `TOKENIZERS_PARALLELISM`
+/// environment variable is ignored since there is no thread pool to dispatch to.
+pub trait MaybeParallelIterator<S>
+where
+    S: Iterator,
+{
+    fn into_maybe_par_iter(self) -> CondIterator<S>;
+    fn into_maybe_par_iter_cond(self, cond: bool) -> CondIterator<S>;
+    fn into_maybe_par_iter_with(self, parallelism: &Parallelism) -> CondIterator<S>;
+}
+
+#[cfg(not(feature = "rayon"))]
+impl<S, I> MaybeParallelIterator<S> for I
+where
+    I: IntoIterator<IntoIter = S, Item = S::Item>,
+    S: Iterator,
+{
+    fn into_maybe_par_iter(self) -> CondIterator<S> {
+        CondIterator::from_serial(self)
+    }
+
+    fn into_maybe_par_iter_cond(self, _cond: bool) -> CondIterator<S> {
+        CondIterator::from_serial(self)
+    }
+
+    fn into_maybe_par_iter_with(self, _parallelism: &Parallelism) -> CondIterator<S> {
+        CondIterator::from_serial(self)
+    }
+}
+
+#[cfg(not(feature = "rayon"))]
+/// Shared reference version of MaybeParallelIterator, works the same but returns an iterator
+/// over references, does not consume self
+pub trait MaybeParallelRefIterator<'data, S>
+where
+    S: Iterator,
+    S::Item: 'data,
+{
+    fn maybe_par_iter(&'data self) -> CondIterator<S>;
+    fn maybe_par_iter_cond(&'data self, cond: bool) -> CondIterator<S>;
+}
+
+#[cfg(not(feature = "rayon"))]
+impl<'data, S, I: 'data + ?Sized> MaybeParallelRefIterator<'data, S> for I
+where
+    &'data I: MaybeParallelIterator<S>,
+    S: Iterator,
+    S::Item: 'data,
+{
+    fn maybe_par_iter(&'data self) -> CondIterator<S> {
+        self.into_maybe_par_iter()
+    }
+
+    fn maybe_par_iter_cond(&'data self, cond: bool) -> CondIterator<S> {
+        self.into_maybe_par_iter_cond(cond)
+    }
+}
+
+#[cfg(not(feature = "rayon"))]
+/// Exclusive reference version of MaybeParallelIterator, works the same but returns an iterator
+/// over mutable references, does not consume self
+pub trait MaybeParallelRefMutIterator<'data, S>
+where
+    S: Iterator,
+    S::Item: 'data,
+{
+    fn maybe_par_iter_mut(&'data mut self) -> CondIterator<S>;
+    fn maybe_par_iter_mut_cond(&'data mut self, cond: bool) -> CondIterator<S>;
+}
+
+#[cfg(not(feature = "rayon"))]
+impl<'data, S, I: 'data + ?Sized> MaybeParallelRefMutIterator<'data, S> for I
+where
+    &'data mut I: MaybeParallelIterator<S>,
+    S: Iterator,
+    S::Item: 'data,
+{
+    fn maybe_par_iter_mut(&'data mut self) -> CondIterator<S> {
+        self.into_maybe_par_iter()
+    }
+
+    fn maybe_par_iter_mut_cond(&'data mut self, cond: bool) -> CondIterator<S> {
+        self.into_maybe_par_iter_cond(cond)
+    }
+}
+
+#[cfg(not(feature = "rayon"))]
+/// Serial version of MaybeParallelBridge, simply wraps the given iterator.
+pub trait MaybeParallelBridge<T, S>
+where
+    S: Iterator<Item = T>,
+{
+    fn maybe_par_bridge(self) -> CondIterator<S>;
+    fn maybe_par_bridge_cond(self, cond: bool) -> CondIterator<S>;
+}
+
+#[cfg(not(feature = "rayon"))]
+impl<T, S> MaybeParallelBridge<T, S> for S
+where
+    S: Iterator<Item = T>,
+{
+    fn maybe_par_bridge(self) -> CondIterator<S> {
+        CondIterator::from_serial(self)
+    }
+
+    fn maybe_par_bridge_cond(self, _cond: bool) -> CondIterator<S> {
+        CondIterator::from_serial(self)
+    }
+}
+
+#[cfg(not(feature = "rayon"))]
+/// Serial version of MaybeParallelSlice, always iterating the chunks in order.
+pub trait MaybeParallelSlice<'data, T> {
+    fn maybe_par_chunks(&'_ self, chunk_size: usize) -> CondIterator<std::slice::Chunks<'_, T>>;
+    fn maybe_par_chunks_cond(
+        &'_ self,
+        cond: bool,
+        chunk_size: usize,
+    ) -> CondIterator<std::slice::Chunks<'_, T>>;
+}
+
+#[cfg(not(feature = "rayon"))]
+impl<T> MaybeParallelSlice<'_, T> for [T] {
+    fn maybe_par_chunks(&'_ self, chunk_size: usize) -> CondIterator<std::slice::Chunks<'_, T>> {
+        CondIterator::from_serial(self.chunks(chunk_size))
+    }
+
+    fn maybe_par_chunks_cond(
+        &'_ self,
+        _cond: bool,
+        chunk_size: usize,
+    ) -> CondIterator<std::slice::Chunks<'_, T>> {
+        CondIterator::from_serial(self.chunks(chunk_size))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,77 @@
+//! Opt-in profiling of the encoding pipeline, recording where each encode
+//! call spends its time, component by component.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+/// The aggregated cost of one component of the pipeline over the profiled
+/// encode calls
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ComponentProfile {
+    /// Total wall time spent in the component
+    pub wall_time: Duration,
+    /// Total number of heap allocations performed by the component. Only
+    /// recorded when [`CountingAllocator`] is installed as the global
+    /// allocator, and stays 0 otherwise
+    pub allocations: u64,
+}
+
+/// The profile of the encode calls performed since profiling was enabled (or
+/// since the last [`crate::TokenizerImpl::take_profile`]), aggregated per
+/// pipeline component
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EncodeProfile {
+    /// Number of encode calls profiled
+    pub encode_calls: u64,
+    /// Added-token extraction and normalization
+    pub normalizer: ComponentProfile,
+    /// Pre-tokenization
+    pub pre_tokenizer: ComponentProfile,
+    /// Model tokenization, including the offsets conversion
+    pub model: ComponentProfile,
+    /// Post-processing, including truncation and padding
+    pub post_processor: ComponentProfile,
+}
+
+thread_local! {
+    static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+}
+
+/// A drop-in wrapper of the [`System`] allocator counting the allocations of
+/// the current thread, so that the profile can attribute them to pipeline
+/// components. Install it to get allocation counts in [`EncodeProfile`]:
+///
+/// ```ignore
+/// #[global_allocator]
+/// static GLOBAL: tokenizers::utils::profiling::CountingAllocator =
+///     tokenizers::utils::profiling::CountingAllocator;
+/// ```
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|c| c.set(c.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|c| c.set(c.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Run `f`, returning its result along with the wall time it took and the
+/// number of allocations it performed on this thread
+pub(crate) fn measure<T>(f: impl FnOnce() -> T) -> (T, Duration, u64) {
+    let allocations_before = ALLOCATIONS.with(|c| c.get());
+    let start = Instant::now();
+    let result = f();
+    let wall_time = start.elapsed();
+    let allocations = ALLOCATIONS.with(|c| c.get()) - allocations_before;
+    (result, wall_time, allocations)
+}
@@ -4,6 +4,7 @@ pub(crate) use indicatif::{ProgressBar, ProgressStyle};
 #[cfg(not(feature = "progressbar"))]
 mod progressbar {
     use std::borrow::Cow;
+    #[derive(Clone)]
     pub struct ProgressBar;
     impl ProgressBar {
         pub fn new(_length: u64) -> Self {
@@ -0,0 +1,84 @@
+use crate::tokenizer::pattern::Pattern;
+use crate::utils::cache::Cache;
+use crate::{Offsets, Result};
+use regex::Regex;
+use std::error::Error;
+use std::sync::Arc;
+
+lazy_static! {
+    /// Compiled regexes interned by pattern, so that the many tokenizer instances of a
+    /// multi-tenant server share a single compiled automaton per pattern
+    static ref REGEX_CACHE: Cache<String, Arc<Regex>> = Cache::default();
+}
+
+/// A wrapper around the pure-Rust `regex` engine. It does not support
+/// lookarounds or backreferences, but is often much faster than oniguruma
+/// on long inputs, so components can opt into it on a per-pattern basis.
+#[derive(Debug, Clone)]
+pub struct SysRegex {
+    regex: Arc<Regex>,
+}
+
+impl SysRegex {
+    pub fn find_iter<'r, 't>(&'r self, inside: &'t str) -> Matches<'r, 't> {
+        Matches(self.regex.find_iter(inside))
+    }
+
+    pub fn new(
+        regex_str: &str,
+    ) -> std::result::Result<Self, Box<dyn Error + Send + Sync + 'static>> {
+        if let Some(regex) = REGEX_CACHE.get(regex_str) {
+            return Ok(Self { regex });
+        }
+        let regex = Arc::new(Regex::new(regex_str)?);
+        REGEX_CACHE.set(regex_str.to_owned(), regex.clone());
+        Ok(Self { regex })
+    }
+}
+
+pub struct Matches<'r, 't>(regex::Matches<'r, 't>);
+
+impl<'r, 't> Iterator for Matches<'r, 't> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|mat| (mat.start(), mat.end()))
+    }
+}
+
+impl Pattern for &SysRegex {
+    fn find_matches(&self, inside: &str) -> Result<Vec<(Offsets, bool)>> {
+        if inside.is_empty() {
+            return Ok(vec![((0, 0), false)]);
+        }
+
+        let mut prev = 0;
+        let mut splits = Vec::with_capacity(inside.len());
+        for (start, end) in self.find_iter(inside) {
+            if prev != start {
+                splits.push(((prev, start), false));
+            }
+            splits.push(((start, end), true));
+            prev = end;
+        }
+        if prev != inside.len() {
+            splits.push(((prev, inside.len()), false))
+        }
+        Ok(splits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regexes_are_interned() {
+        let first = SysRegex::new(r"\w+|[^\w\s]+").unwrap();
+        let second = SysRegex::new(r"\w+|[^\w\s]+").unwrap();
+        assert!(Arc::ptr_eq(&first.regex, &second.regex));
+
+        let other = SysRegex::new(r"\s+").unwrap();
+        assert!(!Arc::ptr_eq(&first.regex, &other.regex));
+    }
+}
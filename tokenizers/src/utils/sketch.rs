@@ -0,0 +1,112 @@
+//! Approximate word counting for corpora too large to count exactly, keeping
+//! only the heaviest words within a fixed number of counters.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+/// Approximates word counts with the space-saving algorithm: at most
+/// `capacity` words are tracked, and when a new word arrives while the
+/// counters are full, the word with the smallest count is evicted and the new
+/// word inherits its count. Any word with a true count above
+/// `total / capacity` is guaranteed to be tracked, and counts are
+/// overestimated by at most `total / capacity`, where `total` is the sum of
+/// all fed counts.
+pub(crate) struct SpaceSavingCounter<C> {
+    capacity: usize,
+    counts: HashMap<String, C>,
+    /// A lazily-pruned min-heap over `(count, word)`: entries whose count no
+    /// longer matches the map are stale and skipped during evictions
+    heap: BinaryHeap<Reverse<(C, String)>>,
+}
+
+impl<C> SpaceSavingCounter<C>
+where
+    C: Copy + Ord + std::ops::AddAssign,
+{
+    /// Create a counter tracking at most `capacity` words
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            counts: HashMap::new(),
+            heap: BinaryHeap::new(),
+        }
+    }
+
+    /// Add `count` to the count of `word`, evicting the smallest tracked word
+    /// if the counters are full
+    pub(crate) fn add(&mut self, word: String, count: C) {
+        if let Some(current) = self.counts.get_mut(&word) {
+            *current += count;
+            self.heap.push(Reverse((*current, word)));
+        } else if self.counts.len() < self.capacity {
+            self.counts.insert(word.clone(), count);
+            self.heap.push(Reverse((count, word)));
+        } else {
+            // Evict the current minimum, skipping stale heap entries: since a
+            // live word always has a heap entry with its current count, the
+            // first up-to-date entry popped is the true minimum
+            let (min_word, min_count) = loop {
+                let Reverse((c, w)) = self.heap.pop().expect("A live entry per tracked word");
+                if self.counts.get(&w) == Some(&c) {
+                    break (w, c);
+                }
+            };
+            self.counts.remove(&min_word);
+            let mut new_count = min_count;
+            new_count += count;
+            self.counts.insert(word.clone(), new_count);
+            self.heap.push(Reverse((new_count, word)));
+        }
+
+        // Keep the stale entries from accumulating over the whole stream
+        if self.heap.len() > 4 * self.capacity {
+            self.heap = self
+                .counts
+                .iter()
+                .map(|(w, c)| Reverse((*c, w.clone())))
+                .collect();
+        }
+    }
+
+    /// The tracked words with their (over)estimated counts
+    pub(crate) fn finish(self) -> HashMap<String, C> {
+        self.counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn space_saving_tracks_heavy_words() {
+        // 10 counters for a stream of 13 distinct words
+        let mut counter: SpaceSavingCounter<u64> = SpaceSavingCounter::new(10);
+        for _ in 0..100 {
+            counter.add("the".to_owned(), 1);
+        }
+        for _ in 0..50 {
+            counter.add("of".to_owned(), 1);
+        }
+        for i in 0..11 {
+            counter.add(format!("rare-{i}"), 1);
+        }
+        assert_eq!(counter.capacity, 10);
+        assert!(counter.counts.len() <= counter.capacity);
+
+        let counts = counter.finish();
+        // The heavy words survive with exact counts: every eviction hits one
+        // of the rare words
+        assert_eq!(counts["the"], 100);
+        assert_eq!(counts["of"], 50);
+        // Evicted words passed their count on, overestimating the survivor
+        // by at most `total / capacity`
+        let rare_max = counts
+            .iter()
+            .filter(|(word, _)| word.starts_with("rare-"))
+            .map(|(_, count)| *count)
+            .max()
+            .unwrap();
+        assert!(rare_max <= 161 / 10);
+    }
+}
@@ -0,0 +1,184 @@
+//! On-disk spilling of word-count maps, to keep the counting phase of a
+//! trainer within a memory budget when feeding huge corpora.
+
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::tokenizer::Result;
+
+/// The estimated memory overhead of a `HashMap<String, _>` entry, on top of
+/// the word bytes themselves: the `String` header, the count and the
+/// hash-map slot
+const ENTRY_OVERHEAD: usize = 48;
+
+/// Distinguishes the spill files of concurrent counters within the process
+static SPILL_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Accumulates word counts like a `HashMap`, but within a memory budget:
+/// whenever the estimated size of the map exceeds the budget, the entries are
+/// sorted and spilled to a temporary file, and `finish` merges the sorted
+/// runs back with an external sort, summing the counts of every word. The
+/// temporary files are removed when the counter is dropped.
+pub(crate) struct SpillingWordCounter<C> {
+    /// The memory budget, in bytes
+    budget: usize,
+    /// The estimated memory held by `counts`
+    estimated: usize,
+    counts: HashMap<String, C>,
+    /// One temporary file per spilled run, each holding its entries sorted
+    /// by word
+    runs: Vec<PathBuf>,
+}
+
+impl<C> SpillingWordCounter<C>
+where
+    C: Copy + std::ops::AddAssign + Serialize + DeserializeOwned,
+{
+    /// Create a counter spilling to disk above `max_memory_mb` megabytes
+    pub(crate) fn new(max_memory_mb: usize) -> Self {
+        Self {
+            budget: max_memory_mb * 1024 * 1024,
+            estimated: 0,
+            counts: HashMap::new(),
+            runs: vec![],
+        }
+    }
+
+    /// Add `count` to the count of `word`, spilling the map to disk if it
+    /// exceeds the memory budget
+    pub(crate) fn add(&mut self, word: String, count: C) -> Result<()> {
+        match self.counts.entry(word) {
+            Entry::Occupied(mut entry) => *entry.get_mut() += count,
+            Entry::Vacant(entry) => {
+                self.estimated += entry.key().len() + ENTRY_OVERHEAD;
+                entry.insert(count);
+            }
+        }
+        if self.estimated > self.budget {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// Write the in-memory entries, sorted by word, to a new temporary file,
+    /// and empty the map
+    fn spill(&mut self) -> Result<()> {
+        let mut entries: Vec<_> = self.counts.drain().collect();
+        entries.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+
+        let path = std::env::temp_dir().join(format!(
+            "tokenizers-spill-{}-{}.jsonl",
+            std::process::id(),
+            SPILL_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        let mut writer = BufWriter::new(file);
+        for (word, count) in &entries {
+            serde_json::to_writer(&mut writer, &(word, count))?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+
+        self.runs.push(path);
+        self.estimated = 0;
+        Ok(())
+    }
+
+    /// Merge the spilled runs and the in-memory entries into the final
+    /// word-count map
+    pub(crate) fn finish(mut self) -> Result<HashMap<String, C>> {
+        if self.runs.is_empty() {
+            return Ok(std::mem::take(&mut self.counts));
+        }
+        // Turn the in-memory leftover into one more sorted run, then merge
+        // the runs back: since each one is sorted by word, repeatedly taking
+        // the smallest current word across all of them visits every entry of
+        // a given word before moving past it
+        self.spill()?;
+        let mut readers = self
+            .runs
+            .iter()
+            .map(|path| Ok(BufReader::new(File::open(path)?).lines()))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut heap = BinaryHeap::new();
+        let mut pending: Vec<Option<C>> = vec![None; readers.len()];
+        for (run, reader) in readers.iter_mut().enumerate() {
+            if let Some(line) = reader.next() {
+                let (word, count): (String, C) = serde_json::from_str(&line?)?;
+                heap.push(Reverse((word, run)));
+                pending[run] = Some(count);
+            }
+        }
+
+        let mut counts: HashMap<String, C> = HashMap::new();
+        while let Some(Reverse((word, run))) = heap.pop() {
+            let count = pending[run].take().expect("Pending count for the run");
+            match counts.entry(word) {
+                Entry::Occupied(mut entry) => *entry.get_mut() += count,
+                Entry::Vacant(entry) => {
+                    entry.insert(count);
+                }
+            }
+            if let Some(line) = readers[run].next() {
+                let (word, count): (String, C) = serde_json::from_str(&line?)?;
+                heap.push(Reverse((word, run)));
+                pending[run] = Some(count);
+            }
+        }
+        Ok(counts)
+    }
+}
+
+impl<C> Drop for SpillingWordCounter<C> {
+    fn drop(&mut self) {
+        for path in &self.runs {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spilling_matches_in_memory_counting() {
+        let words = ["low", "low", "lower", "l\to\nw", "低い", "low", "低い"];
+
+        // A zero budget spills after every single word
+        let mut counter: SpillingWordCounter<u64> = SpillingWordCounter::new(0);
+        for word in words {
+            counter.add(word.to_owned(), 1).unwrap();
+        }
+        assert_eq!(counter.runs.len(), words.len());
+        let spill_paths = counter.runs.clone();
+        let spilled = counter.finish().unwrap();
+
+        // A comfortable budget keeps everything in memory
+        let mut counter: SpillingWordCounter<u64> = SpillingWordCounter::new(1);
+        for word in words {
+            counter.add(word.to_owned(), 1).unwrap();
+        }
+        assert!(counter.runs.is_empty());
+        let in_memory = counter.finish().unwrap();
+
+        assert_eq!(spilled, in_memory);
+        assert_eq!(spilled["low"], 3);
+        assert_eq!(spilled["低い"], 2);
+
+        // The temporary files are cleaned up
+        assert!(spill_paths.iter().all(|path| !path.exists()));
+    }
+}
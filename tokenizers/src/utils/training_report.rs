@@ -0,0 +1,102 @@
+//! A serializable summary of a training run: what the learnt vocabulary
+//! covers, what it dropped, and how it behaves on held-out data.
+
+use crate::tokenizer::{Model, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// The number of dropped characters kept in the report
+const MAX_DROPPED_CHARACTERS: usize = 10;
+
+/// A report on the outcome of a training run, produced by
+/// [`Trainer::train_with_report`](crate::tokenizer::Trainer::train_with_report)
+/// when the trainer is configured to do so.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct TrainingReport {
+    /// The fraction of character occurrences of the training corpus that are
+    /// covered by the characters of the learnt vocabulary
+    pub character_coverage: f64,
+    /// The most frequent characters of the training corpus that are absent
+    /// from the learnt vocabulary, with their occurrence counts, most frequent
+    /// first. At most [`MAX_DROPPED_CHARACTERS`] entries are kept.
+    pub dropped_characters: Vec<(char, u64)>,
+    /// The number of vocabulary tokens per token length, in characters
+    pub token_length_histogram: BTreeMap<usize, usize>,
+    /// The fraction of tokens produced on the validation split that are the
+    /// unknown token. `None` when no validation split was fed, or when the
+    /// model has no unknown token.
+    pub unk_rate: Option<f64>,
+}
+
+impl TrainingReport {
+    /// Build a report from the word counts a trainer accumulated, the trained
+    /// model, and an optional validation split used to measure the unk-rate.
+    pub fn compute<M: Model>(
+        word_counts: &HashMap<String, u64>,
+        validation: &[String],
+        model: &M,
+        unk_token: Option<&str>,
+    ) -> Result<Self> {
+        let vocab_chars: HashSet<char> = model
+            .get_vocab_iter()
+            .flat_map(|(token, _)| token.chars().collect::<Vec<_>>())
+            .collect();
+
+        // Character coverage and dropped characters, weighted by occurrences
+        let mut char_counts: HashMap<char, u64> = HashMap::new();
+        for (word, count) in word_counts {
+            for c in word.chars() {
+                *char_counts.entry(c).or_insert(0) += count;
+            }
+        }
+        let total: u64 = char_counts.values().sum();
+        let covered: u64 = char_counts
+            .iter()
+            .filter(|(c, _)| vocab_chars.contains(c))
+            .map(|(_, count)| count)
+            .sum();
+        let character_coverage = if total > 0 {
+            covered as f64 / total as f64
+        } else {
+            1.0
+        };
+        let mut dropped_characters: Vec<(char, u64)> = char_counts
+            .into_iter()
+            .filter(|(c, _)| !vocab_chars.contains(c))
+            .collect();
+        // Most frequent first, ties broken by the character for determinism
+        dropped_characters.sort_by_key(|(c, count)| (std::cmp::Reverse(*count), *c));
+        dropped_characters.truncate(MAX_DROPPED_CHARACTERS);
+
+        let mut token_length_histogram = BTreeMap::new();
+        for (token, _) in model.get_vocab_iter() {
+            *token_length_histogram
+                .entry(token.chars().count())
+                .or_insert(0) += 1;
+        }
+
+        let unk_rate = match unk_token {
+            Some(unk) if !validation.is_empty() => {
+                let mut total = 0usize;
+                let mut unks = 0usize;
+                for sequence in validation {
+                    for token in model.tokenize(sequence)? {
+                        total += 1;
+                        if token.value == unk {
+                            unks += 1;
+                        }
+                    }
+                }
+                (total > 0).then(|| unks as f64 / total as f64)
+            }
+            _ => None,
+        };
+
+        Ok(Self {
+            character_coverage,
+            dropped_characters,
+            token_length_histogram,
+            unk_rate,
+        })
+    }
+}
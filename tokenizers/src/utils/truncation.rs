@@ -26,6 +26,12 @@ pub struct TruncationParams {
     pub max_length: usize,
     pub strategy: TruncationStrategy,
     pub stride: usize,
+    /// Never cut between two tokens whose values form a single extended
+    /// grapheme cluster, e.g. a character followed by its combining marks at
+    /// char-level. The cut point moves back until it falls on a cluster
+    /// boundary, so the truncated sequence can be shorter than `max_length`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub respect_grapheme_clusters: bool,
 }
 
 impl Default for TruncationParams {
@@ -35,6 +41,7 @@ impl Default for TruncationParams {
             strategy: TruncationStrategy::default(),
             stride: 0,
             direction: TruncationDirection::default(),
+            respect_grapheme_clusters: false,
         }
     }
 }
@@ -47,6 +54,12 @@ pub enum TruncationError {
     /// We cannot truncate the target sequence enough to respect the provided max length.
     #[error("Truncation error: Sequence to truncate too short to respect the provided max_length")]
     SequenceTooShort,
+    /// The number of field budgets does not match the number of sequences.
+    #[error("Truncation error: {0} field budgets provided for {1} sequences")]
+    BudgetCountMismatch(usize, usize),
+    /// The guaranteed minimum allocations alone exceed the budget.
+    #[error("Truncation error: The minimum field allocations exceed the max_length budget")]
+    BudgetTooSmall,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Eq)]
@@ -72,6 +85,134 @@ impl std::convert::AsRef<str> for TruncationStrategy {
     }
 }
 
+/// The token budget of one field of a multi-field input, see
+/// [`truncate_with_budget`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct FieldBudget {
+    /// Fields with a higher priority are served first when distributing the
+    /// budget left over the minimums; ties are broken by field order
+    #[serde(default)]
+    pub priority: u32,
+    /// The number of tokens the field is guaranteed to keep (when it holds
+    /// that many)
+    #[serde(default)]
+    pub min_tokens: usize,
+    /// An optional cap on the tokens the field may keep, regardless of the
+    /// budget left
+    #[serde(default)]
+    pub max_tokens: Option<usize>,
+}
+
+/// Token-budgeted truncation over multiple fields, see
+/// [`truncate_with_budget`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetTruncation {
+    pub max_length: usize,
+    /// One budget per field, in field order
+    pub fields: Vec<FieldBudget>,
+    #[serde(default)]
+    pub direction: TruncationDirection,
+}
+
+/// Distribute a `max_length` token budget over multiple encoded fields: each
+/// field first receives its guaranteed minimum, and the rest of the budget is
+/// handed out by descending priority (e.g. a question field with a high
+/// priority and `min_tokens` covering it is never truncated, while the
+/// context fields absorb the cuts). Returns the truncated encodings along
+/// with what was dropped from each field, so callers can surface or re-chunk
+/// the discarded content.
+pub fn truncate_with_budget(
+    encodings: Vec<Encoding>,
+    params: &BudgetTruncation,
+) -> Result<(Vec<Encoding>, Vec<Encoding>)> {
+    if encodings.len() != params.fields.len() {
+        return Err(Box::new(TruncationError::BudgetCountMismatch(
+            params.fields.len(),
+            encodings.len(),
+        )));
+    }
+
+    // What each field could keep at most, and what it is guaranteed
+    let wanted: Vec<usize> = encodings
+        .iter()
+        .zip(&params.fields)
+        .map(|(encoding, field)| {
+            let len = encoding.get_ids().len();
+            cmp::min(len, field.max_tokens.unwrap_or(len))
+        })
+        .collect();
+    let mut alloc: Vec<usize> = wanted
+        .iter()
+        .zip(&params.fields)
+        .map(|(&wanted, field)| cmp::min(wanted, field.min_tokens))
+        .collect();
+    let allocated: usize = alloc.iter().sum();
+    if allocated > params.max_length {
+        return Err(Box::new(TruncationError::BudgetTooSmall));
+    }
+
+    // Hand out the remaining budget by descending priority, keeping the
+    // field order on ties
+    let mut remaining = params.max_length - allocated;
+    let mut order: Vec<usize> = (0..alloc.len()).collect();
+    order.sort_by_key(|&i| cmp::Reverse(params.fields[i].priority));
+    for i in order {
+        let grant = cmp::min(wanted[i] - alloc[i], remaining);
+        alloc[i] += grant;
+        remaining -= grant;
+    }
+
+    // Truncate each field, collecting the dropped side of each cut
+    let opposite = match params.direction {
+        TruncationDirection::Left => TruncationDirection::Right,
+        TruncationDirection::Right => TruncationDirection::Left,
+    };
+    let mut kept = Vec::with_capacity(encodings.len());
+    let mut dropped = Vec::with_capacity(encodings.len());
+    for (mut encoding, n) in encodings.into_iter().zip(alloc) {
+        let mut rest = encoding.clone();
+        rest.truncate(rest.get_ids().len() - n, 0, opposite);
+        rest.take_overflowing();
+        encoding.truncate(n, 0, params.direction);
+        encoding.take_overflowing();
+        kept.push(encoding);
+        dropped.push(rest);
+    }
+    Ok((kept, dropped))
+}
+
+/// Whether cutting between the token values `left` and `right` falls on an
+/// extended grapheme cluster boundary
+fn is_grapheme_boundary(left: &str, right: &str) -> bool {
+    use unicode_segmentation::UnicodeSegmentation;
+    if left.is_empty() || right.is_empty() {
+        return true;
+    }
+    let joined = format!("{left}{right}");
+    joined.grapheme_indices(true).any(|(i, _)| i == left.len())
+}
+
+/// Reduce the number of kept tokens `n` until the cut point falls on a
+/// grapheme cluster boundary of the token values
+fn align_to_grapheme_boundary(
+    encoding: &Encoding,
+    mut n: usize,
+    direction: TruncationDirection,
+) -> usize {
+    let tokens = encoding.get_tokens();
+    while n > 0 && n < tokens.len() {
+        let cut = match direction {
+            TruncationDirection::Right => n,
+            TruncationDirection::Left => tokens.len() - n,
+        };
+        if is_grapheme_boundary(&tokens[cut - 1], &tokens[cut]) {
+            break;
+        }
+        n -= 1;
+    }
+    n
+}
+
 pub fn truncate_encodings(
     mut encoding: Encoding,
     mut pair_encoding: Option<Encoding>,
@@ -140,10 +281,18 @@ pub fn truncate_encodings(
                 if swap {
                     mem::swap(&mut n1, &mut n2);
                 }
+                if params.respect_grapheme_clusters {
+                    n1 = align_to_grapheme_boundary(&encoding, n1, params.direction);
+                    n2 = align_to_grapheme_boundary(other_encoding, n2, params.direction);
+                }
                 encoding.truncate(n1, params.stride, params.direction);
                 other_encoding.truncate(n2, params.stride, params.direction);
             } else {
-                encoding.truncate(total_length - to_remove, params.stride, params.direction);
+                let mut n = total_length - to_remove;
+                if params.respect_grapheme_clusters {
+                    n = align_to_grapheme_boundary(&encoding, n, params.direction);
+                }
+                encoding.truncate(n, params.stride, params.direction);
             }
         }
         TruncationStrategy::OnlyFirst | TruncationStrategy::OnlySecond => {
@@ -157,7 +306,11 @@ pub fn truncate_encodings(
 
             let target_len = target.get_ids().len();
             if target_len > to_remove {
-                target.truncate(target_len - to_remove, params.stride, params.direction);
+                let mut n = target_len - to_remove;
+                if params.respect_grapheme_clusters {
+                    n = align_to_grapheme_boundary(target, n, params.direction);
+                }
+                target.truncate(n, params.stride, params.direction);
             } else {
                 return Err(Box::new(TruncationError::SequenceTooShort));
             }
@@ -283,6 +436,7 @@ mod tests {
             strategy: TruncationStrategy::LongestFirst,
             stride: 0,
             direction: TruncationDirection::Right,
+            respect_grapheme_clusters: false,
         };
 
         truncate_and_assert(get_empty(), get_empty(), &params, 0, 0);
@@ -313,6 +467,7 @@ mod tests {
             strategy: TruncationStrategy::LongestFirst,
             stride: 0,
             direction: TruncationDirection::Right,
+            respect_grapheme_clusters: false,
         };
 
         truncate_and_assert(get_empty(), get_short(), &params, 0, 0);
@@ -320,6 +475,113 @@ mod tests {
         truncate_and_assert(get_long(), get_long(), &params, 0, 0);
     }
 
+    #[test]
+    fn truncate_encodings_respects_grapheme_clusters() {
+        // "e" followed by a combining acute accent, as separate char-level
+        // tokens: cutting between them would split the `é` cluster
+        let encoding = Encoding::new(
+            vec![1, 2, 3, 4],
+            vec![0, 0, 0, 0],
+            vec![
+                String::from("a"),
+                String::from("b"),
+                String::from("e"),
+                String::from("\u{301}"),
+            ],
+            vec![Some(0), Some(1), Some(2), Some(3)],
+            vec![(0, 1), (1, 2), (2, 3), (3, 5)],
+            vec![0, 0, 0, 0],
+            vec![1, 1, 1, 1],
+            vec![],
+            HashMap::new(),
+        );
+
+        let mut params = TruncationParams {
+            max_length: 3,
+            ..Default::default()
+        };
+
+        // By default the cluster is split
+        let (truncated, _) = truncate_encodings(encoding.clone(), None, &params).unwrap();
+        assert_eq!(truncated.get_tokens(), &["a", "b", "e"]);
+
+        // With the option, the cut point moves back to the cluster boundary
+        params.respect_grapheme_clusters = true;
+        let (truncated, _) = truncate_encodings(encoding.clone(), None, &params).unwrap();
+        assert_eq!(truncated.get_tokens(), &["a", "b"]);
+
+        // Truncating from the left: the cut cannot move forward past
+        // max_length, so the whole cluster is dropped
+        params.direction = TruncationDirection::Left;
+        params.max_length = 1;
+        let (truncated, _) = truncate_encodings(encoding, None, &params).unwrap();
+        assert!(truncated.get_tokens().is_empty());
+
+        // The option is not serialized when disabled
+        params.respect_grapheme_clusters = false;
+        assert!(!serde_json::to_string(&params)
+            .unwrap()
+            .contains("respect_grapheme_clusters"));
+    }
+
+    #[test]
+    fn truncate_encodings_with_budget() {
+        let params = BudgetTruncation {
+            max_length: 6,
+            direction: TruncationDirection::Right,
+            fields: vec![
+                // The question: high priority, guaranteed 2 tokens
+                FieldBudget {
+                    priority: 1,
+                    min_tokens: 2,
+                    max_tokens: None,
+                },
+                // The context: absorbs the cuts
+                FieldBudget {
+                    priority: 0,
+                    min_tokens: 1,
+                    max_tokens: None,
+                },
+            ],
+        };
+
+        // The question (4 tokens) is served first and kept whole, the
+        // context gets the 2 remaining tokens
+        let (kept, dropped) =
+            truncate_with_budget(vec![get_medium(), get_long()], &params).unwrap();
+        assert_eq!(kept[0].get_ids(), &[3, 4, 5, 6]);
+        assert_eq!(kept[1].get_ids(), &[7, 8]);
+        assert!(dropped[0].get_ids().is_empty());
+        assert_eq!(dropped[1].get_ids(), &[9, 10, 11, 12, 13, 14]);
+
+        // A cap keeps budget available for lower priority fields
+        let mut capped = params.clone();
+        capped.fields[0].max_tokens = Some(3);
+        let (kept, dropped) =
+            truncate_with_budget(vec![get_medium(), get_long()], &capped).unwrap();
+        assert_eq!(kept[0].get_ids(), &[3, 4, 5]);
+        assert_eq!(kept[1].get_ids(), &[7, 8, 9]);
+        assert_eq!(dropped[0].get_ids(), &[6]);
+
+        // Minimums that do not fit the budget are an error, and so is a
+        // budget count mismatch
+        let mut too_small = params.clone();
+        too_small.fields[0].min_tokens = 4;
+        too_small.fields[1].min_tokens = 3;
+        assert_eq!(
+            truncate_with_budget(vec![get_medium(), get_long()], &too_small)
+                .unwrap_err()
+                .to_string(),
+            "Truncation error: The minimum field allocations exceed the max_length budget"
+        );
+        assert_eq!(
+            truncate_with_budget(vec![get_medium()], &params)
+                .unwrap_err()
+                .to_string(),
+            "Truncation error: 2 field budgets provided for 1 sequences"
+        );
+    }
+
     #[test]
     fn test_deserialize_defaults() {
         let old_truncation_params = r#"{"max_length":256,"strategy":"LongestFirst","stride":0}"#;
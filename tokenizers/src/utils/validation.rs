@@ -0,0 +1,83 @@
+//! A dry-run validator for tokenization pipelines: run each component on a
+//! few samples with invariant checks, and report which component violated
+//! what. Useful when composing custom normalizers and pre-tokenizers, where
+//! a subtle alignment bug only shows up much later as corrupted offsets.
+
+use serde::{Deserialize, Serialize};
+
+/// An invariant violated by a pipeline component on one of the samples,
+/// collected in a [`ValidationReport`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationIssue {
+    /// The component that violated the invariant: `normalizer`,
+    /// `pre_tokenizer`, `model` or `decoder`
+    pub component: String,
+    /// The index of the sample that triggered the issue
+    pub sample: usize,
+    /// A description of the violated invariant
+    pub detail: String,
+}
+
+/// The outcome of a pipeline dry-run, produced by
+/// [`TokenizerImpl::validate_pipeline`](crate::tokenizer::TokenizerImpl::validate_pipeline)
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct ValidationReport {
+    /// The invariants violated during the dry-run, in sample order
+    pub issues: Vec<ValidationIssue>,
+    /// For each sample, the Levenshtein distance between the sample and
+    /// `decode(encode(sample))`, normalized to `[0, 1]` by the longer of the
+    /// two. A non-zero distance is not an issue per se (lowercasing or
+    /// placeholder tokens are lossy on purpose), but an unexpectedly high one
+    /// usually points at a misbehaving component. Samples that failed to
+    /// encode or decode get a distance of `1.0`.
+    pub round_trip_distances: Vec<f64>,
+}
+
+impl ValidationReport {
+    /// Whether the dry-run completed without any invariant violation
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    pub(crate) fn issue(&mut self, component: &str, sample: usize, detail: String) {
+        self.issues.push(ValidationIssue {
+            component: component.to_string(),
+            sample,
+            detail,
+        });
+    }
+}
+
+/// The Levenshtein distance between the two strings, in chars, normalized to
+/// `[0, 1]` by the length of the longer one
+pub(crate) fn round_trip_distance(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 0.0;
+    }
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut cur = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != cb);
+            cur.push((prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1));
+        }
+        prev = cur;
+    }
+    prev[b.len()] as f64 / longest as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distance_is_normalized() {
+        assert_eq!(round_trip_distance("", ""), 0.0);
+        assert_eq!(round_trip_distance("hello", "hello"), 0.0);
+        assert_eq!(round_trip_distance("hello", ""), 1.0);
+        assert_eq!(round_trip_distance("abcd", "abce"), 0.25);
+    }
+}
@@ -0,0 +1,77 @@
+use proptest::prelude::*;
+
+use tokenizers::normalizers::{
+    ArabicNormalizer, BertNormalizer, Lowercase, Replace, Strip, StripAccents, NFC, NFD, NFKC, NFKD,
+};
+use tokenizers::pre_tokenizers::byte_level::ByteLevel;
+use tokenizers::pre_tokenizers::digits::Digits;
+use tokenizers::pre_tokenizers::metaspace::Metaspace;
+use tokenizers::pre_tokenizers::punctuation::Punctuation;
+use tokenizers::pre_tokenizers::whitespace::Whitespace;
+use tokenizers::tokenizer::{
+    check_alignments, NormalizedString, Normalizer, OffsetReferential, OffsetType,
+    PreTokenizedString, PreTokenizer,
+};
+
+/// Normalize `input` with the given normalizer and check the resulting
+/// alignments invariants
+fn check_normalizer<N: Normalizer>(normalizer: &N, input: &str) {
+    let mut normalized = NormalizedString::from(input);
+    normalizer.normalize(&mut normalized).unwrap();
+    check_alignments(input, &normalized).unwrap();
+}
+
+/// Pre-tokenize `input` and check that every split maps back to a valid,
+/// char-aligned range of the original string
+fn check_pre_tokenizer<PT: PreTokenizer>(pre_tokenizer: &PT, input: &str) {
+    let mut pre_tokenized = PreTokenizedString::from(input);
+    pre_tokenizer.pre_tokenize(&mut pre_tokenized).unwrap();
+    for (_, (start, end), _) in
+        pre_tokenized.get_splits(OffsetReferential::Original, OffsetType::Byte)
+    {
+        assert!(start <= end, "reversed offsets ({}, {})", start, end);
+        assert!(
+            input.get(start..end).is_some(),
+            "offsets ({}, {}) are not valid for {:?}",
+            start,
+            end,
+            input
+        );
+    }
+}
+
+proptest! {
+    #[test]
+    fn unicode_normalizers_keep_alignments(input in "\\PC*") {
+        check_normalizer(&NFC, &input);
+        check_normalizer(&NFD, &input);
+        check_normalizer(&NFKC, &input);
+        check_normalizer(&NFKD, &input);
+    }
+
+    #[test]
+    fn common_normalizers_keep_alignments(input in "\\PC*") {
+        check_normalizer(&Lowercase, &input);
+        check_normalizer(&StripAccents, &input);
+        check_normalizer(&Strip::new(true, true), &input);
+        check_normalizer(&BertNormalizer::default(), &input);
+        check_normalizer(&ArabicNormalizer::default(), &input);
+    }
+
+    #[test]
+    fn replace_keeps_alignments(input in "\\PC*") {
+        // A removal, a one-to-many expansion and a many-to-one contraction
+        check_normalizer(&Replace::new("a", "").unwrap(), &input);
+        check_normalizer(&Replace::new("e", "ee").unwrap(), &input);
+        check_normalizer(&Replace::new("ab", "c").unwrap(), &input);
+    }
+
+    #[test]
+    fn pre_tokenizers_map_back_to_original(input in "\\PC*") {
+        check_pre_tokenizer(&Whitespace, &input);
+        check_pre_tokenizer(&ByteLevel::default(), &input);
+        check_pre_tokenizer(&Punctuation::default(), &input);
+        check_pre_tokenizer(&Digits::new(true), &input);
+        check_pre_tokenizer(&Metaspace::default(), &input);
+    }
+}
@@ -102,11 +102,11 @@ fn processors() {
 
 #[test]
 fn pretoks() {
-    // Test unit struct
-    let bert = BertPreTokenizer;
+    // The default configuration serializes like the historical unit struct
+    let bert = BertPreTokenizer::default();
     let bert_ser = serde_json::to_string(&bert).unwrap();
     assert_eq!(bert_ser, r#"{"type":"BertPreTokenizer"}"#);
-    // empty struct can deserialize from self
+    // it can deserialize from itself
     serde_json::from_str::<BertPreTokenizer>(&bert_ser).unwrap();
     let err: Result<Whitespace, _> = serde_json::from_str(&bert_ser);
     assert!(